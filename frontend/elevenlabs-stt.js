@@ -124,6 +124,15 @@ function showToastIfAvailable(message, type = "info") {
   }
 }
 
+/**
+ * API key of the service streaming connects to (ElevenLabs or Deepgram)
+ */
+function streamingApiKey(settings) {
+  return settings.streaming_provider === "deepgram"
+    ? settings.deepgram_api_key
+    : settings.elevenlabs_api_key;
+}
+
 /**
  * Initialize ElevenLabs streaming based on settings
  */
@@ -141,7 +150,7 @@ async function initElevenLabsStreaming(settings) {
 
   // Connect if ElevenLabs is selected
   if (isElevenLabs) {
-    const apiKey = settings.elevenlabs_api_key?.trim();
+    const apiKey = streamingApiKey(settings)?.trim();
 
     if (!apiKey) {
       log("No API key configured", "warn");
@@ -191,7 +200,7 @@ async function setupElevenLabsEventListeners() {
   cleanupEventListeners();
 
   // Listen for transcript events (both partial and committed)
-  const unsubTranscript = await listen("elevenlabs://transcript", ({ payload }) => {
+  const unsubTranscript = await listen(EVENTS.ELEVENLABS_TRANSCRIPT, ({ payload }) => {
     if (!payload) return;
 
     const resultEl = document.getElementById("last-result");
@@ -218,7 +227,7 @@ async function setupElevenLabsEventListeners() {
   eventListenerCleanup.push(unsubTranscript);
 
   // Connection closed: for ContextReset (4001) auto-reconnect to keep next press instant
-  const unsubClosed = await listen("elevenlabs://connection-closed", (event) => {
+  const unsubClosed = await listen(EVENTS.ELEVENLABS_CONNECTION_CLOSED, (event) => {
     const payload = event.payload || {};
     const code = payload.code;
     const reason = payload.reason;
@@ -230,7 +239,7 @@ async function setupElevenLabsEventListeners() {
         // Re-read current settings to avoid using stale cached values
         try {
           const settings = await window.__TAURI__.core.invoke("get_settings");
-          const apiKey = settings.elevenlabs_api_key || lastApiKey;
+          const apiKey = streamingApiKey(settings) || lastApiKey;
           if (!apiKey) return;
           log("Reconnecting after context reset with fresh settings...");
           connectElevenLabsStreaming(apiKey, lastSampleRate, lastLanguageCode);
@@ -244,7 +253,7 @@ async function setupElevenLabsEventListeners() {
   eventListenerCleanup.push(unsubClosed);
 
   // Handle errors
-  const unsubError = await listen("elevenlabs://error", ({ payload }) => {
+  const unsubError = await listen(EVENTS.ELEVENLABS_ERROR, ({ payload }) => {
     log(`Error from backend: ${payload.error}`, "error");
     // If error indicates connection loss, we might want to reset isConnected
     if (payload.error.includes("Connection is dead") || payload.error.includes("closed")) {
@@ -0,0 +1,40 @@
+// Generated from src-tauri/src/core/events.rs; don't edit by hand.
+// After changing an event there, run `UPDATE_EVENTS_JS=1 cargo test events`.
+const EVENTS = Object.freeze({
+  STATUS: "transcription://status",
+  PARTIAL: "transcription://partial",
+  COMPLETE: "transcription://complete",
+  SEGMENT: "transcription://segment",
+  SETTINGS_CHANGED: "settings://changed",
+  RECORDING_TICK: "recording://tick",
+  OVERLAY_INTERACTIVE: "overlay://interactive",
+  SESSION_CHANGED: "session://changed",
+  RETRANSCRIBE_PROGRESS: "history://retranscribe-progress",
+  UPDATE_AVAILABLE: "update://available",
+  UPDATE_PROGRESS: "updater://progress",
+  UPDATE_DOWNLOADED: "update://downloaded",
+  UPDATE_DEFERRED: "updater://deferred",
+  UPDATE_INSTALLED: "update://installed",
+  ELEVENLABS_SESSION_STARTED: "elevenlabs://session-started",
+  ELEVENLABS_TRANSCRIPT: "elevenlabs://transcript",
+  ELEVENLABS_ERROR: "elevenlabs://error",
+  ELEVENLABS_CONNECTION_CLOSED: "elevenlabs://connection-closed",
+  ELEVENLABS_AUDIO_STATS: "elevenlabs://audio-stats",
+});
+// `phase` of a STATUS event
+const PHASES = Object.freeze({
+  IDLE: "idle",
+  PAUSED: "paused",
+  RECORDING: "recording",
+  TRANSCRIBING: "transcribing",
+  DELIVERING: "delivering",
+  SUCCESS: "success",
+  ERROR: "error",
+});
+// `actions` of an error STATUS event
+const ERROR_ACTIONS = Object.freeze({
+  OPEN_SETTINGS: "open_settings",
+  RETRY: "retry",
+  SWITCH_PROVIDER: "switch_provider",
+  VIEW_LOGS: "view_logs",
+});
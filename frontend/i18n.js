@@ -12,11 +12,16 @@ const translations = {
     'status.ready': 'Готово к записи',
     'status.recording': 'Идёт запись...',
     'status.transcribing': 'Распознавание...',
+    'status.delivering': 'Вставка текста...',
+    'status.paused': 'Подключено, ожидание',
     'status.success': 'Готово',
+    'status.elevatedTarget': 'Окно запущено от администратора — текст скопирован в буфер обмена',
     'status.error': 'Ошибка',
     'status.hint.ready': 'Нажмите горячую клавишу для начала',
     'status.hint.recording': 'Отпустите клавишу для завершения',
     'status.hint.transcribing': 'Обработка аудио...',
+    'status.hint.delivering': 'Не переключайте окно',
+    'status.hint.paused': 'Удерживайте клавишу, чтобы продолжить',
     'status.hint.success': 'Текст скопирован',
     'status.hint.error': 'Попробуйте ещё раз',
 
@@ -40,10 +45,15 @@ const translations = {
     'apikey.openai': 'API ключ OpenAI',
     'apikey.groq': 'API ключ Groq',
     'apikey.elevenlabs': 'API ключ ElevenLabs',
+    'apikey.deepgram': 'API ключ Deepgram',
+    'streamingProvider.label': 'Сервис стриминга',
     'apikey.show': 'Показать/скрыть',
+    'transcriptionLanguage.label': 'Язык речи при переводе (код: ru, en или auto)',
 
     // Model
     'model.label': 'Модель',
+    'model.custom': 'Другая…',
+    'model.custom.placeholder': 'ID модели, например gpt-4o-transcribe',
 
     // Hotkeys section
     'hotkeys.title': 'Горячие клавиши',
@@ -90,6 +100,16 @@ const translations = {
     'vocabulary.terms': 'терминов',
     'vocabulary.import': 'Импорт',
     'vocabulary.export': 'Экспорт',
+    'preview.title': 'Проверка обработки',
+    'preview.prompt': 'Показать промпт',
+    'preview.run': 'Попробовать',
+    'preview.placeholder': 'Пример текста для обработки с сохранёнными настройками',
+    'preview.language': 'Язык примера: {language}',
+    'preview.skipped.not_configured': 'Обработка не включена — текст будет вставлен как есть',
+    'preview.skipped.already_in_target': 'Текст уже на целевом языке — перевод будет пропущен',
+    'preview.refined': 'Ответ модели',
+    'preview.truncated': 'Ответ обрезан по лимиту токенов',
+    'preview.failed': 'Не удалось проверить обработку',
 
     // Behavior section
     'behavior.title': 'Поведение',
@@ -99,6 +119,22 @@ const translations = {
     'behavior.clipboard.hint': 'Копировать результат',
     'behavior.streaming': 'Реалтайм текст',
     'behavior.streaming.hint': 'Показывать текст во время записи',
+    'behavior.notifyComplete': 'Уведомлять о готовности',
+    'behavior.notifyComplete.hint': 'Уведомление с текстом',
+    'behavior.notifyError': 'Уведомлять об ошибках',
+    'behavior.notifyError.hint': 'Уведомление при сбое',
+    'behavior.logTranscripts': 'Журнал расшифровок',
+    'behavior.logTranscripts.hint': 'Сохранять текст в transcripts.log',
+    'behavior.localApi': 'Локальный API',
+    'behavior.localApi.hint': 'Запуск диктовки из других приложений по HTTP на 127.0.0.1',
+    'localApi.token': 'Локальный API слушает порт {port}. Скопируйте токен — он больше не будет показан:',
+    'behavior.postCommand': 'Запускать команду',
+    'behavior.postCommand.hint': 'Передавать текст скрипту на stdin',
+    'behavior.postCommand.label': 'Команда',
+    'unlock.prompt': 'API ключи зашифрованы. Введите пароль настроек:',
+    'unlock.retry': 'Неверный пароль. Попробуйте ещё раз:',
+    'postCommand.confirm': 'Команда будет запускаться с вашими правами после каждой диктовки и получать её текст:\n\n{command}\n\nРазрешить?',
+    'toast.postCommand.unconfirmed': 'Команда не подтверждена и не будет запускаться',
 
     // System section
     'system.title': 'Система',
@@ -108,6 +144,34 @@ const translations = {
     'system.tray.hint': 'Сворачивать при старте',
     'system.autoupdate': 'Автообновления',
     'system.autoupdate.hint': 'Проверять новые версии',
+    'system.channel': 'Канал обновлений',
+    'system.channel.stable': 'Стабильный',
+    'system.channel.beta': 'Бета',
+    'diagnostics.title': 'Диагностика',
+    'diagnostics.run': 'Проверить',
+    'diagnostics.running': 'Проверка...',
+    'diagnostics.copy': 'Копировать JSON',
+    'diagnostics.failed': 'Не удалось выполнить диагностику',
+    'diagnostics.clearTranscripts': 'Удалить журнал расшифровок',
+    'diagnostics.bundle': 'Пакет для поддержки',
+    'diagnostics.bundleCreating': 'Сборка...',
+    'diagnostics.bundleHistory': 'Добавить недавнюю историю',
+    'diagnostics.bundleHistory.hint': 'Последние диктовки попадут в пакет для поддержки',
+    'overlay.title': 'Положение оверлея',
+    'overlay.move': 'Переместить',
+    'overlay.done': 'Готово',
+    'overlay.reset': 'Сбросить',
+    'session.title': 'Сессия диктовки',
+    'session.start': 'Начать',
+    'session.end': 'Завершить',
+    'session.collected': 'Собрано фрагментов: {count}, слов: {words}',
+    'permissions.title': 'Разрешения',
+    'permissions.microphone': 'Микрофон',
+    'permissions.accessibility': 'Универсальный доступ',
+    'permissions.microphone.hint': 'Разрешите доступ в Системные настройки → Конфиденциальность и безопасность → Микрофон',
+    'permissions.accessibility.hint': 'Без этого текст не будет напечатан. Включите Easy Dictate в Системные настройки → Конфиденциальность и безопасность → Универсальный доступ',
+    'permissions.not_determined': 'Запрос появится при первой записи',
+    'permissions.restricted': 'Заблокировано политикой устройства',
     'system.language': 'Язык интерфейса',
 
     // Updates
@@ -115,7 +179,10 @@ const translations = {
     'update.uptodate': 'Актуальная версия',
     'update.install': 'Обновить',
     'update.installing': 'Обновление...',
+    'update.downloading': 'Загрузка...',
+    'update.confirm': 'Приложение перезапустится после установки обновления. Продолжить?',
     'update.restart': 'перезапустите для обновления',
+    'update.deferred': 'установится, когда приложение будет свободно',
 
     // Actions
     'actions.revert': 'Отменить',
@@ -130,17 +197,55 @@ const translations = {
     'toast.changes.reverted': 'Изменения отменены',
     'toast.copied': 'Скопировано',
     'toast.history.cleared': 'История очищена',
+    'toast.transcriptLog.cleared': 'Журнал расшифровок удалён',
+    'toast.bundle.created': 'Пакет сохранён: {path}',
+    'toast.overlay.reset': 'Оверлей снова показывается внизу по центру',
+    'toast.session.saved': 'Сессия из {count} фрагментов скопирована в буфер обмена',
+    'toast.error.session': 'Ошибка сессии',
     'toast.error': 'Ошибка',
     'toast.error.save': 'Ошибка при сохранении',
+    'toast.error.unlock': 'Не удалось разблокировать настройки',
     'toast.error.load': 'Не удалось загрузить настройки',
     'toast.error.delete': 'Не удалось удалить',
     'toast.error.clear': 'Не удалось очистить',
+    'toast.error.bundle': 'Не удалось собрать пакет',
+    'toast.error.retry': 'Запись для повтора больше недоступна',
+    'errorAction.open_settings': 'Открыть настройки',
+    'errorAction.retry': 'Повторить',
+    'errorAction.retry.wait': 'Повторить через {secs} с',
+    'errorAction.switch_provider': 'Сменить провайдера',
+    'errorAction.view_logs': 'Показать журнал',
+    'toast.error.overlay': 'Не удалось изменить положение оверлея',
     'toast.error.hotkey.main': 'Выберите горячую клавишу',
     'toast.error.hotkey.key': 'Нужна основная клавиша',
     'toast.error.hotkey.mouse': 'Мышь не поддерживается',
     'toast.error.hotkey.recognize': 'Не удалось распознать',
     'toast.error.copy': 'Скопировать отчёт',
     'toast.error.report': 'Сообщить об ошибке',
+
+    // Error codes (transcription://status)
+    'error.missing_api_key': 'Не указан API ключ',
+    'error.settings_locked': 'Настройки заблокированы — введите пароль, чтобы использовать API ключи',
+    'error.mic_not_found': 'Микрофон не найден',
+    'error.mic_muted': 'Микрофон выключен — включите его в меню трея',
+    'error.mic_blocked': 'Микрофон отключён или запрещён в настройках Windows',
+    'error.no_audio': 'Запись пустая',
+    'error.unsupported_audio_format': 'Формат аудиофайла не поддерживается',
+    'error.auth_failed': 'Неверный API ключ',
+    'error.rate_limited': 'Слишком много запросов, попробуйте позже',
+    'error.quota_exceeded': 'Исчерпана квота',
+    'error.network': 'Нет соединения с сервером',
+    'error.commit_timeout': 'Сервер не подтвердил запись вовремя',
+    'error.connect_timeout': 'Сервер ElevenLabs не ответил вовремя',
+    'error.transcription_timeout': 'Распознавание не завершилось вовремя',
+    'error.streaming_error': 'Ошибка стриминга',
+    'error.refinement_failed': 'Ошибка обработки текста',
+    'error.clipboard_failed': 'Не удалось скопировать в буфер обмена',
+    'error.typing_failed': 'Эмулятор клавиатуры недоступен, текст не может быть напечатан',
+    'error.hotkey_registration_failed': 'Не удалось зарегистрировать горячую клавишу. Закройте другие копии приложения.',
+    'error.settings_save_failed': 'Не удалось сохранить настройки',
+    'error.settings_load_failed': 'Не удалось загрузить настройки, используются значения по умолчанию',
+    'error.autostart_failed': 'Не удалось изменить автозапуск',
   },
 
   en: {
@@ -152,11 +257,16 @@ const translations = {
     'status.ready': 'Ready to record',
     'status.recording': 'Recording...',
     'status.transcribing': 'Transcribing...',
+    'status.delivering': 'Inserting text...',
+    'status.paused': 'Connected, waiting',
     'status.success': 'Done',
+    'status.elevatedTarget': 'Target window is elevated — transcript copied to clipboard instead',
     'status.error': 'Error',
     'status.hint.ready': 'Press hotkey to start',
     'status.hint.recording': 'Release key to finish',
     'status.hint.transcribing': 'Processing audio...',
+    'status.hint.delivering': "Don't switch windows",
+    'status.hint.paused': 'Hold the hotkey to continue',
     'status.hint.success': 'Text copied',
     'status.hint.error': 'Try again',
 
@@ -180,10 +290,15 @@ const translations = {
     'apikey.openai': 'OpenAI API Key',
     'apikey.groq': 'Groq API Key',
     'apikey.elevenlabs': 'ElevenLabs API Key',
+    'apikey.deepgram': 'Deepgram API Key',
+    'streamingProvider.label': 'Streaming service',
     'apikey.show': 'Show/hide',
+    'transcriptionLanguage.label': 'Spoken language for translated dictations (code: ru, en or auto)',
 
     // Model
     'model.label': 'Model',
+    'model.custom': 'Custom…',
+    'model.custom.placeholder': 'Model id, e.g. gpt-4o-transcribe',
 
     // Hotkeys section
     'hotkeys.title': 'Hotkeys',
@@ -230,6 +345,16 @@ const translations = {
     'vocabulary.terms': 'terms',
     'vocabulary.import': 'Import',
     'vocabulary.export': 'Export',
+    'preview.title': 'Refinement preview',
+    'preview.prompt': 'Show prompt',
+    'preview.run': 'Try it',
+    'preview.placeholder': 'Sample text to refine with the saved settings',
+    'preview.language': 'Sample language: {language}',
+    'preview.skipped.not_configured': 'Refinement is off — the text would be inserted as is',
+    'preview.skipped.already_in_target': 'The text is already in the target language — translation would be skipped',
+    'preview.refined': 'Model answer',
+    'preview.truncated': 'The answer was cut off at the token limit',
+    'preview.failed': 'Refinement preview failed',
 
     // Behavior section
     'behavior.title': 'Behavior',
@@ -239,6 +364,22 @@ const translations = {
     'behavior.clipboard.hint': 'Copy result to clipboard',
     'behavior.streaming': 'Realtime text',
     'behavior.streaming.hint': 'Show text while recording',
+    'behavior.notifyComplete': 'Notify on completion',
+    'behavior.notifyComplete.hint': 'Show a notification with the text',
+    'behavior.notifyError': 'Notify on errors',
+    'behavior.notifyError.hint': 'Show a notification when something fails',
+    'behavior.logTranscripts': 'Transcript log',
+    'behavior.logTranscripts.hint': 'Save recognized text to transcripts.log',
+    'behavior.localApi': 'Local API',
+    'behavior.localApi.hint': 'Let other apps start dictation over HTTP on 127.0.0.1',
+    'localApi.token': 'The local API listens on port {port}. Copy the token, it won\'t be shown again:',
+    'behavior.postCommand': 'Run a command',
+    'behavior.postCommand.hint': 'Pass each transcript to a script on stdin',
+    'behavior.postCommand.label': 'Command',
+    'unlock.prompt': 'The API keys are encrypted. Enter the settings passphrase:',
+    'unlock.retry': 'Wrong passphrase. Try again:',
+    'postCommand.confirm': 'This command will run with your privileges after every dictation and receive its text:\n\n{command}\n\nAllow it?',
+    'toast.postCommand.unconfirmed': 'The command was not confirmed and will not run',
 
     // System section
     'system.title': 'System',
@@ -248,6 +389,34 @@ const translations = {
     'system.tray.hint': 'Minimize to tray on launch',
     'system.autoupdate': 'Auto-update',
     'system.autoupdate.hint': 'Check for new versions',
+    'system.channel': 'Update channel',
+    'system.channel.stable': 'Stable',
+    'system.channel.beta': 'Beta',
+    'diagnostics.title': 'Diagnostics',
+    'diagnostics.run': 'Run',
+    'diagnostics.running': 'Checking...',
+    'diagnostics.copy': 'Copy JSON',
+    'diagnostics.failed': 'Diagnostics failed',
+    'diagnostics.clearTranscripts': 'Delete transcript log',
+    'diagnostics.bundle': 'Support bundle',
+    'diagnostics.bundleCreating': 'Collecting...',
+    'diagnostics.bundleHistory': 'Include recent history',
+    'diagnostics.bundleHistory.hint': 'Adds your last dictations to the support bundle',
+    'overlay.title': 'Overlay position',
+    'overlay.move': 'Move',
+    'overlay.done': 'Done',
+    'overlay.reset': 'Reset',
+    'session.title': 'Dictation session',
+    'session.start': 'Start',
+    'session.end': 'End',
+    'session.collected': '{count} utterances, {words} words collected',
+    'permissions.title': 'Permissions',
+    'permissions.microphone': 'Microphone',
+    'permissions.accessibility': 'Accessibility',
+    'permissions.microphone.hint': 'Allow access in System Settings → Privacy & Security → Microphone',
+    'permissions.accessibility.hint': 'Text cannot be typed without it. Enable Easy Dictate in System Settings → Privacy & Security → Accessibility',
+    'permissions.not_determined': 'You will be asked on the first recording',
+    'permissions.restricted': 'Blocked by a device policy',
     'system.language': 'Interface language',
 
     // Updates
@@ -255,7 +424,10 @@ const translations = {
     'update.uptodate': 'Up to date',
     'update.install': 'Update',
     'update.installing': 'Updating...',
+    'update.downloading': 'Downloading...',
+    'update.confirm': 'The app will restart after the update is installed. Continue?',
     'update.restart': 'restart to update',
+    'update.deferred': 'will install when idle',
 
     // Actions
     'actions.revert': 'Revert',
@@ -270,17 +442,55 @@ const translations = {
     'toast.changes.reverted': 'Changes reverted',
     'toast.copied': 'Copied',
     'toast.history.cleared': 'History cleared',
+    'toast.transcriptLog.cleared': 'Transcript log deleted',
+    'toast.bundle.created': 'Bundle saved: {path}',
+    'toast.overlay.reset': 'The overlay is back at the bottom centre',
+    'toast.session.saved': 'Session of {count} utterances copied to the clipboard',
+    'toast.error.session': 'Session error',
     'toast.error': 'Error',
     'toast.error.save': 'Failed to save',
+    'toast.error.unlock': 'Failed to unlock the settings',
     'toast.error.load': 'Failed to load settings',
     'toast.error.delete': 'Failed to delete',
     'toast.error.clear': 'Failed to clear',
+    'toast.error.bundle': 'Failed to create the bundle',
+    'toast.error.retry': 'The recording is no longer available to retry',
+    'errorAction.open_settings': 'Open settings',
+    'errorAction.retry': 'Retry',
+    'errorAction.retry.wait': 'Retry in {secs}s',
+    'errorAction.switch_provider': 'Switch provider',
+    'errorAction.view_logs': 'View logs',
+    'toast.error.overlay': 'Failed to change the overlay position',
     'toast.error.hotkey.main': 'Select a hotkey',
     'toast.error.hotkey.key': 'Need a main key',
     'toast.error.hotkey.mouse': 'Mouse not supported',
     'toast.error.hotkey.recognize': 'Could not recognize',
     'toast.error.copy': 'Copy report',
     'toast.error.report': 'Report bug',
+
+    // Error codes (transcription://status)
+    'error.missing_api_key': 'API key is missing',
+    'error.settings_locked': 'Settings are locked — enter the passphrase to use the API keys',
+    'error.mic_not_found': 'No microphone found',
+    'error.mic_muted': 'The microphone is muted — unmute it from the tray menu',
+    'error.mic_blocked': 'The microphone is muted or blocked in Windows settings',
+    'error.no_audio': 'The recording is empty',
+    'error.unsupported_audio_format': 'This audio file format is not supported',
+    'error.auth_failed': 'The API key was rejected',
+    'error.rate_limited': 'Too many requests, try again later',
+    'error.quota_exceeded': 'Quota exceeded',
+    'error.network': 'Cannot reach the server',
+    'error.commit_timeout': 'The server did not confirm the recording in time',
+    'error.connect_timeout': 'ElevenLabs did not answer in time',
+    'error.transcription_timeout': 'Transcription timed out',
+    'error.streaming_error': 'Streaming error',
+    'error.refinement_failed': 'Text refinement failed',
+    'error.clipboard_failed': 'Failed to copy to clipboard',
+    'error.typing_failed': 'Keyboard emulator unavailable, text cannot be typed',
+    'error.hotkey_registration_failed': 'Hotkey registration failed. Close other instances and restart.',
+    'error.settings_save_failed': 'Failed to save settings',
+    'error.settings_load_failed': 'Failed to load settings, using defaults',
+    'error.autostart_failed': 'Failed to update autostart',
   }
 };
 
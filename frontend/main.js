@@ -55,6 +55,7 @@ const statusOrb = document.getElementById("status-orb");
 const statusText = document.getElementById("status-text");
 const statusHint = document.getElementById("status-hint");
 const progressEl = document.getElementById("progress");
+const errorActionsEl = document.getElementById("error-actions");
 const resultEl = document.getElementById("last-result");
 const toastEl = document.getElementById("toast");
 
@@ -64,10 +65,15 @@ const providerRadios = document.querySelectorAll('input[name="provider"]');
 const apiKeyInput = document.getElementById("apiKey");
 const groqApiKeyInput = document.getElementById("groqApiKey");
 const elevenlabsApiKeyInput = document.getElementById("elevenlabsApiKey");
+const streamingProviderSelect = document.getElementById("streamingProvider");
+const deepgramApiKeyInput = document.getElementById("deepgramApiKey");
+const deepgramApiKeyField = document.getElementById("deepgram-api-key-field");
+const transcriptionLanguageInput = document.getElementById("transcriptionLanguage");
 const openaiApiKeyField = document.getElementById("openai-api-key-field");
 const groqApiKeyField = document.getElementById("groq-api-key-field");
 const elevenlabsApiKeyField = document.getElementById("elevenlabs-api-key-field");
 const modelSelect = document.getElementById("model");
+const modelCustomInput = document.getElementById("modelCustom");
 
 // Hotkeys
 const hotkeyHiddenInput = document.getElementById("hotkey");
@@ -83,6 +89,12 @@ const toggleTranslateHotkeyClearBtn = document.getElementById("toggleTranslateHo
 // Behavior toggles
 const simulateTypingInput = document.getElementById("simulateTyping");
 const copyToClipboardInput = document.getElementById("copyToClipboard");
+const notifyOnCompleteInput = document.getElementById("notifyOnComplete");
+const notifyOnErrorInput = document.getElementById("notifyOnError");
+const logTranscriptsInput = document.getElementById("logTranscripts");
+const localApiEnabledInput = document.getElementById("localApiEnabled");
+const postCommandEnabledInput = document.getElementById("postCommandEnabled");
+const postCommandInput = document.getElementById("postCommand");
 const autoStartInput = document.getElementById("autoStart");
 const startMinimizedInput = document.getElementById("startMinimized");
 const autoUpdateInput = document.getElementById("autoUpdate");
@@ -117,6 +129,7 @@ const clearHistoryBtn = document.getElementById("clearHistoryBtn");
 
 // UI Language
 const uiLanguageSelect = document.getElementById("uiLanguage");
+const updateChannelSelect = document.getElementById("updateChannel");
 
 // ============================================================================
 // Constants
@@ -291,7 +304,7 @@ async function persistSettings(payload, successMessage = null) {
     await invoke("save_settings", { settings: payload });
     dbg("invoke(save_settings) ok");
     initialSettings = { ...payload };
-    if (emit) emit('settings://changed', {});
+    if (emit) emit(EVENTS.SETTINGS_CHANGED, {});
     if (successMessage) showToast(successMessage);
     else if (successMessage !== false) showToast(t('toast.saved'));
     return true;
@@ -303,6 +316,51 @@ async function persistSettings(payload, successMessage = null) {
   }
 }
 
+// A new or changed post-transcription command stays off until the user confirms it here
+async function confirmPostCommand(settings) {
+  const command = settings.post_command;
+  if (!settings.post_command_enabled || !command || command === initialSettings?.post_command_confirmed) {
+    return;
+  }
+  if (!window.confirm(t('postCommand.confirm', { command }))) {
+    showToast(t('toast.postCommand.unconfirmed'), "error");
+    return;
+  }
+  try {
+    await invoke("confirm_post_command", { command });
+    initialSettings = { ...initialSettings, post_command_confirmed: command };
+  } catch (error) {
+    await showErrorToastWithReport(t('toast.error.save'), errMsg(error));
+  }
+}
+
+const UNLOCK_ATTEMPTS = 3;
+
+// Ask for the passphrase of encrypted API keys. Cancelling leaves them locked: the
+// app still works, but providers that need a key report `settings_locked`.
+async function unlockSettings() {
+  try {
+    if (await invoke("get_settings_lock") !== "locked") return;
+  } catch (error) {
+    dbg(`get_settings_lock failed: ${errMsg(error)}`, "error");
+    return;
+  }
+  for (let attempt = 0; attempt < UNLOCK_ATTEMPTS; attempt++) {
+    const passphrase = window.prompt(t(attempt ? 'unlock.retry' : 'unlock.prompt'));
+    if (passphrase === null) break;
+    try {
+      await invoke("unlock_settings", { passphrase });
+      return;
+    } catch (error) {
+      if (errCode(error) !== "wrong_passphrase") {
+        await showErrorToastWithReport(t('toast.error.unlock'), errMsg(error));
+        return;
+      }
+    }
+  }
+  showToast(t('error.settings_locked'), "error");
+}
+
 // Helper function to get translation (uses window.i18n if available)
 function t(key, params = {}) {
   if (window.i18n && window.i18n.t) {
@@ -325,10 +383,79 @@ function setStatus(state, text, hint = null) {
   }
 }
 
+let retryCountdown = null;
+
+/** Show a button for each recovery action offered with an error; none hides the row. */
+function showErrorActions(actions = [], retryAfterSecs = null) {
+  if (!errorActionsEl) return;
+  clearInterval(retryCountdown);
+  retryCountdown = null;
+  errorActionsEl.replaceChildren();
+  errorActionsEl.hidden = actions.length === 0;
+
+  for (const action of actions) {
+    const btn = document.createElement("button");
+    btn.type = "button";
+    btn.className = "vocabulary-btn";
+    btn.textContent = t(`errorAction.${action}`);
+    btn.addEventListener("click", () => runErrorAction(action));
+    errorActionsEl.append(btn);
+
+    if (action === ERROR_ACTIONS.RETRY && retryAfterSecs > 0) {
+      // The provider asked to wait; keep the button disabled until then
+      let left = retryAfterSecs;
+      const tick = () => {
+        btn.disabled = left > 0;
+        btn.textContent = left > 0
+          ? t('errorAction.retry.wait', { secs: left })
+          : t('errorAction.retry');
+        if (left-- <= 0) {
+          clearInterval(retryCountdown);
+          retryCountdown = null;
+        }
+      };
+      tick();
+      retryCountdown = setInterval(tick, 1000);
+    }
+  }
+}
+
+function showTab(tabName) {
+  document.querySelector(`.tab-btn[data-tab="${tabName}"]`)?.click();
+}
+
+async function runErrorAction(action) {
+  try {
+    switch (action) {
+      case ERROR_ACTIONS.RETRY:
+        if (!await invoke("retry_last_transcription")) {
+          showToast(t('toast.error.retry'), "error");
+        }
+        break;
+      case ERROR_ACTIONS.OPEN_SETTINGS:
+        showTab("settings");
+        await invoke("show_settings");
+        break;
+      case ERROR_ACTIONS.SWITCH_PROVIDER:
+        showTab("settings");
+        document.querySelector('input[name="provider"]:checked')?.focus();
+        break;
+      case ERROR_ACTIONS.VIEW_LOGS:
+        await invoke("reveal_log");
+        break;
+    }
+    showErrorActions();
+  } catch (err) {
+    showToast(`${t('toast.error')}: ${errMsg(err)}`, "error");
+  }
+}
+
 function getDefaultHint(state) {
   switch (state) {
     case 'recording': return t('status.hint.recording');
     case 'transcribing': return t('status.hint.transcribing');
+    case 'delivering': return t('status.hint.delivering');
+    case 'paused': return t('status.hint.paused');
     case 'success': return t('status.hint.success');
     case 'error': return t('status.hint.error');
     default: return t('status.hint.ready');
@@ -493,6 +620,21 @@ function bindingHasMainKey(binding) {
 // Provider & UI Sync
 // ============================================================================
 
+function formatLatency(ms) {
+  return `${(ms / 1000).toFixed(1)}s`;
+}
+
+// A target picked in the form joins the cycle list, which must contain the active target
+function withTargetLanguage(languages, target) {
+  const list = languages ?? [];
+  return target && list.length && !list.includes(target) ? [...list, target] : list;
+}
+
+function providerLabel(provider) {
+  const names = { openai: "OpenAI", groq: "Groq", elevenlabs: "ElevenLabs", ollama: "Ollama" };
+  return names[provider] ?? provider ?? "";
+}
+
 function getSelectedProvider() {
   for (const radio of providerRadios) {
     if (radio.checked) return radio.value;
@@ -513,29 +655,90 @@ function updateProviderFields() {
   if (openaiApiKeyField) openaiApiKeyField.hidden = provider !== 'openai';
   if (groqApiKeyField) groqApiKeyField.hidden = provider !== 'groq';
   if (elevenlabsApiKeyField) elevenlabsApiKeyField.hidden = provider !== 'elevenlabs';
+  if (deepgramApiKeyField) deepgramApiKeyField.hidden = streamingProviderSelect?.value !== 'deepgram';
 
   // Update model options
-  const currentModel = modelSelect?.value || '';
   if (!modelSelect) return;
+  const model = modelForProvider(provider, getSelectedModel());
+  renderModelOptions(FALLBACK_MODELS[provider] ?? FALLBACK_MODELS.openai, model);
+  refreshModelOptions(provider);
+}
+
+// ============================================================================
+// Model picker
+// ============================================================================
 
+// Shown until (or instead of, e.g. without an API key) the provider's own list
+const FALLBACK_MODELS = {
+  openai: [
+    { id: "gpt-4o-transcribe", label: "gpt-4o-transcribe" },
+    { id: "gpt-4o-mini-transcribe", label: "gpt-4o-mini-transcribe" },
+    { id: "whisper-1", label: "whisper-1 (fallback)" },
+  ],
+  groq: [
+    { id: "groq/whisper-large-v3-turbo", label: "Whisper Large v3 Turbo" },
+    { id: "groq/whisper-large-v3", label: "Whisper Large v3" },
+  ],
+  elevenlabs: [{ id: "scribe_v2_realtime", label: "Scribe v2 Realtime" }],
+};
+const DEFAULT_MODELS = {
+  openai: "gpt-4o-transcribe",
+  groq: "groq/whisper-large-v3-turbo",
+  elevenlabs: "scribe_v2_realtime",
+};
+const MODEL_CUSTOM = "__custom__";
+let modelListRequest = 0;
+
+// Keep `model` if it belongs to `provider`, like `TranscriptionProvider::accepts_model`
+function modelForProvider(provider, model) {
+  const isGroq = model.startsWith("groq/");
+  const isElevenLabs = model.startsWith("scribe");
+  const accepted = provider === 'groq' ? isGroq
+    : provider === 'elevenlabs' ? isElevenLabs
+    : model !== "" && !isGroq && !isElevenLabs;
+  return accepted ? model : (DEFAULT_MODELS[provider] ?? DEFAULT_MODELS.openai);
+}
+
+function getSelectedModel() {
+  if (!modelSelect) return "";
+  if (modelSelect.value === MODEL_CUSTOM) return modelCustomInput?.value.trim() ?? "";
+  return modelSelect.value;
+}
+
+// Select `model`, or type it into the free-text field if it isn't in the list
+function setSelectedModel(model) {
+  if (!modelSelect) return;
+  const listed = [...modelSelect.options].some(option => option.value === model && option.value !== MODEL_CUSTOM);
+  modelSelect.value = listed ? model : MODEL_CUSTOM;
+  if (modelCustomInput) {
+    if (!listed) modelCustomInput.value = model;
+    modelCustomInput.hidden = listed;
+  }
+}
+
+function renderModelOptions(models, selected) {
   modelSelect.innerHTML = '';
+  for (const { id, label } of models) {
+    modelSelect.add(new Option(label ?? id, id));
+  }
+  modelSelect.add(new Option(t('model.custom'), MODEL_CUSTOM));
+  setSelectedModel(selected);
+}
 
-  if (provider === 'groq') {
-    modelSelect.innerHTML = `
-      <option value="groq/whisper-large-v3-turbo">Whisper Large v3 Turbo</option>
-      <option value="groq/whisper-large-v3">Whisper Large v3</option>
-    `;
-    modelSelect.value = currentModel.startsWith("groq/") ? currentModel : "groq/whisper-large-v3-turbo";
-  } else if (provider === 'elevenlabs') {
-    modelSelect.innerHTML = `<option value="scribe_v2_realtime">Scribe v2 Realtime</option>`;
-    modelSelect.value = "scribe_v2_realtime";
-  } else {
-    modelSelect.innerHTML = `
-      <option value="gpt-4o-transcribe">gpt-4o-transcribe</option>
-      <option value="gpt-4o-mini-transcribe">gpt-4o-mini-transcribe</option>
-      <option value="whisper-1">whisper-1 (fallback)</option>
-    `;
-    modelSelect.value = currentModel.startsWith("groq/") ? "gpt-4o-transcribe" : currentModel;
+// Swap in the models the provider actually offers; the fallback list stays if
+// the key is missing or the request fails
+async function refreshModelOptions(provider) {
+  if (!invoke || !modelSelect) return;
+  const request = ++modelListRequest;
+  try {
+    const list = await invoke("list_models", { provider });
+    if (request !== modelListRequest) return;
+    const models = list.models.filter(model => model.kind === "transcription");
+    if (list.reason) dbg(`list_models(${provider}): ${list.reason}`);
+    if (models.length === 0) return;
+    renderModelOptions(models, getSelectedModel());
+  } catch (error) {
+    dbg(`list_models failed: ${errMsg(error)}`, "warn");
   }
 }
 
@@ -589,8 +792,19 @@ async function importVocabulary() {
     if (!file) return;
     const text = await file.text();
     const existing = getVocabularyArray();
-    const newTerms = text.split('\n').map(l => l.trim()).filter(l => l.length > 0);
-    const merged = [...new Set([...existing, ...newTerms])];
+    // Same rules as the backend import: `#` lines and trailing ` #` comments are skipped
+    const newTerms = text.split('\n')
+      .map(l => l.trim())
+      .filter(l => !l.startsWith('#'))
+      .map(l => l.split(/\s#/)[0].replace(/\s+/g, ' ').trim())
+      .filter(l => l.length > 0);
+    const seen = new Set();
+    const merged = [...existing, ...newTerms].filter((term) => {
+      const key = term.toLowerCase();
+      if (seen.has(key)) return false;
+      seen.add(key);
+      return true;
+    });
     if (customVocabularyInput) customVocabularyInput.value = merged.join('\n');
     updateVocabularyCount();
   };
@@ -644,15 +858,18 @@ async function loadSettings() {
 
     // Provider
     setSelectedProvider(settings.provider ?? "openai");
+    if (streamingProviderSelect) streamingProviderSelect.value = settings.streaming_provider ?? "elevenlabs";
     updateProviderFields();
 
     // API Keys
     if (apiKeyInput) apiKeyInput.value = settings.api_key ?? "";
     if (groqApiKeyInput) groqApiKeyInput.value = settings.groq_api_key ?? "";
     if (elevenlabsApiKeyInput) elevenlabsApiKeyInput.value = settings.elevenlabs_api_key ?? "";
+    if (deepgramApiKeyInput) deepgramApiKeyInput.value = settings.deepgram_api_key ?? "";
+    if (transcriptionLanguageInput) transcriptionLanguageInput.value = settings.transcription_language ?? "auto";
 
     // Model
-    if (modelSelect) modelSelect.value = settings.model ?? "gpt-4o-transcribe";
+    setSelectedModel(settings.model ?? "gpt-4o-transcribe");
 
     // Hotkeys
     renderHotkey(settings.hotkey ?? DEFAULT_HOTKEY, 'main');
@@ -663,11 +880,18 @@ async function loadSettings() {
     if (simulateTypingInput) simulateTypingInput.checked = Boolean(settings.simulate_typing);
     if (copyToClipboardInput) copyToClipboardInput.checked = Boolean(settings.copy_to_clipboard);
     if (useStreamingInput) useStreamingInput.checked = Boolean(settings.use_streaming);
+    if (notifyOnCompleteInput) notifyOnCompleteInput.checked = Boolean(settings.notify_on_complete);
+    if (notifyOnErrorInput) notifyOnErrorInput.checked = Boolean(settings.notify_on_error ?? true);
+    if (logTranscriptsInput) logTranscriptsInput.checked = Boolean(settings.log_transcripts);
+    if (localApiEnabledInput) localApiEnabledInput.checked = Boolean(settings.local_api_enabled);
+    if (postCommandEnabledInput) postCommandEnabledInput.checked = Boolean(settings.post_command_enabled);
+    if (postCommandInput) postCommandInput.value = settings.post_command ?? "";
 
     // System
     if (autoStartInput) autoStartInput.checked = Boolean(settings.auto_start);
     if (startMinimizedInput) startMinimizedInput.checked = Boolean(settings.start_minimized);
     if (autoUpdateInput) autoUpdateInput.checked = Boolean(settings.auto_update ?? true);
+    if (updateChannelSelect) updateChannelSelect.value = settings.update_channel ?? "stable";
 
     // Translation
     if (autoTranslateInput) autoTranslateInput.checked = Boolean(settings.auto_translate);
@@ -707,12 +931,17 @@ async function loadSettings() {
 
 function currentSettings() {
   return {
+    // Keep settings that have no form control so saving doesn't reset them to defaults
+    ...(initialSettings ?? {}),
     provider: getSelectedProvider(),
     llm_provider: llmProviderSelect?.value ?? "groq",
     api_key: apiKeyInput?.value.trim() ?? "",
     groq_api_key: groqApiKeyInput?.value.trim() ?? "",
     elevenlabs_api_key: elevenlabsApiKeyInput?.value.trim() ?? "",
-    model: modelSelect?.value ?? "gpt-4o-transcribe",
+    streaming_provider: streamingProviderSelect?.value ?? "elevenlabs",
+    deepgram_api_key: deepgramApiKeyInput?.value.trim() ?? "",
+    transcription_language: transcriptionLanguageInput?.value.trim() || "auto",
+    model: getSelectedModel() || "gpt-4o-transcribe",
     hotkey: normalizeHotkeyValue(hotkeyHiddenInput?.value),
     translate_hotkey: normalizeHotkeyValue(translateHotkeyHiddenInput?.value),
     toggle_translate_hotkey: normalizeHotkeyValue(toggleTranslateHotkeyHiddenInput?.value),
@@ -721,15 +950,22 @@ function currentSettings() {
     auto_start: autoStartInput?.checked ?? false,
     start_minimized: startMinimizedInput?.checked ?? false,
     auto_update: autoUpdateInput?.checked ?? true,
+    update_channel: updateChannelSelect?.value ?? "stable",
     use_streaming: useStreamingInput?.checked ?? false,
+    notify_on_complete: notifyOnCompleteInput?.checked ?? false,
+    notify_on_error: notifyOnErrorInput?.checked ?? true,
+    log_transcripts: logTranscriptsInput?.checked ?? false,
+    post_command_enabled: postCommandEnabledInput?.checked ?? false,
+    post_command: postCommandInput?.value.trim() ?? "",
     auto_translate: autoTranslateInput?.checked ?? false,
     target_language: targetLanguageSelect?.value ?? "русский",
+    target_languages: withTargetLanguage(initialSettings?.target_languages, targetLanguageSelect?.value),
     use_custom_instructions: useCustomInstructionsInput?.checked ?? false,
     custom_instructions: (customInstructionsInput?.value ?? "").trim(),
     use_vocabulary: useVocabularyInput?.checked ?? false,
     custom_vocabulary: getVocabularyArray(),
     ui_language: window.i18n?.getLanguage() ?? "ru",
-    llm_model: initialSettings?.llm_model ?? "gpt-4o-mini",
+    llm_model: initialSettings?.llm_model ?? "",
   };
 }
 
@@ -755,6 +991,9 @@ form?.addEventListener("submit", async (event) => {
   if (saveBtn) saveBtn.disabled = true;
   try {
     const saved = await persistSettings(payload, t('toast.settings.saved'));
+    if (saved) await confirmPostCommand(payload);
+    // A key entered just now can list the provider's models
+    if (saved) refreshModelOptions(payload.provider);
     if (saved && window.ElevenLabsSTT?.init) {
       await window.ElevenLabsSTT.init(payload);
     }
@@ -769,12 +1008,15 @@ revertBtn?.addEventListener("click", () => {
   cancelHotkeyCapture();
 
   setSelectedProvider(initialSettings.provider ?? "openai");
+  if (streamingProviderSelect) streamingProviderSelect.value = initialSettings.streaming_provider ?? "elevenlabs";
   updateProviderFields();
 
   if (apiKeyInput) apiKeyInput.value = initialSettings.api_key ?? "";
   if (groqApiKeyInput) groqApiKeyInput.value = initialSettings.groq_api_key ?? "";
   if (elevenlabsApiKeyInput) elevenlabsApiKeyInput.value = initialSettings.elevenlabs_api_key ?? "";
-  if (modelSelect) modelSelect.value = initialSettings.model ?? "gpt-4o-transcribe";
+  if (deepgramApiKeyInput) deepgramApiKeyInput.value = initialSettings.deepgram_api_key ?? "";
+  if (transcriptionLanguageInput) transcriptionLanguageInput.value = initialSettings.transcription_language ?? "auto";
+  setSelectedModel(initialSettings.model ?? "gpt-4o-transcribe");
 
   renderHotkey(initialSettings.hotkey ?? DEFAULT_HOTKEY, 'main');
   renderHotkey(initialSettings.translate_hotkey ?? "", 'translate');
@@ -783,9 +1025,16 @@ revertBtn?.addEventListener("click", () => {
   if (simulateTypingInput) simulateTypingInput.checked = Boolean(initialSettings.simulate_typing);
   if (copyToClipboardInput) copyToClipboardInput.checked = Boolean(initialSettings.copy_to_clipboard);
   if (useStreamingInput) useStreamingInput.checked = Boolean(initialSettings.use_streaming);
+  if (notifyOnCompleteInput) notifyOnCompleteInput.checked = Boolean(initialSettings.notify_on_complete);
+  if (notifyOnErrorInput) notifyOnErrorInput.checked = Boolean(initialSettings.notify_on_error ?? true);
+  if (logTranscriptsInput) logTranscriptsInput.checked = Boolean(initialSettings.log_transcripts);
+  if (localApiEnabledInput) localApiEnabledInput.checked = Boolean(initialSettings.local_api_enabled);
+  if (postCommandEnabledInput) postCommandEnabledInput.checked = Boolean(initialSettings.post_command_enabled);
+  if (postCommandInput) postCommandInput.value = initialSettings.post_command ?? "";
   if (autoStartInput) autoStartInput.checked = Boolean(initialSettings.auto_start);
   if (startMinimizedInput) startMinimizedInput.checked = Boolean(initialSettings.start_minimized);
   if (autoUpdateInput) autoUpdateInput.checked = Boolean(initialSettings.auto_update ?? true);
+  if (updateChannelSelect) updateChannelSelect.value = initialSettings.update_channel ?? "stable";
   if (autoTranslateInput) autoTranslateInput.checked = Boolean(initialSettings.auto_translate);
   if (targetLanguageSelect) targetLanguageSelect.value = initialSettings.target_language ?? "русский";
   if (llmProviderSelect) llmProviderSelect.value = initialSettings.llm_provider ?? "groq";
@@ -832,10 +1081,36 @@ toggleTranslateHotkeyClearBtn?.addEventListener("click", (e) => { e.stopPropagat
 providerRadios.forEach(radio => {
   radio.addEventListener("change", updateProviderFields);
 });
+streamingProviderSelect?.addEventListener("change", updateProviderFields);
+
+// Model picker: "Custom…" reveals the free-text field
+modelSelect?.addEventListener("change", () => {
+  const custom = modelSelect.value === MODEL_CUSTOM;
+  if (modelCustomInput) {
+    modelCustomInput.hidden = !custom;
+    if (custom) modelCustomInput.focus();
+  }
+});
 
 // Translation toggle
 autoTranslateInput?.addEventListener("change", syncTranslationUi);
 
+// The local API switches on its own rather than on save, so a new token can be
+// shown right away; it isn't shown again
+localApiEnabledInput?.addEventListener("change", async () => {
+  const enabled = localApiEnabledInput.checked;
+  try {
+    const info = await invoke("set_local_api_enabled", { enabled });
+    initialSettings = { ...initialSettings, local_api_enabled: enabled };
+    if (info.token) {
+      window.prompt(t('localApi.token', { port: info.port ?? '?' }), info.token);
+    }
+  } catch (error) {
+    localApiEnabledInput.checked = !enabled;
+    await showErrorToastWithReport(t('toast.error.save'), errMsg(error));
+  }
+});
+
 // Custom instructions toggle
 useCustomInstructionsInput?.addEventListener("change", syncCustomInstructionsUi);
 
@@ -946,14 +1221,30 @@ function renderHistory(entries) {
     if (entry.custom_instructions_used) {
       providerBadges += `<span class="history-entry-provider custom">custom</span>`;
     }
+    if (entry.was_modified) {
+      providerBadges += `<span class="history-entry-provider modified" title="Words changed by refinement">±${entry.change_count}</span>`;
+    }
+    if (entry.refinement_failed) {
+      providerBadges += `<span class="history-entry-provider failed" title="LLM refinement failed, raw transcript delivered">raw</span>`;
+    }
+    if (entry.latency_ms != null) {
+      providerBadges += `<span class="history-entry-provider latency" title="Time from end of recording to delivery">${formatLatency(entry.latency_ms)}</span>`;
+    }
+    if (entry.words_per_minute != null) {
+      providerBadges += `<span class="history-entry-provider latency" title="${entry.word_count} words, ${entry.char_count} characters">${Math.round(entry.words_per_minute)} wpm</span>`;
+    }
 
     // Language badges
     let langBadges = '';
     if (entry.source_language) {
       langBadges += `<span class="history-entry-lang">${entry.source_language}</span>`;
     }
-    if (hasTranslation && entry.target_language) {
-      langBadges += `<span class="history-entry-translated">→ ${entry.target_language}</span>`;
+    if ((hasTranslation || entry.translated) && entry.target_language) {
+      // Transcribed straight into the target language has no separate translated text
+      const forced = entry.translation_forced ? ' title="Translated with the translate hotkey"' : '';
+      langBadges += `<span class="history-entry-translated"${forced}>→ ${entry.target_language}</span>`;
+    } else if (entry.combined_translation) {
+      langBadges += `<span class="history-entry-translated" title="Transcribed and translated by OpenAI in one request">→ ${entry.target_language ?? "English"}</span>`;
     }
 
     // Text to copy - prefer translated if available
@@ -972,11 +1263,17 @@ function renderHistory(entries) {
       </div>
     ` : '';
 
+    // The recording as other providers transcribed it (retranscribe_recordings)
+    const alternateRows = (entry.alternate_transcripts ?? []).map((alternate) => `
+      <p class="history-entry-original"><span class="history-entry-provider ${escapeAttr(alternate.provider)}">${escapeHtml(alternate.provider)}</span> ${escapeHtml(alternate.text)}</p>
+    `).join('');
+
     return `
       <div class="history-entry" data-id="${entry.id}">
         <div class="history-entry-content">
           <p class="history-entry-text">${mainText}</p>
           ${originalRow}
+          ${alternateRows}
           <div class="history-entry-meta">
             <span class="history-entry-time">${time}</span>
             ${providerBadges}
@@ -1130,6 +1427,9 @@ window.addEventListener("DOMContentLoaded", async () => {
     window.ElevenLabsSTT.setupEventListeners();
   }
 
+  // Encrypted API keys are unlocked before the settings are shown
+  await unlockSettings();
+
   // Load settings
   await loadSettings();
 
@@ -1143,16 +1443,244 @@ window.addEventListener("DOMContentLoaded", async () => {
     }
   }
 
+  // Permissions (macOS): explain how to grant what is missing instead of failing silently
+  const permissionsPanel = document.getElementById("permissionsPanel");
+  const permissionsList = document.getElementById("permissionsList");
+
+  async function refreshPermissions() {
+    if (!invoke || !permissionsPanel) return;
+    let permissions;
+    try {
+      permissions = await invoke("check_permissions");
+    } catch (err) {
+      console.error("Failed to check permissions", err);
+      return;
+    }
+    const missing = Object.entries(permissions ?? {})
+      .filter(([, state]) => state !== "granted" && state !== "not_required");
+    permissionsList.replaceChildren(...missing.map(([name, state]) => {
+      const item = document.createElement("li");
+      item.classList.toggle("failed", state !== "not_determined");
+      const nameEl = document.createElement("span");
+      nameEl.className = "check-name";
+      nameEl.textContent = t(`permissions.${name}`);
+      const detailEl = document.createElement("span");
+      detailEl.textContent = state === "denied" ? t(`permissions.${name}.hint`) : t(`permissions.${state}`);
+      item.append(nameEl, detailEl);
+      return item;
+    }));
+    permissionsPanel.hidden = missing.length === 0;
+  }
+
+  refreshPermissions();
+  // The user grants permissions in System Settings and comes back
+  window.addEventListener("focus", refreshPermissions);
+
+  // Overlay placement: while "Move" is on, the overlay takes the mouse and is saved
+  // wherever the user drops it
+  const moveOverlayBtn = document.getElementById("moveOverlay");
+  let placingOverlay = false;
+
+  async function setOverlayInteractive(interactive) {
+    try {
+      await invoke("set_overlay_interactive", { interactive });
+      placingOverlay = interactive;
+      moveOverlayBtn.textContent = t(interactive ? 'overlay.done' : 'overlay.move');
+    } catch (err) {
+      showToast(`${t('toast.error.overlay')}: ${errMsg(err)}`, "error");
+    }
+  }
+
+  moveOverlayBtn?.addEventListener("click", () => {
+    if (!invoke) return;
+    setOverlayInteractive(!placingOverlay);
+  });
+
+  document.getElementById("resetOverlayPosition")?.addEventListener("click", async () => {
+    if (!invoke) return;
+    try {
+      await invoke("reset_overlay_position");
+      showToast(t('toast.overlay.reset'));
+    } catch (err) {
+      showToast(`${t('toast.error.overlay')}: ${errMsg(err)}`, "error");
+    }
+  });
+
+  // Dictation session: the button starts or ends it, the tray can too
+  const toggleSessionBtn = document.getElementById("toggleSession");
+  const sessionInfoEl = document.getElementById("sessionInfo");
+  let sessionActive = false;
+
+  function showSession(info) {
+    sessionActive = Boolean(info?.active);
+    if (toggleSessionBtn) toggleSessionBtn.textContent = t(sessionActive ? 'session.end' : 'session.start');
+    if (sessionInfoEl) {
+      sessionInfoEl.hidden = !sessionActive;
+      sessionInfoEl.textContent = sessionActive
+        ? t('session.collected', { count: info.utterances, words: info.word_count })
+        : '';
+    }
+  }
+
+  toggleSessionBtn?.addEventListener("click", async () => {
+    if (!invoke) return;
+    toggleSessionBtn.disabled = true;
+    try {
+      if (sessionActive) {
+        const summary = await invoke("end_session");
+        showSession(null);
+        if (summary.utterances > 0) showToast(t('toast.session.saved', { count: summary.utterances }));
+      } else {
+        showSession(await invoke("start_session"));
+      }
+    } catch (err) {
+      showToast(`${t('toast.error.session')}: ${errMsg(err)}`, "error");
+    } finally {
+      toggleSessionBtn.disabled = false;
+    }
+  });
+
+  if (invoke) {
+    invoke("get_session").then(showSession).catch(() => {});
+  }
+
+  // Refinement preview: what the saved settings would send to the LLM for a sample
+  const previewSample = document.getElementById("previewSample");
+  const previewResult = document.getElementById("previewResult");
+
+  const runPreview = async (button, execute) => {
+    if (!invoke || !previewSample) return;
+    button.disabled = true;
+    try {
+      const preview = await invoke("preview_refinement", {
+        sampleText: previewSample.value,
+        execute,
+      });
+      const lines = [`${preview.llm_provider} · ${preview.model}`];
+      if (preview.source_language) {
+        lines.push(t('preview.language', { language: preview.source_language }));
+      }
+      if (preview.skipped) {
+        lines.push(t(`preview.skipped.${preview.skipped}`));
+      }
+      if (preview.system_prompt) lines.push("", preview.system_prompt);
+      if (preview.context_prompt) lines.push("", preview.context_prompt);
+      if (preview.refined != null) {
+        lines.push("", `${t('preview.refined')}:`, preview.refined);
+        if (preview.truncated) lines.push(t('preview.truncated'));
+      }
+      previewResult.textContent = lines.join("\n");
+      previewResult.hidden = false;
+    } catch (err) {
+      showToast(`${t('preview.failed')}: ${errMsg(err)}`, "error");
+    } finally {
+      button.disabled = false;
+    }
+  };
+
+  const previewPromptBtn = document.getElementById("previewPrompt");
+  const previewRunBtn = document.getElementById("previewRun");
+  previewPromptBtn?.addEventListener("click", () => runPreview(previewPromptBtn, false));
+  previewRunBtn?.addEventListener("click", () => runPreview(previewRunBtn, true));
+
+  // Diagnostics: one JSON blob users can paste into a support request
+  const runDiagnosticsBtn = document.getElementById("runDiagnostics");
+  const copyDiagnosticsBtn = document.getElementById("copyDiagnostics");
+  const diagnosticsList = document.getElementById("diagnosticsList");
+  let lastDiagnostics = null;
+
+  runDiagnosticsBtn?.addEventListener("click", async () => {
+    if (!invoke) return;
+    runDiagnosticsBtn.disabled = true;
+    runDiagnosticsBtn.textContent = t('diagnostics.running');
+    try {
+      lastDiagnostics = await invoke("run_diagnostics");
+      diagnosticsList.replaceChildren(...lastDiagnostics.map(({ name, ok, detail }) => {
+        const item = document.createElement("li");
+        item.classList.toggle("failed", !ok);
+        const nameEl = document.createElement("span");
+        nameEl.className = "check-name";
+        nameEl.textContent = name;
+        const detailEl = document.createElement("span");
+        detailEl.textContent = detail;
+        item.append(nameEl, detailEl);
+        return item;
+      }));
+      diagnosticsList.hidden = false;
+      copyDiagnosticsBtn.hidden = false;
+    } catch (err) {
+      showToast(`${t('diagnostics.failed')}: ${errMsg(err)}`, "error");
+    } finally {
+      runDiagnosticsBtn.disabled = false;
+      runDiagnosticsBtn.textContent = t('diagnostics.run');
+    }
+  });
+
+  copyDiagnosticsBtn?.addEventListener("click", async () => {
+    if (!lastDiagnostics) return;
+    await navigator.clipboard.writeText(JSON.stringify(lastDiagnostics, null, 2));
+    showToast(t('toast.copied'));
+  });
+
+  document.getElementById("clearTranscriptLog")?.addEventListener("click", async () => {
+    if (!invoke) return;
+    try {
+      await invoke("clear_transcript_log");
+      showToast(t('toast.transcriptLog.cleared'));
+    } catch (err) {
+      showToast(`${t('toast.error.clear')}: ${errMsg(err)}`, "error");
+    }
+  });
+
+  // Support bundle: settings without keys, the recent log and diagnostics in one zip
+  const supportBundleBtn = document.getElementById("createSupportBundle");
+  supportBundleBtn?.addEventListener("click", async () => {
+    if (!invoke) return;
+    supportBundleBtn.disabled = true;
+    supportBundleBtn.textContent = t('diagnostics.bundleCreating');
+    try {
+      const path = await invoke("create_support_bundle", {
+        includeHistory: document.getElementById("bundleIncludeHistory")?.checked ?? false,
+      });
+      showToast(t('toast.bundle.created', { path }));
+      await invoke("reveal_support_bundle", { path }).catch((err) => {
+        console.warn("Failed to reveal support bundle:", err);
+      });
+    } catch (err) {
+      showToast(`${t('toast.error.bundle')}: ${errMsg(err)}`, "error");
+    } finally {
+      supportBundleBtn.disabled = false;
+      supportBundleBtn.textContent = t('diagnostics.bundle');
+    }
+  });
+
   // Check for updates button
   const checkUpdatesBtn = document.getElementById("checkUpdatesBtn");
   const updateStatusEl = document.getElementById("updateStatus");
   const installUpdateBtn = document.getElementById("installUpdateBtn");
 
-  function showUpdateAvailable(version) {
+  // Set once the package is on disk (manual download or the startup auto-update)
+  let updateDownloaded = false;
+
+  function showUpdateAvailable(version, notes) {
     if (updateStatusEl) {
       updateStatusEl.hidden = false;
       updateStatusEl.className = "update-status update-available";
       updateStatusEl.textContent = `v${version} ${t('update.available')}`;
+      updateStatusEl.title = notes ?? "";
+    }
+    if (installUpdateBtn) {
+      installUpdateBtn.hidden = false;
+      installUpdateBtn.disabled = false;
+      installUpdateBtn.textContent = t('update.install');
+    }
+  }
+
+  function showUpdateDeferred(version) {
+    if (updateStatusEl) {
+      updateStatusEl.hidden = false;
+      updateStatusEl.className = "update-status update-available";
+      updateStatusEl.textContent = `v${version} — ${t('update.deferred')}`;
     }
     if (installUpdateBtn) {
       installUpdateBtn.hidden = false;
@@ -1176,9 +1704,9 @@ window.addEventListener("DOMContentLoaded", async () => {
       updateStatusEl.hidden = true;
       if (installUpdateBtn) installUpdateBtn.hidden = true;
       try {
-        const newVersion = await invoke("check_for_updates");
-        if (newVersion) {
-          showUpdateAvailable(newVersion);
+        const info = await invoke("check_for_updates");
+        if (info?.available) {
+          showUpdateAvailable(info.latest, info.notes);
         } else {
           updateStatusEl.hidden = false;
           updateStatusEl.className = "update-status up-to-date";
@@ -1196,9 +1724,14 @@ window.addEventListener("DOMContentLoaded", async () => {
 
   if (installUpdateBtn && invoke) {
     installUpdateBtn.addEventListener("click", async () => {
+      if (!window.confirm(t('update.confirm'))) return;
       installUpdateBtn.disabled = true;
-      installUpdateBtn.textContent = t('update.installing');
       try {
+        if (!updateDownloaded) {
+          installUpdateBtn.textContent = t('update.downloading');
+          await invoke("download_update");
+        }
+        installUpdateBtn.textContent = t('update.installing');
         await invoke("install_update");
       } catch (err) {
         installUpdateBtn.disabled = false;
@@ -1215,59 +1748,128 @@ window.addEventListener("DOMContentLoaded", async () => {
   const unlistenFns = [];
 
   if (listen) {
-    unlistenFns.push(await listen("transcription://status", ({ payload }) => {
-      const { phase, message } = payload;
-      if (phase === "recording") {
+    unlistenFns.push(await listen(EVENTS.STATUS, ({ payload }) => {
+      const { phase, code, provider, actions, retry_after_secs } = payload;
+      let { message } = payload;
+      if (phase === PHASES.ERROR && code) {
+        // Prefer the translated text for known error codes, keep the raw message otherwise
+        const key = `error.${code}`;
+        const translated = t(key);
+        if (translated !== key) message = provider ? `${providerLabel(provider)}: ${translated}` : translated;
+      }
+      if (phase === PHASES.RECORDING) {
         setStatus("recording", message ?? t('status.recording'));
         if (progressEl) { progressEl.hidden = false; progressEl.removeAttribute("value"); }
-      } else if (phase === "transcribing") {
+      } else if (phase === PHASES.TRANSCRIBING) {
         setStatus("transcribing", message ?? t('status.transcribing'));
         if (progressEl) { progressEl.hidden = false; progressEl.value = 0; }
-      } else if (phase === "idle") {
+      } else if (phase === PHASES.DELIVERING) {
+        if (progressEl) { progressEl.hidden = false; progressEl.removeAttribute("value"); }
+        setStatus("delivering", t('status.delivering'));
+      } else if (phase === PHASES.PAUSED) {
+        if (progressEl) progressEl.hidden = true;
+        setStatus("paused", message ?? t('status.paused'));
+      } else if (phase === PHASES.IDLE) {
         if (progressEl) progressEl.hidden = true;
         setStatus("idle", message ?? t('status.ready'));
-      } else if (phase === "error") {
+      } else if (phase === PHASES.ERROR) {
         if (progressEl) progressEl.hidden = true;
         setStatus("error", message ?? t('status.error'));
         showToast(message ?? t('toast.error'), "error");
-      } else if (phase === "success") {
+      } else if (phase === PHASES.SUCCESS) {
         if (progressEl) progressEl.hidden = true;
         setStatus("success", message ?? t('status.success'));
       }
+      showErrorActions(phase === PHASES.ERROR ? actions : [], retry_after_secs);
     }));
 
-    unlistenFns.push(await listen("transcription://partial", ({ payload }) => {
+    unlistenFns.push(await listen(EVENTS.PARTIAL, ({ payload }) => {
       if (!payload?.text || !resultEl) return;
       resultEl.hidden = false;
       resultEl.classList.add("partial");
+      resultEl.classList.toggle("interim", Boolean(payload.interim));
       resultEl.textContent = payload.text;
       setStatus("recording", t('status.transcribing'));
     }));
 
-    unlistenFns.push(await listen("transcription://complete", ({ payload }) => {
+    unlistenFns.push(await listen(EVENTS.COMPLETE, ({ payload }) => {
       if (resultEl) {
-        resultEl.classList.remove("partial");
+        resultEl.classList.remove("partial", "interim");
         if (payload?.text) {
           resultEl.hidden = false;
           resultEl.textContent = payload.text;
         }
       }
-      showToast(t('status.success'), "success");
+      if (payload?.elevated_target) {
+        // Windows drops input sent to an elevated window, so the text was only copied
+        showToast(t('status.elevatedTarget'), "error");
+      } else {
+        showToast(t('status.success'), "success");
+      }
       setStatus("success", t('status.success'));
       loadHistory();
     }));
 
+    // A segment was delivered while the dictation goes on
+    unlistenFns.push(await listen(EVENTS.SEGMENT, ({ payload }) => {
+      if (resultEl && payload?.text) {
+        resultEl.classList.remove("partial");
+        resultEl.hidden = false;
+        resultEl.textContent = payload.text;
+      }
+      if (payload?.elevated_target) {
+        showToast(t('status.elevatedTarget'), "error");
+      }
+      loadHistory();
+    }));
+
     // Auto-update notifications
-    unlistenFns.push(await listen("update://available", ({ payload: version }) => {
+    unlistenFns.push(await listen(EVENTS.SESSION_CHANGED, ({ payload }) => {
+      showSession(payload);
+    }));
+
+    unlistenFns.push(await listen(EVENTS.UPDATE_AVAILABLE, ({ payload: version }) => {
       showUpdateAvailable(version);
     }));
 
-    unlistenFns.push(await listen("update://installed", ({ payload: version }) => {
+    unlistenFns.push(await listen(EVENTS.UPDATE_PROGRESS, ({ payload }) => {
+      if (!installUpdateBtn || !payload?.total) return;
+      const percent = Math.min(100, Math.round((payload.downloaded / payload.total) * 100));
+      installUpdateBtn.textContent = `${t('update.downloading')} ${percent}%`;
+    }));
+
+    unlistenFns.push(await listen(EVENTS.UPDATE_DOWNLOADED, () => {
+      updateDownloaded = true;
+    }));
+
+    unlistenFns.push(await listen(EVENTS.UPDATE_DEFERRED, ({ payload: version }) => {
+      showUpdateDeferred(version);
+    }));
+
+    unlistenFns.push(await listen(EVENTS.UPDATE_INSTALLED, ({ payload: version }) => {
       showUpdateInstalled(version);
     }));
 
-    unlistenFns.push(await listen("settings://changed", ({ payload }) => {
-      const { auto_translate, target_language } = payload;
+    unlistenFns.push(await listen(EVENTS.SETTINGS_CHANGED, async ({ payload }) => {
+      const { provider, model, auto_translate, target_language, active_preset } = payload;
+      if (provider && provider !== getSelectedProvider()) {
+        // Provider switched from the tray
+        setSelectedProvider(provider);
+        updateProviderFields();
+        if (model) setSelectedModel(model);
+        if (initialSettings) initialSettings = { ...initialSettings, provider, model };
+      }
+      if (initialSettings && active_preset && active_preset !== initialSettings.active_preset) {
+        // Preset switched from tray/hotkey: pick up its instructions so saving doesn't revert it
+        const fresh = await invoke("get_settings");
+        initialSettings = {
+          ...initialSettings,
+          prompt_presets: fresh.prompt_presets,
+          active_preset: fresh.active_preset,
+          custom_instructions: fresh.custom_instructions,
+        };
+        if (customInstructionsInput) customInstructionsInput.value = fresh.custom_instructions ?? "";
+      }
       if (autoTranslateInput && typeof auto_translate === 'boolean') {
         autoTranslateInput.checked = auto_translate;
       }
@@ -10,6 +10,17 @@
     console.log("%c[Mocks] Initializing Tauri API Mocks...", "color: #4a90e2; font-weight: bold;");
 
     const listeners = new Map();
+    let mockMicMuted = false;
+    let mockSettingsLock = "off";
+    let mockLocalApiToken = "";
+    const idleSession = {
+        active: false,
+        started_at: null,
+        utterances: 0,
+        word_count: 0,
+        last_utterance_at: null,
+    };
+    let mockSession = { ...idleSession };
 
     // Mock Settings
     let mockSettings = {
@@ -17,14 +28,24 @@
         api_key: "sk-mock-key-12345",
         groq_api_key: "",
         elevenlabs_api_key: "",
+        deepgram_api_key: "",
+        streaming_provider: "elevenlabs",
+        transcription_language: "auto",
         model: "gpt-4o-transcribe",
         hotkey: "Ctrl+Shift+Space",
         simulate_typing: true,
         copy_to_clipboard: false,
         auto_start: false,
         use_streaming: true,
+        notify_on_complete: false,
+        notify_on_error: true,
+        log_transcripts: false,
+        post_command: "",
+        post_command_enabled: false,
+        post_command_confirmed: "",
         auto_translate: false,
         target_language: "русский",
+        target_languages: ["English", "Russian", "German"],
         llm_provider: "openai",
         use_custom_instructions: false,
         custom_instructions: "",
@@ -41,7 +62,11 @@
 
                     case "save_settings":
                         if (args.settings) {
-                            mockSettings = { ...args.settings };
+                            // Like the backend, only confirm_post_command confirms a command
+                            mockSettings = {
+                                ...args.settings,
+                                post_command_confirmed: mockSettings.post_command_confirmed,
+                            };
                             console.log("[Mocks] Settings saved:", mockSettings);
                         }
                         return true;
@@ -50,13 +75,62 @@
                         console.log(`[Backend Log] ${args.level}: ${args.message}`);
                         return;
 
+                    case "list_models": {
+                        const models = {
+                            openai: ["gpt-4o-transcribe", "gpt-4o-mini-transcribe", "whisper-1"],
+                            groq: ["groq/whisper-large-v3-turbo", "groq/whisper-large-v3"],
+                            elevenlabs: ["scribe_v2_realtime"],
+                        }[args.provider] ?? [];
+                        return {
+                            models: models.map((id) => ({ id, kind: "transcription" })),
+                            reason: null,
+                        };
+                    }
+
+                    case "get_history_entry_audio":
+                        // Recordings aren't kept in the mock
+                        throw { code: "not_found", message: `History entry ${args.id} has no saved recording` };
+
+                    case "retranscribe_recordings":
+                        // No entry has a saved recording in the mock
+                        return { completed: 0, failed: 0, skipped: args.ids.length, cancelled: false };
+
+                    case "cancel_retranscription":
+                        return false;
+
+                    case "events_manifest":
+                        return Object.entries(EVENTS).map(([key, name]) => ({
+                            key,
+                            name,
+                            payload: {},
+                        }));
+
                     case "elevenlabs_streaming_is_connected":
                         return false;
 
+                    case "get_streaming_state":
+                        return {
+                            connected: false,
+                            transmitting: false,
+                            committing: false,
+                            in_flight_bytes: 0,
+                            coalesced_chunks: 0,
+                            dropped_chunks: 0,
+                            audio: {
+                                captured_chunks: 0,
+                                sent_chunks: 0,
+                                voiced_chunks: 0,
+                                bytes_sent: 0,
+                                dropped_buffer_full: 0,
+                                dropped_socket_behind: 0,
+                                ignored_gate_closed: 0,
+                            },
+                        };
+
                     case "elevenlabs_streaming_connect":
                         console.log("[Mocks] Connecting to ElevenLabs...");
                         setTimeout(() => {
-                            triggerEvent("transcription://status", { phase: "transcribing", message: "Mock: Connected" });
+                            triggerEvent(EVENTS.STATUS, { phase: "transcribing", message: "Mock: Connected" });
                         }, 500);
                         return true;
 
@@ -67,6 +141,152 @@
                     case "ping":
                         return "pong";
 
+                    case "run_diagnostics":
+                        return [
+                            { name: "microphone", ok: true, detail: "Mock Microphone (48000 Hz, 1 ch, F32)" },
+                            { name: "microphone_access", ok: true, detail: "Not muted or blocked by the system" },
+                            { name: "openai_key", ok: false, detail: "OpenAI responded with 401 Unauthorized" },
+                            { name: "hotkeys", ok: true, detail: "Registered Ctrl+Shift+Space" },
+                        ];
+
+                    case "clear_transcript_log":
+                        return null;
+
+                    case "confirm_post_command":
+                        if (!mockSettings.post_command || args.command.trim() !== mockSettings.post_command) {
+                            throw { code: "not_found", message: "The post-transcription command to confirm is not the saved one" };
+                        }
+                        mockSettings = { ...mockSettings, post_command_confirmed: mockSettings.post_command };
+                        return null;
+
+                    case "set_mic_muted":
+                        mockMicMuted = args.muted;
+                        return mockMicMuted;
+
+                    case "get_mic_muted":
+                        return mockMicMuted;
+
+                    case "get_settings_lock":
+                        return mockSettingsLock;
+
+                    case "unlock_settings":
+                        if (mockSettingsLock !== "locked") {
+                            throw { code: "settings", message: "Settings are not locked" };
+                        }
+                        if (args.passphrase !== "mock") {
+                            throw { code: "wrong_passphrase", message: "Wrong passphrase" };
+                        }
+                        mockSettingsLock = "unlocked";
+                        return null;
+
+                    case "set_settings_passphrase":
+                        mockSettingsLock = args.passphrase ? "unlocked" : "off";
+                        mockSettings.encrypt_settings = Boolean(args.passphrase);
+                        return mockSettings.encrypt_settings;
+
+                    case "set_local_api_enabled": {
+                        mockSettings.local_api_enabled = args.enabled;
+                        const fresh = args.enabled && !mockLocalApiToken;
+                        if (fresh) mockLocalApiToken = "mock-local-api-token";
+                        return {
+                            enabled: args.enabled,
+                            port: args.enabled ? 51234 : null,
+                            token: fresh ? mockLocalApiToken : null,
+                        };
+                    }
+
+                    case "reset_local_api_token":
+                        mockLocalApiToken = "mock-local-api-token-2";
+                        return mockLocalApiToken;
+
+                    case "get_local_api":
+                        return {
+                            enabled: Boolean(mockSettings.local_api_enabled),
+                            port: mockSettings.local_api_enabled ? 51234 : null,
+                            token: null,
+                        };
+
+                    case "get_foreground_app":
+                        return { path: "C:\\Program Files\\Slack\\slack.exe", name: "slack.exe" };
+
+                    case "set_next_provider":
+                    case "stop_speaking":
+                    case "set_overlay_interactive":
+                    case "start_dictation":
+                    case "stop_dictation":
+                    case "commit_segment":
+                        return null;
+
+                    case "start_session":
+                        mockSession = {
+                            active: true,
+                            started_at: new Date().toISOString(),
+                            utterances: 0,
+                            word_count: 0,
+                            last_utterance_at: null,
+                        };
+                        return mockSession;
+
+                    case "end_session": {
+                        const summary = {
+                            text: "",
+                            utterances: mockSession.utterances,
+                            llm_applied: false,
+                            history_id: null,
+                            output_file: null,
+                        };
+                        mockSession = { ...idleSession };
+                        return summary;
+                    }
+
+                    case "get_session":
+                        return mockSession;
+
+                    case "create_support_bundle":
+                        return `/tmp/easy-dictate-support-mock${args.includeHistory ? "-history" : ""}.zip`;
+
+                    case "reveal_support_bundle":
+                    case "reveal_log":
+                    case "show_settings":
+                        return null;
+
+                    case "retry_last_transcription":
+                        return false;
+
+                    case "preview_refinement": {
+                        const configured =
+                            mockSettings.auto_translate || mockSettings.use_custom_instructions;
+                        return {
+                            llm_provider: mockSettings.llm_provider,
+                            model: mockSettings.llm_model || "gpt-4o-mini",
+                            source_language: "English",
+                            skipped: configured ? null : "not_configured",
+                            system_prompt: configured
+                                ? `You are assisting with high quality speech transcription cleanup. Translate the transcript into ${mockSettings.target_language}.`
+                                : null,
+                            context_prompt: null,
+                            refined: configured && args.execute ? `[mock] ${args.sampleText}` : null,
+                            truncated: false,
+                        };
+                    }
+
+                    case "reset_overlay_position":
+                        mockSettings = { ...mockSettings, overlay_position: null };
+                        return null;
+
+                    case "cycle_target_language": {
+                        const languages = mockSettings.target_languages;
+                        const next = (languages.indexOf(mockSettings.target_language) + 1) % languages.length;
+                        mockSettings = { ...mockSettings, target_language: languages[next], auto_translate: true };
+                        return mockSettings.target_language;
+                    }
+
+                    case "check_permissions":
+                        return { microphone: "granted", accessibility: "denied" };
+
+                    case "transcribe_file":
+                        return "Mock transcription result for E2E testing";
+
                     default:
                         console.warn(`[Mocks] Unknown command: ${cmd}`);
                         return null;
@@ -107,7 +327,7 @@
 
     // Auto-trigger some status after load to show it works
     setTimeout(() => {
-        window.mockTrigger("transcription://status", {
+        window.mockTrigger(EVENTS.STATUS, {
             phase: "idle",
             message: "Готово к записи (Mock)"
         });
@@ -1,9 +1,10 @@
-const { listen } = window.__TAURI__.event;
+const { listen, emit } = window.__TAURI__.event;
 const { invoke } = window.__TAURI__.core;
 const { getCurrentWindow } = window.__TAURI__.window;
 
 const container = document.getElementById('overlay-container');
 const textEl = document.getElementById('transcription-text');
+const timerEl = document.getElementById('recording-timer');
 const appWindow = getCurrentWindow();
 
 function log(msg) {
@@ -14,11 +15,24 @@ function log(msg) {
 let hideTimeout = null;
 let animationTimeout = null;
 let showRealtimeText = true;
+let hideAfterMs = 2500;
+let targetLanguage = null;
+let showLevels = true;
+// The user is dragging the overlay into place; `placing` if it's up only for that
+let interactive = false;
+let placing = false;
+let moveTimeout = null;
+
+// Sent to the backend, which saves where the overlay was dropped
+const OVERLAY_MOVED = 'overlay://moved';
 
 async function loadSettings() {
     try {
         const settings = await invoke('get_settings');
         showRealtimeText = settings.use_streaming !== false;
+        hideAfterMs = settings.overlay_hide_after_ms ?? 2500;
+        targetLanguage = settings.target_language;
+        showLevels = settings.overlay_show_levels !== false;
         log('Settings loaded: showRealtimeText=' + showRealtimeText);
     } catch (e) {
         log('Failed to load settings: ' + e);
@@ -40,6 +54,8 @@ async function showOverlay() {
 }
 
 function hideOverlay(delay = 2000) {
+    // Stays up until the user is done placing it
+    if (interactive) return;
     if (hideTimeout) clearTimeout(hideTimeout);
     if (animationTimeout) clearTimeout(animationTimeout);
 
@@ -54,11 +70,35 @@ function hideOverlay(delay = 2000) {
 
 function updateText(text) {
     textEl.textContent = text;
-    textEl.classList.remove('updating');
+    textEl.classList.remove('updating', 'interim');
     void textEl.offsetWidth;
     textEl.classList.add('updating');
 }
 
+function formatSeconds(total) {
+    const minutes = Math.floor(total / 60);
+    const seconds = String(total % 60).padStart(2, '0');
+    return `${minutes}:${seconds}`;
+}
+
+function updateTimer(elapsed, remaining) {
+    if (elapsed == null) {
+        timerEl.textContent = '';
+        timerEl.classList.add('hidden');
+        return;
+    }
+    // Count down once a limit is known, otherwise show elapsed time
+    timerEl.textContent = remaining != null ? `-${formatSeconds(remaining)}` : formatSeconds(elapsed);
+    timerEl.classList.toggle('ending', remaining != null && remaining <= 10);
+    timerEl.classList.remove('hidden');
+}
+
+function showLatency(timings) {
+    if (timings?.total_ms == null) return;
+    timerEl.textContent = `${(timings.total_ms / 1000).toFixed(1)}s`;
+    timerEl.classList.remove('ending', 'hidden');
+}
+
 function setStatus(status) {
     container.classList.remove('success', 'error');
     if (status === 'success') container.classList.add('success');
@@ -70,50 +110,118 @@ async function init() {
     
     await loadSettings();
 
-    await listen('settings://changed', async () => {
+    await listen(EVENTS.SETTINGS_CHANGED, async (event) => {
         log('Settings changed, reloading...');
+        const { auto_translate, target_language } = event.payload ?? {};
+        const switched = auto_translate && target_language && target_language !== targetLanguage;
         await loadSettings();
+        // Briefly show the new target, unless a dictation is on screen
+        if (switched && container.classList.contains('hidden')) {
+            setStatus('');
+            updateTimer(null);
+            updateText(`→ ${target_language}`);
+            showOverlay();
+            hideOverlay(hideAfterMs);
+        }
     });
 
-    await listen('transcription://status', (event) => {
+    await listen(EVENTS.STATUS, (event) => {
         log('Status event: ' + JSON.stringify(event.payload));
         const { phase } = event.payload;
 
-        if (phase === 'recording') {
+        if (phase === PHASES.RECORDING) {
+            placing = false;
             setStatus('recording');
             updateText('');
+            // No ticks come while the timer is turned off for the overlay
+            updateTimer(showLevels ? 0 : null, null);
             showOverlay();
-        } else if (phase === 'transcribing') {
+        } else if (phase === PHASES.TRANSCRIBING || phase === PHASES.DELIVERING) {
             setStatus('transcribing');
-        } else if (phase === 'success') {
+            updateTimer(null);
+        } else if (phase === PHASES.SUCCESS) {
             setStatus('success');
             hideOverlay(500);
-        } else if (phase === 'error') {
+        } else if (phase === PHASES.PAUSED) {
+            setStatus('idle');
+            updateTimer(null);
+            hideOverlay(0);
+        } else if (phase === PHASES.ERROR) {
             setStatus('error');
+            updateTimer(null);
             updateText('Ошибка');
-            hideOverlay(3000);
-        } else if (phase === 'idle') {
+            hideOverlay(1500);
+        } else if (phase === PHASES.IDLE) {
+            // After success the timer shows the latency until the overlay fades out
             if (!container.classList.contains('success')) {
+                updateTimer(null);
                 hideOverlay(0);
             }
         }
     });
 
-    await listen('transcription://partial', (event) => {
+    await listen(EVENTS.OVERLAY_INTERACTIVE, (event) => {
+        interactive = Boolean(event.payload?.interactive);
+        container.classList.toggle('interactive', interactive);
+        if (interactive) {
+            if (container.classList.contains('hidden')) {
+                placing = true;
+                setStatus('');
+                updateTimer(null);
+                updateText('Перетащите окно в нужное место');
+            }
+            showOverlay();
+        } else if (placing) {
+            placing = false;
+            hideOverlay(0);
+        }
+    });
+
+    container.addEventListener('mousedown', (event) => {
+        if (interactive && event.button === 0) {
+            appWindow.startDragging().catch(e => log('startDragging error: ' + e));
+        }
+    });
+
+    // A drag fires a burst of moves; only where it ends is saved
+    await appWindow.onMoved(({ payload }) => {
+        if (!interactive) return;
+        if (moveTimeout) clearTimeout(moveTimeout);
+        moveTimeout = setTimeout(() => {
+            emit(OVERLAY_MOVED, { x: payload.x, y: payload.y })
+                .catch(e => log('Failed to report overlay position: ' + e));
+        }, 300);
+    });
+
+    // Partials, the final text and ticks are left out by the backend when the
+    // overlay_show_* settings turn them off; that only works for listeners on this window
+    await appWindow.listen(EVENTS.RECORDING_TICK, (event) => {
+        const { elapsed_secs, remaining_secs } = event.payload ?? {};
+        updateTimer(elapsed_secs ?? 0, remaining_secs ?? null);
+    });
+
+    await appWindow.listen(EVENTS.PARTIAL, (event) => {
         if (!showRealtimeText) {
             return;
         }
         if (event.payload?.text) {
             updateText(event.payload.text);
+            textEl.classList.toggle('interim', Boolean(event.payload.interim));
             showOverlay();
         }
     });
 
-    await listen('transcription://complete', (event) => {
+    // The text so far was delivered; partials of the next segment start from scratch
+    await listen(EVENTS.SEGMENT, () => {
+        updateText('');
+    });
+
+    await appWindow.listen(EVENTS.COMPLETE, (event) => {
         if (event.payload?.text) {
             updateText(event.payload.text);
+            showLatency(event.payload.timings);
             setStatus('success');
-            hideOverlay(2500);
+            hideOverlay(hideAfterMs);
         }
     });
 }
@@ -0,0 +1,220 @@
+/// RMS level the gain loop aims for, in dBFS
+pub const DEFAULT_TARGET_DB: f32 = -20.0;
+/// Most the loop amplifies a quiet speaker, in dB
+pub const DEFAULT_MAX_GAIN_DB: f32 = 24.0;
+
+const TARGET_DB_RANGE: (f32, f32) = (-40.0, -6.0);
+const MAX_GAIN_DB_RANGE: (f32, f32) = (0.0, 40.0);
+/// Loud input is turned down by at most this much
+const MIN_GAIN_DB: f32 = -12.0;
+/// Chunks quieter than this are treated as silence: the gain holds instead of rising
+/// towards the cap on background noise
+const SILENCE_DB: f32 = -50.0;
+/// The gain rises slowly, so pauses and breaths don't pump up the noise floor...
+const RISE_DB_PER_SEC: f32 = 10.0;
+/// ...and drops fast when the speaker gets louder
+const FALL_DB_PER_SEC: f32 = 40.0;
+/// Amplified peaks stay this far below full scale
+const PEAK_CEILING_DB: f32 = -1.0;
+
+const FULL_SCALE: f32 = 32768.0;
+
+fn to_db(level: f32) -> f32 {
+    20.0 * level.max(1e-9).log10()
+}
+
+fn to_linear(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AgcConfig {
+    pub target_db: f32,
+    pub max_gain_db: f32,
+}
+
+impl AgcConfig {
+    /// Out-of-range values are clamped to what the loop supports
+    pub fn new(target_db: f32, max_gain_db: f32) -> Self {
+        Self {
+            target_db: target_db.clamp(TARGET_DB_RANGE.0, TARGET_DB_RANGE.1),
+            max_gain_db: max_gain_db.clamp(MAX_GAIN_DB_RANGE.0, MAX_GAIN_DB_RANGE.1),
+        }
+    }
+}
+
+impl Default for AgcConfig {
+    fn default() -> Self {
+        Self::new(DEFAULT_TARGET_DB, DEFAULT_MAX_GAIN_DB)
+    }
+}
+
+/// Automatic gain control for mono PCM16 chunks: evens out the level between a
+/// speaker close to the microphone and one across the room. Starts at unity gain;
+/// a new one is made for each dictation.
+#[derive(Debug, Clone)]
+pub struct Agc {
+    config: AgcConfig,
+    sample_rate: u32,
+    gain_db: f32,
+}
+
+impl Agc {
+    pub fn new(config: AgcConfig, sample_rate: u32) -> Self {
+        Self {
+            config,
+            sample_rate: sample_rate.max(1),
+            gain_db: 0.0,
+        }
+    }
+
+    pub fn gain_db(&self) -> f32 {
+        self.gain_db
+    }
+
+    /// Amplify little-endian PCM16 in place. The gain moves towards the target once
+    /// per chunk and is ramped across the chunk, so there are no steps in the signal.
+    pub fn process(&mut self, pcm: &mut [u8]) {
+        let count = pcm.len() / 2;
+        if count == 0 {
+            return;
+        }
+
+        let mut sum_squares = 0.0f64;
+        let mut peak = 0.0f32;
+        for bytes in pcm.chunks_exact(2) {
+            let sample = i16::from_le_bytes([bytes[0], bytes[1]]) as f32;
+            sum_squares += (sample * sample) as f64;
+            peak = peak.max(sample.abs());
+        }
+        let level_db = to_db((sum_squares / count as f64).sqrt() as f32 / FULL_SCALE);
+
+        let previous = self.gain_db;
+        if level_db > SILENCE_DB {
+            let wanted =
+                (self.config.target_db - level_db).clamp(MIN_GAIN_DB, self.config.max_gain_db);
+            let elapsed = count as f32 / self.sample_rate as f32;
+            self.gain_db = if wanted > previous {
+                (previous + RISE_DB_PER_SEC * elapsed).min(wanted)
+            } else {
+                (previous - FALL_DB_PER_SEC * elapsed).max(wanted)
+            };
+        }
+        // A sudden loud chunk is turned down right away instead of clipping
+        let ceiling = PEAK_CEILING_DB - to_db(peak / FULL_SCALE);
+        self.gain_db = self.gain_db.min(ceiling);
+
+        let start = to_linear(previous.min(ceiling));
+        let end = to_linear(self.gain_db);
+        for (i, bytes) in pcm.chunks_exact_mut(2).enumerate() {
+            let gain = start + (end - start) * (i + 1) as f32 / count as f32;
+            let sample = i16::from_le_bytes([bytes[0], bytes[1]]) as f32 * gain;
+            let sample = sample.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+            bytes.copy_from_slice(&sample.to_le_bytes());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 16_000;
+    /// 100 ms, like the streaming capture
+    const CHUNK: usize = 1_600;
+
+    /// Consecutive chunks of a 440 Hz sine with the given RMS level
+    fn sine(level_db: f32, chunks: usize) -> Vec<Vec<u8>> {
+        let amplitude = to_linear(level_db) * FULL_SCALE * std::f32::consts::SQRT_2;
+        let samples: Vec<u8> = (0..CHUNK * chunks)
+            .flat_map(|n| {
+                let phase = 2.0 * std::f32::consts::PI * 440.0 * n as f32 / RATE as f32;
+                ((amplitude * phase.sin()).round() as i16).to_le_bytes()
+            })
+            .collect();
+        samples.chunks(CHUNK * 2).map(<[u8]>::to_vec).collect()
+    }
+
+    fn rms_db(pcm: &[u8]) -> f32 {
+        let samples: Vec<f32> = pcm
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32)
+            .collect();
+        let mean = samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32;
+        to_db(mean.sqrt() / FULL_SCALE)
+    }
+
+    #[test]
+    fn test_steady_sine_converges_to_target() {
+        let mut agc = Agc::new(AgcConfig::default(), RATE);
+        let mut last = Vec::new();
+        for mut chunk in sine(-40.0, 50) {
+            agc.process(&mut chunk);
+            last = chunk;
+        }
+        assert!((rms_db(&last) - DEFAULT_TARGET_DB).abs() < 0.5);
+        assert!((agc.gain_db() - 20.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn test_loud_input_is_turned_down_quickly() {
+        let mut agc = Agc::new(AgcConfig::default(), RATE);
+        let mut last = Vec::new();
+        for mut chunk in sine(-8.0, 5) {
+            agc.process(&mut chunk);
+            last = chunk;
+        }
+        assert!((rms_db(&last) - DEFAULT_TARGET_DB).abs() < 0.5);
+    }
+
+    #[test]
+    fn test_silence_is_not_amplified() {
+        let mut agc = Agc::new(AgcConfig::default(), RATE);
+        for mut chunk in sine(-70.0, 20) {
+            agc.process(&mut chunk);
+        }
+        assert_eq!(agc.gain_db(), 0.0);
+
+        let mut digital_silence = vec![0u8; CHUNK * 2];
+        agc.process(&mut digital_silence);
+        assert!(digital_silence.iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_gain_rises_slowly_towards_the_cap() {
+        let mut agc = Agc::new(AgcConfig::default(), RATE);
+        // Quiet enough to want the full cap, loud enough not to count as silence
+        for mut chunk in sine(-48.0, 5) {
+            agc.process(&mut chunk);
+        }
+        assert!((agc.gain_db() - 5.0).abs() < 0.01);
+        for mut chunk in sine(-48.0, 50) {
+            agc.process(&mut chunk);
+        }
+        assert_eq!(agc.gain_db(), DEFAULT_MAX_GAIN_DB);
+    }
+
+    #[test]
+    fn test_peaks_never_clip() {
+        let mut agc = Agc::new(AgcConfig::default(), RATE);
+        for mut chunk in sine(-40.0, 50) {
+            agc.process(&mut chunk);
+        }
+        // The speaker suddenly leans into the microphone
+        let mut loud = sine(-6.0, 1).remove(0);
+        agc.process(&mut loud);
+        let peak = loud
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]).unsigned_abs())
+            .max()
+            .unwrap();
+        assert!(peak < i16::MAX as u16);
+    }
+
+    #[test]
+    fn test_config_limits() {
+        let config = AgcConfig::new(0.0, 100.0);
+        assert_eq!(config.target_db, -6.0);
+        assert_eq!(config.max_gain_db, 40.0);
+    }
+}
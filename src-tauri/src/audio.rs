@@ -1,8 +1,10 @@
-﻿use std::{
-    io::Cursor,
-    sync::{mpsc, Arc, Mutex},
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
     thread,
-    time::Instant,
+    time::{Duration, Instant},
 };
 
 use anyhow::{anyhow, Context, Result};
@@ -10,44 +12,196 @@ use cpal::{
     traits::DeviceTrait, traits::HostTrait, traits::StreamTrait, Sample, SampleFormat, SizedSample,
     Stream,
 };
-use hound::{SampleFormat as WavSampleFormat, WavSpec, WavWriter};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::watch;
 
-pub struct Recorder;
+use crate::{
+    messages::{self, Msg},
+    mic_access::{MicAccessCheck, SystemMicAccess},
+    recording_buffer::RecordingBuffer,
+};
+
+/// Without spilling to disk a recording keeps at most this much audio
+const MAX_IN_MEMORY_SECS: usize = 120;
+
+/// A capture refused because the microphone is hard-muted
+#[derive(Debug, Error)]
+#[error("{}", messages::text(Msg::MicMuted))]
+pub struct MicMuted;
+
+/// The system has no input device to record from
+#[derive(Debug, Error)]
+#[error("{}", messages::text(Msg::NoMicrophone))]
+pub struct NoMicrophone;
+
+/// A recording that stopped before any audio arrived
+#[derive(Debug, Error)]
+#[error("{}", messages::text(Msg::NoAudioRecorded))]
+pub struct NoAudioRecorded;
+
+/// The hard mute switch. While it is on, nothing opens the microphone: recordings
+/// don't start, the streaming gate doesn't open and the streaming capture stops.
+/// Clones share the switch.
+#[derive(Debug, Clone)]
+pub struct MicMute(Arc<watch::Sender<bool>>);
+
+impl Default for MicMute {
+    fn default() -> Self {
+        Self(Arc::new(watch::Sender::new(false)))
+    }
+}
+
+impl MicMute {
+    pub fn is_muted(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Returns whether the switch moved
+    pub fn set(&self, muted: bool) -> bool {
+        self.0.send_if_modified(|current| {
+            let changed = *current != muted;
+            *current = muted;
+            changed
+        })
+    }
+
+    /// Fails with `MicMuted` while muted
+    pub fn check(&self) -> Result<()> {
+        if self.is_muted() {
+            return Err(MicMuted.into());
+        }
+        Ok(())
+    }
+
+    /// Follows the switch; the receiver starts with the current state seen
+    pub fn subscribe(&self) -> watch::Receiver<bool> {
+        self.0.subscribe()
+    }
+}
+
+/// The input device a capture runs on and what was done to its audio, recorded
+/// with each dictation
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaptureInfo {
+    pub device: String,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// Converted to another sample rate before upload; the live captures send the
+    /// device rate
+    pub resampled: bool,
+    /// Automatic gain control was applied (streaming only)
+    pub agc: bool,
+    /// Quiet chunks were silenced before upload
+    pub noise_gate: bool,
+}
+
+/// The default input device and the format it records in
+pub(crate) fn default_input() -> Result<(cpal::Device, cpal::SupportedStreamConfig, CaptureInfo)> {
+    let host = cpal::default_host();
+    let device = host.default_input_device().ok_or(NoMicrophone)?;
+    let config = device
+        .default_input_config()
+        .context("Failed to query default input configuration")?;
+    let info = CaptureInfo {
+        device: device.name().unwrap_or_else(|_| "<unnamed device>".into()),
+        sample_rate: config.sample_rate().0,
+        channels: config.channels(),
+        ..CaptureInfo::default()
+    };
+    Ok((device, config, info))
+}
+
+/// What a capture started now would record, without opening the device
+pub fn default_input_info() -> Result<CaptureInfo> {
+    default_input().map(|(_, _, info)| info)
+}
+
+pub struct Recorder {
+    mute: MicMute,
+    access: Arc<dyn MicAccessCheck>,
+}
 
 pub struct RecordingSession {
     stop_tx: Option<mpsc::Sender<()>>,
-    handle: Option<thread::JoinHandle<Result<RecordingResult>>>,
+    handle: Option<thread::JoinHandle<Result<RecordingBuffer>>>,
     started_at: Instant,
+    snapshot: AudioSnapshot,
 }
 
-struct RecordingResult {
-    buffer: Vec<f32>,
-    sample_rate: u32,
-    channels: u16,
+/// Read access to the audio a recording has captured so far, for interim transcripts
+#[derive(Clone)]
+pub struct AudioSnapshot {
+    buffer: Arc<Mutex<RecordingBuffer>>,
+    /// Cleared when the recording stops
+    recording: Arc<AtomicBool>,
+}
+
+impl AudioSnapshot {
+    pub fn is_recording(&self) -> bool {
+        self.recording.load(Ordering::Acquire)
+    }
+
+    /// WAV of everything captured so far; None once the recording stopped or went
+    /// to disk
+    pub fn wav(&self) -> Option<Result<Vec<u8>>> {
+        if !self.is_recording() {
+            return None;
+        }
+        match self.buffer.lock() {
+            Ok(buffer) => buffer.wav(),
+            Err(_) => Some(Err(anyhow!("Failed to access recorded samples"))),
+        }
+    }
 }
 
 impl Recorder {
-    pub fn new() -> Result<Self> {
-        Ok(Self)
-    }
-
-    pub fn start(&self) -> Result<RecordingSession> {
-        let host = cpal::default_host();
-        let device = host
-            .default_input_device()
-            .ok_or_else(|| anyhow!("No input microphone detected"))?;
-        let config = device
-            .default_input_config()
-            .context("Failed to query default input configuration")?;
+    pub fn new(mute: MicMute) -> Result<Self> {
+        Ok(Self::with_access_check(mute, Arc::new(SystemMicAccess)))
+    }
+
+    pub fn with_access_check(mute: MicMute, access: Arc<dyn MicAccessCheck>) -> Self {
+        Self { mute, access }
+    }
+
+    /// Fails with `MicMuted` while hard-muted, or with `MicBlocked` while the OS
+    /// would only deliver silence
+    pub fn check_access(&self) -> Result<()> {
+        self.mute.check()?;
+        if let Some(blocked) = self.access.blocked() {
+            return Err(blocked.into());
+        }
+        Ok(())
+    }
+
+    /// Start recording from the default input device. Once the recording is longer
+    /// than `spill_after` its audio goes to a temporary file instead of memory;
+    /// without it the recording stops growing after two minutes.
+    pub fn start(&self, spill_after: Option<Duration>) -> Result<(RecordingSession, CaptureInfo)> {
+        self.check_access()?;
+        let (device, config, info) = default_input()?;
         let sample_format = config.sample_format();
         let config: cpal::StreamConfig = config.into();
         let (stop_tx, stop_rx) = mpsc::channel();
 
-        let handle = thread::spawn(move || -> Result<RecordingResult> {
-            let channels = config.channels as usize;
-            let sample_rate = config.sample_rate.0;
-            let max_samples = (sample_rate as usize) * channels * 120; // 2 minutes max
-            let buffer = Arc::new(Mutex::new(Vec::<f32>::with_capacity(max_samples)));
+        let samples_per_sec = config.sample_rate.0 as usize * config.channels as usize;
+        let buffer = Arc::new(Mutex::new(
+            RecordingBuffer::new(
+                config.sample_rate.0,
+                config.channels,
+                samples_per_sec * MAX_IN_MEMORY_SECS,
+                spill_after.map(|after| (samples_per_sec as f64 * after.as_secs_f64()) as usize),
+            )
+            // Here rather than in the audio callback, which must not touch the disk
+            .start_spill(),
+        ));
+        let snapshot = AudioSnapshot {
+            buffer: buffer.clone(),
+            recording: Arc::new(AtomicBool::new(true)),
+        };
+
+        let handle = thread::spawn(move || -> Result<RecordingBuffer> {
             let buffer_clone = buffer.clone();
             let buffer_for_err = buffer.clone();
 
@@ -67,24 +221,22 @@ impl Recorder {
             let mut data = buffer
                 .lock()
                 .map_err(|_| anyhow!("Failed to access recorded samples"))?;
-            let collected = std::mem::take(&mut *data);
-            Ok(RecordingResult {
-                buffer: collected,
-                sample_rate,
-                channels: config.channels,
-            })
+            Ok(std::mem::take(&mut *data))
         });
 
-        Ok(RecordingSession {
+        let session = RecordingSession {
             stop_tx: Some(stop_tx),
             handle: Some(handle),
             started_at: Instant::now(),
-        })
+            snapshot,
+        };
+        Ok((session, info))
     }
 }
 
 impl Drop for RecordingSession {
     fn drop(&mut self) {
+        self.snapshot.recording.store(false, Ordering::Release);
         if let Some(tx) = self.stop_tx.take() {
             let _ = tx.send(());
         }
@@ -94,12 +246,39 @@ impl Drop for RecordingSession {
     }
 }
 
+impl Recorder {
+    /// Open (without starting) a stream on the default input device.
+    /// Returns a description of the device for diagnostics.
+    pub fn probe_input_device() -> Result<String> {
+        let (device, config, info) = default_input()?;
+        let sample_format = config.sample_format();
+        let config: cpal::StreamConfig = config.into();
+
+        let stream = build_stream(
+            &device,
+            &config,
+            sample_format,
+            Arc::new(Mutex::new(RecordingBuffer::default())),
+            |_| {},
+        )?;
+        drop(stream);
+
+        Ok(format!(
+            "{} ({} Hz, {} ch, {:?})",
+            info.device, info.sample_rate, info.channels, sample_format
+        ))
+    }
+}
+
 impl RecordingSession {
+    pub fn snapshot(&self) -> AudioSnapshot {
+        self.snapshot.clone()
+    }
+
     pub fn stop(mut self) -> Result<Vec<u8>> {
+        self.snapshot.recording.store(false, Ordering::Release);
         if self.started_at.elapsed().as_millis() < 120 {
-            return Err(anyhow!(
-                "Recording stopped too quickly. Hold the hotkey for a little longer before releasing."
-            ));
+            return Err(anyhow!("{}", messages::text(Msg::RecordingTooQuick)));
         }
 
         if let Some(tx) = self.stop_tx.take() {
@@ -110,35 +289,15 @@ impl RecordingSession {
             .take()
             .ok_or_else(|| anyhow!("Recording session already finished"))?;
 
-        let result = handle
+        let buffer = handle
             .join()
             .map_err(|_| anyhow!("Failed to join recording thread"))??;
 
-        if result.buffer.is_empty() {
-            return Err(anyhow!("Recording produced no audio"));
+        if buffer.is_empty() {
+            return Err(NoAudioRecorded.into());
         }
 
-        let mut cursor = Cursor::new(Vec::with_capacity(result.buffer.len() * 2));
-        let mut writer = WavWriter::new(
-            &mut cursor,
-            WavSpec {
-                channels: result.channels,
-                sample_rate: result.sample_rate,
-                bits_per_sample: 16,
-                sample_format: WavSampleFormat::Int,
-            },
-        )
-        .context("Failed to create WAV writer")?;
-
-        for sample in result.buffer {
-            let amp = (sample * i16::MAX as f32).clamp(i16::MIN as f32, i16::MAX as f32) as i16;
-            writer
-                .write_sample(amp)
-                .context("Failed to write WAV sample")?;
-        }
-
-        writer.finalize().context("Failed to finalize WAV output")?;
-        Ok(cursor.into_inner())
+        buffer.into_wav()
     }
 }
 
@@ -146,61 +305,36 @@ fn build_stream(
     device: &cpal::Device,
     config: &cpal::StreamConfig,
     sample_format: SampleFormat,
-    buffer: Arc<Mutex<Vec<f32>>>,
+    buffer: Arc<Mutex<RecordingBuffer>>,
     err_fn: impl Fn(cpal::StreamError) + Send + 'static,
 ) -> Result<Stream> {
-    let channels = config.channels as usize;
-    let max_samples = config.sample_rate.0 as usize * channels * 120;
-
     match sample_format {
-        SampleFormat::F32 => {
-            build::<f32>(device, config, buffer, err_fn, channels, max_samples, |s| s)
-        }
-        SampleFormat::F64 => {
-            build::<f64>(device, config, buffer, err_fn, channels, max_samples, |s| {
-                s as f32
-            })
-        }
-        SampleFormat::I16 => {
-            build::<i16>(device, config, buffer, err_fn, channels, max_samples, |s| {
-                s as f32 / i16::MAX as f32
-            })
-        }
-        SampleFormat::I32 => {
-            build::<i32>(device, config, buffer, err_fn, channels, max_samples, |s| {
-                (s as f64 / i32::MAX as f64) as f32
-            })
-        }
-        SampleFormat::I8 => {
-            build::<i8>(device, config, buffer, err_fn, channels, max_samples, |s| {
-                s as f32 / i8::MAX as f32
-            })
-        }
-        SampleFormat::I64 => {
-            build::<i64>(device, config, buffer, err_fn, channels, max_samples, |s| {
-                (s as f64 / i64::MAX as f64) as f32
-            })
-        }
-        SampleFormat::U8 => {
-            build::<u8>(device, config, buffer, err_fn, channels, max_samples, |s| {
-                (s as f32 / u8::MAX as f32) * 2.0 - 1.0
-            })
-        }
-        SampleFormat::U16 => {
-            build::<u16>(device, config, buffer, err_fn, channels, max_samples, |s| {
-                (s as f32 / u16::MAX as f32) * 2.0 - 1.0
-            })
-        }
-        SampleFormat::U32 => {
-            build::<u32>(device, config, buffer, err_fn, channels, max_samples, |s| {
-                ((s as f64) / u32::MAX as f64 * 2.0 - 1.0) as f32
-            })
-        }
-        SampleFormat::U64 => {
-            build::<u64>(device, config, buffer, err_fn, channels, max_samples, |s| {
-                ((s as f64) / u64::MAX as f64 * 2.0 - 1.0) as f32
-            })
-        }
+        SampleFormat::F32 => build::<f32>(device, config, buffer, err_fn, |s| s),
+        SampleFormat::F64 => build::<f64>(device, config, buffer, err_fn, |s| s as f32),
+        SampleFormat::I16 => build::<i16>(device, config, buffer, err_fn, |s| {
+            s as f32 / i16::MAX as f32
+        }),
+        SampleFormat::I32 => build::<i32>(device, config, buffer, err_fn, |s| {
+            (s as f64 / i32::MAX as f64) as f32
+        }),
+        SampleFormat::I8 => build::<i8>(device, config, buffer, err_fn, |s| {
+            s as f32 / i8::MAX as f32
+        }),
+        SampleFormat::I64 => build::<i64>(device, config, buffer, err_fn, |s| {
+            (s as f64 / i64::MAX as f64) as f32
+        }),
+        SampleFormat::U8 => build::<u8>(device, config, buffer, err_fn, |s| {
+            (s as f32 / u8::MAX as f32) * 2.0 - 1.0
+        }),
+        SampleFormat::U16 => build::<u16>(device, config, buffer, err_fn, |s| {
+            (s as f32 / u16::MAX as f32) * 2.0 - 1.0
+        }),
+        SampleFormat::U32 => build::<u32>(device, config, buffer, err_fn, |s| {
+            ((s as f64) / u32::MAX as f64 * 2.0 - 1.0) as f32
+        }),
+        SampleFormat::U64 => build::<u64>(device, config, buffer, err_fn, |s| {
+            ((s as f64) / u64::MAX as f64 * 2.0 - 1.0) as f32
+        }),
         other => Err(anyhow!("Unsupported sample format: {other:?}")),
     }
 }
@@ -208,25 +342,15 @@ fn build_stream(
 fn build<T: Sample + SizedSample + 'static>(
     device: &cpal::Device,
     config: &cpal::StreamConfig,
-    buffer: Arc<Mutex<Vec<f32>>>,
+    buffer: Arc<Mutex<RecordingBuffer>>,
     err_fn: impl Fn(cpal::StreamError) + Send + 'static,
-    channels: usize,
-    max_samples: usize,
     convert: fn(T) -> f32,
 ) -> Result<Stream> {
     let stream = device.build_input_stream(
         config,
         move |data: &[T], _| {
             if let Ok(mut buf) = buffer.lock() {
-                if buf.len() >= max_samples {
-                    return;
-                }
-                buf.reserve(data.len());
-                for frame in data.chunks(channels) {
-                    for &sample in frame {
-                        buf.push(convert(sample));
-                    }
-                }
+                buf.extend(data.iter().map(|&sample| convert(sample)));
             }
         },
         err_fn,
@@ -234,3 +358,46 @@ fn build<T: Sample + SizedSample + 'static>(
     )?;
     Ok(stream)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mic_access::MicBlocked;
+
+    #[test]
+    fn test_muted_recorder_refuses_to_start() {
+        let mute = MicMute::default();
+        let recorder = Recorder::new(mute.clone()).unwrap();
+        assert!(mute.set(true));
+        assert!(!mute.set(true));
+
+        let err = recorder.start(None).err().unwrap();
+        assert!(err.is::<MicMuted>());
+    }
+
+    struct Blocked;
+
+    impl MicAccessCheck for Blocked {
+        fn blocked(&self) -> Option<MicBlocked> {
+            Some(MicBlocked::EndpointMuted)
+        }
+    }
+
+    #[test]
+    fn test_blocked_microphone_is_not_recorded() {
+        let recorder = Recorder::with_access_check(MicMute::default(), Arc::new(Blocked));
+        let err = recorder.start(None).err().unwrap();
+        assert_eq!(err.downcast_ref(), Some(&MicBlocked::EndpointMuted));
+    }
+
+    #[test]
+    fn test_subscribers_see_the_switch() {
+        let mute = MicMute::default();
+        let mut rx = mute.subscribe();
+        assert!(!rx.has_changed().unwrap());
+        mute.set(true);
+        assert!(rx.has_changed().unwrap());
+        assert!(*rx.borrow_and_update());
+        assert!(mute.check().is_err());
+    }
+}
@@ -0,0 +1,455 @@
+use std::{fs::File, io::Cursor, path::Path, time::Duration};
+
+use hound::{SampleFormat as WavSampleFormat, WavReader, WavSpec, WavWriter};
+use symphonia::core::{
+    audio::SampleBuffer, codecs::DecoderOptions, errors::Error as SymphoniaError,
+    formats::FormatOptions, io::MediaSourceStream, meta::MetadataOptions, probe::Hint,
+};
+
+/// Files are converted to mono at this rate (or their own rate, if lower): plenty for
+/// speech and a third of the upload size of a 48 kHz phone memo
+const TARGET_SAMPLE_RATE: u32 = 16_000;
+
+/// Extensions `decode_to_wav` accepts; WAV is read by hound, the rest by symphonia
+const SUPPORTED_EXTENSIONS: &[&str] = &["wav", "mp3", "m4a", "mp4", "aac"];
+
+#[derive(Debug, thiserror::Error)]
+pub enum AudioFileError {
+    #[error("Unsupported audio format: {0}")]
+    Unsupported(String),
+
+    #[error("Failed to read audio file: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Failed to decode audio file: {0}")]
+    Decode(String),
+
+    #[error("Audio file produced no audio")]
+    Empty,
+}
+
+impl From<hound::Error> for AudioFileError {
+    fn from(err: hound::Error) -> Self {
+        match err {
+            hound::Error::IoError(e) => Self::Io(e),
+            hound::Error::Unsupported => Self::Unsupported("WAV encoding".to_string()),
+            other => Self::Decode(other.to_string()),
+        }
+    }
+}
+
+impl From<SymphoniaError> for AudioFileError {
+    fn from(err: SymphoniaError) -> Self {
+        match err {
+            SymphoniaError::IoError(e) => Self::Io(e),
+            SymphoniaError::Unsupported(what) => Self::Unsupported(what.to_string()),
+            other => Self::Decode(other.to_string()),
+        }
+    }
+}
+
+/// Linear-interpolation downsampler fed one sample at a time. No low-pass filter;
+/// speech keeps almost nothing above the new Nyquist frequency anyway.
+struct Resampler {
+    /// Input samples per output sample
+    step: f64,
+    /// Input position of the next output sample
+    next: f64,
+    index: u64,
+    previous: f32,
+}
+
+impl Resampler {
+    fn new(from_rate: u32, to_rate: u32) -> Self {
+        Self {
+            step: f64::from(from_rate) / f64::from(to_rate),
+            next: 0.0,
+            index: 0,
+            previous: 0.0,
+        }
+    }
+
+    fn push(&mut self, sample: f32, out: &mut Vec<f32>) {
+        let index = self.index as f64;
+        while self.next <= index {
+            // Between the previous input sample (index - 1) and this one
+            let frac = (self.next - (index - 1.0)) as f32;
+            out.push(self.previous + (sample - self.previous) * frac);
+            self.next += self.step;
+        }
+        self.previous = sample;
+        self.index += 1;
+    }
+}
+
+/// Mono 16-bit samples collected frame by frame while the source is decoded
+struct WavEncoder {
+    sample_rate: u32,
+    resampler: Resampler,
+    resampled: Vec<f32>,
+    samples: Vec<i16>,
+}
+
+impl WavEncoder {
+    fn new(source_rate: u32) -> Self {
+        let sample_rate = source_rate.min(TARGET_SAMPLE_RATE);
+        Self {
+            sample_rate,
+            resampler: Resampler::new(source_rate, sample_rate),
+            resampled: Vec::new(),
+            samples: Vec::new(),
+        }
+    }
+
+    /// Add one frame of interleaved samples in -1.0..=1.0, downmixed to mono
+    fn push_frame(&mut self, frame: &[f32]) {
+        if frame.is_empty() {
+            return;
+        }
+        let mono = frame.iter().sum::<f32>() / frame.len() as f32;
+        self.resampler.push(mono, &mut self.resampled);
+        self.samples.extend(
+            self.resampled
+                .drain(..)
+                .map(|s| (s * i16::MAX as f32).clamp(i16::MIN as f32, i16::MAX as f32) as i16),
+        );
+    }
+
+    fn finish(self) -> Result<Vec<u8>, AudioFileError> {
+        if self.samples.is_empty() {
+            return Err(AudioFileError::Empty);
+        }
+
+        let mut cursor = Cursor::new(Vec::with_capacity(self.samples.len() * 2 + 44));
+        let mut writer = WavWriter::new(
+            &mut cursor,
+            WavSpec {
+                channels: 1,
+                sample_rate: self.sample_rate,
+                bits_per_sample: 16,
+                sample_format: WavSampleFormat::Int,
+            },
+        )?;
+        for sample in self.samples {
+            writer.write_sample(sample)?;
+        }
+        writer.finalize()?;
+        Ok(cursor.into_inner())
+    }
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_lowercase)
+        .unwrap_or_default()
+}
+
+pub fn is_supported(path: &Path) -> bool {
+    SUPPORTED_EXTENSIONS.contains(&extension(path).as_str())
+}
+
+/// Playing time of in-memory WAV bytes, from the header; None if they don't parse
+pub fn wav_duration(wav: &[u8]) -> Option<Duration> {
+    let reader = WavReader::new(Cursor::new(wav)).ok()?;
+    let sample_rate = reader.spec().sample_rate;
+    (sample_rate > 0)
+        .then(|| Duration::from_secs_f64(f64::from(reader.duration()) / f64::from(sample_rate)))
+}
+
+/// The part of the WAV file at `path` from `start` to `end`, as WAV bytes in the file's
+/// own format. Times are converted with the file's sample rate; a range running past
+/// the end is cut to what the file holds, and one starting past it gives an empty clip.
+pub fn slice_wav(path: &Path, start: Duration, end: Duration) -> Result<Vec<u8>, AudioFileError> {
+    let mut reader = WavReader::open(path)?;
+    let spec = reader.spec();
+    let frames = reader.duration();
+    let frame_at = |at: Duration| {
+        // Float to int casts saturate, so a huge `at` lands on `frames`
+        ((at.as_secs_f64() * f64::from(spec.sample_rate)).round() as u32).min(frames)
+    };
+    let first = frame_at(start);
+    let last = frame_at(end).max(first);
+    reader.seek(first)?;
+    let count = (last - first) as usize * usize::from(spec.channels);
+
+    let mut bytes = Cursor::new(Vec::new());
+    let mut writer = WavWriter::new(&mut bytes, spec)?;
+    match spec.sample_format {
+        WavSampleFormat::Float => {
+            for sample in reader.samples::<f32>().take(count) {
+                writer.write_sample(sample?)?;
+            }
+        }
+        WavSampleFormat::Int => {
+            for sample in reader.samples::<i32>().take(count) {
+                writer.write_sample(sample?)?;
+            }
+        }
+    }
+    writer.finalize()?;
+    Ok(bytes.into_inner())
+}
+
+/// Convert an audio file into the WAV bytes `TranscriptionService::perform` expects.
+/// The file is decoded as it is read, so only the (much smaller) mono output is held in memory.
+pub fn decode_to_wav(path: &Path) -> Result<Vec<u8>, AudioFileError> {
+    match extension(path).as_str() {
+        "wav" => decode_wav(path),
+        ext if SUPPORTED_EXTENSIONS.contains(&ext) => decode_compressed(path, ext),
+        "" => Err(AudioFileError::Unsupported(
+            "file without extension".to_string(),
+        )),
+        other => Err(AudioFileError::Unsupported(format!(".{other} files"))),
+    }
+}
+
+fn decode_wav(path: &Path) -> Result<Vec<u8>, AudioFileError> {
+    let mut reader = WavReader::open(path)?;
+    let spec = reader.spec();
+    let channels = usize::from(spec.channels);
+    let mut encoder = WavEncoder::new(spec.sample_rate);
+    let mut frame = Vec::with_capacity(channels);
+
+    let mut push = |sample: f32| {
+        frame.push(sample);
+        if frame.len() == channels {
+            encoder.push_frame(&frame);
+            frame.clear();
+        }
+    };
+    match spec.sample_format {
+        WavSampleFormat::Float => {
+            for sample in reader.samples::<f32>() {
+                push(sample?);
+            }
+        }
+        WavSampleFormat::Int => {
+            let scale = (1_i64 << (spec.bits_per_sample - 1)) as f32;
+            for sample in reader.samples::<i32>() {
+                push(sample? as f32 / scale);
+            }
+        }
+    }
+
+    encoder.finish()
+}
+
+fn decode_compressed(path: &Path, extension: &str) -> Result<Vec<u8>, AudioFileError> {
+    let source = MediaSourceStream::new(Box::new(File::open(path)?), Default::default());
+    let mut hint = Hint::new();
+    hint.with_extension(extension);
+
+    let mut format = symphonia::default::get_probe()
+        .format(
+            &hint,
+            source,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )?
+        .format;
+    let track = format
+        .default_track()
+        .ok_or_else(|| AudioFileError::Unsupported("file without an audio track".to_string()))?;
+    let track_id = track.id;
+    let sample_rate = track
+        .codec_params
+        .sample_rate
+        .ok_or_else(|| AudioFileError::Decode("unknown sample rate".to_string()))?;
+    let mut decoder =
+        symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+
+    let mut encoder = WavEncoder::new(sample_rate);
+    let mut buffer: Option<SampleBuffer<f32>> = None;
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            // Symphonia reports the end of the stream as an unexpected EOF
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                break
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // A corrupt packet only loses a few milliseconds; keep going
+            Err(SymphoniaError::DecodeError(e)) => {
+                tracing::warn!("[AudioFile] Skipping undecodable packet: {}", e);
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let spec = *decoded.spec();
+        let channels = spec.channels.count();
+        let needed = decoded.capacity() * channels;
+        let buffer = match &mut buffer {
+            Some(buffer) if buffer.capacity() >= needed => buffer,
+            slot => slot.insert(SampleBuffer::new(decoded.capacity() as u64, spec)),
+        };
+        buffer.copy_interleaved_ref(decoded);
+        for frame in buffer.samples().chunks(channels) {
+            encoder.push_frame(frame);
+        }
+    }
+
+    encoder.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_wav(path: &Path, spec: WavSpec, samples: &[i16]) {
+        let mut writer = WavWriter::create(path, spec).unwrap();
+        for &sample in samples {
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    fn read_wav(bytes: Vec<u8>) -> (WavSpec, Vec<i16>) {
+        let mut reader = WavReader::new(Cursor::new(bytes)).unwrap();
+        let samples = reader.samples::<i16>().map(Result::unwrap).collect();
+        (reader.spec(), samples)
+    }
+
+    #[test]
+    fn test_wav_duration_from_header() {
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: 16_000,
+            bits_per_sample: 16,
+            sample_format: WavSampleFormat::Int,
+        };
+        let mut bytes = Cursor::new(Vec::new());
+        let mut writer = WavWriter::new(&mut bytes, spec).unwrap();
+        // 2.5 seconds: 40 000 frames of two samples
+        for _ in 0..40_000 * 2 {
+            writer.write_sample(0i16).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        assert_eq!(
+            wav_duration(bytes.get_ref()),
+            Some(Duration::from_millis(2500))
+        );
+        assert_eq!(wav_duration(b"not a wav file"), None);
+    }
+
+    #[test]
+    fn test_resampler_downsamples_by_ratio() {
+        let mut resampler = Resampler::new(48_000, 16_000);
+        let mut out = Vec::new();
+        for i in 0..48 {
+            resampler.push(i as f32, &mut out);
+        }
+        assert_eq!(out, (0..16).map(|i| (i * 3) as f32).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_stereo_wav_becomes_mono_16k() {
+        let dir = std::env::temp_dir().join(format!("easy-dictate-audio-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("memo.WAV");
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: 32_000,
+            bits_per_sample: 16,
+            sample_format: WavSampleFormat::Int,
+        };
+        // One second; the channels average to 1000
+        let samples: Vec<i16> = (0..32_000).flat_map(|_| [500, 1500]).collect();
+        write_wav(&path, spec, &samples);
+
+        let (spec, samples) = read_wav(decode_to_wav(&path).unwrap());
+        assert_eq!((spec.channels, spec.sample_rate), (1, 16_000));
+        assert_eq!(samples.len(), 16_000);
+        assert!(samples.iter().all(|&s| (999..=1001).contains(&s)));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_slice_wav_uses_the_file_rate_and_clamps() {
+        let dir = std::env::temp_dir().join(format!("easy-dictate-slice-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("recording.wav");
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: 48_000,
+            bits_per_sample: 16,
+            sample_format: WavSampleFormat::Int,
+        };
+        // One second; each frame holds its index / 10 in both channels
+        let samples: Vec<i16> = (0..48_000)
+            .flat_map(|i: i32| [(i / 10) as i16; 2])
+            .collect();
+        write_wav(&path, spec, &samples);
+
+        let (sliced, clip) = read_wav(
+            slice_wav(
+                &path,
+                Duration::from_millis(250),
+                Duration::from_millis(500),
+            )
+            .unwrap(),
+        );
+        assert_eq!(sliced, spec);
+        assert_eq!(clip.len(), 12_000 * 2);
+        assert_eq!(clip[0], 1_200);
+        assert_eq!(clip[clip.len() - 1], 2_399);
+
+        // Past the end: cut to the file, or empty
+        let (_, tail) = read_wav(
+            slice_wav(&path, Duration::from_millis(900), Duration::from_secs(60)).unwrap(),
+        );
+        assert_eq!(tail.len(), 4_800 * 2);
+        let (_, empty) =
+            read_wav(slice_wav(&path, Duration::from_secs(5), Duration::from_secs(6)).unwrap());
+        assert!(empty.is_empty());
+        let (_, reversed) = read_wav(
+            slice_wav(
+                &path,
+                Duration::from_millis(500),
+                Duration::from_millis(100),
+            )
+            .unwrap(),
+        );
+        assert!(reversed.is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_unsupported_and_empty_files() {
+        assert!(matches!(
+            decode_to_wav(Path::new("notes.ogg")),
+            Err(AudioFileError::Unsupported(_))
+        ));
+        assert!(matches!(
+            decode_to_wav(Path::new("memo")),
+            Err(AudioFileError::Unsupported(_))
+        ));
+        assert!(is_supported(Path::new("Voice Memo.M4A")));
+        assert!(!is_supported(Path::new("notes.ogg")));
+
+        let dir = std::env::temp_dir().join(format!("easy-dictate-empty-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("empty.wav");
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: 16_000,
+            bits_per_sample: 16,
+            sample_format: WavSampleFormat::Int,
+        };
+        write_wav(&path, spec, &[]);
+        assert!(matches!(decode_to_wav(&path), Err(AudioFileError::Empty)));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,61 +1,169 @@
 use anyhow::{anyhow, Context, Result};
 use cpal::{
-    traits::{DeviceTrait, HostTrait, StreamTrait},
+    traits::{DeviceTrait, StreamTrait},
     Sample, SampleFormat, SizedSample, Stream,
 };
+use serde::Serialize;
 use std::sync::{
-    atomic::{AtomicBool, Ordering},
+    atomic::{AtomicBool, AtomicU64, Ordering},
     Arc,
 };
 use tokio::sync::mpsc;
 
+use crate::audio::{default_input, CaptureInfo};
+
+/// Length of each captured chunk
+pub const CHUNK_MS: u64 = 100;
+
 /// Maximum number of audio chunks to buffer before dropping (prevents memory exhaustion)
 /// With 100ms chunks, this is ~5 seconds of audio
 const MAX_AUDIO_BUFFER_SIZE: usize = 50;
 
+/// Lost chunks (about half a second) in one utterance that make its transcript suspect
+const LOST_CHUNKS_WARNING: u64 = 5;
+
+/// What happened to the streamed audio of one dictation: counted by the capture and
+/// the streaming client, reset when the gate opens
+#[derive(Debug, Default)]
+pub struct AudioCounters {
+    captured: AtomicU64,
+    sent: AtomicU64,
+    voiced: AtomicU64,
+    bytes_sent: AtomicU64,
+    dropped_buffer_full: AtomicU64,
+    dropped_socket_behind: AtomicU64,
+    ignored_gate_closed: AtomicU64,
+}
+
+/// A snapshot of `AudioCounters`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct AudioStats {
+    pub captured_chunks: u64,
+    pub sent_chunks: u64,
+    /// Sent chunks loud enough to hold speech
+    pub voiced_chunks: u64,
+    pub bytes_sent: u64,
+    /// Dropped because the capture buffer was full
+    pub dropped_buffer_full: u64,
+    /// Dropped because the socket fell too far behind
+    pub dropped_socket_behind: u64,
+    /// Captured while the gate was closed
+    pub ignored_gate_closed: u64,
+}
+
+impl AudioStats {
+    /// Chunks captured with the gate open that never reached the server
+    pub fn lost_chunks(&self) -> u64 {
+        self.dropped_buffer_full + self.dropped_socket_behind
+    }
+
+    /// Enough audio was lost that the transcript may have gaps
+    pub fn incomplete(&self) -> bool {
+        self.lost_chunks() >= LOST_CHUNKS_WARNING
+    }
+}
+
+impl AudioCounters {
+    pub fn reset(&self) {
+        for counter in [
+            &self.captured,
+            &self.sent,
+            &self.voiced,
+            &self.bytes_sent,
+            &self.dropped_buffer_full,
+            &self.dropped_socket_behind,
+            &self.ignored_gate_closed,
+        ] {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+
+    pub fn captured(&self) {
+        self.captured.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A chunk of `bytes` went out; `voiced` if it was loud enough to hold speech
+    pub fn sent(&self, bytes: usize, voiced: bool) {
+        self.sent.fetch_add(1, Ordering::Relaxed);
+        if voiced {
+            self.voiced.fetch_add(1, Ordering::Relaxed);
+        }
+        self.bytes_sent.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn dropped_buffer_full(&self) {
+        self.dropped_buffer_full.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn dropped_socket_behind(&self) {
+        self.dropped_socket_behind.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn ignored_gate_closed(&self) {
+        self.ignored_gate_closed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> AudioStats {
+        AudioStats {
+            captured_chunks: self.captured.load(Ordering::Relaxed),
+            sent_chunks: self.sent.load(Ordering::Relaxed),
+            voiced_chunks: self.voiced.load(Ordering::Relaxed),
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            dropped_buffer_full: self.dropped_buffer_full.load(Ordering::Relaxed),
+            dropped_socket_behind: self.dropped_socket_behind.load(Ordering::Relaxed),
+            ignored_gate_closed: self.ignored_gate_closed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Test hook: the next `ContinuousAudioCapture::new` fails, as on a machine whose
+/// capture backend can't be set up
+#[cfg(debug_assertions)]
+pub static FAIL_NEXT_CAPTURE: AtomicBool = AtomicBool::new(false);
+
 /// Continuous audio capture for ElevenLabs streaming
 pub struct ContinuousAudioCapture {
     stream: Option<Stream>,
     is_running: Arc<AtomicBool>,
     audio_tx: Option<mpsc::Sender<Vec<u8>>>,
-    sample_rate: u32,
 }
 
 impl ContinuousAudioCapture {
-    pub fn new() -> Result<Self> {
+    /// `is_running` is set while the device stream is open, for observers on
+    /// other threads
+    pub fn new(is_running: Arc<AtomicBool>) -> Result<Self> {
+        #[cfg(debug_assertions)]
+        if FAIL_NEXT_CAPTURE.swap(false, Ordering::SeqCst) {
+            return Err(anyhow!("Audio capture failed (forced by test hook)"));
+        }
+        is_running.store(false, Ordering::Release);
         Ok(Self {
             stream: None,
-            is_running: Arc::new(AtomicBool::new(false)),
+            is_running,
             audio_tx: None,
-            sample_rate: 0,
         })
     }
 
     /// Starts continuous audio capture
-    /// Returns a receiver for audio chunks (PCM16 little-endian)
-    pub fn start(&mut self) -> Result<mpsc::Receiver<Vec<u8>>> {
+    /// Returns a receiver for audio chunks (PCM16 little-endian) and the device they
+    /// come from; captured and dropped chunks are counted in `counters`
+    pub fn start(
+        &mut self,
+        counters: Arc<AudioCounters>,
+    ) -> Result<(mpsc::Receiver<Vec<u8>>, CaptureInfo)> {
         if self.is_running.load(Ordering::Acquire) {
             return Err(anyhow!("Audio capture already running"));
         }
 
-        let host = cpal::default_host();
-        let device = host
-            .default_input_device()
-            .ok_or_else(|| anyhow!("No input microphone detected"))?;
-
-        let config = device
-            .default_input_config()
-            .context("Failed to query default input configuration")?;
-
+        let (device, config, info) = default_input()?;
         let sample_format = config.sample_format();
         let config: cpal::StreamConfig = config.into();
-
-        self.sample_rate = config.sample_rate.0;
         let channels = config.channels as usize;
 
         tracing::info!(
-            "[AudioStream] Starting continuous capture: {} Hz, {} channels (-> mono), format: {:?}",
-            self.sample_rate,
+            "[AudioStream] Starting continuous capture on {}: {} Hz, {} channels (-> mono), format: {:?}",
+            info.device,
+            info.sample_rate,
             channels,
             sample_format
         );
@@ -63,15 +171,14 @@ impl ContinuousAudioCapture {
         // Use bounded channel to prevent memory exhaustion if receiver can't keep up
         let (tx, rx) = mpsc::channel(MAX_AUDIO_BUFFER_SIZE);
         self.audio_tx = Some(tx.clone());
-        let chunk_size_ms = 100; // 100ms chunks
-                                 // Output is mono regardless of input channels, so samples_per_chunk is for 1 channel
-        let samples_per_chunk = self.sample_rate as usize * chunk_size_ms / 1000;
+        // Output is mono regardless of input channels, so samples_per_chunk is for 1 channel
+        let samples_per_chunk = info.sample_rate as usize * CHUNK_MS as usize / 1000;
 
         let stream = build_streaming_input(
             &device,
             &config,
             sample_format,
-            tx,
+            ChunkSender { tx, counters },
             channels,
             samples_per_chunk,
         )?;
@@ -81,7 +188,7 @@ impl ContinuousAudioCapture {
         self.is_running.store(true, Ordering::Release);
 
         tracing::info!("[AudioStream] Continuous capture started");
-        Ok(rx)
+        Ok((rx, info))
     }
 
     /// Stops continuous audio capture
@@ -102,10 +209,6 @@ impl ContinuousAudioCapture {
         tracing::info!("[AudioStream] Continuous capture stopped");
         Ok(())
     }
-
-    pub fn sample_rate(&self) -> u32 {
-        self.sample_rate
-    }
 }
 
 impl Drop for ContinuousAudioCapture {
@@ -114,12 +217,18 @@ impl Drop for ContinuousAudioCapture {
     }
 }
 
+/// The capture end of the chunk channel
+struct ChunkSender {
+    tx: mpsc::Sender<Vec<u8>>,
+    counters: Arc<AudioCounters>,
+}
+
 /// Builds CPAL input stream that sends PCM16 chunks via channel
 fn build_streaming_input(
     device: &cpal::Device,
     config: &cpal::StreamConfig,
     sample_format: SampleFormat,
-    tx: mpsc::Sender<Vec<u8>>,
+    tx: ChunkSender,
     channels: usize,
     chunk_size: usize,
 ) -> Result<Stream> {
@@ -167,7 +276,7 @@ fn build_streaming_input(
 fn build_stream<T: Sample + SizedSample + Send + 'static>(
     device: &cpal::Device,
     config: &cpal::StreamConfig,
-    tx: mpsc::Sender<Vec<u8>>,
+    tx: ChunkSender,
     err_fn: impl Fn(cpal::StreamError) + Send + 'static,
     channels: usize,
     chunk_size: usize,
@@ -194,14 +303,16 @@ fn build_stream<T: Sample + SizedSample + Send + 'static>(
             while buffer.len() >= chunk_size * 2 {
                 // *2 because i16 = 2 bytes
                 let chunk: Vec<u8> = buffer.drain(..chunk_size * 2).collect();
+                tx.counters.captured();
 
                 // Try to send chunk - if buffer is full, drop oldest audio to prevent blocking
                 // Audio callback must not block or it will cause audio glitches
-                match tx.try_send(chunk) {
+                match tx.tx.try_send(chunk) {
                     Ok(()) => {}
                     Err(mpsc::error::TrySendError::Full(_)) => {
                         // Buffer full - this means receiver can't keep up
                         // Drop this chunk to prevent memory buildup and audio glitches
+                        tx.counters.dropped_buffer_full();
                         tracing::warn!("[AudioStream] Buffer full, dropping audio chunk");
                     }
                     Err(mpsc::error::TrySendError::Closed(_)) => {
@@ -223,3 +334,44 @@ fn convert_f32_to_i16(sample: f32) -> i16 {
     let clamped = sample.clamp(-1.0, 1.0);
     (clamped * i16::MAX as f32) as i16
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forced_capture_failure_applies_once() {
+        FAIL_NEXT_CAPTURE.store(true, Ordering::SeqCst);
+        assert!(ContinuousAudioCapture::new(Arc::default()).is_err());
+        assert!(ContinuousAudioCapture::new(Arc::default()).is_ok());
+    }
+
+    #[test]
+    fn test_counters_track_one_dictation() {
+        let counters = AudioCounters::default();
+        for _ in 0..10 {
+            counters.captured();
+        }
+        for n in 0..6 {
+            counters.sent(3200, n % 2 == 0);
+        }
+        counters.dropped_buffer_full();
+        counters.dropped_socket_behind();
+        counters.ignored_gate_closed();
+
+        let stats = counters.stats();
+        assert_eq!(stats.captured_chunks, 10);
+        assert_eq!(stats.voiced_chunks, 3);
+        assert_eq!(stats.bytes_sent, 6 * 3200);
+        assert_eq!(stats.lost_chunks(), 2);
+        assert!(!stats.incomplete());
+
+        for _ in 0..3 {
+            counters.dropped_buffer_full();
+        }
+        assert!(counters.stats().incomplete());
+
+        counters.reset();
+        assert_eq!(counters.stats(), AudioStats::default());
+    }
+}
@@ -1,25 +1,85 @@
-﻿use std::{
-    fs,
-    io::{Read, Write},
-    net::TcpListener,
+use std::{
+    fs::{self, File},
+    io::{BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    net::{TcpListener, TcpStream},
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex,
+    },
     thread,
+    time::{Duration, SystemTime},
 };
 
 use anyhow::{Context, Result};
+use notify::{EventKind, RecursiveMode, Watcher};
+use tokio_tungstenite::tungstenite::{
+    handshake::derive_accept_key, protocol::Role, Message, WebSocket,
+};
+
+const LIVERELOAD_PATH: &str = "/__livereload";
+/// Editors save in bursts (temp file, rename, chmod); one reload per burst
+const DEBOUNCE: Duration = Duration::from_millis(150);
+/// Idle live-reload sockets are pinged at this interval to notice closed tabs
+const PING_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Injected into served HTML pages when `--watch` is on
+const LIVERELOAD_SCRIPT: &str = r#"<script>
+(() => {
+    const socket = new WebSocket(`ws://${location.host}/__livereload`);
+    socket.onmessage = (event) => {
+        if (event.data === 'reload') location.reload();
+    };
+})();
+</script>
+"#;
+
+/// Open live-reload connections, one channel per client thread
+#[derive(Default)]
+struct LiveReload {
+    clients: Mutex<Vec<mpsc::Sender<()>>>,
+}
+
+impl LiveReload {
+    fn subscribe(&self) -> mpsc::Receiver<()> {
+        let (tx, rx) = mpsc::channel();
+        self.clients.lock().unwrap().push(tx);
+        rx
+    }
+
+    /// Tell every client to reload; clients whose thread has ended are dropped
+    fn broadcast(&self) {
+        let mut clients = self.clients.lock().unwrap();
+        clients.retain(|client| client.send(()).is_ok());
+        println!("[dev-server] Reloading {} client(s)", clients.len());
+    }
+}
 
 fn main() -> Result<()> {
+    let watch = std::env::args().skip(1).any(|arg| arg == "--watch");
     let root = resolve_root()?;
     let root = Arc::new(root);
+
+    let live_reload = if watch {
+        let live_reload = Arc::new(LiveReload::default());
+        watch_root(&root, Arc::clone(&live_reload))?;
+        Some(live_reload)
+    } else {
+        None
+    };
+
     let addr = "127.0.0.1:1420";
     let listener = TcpListener::bind(addr).context("Failed to bind dev-server address")?;
     println!("Dev server listening on http://{addr}");
+    if watch {
+        println!("Live reload enabled, watching {}", root.display());
+    }
 
     for stream in listener.incoming() {
         let root = Arc::clone(&root);
+        let live_reload = live_reload.clone();
         thread::spawn(move || {
-            if let Err(err) = handle_connection(stream, &root) {
+            if let Err(err) = handle_connection(stream, &root, live_reload.as_deref()) {
                 eprintln!("[dev-server] {err}");
             }
         });
@@ -28,6 +88,69 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+/// Watch the frontend directory and broadcast one reload per burst of changes
+fn watch_root(root: &Path, live_reload: Arc<LiveReload>) -> Result<()> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).context("Failed to create file watcher")?;
+    watcher
+        .watch(root, RecursiveMode::Recursive)
+        .with_context(|| format!("Failed to watch {root:?}"))?;
+
+    thread::spawn(move || {
+        // Owned by the thread so the watch lasts as long as the server
+        let _watcher = watcher;
+        let is_change = |event: &notify::Result<notify::Event>| match event {
+            Ok(event) => !matches!(event.kind, EventKind::Access(_)),
+            Err(err) => {
+                eprintln!("[dev-server] Watch error: {err}");
+                false
+            }
+        };
+
+        while let Ok(event) = rx.recv() {
+            if !is_change(&event) {
+                continue;
+            }
+            // Wait until the burst is over
+            loop {
+                match rx.recv_timeout(DEBOUNCE) {
+                    Ok(_) => continue,
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+            live_reload.broadcast();
+        }
+    });
+    Ok(())
+}
+
+/// Hold one live-reload WebSocket (handshake already answered) until a reload is
+/// due or the tab goes away
+fn serve_live_reload(stream: TcpStream, live_reload: &LiveReload) -> Result<()> {
+    let mut socket = WebSocket::from_raw_socket(stream, Role::Server, None);
+    let reloads = live_reload.subscribe();
+
+    loop {
+        match reloads.recv_timeout(PING_INTERVAL) {
+            Ok(()) => {
+                // The page reloads and opens a new connection
+                socket.send(Message::Text("reload".into()))?;
+                let _ = socket.close(None);
+                let _ = socket.flush();
+                return Ok(());
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                if socket.send(Message::Ping(Vec::new())).is_err() {
+                    // Closed tab; dropping `reloads` unsubscribes it
+                    return Ok(());
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+    }
+}
+
 fn resolve_root() -> Result<PathBuf> {
     let candidates = [
         PathBuf::from("frontend"),
@@ -49,72 +172,366 @@ fn resolve_root() -> Result<PathBuf> {
     );
 }
 
-fn handle_connection(stream: std::io::Result<std::net::TcpStream>, root: &Path) -> Result<()> {
-    let mut stream = stream?;
-    let mut buffer = [0_u8; 4096];
-    let read = stream.read(&mut buffer)?;
-    if read == 0 {
-        return Ok(());
+/// Largest request head (request line and headers) the server reads
+const MAX_HEAD_BYTES: usize = 64 * 1024;
+
+#[derive(Debug)]
+struct Request {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
     }
+}
 
-    let request = String::from_utf8_lossy(&buffer[..read]);
-    let line = request.lines().next().unwrap_or("");
-    let mut parts = line.split_whitespace();
-    let method = parts.next().unwrap_or("");
-    let path = parts.next().unwrap_or("/");
+fn parse_request(head: &str) -> Option<Request> {
+    // Blank lines before the request line are to be ignored
+    let mut lines = head.trim_start_matches(['\r', '\n']).lines();
+    let mut parts = lines.next()?.split_whitespace();
+    let method = parts.next()?.to_string();
+    let path = parts.next()?.to_string();
+    let headers = lines
+        .take_while(|line| !line.is_empty())
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect();
+    Some(Request {
+        method,
+        path,
+        headers,
+    })
+}
 
-    if method != "GET" && method != "HEAD" {
-        return respond(&mut stream, 405, "Method Not Allowed", b"", "text/plain");
+/// Read the request line and headers, however many packets they span.
+/// `None` if the client closed the connection without sending anything.
+fn read_request(reader: &mut impl BufRead) -> Result<Option<Request>> {
+    let mut head = String::new();
+    loop {
+        let remaining = (MAX_HEAD_BYTES + 1).saturating_sub(head.len()) as u64;
+        if remaining == 0 {
+            anyhow::bail!("Request head exceeds {MAX_HEAD_BYTES} bytes");
+        }
+        let read = (&mut *reader).take(remaining).read_line(&mut head)?;
+        if read == 0 {
+            if head.is_empty() {
+                return Ok(None);
+            }
+            anyhow::bail!("Connection closed in the middle of the request head");
+        }
+        if head.ends_with("\r\n\r\n") || head.ends_with("\n\n") {
+            break;
+        }
     }
+    parse_request(&head)
+        .map(Some)
+        .context("Malformed request line")
+}
 
-    let path = sanitize_path(path);
-    if path.contains("../") || path.contains("./") {
-        return respond(&mut stream, 400, "Bad Request", b"", "text/plain");
+#[derive(Debug)]
+struct Response {
+    status: u16,
+    reason: &'static str,
+    headers: Vec<(&'static str, String)>,
+    /// Announced length; differs from `body` for HEAD requests
+    content_length: u64,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn new(status: u16, reason: &'static str) -> Self {
+        Self {
+            status,
+            reason,
+            headers: Vec::new(),
+            content_length: 0,
+            body: Vec::new(),
+        }
     }
 
-    let file_path = if path == "/" {
-        root.join("index.html")
-    } else {
-        root.join(path.trim_start_matches('/'))
+    fn text(status: u16, reason: &'static str) -> Self {
+        Self::new(status, reason)
+            .header("Content-Type", "text/plain")
+            .body(reason.as_bytes().to_vec())
+    }
+
+    fn header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+
+    fn body(mut self, body: Vec<u8>) -> Self {
+        self.content_length = body.len() as u64;
+        self.body = body;
+        self
+    }
+
+    fn write_to(&self, out: &mut impl Write) -> std::io::Result<()> {
+        write!(out, "HTTP/1.1 {} {}\r\n", self.status, self.reason)?;
+        for (name, value) in &self.headers {
+            write!(out, "{name}: {value}\r\n")?;
+        }
+        // A 101 hands the connection over to the WebSocket
+        if self.status != 101 {
+            write!(
+                out,
+                "Content-Length: {}\r\nCache-Control: no-cache\r\nConnection: close\r\n",
+                self.content_length
+            )?;
+        }
+        out.write_all(b"\r\n")?;
+        out.write_all(&self.body)?;
+        out.flush()
+    }
+}
+
+/// What a `Range` header asks for, given the full length of the content
+#[derive(Debug, PartialEq, Eq)]
+enum RangeRequest {
+    /// Inclusive byte range
+    Bytes(u64, u64),
+    Unsatisfiable,
+    /// Malformed or multi-range: serve the whole content, as RFC 9110 allows
+    Ignored,
+}
+
+fn parse_range(header: &str, len: u64) -> RangeRequest {
+    let Some(spec) = header.trim().strip_prefix("bytes=") else {
+        return RangeRequest::Ignored;
+    };
+    if spec.contains(',') {
+        return RangeRequest::Ignored;
+    }
+    let Some((start, end)) = spec.split_once('-') else {
+        return RangeRequest::Ignored;
     };
+    let (start, end) = (start.trim(), end.trim());
 
-    if !file_path.exists() {
-        let body = b"Not Found";
-        return respond(&mut stream, 404, "Not Found", body, "text/plain");
+    if start.is_empty() {
+        // Suffix range: the last N bytes
+        return match end.parse::<u64>() {
+            Ok(0) => RangeRequest::Unsatisfiable,
+            Ok(_) if len == 0 => RangeRequest::Unsatisfiable,
+            Ok(suffix) => RangeRequest::Bytes(len.saturating_sub(suffix), len - 1),
+            Err(_) => RangeRequest::Ignored,
+        };
     }
 
+    let Ok(start) = start.parse::<u64>() else {
+        return RangeRequest::Ignored;
+    };
+    let end = if end.is_empty() {
+        u64::MAX
+    } else {
+        match end.parse::<u64>() {
+            Ok(end) if end >= start => end,
+            _ => return RangeRequest::Ignored,
+        }
+    };
+    if start >= len {
+        return RangeRequest::Unsatisfiable;
+    }
+    RangeRequest::Bytes(start, end.min(len - 1))
+}
+
+/// Strong validator from modification time and size; pages with the live-reload
+/// script injected get their own tag
+fn etag(metadata: &fs::Metadata, injected: bool) -> String {
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map_or(0, |since| since.as_nanos());
+    let suffix = if injected { "-lr" } else { "" };
+    format!("\"{modified:x}-{:x}{suffix}\"", metadata.len())
+}
+
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match.split(',').map(str::trim).any(|candidate| {
+        candidate == "*" || candidate.strip_prefix("W/").unwrap_or(candidate) == etag
+    })
+}
+
+fn read_file_range(path: &Path, start: u64, len: u64) -> std::io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(start))?;
+    let mut body = Vec::with_capacity(len as usize);
+    file.take(len).read_to_end(&mut body)?;
+    Ok(body)
+}
+
+/// Answer a static-file request from `root`. Files are only read for GET, and then
+/// only the requested range; unchanged files are answered with 304.
+fn build_response(request: &Request, root: &Path, live_reload: bool) -> Result<Response> {
+    let head_only = match request.method.as_str() {
+        "GET" => false,
+        "HEAD" => true,
+        _ => return Ok(Response::text(405, "Method Not Allowed").header("Allow", "GET, HEAD")),
+    };
+
+    let file_path = match resolve_path(root, &request.path) {
+        Resolved::File(path) => path,
+        Resolved::Rejected => return Ok(Response::text(400, "Bad Request")),
+        Resolved::Missing => return Ok(Response::text(404, "Not Found")),
+    };
+    let metadata = match fs::metadata(&file_path) {
+        Ok(metadata) if metadata.is_file() => metadata,
+        _ => return Ok(Response::text(404, "Not Found")),
+    };
+
     let mime = content_type(file_path.extension().and_then(|e| e.to_str()));
-    let body = if method == "HEAD" {
-        Vec::new()
+    let inject = live_reload && mime.starts_with("text/html");
+    let etag = etag(&metadata, inject);
+    if request
+        .header("If-None-Match")
+        .is_some_and(|value| etag_matches(value, &etag))
+    {
+        return Ok(Response::new(304, "Not Modified").header("ETag", etag));
+    }
+
+    // Injected pages are small and served from memory; everything else stays on disk
+    let injected = if inject {
+        let html = fs::read(&file_path).with_context(|| format!("Failed to read {file_path:?}"))?;
+        Some(inject_live_reload(&html))
     } else {
-        fs::read(&file_path).with_context(|| format!("Failed to read {file_path:?}"))?
+        None
+    };
+    let len = injected
+        .as_ref()
+        .map_or(metadata.len(), |body| body.len() as u64);
+
+    let range = request
+        .header("Range")
+        .map_or(RangeRequest::Ignored, |value| parse_range(value, len));
+    let (response, start, count) = match range {
+        RangeRequest::Unsatisfiable => {
+            return Ok(Response::text(416, "Range Not Satisfiable")
+                .header("Content-Range", format!("bytes */{len}")));
+        }
+        RangeRequest::Bytes(start, end) => (
+            Response::new(206, "Partial Content")
+                .header("Content-Range", format!("bytes {start}-{end}/{len}")),
+            start,
+            end - start + 1,
+        ),
+        RangeRequest::Ignored => (Response::new(200, "OK"), 0, len),
     };
+    let mut response = response
+        .header("Content-Type", mime)
+        .header("ETag", etag)
+        .header("Accept-Ranges", "bytes");
 
-    respond(&mut stream, 200, "OK", &body, mime)
+    if !head_only {
+        response.body = match injected {
+            Some(body) => body[start as usize..(start + count) as usize].to_vec(),
+            None => read_file_range(&file_path, start, count)
+                .with_context(|| format!("Failed to read {file_path:?}"))?,
+        };
+    }
+    response.content_length = count;
+    Ok(response)
 }
 
-fn respond(
-    stream: &mut std::net::TcpStream,
-    status: u16,
-    text: &str,
-    body: &[u8],
-    mime: &str,
+/// Longest decoded request path that is looked up on disk
+const MAX_PATH_BYTES: usize = 1024;
+
+/// Request path as a path relative to the frontend root, or `None` if it could name
+/// anything outside it. Both `/` and `\` separate segments, since Windows accepts either.
+fn relative_path(request_path: &str) -> Option<PathBuf> {
+    let decoded = sanitize_path(request_path);
+    if decoded.len() > MAX_PATH_BYTES || decoded.contains('\0') {
+        return None;
+    }
+    let rest = decoded.strip_prefix('/')?;
+    if rest.is_empty() {
+        return Some(PathBuf::from("index.html"));
+    }
+
+    let mut relative = PathBuf::new();
+    for segment in rest.split(['/', '\\']) {
+        // Empty segments come from `//` or `\\` (UNC) prefixes, `:` from drive letters
+        // and alternate data streams
+        if segment.is_empty() || segment == "." || segment == ".." || segment.contains(':') {
+            return None;
+        }
+        relative.push(segment);
+    }
+    Some(relative)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Resolved {
+    File(PathBuf),
+    /// Malformed, or resolves outside the root (e.g. through a symlink)
+    Rejected,
+    Missing,
+}
+
+/// Map a request path to a file under `root` (which must be canonical)
+fn resolve_path(root: &Path, request_path: &str) -> Resolved {
+    let Some(relative) = relative_path(request_path) else {
+        return Resolved::Rejected;
+    };
+    match root.join(relative).canonicalize() {
+        Ok(path) if path.starts_with(root) => Resolved::File(path),
+        Ok(_) => Resolved::Rejected,
+        Err(_) => Resolved::Missing,
+    }
+}
+
+/// 101 response completing the live-reload WebSocket handshake
+fn websocket_upgrade(request: &Request) -> Response {
+    match request.header("Sec-WebSocket-Key") {
+        Some(key) => Response::new(101, "Switching Protocols")
+            .header("Upgrade", "websocket")
+            .header("Connection", "Upgrade")
+            .header("Sec-WebSocket-Accept", derive_accept_key(key.as_bytes())),
+        None => Response::text(400, "Bad Request"),
+    }
+}
+
+fn handle_connection(
+    stream: std::io::Result<TcpStream>,
+    root: &Path,
+    live_reload: Option<&LiveReload>,
 ) -> Result<()> {
-    write!(
-        stream,
-        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
-        status,
-        text,
-        mime,
-        body.len()
-    )?;
-    if !body.is_empty() {
-        stream.write_all(body)?;
-    }
-    stream.flush()?;
+    let mut stream = stream?;
+    let Some(request) = read_request(&mut BufReader::new(&stream))? else {
+        return Ok(());
+    };
+
+    if let Some(live_reload) = live_reload {
+        if request.method == "GET" && sanitize_path(&request.path) == LIVERELOAD_PATH {
+            let response = websocket_upgrade(&request);
+            response.write_to(&mut stream)?;
+            if response.status == 101 {
+                return serve_live_reload(stream, live_reload);
+            }
+            return Ok(());
+        }
+    }
+
+    build_response(&request, root, live_reload.is_some())?.write_to(&mut stream)?;
     Ok(())
 }
 
+/// Insert the live-reload client before `</body>`, or append it to the page
+fn inject_live_reload(html: &[u8]) -> Vec<u8> {
+    let html = String::from_utf8_lossy(html);
+    let at = html.rfind("</body>").unwrap_or(html.len());
+    let mut out = String::with_capacity(html.len() + LIVERELOAD_SCRIPT.len());
+    out.push_str(&html[..at]);
+    out.push_str(LIVERELOAD_SCRIPT);
+    out.push_str(&html[at..]);
+    out.into_bytes()
+}
+
 fn sanitize_path(path: &str) -> String {
     let without_query = path.split('?').next().unwrap_or("");
     percent_encoding::percent_decode_str(without_query)
@@ -138,3 +555,260 @@ fn content_type(ext: Option<&str>) -> &'static str {
         _ => "application/octet-stream",
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn request(method: &str, path: &str, headers: &[(&str, &str)]) -> Request {
+        Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            headers: headers
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+
+    /// Frontend directory with a 100-byte media file and a page
+    fn site(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("dev-server-{name}-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("media.bin"), (0..100).collect::<Vec<u8>>()).unwrap();
+        fs::write(root.join("index.html"), "<html><body>hi</body></html>").unwrap();
+        // Like `resolve_root`; the temp dir itself may sit behind a symlink
+        root.canonicalize().unwrap()
+    }
+
+    fn header<'a>(response: &'a Response, name: &str) -> Option<&'a str> {
+        response
+            .headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    #[test]
+    fn test_read_request_spanning_large_headers() {
+        let cookie = "x".repeat(6000);
+        let raw = format!(
+            "GET /app.js?v=2 HTTP/1.1\r\nHost: localhost\r\nCookie: {cookie}\r\nrange: bytes=0-9\r\n\r\nleftover"
+        );
+        let request = read_request(&mut Cursor::new(raw)).unwrap().unwrap();
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.path, "/app.js?v=2");
+        assert_eq!(request.header("Cookie").unwrap().len(), 6000);
+        assert_eq!(request.header("Range"), Some("bytes=0-9"));
+
+        assert!(read_request(&mut Cursor::new("")).unwrap().is_none());
+        assert!(read_request(&mut Cursor::new("GET / HTTP/1.1\r\nHost")).is_err());
+        let huge = format!(
+            "GET / HTTP/1.1\r\nX: {}\r\n\r\n",
+            "y".repeat(MAX_HEAD_BYTES)
+        );
+        assert!(read_request(&mut Cursor::new(huge)).is_err());
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("bytes=0-9", 100), RangeRequest::Bytes(0, 9));
+        assert_eq!(parse_range("bytes=90-", 100), RangeRequest::Bytes(90, 99));
+        assert_eq!(
+            parse_range("bytes=90-500", 100),
+            RangeRequest::Bytes(90, 99)
+        );
+        assert_eq!(parse_range("bytes=-10", 100), RangeRequest::Bytes(90, 99));
+        assert_eq!(parse_range("bytes=-500", 100), RangeRequest::Bytes(0, 99));
+        assert_eq!(parse_range("bytes=100-", 100), RangeRequest::Unsatisfiable);
+        assert_eq!(parse_range("bytes=-0", 100), RangeRequest::Unsatisfiable);
+        assert_eq!(parse_range("bytes=0-", 0), RangeRequest::Unsatisfiable);
+        assert_eq!(parse_range("bytes=9-0", 100), RangeRequest::Ignored);
+        assert_eq!(parse_range("bytes=0-1,5-6", 100), RangeRequest::Ignored);
+        assert_eq!(parse_range("items=0-1", 100), RangeRequest::Ignored);
+    }
+
+    #[test]
+    fn test_head_reports_real_length() {
+        let root = site("head");
+        let response = build_response(&request("HEAD", "/media.bin", &[]), &root, false).unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.content_length, 100);
+        assert!(response.body.is_empty());
+
+        let mut out = Vec::new();
+        response.write_to(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(out.contains("Content-Length: 100\r\n"));
+        assert!(out.ends_with("\r\n\r\n"));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_range_responses() {
+        let root = site("range");
+        let partial = build_response(
+            &request("GET", "/media.bin", &[("Range", "bytes=10-19")]),
+            &root,
+            false,
+        )
+        .unwrap();
+        assert_eq!(partial.status, 206);
+        assert_eq!(partial.body, (10..20).collect::<Vec<u8>>());
+        assert_eq!(partial.content_length, 10);
+        assert_eq!(header(&partial, "Content-Range"), Some("bytes 10-19/100"));
+
+        let unsatisfiable = build_response(
+            &request("GET", "/media.bin", &[("Range", "bytes=200-")]),
+            &root,
+            false,
+        )
+        .unwrap();
+        assert_eq!(unsatisfiable.status, 416);
+        assert_eq!(header(&unsatisfiable, "Content-Range"), Some("bytes */100"));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_etag_revalidation() {
+        let root = site("etag");
+        let full = build_response(&request("GET", "/media.bin", &[]), &root, false).unwrap();
+        assert_eq!(full.status, 200);
+        assert_eq!(full.body.len(), 100);
+        let etag = header(&full, "ETag").unwrap().to_string();
+
+        let cached = build_response(
+            &request(
+                "GET",
+                "/media.bin",
+                &[("If-None-Match", &format!("W/{etag}"))],
+            ),
+            &root,
+            false,
+        )
+        .unwrap();
+        assert_eq!(cached.status, 304);
+        assert!(cached.body.is_empty());
+
+        let stale = build_response(
+            &request("GET", "/media.bin", &[("If-None-Match", "\"0-0\"")]),
+            &root,
+            false,
+        )
+        .unwrap();
+        assert_eq!(stale.status, 200);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_live_reload_injection() {
+        let root = site("inject");
+        let plain = build_response(&request("GET", "/", &[]), &root, false).unwrap();
+        let injected = build_response(&request("GET", "/", &[]), &root, true).unwrap();
+        let html = String::from_utf8(injected.body.clone()).unwrap();
+        assert!(html.contains("/__livereload"));
+        assert!(html.ends_with("</body></html>"));
+        assert_eq!(injected.content_length, injected.body.len() as u64);
+        assert_ne!(header(&plain, "ETag"), header(&injected, "ETag"));
+
+        assert_eq!(
+            build_response(&request("POST", "/", &[]), &root, false)
+                .unwrap()
+                .status,
+            405
+        );
+        assert_eq!(
+            build_response(&request("GET", "/missing.js", &[]), &root, false)
+                .unwrap()
+                .status,
+            404
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_relative_path_accepts_plain_paths() {
+        assert_eq!(relative_path("/"), Some(PathBuf::from("index.html")));
+        assert_eq!(
+            relative_path("/main.js?v=3"),
+            Some(PathBuf::from("main.js"))
+        );
+        assert_eq!(
+            relative_path("/assets/icon%20small.png"),
+            Some(["assets", "icon small.png"].iter().collect())
+        );
+        // Dots inside a name are fine
+        assert_eq!(
+            relative_path("/vendor/lib..min.js"),
+            Some(["vendor", "lib..min.js"].iter().collect())
+        );
+    }
+
+    #[test]
+    fn test_relative_path_rejects_malicious_paths() {
+        let long = format!("/{}", "a".repeat(MAX_PATH_BYTES));
+        let malicious = [
+            "/../secret",
+            "/assets/../../secret",
+            "/%2e%2e/secret",
+            "/%2E%2E%2Fsecret",
+            "/..%2fsecret",
+            "/..%5csecret",
+            "/..\\secret",
+            "/assets\\..\\..\\secret",
+            "/.%2e/secret",
+            "/./index.html",
+            "//etc/passwd",
+            "/%2fetc/passwd",
+            "/C:/Windows/win.ini",
+            "/c:%5cWindows%5cwin.ini",
+            "/\\\\server\\share\\file",
+            "/%5c%5cserver%5cshare",
+            "/index.html%00.png",
+            "/index.html::$DATA",
+            "relative.html",
+            "",
+            long.as_str(),
+        ];
+        for path in malicious {
+            assert_eq!(relative_path(path), None, "{path:?} was accepted");
+        }
+    }
+
+    #[test]
+    fn test_resolve_path() {
+        let root = site("resolve");
+        assert_eq!(
+            resolve_path(&root, "/media.bin"),
+            Resolved::File(root.join("media.bin"))
+        );
+        assert_eq!(resolve_path(&root, "/missing.js"), Resolved::Missing);
+        assert_eq!(resolve_path(&root, "/%2e%2e/secret"), Resolved::Rejected);
+        assert_eq!(
+            build_response(&request("GET", "/..%5c..%5csecret", &[]), &root, false)
+                .unwrap()
+                .status,
+            400
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_path_rejects_symlink_out_of_root() {
+        let root = site("symlink");
+        let outside = root
+            .parent()
+            .unwrap()
+            .join(format!("dev-server-outside-{}", std::process::id()));
+        fs::write(&outside, "secret").unwrap();
+        std::os::unix::fs::symlink(&outside, root.join("leak.txt")).unwrap();
+
+        assert_eq!(resolve_path(&root, "/leak.txt"), Resolved::Rejected);
+
+        fs::remove_file(outside).unwrap();
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,259 @@
+/// Rough token estimate for chat models (~4 chars per token across common languages)
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+fn char_len(text: &str) -> usize {
+    text.chars().count()
+}
+
+/// Split text into paragraphs on blank lines
+fn paragraphs(text: &str) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            if !current.is_empty() {
+                result.push(current.join("\n"));
+                current.clear();
+            }
+        } else {
+            current.push(line.trim_end());
+        }
+    }
+    if !current.is_empty() {
+        result.push(current.join("\n"));
+    }
+    result
+}
+
+/// Split a paragraph after sentence-ending punctuation followed by whitespace
+fn sentences(paragraph: &str) -> Vec<&str> {
+    let mut result = Vec::new();
+    let mut start = 0;
+    let mut chars = paragraph.char_indices().peekable();
+    while let Some((idx, ch)) = chars.next() {
+        if !matches!(ch, '.' | '!' | '?' | '…') {
+            continue;
+        }
+        let end = idx + ch.len_utf8();
+        if chars
+            .peek()
+            .map(|(_, next)| next.is_whitespace())
+            .unwrap_or(true)
+        {
+            let sentence = paragraph[start..end].trim();
+            if !sentence.is_empty() {
+                result.push(sentence);
+            }
+            start = end;
+        }
+    }
+    let rest = paragraph[start..].trim();
+    if !rest.is_empty() {
+        result.push(rest);
+    }
+    result
+}
+
+/// Last resort for a single sentence over budget: break on words, then on chars
+fn hard_split(sentence: &str, max_chars: usize) -> Vec<String> {
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    for word in sentence.split_whitespace() {
+        let word_len = char_len(word);
+        if word_len > max_chars {
+            if !current.is_empty() {
+                pieces.push(std::mem::take(&mut current));
+            }
+            let chars: Vec<char> = word.chars().collect();
+            pieces.extend(
+                chars
+                    .chunks(max_chars)
+                    .map(|c| c.iter().collect::<String>()),
+            );
+            continue;
+        }
+        if current.is_empty() {
+            current.push_str(word);
+        } else if char_len(&current) + 1 + word_len <= max_chars {
+            current.push(' ');
+            current.push_str(word);
+        } else {
+            pieces.push(std::mem::replace(&mut current, word.to_string()));
+        }
+    }
+    if !current.is_empty() {
+        pieces.push(current);
+    }
+    pieces
+}
+
+/// A piece of a split transcript
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    pub text: String,
+    /// What stood between this chunk and the previous one: "\n\n" at a paragraph
+    /// break, " " inside a paragraph; empty for the first chunk
+    pub separator: &'static str,
+}
+
+/// Split a transcript into chunks of at most `max_chars` characters for refinement.
+/// Paragraphs are kept together where possible, then sentences; words are only
+/// broken up when a single sentence exceeds the budget. `max_chars == 0` disables splitting.
+pub fn split_into_chunks(text: &str, max_chars: usize) -> Vec<Chunk> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Vec::new();
+    }
+    let mut current = Chunk {
+        text: String::new(),
+        separator: "",
+    };
+    if max_chars == 0 || char_len(text) <= max_chars {
+        current.text = text.to_string();
+        return vec![current];
+    }
+
+    let mut chunks = Vec::new();
+
+    for paragraph in paragraphs(text) {
+        let pieces: Vec<String> = if char_len(&paragraph) <= max_chars {
+            vec![paragraph]
+        } else {
+            sentences(&paragraph)
+                .into_iter()
+                .flat_map(|sentence| {
+                    if char_len(sentence) <= max_chars {
+                        vec![sentence.to_string()]
+                    } else {
+                        hard_split(sentence, max_chars)
+                    }
+                })
+                .collect()
+        };
+
+        for (i, piece) in pieces.into_iter().enumerate() {
+            let separator = if i == 0 { "\n\n" } else { " " };
+            if current.text.is_empty() {
+                current.text = piece;
+            } else if char_len(&current.text) + separator.len() + char_len(&piece) <= max_chars {
+                current.text.push_str(separator);
+                current.text.push_str(&piece);
+            } else {
+                let next = Chunk {
+                    text: piece,
+                    separator,
+                };
+                chunks.push(std::mem::replace(&mut current, next));
+            }
+        }
+    }
+
+    if !current.text.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// Put (refined) chunks back together with the separators they were split at
+pub fn join_chunks<'a>(parts: impl IntoIterator<Item = (&'static str, &'a str)>) -> String {
+    parts
+        .into_iter()
+        .fold(String::new(), |mut text, (separator, part)| {
+            if !text.is_empty() {
+                text.push_str(separator);
+            }
+            text.push_str(part);
+            text
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(chunks: &[Chunk]) -> Vec<&str> {
+        chunks.iter().map(|chunk| chunk.text.as_str()).collect()
+    }
+
+    fn rejoin(chunks: &[Chunk]) -> String {
+        join_chunks(
+            chunks
+                .iter()
+                .map(|chunk| (chunk.separator, chunk.text.as_str())),
+        )
+    }
+
+    #[test]
+    fn test_short_text_is_single_chunk() {
+        assert_eq!(
+            texts(&split_into_chunks("  Hello there.  ", 100)),
+            vec!["Hello there."]
+        );
+        assert!(split_into_chunks("   ", 100).is_empty());
+    }
+
+    #[test]
+    fn test_zero_budget_disables_splitting() {
+        let text = "One. Two. Three.";
+        assert_eq!(texts(&split_into_chunks(text, 0)), vec![text]);
+    }
+
+    #[test]
+    fn test_splits_on_sentence_boundaries() {
+        let text = "First sentence here. Second sentence here! Third one? Fourth.";
+        let chunks = split_into_chunks(text, 45);
+        assert_eq!(
+            texts(&chunks),
+            vec![
+                "First sentence here. Second sentence here!",
+                "Third one? Fourth."
+            ]
+        );
+        // One paragraph stays one paragraph
+        assert_eq!(chunks[1].separator, " ");
+        assert_eq!(rejoin(&chunks), text);
+    }
+
+    #[test]
+    fn test_keeps_paragraphs_together_when_they_fit() {
+        let text = "Para one.\n\nPara two.\n\nPara three is longer.";
+        let chunks = split_into_chunks(text, 25);
+        assert_eq!(
+            texts(&chunks),
+            vec!["Para one.\n\nPara two.", "Para three is longer."]
+        );
+        assert_eq!(chunks[1].separator, "\n\n");
+        assert_eq!(rejoin(&chunks), text);
+    }
+
+    #[test]
+    fn test_respects_budget_and_preserves_words() {
+        let text =
+            "Это очень длинное предложение без точек которое нужно разбить на части ".repeat(10);
+        let chunks = split_into_chunks(&text, 50);
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|c| c.text.chars().count() <= 50));
+        let rejoined: Vec<&str> = chunks
+            .iter()
+            .flat_map(|c| c.text.split_whitespace())
+            .collect();
+        let original: Vec<&str> = text.split_whitespace().collect();
+        assert_eq!(rejoined, original);
+    }
+
+    #[test]
+    fn test_decimal_points_do_not_split_sentences() {
+        assert_eq!(
+            sentences("Version 2.5 is out. Update now."),
+            vec!["Version 2.5 is out.", "Update now."]
+        );
+    }
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcde"), 2);
+    }
+}
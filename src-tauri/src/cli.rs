@@ -0,0 +1,322 @@
+use std::{path::PathBuf, sync::Arc};
+
+use anyhow::anyhow;
+
+use crate::{
+    audio_file::{self, AudioFileError},
+    core::{
+        events::ErrorCode,
+        timings::Timings,
+        transcription::{prepare_for_delivery, TranscriptionService},
+    },
+    input::KeyboardController,
+    settings::{SettingsStore, TranscriptionProvider},
+};
+
+const USAGE: &str = "\
+Usage:
+  easy-dictate [--minimized]
+  easy-dictate --transcribe <FILE> [--output <FILE>] [--provider <NAME>] [--no-llm]
+
+Options:
+  --transcribe <FILE>  Transcribe an audio file (wav, mp3, m4a) and exit
+  -o, --output <FILE>  Write the text to FILE instead of stdout
+  --provider <NAME>    Use another transcription provider: openai, groq, elevenlabs
+  --no-llm             Skip LLM refinement (translation, instructions, vocabulary)
+  --minimized          Start hidden in the tray (also --autostart)
+  -h, --help           Show this help
+
+Exit codes:
+  0  Success
+  1  Other error
+  2  Invalid arguments
+  3  The audio file could not be read or decoded
+  4  API key missing or rejected
+  5  The provider failed or could not be reached";
+
+const EXIT_OK: i32 = 0;
+const EXIT_FAILURE: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_INPUT: i32 = 3;
+const EXIT_AUTH: i32 = 4;
+const EXIT_PROVIDER: i32 = 5;
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Gui { start_minimized: bool },
+    Transcribe(TranscribeArgs),
+    Help,
+}
+
+#[derive(Debug, PartialEq)]
+struct TranscribeArgs {
+    input: PathBuf,
+    output: Option<PathBuf>,
+    provider: Option<TranscriptionProvider>,
+    no_llm: bool,
+}
+
+#[derive(Debug, thiserror::Error)]
+enum CliError {
+    #[error("{0}")]
+    Input(#[from] AudioFileError),
+
+    #[error("{0:#}")]
+    Transcription(anyhow::Error),
+
+    #[error("{0:#}")]
+    Other(#[from] anyhow::Error),
+}
+
+impl CliError {
+    fn exit_code(&self) -> i32 {
+        match self {
+            Self::Input(_) => EXIT_INPUT,
+            Self::Transcription(err) => match ErrorCode::classify(err) {
+                ErrorCode::MissingApiKey | ErrorCode::SettingsLocked | ErrorCode::AuthFailed => {
+                    EXIT_AUTH
+                }
+                ErrorCode::Network
+                | ErrorCode::RateLimited
+                | ErrorCode::QuotaExceeded
+                | ErrorCode::ProviderError => EXIT_PROVIDER,
+                _ => EXIT_FAILURE,
+            },
+            Self::Other(_) => EXIT_FAILURE,
+        }
+    }
+}
+
+fn value(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, String> {
+    args.next()
+        .filter(|value| !value.starts_with("--"))
+        .ok_or_else(|| format!("{flag} needs a value"))
+}
+
+fn parse_provider(name: &str) -> Result<TranscriptionProvider, String> {
+    TranscriptionProvider::ALL
+        .into_iter()
+        .find(|provider| provider.id().eq_ignore_ascii_case(name))
+        .ok_or_else(|| format!("Unknown provider: {name}"))
+}
+
+fn parse(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut args = args.into_iter();
+    let mut start_minimized = false;
+    let mut input = None;
+    let mut output = None;
+    let mut provider = None;
+    let mut no_llm = false;
+    let mut unknown = Vec::new();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--autostart" | "--minimized" => start_minimized = true,
+            "-h" | "--help" => return Ok(Command::Help),
+            "--transcribe" => input = Some(PathBuf::from(value(&mut args, &arg)?)),
+            "-o" | "--output" => output = Some(PathBuf::from(value(&mut args, &arg)?)),
+            "--provider" => provider = Some(parse_provider(&value(&mut args, &arg)?)?),
+            "--no-llm" => no_llm = true,
+            _ => unknown.push(arg),
+        }
+    }
+
+    let Some(input) = input else {
+        if output.is_some() || provider.is_some() || no_llm {
+            return Err("--output, --provider and --no-llm require --transcribe".to_string());
+        }
+        // Arguments added by the OS or a launcher are none of the GUI's business
+        return Ok(Command::Gui { start_minimized });
+    };
+    if let Some(arg) = unknown.first() {
+        return Err(format!("Unknown argument: {arg}"));
+    }
+    Ok(Command::Transcribe(TranscribeArgs {
+        input,
+        output,
+        provider,
+        no_llm,
+    }))
+}
+
+/// Release builds use the Windows GUI subsystem and start without a console;
+/// borrow the one of the shell that started us so stdout/stderr are visible
+#[cfg(windows)]
+fn attach_console() {
+    use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+    // Fails harmlessly when started from Explorer or when a console is attached
+    let _ = unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
+
+/// Handle the command line before the Tauri app is built. Modes other than the GUI
+/// run to completion and exit the process; for the GUI returns whether to start minimized.
+pub fn start_minimized_or_exit(identifier: &str) -> bool {
+    let command = parse(std::env::args().skip(1));
+    if !matches!(command, Ok(Command::Gui { .. })) {
+        attach_console();
+    }
+
+    let code = match command {
+        Ok(Command::Gui { start_minimized }) => return start_minimized,
+        Ok(Command::Help) => {
+            println!("{USAGE}");
+            EXIT_OK
+        }
+        Ok(Command::Transcribe(args)) => transcribe(&args, identifier),
+        Err(message) => {
+            eprintln!("{message}\n\n{USAGE}");
+            EXIT_USAGE
+        }
+    };
+    std::process::exit(code);
+}
+
+fn transcribe(args: &TranscribeArgs, identifier: &str) -> i32 {
+    let text = match tauri::async_runtime::block_on(run_transcription(args, identifier)) {
+        Ok(text) => text,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            return err.exit_code();
+        }
+    };
+
+    match &args.output {
+        Some(path) => {
+            if let Err(e) = std::fs::write(path, format!("{text}\n")) {
+                eprintln!("Error: Failed to write {}: {e}", path.display());
+                return EXIT_FAILURE;
+            }
+        }
+        None => println!("{text}"),
+    }
+    EXIT_OK
+}
+
+/// Same transcription and refinement as a dictation, with the saved settings
+/// (and their API keys), but no window, tray, clipboard or history
+async fn run_transcription(args: &TranscribeArgs, identifier: &str) -> Result<String, CliError> {
+    // Where Tauri's `app_config_dir` points for this identifier
+    let config_dir = dirs::config_dir()
+        .ok_or_else(|| anyhow!("Failed to locate the config directory"))?
+        .join(identifier);
+    let mut settings = SettingsStore::new(config_dir).load().await?;
+    if let Some(provider) = args.provider {
+        settings.switch_provider(provider);
+    }
+    if args.no_llm {
+        settings.auto_translate = false;
+        settings.use_custom_instructions = false;
+        settings.use_vocabulary = false;
+    }
+
+    let input = args.input.clone();
+    let audio_wav = tokio::task::spawn_blocking(move || audio_file::decode_to_wav(&input))
+        .await
+        .map_err(|e| anyhow!("Decoding task failed: {e}"))??;
+
+    let service = TranscriptionService::with_default_clients(Arc::new(KeyboardController::new()?))?;
+    let result = service
+        .perform(
+            &settings,
+            audio_wav,
+            &[],
+            &mut Timings::start(),
+            &|index, total| eprintln!("Refining part {index}/{total}..."),
+        )
+        .await
+        .map_err(CliError::Transcription)?;
+    Ok(prepare_for_delivery(&settings, result.processed.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::speech_to_text::{HttpStatusError, ProviderError};
+
+    fn parse_args(args: &[&str]) -> Result<Command, String> {
+        parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_gui_mode() {
+        assert_eq!(
+            parse_args(&[]),
+            Ok(Command::Gui {
+                start_minimized: false
+            })
+        );
+        // Launchers may add arguments of their own
+        assert_eq!(
+            parse_args(&["--autostart", "-psn_0_12345"]),
+            Ok(Command::Gui {
+                start_minimized: true
+            })
+        );
+        assert_eq!(parse_args(&["--minimized", "--help"]), Ok(Command::Help));
+    }
+
+    #[test]
+    fn test_transcribe_mode() {
+        assert_eq!(
+            parse_args(&[
+                "--transcribe",
+                "meeting.wav",
+                "--output",
+                "out.txt",
+                "--provider",
+                "Groq",
+                "--no-llm",
+            ]),
+            Ok(Command::Transcribe(TranscribeArgs {
+                input: PathBuf::from("meeting.wav"),
+                output: Some(PathBuf::from("out.txt")),
+                provider: Some(TranscriptionProvider::Groq),
+                no_llm: true,
+            }))
+        );
+        assert_eq!(
+            parse_args(&["--transcribe", "memo.m4a"]),
+            Ok(Command::Transcribe(TranscribeArgs {
+                input: PathBuf::from("memo.m4a"),
+                output: None,
+                provider: None,
+                no_llm: false,
+            }))
+        );
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        assert!(parse_args(&["--transcribe"]).is_err());
+        assert!(parse_args(&["--transcribe", "--no-llm"]).is_err());
+        assert!(parse_args(&["--transcribe", "a.wav", "--provider", "whisper"]).is_err());
+        assert!(parse_args(&["--transcribe", "a.wav", "--verbose"]).is_err());
+        assert!(parse_args(&["--no-llm"]).is_err());
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        assert_eq!(
+            CliError::from(AudioFileError::Empty).exit_code(),
+            EXIT_INPUT
+        );
+        assert_eq!(
+            CliError::Transcription(ProviderError::MissingApiKey("Groq").into_anyhow()).exit_code(),
+            EXIT_AUTH
+        );
+        assert_eq!(
+            CliError::Transcription(
+                HttpStatusError::new("OpenAI", reqwest::StatusCode::SERVICE_UNAVAILABLE).into()
+            )
+            .exit_code(),
+            EXIT_PROVIDER
+        );
+        assert_eq!(
+            CliError::Other(anyhow!("Failed to parse settings")).exit_code(),
+            EXIT_FAILURE
+        );
+    }
+}
@@ -0,0 +1,156 @@
+use serde::Serialize;
+
+use crate::settings::AppRule;
+
+/// The process owning the foreground window
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ForegroundApp {
+    /// Full path of the executable
+    pub path: String,
+    /// Executable file name, e.g. "slack.exe"
+    pub name: String,
+}
+
+impl ForegroundApp {
+    pub fn from_path(path: impl Into<String>) -> Self {
+        let path = path.into();
+        let name = path
+            .rsplit(['\\', '/'])
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        Self { path, name }
+    }
+
+    /// How closely `process` names this app: 3 for the full path, 2 for the file
+    /// name, 1 for the name without extension, 0 for no match. Case-insensitive,
+    /// like Windows paths.
+    fn specificity(&self, process: &str) -> u8 {
+        let stem = match self.name.rfind('.') {
+            Some(dot) if dot > 0 => &self.name[..dot],
+            _ => &self.name,
+        };
+        if process.eq_ignore_ascii_case(&self.path) {
+            3
+        } else if process.eq_ignore_ascii_case(&self.name) {
+            2
+        } else if process.eq_ignore_ascii_case(stem) {
+            1
+        } else {
+            0
+        }
+    }
+}
+
+/// The rule for `app`: the most specific match, the first of equally specific ones
+pub fn matching_rule<'a>(rules: &'a [AppRule], app: &ForegroundApp) -> Option<&'a AppRule> {
+    let mut best: Option<(u8, &AppRule)> = None;
+    for rule in rules {
+        let score = app.specificity(&rule.process);
+        if score > best.map_or(0, |(best_score, _)| best_score) {
+            best = Some((score, rule));
+        }
+    }
+    best.map(|(_, rule)| rule)
+}
+
+/// The foreground application; None if it can't be determined and outside Windows
+#[cfg(windows)]
+pub fn foreground_app() -> Option<ForegroundApp> {
+    win32::foreground_image_path().map(ForegroundApp::from_path)
+}
+
+#[cfg(not(windows))]
+pub fn foreground_app() -> Option<ForegroundApp> {
+    None
+}
+
+#[cfg(windows)]
+mod win32 {
+    use windows::{
+        core::PWSTR,
+        Win32::{
+            Foundation::CloseHandle,
+            System::Threading::{
+                OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32,
+                PROCESS_QUERY_LIMITED_INFORMATION,
+            },
+            UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId},
+        },
+    };
+
+    /// Executable path of the process owning the foreground window
+    pub fn foreground_image_path() -> Option<String> {
+        // SAFETY: plain queries on a window handle that may be null or stale; both
+        // calls fail gracefully in that case
+        let pid = unsafe {
+            let hwnd = GetForegroundWindow();
+            if hwnd.is_invalid() {
+                return None;
+            }
+            let mut pid = 0u32;
+            GetWindowThreadProcessId(hwnd, Some(&mut pid));
+            pid
+        };
+        if pid == 0 {
+            return None;
+        }
+
+        // SAFETY: the handle is only used after a successful open and closed exactly once
+        let process = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) }.ok()?;
+        let mut buffer = [0u16; 1024];
+        let mut len = buffer.len() as u32;
+        // SAFETY: `buffer` holds `len` UTF-16 units; on success `len` is the length written
+        let result = unsafe {
+            QueryFullProcessImageNameW(
+                process,
+                PROCESS_NAME_WIN32,
+                PWSTR(buffer.as_mut_ptr()),
+                &mut len,
+            )
+        };
+        unsafe {
+            let _ = CloseHandle(process);
+        }
+        result.ok()?;
+        Some(String::from_utf16_lossy(&buffer[..len as usize]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(process: &str) -> AppRule {
+        AppRule {
+            process: process.to_string(),
+            ..AppRule::default()
+        }
+    }
+
+    #[test]
+    fn test_matching_ignores_case() {
+        let app = ForegroundApp::from_path(r"C:\Program Files\Slack\slack.exe");
+        assert_eq!(app.name, "slack.exe");
+        let rules = [rule("Outlook.exe"), rule("SLACK.EXE")];
+        assert_eq!(matching_rule(&rules, &app), Some(&rules[1]));
+        assert_eq!(matching_rule(&[rule("Slack")], &app), Some(&rule("Slack")));
+        assert_eq!(matching_rule(&[rule("slac")], &app), None);
+    }
+
+    #[test]
+    fn test_most_specific_rule_wins() {
+        let app = ForegroundApp::from_path(r"C:\Users\me\AppData\Local\Programs\Code\Code.exe");
+        let rules = [
+            rule("code"),
+            rule("code.exe"),
+            rule(r"c:\users\me\appdata\local\programs\code\code.exe"),
+            rule("Code.exe"),
+        ];
+        assert_eq!(matching_rule(&rules, &app), Some(&rules[2]));
+        assert_eq!(matching_rule(&rules[..2], &app), Some(&rules[1]));
+        // Between equally specific rules the first one wins
+        let tie = [rule("code.exe"), rule("Code.exe")];
+        assert_eq!(matching_rule(&tie, &app), Some(&tie[0]));
+    }
+}
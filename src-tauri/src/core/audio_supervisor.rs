@@ -0,0 +1,131 @@
+use std::{
+    any::Any,
+    time::{Duration, Instant},
+};
+
+use tauri::{AppHandle, Manager, State};
+
+use crate::messages::{self, Msg};
+
+use super::{
+    commands::{connect_with_last_config, disconnect_streaming},
+    events::{emit_error_code, emit_status, ErrorCode, StatusPhase},
+    state::AppState,
+};
+
+/// A second crash this soon after the previous one is not restarted again
+const RESTART_COOLDOWN: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone)]
+pub struct AudioThreadPanic {
+    pub message: String,
+    pub at: Instant,
+}
+
+/// Text of a panic payload (`panic!` with a literal or a formatted message)
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+/// Restart once per crash, but not in a loop if the restarted thread crashes right away
+fn should_restart(previous: Option<Instant>, now: Instant) -> bool {
+    previous.map_or(true, |at| {
+        now.saturating_duration_since(at) >= RESTART_COOLDOWN
+    })
+}
+
+/// Called on the audio streaming thread after its body panicked: tear the streaming
+/// connection down and try one automatic restart
+pub fn handle_audio_thread_panic(app: &AppHandle, message: String) {
+    tracing::error!("[AudioStreaming] Audio thread panicked: {}", message);
+
+    let state: State<'_, AppState> = app.state();
+    let now = Instant::now();
+    let previous = state
+        .audio_thread_panic()
+        .lock()
+        .ok()
+        .and_then(|mut guard| {
+            guard
+                .replace(AudioThreadPanic {
+                    message: message.clone(),
+                    at: now,
+                })
+                .map(|panic| panic.at)
+        });
+    let restart = should_restart(previous, now);
+
+    let provider = Some("elevenlabs");
+    if restart {
+        emit_error_code(
+            app,
+            ErrorCode::StreamingError,
+            provider,
+            messages::text(Msg::CaptureRestarting),
+        );
+    } else {
+        emit_error_code(
+            app,
+            ErrorCode::StreamingError,
+            provider,
+            &messages::fill(Msg::CaptureCrashedAgain, &[("error", &message)]),
+        );
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let state: State<'_, AppState> = app.state();
+        // Waits for this thread to signal it finished and closes the WebSocket
+        if let Err(e) = disconnect_streaming(&state).await {
+            tracing::warn!("[AudioStreaming] Teardown after panic failed: {}", e);
+        }
+
+        if restart {
+            match connect_with_last_config(&app).await {
+                Ok(()) => tracing::info!("[AudioStreaming] Audio capture restarted"),
+                Err(e) => {
+                    tracing::error!("[AudioStreaming] Restart failed: {}", e);
+                    emit_error_code(
+                        &app,
+                        ErrorCode::StreamingError,
+                        provider,
+                        &messages::fill(Msg::CaptureRestartFailed, &[("error", &e)]),
+                    );
+                }
+            }
+        }
+        emit_status(
+            &app,
+            StatusPhase::Idle,
+            Some(messages::text(Msg::ReadyForNext)),
+        );
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_panic_message() {
+        let literal = std::panic::catch_unwind(|| panic!("backend exploded")).unwrap_err();
+        assert_eq!(panic_message(literal.as_ref()), "backend exploded");
+
+        let formatted = std::panic::catch_unwind(|| panic!("device {} vanished", 3)).unwrap_err();
+        assert_eq!(panic_message(formatted.as_ref()), "device 3 vanished");
+    }
+
+    #[test]
+    fn test_should_restart_once_per_cooldown() {
+        let now = Instant::now();
+        assert!(should_restart(None, now));
+        assert!(!should_restart(Some(now), now + Duration::from_secs(5)));
+        assert!(should_restart(Some(now), now + RESTART_COOLDOWN));
+    }
+}
@@ -0,0 +1,141 @@
+use std::sync::{Arc, PoisonError, RwLock};
+
+use anyhow::Result;
+
+use crate::{
+    elevenlabs::ElevenLabsClient,
+    groq::{self, GroqClient},
+    groq_llm::GroqLLMClient,
+    input::KeyboardController,
+    ollama::OllamaClient,
+    openai::{self, OpenAiClient},
+    settings::AppSettings,
+};
+
+use super::transcription::TranscriptionService;
+
+/// The settings the provider clients are built with, resolved to what the
+/// clients use
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientConfig {
+    pub openai_base_url: String,
+    pub groq_base_url: String,
+}
+
+impl ClientConfig {
+    pub fn from_settings(settings: &AppSettings) -> Self {
+        let or_default = |url: &str, default: String| {
+            if url.trim().is_empty() {
+                default
+            } else {
+                url.trim().to_string()
+            }
+        };
+        Self {
+            openai_base_url: or_default(&settings.openai_base_url, openai::default_base_url()),
+            groq_base_url: or_default(&settings.groq_base_url, groq::DEFAULT_BASE_URL.into()),
+        }
+    }
+
+    /// What differs in `next`, for the log
+    fn changes(&self, next: &Self) -> Vec<&'static str> {
+        let mut changes = Vec::new();
+        if self.openai_base_url != next.openai_base_url {
+            changes.push("OpenAI base URL");
+        }
+        if self.groq_base_url != next.groq_base_url {
+            changes.push("Groq base URL");
+        }
+        changes
+    }
+
+    /// Provider clients configured like this
+    pub fn build(&self, keyboard: Arc<KeyboardController>) -> Result<TranscriptionService> {
+        Ok(TranscriptionService::new(
+            OpenAiClient::with_base_url(self.openai_base_url.clone())?,
+            GroqClient::with_base_url(self.groq_base_url.clone())?,
+            GroqLLMClient::with_base_url(self.groq_base_url.clone())?,
+            OllamaClient::new()?,
+            ElevenLabsClient::new()?,
+            keyboard,
+        ))
+    }
+}
+
+struct Clients {
+    config: ClientConfig,
+    service: TranscriptionService,
+}
+
+/// Builds the `TranscriptionService` and builds it again when the settings its
+/// clients depend on change. A transcription keeps the service it started with.
+pub struct ClientFactory {
+    keyboard: Arc<KeyboardController>,
+    current: RwLock<Clients>,
+}
+
+impl ClientFactory {
+    pub fn new(keyboard: Arc<KeyboardController>, settings: &AppSettings) -> Result<Self> {
+        let config = ClientConfig::from_settings(settings);
+        let service = config.build(keyboard.clone())?;
+        Ok(Self {
+            keyboard,
+            current: RwLock::new(Clients { config, service }),
+        })
+    }
+
+    pub fn service(&self) -> TranscriptionService {
+        let clients = self.current.read().unwrap_or_else(PoisonError::into_inner);
+        clients.service.clone()
+    }
+
+    /// Rebuild the clients if `settings` configure them differently. On failure the
+    /// current clients stay in use.
+    pub fn refresh(&self, settings: &AppSettings) {
+        let config = ClientConfig::from_settings(settings);
+        let changes = self
+            .current
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .config
+            .changes(&config);
+        if changes.is_empty() {
+            return;
+        }
+        match config.build(self.keyboard.clone()) {
+            Ok(service) => {
+                *self.current.write().unwrap_or_else(PoisonError::into_inner) =
+                    Clients { config, service };
+                tracing::info!(
+                    "[Clients] Rebuilt provider clients: {} changed",
+                    changes.join(", ")
+                );
+            }
+            Err(e) => tracing::error!(
+                "[Clients] Keeping the current clients; rebuilding them failed: {:#}",
+                e
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_urls_use_the_defaults() {
+        let config = ClientConfig::from_settings(&AppSettings::default());
+        assert_eq!(config.openai_base_url, openai::default_base_url());
+        assert_eq!(config.groq_base_url, groq::DEFAULT_BASE_URL);
+
+        let settings = AppSettings {
+            groq_base_url: "http://localhost:8080".to_string(),
+            ..AppSettings::default()
+        };
+        let next = ClientConfig::from_settings(&settings);
+        assert_eq!(next.groq_base_url, "http://localhost:8080");
+        assert_eq!(config.changes(&next), vec!["Groq base URL"]);
+        assert!(next.changes(&next).is_empty());
+    }
+}
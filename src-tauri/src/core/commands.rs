@@ -1,20 +1,54 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
 use tauri::{AppHandle, State};
 
-use crate::settings::AppSettings;
+use crate::{
+    agc::Agc,
+    audio, audio_file,
+    elevenlabs_streaming::ConnectionConfig,
+    messages::{self, Msg},
+    preflight,
+    settings::{resolve_api_key, AppSettings, TranscriptionProvider},
+    settings_vault::{LockState, SettingsLocked, VaultError},
+};
 
 use super::{
+    app_rules::{self, ForegroundApp},
+    audio_supervisor,
+    diagnostics::{self, DiagnosticCheck},
+    dictation_session::{self, SessionInfo, SessionSummary},
     error::CommandError,
-    events::{emit_error, emit_settings_changed, emit_status, StatusPhase},
-    hotkey,
+    events::{emit_error_code, emit_settings_changed, emit_status, ErrorCode, StatusPhase},
+    file_transcription, hotkey,
+    local_api::{self, LocalApiInfo},
+    model_catalog::{self, ModelList},
+    overlay,
+    permissions::{self, Permissions},
+    preconnect, recording_timer,
+    retranscribe::{self, RetranscribeSummary},
+    session::DictationOptions,
     state::{AppState, AudioStreamingHandle},
+    support_bundle,
+    test_state::{TestCondition, TestState},
+    transcript_log,
+    transcription::{self, RefinementPreview},
+    tray,
+    updater::{self, UpdateInfo},
+    vocabulary,
 };
-use cpal::traits::{DeviceTrait, HostTrait};
 
 type CmdResult<T = ()> = Result<T, CommandError>;
 
 #[tauri::command]
 pub async fn get_settings(state: State<'_, AppState>) -> CmdResult<AppSettings> {
-    Ok((*state.current_settings().await).clone().normalized())
+    state.wait_for_settings_loaded().await;
+    let mut settings = (*state.current_settings().await).clone().normalized();
+    // Revealed only by `set_local_api_enabled` and `reset_local_api_token`
+    settings.local_api_token.clear();
+    Ok(settings)
 }
 
 #[tauri::command]
@@ -23,27 +57,121 @@ pub async fn save_settings(
     state: State<'_, AppState>,
     settings: AppSettings,
 ) -> CmdResult {
-    let normalized = settings.normalized();
+    let mut normalized = settings.normalized();
     normalized.validate()?;
-
+    // Never let a form filled from provisional defaults overwrite the saved file
+    state.wait_for_settings_loaded().await;
+    let _apply = state.lock_settings_apply().await;
+
+    let current = state.current_settings().await;
+    let was_logging = current.log_transcripts;
+    // Only `confirm_post_command` may confirm a command
+    normalized.post_command_confirmed = current.post_command_confirmed.clone();
+    // ...and only `set_settings_passphrase` turn encryption on or off
+    normalized.encrypt_settings = current.encrypt_settings;
+    normalized.keys_locked = current.keys_locked;
+    // The form never sees the local API token
+    normalized.local_api_token = current.local_api_token.clone();
+    // The overlay is pinned by dragging it, not from the form
+    normalized.overlay_position = current.overlay_position;
+    // While locked the token is sealed with the keys, not missing
+    if normalized.local_api_enabled
+        && normalized.local_api_token.is_empty()
+        && !normalized.keys_locked
+    {
+        normalized.local_api_token = local_api::generate_token()?;
+    }
+    if normalized.keys_locked
+        && [
+            &normalized.api_key,
+            &normalized.groq_api_key,
+            &normalized.elevenlabs_api_key,
+            &normalized.deepgram_api_key,
+        ]
+        .iter()
+        .any(|key| !key.is_empty())
+    {
+        // Saving while locked keeps the encrypted keys, so a new key would be lost
+        return Err(VaultError::from(SettingsLocked).into());
+    }
     state.persist_settings(&normalized).await?;
     state.replace_settings(normalized.clone()).await;
 
+    // A live connection still goes to the service configured before
+    if current.streaming_provider != normalized.streaming_provider
+        && state.elevenlabs_streaming().is_connected().await
+    {
+        if let Err(err) = disconnect_streaming(&state).await {
+            tracing::warn!(
+                "[Commands] Failed to drop the streaming connection: {}",
+                err
+            );
+        }
+    }
+
+    if was_logging && !normalized.log_transcripts {
+        if let Err(err) = transcript_log::clear(&app).await {
+            tracing::warn!("[Transcript] Failed to remove transcript log: {}", err);
+        }
+    }
+
     if let Err(err) = apply_autostart(&app, normalized.auto_start) {
-        emit_error(&app, &format!("Autostart update failed: {err}"));
+        emit_error_code(
+            &app,
+            ErrorCode::AutostartFailed,
+            None,
+            &messages::fill(Msg::AutostartFailed, &[("error", &err)]),
+        );
+    }
+
+    state.enforce_history_limits().await;
+
+    if let Err(err) = tray::refresh_tray_menu(&app, &normalized) {
+        tracing::warn!("[Tray] Failed to refresh tray menu: {}", err);
     }
 
-    hotkey::rebind_hotkey(&app, &normalized)?;
+    hotkey::rebind_hotkey(&app, &normalized).await?;
+    local_api::apply_settings(&app, &normalized).await;
+    preconnect::apply_settings(&app, &normalized);
 
     emit_status(
         &app,
         StatusPhase::Idle,
-        Some("Settings saved. Ready for the next transcription."),
+        Some(messages::text(Msg::SettingsSaved)),
     );
 
     Ok(())
 }
 
+/// Confirm the post-transcription command the user was shown. It runs with the
+/// user's privileges on every transcript, so after any change it stays off until
+/// confirmed again; `command` must be the saved one.
+#[tauri::command]
+pub async fn confirm_post_command(state: State<'_, AppState>, command: String) -> CmdResult {
+    state.wait_for_settings_loaded().await;
+    let command = command.trim().to_string();
+    let _apply = state.lock_settings_apply().await;
+    let mut confirmed = false;
+    let settings = state
+        .update_settings(|s| {
+            if !s.post_command.is_empty() && s.post_command == command {
+                s.post_command_confirmed = command.clone();
+                confirmed = true;
+            }
+        })
+        .await;
+
+    if !confirmed {
+        return Err(CommandError::NotFound(
+            "The post-transcription command to confirm is not the saved one".to_string(),
+        ));
+    }
+
+    state.persist_settings(&settings).await?;
+    tracing::info!("[Hook] Post-transcription command confirmed");
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn ping() -> CmdResult<&'static str> {
     Ok("pong")
@@ -56,13 +184,58 @@ pub async fn get_app_version(app: AppHandle) -> String {
 
 #[tauri::command]
 pub async fn toggle_auto_translate(app: AppHandle, state: State<'_, AppState>) -> CmdResult<bool> {
+    let settings = change_translation(&app, &state, TranslationChange::Toggle).await?;
+    Ok(settings.auto_translate)
+}
+
+/// Switch to the next of the configured target languages; returns the new target
+#[tauri::command]
+pub async fn cycle_target_language(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> CmdResult<String> {
+    let settings = change_translation(&app, &state, TranslationChange::Cycle).await?;
+    Ok(settings.target_language)
+}
+
+/// Translation change requested from the UI, tray or hotkey
+pub(crate) enum TranslationChange {
+    Toggle,
+    /// Pick a target language; also turns translation on
+    Target(String),
+    /// Advance through `target_languages`; also turns translation on
+    Cycle,
+}
+
+/// Apply a translation change, persist it and notify the UI and tray
+pub(crate) async fn change_translation(
+    app: &AppHandle,
+    state: &AppState,
+    change: TranslationChange,
+) -> CmdResult<AppSettings> {
+    let _apply = state.lock_settings_apply().await;
     // Atomic read-modify-write under exclusive lock to prevent TOCTOU race
+    let mut changed = true;
     let settings = state
-        .update_settings(|s| {
-            s.auto_translate = !s.auto_translate;
+        .update_settings(|s| match change {
+            TranslationChange::Toggle => s.auto_translate = !s.auto_translate,
+            TranslationChange::Target(language) => {
+                s.target_language = language;
+                s.auto_translate = true;
+            }
+            TranslationChange::Cycle => {
+                changed = s.cycle_target_language().is_some();
+                s.auto_translate |= changed;
+            }
         })
         .await;
 
+    if !changed {
+        return Err(CommandError::NotFound(
+            "No target languages configured".to_string(),
+        ));
+    }
+
     state.persist_settings(&settings).await?;
 
     tracing::info!(
@@ -72,22 +245,429 @@ pub async fn toggle_auto_translate(app: AppHandle, state: State<'_, AppState>) -
     );
 
     // Emit settings changed event for UI sync
-    emit_settings_changed(&app, settings.auto_translate, &settings.target_language);
+    emit_settings_changed(app, &settings);
 
     // Emit status update with target language info
     let message = if settings.auto_translate {
-        format!("Перевод ВКЛ → {}", settings.target_language)
+        messages::fill(
+            Msg::TranslationOn,
+            &[("language", &settings.target_language)],
+        )
     } else {
-        "Перевод ВЫКЛ".to_string()
+        messages::text(Msg::TranslationOff).to_string()
     };
-    emit_status(&app, StatusPhase::Idle, Some(&message));
+    emit_status(app, StatusPhase::Idle, Some(&message));
 
-    Ok(settings.auto_translate)
+    Ok(settings)
+}
+
+/// Start a dictation as if the hotkey was pressed; `stop_dictation` ends it
+#[tauri::command]
+pub async fn start_dictation(app: AppHandle, options: Option<DictationOptions>) -> CmdResult {
+    hotkey::handle_hotkey_pressed(&app, options.unwrap_or_default());
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn stop_dictation(app: AppHandle) -> CmdResult {
+    hotkey::handle_hotkey_released(&app);
+    Ok(())
+}
+
+/// Deliver what was dictated so far and keep recording into the next segment, as
+/// the segment commit hotkey does. Only streamed (ElevenLabs) dictations have segments.
+#[tauri::command]
+pub async fn commit_segment(app: AppHandle) -> CmdResult {
+    hotkey::handle_commit_segment_hotkey(&app);
+    Ok(())
+}
+
+/// Hard-mute the microphone, or lift the mute; returns the new state
+#[tauri::command]
+pub async fn set_mic_muted(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    muted: bool,
+) -> CmdResult<bool> {
+    change_mic_mute(&app, &state, muted);
+    Ok(muted)
+}
+
+#[tauri::command]
+pub async fn get_mic_muted(state: State<'_, AppState>) -> CmdResult<bool> {
+    Ok(state.mic_mute().is_muted())
+}
+
+/// Start a dictation session: utterances are collected until `end_session`
+#[tauri::command]
+pub async fn start_session(app: AppHandle, state: State<'_, AppState>) -> CmdResult<SessionInfo> {
+    Ok(dictation_session::start(&app, &state)?)
+}
+
+/// End the dictation session, turning its utterances into one document that is
+/// saved to history, copied and appended to `session_output_file`
+#[tauri::command]
+pub async fn end_session(app: AppHandle, state: State<'_, AppState>) -> CmdResult<SessionSummary> {
+    Ok(dictation_session::end(&app, &state).await?)
+}
+
+#[tauri::command]
+pub async fn get_session(state: State<'_, AppState>) -> CmdResult<SessionInfo> {
+    Ok(state.dictation_session().info())
+}
+
+/// The refinement prompt the current settings would send for `sample_text`. The
+/// model is only called with `execute`, which needs the LLM provider's key.
+#[tauri::command]
+pub async fn preview_refinement(
+    state: State<'_, AppState>,
+    sample_text: String,
+    execute: Option<bool>,
+) -> CmdResult<RefinementPreview> {
+    state.wait_for_settings_loaded().await;
+    let settings = state.current_settings().await;
+    let context = state
+        .refinement_context(settings.refinement_context_entries)
+        .await;
+    Ok(state
+        .transcription()
+        .preview_refinement(&settings, &sample_text, &context, execute.unwrap_or(false))
+        .await?)
+}
+
+/// Zip settings (keys masked), the recent log, diagnostics and, if asked, recent
+/// history for a bug report; returns where the zip was written
+#[tauri::command]
+pub async fn create_support_bundle(app: AppHandle, include_history: bool) -> CmdResult<String> {
+    let path = support_bundle::create(&app, include_history).await?;
+    Ok(path.display().to_string())
+}
+
+/// Show a bundle from `create_support_bundle` in the file manager
+#[tauri::command]
+pub async fn reveal_support_bundle(path: String) -> CmdResult {
+    Ok(support_bundle::reveal(std::path::Path::new(&path))?)
+}
+
+/// Show the app log in the file manager
+#[tauri::command]
+pub async fn reveal_log(app: AppHandle) -> CmdResult {
+    Ok(support_bundle::reveal_log(&app)?)
+}
+
+/// Bring up the settings window, e.g. from an error's `open_settings` action
+#[tauri::command]
+pub async fn show_settings(app: AppHandle) -> CmdResult {
+    tray::show_settings_window(&app);
+    Ok(())
+}
+
+/// Transcribe the recording of the last failed dictation again; false if none was
+/// kept
+#[tauri::command]
+pub async fn retry_last_transcription(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> CmdResult<bool> {
+    let Some(retry) = state.retry_audio() else {
+        return Ok(false);
+    };
+    if state.is_transcribing().swap(true, Ordering::SeqCst) {
+        return Err(CommandError::Unavailable(
+            "A transcription is already in progress".into(),
+        ));
+    }
+    tracing::info!(
+        "[Commands] Retrying the last transcription ({} bytes)",
+        retry.wav.len()
+    );
+    emit_status(
+        &app,
+        StatusPhase::Transcribing,
+        Some(messages::text(Msg::UploadingAudio)),
+    );
+    transcription::spawn_transcription(&app, retry.wav, retry.session);
+    Ok(true)
+}
+
+/// Stop reading a transcript aloud
+#[tauri::command]
+pub async fn stop_speaking(state: State<'_, AppState>) -> CmdResult {
+    state.speaker().stop();
+    Ok(())
+}
+
+/// Whether the API keys are encrypted (`encrypt_settings`) and still locked
+#[tauri::command]
+pub async fn get_settings_lock(state: State<'_, AppState>) -> CmdResult<LockState> {
+    state.wait_for_settings_loaded().await;
+    Ok(state.settings_store().lock_state())
+}
+
+/// Decrypt the API keys with the passphrase. Until then the rest of the settings
+/// work, but providers that need a key report `settings_locked`.
+#[tauri::command]
+pub async fn unlock_settings(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    passphrase: String,
+) -> CmdResult {
+    state.wait_for_settings_loaded().await;
+    let _apply = state.lock_settings_apply().await;
+    let keys = state.settings_store().unlock(&passphrase)?;
+    let settings = state
+        .update_settings(|s| {
+            keys.apply(s);
+            s.keys_locked = false;
+        })
+        .await;
+    tracing::info!("[Settings] API keys unlocked");
+    // The local API token was sealed with the keys
+    local_api::apply_settings(&app, &settings).await;
+    emit_settings_changed(&app, &settings);
+    Ok(())
+}
+
+/// Encrypt the API keys with `passphrase`, change it (`current` must be the one in
+/// use), or store the keys in the keychain again if `passphrase` is empty. Returns
+/// whether the keys are encrypted now.
+#[tauri::command]
+pub async fn set_settings_passphrase(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    current: String,
+    passphrase: String,
+) -> CmdResult<bool> {
+    state.wait_for_settings_loaded().await;
+    let _apply = state.lock_settings_apply().await;
+    let encrypted = state
+        .settings_store()
+        .set_passphrase(&current, &passphrase)?;
+    let settings = state
+        .update_settings(|s| s.encrypt_settings = encrypted)
+        .await;
+    state.persist_settings(&settings).await?;
+    tracing::info!(
+        "[Settings] API key encryption {}",
+        if encrypted { "on" } else { "off" }
+    );
+    emit_settings_changed(&app, &settings);
+    Ok(encrypted)
+}
+
+/// Turn the local HTTP API on or off. The first time it's enabled a token is
+/// generated and returned, once.
+#[tauri::command]
+pub async fn set_local_api_enabled(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    enabled: bool,
+) -> CmdResult<LocalApiInfo> {
+    state.wait_for_settings_loaded().await;
+    let _apply = state.lock_settings_apply().await;
+    let current = state.current_settings().await;
+    if enabled {
+        // A token made now couldn't be saved next to the sealed one
+        current.ensure_unlocked().map_err(VaultError::from)?;
+    }
+    let token = if enabled && current.local_api_token.is_empty() {
+        Some(local_api::generate_token()?)
+    } else {
+        None
+    };
+    let settings = state
+        .update_settings(|s| {
+            s.local_api_enabled = enabled;
+            if let Some(token) = &token {
+                s.local_api_token = token.clone();
+            }
+        })
+        .await;
+    state.persist_settings(&settings).await?;
+    local_api::apply_settings(&app, &settings).await;
+    emit_settings_changed(&app, &settings);
+    Ok(LocalApiInfo {
+        enabled,
+        port: state.local_api().port(),
+        token,
+    })
+}
+
+/// Replace the local API token, cutting off clients using the old one; returns
+/// the new token, which isn't shown again
+#[tauri::command]
+pub async fn reset_local_api_token(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> CmdResult<String> {
+    state.wait_for_settings_loaded().await;
+    let _apply = state.lock_settings_apply().await;
+    state
+        .current_settings()
+        .await
+        .ensure_unlocked()
+        .map_err(VaultError::from)?;
+    let token = local_api::generate_token()?;
+    let settings = state
+        .update_settings(|s| s.local_api_token = token.clone())
+        .await;
+    state.persist_settings(&settings).await?;
+    local_api::apply_settings(&app, &settings).await;
+    Ok(token)
+}
+
+#[tauri::command]
+pub async fn get_local_api(state: State<'_, AppState>) -> CmdResult<LocalApiInfo> {
+    state.wait_for_settings_loaded().await;
+    Ok(LocalApiInfo {
+        enabled: state.current_settings().await.local_api_enabled,
+        port: state.local_api().port(),
+        token: None,
+    })
+}
+
+/// The application in the foreground, for creating an app rule; always None
+/// outside Windows
+#[tauri::command]
+pub async fn get_foreground_app() -> CmdResult<Option<ForegroundApp>> {
+    Ok(app_rules::foreground_app())
+}
+
+/// Switch the hard mute and show it in the tray. Muting ends a dictation in progress
+/// as if its hotkey was released; the streaming capture closes the device once the
+/// gate is closed.
+pub(crate) fn change_mic_mute(app: &AppHandle, state: &AppState, muted: bool) {
+    if !state.mic_mute().set(muted) {
+        return;
+    }
+    tracing::info!("[Mic] Hard mute {}", if muted { "on" } else { "off" });
+    if muted {
+        hotkey::handle_hotkey_released(app);
+    }
+    tray::set_tray_muted(app, muted);
+}
+
+/// Use `provider` for the next dictation only, without changing the setting.
+/// None clears a choice that was not used yet.
+#[tauri::command]
+pub async fn set_next_provider(
+    state: State<'_, AppState>,
+    provider: Option<TranscriptionProvider>,
+) -> CmdResult {
+    tracing::info!("[Commands] Next dictation provider: {:?}", provider);
+    state.set_next_provider(provider);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn set_provider(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    provider: TranscriptionProvider,
+) -> CmdResult<TranscriptionProvider> {
+    change_provider(&app, &state, provider).await
+}
+
+/// Switch the transcription provider, persist it and notify the UI and tray.
+/// A live ElevenLabs streaming session is disconnected first.
+pub(crate) async fn change_provider(
+    app: &AppHandle,
+    state: &AppState,
+    provider: TranscriptionProvider,
+) -> CmdResult<TranscriptionProvider> {
+    let current = state.current_settings().await.provider;
+    if current == provider {
+        return Ok(provider);
+    }
+
+    if current == TranscriptionProvider::ElevenLabs
+        && state.elevenlabs_streaming().is_connected().await
+    {
+        disconnect_streaming(state).await?;
+    }
+
+    let _apply = state.lock_settings_apply().await;
+    let settings = state.update_settings(|s| s.switch_provider(provider)).await;
+    state.persist_settings(&settings).await?;
+
+    tracing::info!(
+        "[Provider] Transcription provider now: {} (model: {})",
+        settings.provider.display_name(),
+        settings.model
+    );
+
+    emit_settings_changed(app, &settings);
+    emit_status(
+        app,
+        StatusPhase::Idle,
+        Some(&messages::fill(
+            Msg::ProviderSwitched,
+            &[("provider", &settings.provider.display_name())],
+        )),
+    );
+
+    Ok(settings.provider)
+}
+
+#[tauri::command]
+pub async fn set_active_preset(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    name: String,
+) -> CmdResult<String> {
+    change_active_preset(&app, &state, Some(&name)).await
+}
+
+/// Switch to the named prompt preset (or the next one when `name` is None),
+/// persist the choice and notify the UI and tray.
+pub(crate) async fn change_active_preset(
+    app: &AppHandle,
+    state: &AppState,
+    name: Option<&str>,
+) -> CmdResult<String> {
+    let _apply = state.lock_settings_apply().await;
+    let mut changed = false;
+    let settings = state
+        .update_settings(|s| {
+            changed = match name {
+                Some(name) => s.select_preset(name),
+                None => s.cycle_preset().is_some(),
+            };
+        })
+        .await;
+
+    if !changed {
+        return Err(CommandError::NotFound(match name {
+            Some(name) => format!("Prompt preset '{}' not found", name),
+            None => "No prompt presets configured".to_string(),
+        }));
+    }
+
+    state.persist_settings(&settings).await?;
+
+    tracing::info!("[Presets] Active preset now: {}", settings.active_preset);
+
+    emit_settings_changed(app, &settings);
+    if let Err(err) = tray::refresh_tray_menu(app, &settings) {
+        tracing::warn!("[Tray] Failed to refresh tray menu: {}", err);
+    }
+    emit_status(
+        app,
+        StatusPhase::Idle,
+        Some(&messages::fill(
+            Msg::PresetSwitched,
+            &[("preset", &settings.active_preset)],
+        )),
+    );
+
+    Ok(settings.active_preset.clone())
 }
 
 #[tauri::command]
 pub async fn frontend_log(level: Option<String>, message: String) -> CmdResult {
     let lvl = level.as_deref().unwrap_or("info");
+    // The webview may log request or settings objects that carry keys
+    let message = crate::redact::redact(&message);
     match lvl {
         "error" => tracing::error!("[frontend] {}", message),
         "warn" => tracing::warn!("[frontend] {}", message),
@@ -98,6 +678,22 @@ pub async fn frontend_log(level: Option<String>, message: String) -> CmdResult {
     Ok(())
 }
 
+/// Every event the backend emits, with the JSON schema of its payload
+#[tauri::command]
+pub async fn events_manifest() -> CmdResult<Vec<super::events::EventSpec>> {
+    Ok(super::events::manifest())
+}
+
+/// Models the settings can offer for `provider`; empty with a reason when the key
+/// is missing or the provider can't be reached
+#[tauri::command]
+pub async fn list_models(
+    state: State<'_, AppState>,
+    provider: TranscriptionProvider,
+) -> CmdResult<ModelList> {
+    Ok(model_catalog::list_models(&state, provider).await)
+}
+
 pub(crate) fn apply_autostart(app: &AppHandle, should_enable: bool) -> CmdResult {
     #[cfg(debug_assertions)]
     {
@@ -128,6 +724,8 @@ pub(crate) fn apply_autostart(app: &AppHandle, should_enable: bool) -> CmdResult
 // ElevenLabs Gated Streaming Commands
 // ============================================================================
 
+/// Connect streaming to the configured streaming provider; `api_key` is that
+/// provider's key field
 #[tauri::command]
 pub async fn elevenlabs_streaming_connect(
     app: AppHandle,
@@ -136,18 +734,33 @@ pub async fn elevenlabs_streaming_connect(
     sample_rate: u32,
     language_code: String,
 ) -> CmdResult {
-    // Determine actual input device sample rate to avoid mismatches with server format
-    let actual_sample_rate = {
-        let host = cpal::default_host();
-        if let Some(device) = host.default_input_device() {
-            match device.default_input_config() {
-                Ok(cfg) => cfg.sample_rate().0,
-                Err(_) => sample_rate,
-            }
-        } else {
-            sample_rate
-        }
-    };
+    let provider = state.current_settings().await.streaming_provider;
+    connect_streaming(
+        &app,
+        &state,
+        ConnectionConfig {
+            provider,
+            api_key,
+            sample_rate,
+            language_code,
+        },
+    )
+    .await
+}
+
+/// Connect the streaming WebSocket with `config` and start the audio thread feeding it
+pub(crate) async fn connect_streaming(
+    app: &AppHandle,
+    state: &AppState,
+    config: ConnectionConfig,
+) -> CmdResult {
+    // The key field is passed as saved, which may be an `env:` reference
+    let api_key = resolve_api_key(&config.api_key)?;
+    let sample_rate = config.sample_rate;
+
+    // The capture sends at the device rate, so the server must expect that one
+    let actual_sample_rate =
+        audio::default_input_info().map_or(sample_rate, |info| info.sample_rate);
 
     if actual_sample_rate != sample_rate {
         tracing::info!(
@@ -158,93 +771,182 @@ pub async fn elevenlabs_streaming_connect(
     }
 
     // 1. Connect to WebSocket using the actual device sample rate
+    let connect_timeout = state.current_settings().await.elevenlabs_connect_timeout();
     state
         .elevenlabs_streaming()
-        .connect(api_key, actual_sample_rate, language_code, app.clone())
+        .connect(
+            ConnectionConfig {
+                api_key,
+                sample_rate: actual_sample_rate,
+                ..config
+            },
+            connect_timeout,
+            app.clone(),
+        )
         .await?;
 
     // 2. Stop and wait for any existing audio streaming task to prevent concurrent access
-    let prev_handle = {
-        let mut guard = state
-            .audio_streaming_handle()
-            .lock()
-            .map_err(|_| CommandError::Lock("audio streaming handle".into()))?;
-        guard.take()
-    };
-
-    if let Some(handle) = prev_handle {
-        tracing::info!(
-            "[Commands] Stopping existing audio streaming task and waiting for it to finish"
-        );
-        handle.cancel_token.cancel();
-        tokio::task::spawn_blocking(move || match handle.join_handle.join() {
-            Ok(()) => tracing::info!("[Commands] Previous audio streaming task finished cleanly"),
-            Err(_) => tracing::warn!("[Commands] Previous audio streaming task panicked"),
-        })
-        .await
-        .map_err(|e| CommandError::Io(format!("Failed to join audio streaming task: {}", e)))?;
-    }
+    state.stop_audio_streaming().await;
 
     // 3. Spawn dedicated thread for audio streaming (CPAL Stream is !Send)
     let cancel_token = tokio_util::sync::CancellationToken::new();
     let cancel_clone = cancel_token.clone();
     let streaming_client = state.elevenlabs_streaming().clone();
 
-    let join_handle = std::thread::spawn(move || {
-        use crate::audio_stream::ContinuousAudioCapture;
+    let (done, finished) = tokio::sync::oneshot::channel();
+    let (started_tx, started) = tokio::sync::oneshot::channel();
+    let running = Arc::new(AtomicBool::new(true));
+    let thread_running = running.clone();
+    let thread_app = app.clone();
+    std::thread::spawn(move || {
+        // A panic in the capture backend must not leave an open gate with no audio behind it
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            run_audio_thread(&thread_app, streaming_client, cancel_clone, started_tx)
+        }));
+        if let Err(payload) = result {
+            audio_supervisor::handle_audio_thread_panic(
+                &thread_app,
+                audio_supervisor::panic_message(payload.as_ref()),
+            );
+        }
+        thread_running.store(false, Ordering::SeqCst);
+        let _ = done.send(());
+    });
 
-        // Create audio capture on this thread
-        let mut audio_capture = match ContinuousAudioCapture::new() {
-            Ok(capture) => capture,
-            Err(e) => {
-                tracing::error!("[AudioStreaming] Failed to create audio capture: {}", e);
-                return;
-            }
-        };
+    // Store handle for proper cleanup later
+    *state.audio_streaming_handle().lock().await = Some(AudioStreamingHandle {
+        cancel_token,
+        finished,
+        running,
+    });
 
-        // Start audio capture
-        let audio_rx = match audio_capture.start() {
-            Ok(rx) => rx,
+    // A connected socket with no capture behind it would take dictations and never
+    // transcribe them. A sender dropped unsent means the thread panicked first.
+    let startup = started
+        .await
+        .unwrap_or_else(|_| Err("audio thread exited before capture started".to_string()));
+    if let Err(reason) = startup {
+        tracing::error!("[Commands] Streaming audio failed to start: {}", reason);
+        if let Err(e) = disconnect_streaming(state).await {
+            tracing::warn!("[Commands] Failed to drop the connection: {}", e);
+        }
+        return Err(anyhow::Error::new(AudioStartFailed(reason)).into());
+    }
+
+    tracing::info!("[Commands] Streaming connected and audio pipeline started");
+
+    Ok(())
+}
+
+/// The streaming audio thread couldn't start capturing; the connection was dropped
+#[derive(Debug, thiserror::Error)]
+#[error("Streaming audio capture failed to start: {0}")]
+pub struct AudioStartFailed(String);
+
+/// Reconnect streaming (WebSocket and audio thread) with the config of the last connect,
+/// unless it went to another streaming provider than the configured one
+pub(crate) async fn connect_with_last_config(app: &AppHandle) -> CmdResult {
+    use tauri::Manager;
+
+    let state: State<'_, AppState> = app.state();
+    let provider = state.current_settings().await.streaming_provider;
+    let config = state
+        .elevenlabs_streaming()
+        .get_last_config()
+        .await
+        .filter(|config| config.provider == provider)
+        .ok_or_else(|| CommandError::NotFound("No previous streaming connection".into()))?;
+    connect_streaming(app, &state, config).await
+}
+
+/// Body of the dedicated audio streaming thread (CPAL Stream is !Send)
+/// `started` reports whether capture got going, so the connect can fail with it
+fn run_audio_thread(
+    app: &AppHandle,
+    streaming_client: crate::elevenlabs_streaming::StreamingClient,
+    cancel_token: tokio_util::sync::CancellationToken,
+    started: tokio::sync::oneshot::Sender<Result<(), String>>,
+) {
+    use crate::audio_stream::ContinuousAudioCapture;
+    use tauri::Manager;
+
+    let state: State<'_, AppState> = app.state();
+    // Create audio capture on this thread
+    let mut audio_capture = match ContinuousAudioCapture::new(state.capture_running()) {
+        Ok(capture) => capture,
+        Err(e) => {
+            tracing::error!("[AudioStreaming] Failed to create audio capture: {}", e);
+            let _ = started.send(Err(format!("{e:#}")));
+            return;
+        }
+    };
+
+    // Start audio capture; a hard-muted microphone stays closed until it is unmuted
+    let audio_rx = if state.mic_mute().is_muted() {
+        tracing::info!("[AudioStreaming] Microphone is muted, capture waits for unmute");
+        state.set_streaming_capture(None);
+        None
+    } else {
+        match audio_capture.start(streaming_client.audio_counters()) {
+            Ok((rx, capture)) => {
+                tracing::info!(
+                    "[AudioStreaming] Audio capture started: {} Hz",
+                    capture.sample_rate
+                );
+                state.set_streaming_capture(Some(capture));
+                Some(rx)
+            }
             Err(e) => {
                 tracing::error!("[AudioStreaming] Failed to start audio capture: {}", e);
+                let _ = started.send(Err(format!("{e:#}")));
                 return;
             }
-        };
-
-        let sample_rate = audio_capture.sample_rate();
-        tracing::info!("[AudioStreaming] Audio capture started: {} Hz", sample_rate);
-
-        // Reuse the existing Tauri async runtime instead of creating a new one
-        let rt_handle = tauri::async_runtime::handle();
-        rt_handle.block_on(async move {
-            audio_streaming_task(audio_rx, audio_capture, streaming_client, cancel_clone).await;
-        });
-    });
+        }
+    };
+    let _ = started.send(Ok(()));
 
-    // Store handle for proper cleanup later
-    let mut handle_guard = state
-        .audio_streaming_handle()
-        .lock()
-        .map_err(|_| CommandError::Lock("audio streaming handle".into()))?;
-    *handle_guard = Some(AudioStreamingHandle {
-        cancel_token,
-        join_handle,
+    // Reuse the existing Tauri async runtime instead of creating a new one
+    let rt_handle = tauri::async_runtime::handle();
+    rt_handle.block_on(async move {
+        audio_streaming_task(app, audio_rx, audio_capture, streaming_client, cancel_token).await;
     });
+}
 
-    tracing::info!("[Commands] ElevenLabs streaming connected and audio pipeline started");
-
-    Ok(())
+/// Next chunk of the running capture; never resolves while the capture is stopped
+async fn next_chunk(
+    audio_rx: &mut Option<tokio::sync::mpsc::Receiver<Vec<u8>>>,
+) -> Option<Vec<u8>> {
+    match audio_rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
 }
 
-/// Background task that manages audio capture and forwards chunks to ElevenLabs WebSocket
+/// Background task that manages audio capture and forwards chunks to ElevenLabs WebSocket.
+/// While the microphone is hard-muted and the gate is closed the device stream is
+/// stopped (`audio_rx` is None), and started again on unmute.
 async fn audio_streaming_task(
-    mut audio_rx: tokio::sync::mpsc::Receiver<Vec<u8>>,
+    app: &AppHandle,
+    mut audio_rx: Option<tokio::sync::mpsc::Receiver<Vec<u8>>>,
     mut audio_capture: crate::audio_stream::ContinuousAudioCapture,
-    streaming_client: crate::elevenlabs_streaming::ElevenLabsStreamingClient,
+    streaming_client: crate::elevenlabs_streaming::StreamingClient,
     cancel_token: tokio_util::sync::CancellationToken,
 ) {
+    use tauri::Manager;
+
     tracing::info!("[AudioStreaming] Task started");
 
+    let state = app.state::<AppState>();
+    let mic_mute = state.mic_mute().clone();
+    let mut mute_changes = mic_mute.subscribe();
+    let mut gate_openings = streaming_client.gate_openings();
+    // Not known while a capture that began muted waits for unmute
+    let mut sample_rate = state
+        .streaming_capture()
+        .map_or(0, |capture| capture.sample_rate);
+    let mut agc = streaming_agc(app, sample_rate).await;
+    let mut thresholds = state.current_settings().await.upload_thresholds();
+
     loop {
         tokio::select! {
             _ = cancel_token.cancelled() => {
@@ -252,42 +954,81 @@ async fn audio_streaming_task(
                 let _ = audio_capture.stop();
                 break;
             }
-            chunk = audio_rx.recv() => {
+            Ok(()) = mute_changes.changed() => {
+                let muted = *mute_changes.borrow_and_update();
+                if muted {
+                    if audio_rx.is_some() && !streaming_client.state().await.transmitting {
+                        tracing::info!("[AudioStreaming] Microphone muted, stopping capture");
+                        let _ = audio_capture.stop();
+                        audio_rx = None;
+                    }
+                } else if audio_rx.is_none() {
+                    match audio_capture.start(streaming_client.audio_counters()) {
+                        Ok((rx, capture)) => {
+                            tracing::info!("[AudioStreaming] Microphone unmuted, capture started");
+                            audio_rx = Some(rx);
+                            sample_rate = capture.sample_rate;
+                            state.set_streaming_capture(Some(capture));
+                            agc = streaming_agc(app, sample_rate).await;
+                        }
+                        Err(e) => {
+                            tracing::error!("[AudioStreaming] Failed to start audio capture: {}", e);
+                            break;
+                        }
+                    }
+                }
+            }
+            chunk = next_chunk(&mut audio_rx) => {
+                // Muted while the gate was open: stop once that dictation closed it
+                if chunk.is_some()
+                    && mic_mute.is_muted()
+                    && !streaming_client.state().await.transmitting
+                {
+                    tracing::info!("[AudioStreaming] Gate closed while muted, stopping capture");
+                    let _ = audio_capture.stop();
+                    audio_rx = None;
+                    continue;
+                }
                 match chunk {
-                    Some(pcm_data) => {
-                        // Calculate RMS to check for silence/noise
-                        let mut sum_squares = 0.0;
-                        let mut sample_count = 0;
-
-                        for chunk in pcm_data.chunks_exact(2) {
-                            let sample = i16::from_le_bytes([chunk[0], chunk[1]]) as f32;
-                            sum_squares += sample * sample;
-                            sample_count += 1;
+                    Some(mut pcm_data) => {
+                        // Each dictation starts from unity gain, with the AGC settings of the moment
+                        let openings = streaming_client.gate_openings();
+                        if openings != gate_openings {
+                            gate_openings = openings;
+                            agc = streaming_agc(app, sample_rate).await;
+                            thresholds = state.current_settings().await.upload_thresholds();
                         }
 
-                        let rms = if sample_count > 0 {
-                            (sum_squares / sample_count as f32).sqrt()
-                        } else {
-                            0.0
-                        };
+                        // Measured before the AGC, which lifts room noise towards speech level
+                        let rms = preflight::pcm_rms(&pcm_data);
+                        let voiced = thresholds.is_voiced(&pcm_data);
 
                         // Log RMS periodically (every ~1 second = 10 chunks of 100ms)
                         static CHUNK_COUNTER: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(0);
                         let count = CHUNK_COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
                         if count % 10 == 0 {
-                            tracing::debug!("[AudioStreaming] RMS level: {:.0}", rms);
+                            tracing::debug!(
+                                "[AudioStreaming] RMS level: {:.4}, voiced: {}, AGC gain: {:.1} dB",
+                                rms,
+                                voiced,
+                                agc.as_ref().map_or(0.0, Agc::gain_db)
+                            );
                         }
 
                         // Noise gate temporarily disabled for debugging
                         // TODO: Re-enable after fixing the issue
-                        // if rms < NOISE_THRESHOLD {
+                        // if !voiced {
                         //     // Silence the chunk
                         //     pcm_data.fill(0);
                         // }
-                        tracing::debug!("[AudioStreaming] RMS level: {:.0}", rms);
+                        tracing::debug!("[AudioStreaming] RMS level: {:.4}", rms);
+
+                        if let Some(agc) = agc.as_mut() {
+                            agc.process(&mut pcm_data);
+                        }
 
                         // Send chunk to streaming client (will check gate internally)
-                        if let Err(e) = streaming_client.send_audio_chunk(pcm_data).await {
+                        if let Err(e) = streaming_client.send_audio_chunk(pcm_data, voiced).await {
                             tracing::error!("[AudioStreaming] Failed to send chunk: {}", e);
                             // If connection is dead or other fatal error, stop the loop
                             let err_str = e.to_string();
@@ -309,29 +1050,28 @@ async fn audio_streaming_task(
     tracing::info!("[AudioStreaming] Task finished");
 }
 
+/// Gain control for the streamed audio, if the settings enable it
+async fn streaming_agc(app: &AppHandle, sample_rate: u32) -> Option<Agc> {
+    use tauri::Manager;
+
+    let settings = app.state::<AppState>().current_settings().await;
+    settings.agc().map(|config| Agc::new(config, sample_rate))
+}
+
 #[tauri::command]
 pub async fn elevenlabs_streaming_disconnect(state: State<'_, AppState>) -> CmdResult {
+    disconnect_streaming(&state).await
+}
+
+/// Stop the audio streaming task and close the ElevenLabs WebSocket
+pub(crate) async fn disconnect_streaming(state: &AppState) -> CmdResult {
     tracing::info!("[Commands] Disconnecting ElevenLabs streaming...");
 
-    // 1. Stop audio streaming task and wait for it to finish
-    let prev_handle = {
-        let mut guard = state
-            .audio_streaming_handle()
-            .lock()
-            .map_err(|_| CommandError::Lock("audio streaming handle".into()))?;
-        guard.take()
-    };
+    // An open gate counts as a recording; its tick timer must not outlive the connection
+    recording_timer::cancel_recording_timer(state);
 
-    if let Some(handle) = prev_handle {
-        tracing::info!("[Commands] Stopping audio streaming task...");
-        handle.cancel_token.cancel();
-        tokio::task::spawn_blocking(move || match handle.join_handle.join() {
-            Ok(()) => tracing::info!("[Commands] Audio streaming task stopped cleanly"),
-            Err(_) => tracing::warn!("[Commands] Audio streaming task panicked"),
-        })
-        .await
-        .map_err(|e| CommandError::Io(format!("Failed to join audio streaming task: {}", e)))?;
-    }
+    // 1. Stop audio streaming task and wait for it to finish
+    state.stop_audio_streaming().await;
 
     // 2. Disconnect WebSocket
     state.elevenlabs_streaming().disconnect().await?;
@@ -343,13 +1083,18 @@ pub async fn elevenlabs_streaming_disconnect(state: State<'_, AppState>) -> CmdR
 
 #[tauri::command]
 pub async fn elevenlabs_streaming_open_gate(state: State<'_, AppState>) -> CmdResult {
+    state.recorder().check_access()?;
     state.elevenlabs_streaming().open_gate().await?;
     Ok(())
 }
 
 #[tauri::command]
 pub async fn elevenlabs_streaming_close_gate(state: State<'_, AppState>) -> CmdResult {
-    state.elevenlabs_streaming().close_gate_and_commit().await?;
+    let silence = state.current_settings().await.commit_silence();
+    state
+        .elevenlabs_streaming()
+        .close_gate_and_commit(silence)
+        .await?;
     Ok(())
 }
 
@@ -358,9 +1103,11 @@ pub async fn elevenlabs_streaming_send_chunk(
     state: State<'_, AppState>,
     pcm_data: Vec<u8>,
 ) -> CmdResult {
+    let thresholds = state.current_settings().await.upload_thresholds();
+    let voiced = thresholds.is_voiced(&pcm_data);
     state
         .elevenlabs_streaming()
-        .send_audio_chunk(pcm_data)
+        .send_audio_chunk(pcm_data, voiced)
         .await?;
     Ok(())
 }
@@ -370,6 +1117,15 @@ pub async fn elevenlabs_streaming_is_connected(state: State<'_, AppState>) -> Cm
     Ok(state.elevenlabs_streaming().is_connected().await)
 }
 
+/// Connection state, how many chunks the send queue coalesced or dropped and what
+/// happened to the audio of the current dictation
+#[tauri::command]
+pub async fn get_streaming_state(
+    state: State<'_, AppState>,
+) -> CmdResult<crate::elevenlabs_streaming::StreamingState> {
+    Ok(state.elevenlabs_streaming().state().await)
+}
+
 // ============================================================================
 // History Commands
 // ============================================================================
@@ -387,11 +1143,177 @@ pub async fn clear_history(state: State<'_, AppState>) -> CmdResult {
     Ok(())
 }
 
+/// WAV bytes of `start_ms..end_ms` of a history entry's saved recording, for playback
+/// of one of its `segments`
+#[tauri::command]
+pub async fn get_history_entry_audio(
+    state: State<'_, AppState>,
+    id: u64,
+    start_ms: u64,
+    end_ms: u64,
+) -> CmdResult<tauri::ipc::Response> {
+    let entry = state
+        .history_entry(id)
+        .await
+        .ok_or_else(|| CommandError::NotFound(format!("History entry {id} not found")))?;
+    let path = entry.recording_path.ok_or_else(|| {
+        CommandError::NotFound(format!("History entry {id} has no saved recording"))
+    })?;
+    let slice = tokio::task::spawn_blocking(move || {
+        audio_file::slice_wav(
+            &path,
+            std::time::Duration::from_millis(start_ms),
+            std::time::Duration::from_millis(end_ms),
+        )
+    })
+    .await
+    .map_err(|e| CommandError::Io(e.to_string()))??;
+    Ok(tauri::ipc::Response::new(slice))
+}
+
+/// Transcribe the saved recordings of history entries `ids` again with `provider`,
+/// storing the results as alternate transcripts. Resolves when the batch ends.
+#[tauri::command]
+pub async fn retranscribe_recordings(
+    app: AppHandle,
+    ids: Vec<u64>,
+    provider: TranscriptionProvider,
+) -> CmdResult<RetranscribeSummary> {
+    retranscribe::retranscribe(&app, ids, provider).await
+}
+
+/// Stop a running `retranscribe_recordings`; false if none is running
+#[tauri::command]
+pub async fn cancel_retranscription(state: State<'_, AppState>) -> CmdResult<bool> {
+    Ok(retranscribe::cancel(&state))
+}
+
 #[tauri::command]
 pub async fn delete_history_entry(state: State<'_, AppState>, id: u64) -> CmdResult<bool> {
     Ok(state.delete_history_entry(id).await)
 }
 
+/// Add the terms of a vocabulary file (one per line, `#` comments) to the global
+/// vocabulary, or replace it with them; returns the resulting list
+#[tauri::command]
+pub async fn import_vocabulary(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+    mode: Option<vocabulary::ImportMode>,
+) -> CmdResult<Vec<String>> {
+    let source = std::path::PathBuf::from(path.trim());
+    let contents = tokio::fs::read_to_string(&source).await.map_err(|e| {
+        CommandError::Io(format!(
+            "Failed to read vocabulary from {}: {}",
+            source.display(),
+            e
+        ))
+    })?;
+    let imported = vocabulary::parse(&contents);
+    let count = imported.len();
+    let mode = mode.unwrap_or_default();
+
+    let _apply = state.lock_settings_apply().await;
+    let settings = state
+        .update_settings(|s| {
+            s.custom_vocabulary = vocabulary::combine(&s.custom_vocabulary, imported, mode)
+        })
+        .await;
+    state.persist_settings(&settings).await?;
+    emit_settings_changed(&app, &settings);
+
+    tracing::info!(
+        "[Vocabulary] Imported {} terms from {} ({:?}), {} in the list now",
+        count,
+        source.display(),
+        mode,
+        settings.custom_vocabulary.len()
+    );
+    Ok(settings.custom_vocabulary.clone())
+}
+
+/// Write the global vocabulary to `path`, one term per line; returns the number of terms
+#[tauri::command]
+pub async fn export_vocabulary(state: State<'_, AppState>, path: String) -> CmdResult<usize> {
+    let target = std::path::PathBuf::from(path.trim());
+    let terms = state.current_settings().await.custom_vocabulary.clone();
+    tokio::fs::write(&target, vocabulary::render(&terms))
+        .await
+        .map_err(|e| {
+            CommandError::Io(format!(
+                "Failed to write vocabulary to {}: {}",
+                target.display(),
+                e
+            ))
+        })?;
+
+    tracing::info!(
+        "[Vocabulary] Exported {} terms to {}",
+        terms.len(),
+        target.display()
+    );
+    Ok(terms.len())
+}
+
+#[tauri::command]
+pub async fn get_dictation_stats(
+    state: State<'_, AppState>,
+) -> CmdResult<super::stats::DictationStats> {
+    let history = state.get_history().await;
+    Ok(super::stats::compute_stats(&history, &chrono::Local::now()))
+}
+
+/// Export the full history to `path`; returns the number of exported entries
+#[tauri::command]
+pub async fn export_history(
+    state: State<'_, AppState>,
+    path: String,
+    format: super::export::ExportFormat,
+) -> CmdResult<usize> {
+    let target = std::path::PathBuf::from(path.trim());
+    let parent_missing = target
+        .parent()
+        .map(|dir| !dir.as_os_str().is_empty() && !dir.exists())
+        .unwrap_or(false);
+    if parent_missing || target.is_dir() {
+        return Err(CommandError::NotFound(format!(
+            "Export destination {} is not a writable file path",
+            target.display()
+        )));
+    }
+
+    let history = state.get_history().await;
+    let contents = super::export::serialize_history(&history, format, &chrono::Local)?;
+
+    tokio::fs::write(&target, contents).await.map_err(|e| {
+        CommandError::Io(format!(
+            "Failed to write history export to {}: {}",
+            target.display(),
+            e
+        ))
+    })?;
+
+    tracing::info!(
+        "[History] Exported {} entries to {}",
+        history.len(),
+        target.display()
+    );
+    Ok(history.len())
+}
+
+/// Delete history entries older than `days`. 0 is rejected: the retention cutoff
+/// treats it as "no limit", so it would silently purge nothing.
+#[tauri::command]
+pub async fn purge_history_older_than(state: State<'_, AppState>, days: u32) -> CmdResult<usize> {
+    if days == 0 {
+        return Err(CommandError::InvalidArgument(
+            "Purging history needs an age of at least 1 day".into(),
+        ));
+    }
+    Ok(state.purge_history_older_than(days).await)
+}
+
 // ============================================================================
 // Test Mode Commands (for E2E testing without microphone)
 // ============================================================================
@@ -425,12 +1347,10 @@ pub async fn inject_test_audio(
             return Err(anyhow!("Audio data is empty").into());
         }
 
-        // Validate WAV header (RIFF....WAVE)
-        if audio_data.len() < 12 || &audio_data[0..4] != b"RIFF" || &audio_data[8..12] != b"WAVE" {
-            return Err(anyhow!("Invalid WAV format: missing RIFF/WAVE header").into());
-        }
+        crate::elevenlabs::check_wav(&audio_data).map_err(anyhow::Error::new)?;
 
         let audio_wav = audio_data;
+        let recorded = crate::audio_file::wav_duration(&audio_wav);
 
         // Get settings and perform transcription directly
         let settings = state.current_settings().await;
@@ -443,19 +1363,51 @@ pub async fn inject_test_audio(
             Some("Processing test audio..."),
         );
 
-        match service.perform(&settings, audio_wav).await {
+        let context = state
+            .refinement_context(settings.refinement_context_entries)
+            .await;
+
+        let mut timings = super::timings::Timings::start();
+        match service
+            .perform(
+                &settings,
+                audio_wav,
+                &context,
+                &mut timings,
+                &super::transcription::refinement_progress(&app),
+            )
+            .await
+        {
             Ok(result) => {
-                let trimmed = result.processed.trim().to_string();
+                let trimmed =
+                    super::transcription::prepare_for_delivery(&settings, result.processed.trim());
                 tracing::info!("[TestMode] Transcription result: {}", trimmed);
+                timings.mark_delivered();
 
+                // Nothing is typed in test mode, but the phases follow a real dictation
+                super::events::emit_status(&app, super::events::StatusPhase::Delivering, None);
                 super::events::emit_status(&app, super::events::StatusPhase::Success, None);
-                super::events::emit_complete(&app, &trimmed);
+                let stats = crate::text_utils::TextStats::measure(&trimmed, recorded);
+                let translation = super::session::SessionContext::default()
+                    .translation(&settings, result.llm_applied || result.combined_translation);
+                super::events::emit_complete(
+                    &app,
+                    super::events::CompletePayload {
+                        text: &trimmed,
+                        diff: None,
+                        timings: Some(&timings.report()),
+                        stats: &stats,
+                        elevated_target: false,
+                        translation: &translation,
+                        capture: None,
+                    },
+                );
 
                 Ok(trimmed)
             }
             Err(err) => {
                 tracing::error!("[TestMode] Transcription failed: {}", err);
-                super::events::emit_error(&app, &err.to_string());
+                super::events::emit_error_from(&app, &err, None);
                 Err(err.into())
             }
         }
@@ -464,9 +1416,28 @@ pub async fn inject_test_audio(
 
 /// Get current app state for testing
 #[tauri::command]
-pub async fn get_test_state(
+pub async fn get_test_state(#[allow(unused)] state: State<'_, AppState>) -> CmdResult<TestState> {
+    #[cfg(not(debug_assertions))]
+    {
+        return Err(CommandError::Unavailable(
+            "Test commands are not available in release builds".into(),
+        ));
+    }
+
+    #[cfg(debug_assertions)]
+    {
+        Ok(TestState::collect(&state).await)
+    }
+}
+
+/// Long-poll until `condition` holds and return the state it held in, so tests
+/// don't need sleeps; fails after `timeout_ms` (default 10 s)
+#[tauri::command]
+pub async fn wait_for_test_state(
     #[allow(unused)] state: State<'_, AppState>,
-) -> CmdResult<serde_json::Value> {
+    #[allow(unused)] condition: TestCondition,
+    #[allow(unused)] timeout_ms: Option<u64>,
+) -> CmdResult<TestState> {
     #[cfg(not(debug_assertions))]
     {
         return Err(CommandError::Unavailable(
@@ -476,29 +1447,52 @@ pub async fn get_test_state(
 
     #[cfg(debug_assertions)]
     {
-        use std::sync::atomic::Ordering;
-
-        let is_recording = state
-            .active_recording()
-            .lock()
-            .map(|guard| guard.is_some())
-            .unwrap_or(false);
-        let is_transcribing = state.is_transcribing().load(Ordering::SeqCst);
-        let settings = state.current_settings().await;
+        use super::test_state;
+        use std::time::Duration;
+
+        let timeout = timeout_ms.map_or(test_state::DEFAULT_WAIT_TIMEOUT, Duration::from_millis);
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let current = TestState::collect(&state).await;
+            if condition.holds(&current) {
+                return Ok(current);
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(CommandError::Timeout(format!(
+                    "{:?} did not hold within {} ms",
+                    condition,
+                    timeout.as_millis()
+                )));
+            }
+            tokio::time::sleep(test_state::POLL_INTERVAL).await;
+        }
+    }
+}
 
-        Ok(serde_json::json!({
-            "is_recording": is_recording,
-            "is_transcribing": is_transcribing,
-            "provider": format!("{:?}", settings.provider),
-            "has_api_key": !settings.api_key.is_empty(),
-            "hotkey": settings.hotkey
-        }))
+/// Make the next streaming audio capture fail to start, for testing the fallback
+#[tauri::command]
+pub async fn fail_next_audio_capture() -> CmdResult {
+    #[cfg(not(debug_assertions))]
+    {
+        return Err(CommandError::Unavailable(
+            "Test commands are not available in release builds".into(),
+        ));
+    }
+
+    #[cfg(debug_assertions)]
+    {
+        tracing::info!("[TestMode] Next streaming audio capture will fail");
+        crate::audio_stream::FAIL_NEXT_CAPTURE.store(true, Ordering::SeqCst);
+        Ok(())
     }
 }
 
 /// Simulate hotkey press for testing (starts recording)
 #[tauri::command]
-pub async fn simulate_hotkey_press(#[allow(unused)] app: AppHandle) -> CmdResult {
+pub async fn simulate_hotkey_press(
+    #[allow(unused)] app: AppHandle,
+    #[allow(unused)] provider_override: Option<TranscriptionProvider>,
+) -> CmdResult {
     #[cfg(not(debug_assertions))]
     {
         return Err(CommandError::Unavailable(
@@ -509,7 +1503,13 @@ pub async fn simulate_hotkey_press(#[allow(unused)] app: AppHandle) -> CmdResult
     #[cfg(debug_assertions)]
     {
         tracing::info!("[TestMode] Simulating hotkey press");
-        super::hotkey::handle_hotkey_pressed(&app, false);
+        super::hotkey::handle_hotkey_pressed(
+            &app,
+            DictationOptions {
+                provider_override,
+                ..DictationOptions::default()
+            },
+        );
         Ok(())
     }
 }
@@ -564,7 +1564,17 @@ pub async fn show_main_window(#[allow(unused)] app: AppHandle) -> CmdResult {
 pub async fn show_overlay_no_focus(app: AppHandle) -> CmdResult {
     use tauri::Manager;
 
-    if let Some(window) = app.get_webview_window("overlay") {
+    if let Some(window) = app.get_webview_window(overlay::OVERLAY_LABEL) {
+        let state = app.state::<AppState>();
+        let pinned =
+            overlay::pinned_placement(&window, state.current_settings().await.overlay_position);
+        // Takes the mouse only while the user is placing it
+        #[cfg_attr(
+            not(any(target_os = "windows", target_os = "macos")),
+            allow(unused_variables)
+        )]
+        let click_through = !state.is_overlay_interactive();
+
         #[cfg(target_os = "windows")]
         {
             use windows::Win32::Foundation::{HWND, POINT, RECT};
@@ -584,7 +1594,7 @@ pub async fn show_overlay_no_focus(app: AppHandle) -> CmdResult {
 
                 // Get cursor position and find monitor
                 let mut cursor_pos = POINT::default();
-                let (x, y) = unsafe {
+                let (x, y) = pinned.unwrap_or_else(|| unsafe {
                     let _ = GetCursorPos(&mut cursor_pos);
                     let monitor = MonitorFromPoint(cursor_pos, MONITOR_DEFAULTTONEAREST);
 
@@ -605,7 +1615,7 @@ pub async fn show_overlay_no_focus(app: AppHandle) -> CmdResult {
                         // Fallback to primary monitor center-bottom
                         (100, 800)
                     }
-                };
+                });
 
                 tracing::info!(
                     "[Commands] Positioning overlay at ({}, {}) on monitor with cursor",
@@ -627,7 +1637,7 @@ pub async fn show_overlay_no_focus(app: AppHandle) -> CmdResult {
                 }
 
                 window
-                    .set_ignore_cursor_events(true)
+                    .set_ignore_cursor_events(click_through)
                     .map_err(|e| anyhow::anyhow!(e))?;
             } else {
                 tracing::warn!(
@@ -637,83 +1647,101 @@ pub async fn show_overlay_no_focus(app: AppHandle) -> CmdResult {
             }
         }
 
-        #[cfg(not(target_os = "windows"))]
+        #[cfg(target_os = "macos")]
         {
+            overlay::show_without_focus(&window, pinned, click_through)?;
+        }
+
+        #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+        {
+            if let Some((x, y)) = pinned {
+                window
+                    .set_position(tauri::PhysicalPosition::new(x, y))
+                    .map_err(|e| anyhow::anyhow!(e))?;
+            }
             window.show().map_err(|e| anyhow::anyhow!(e))?;
         }
     }
     Ok(())
 }
 
+/// Let the user drag the overlay to where it should appear (the spot is saved when
+/// they drop it), or make it click-through again
+#[tauri::command]
+pub async fn set_overlay_interactive(app: AppHandle, interactive: bool) -> CmdResult {
+    overlay::set_interactive(&app, interactive)?;
+    if interactive {
+        overlay::cancel_overlay_hide(&app);
+        show_overlay_no_focus(app).await?;
+    }
+    Ok(())
+}
+
+/// Forget where the user dragged the overlay and go back to the automatic placement
+#[tauri::command]
+pub async fn reset_overlay_position(app: AppHandle) -> CmdResult {
+    use tauri::Manager;
+
+    overlay::pin(&app, None).await?;
+    let visible = app
+        .get_webview_window(overlay::OVERLAY_LABEL)
+        .is_some_and(|window| window.is_visible().unwrap_or(false));
+    if visible {
+        show_overlay_no_focus(app).await?;
+    }
+    Ok(())
+}
+
 // ============================================================================
 // Updater Commands
 // ============================================================================
 
 #[tauri::command]
-pub async fn check_for_updates(app: AppHandle) -> CmdResult<Option<String>> {
-    use tauri_plugin_updater::UpdaterExt;
-
-    let updater = app
-        .updater_builder()
-        .build()
-        .map_err(|e| anyhow::anyhow!(e))?;
+pub async fn check_for_updates(app: AppHandle) -> CmdResult<UpdateInfo> {
+    let (info, _) = updater::check(&app)
+        .await
+        .map_err(|e| CommandError::Io(e.to_string()))?;
+    Ok(info)
+}
 
-    match updater.check().await {
-        Ok(Some(update)) => {
-            tracing::info!(
-                "[Updater] Update available: {} -> {}",
-                update.current_version,
-                update.version
-            );
-            Ok(Some(update.version.to_string()))
-        }
-        Ok(None) => {
-            tracing::info!("[Updater] App is up to date");
-            Ok(None)
-        }
-        Err(e) => {
-            tracing::warn!("[Updater] Check failed: {}", e);
-            Err(CommandError::Io(format!("Update check failed: {}", e)))
-        }
-    }
+#[tauri::command]
+pub async fn download_update(app: AppHandle) -> CmdResult<String> {
+    let (_, update) = updater::check(&app)
+        .await
+        .map_err(|e| CommandError::Io(e.to_string()))?;
+    let update = update.ok_or_else(|| CommandError::Io("No update available".into()))?;
+    updater::download(&app, update)
+        .await
+        .map_err(|e| CommandError::Io(e.to_string()))
 }
 
 #[tauri::command]
-pub async fn install_update(app: AppHandle) -> CmdResult<()> {
-    use tauri::Emitter;
-    use tauri_plugin_updater::UpdaterExt;
+pub async fn install_update(app: AppHandle) -> CmdResult<String> {
+    updater::install(&app).map_err(|e| CommandError::Io(e.to_string()))
+}
 
-    let updater = app
-        .updater_builder()
-        .build()
-        .map_err(|e| anyhow::anyhow!(e))?;
+/// Check each subsystem and return one `{ name, ok, detail }` entry per check
+#[tauri::command]
+pub async fn run_diagnostics(app: AppHandle) -> CmdResult<Vec<DiagnosticCheck>> {
+    Ok(diagnostics::run(&app).await)
+}
 
-    let update = updater
-        .check()
-        .await
-        .map_err(|e| CommandError::Io(format!("Update check failed: {}", e)))?
-        .ok_or_else(|| CommandError::Io("No update available".into()))?;
-
-    let version = update.version.to_string();
-    tracing::info!("[Updater] Downloading update v{}...", version);
-
-    update
-        .download_and_install(
-            |chunk, total| {
-                tracing::debug!(
-                    "[Updater] Downloaded {} of {} bytes",
-                    chunk,
-                    total.unwrap_or(0)
-                );
-            },
-            || {
-                tracing::info!("[Updater] Download finished, installing...");
-            },
-        )
+/// Delete `transcripts.log` and its rotated copy
+#[tauri::command]
+pub async fn clear_transcript_log(app: AppHandle) -> CmdResult {
+    transcript_log::clear(&app)
         .await
-        .map_err(|e| CommandError::Io(format!("Update install failed: {}", e)))?;
+        .map_err(|e| CommandError::Io(e.to_string()))
+}
 
-    tracing::info!("[Updater] Update v{} installed. Restart required.", version);
-    let _ = app.emit("update://installed", &version);
-    Ok(())
+/// Microphone and accessibility authorization, so the UI can explain how to grant them
+#[tauri::command]
+pub async fn check_permissions() -> CmdResult<Permissions> {
+    Ok(permissions::check())
+}
+
+/// Transcribe an audio file (wav, mp3, m4a) into the clipboard and history
+#[tauri::command]
+pub async fn transcribe_file(app: AppHandle, path: String) -> CmdResult<String> {
+    file_transcription::transcribe_file(&app, path.into()).await
 }
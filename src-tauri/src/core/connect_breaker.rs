@@ -0,0 +1,116 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Consecutive failed streaming connects that open the breaker
+pub const FAILURE_THRESHOLD: u32 = 2;
+/// How long dictations skip streaming once the breaker is open
+pub const COOLDOWN: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    failures: u32,
+    open_until: Option<Instant>,
+}
+
+/// Circuit breaker for the ElevenLabs streaming connect: after repeated failures
+/// dictations go straight to legacy recording for a while instead of waiting on
+/// yet another handshake
+#[derive(Debug)]
+pub struct ConnectBreaker {
+    state: Mutex<BreakerState>,
+    threshold: u32,
+    cooldown: Duration,
+}
+
+impl Default for ConnectBreaker {
+    fn default() -> Self {
+        Self::new(FAILURE_THRESHOLD, COOLDOWN)
+    }
+}
+
+impl ConnectBreaker {
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            state: Mutex::new(BreakerState::default()),
+            threshold,
+            cooldown,
+        }
+    }
+
+    /// Time left until streaming is tried again; None if a connect may be attempted.
+    /// Once the cooldown is over one attempt is let through; it failing opens the
+    /// breaker again right away.
+    pub fn open_for(&self, now: Instant) -> Option<Duration> {
+        let state = self.state.lock().ok()?;
+        state
+            .open_until
+            .filter(|until| *until > now)
+            .map(|until| until - now)
+    }
+
+    pub fn record_success(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            *state = BreakerState::default();
+        }
+    }
+
+    /// Count a failed connect; true if it opened the breaker
+    pub fn record_failure(&self, now: Instant) -> bool {
+        let Ok(mut state) = self.state.lock() else {
+            return false;
+        };
+        state.failures += 1;
+        if state.failures < self.threshold {
+            return false;
+        }
+        state.open_until = Some(now + self.cooldown);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opens_after_consecutive_failures() {
+        let breaker = ConnectBreaker::new(2, Duration::from_secs(60));
+        let now = Instant::now();
+        assert!(!breaker.record_failure(now));
+        assert_eq!(breaker.open_for(now), None);
+
+        assert!(breaker.record_failure(now));
+        assert_eq!(
+            breaker.open_for(now + Duration::from_secs(15)),
+            Some(Duration::from_secs(45))
+        );
+    }
+
+    #[test]
+    fn test_success_resets_the_count() {
+        let breaker = ConnectBreaker::new(2, Duration::from_secs(60));
+        let now = Instant::now();
+        assert!(!breaker.record_failure(now));
+        breaker.record_success();
+        assert!(!breaker.record_failure(now));
+        assert_eq!(breaker.open_for(now), None);
+    }
+
+    #[test]
+    fn test_failed_retry_after_cooldown_opens_again() {
+        let breaker = ConnectBreaker::new(2, Duration::from_secs(60));
+        let now = Instant::now();
+        breaker.record_failure(now);
+        breaker.record_failure(now);
+
+        let later = now + Duration::from_secs(61);
+        assert_eq!(breaker.open_for(later), None);
+        assert!(breaker.record_failure(later));
+        assert!(breaker.open_for(later).is_some());
+
+        breaker.record_success();
+        assert_eq!(breaker.open_for(later), None);
+    }
+}
@@ -0,0 +1,255 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use anyhow::{anyhow, Result};
+use tokio::sync::Mutex;
+
+use crate::{
+    elevation::{ElevationCheck, ForegroundWindow},
+    input::KeyboardController,
+};
+
+/// Anything that can type text into the focused window
+pub trait Typist: Send + Sync + 'static {
+    fn type_text(&self, text: &str) -> Result<()>;
+}
+
+impl Typist for KeyboardController {
+    fn type_text(&self, text: &str) -> Result<()> {
+        KeyboardController::type_text(self, text)
+    }
+}
+
+/// How a delivered text reached the user
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Delivery {
+    /// Typing was skipped because the focused window runs elevated and would discard
+    /// synthetic input; the text was copied to the clipboard instead
+    pub elevated_target: bool,
+}
+
+/// Serializes output delivery (clipboard write + typing) across both transcription
+/// paths, so two results finishing at once are never typed interleaved.
+/// The lock is fair, so results are delivered in the order they completed.
+pub struct DeliveryQueue {
+    turn: Mutex<()>,
+    /// Deliveries waiting for their turn or in progress
+    pending: Arc<AtomicUsize>,
+    elevation: Arc<dyn ElevationCheck>,
+}
+
+/// Counts one delivery as pending until dropped, also if the delivery is cancelled
+struct PendingGuard(Arc<AtomicUsize>);
+
+impl PendingGuard {
+    fn new(pending: &Arc<AtomicUsize>) -> Self {
+        pending.fetch_add(1, Ordering::SeqCst);
+        Self(pending.clone())
+    }
+}
+
+impl Drop for PendingGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Default for DeliveryQueue {
+    fn default() -> Self {
+        Self::with_elevation_check(Arc::new(ForegroundWindow))
+    }
+}
+
+impl DeliveryQueue {
+    pub fn with_elevation_check(elevation: Arc<dyn ElevationCheck>) -> Self {
+        Self {
+            turn: Mutex::new(()),
+            pending: Arc::new(AtomicUsize::new(0)),
+            elevation,
+        }
+    }
+
+    /// Number of deliveries waiting for their turn or in progress
+    pub fn pending(&self) -> usize {
+        self.pending.load(Ordering::SeqCst)
+    }
+
+    /// Wait for our turn, pass the text to `copy` if `copy_to_clipboard`, then type it
+    /// if `simulate_typing`. Text that can't be typed into an elevated window is
+    /// copied even if `copy_to_clipboard` is off.
+    pub async fn deliver<T: Typist>(
+        &self,
+        typist: Arc<T>,
+        text: String,
+        simulate_typing: bool,
+        copy_to_clipboard: bool,
+        copy: impl FnOnce(&str) + Send,
+    ) -> Result<Delivery> {
+        let _pending = PendingGuard::new(&self.pending);
+        let _turn = self.turn.lock().await;
+        if text.is_empty() {
+            return Ok(Delivery::default());
+        }
+
+        let elevated_target = simulate_typing && self.elevation.input_blocked();
+        if copy_to_clipboard || elevated_target {
+            copy(&text);
+        }
+        if elevated_target {
+            tracing::warn!("[Delivery] Focused window is elevated, not typing");
+        } else if simulate_typing {
+            tokio::task::spawn_blocking(move || typist.type_text(&text))
+                .await
+                .map_err(|e| anyhow!("Typing task failed: {e}"))??;
+        }
+        Ok(Delivery { elevated_target })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Mutex as StdMutex, time::Duration};
+
+    use super::*;
+
+    /// Records every typed character, slowly enough for deliveries to overlap
+    #[derive(Default)]
+    struct RecordingTypist {
+        typed: StdMutex<String>,
+    }
+
+    impl Typist for RecordingTypist {
+        fn type_text(&self, text: &str) -> Result<()> {
+            for ch in text.chars() {
+                self.typed.lock().unwrap().push(ch);
+                std::thread::sleep(Duration::from_millis(2));
+            }
+            Ok(())
+        }
+    }
+
+    struct Elevated(bool);
+
+    impl ElevationCheck for Elevated {
+        fn input_blocked(&self) -> bool {
+            self.0
+        }
+    }
+
+    fn queue() -> DeliveryQueue {
+        DeliveryQueue::with_elevation_check(Arc::new(Elevated(false)))
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_concurrent_deliveries_do_not_interleave() {
+        let queue = Arc::new(queue());
+        let typist = Arc::new(RecordingTypist::default());
+        let copied = Arc::new(StdMutex::new(Vec::new()));
+
+        let first = {
+            let (queue, typist, copied) = (queue.clone(), typist.clone(), copied.clone());
+            tokio::spawn(async move {
+                queue
+                    .deliver(typist, "aaaaaaaaaa".into(), true, true, |text| {
+                        copied.lock().unwrap().push(text.to_string())
+                    })
+                    .await
+            })
+        };
+        // Make sure the first delivery holds the turn before the second one queues
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        let second = {
+            let (queue, typist, copied) = (queue.clone(), typist.clone(), copied.clone());
+            tokio::spawn(async move {
+                queue
+                    .deliver(typist, "bbbbbbbbbb".into(), true, true, |text| {
+                        copied.lock().unwrap().push(text.to_string())
+                    })
+                    .await
+            })
+        };
+
+        first.await.unwrap().unwrap();
+        second.await.unwrap().unwrap();
+
+        assert_eq!(*typist.typed.lock().unwrap(), "aaaaaaaaaabbbbbbbbbb");
+        assert_eq!(*copied.lock().unwrap(), vec!["aaaaaaaaaa", "bbbbbbbbbb"]);
+    }
+
+    #[tokio::test]
+    async fn test_copy_only_skips_typing() {
+        let queue = queue();
+        let typist = Arc::new(RecordingTypist::default());
+        let mut copied = None;
+
+        queue
+            .deliver(typist.clone(), "hello".into(), false, true, |text| {
+                copied = Some(text.to_string())
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(copied.as_deref(), Some("hello"));
+        assert!(typist.typed.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_elevated_target_is_copied_instead_of_typed() {
+        let queue = DeliveryQueue::with_elevation_check(Arc::new(Elevated(true)));
+        let typist = Arc::new(RecordingTypist::default());
+        let mut copied = None;
+
+        // Copied even though the clipboard option is off, or the text would be lost
+        let delivery = queue
+            .deliver(typist.clone(), "hello".into(), true, false, |text| {
+                copied = Some(text.to_string())
+            })
+            .await
+            .unwrap();
+
+        assert!(delivery.elevated_target);
+        assert_eq!(copied.as_deref(), Some("hello"));
+        assert!(typist.typed.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_elevation_is_ignored_without_typing() {
+        let queue = DeliveryQueue::with_elevation_check(Arc::new(Elevated(true)));
+        let typist = Arc::new(RecordingTypist::default());
+        let mut copied = None;
+
+        let delivery = queue
+            .deliver(typist, "hello".into(), false, false, |text| {
+                copied = Some(text.to_string())
+            })
+            .await
+            .unwrap();
+
+        assert!(!delivery.elevated_target);
+        assert_eq!(copied, None);
+    }
+
+    #[tokio::test]
+    async fn test_pending_counts_delivery_in_progress() {
+        let queue = Arc::new(queue());
+        assert_eq!(queue.pending(), 0);
+
+        // About 200 ms of typing
+        let delivery = {
+            let queue = queue.clone();
+            tokio::spawn(async move {
+                let typist = Arc::new(RecordingTypist::default());
+                queue
+                    .deliver(typist, "a".repeat(100), true, false, |_| {})
+                    .await
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(queue.pending(), 1);
+
+        delivery.await.unwrap().unwrap();
+        assert_eq!(queue.pending(), 0);
+    }
+}
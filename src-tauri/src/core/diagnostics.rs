@@ -0,0 +1,289 @@
+use std::{
+    future::Future,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+
+use crate::{
+    audio::Recorder,
+    input::InputStatus,
+    mic_access::{MicAccessCheck, SystemMicAccess},
+    settings::{resolve_api_key, LLMProvider, TranscriptionProvider},
+};
+
+use super::state::AppState;
+
+/// Every check gives up after this long, so the whole report stays within a few seconds
+const CHECK_TIMEOUT: Duration = Duration::from_secs(4);
+
+/// One line of the diagnostics report
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct DiagnosticCheck {
+    pub name: String,
+    pub ok: bool,
+    pub detail: String,
+}
+
+async fn timed<F>(name: impl Into<String>, check: F) -> DiagnosticCheck
+where
+    F: Future<Output = Result<String>>,
+{
+    let name = name.into();
+    let (ok, detail) = match tokio::time::timeout(CHECK_TIMEOUT, check).await {
+        Ok(Ok(detail)) => (true, detail),
+        Ok(Err(e)) => (false, format!("{e:#}")),
+        Err(_) => (
+            false,
+            format!("Timed out after {}s", CHECK_TIMEOUT.as_secs()),
+        ),
+    };
+    DiagnosticCheck { name, ok, detail }
+}
+
+/// Providers whose key is configured, with the key to check
+fn configured_keys(
+    openai: &str,
+    groq: &str,
+    elevenlabs: &str,
+) -> Vec<(TranscriptionProvider, String)> {
+    [
+        (TranscriptionProvider::OpenAI, openai),
+        (TranscriptionProvider::Groq, groq),
+        (TranscriptionProvider::ElevenLabs, elevenlabs),
+    ]
+    .into_iter()
+    .filter(|(_, key)| !key.trim().is_empty())
+    .map(|(provider, key)| (provider, key.trim().to_string()))
+    .collect()
+}
+
+fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    match secs {
+        0..=59 => format!("{secs}s ago"),
+        60..=3599 => format!("{}m ago", secs / 60),
+        3600..=86_399 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86_400),
+    }
+}
+
+/// Run all subsystem checks concurrently; a support request needs just this JSON
+pub async fn run(app: &AppHandle) -> Vec<DiagnosticCheck> {
+    let state: State<'_, AppState> = app.state();
+    let settings = state.current_settings().await;
+    let service = state.transcription();
+
+    let microphone = timed("microphone", async {
+        tauri::async_runtime::spawn_blocking(Recorder::probe_input_device)
+            .await
+            .map_err(|e| anyhow!("Device probe panicked: {e}"))?
+    });
+
+    let microphone_access = timed("microphone_access", async {
+        let blocked = tauri::async_runtime::spawn_blocking(|| SystemMicAccess.blocked())
+            .await
+            .map_err(|e| anyhow!("Access check panicked: {e}"))?;
+        match blocked {
+            None => Ok("Not muted or blocked by the system".to_string()),
+            Some(blocked) => Err(blocked.into()),
+        }
+    });
+
+    let keys = futures::future::join_all(
+        configured_keys(
+            &settings.api_key,
+            &settings.groq_api_key,
+            &settings.elevenlabs_api_key,
+        )
+        .into_iter()
+        .map(|(provider, key)| {
+            let service = &service;
+            timed(format!("{}_key", provider.id()), async move {
+                let key = resolve_api_key(&key)?;
+                service.check_api_key(&provider, &key).await?;
+                Ok("Key accepted".to_string())
+            })
+        }),
+    );
+
+    let ollama = async {
+        if settings.llm_provider != LLMProvider::Ollama {
+            return None;
+        }
+        Some(
+            timed("ollama_server", async {
+                service.check_ollama(&settings.ollama_base_url).await?;
+                Ok(format!("Reachable at {}", settings.ollama_base_url))
+            })
+            .await,
+        )
+    };
+
+    let streaming = timed("elevenlabs_streaming", async {
+        let connected = state.elevenlabs_streaming().is_connected().await;
+        Ok(if connected {
+            "Connected".to_string()
+        } else if settings.provider == TranscriptionProvider::ElevenLabs {
+            "Not connected (connects on the next dictation)".to_string()
+        } else {
+            "Not in use".to_string()
+        })
+    });
+
+    let hotkeys = timed("hotkeys", async {
+        let errors = state
+            .hotkey_errors()
+            .lock()
+            .map_err(|_| anyhow!("Failed to lock hotkey state"))?
+            .clone();
+        match errors {
+            None => Err(anyhow!("Hotkeys have not been registered yet")),
+            Some(errors) if errors.is_empty() => {
+                Ok(format!("Registered {}", settings.normalized_hotkey()))
+            }
+            Some(errors) => Err(anyhow!(errors.join("; "))),
+        }
+    });
+
+    let audio_thread = timed("audio_thread", async {
+        let panic = state
+            .audio_thread_panic()
+            .lock()
+            .map_err(|_| anyhow!("Failed to lock audio thread state"))?
+            .clone();
+        match panic {
+            None => Ok("No crashes".to_string()),
+            Some(panic) => Err(anyhow!(
+                "Panicked {}: {}",
+                format_age(panic.at.elapsed()),
+                panic.message
+            )),
+        }
+    });
+
+    let keyboard = timed("keyboard", async {
+        match state.transcription().keyboard().status() {
+            InputStatus::Ready => Ok("Keyboard emulator ready".to_string()),
+            InputStatus::Starting => Err(anyhow!("Keyboard emulator is still starting")),
+            InputStatus::Failed(error) => Err(anyhow!(error)),
+        }
+    });
+
+    let overlay = timed("overlay_window", async {
+        app.get_webview_window("overlay")
+            .map(|_| "Present".to_string())
+            .ok_or_else(|| anyhow!("Overlay window is missing"))
+    });
+
+    let log_directory = timed("log_directory", async {
+        let dir = app.path().app_log_dir()?;
+        tokio::fs::create_dir_all(&dir)
+            .await
+            .with_context(|| format!("Cannot create {}", dir.display()))?;
+        let probe = dir.join(".diagnostics-probe");
+        tokio::fs::write(&probe, b"ok")
+            .await
+            .with_context(|| format!("Cannot write to {}", dir.display()))?;
+        let _ = tokio::fs::remove_file(&probe).await;
+        Ok(format!("Writable: {}", dir.display()))
+    });
+
+    let settings_file = timed("settings_file", async {
+        let path = state.settings_path();
+        let metadata = tokio::fs::metadata(&path)
+            .await
+            .with_context(|| format!("{} not found (using defaults)", path.display()))?;
+        let age = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .map(|age| format!(", saved {}", format_age(age)))
+            .unwrap_or_default();
+        Ok(format!("{}{age}", path.display()))
+    });
+
+    let (
+        microphone,
+        microphone_access,
+        keys,
+        ollama,
+        streaming,
+        audio_thread,
+        hotkeys,
+        keyboard,
+        overlay,
+        log_directory,
+        settings_file,
+    ) = tokio::join!(
+        microphone,
+        microphone_access,
+        keys,
+        ollama,
+        streaming,
+        audio_thread,
+        hotkeys,
+        keyboard,
+        overlay,
+        log_directory,
+        settings_file
+    );
+
+    let mut checks = vec![microphone, microphone_access];
+    checks.extend(keys);
+    checks.extend(ollama);
+    checks.extend([
+        streaming,
+        audio_thread,
+        hotkeys,
+        keyboard,
+        overlay,
+        log_directory,
+        settings_file,
+    ]);
+    checks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_configured_keys_skips_empty() {
+        let keys = configured_keys("sk-test", "  ", "el-key ");
+        assert_eq!(
+            keys,
+            vec![
+                (TranscriptionProvider::OpenAI, "sk-test".to_string()),
+                (TranscriptionProvider::ElevenLabs, "el-key".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(Duration::from_secs(5)), "5s ago");
+        assert_eq!(format_age(Duration::from_secs(300)), "5m ago");
+        assert_eq!(format_age(Duration::from_secs(7200)), "2h ago");
+        assert_eq!(format_age(Duration::from_secs(3 * 86_400)), "3d ago");
+    }
+
+    #[tokio::test]
+    async fn test_timed_reports_errors() {
+        let failed = timed("failing", async { Err(anyhow!("boom")) }).await;
+        assert_eq!(
+            failed,
+            DiagnosticCheck {
+                name: "failing".into(),
+                ok: false,
+                detail: "boom".into(),
+            }
+        );
+
+        let passed = timed("passing", async { Ok("fine".to_string()) }).await;
+        assert!(passed.ok);
+        assert_eq!(passed.detail, "fine");
+    }
+}
@@ -0,0 +1,444 @@
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::PathBuf,
+    sync::{Mutex, PoisonError},
+};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local, Utc};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_clipboard_manager::ClipboardExt as _;
+
+use crate::{
+    messages::{self, Msg},
+    settings::{AppSettings, PromptPreset},
+    text_utils::{count_words, TextStats},
+};
+
+use super::{
+    events::{emit_error, emit_status, StatusPhase, EVENT_SESSION_CHANGED},
+    session::Translation,
+    state::{AppState, NewHistoryEntry},
+    transcription::{prepare_for_delivery, refinement_progress},
+    tray,
+};
+
+/// Name of the preset carrying `session_instructions` into the refinement job
+const SESSION_PRESET: &str = "Session";
+
+/// An utterance dictated during a session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Utterance {
+    pub at: DateTime<Utc>,
+    pub text: String,
+}
+
+/// A dictation session collecting utterances into one document, e.g. meeting minutes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DictationSession {
+    pub started_at: DateTime<Utc>,
+    pub utterances: Vec<Utterance>,
+}
+
+impl DictationSession {
+    pub fn new(started_at: DateTime<Utc>) -> Self {
+        Self {
+            started_at,
+            utterances: Vec::new(),
+        }
+    }
+
+    /// Add an utterance; blank ones are dropped. Returns the number collected so far.
+    pub fn push(&mut self, text: &str, at: DateTime<Utc>) -> usize {
+        let text = text.trim();
+        if !text.is_empty() {
+            self.utterances.push(Utterance {
+                at,
+                text: text.to_string(),
+            });
+        }
+        self.utterances.len()
+    }
+
+    /// The utterances in order, one paragraph each
+    pub fn document(&self) -> String {
+        self.utterances
+            .iter()
+            .map(|utterance| utterance.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    pub fn info(&self) -> SessionInfo {
+        SessionInfo {
+            active: true,
+            started_at: Some(self.started_at),
+            utterances: self.utterances.len(),
+            word_count: self
+                .utterances
+                .iter()
+                .map(|utterance| count_words(&utterance.text))
+                .sum(),
+            last_utterance_at: self.utterances.last().map(|utterance| utterance.at),
+        }
+    }
+}
+
+/// What `get_session`, `get_test_state` and `session://changed` report
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SessionInfo {
+    pub active: bool,
+    pub started_at: Option<DateTime<Utc>>,
+    pub utterances: usize,
+    pub word_count: usize,
+    pub last_utterance_at: Option<DateTime<Utc>>,
+}
+
+/// What `end_session` made of the session
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionSummary {
+    /// The combined document, as copied to the clipboard
+    pub text: String,
+    pub utterances: usize,
+    pub llm_applied: bool,
+    /// History entry of the document; None if it's empty or history is off
+    pub history_id: Option<u64>,
+    /// File the document was appended to
+    pub output_file: Option<String>,
+}
+
+/// The running session, if any. Kept apart from the provider clients and the
+/// dictation in progress, so switching providers or presets mid-session keeps it.
+#[derive(Default)]
+pub struct SessionBuffer {
+    session: Mutex<Option<DictationSession>>,
+}
+
+impl SessionBuffer {
+    /// Start a session; false if one is already running
+    pub fn start(&self, now: DateTime<Utc>) -> bool {
+        let mut session = self.session.lock().unwrap_or_else(PoisonError::into_inner);
+        if session.is_some() {
+            return false;
+        }
+        *session = Some(DictationSession::new(now));
+        true
+    }
+
+    /// Add `text` to the running session; the number of utterances collected, or
+    /// None without a session
+    pub fn capture(&self, text: &str, now: DateTime<Utc>) -> Option<usize> {
+        self.session
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_mut()
+            .map(|session| session.push(text, now))
+    }
+
+    /// End the running session and hand over what it collected
+    pub fn take(&self) -> Option<DictationSession> {
+        self.session
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+    }
+
+    pub fn info(&self) -> SessionInfo {
+        self.session
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+            .map(DictationSession::info)
+            .unwrap_or_default()
+    }
+}
+
+/// Settings for the LLM pass over a session document: `session_instructions` as the
+/// only instructions and no translation, which each utterance already had. None if
+/// the pass is turned off.
+pub fn document_settings(settings: &AppSettings) -> Option<AppSettings> {
+    let instructions = settings.session_instructions.trim();
+    if instructions.is_empty() {
+        return None;
+    }
+    let mut document = settings.clone();
+    document.auto_translate = false;
+    document.use_custom_instructions = true;
+    // First, so it wins over a preset of the user's with the same name
+    document.prompt_presets.insert(
+        0,
+        PromptPreset {
+            name: SESSION_PRESET.to_string(),
+            instructions: instructions.to_string(),
+            vocabulary: Vec::new(),
+        },
+    );
+    document.active_preset = SESSION_PRESET.to_string();
+    Some(document)
+}
+
+/// What a finished session adds to `session_output_file`: a heading with its local
+/// start and end time, then the document
+pub fn file_block(document: &str, started_at: DateTime<Utc>, ended_at: DateTime<Utc>) -> String {
+    let started = started_at.with_timezone(&Local);
+    let ended = ended_at.with_timezone(&Local);
+    format!(
+        "## {} – {}\n\n{}\n\n",
+        started.format("%Y-%m-%d %H:%M"),
+        ended.format("%H:%M"),
+        document.trim()
+    )
+}
+
+fn emit_changed(app: &AppHandle, info: &SessionInfo) {
+    if let Err(e) = app.emit(EVENT_SESSION_CHANGED, info) {
+        tracing::error!("[Session] Failed to emit session-changed event: {}", e);
+    }
+    tray::set_tray_session(app, info.active);
+}
+
+/// Start collecting utterances instead of (or besides) delivering them
+pub fn start(app: &AppHandle, state: &AppState) -> Result<SessionInfo> {
+    if !state.dictation_session().start(Utc::now()) {
+        return Err(anyhow!("{}", messages::text(Msg::SessionAlreadyRunning)));
+    }
+    tracing::info!("[Session] Dictation session started");
+    let info = state.dictation_session().info();
+    emit_changed(app, &info);
+    emit_status(
+        app,
+        StatusPhase::Idle,
+        Some(messages::text(Msg::SessionStarted)),
+    );
+    Ok(info)
+}
+
+/// Add a delivered utterance to the running session. Returns the number collected,
+/// or None without a session; the text is then delivered as usual.
+pub fn capture(app: &AppHandle, text: &str) -> Option<usize> {
+    let state = app.state::<AppState>();
+    let count = state.dictation_session().capture(text, Utc::now())?;
+    emit_changed(app, &state.dictation_session().info());
+    Some(count)
+}
+
+/// End the running session, or start one; the tray item does this
+pub async fn toggle(app: &AppHandle) -> Result<()> {
+    let state = app.state::<AppState>();
+    if state.dictation_session().info().active {
+        end(app, &state).await.map(|_| ())
+    } else {
+        start(app, &state).map(|_| ())
+    }
+}
+
+/// End the running session: refine the combined text if `session_instructions` are
+/// set (chunk by chunk when long), then save it as one history entry, copy it and
+/// append it to `session_output_file`. An utterance still being transcribed is
+/// delivered as usual.
+pub async fn end(app: &AppHandle, state: &AppState) -> Result<SessionSummary> {
+    let session = state
+        .dictation_session()
+        .take()
+        .ok_or_else(|| anyhow!("{}", messages::text(Msg::NoSessionRunning)))?;
+    let ended_at = Utc::now();
+    emit_changed(app, &SessionInfo::default());
+    let utterances = session.utterances.len();
+    tracing::info!(
+        "[Session] Dictation session ended with {} utterances",
+        utterances
+    );
+
+    let raw = session.document();
+    if raw.is_empty() {
+        emit_status(
+            app,
+            StatusPhase::Idle,
+            Some(messages::text(Msg::SessionEmpty)),
+        );
+        return Ok(SessionSummary {
+            text: raw,
+            utterances,
+            llm_applied: false,
+            history_id: None,
+            output_file: None,
+        });
+    }
+
+    let settings = state.current_settings().await;
+    emit_status(
+        app,
+        StatusPhase::Transcribing,
+        Some(messages::text(Msg::SessionCombining)),
+    );
+    let (text, llm_applied, refinement_failed, source_language) = match document_settings(&settings)
+    {
+        Some(document) => {
+            let refinement = state
+                .transcription()
+                .apply_refinement(&document, raw.clone(), &[], &refinement_progress(app))
+                .await;
+            match refinement {
+                Ok(refinement) => (
+                    refinement.text.trim().to_string(),
+                    refinement.llm_applied,
+                    refinement.refinement_failed,
+                    refinement.source_language,
+                ),
+                Err(err) => {
+                    // The utterances are gone from the buffer, so keep them unrefined
+                    tracing::warn!("[Session] Refining the session failed: {}", err);
+                    (raw.clone(), false, true, None)
+                }
+            }
+        }
+        None => (raw.clone(), false, false, None),
+    };
+
+    let history_id = if settings.history_enabled {
+        let stats = TextStats::measure(&text, None);
+        let translated = (llm_applied && text != raw).then(|| text.clone());
+        state
+            .add_history_entry(NewHistoryEntry {
+                original: raw,
+                translated,
+                source_language,
+                translation: Translation::default(),
+                transcription_provider: Some(format!("{:?}", settings.provider).to_lowercase()),
+                llm_provider: llm_applied
+                    .then(|| format!("{:?}", settings.llm_provider).to_lowercase()),
+                custom_instructions_used: llm_applied,
+                refinement_failed,
+                combined_translation: false,
+                change_count: 0,
+                latency_ms: None,
+                stats,
+                segments: Vec::new(),
+                capture: None,
+            })
+            .await
+            .map(|entry| entry.id)
+    } else {
+        None
+    };
+
+    let delivered = prepare_for_delivery(&settings, &text);
+    if let Err(err) = app.clipboard().write_text(delivered.clone()) {
+        emit_error(
+            app,
+            &messages::fill(Msg::ClipboardFailed, &[("error", &err)]),
+        );
+    }
+
+    let output_file = settings.session_output_file.trim();
+    let output_file = if output_file.is_empty() {
+        None
+    } else {
+        let path = PathBuf::from(output_file);
+        let block = file_block(&delivered, session.started_at, ended_at);
+        let written = tauri::async_runtime::spawn_blocking(move || {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .and_then(|mut file| file.write_all(block.as_bytes()))
+        })
+        .await
+        .map_err(std::io::Error::other)
+        .and_then(|result| result);
+        match written {
+            Ok(()) => Some(output_file.to_string()),
+            Err(err) => {
+                emit_error(
+                    app,
+                    &messages::fill(
+                        Msg::SessionFileFailed,
+                        &[("path", &output_file), ("error", &err)],
+                    ),
+                );
+                None
+            }
+        }
+    };
+
+    emit_status(
+        app,
+        StatusPhase::Success,
+        Some(&messages::fill(
+            Msg::SessionSaved,
+            &[("count", &utterances)],
+        )),
+    );
+    emit_status(app, StatusPhase::Idle, None);
+    Ok(SessionSummary {
+        text: delivered,
+        utterances,
+        llm_applied,
+        history_id,
+        output_file,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn at(minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 15, 9, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_session_collects_utterances_in_order() {
+        let buffer = SessionBuffer::default();
+        assert_eq!(buffer.capture("lost", at(0)), None);
+        assert!(buffer.start(at(0)));
+        assert!(!buffer.start(at(1)));
+
+        assert_eq!(buffer.capture(" Budget is approved. ", at(2)), Some(1));
+        assert_eq!(buffer.capture("  ", at(3)), Some(1));
+        assert_eq!(buffer.capture("Next call on Friday", at(4)), Some(2));
+        let info = buffer.info();
+        assert!(info.active);
+        assert_eq!(info.utterances, 2);
+        assert_eq!(info.word_count, 7);
+        assert_eq!(info.last_utterance_at, Some(at(4)));
+
+        let session = buffer.take().unwrap();
+        assert_eq!(session.started_at, at(0));
+        assert_eq!(
+            session.document(),
+            "Budget is approved.\n\nNext call on Friday"
+        );
+        assert_eq!(buffer.info(), SessionInfo::default());
+        assert!(buffer.take().is_none());
+    }
+
+    #[test]
+    fn test_document_settings_use_only_the_session_instructions() {
+        let mut settings = AppSettings {
+            auto_translate: true,
+            use_custom_instructions: false,
+            session_instructions: " Write minutes ".to_string(),
+            ..AppSettings::default()
+        };
+        let document = document_settings(&settings).unwrap();
+        assert!(!document.auto_translate);
+        assert_eq!(document.active_instructions(), Some("Write minutes"));
+        assert!(document.requires_llm());
+
+        settings.session_instructions.clear();
+        assert!(document_settings(&settings).is_none());
+    }
+
+    #[test]
+    fn test_file_block_has_a_heading_and_the_document() {
+        let block = file_block("Minutes\n", at(5), at(50));
+        let local = at(5).with_timezone(&Local);
+        assert!(block.starts_with(&format!("## {}", local.format("%Y-%m-%d %H:%M"))));
+        assert!(block.ends_with("\n\nMinutes\n\n"));
+    }
+}
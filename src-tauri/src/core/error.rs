@@ -1,11 +1,14 @@
 use serde::ser::SerializeMap;
 use serde::Serialize;
 
+use crate::{audio_file::AudioFileError, settings_vault::VaultError};
+
 #[derive(Debug, thiserror::Error)]
 pub enum CommandError {
     #[error("{0}")]
     Settings(#[from] crate::settings::SettingsValidationError),
 
+    // Provider and streaming clients report `anyhow::Error`, which converts via `Hotkey`
     #[error("{0}")]
     Hotkey(#[from] anyhow::Error),
 
@@ -16,12 +19,25 @@ pub enum CommandError {
     Lock(String),
 
     #[error("{0}")]
-    #[allow(dead_code)]
     NotFound(String),
 
+    /// A command argument outside what the command accepts
+    #[error("{0}")]
+    InvalidArgument(String),
+
     #[error("{0}")]
     #[allow(dead_code)] // Used in release builds (#[cfg(not(debug_assertions))])
     Unavailable(String),
+
+    #[error("{0}")]
+    UnsupportedAudio(String),
+
+    #[error("{0}")]
+    Vault(#[from] VaultError),
+
+    #[error("{0}")]
+    #[allow(dead_code)] // Only the debug-build test commands time out
+    Timeout(String),
 }
 
 impl CommandError {
@@ -32,13 +48,37 @@ impl CommandError {
             Self::Io(_) => "io",
             Self::Lock(_) => "lock",
             Self::NotFound(_) => "not_found",
+            Self::InvalidArgument(_) => "invalid_argument",
             Self::Unavailable(_) => "unavailable",
+            Self::UnsupportedAudio(_) => "unsupported_audio_format",
+            Self::Vault(VaultError::WrongPassphrase) => "wrong_passphrase",
+            Self::Vault(VaultError::Locked(_)) => "settings_locked",
+            Self::Vault(_) => "settings",
+            Self::Timeout(_) => "timeout",
+        }
+    }
+}
+
+/// A poisoned lock surfaces as `CommandError::Lock`
+impl<T> From<std::sync::PoisonError<T>> for CommandError {
+    fn from(err: std::sync::PoisonError<T>) -> Self {
+        Self::Lock(err.to_string())
+    }
+}
+
+impl From<AudioFileError> for CommandError {
+    fn from(err: AudioFileError) -> Self {
+        match err {
+            AudioFileError::Unsupported(_) => Self::UnsupportedAudio(err.to_string()),
+            other => Self::Io(other.to_string()),
         }
     }
 }
 
 // Tauri commands require the error type to implement Serialize.
 // We serialize as { code, message } so the frontend can branch on error type.
+// Commands used to reject with a plain string: `message` keeps that display text,
+// so callers only need to read `err.message` (see `errMsg` in main.js).
 impl Serialize for CommandError {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -50,3 +90,73 @@ impl Serialize for CommandError {
         map.end()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{settings::SettingsValidationError, settings_vault::SettingsLocked};
+
+    fn json(err: CommandError) -> serde_json::Value {
+        serde_json::to_value(err).unwrap()
+    }
+
+    #[test]
+    fn test_serializes_code_and_message() {
+        let cases = [
+            (
+                CommandError::from(SettingsValidationError::MissingHotkey),
+                "settings",
+            ),
+            (
+                CommandError::from(anyhow::anyhow!("Not connected")),
+                "hotkey",
+            ),
+            (CommandError::Io("disk full".into()), "io"),
+            (CommandError::Lock("audio streaming handle".into()), "lock"),
+            (
+                CommandError::NotFound("Main window not found".into()),
+                "not_found",
+            ),
+            (
+                CommandError::InvalidArgument("days must be at least 1".into()),
+                "invalid_argument",
+            ),
+            (
+                CommandError::Unavailable("release build".into()),
+                "unavailable",
+            ),
+            (
+                CommandError::from(AudioFileError::Unsupported(".ogg files".into())),
+                "unsupported_audio_format",
+            ),
+            (
+                CommandError::from(VaultError::WrongPassphrase),
+                "wrong_passphrase",
+            ),
+            (
+                CommandError::from(VaultError::from(SettingsLocked)),
+                "settings_locked",
+            ),
+        ];
+
+        for (err, code) in cases {
+            let message = err.to_string();
+            assert_eq!(
+                json(err),
+                serde_json::json!({ "code": code, "message": message })
+            );
+        }
+    }
+
+    #[test]
+    fn test_poisoned_lock_maps_to_lock() {
+        let mutex = std::sync::Mutex::new(());
+        let _ = std::panic::catch_unwind(|| {
+            let _guard = mutex.lock().unwrap();
+            panic!("poison");
+        });
+
+        let err: CommandError = mutex.lock().unwrap_err().into();
+        assert_eq!(json(err)["code"], "lock");
+    }
+}
@@ -1,58 +1,354 @@
-use serde::Serialize;
-use tauri::{AppHandle, Emitter, Manager};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Emitter, EventTarget, Manager};
 
-use super::state::AppState;
+use crate::{
+    audio::{CaptureInfo, MicMuted, NoAudioRecorded, NoMicrophone},
+    audio_file::AudioFileError,
+    diff::TextDiff,
+    elevenlabs_protocol::{ServerError, Word},
+    elevenlabs_streaming::ConnectTimeout,
+    messages::{self, Msg},
+    mic_access::MicBlocked,
+    settings::{AppSettings, SettingsValidationError, TranscriptionProvider},
+    settings_vault::SettingsLocked,
+    speech_to_text::{HttpStatusError, ProviderError},
+    text_utils::TextStats,
+};
 
+use super::{
+    notify,
+    overlay::{self, OVERLAY_LABEL},
+    session::Translation,
+    state::AppState,
+    status_watchdog,
+    timings::TimingsReport,
+    tray,
+};
+
+// Every event the backend emits. `manifest` describes their payloads, and
+// `frontend/events.js` is generated from it, so the webview never spells a name out.
 pub const EVENT_STATUS: &str = "transcription://status";
 pub const EVENT_PARTIAL: &str = "transcription://partial";
 pub const EVENT_COMPLETE: &str = "transcription://complete";
+/// A segment was delivered while the dictation goes on (`commit_segment`)
+pub const EVENT_SEGMENT: &str = "transcription://segment";
 pub const EVENT_SETTINGS_CHANGED: &str = "settings://changed";
+pub const EVENT_RECORDING_TICK: &str = "recording://tick";
+/// The overlay starts or stops taking the mouse so the user can drag it
+pub const EVENT_OVERLAY_INTERACTIVE: &str = "overlay://interactive";
+/// A dictation session started, collected an utterance or ended
+pub const EVENT_SESSION_CHANGED: &str = "session://changed";
+pub const EVENT_RETRANSCRIBE_PROGRESS: &str = "history://retranscribe-progress";
+pub const EVENT_UPDATE_AVAILABLE: &str = "update://available";
+pub const EVENT_UPDATE_PROGRESS: &str = "updater://progress";
+pub const EVENT_UPDATE_DOWNLOADED: &str = "update://downloaded";
+/// The downloaded update waits for the app to be idle before it is installed
+pub const EVENT_UPDATE_DEFERRED: &str = "updater://deferred";
+pub const EVENT_UPDATE_INSTALLED: &str = "update://installed";
+pub const EVENT_ELEVENLABS_SESSION_STARTED: &str = "elevenlabs://session-started";
+pub const EVENT_ELEVENLABS_TRANSCRIPT: &str = "elevenlabs://transcript";
+pub const EVENT_ELEVENLABS_ERROR: &str = "elevenlabs://error";
+pub const EVENT_ELEVENLABS_CONNECTION_CLOSED: &str = "elevenlabs://connection-closed";
+/// What happened to the streamed audio of an utterance, at its commit
+pub const EVENT_ELEVENLABS_AUDIO_STATS: &str = "elevenlabs://audio-stats";
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum StatusPhase {
     Idle,
+    /// Streaming connection is open but nothing is being recorded
+    Paused,
     Recording,
     Transcribing,
+    /// The text is being typed or pasted into the focused window
+    Delivering,
     Success,
     Error,
 }
 
 impl StatusPhase {
+    pub const ALL: [StatusPhase; 7] = [
+        StatusPhase::Idle,
+        StatusPhase::Paused,
+        StatusPhase::Recording,
+        StatusPhase::Transcribing,
+        StatusPhase::Delivering,
+        StatusPhase::Success,
+        StatusPhase::Error,
+    ];
+
     pub fn key(self) -> &'static str {
         match self {
             StatusPhase::Idle => "idle",
+            StatusPhase::Paused => "paused",
             StatusPhase::Recording => "recording",
             StatusPhase::Transcribing => "transcribing",
+            StatusPhase::Delivering => "delivering",
             StatusPhase::Success => "success",
             StatusPhase::Error => "error",
         }
     }
 
     pub fn default_message(self) -> &'static str {
-        match self {
-            StatusPhase::Idle => "Ready. Use the global hotkey to start a recording.",
-            StatusPhase::Recording => "Listening... release the hotkey to stop.",
-            StatusPhase::Transcribing => "Transcribing audio...",
-            StatusPhase::Success => "Transcription complete.",
-            StatusPhase::Error => "Something went wrong.",
-        }
+        messages::text(match self {
+            StatusPhase::Idle => Msg::StatusIdle,
+            StatusPhase::Paused => Msg::StatusPaused,
+            StatusPhase::Recording => Msg::StatusRecording,
+            StatusPhase::Transcribing => Msg::StatusTranscribing,
+            StatusPhase::Delivering => Msg::StatusDelivering,
+            StatusPhase::Success => Msg::StatusSuccess,
+            StatusPhase::Error => Msg::StatusError,
+        })
     }
 
     pub fn tray_label(self) -> &'static str {
         match self {
             StatusPhase::Idle => "Status: Idle",
+            StatusPhase::Paused => "Status: Paused",
             StatusPhase::Recording => "Status: Recording",
             StatusPhase::Transcribing => "Status: Transcribing",
+            StatusPhase::Delivering => "Status: Delivering",
             StatusPhase::Success => "Status: Complete",
             StatusPhase::Error => "Status: Error",
         }
     }
+
+    /// Whether a status may follow `self`. Idle and Error can always come next and
+    /// any phase may repeat with a new message; Success only follows Delivering, so
+    /// it is never shown before the text is in place.
+    pub fn can_precede(self, next: StatusPhase) -> bool {
+        use StatusPhase::*;
+        if self == next || matches!(next, Idle | Error) {
+            return true;
+        }
+        match self {
+            Idle | Paused | Success | Error => {
+                matches!(next, Recording | Transcribing | Delivering)
+            }
+            Recording => matches!(next, Paused | Transcribing),
+            // A new dictation may start while the last one is still processed
+            Transcribing => matches!(next, Recording | Delivering),
+            Delivering => matches!(next, Recording | Success),
+        }
+    }
+}
+
+/// Last emitted phase, to flag illegal sequences in debug builds
+#[cfg(debug_assertions)]
+static LAST_PHASE: std::sync::Mutex<Option<StatusPhase>> = std::sync::Mutex::new(None);
+
+#[cfg(debug_assertions)]
+fn check_transition(phase: StatusPhase) {
+    let Ok(mut last) = LAST_PHASE.lock() else {
+        return;
+    };
+    if let Some(previous) = last.replace(phase) {
+        if !previous.can_precede(phase) {
+            tracing::warn!(
+                "[Events] Unexpected status transition {} -> {}",
+                previous.key(),
+                phase.key()
+            );
+        }
+    }
+}
+
+/// Machine-readable reason attached to error status events, so the UI can pick a
+/// translated message instead of matching on the human-readable text
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// The selected transcription or LLM provider has no API key
+    MissingApiKey,
+    /// The API keys are encrypted and the settings haven't been unlocked yet
+    SettingsLocked,
+    /// No input device, or the microphone could not be opened
+    MicNotFound,
+    /// The microphone is hard-muted, so nothing may open it
+    MicMuted,
+    /// The OS would record silence: the microphone is muted or its access turned off
+    /// in the system settings
+    MicBlocked,
+    /// The recording finished without any samples
+    NoAudio,
+    /// A transcribed audio file is in a format or codec that can't be decoded
+    UnsupportedAudioFormat,
+    /// The provider rejected the API key (HTTP 401/403, ElevenLabs auth_error)
+    AuthFailed,
+    /// Too many requests (HTTP 429)
+    RateLimited,
+    /// The account ran out of credits (ElevenLabs quota_exceeded)
+    QuotaExceeded,
+    /// The provider could not be reached
+    Network,
+    /// Any other error response from a provider
+    ProviderError,
+    /// ElevenLabs did not confirm the commit in time
+    CommitTimeout,
+    /// The ElevenLabs WebSocket handshake did not finish in time
+    ConnectTimeout,
+    /// No result arrived within the transcribing time limit; reset by the status watchdog
+    TranscriptionTimeout,
+    /// The ElevenLabs streaming connection failed or reported an error
+    StreamingError,
+    /// LLM refinement failed and the failure policy aborts the dictation
+    RefinementFailed,
+    ClipboardFailed,
+    /// The keyboard emulator could not be initialized, so nothing can be typed
+    TypingFailed,
+    HotkeyRegistrationFailed,
+    SettingsSaveFailed,
+    /// The settings file could not be read; the app runs with defaults
+    SettingsLoadFailed,
+    AutostartFailed,
+    Unknown,
+}
+
+impl ErrorCode {
+    /// What the user can do about this error; `Retry` only if `can_retry`, i.e.
+    /// the failed recording was kept
+    pub fn actions(self, can_retry: bool) -> Vec<ErrorAction> {
+        use ErrorAction::*;
+        let actions: &[ErrorAction] = match self {
+            Self::MissingApiKey | Self::AuthFailed => &[OpenSettings, SwitchProvider],
+            Self::SettingsLocked | Self::HotkeyRegistrationFailed => &[OpenSettings],
+            Self::QuotaExceeded => &[SwitchProvider, OpenSettings],
+            Self::RateLimited
+            | Self::Network
+            | Self::CommitTimeout
+            | Self::ConnectTimeout
+            | Self::TranscriptionTimeout => &[Retry],
+            Self::ProviderError | Self::StreamingError => &[Retry, SwitchProvider],
+            Self::RefinementFailed => &[Retry, OpenSettings],
+            Self::MicNotFound
+            | Self::ClipboardFailed
+            | Self::TypingFailed
+            | Self::SettingsSaveFailed
+            | Self::SettingsLoadFailed
+            | Self::AutostartFailed
+            | Self::Unknown => &[ViewLogs],
+            Self::MicMuted | Self::MicBlocked | Self::NoAudio | Self::UnsupportedAudioFormat => &[],
+        };
+        actions
+            .iter()
+            .copied()
+            .filter(|&action| can_retry || action != Retry)
+            .collect()
+    }
+
+    /// Best-effort classification of an error bubbled up from a provider client
+    pub fn classify(err: &anyhow::Error) -> Self {
+        if err.chain().any(|c| c.is::<ConnectTimeout>()) {
+            return Self::ConnectTimeout;
+        }
+        if err.chain().any(|c| c.is::<MicMuted>()) {
+            return Self::MicMuted;
+        }
+        if err.chain().any(|c| c.is::<MicBlocked>()) {
+            return Self::MicBlocked;
+        }
+        if err.chain().any(|c| c.is::<SettingsLocked>()) {
+            return Self::SettingsLocked;
+        }
+        if let Some(e) = err.chain().find_map(|c| c.downcast_ref::<ServerError>()) {
+            return e.error_code();
+        }
+        if let Some(e) = err
+            .chain()
+            .find_map(|c| c.downcast_ref::<HttpStatusError>())
+        {
+            return Self::from_http_status(e.status.as_u16());
+        }
+        if let Some(e) = err.chain().find_map(|c| c.downcast_ref::<reqwest::Error>()) {
+            return match e.status() {
+                Some(status) => Self::from_http_status(status.as_u16()),
+                None => Self::Network,
+            };
+        }
+        if err.chain().any(|c| {
+            matches!(
+                c.downcast_ref::<ProviderError>(),
+                Some(ProviderError::MissingApiKey(_))
+            ) || matches!(
+                c.downcast_ref::<SettingsValidationError>(),
+                Some(
+                    SettingsValidationError::MissingApiKey(_)
+                        | SettingsValidationError::MissingRefinementKey(_)
+                        | SettingsValidationError::UnsetKeyVariable(_)
+                )
+            )
+        }) {
+            return Self::MissingApiKey;
+        }
+        if err.chain().any(|c| c.is::<NoMicrophone>()) {
+            return Self::MicNotFound;
+        }
+        if err.chain().any(|c| {
+            c.is::<NoAudioRecorded>()
+                || matches!(
+                    c.downcast_ref::<AudioFileError>(),
+                    Some(AudioFileError::Empty)
+                )
+        }) {
+            return Self::NoAudio;
+        }
+        Self::Unknown
+    }
+
+    fn from_http_status(status: u16) -> Self {
+        match status {
+            401 | 403 => Self::AuthFailed,
+            429 => Self::RateLimited,
+            _ => Self::ProviderError,
+        }
+    }
+}
+
+/// Something the user can do about an error, offered with it so the UI can show a
+/// button for it
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorAction {
+    /// Show the settings window (`show_settings`), e.g. to enter a key
+    OpenSettings,
+    /// Transcribe the failed recording again (`retry_last_transcription`)
+    Retry,
+    /// Pick another transcription provider (`set_provider`)
+    SwitchProvider,
+    /// Show the app log in the file manager (`reveal_log`)
+    ViewLogs,
+}
+
+impl ErrorAction {
+    pub const ALL: [ErrorAction; 4] = [
+        ErrorAction::OpenSettings,
+        ErrorAction::Retry,
+        ErrorAction::SwitchProvider,
+        ErrorAction::ViewLogs,
+    ];
+
+    pub fn key(self) -> &'static str {
+        match self {
+            ErrorAction::OpenSettings => "open_settings",
+            ErrorAction::Retry => "retry",
+            ErrorAction::SwitchProvider => "switch_provider",
+            ErrorAction::ViewLogs => "view_logs",
+        }
+    }
 }
 
 #[derive(Clone, Serialize)]
 struct StatusPayload<'a> {
     phase: &'static str,
+    /// Human-readable text; kept for listeners that predate `code`
     message: &'a str,
+    code: Option<ErrorCode>,
+    /// Provider involved in the error, e.g. "openai" or "elevenlabs"
+    provider: Option<&'a str>,
+    /// What the user can do about an error; empty for other phases
+    actions: Vec<ErrorAction>,
+    /// Seconds the provider asked to wait before a `retry`
+    retry_after_secs: Option<u64>,
 }
 
 #[derive(Clone, Serialize)]
@@ -60,19 +356,86 @@ struct TextPayload<'a> {
     text: &'a str,
 }
 
+#[derive(Clone, Serialize)]
+struct InterimPayload<'a> {
+    text: &'a str,
+    /// Transcript of the audio so far while still recording; replaced by the final one
+    interim: bool,
+}
+
+#[derive(Clone, Serialize)]
+struct OverlayInteractivePayload {
+    interactive: bool,
+}
+
+#[derive(Clone, Serialize)]
+struct RecordingTickPayload {
+    elapsed_secs: u64,
+    /// Seconds until the recording is stopped automatically; None without a limit
+    remaining_secs: Option<u64>,
+}
+
+/// A delivered dictation, for `emit_complete`
+#[derive(Clone, Serialize)]
+pub struct CompletePayload<'a> {
+    pub text: &'a str,
+    /// What refinement changed relative to the raw transcript; None if no LLM ran
+    pub diff: Option<&'a TextDiff>,
+    pub timings: Option<&'a TimingsReport>,
+    /// `word_count`, `char_count` and `words_per_minute` of `text`
+    #[serde(flatten)]
+    pub stats: &'a TextStats,
+    /// Typing was skipped because the focused window runs elevated; the text is
+    /// on the clipboard instead
+    pub elevated_target: bool,
+    /// `translated`, `translation_forced` and `target_language`
+    #[serde(flatten)]
+    pub translation: &'a Translation,
+    /// Microphone the dictation was recorded with, if known
+    pub capture: Option<&'a CaptureInfo>,
+}
+
 pub fn emit_status(app: &AppHandle, phase: StatusPhase, message: Option<&str>) {
+    emit_status_payload(app, phase, message, None, None, None);
+}
+
+fn emit_status_payload(
+    app: &AppHandle,
+    phase: StatusPhase,
+    message: Option<&str>,
+    code: Option<ErrorCode>,
+    provider: Option<&str>,
+    retry_after: Option<std::time::Duration>,
+) {
     let text = message.unwrap_or_else(|| phase.default_message());
+    #[cfg(debug_assertions)]
+    check_transition(phase);
+    let can_retry = app
+        .try_state::<AppState>()
+        .is_some_and(|state| state.has_retry_audio());
+    let actions = code.map_or_else(Vec::new, |code| code.actions(can_retry));
+    let retry_after_secs = retry_after
+        .filter(|_| actions.contains(&ErrorAction::Retry))
+        .map(|after| after.as_secs());
     if let Err(e) = app.emit(
         EVENT_STATUS,
         StatusPayload {
             phase: phase.key(),
             message: text,
+            code,
+            provider,
+            actions,
+            retry_after_secs,
         },
     ) {
         tracing::error!("[Events] Failed to emit status event: {}", e);
     }
 
     if let Some(state) = app.try_state::<AppState>() {
+        if matches!(phase, StatusPhase::Error) {
+            state.record_error(code.unwrap_or(ErrorCode::Unknown));
+        }
+        status_watchdog::track_phase(&state, phase);
         if let Ok(guard) = state.tray_status_item().lock() {
             if let Some(item) = guard.as_ref() {
                 if let Err(e) = item.set_text(phase.tray_label()) {
@@ -81,38 +444,1333 @@ pub fn emit_status(app: &AppHandle, phase: StatusPhase, message: Option<&str>) {
             }
         }
     }
+
+    tray::update_tray_icon(app, phase);
+}
+
+/// What an event puts on the overlay, each switched by one of the `overlay_show_*`
+/// settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OverlayContent {
+    Partials,
+    Final,
+    Levels,
+}
+
+impl OverlayContent {
+    fn shown(self, settings: &AppSettings) -> bool {
+        match self {
+            OverlayContent::Partials => settings.overlay_show_partials,
+            OverlayContent::Final => settings.overlay_show_final,
+            OverlayContent::Levels => settings.overlay_show_levels,
+        }
+    }
+}
+
+/// Whether an event carrying `content` may reach listener `target` under `settings`.
+/// Only the overlay window is ever left out.
+fn reaches(target: &EventTarget, content: OverlayContent, settings: &AppSettings) -> bool {
+    let overlay = match target {
+        EventTarget::AnyLabel { label }
+        | EventTarget::Window { label }
+        | EventTarget::Webview { label }
+        | EventTarget::WebviewWindow { label } => label == OVERLAY_LABEL,
+        _ => false,
+    };
+    !overlay || content.shown(settings)
+}
+
+/// Emit an event the overlay shows, leaving the overlay out if the settings hide
+/// `content` there. The overlay listens on its own window for these events, since
+/// a listener for any target would get them either way.
+fn emit_overlay_content<S: Serialize + Clone>(
+    app: &AppHandle,
+    event: &str,
+    content: OverlayContent,
+    payload: S,
+) -> tauri::Result<()> {
+    // Sent everywhere while the settings are being replaced
+    let settings = app
+        .try_state::<AppState>()
+        .and_then(|state| state.try_current_settings());
+    match settings {
+        Some(settings) if !content.shown(&settings) => {
+            app.emit_filter(event, payload, |target| reaches(target, content, &settings))
+        }
+        _ => app.emit(event, payload),
+    }
 }
 
 pub fn emit_partial(app: &AppHandle, text: &str) {
-    if let Err(e) = app.emit(EVENT_PARTIAL, TextPayload { text }) {
+    if let Err(e) = emit_overlay_content(
+        app,
+        EVENT_PARTIAL,
+        OverlayContent::Partials,
+        TextPayload { text },
+    ) {
         tracing::error!("[Events] Failed to emit partial event: {}", e);
     }
 }
 
-pub fn emit_complete(app: &AppHandle, text: &str) {
-    if let Err(e) = app.emit(EVENT_COMPLETE, TextPayload { text }) {
+/// Partial text from an interim transcription of a recording in progress
+pub fn emit_interim(app: &AppHandle, text: &str) {
+    if let Err(e) = emit_overlay_content(
+        app,
+        EVENT_PARTIAL,
+        OverlayContent::Partials,
+        InterimPayload {
+            text,
+            interim: true,
+        },
+    ) {
+        tracing::error!("[Events] Failed to emit interim partial event: {}", e);
+    }
+}
+
+pub fn emit_overlay_interactive(app: &AppHandle, interactive: bool) {
+    if let Err(e) = app.emit_to(
+        OVERLAY_LABEL,
+        EVENT_OVERLAY_INTERACTIVE,
+        OverlayInteractivePayload { interactive },
+    ) {
+        tracing::error!("[Events] Failed to emit overlay-interactive event: {}", e);
+    }
+}
+
+pub fn emit_recording_tick(app: &AppHandle, elapsed_secs: u64, remaining_secs: Option<u64>) {
+    if let Err(e) = emit_overlay_content(
+        app,
+        EVENT_RECORDING_TICK,
+        OverlayContent::Levels,
+        RecordingTickPayload {
+            elapsed_secs,
+            remaining_secs,
+        },
+    ) {
+        tracing::error!("[Events] Failed to emit recording tick: {}", e);
+    }
+}
+
+pub fn emit_complete(app: &AppHandle, payload: CompletePayload<'_>) {
+    let text = payload.text;
+    if let Err(e) = emit_overlay_content(app, EVENT_COMPLETE, OverlayContent::Final, payload) {
         tracing::error!("[Events] Failed to emit complete event: {}", e);
     }
+    if let Some(state) = app.try_state::<AppState>() {
+        state.record_transcript(text);
+    }
+    overlay::hide_overlay_after_complete(app);
+    notify::notify_complete(app, text);
+}
+
+#[derive(Clone, Serialize)]
+struct SegmentPayload<'a> {
+    text: &'a str,
+    #[serde(flatten)]
+    stats: &'a TextStats,
+    elevated_target: bool,
+}
+
+/// Like `emit_complete` for a segment: the recording goes on, so the status and the
+/// overlay are left alone
+pub fn emit_segment(app: &AppHandle, text: &str, stats: &TextStats, elevated_target: bool) {
+    if let Err(e) = app.emit(
+        EVENT_SEGMENT,
+        SegmentPayload {
+            text,
+            stats,
+            elevated_target,
+        },
+    ) {
+        tracing::error!("[Events] Failed to emit segment event: {}", e);
+    }
+    if let Some(state) = app.try_state::<AppState>() {
+        state.record_transcript(text);
+    }
 }
 
 pub fn emit_error(app: &AppHandle, message: &str) {
     emit_status(app, StatusPhase::Error, Some(message));
+    overlay::hide_overlay_after_error(app);
+    notify::notify_error(app, message);
+}
+
+/// Like `emit_error`, with a machine-readable code, the provider involved and what
+/// the user can do about it
+pub fn emit_error_code(app: &AppHandle, code: ErrorCode, provider: Option<&str>, message: &str) {
+    emit_error_payload(app, code, provider, message, None);
+}
+
+fn emit_error_payload(
+    app: &AppHandle,
+    code: ErrorCode,
+    provider: Option<&str>,
+    message: &str,
+    retry_after: Option<std::time::Duration>,
+) {
+    tracing::debug!(
+        "[Events] Error {:?} (provider: {:?}): {}",
+        code,
+        provider,
+        message
+    );
+    emit_status_payload(
+        app,
+        StatusPhase::Error,
+        Some(message),
+        Some(code),
+        provider,
+        retry_after,
+    );
+    overlay::hide_overlay_after_error(app);
+    notify::notify_error(app, message);
+}
+
+/// Emit an error bubbled up as `anyhow::Error`, classifying it into an `ErrorCode`
+pub fn emit_error_from(app: &AppHandle, err: &anyhow::Error, provider: Option<&str>) {
+    let retry_after = err
+        .chain()
+        .find_map(|c| c.downcast_ref::<HttpStatusError>())
+        .and_then(|e| e.retry_after);
+    emit_error_payload(
+        app,
+        ErrorCode::classify(err),
+        provider,
+        &err.to_string(),
+        retry_after,
+    );
+}
+
+/// `history://retranscribe-progress`, after each recording of a re-transcription batch
+#[derive(Debug, Clone, Serialize)]
+pub struct RetranscribeProgressPayload {
+    /// History entry whose recording was just processed
+    pub id: u64,
+    pub done: usize,
+    pub total: usize,
+    /// Why this recording has no new transcript; None on success
+    pub error: Option<String>,
+}
+
+/// `updater://progress`
+#[derive(Clone, Serialize)]
+pub struct UpdateProgressPayload {
+    pub downloaded: u64,
+    pub total: Option<u64>,
+}
+
+/// `elevenlabs://session-started`
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionStartedPayload {
+    pub session_id: String,
+}
+
+/// `elevenlabs://transcript`, partial or committed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StreamingTranscriptPayload {
+    pub text: String,
+    pub is_partial: bool,
+    /// Word timings of a `committed_transcript_with_timestamps`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub words: Option<Vec<Word>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language_code: Option<String>,
+}
+
+/// `elevenlabs://error`
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamingErrorPayload {
+    pub error: String,
+    pub code: ErrorCode,
+    /// The error message from the server, if the server reported it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_error: Option<ServerError>,
+}
+
+/// `elevenlabs://connection-closed`
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionClosedPayload {
+    pub code: u16,
+    pub reason: String,
 }
 
 #[derive(Clone, Serialize)]
 pub struct SettingsChangedPayload {
+    pub provider: TranscriptionProvider,
+    pub model: String,
     pub auto_translate: bool,
     pub target_language: String,
+    pub active_preset: String,
 }
 
-pub fn emit_settings_changed(app: &AppHandle, auto_translate: bool, target_language: &str) {
+pub fn emit_settings_changed(app: &AppHandle, settings: &AppSettings) {
     if let Err(e) = app.emit(
         EVENT_SETTINGS_CHANGED,
         SettingsChangedPayload {
-            auto_translate,
-            target_language: target_language.to_string(),
+            provider: settings.provider,
+            model: settings.model.clone(),
+            auto_translate: settings.auto_translate,
+            target_language: settings.target_language.clone(),
+            active_preset: settings.active_preset.clone(),
         },
     ) {
         tracing::error!("[Events] Failed to emit settings-changed event: {}", e);
     }
+
+    tray::sync_tray_menu(app, settings);
+}
+
+/// An event the backend emits, as listed by the `events_manifest` command
+#[derive(Debug, Clone, Serialize)]
+pub struct EventSpec {
+    /// The constant's name without `EVENT_`, as in `EVENTS` of `frontend/events.js`
+    pub key: &'static str,
+    pub name: &'static str,
+    /// JSON schema of the payload
+    pub payload: Value,
+}
+
+/// Schema of an object with exactly `properties`, of which `required` are always there
+fn object(properties: Value, required: &[&str]) -> Value {
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false,
+    })
+}
+
+/// `schema`, or null
+fn nullable(mut schema: Value) -> Value {
+    let kind = schema["type"].take();
+    schema["type"] = json!([kind, "null"]);
+    schema
+}
+
+fn of_type(kind: &str) -> Value {
+    json!({ "type": kind })
+}
+
+/// The properties `TextStats` adds to a payload it's flattened into
+fn text_stats_properties() -> [(&'static str, Value); 3] {
+    [
+        ("word_count", of_type("integer")),
+        ("char_count", of_type("integer")),
+        ("words_per_minute", nullable(of_type("number"))),
+    ]
+}
+
+fn with_text_stats(mut properties: Value) -> Value {
+    for (name, schema) in text_stats_properties() {
+        properties[name] = schema;
+    }
+    properties
+}
+
+/// Every event the backend emits, with the schema of its payload
+pub fn manifest() -> Vec<EventSpec> {
+    let phases: Vec<&str> = StatusPhase::ALL.iter().map(|phase| phase.key()).collect();
+    let actions: Vec<&str> = ErrorAction::ALL.iter().map(|action| action.key()).collect();
+    let providers: Vec<Value> = TranscriptionProvider::ALL
+        .iter()
+        .map(|p| json!(p))
+        .collect();
+    let diff = object(
+        json!({
+            "ops": {
+                "type": "array",
+                "items": object(
+                    json!({
+                        "op": { "enum": ["equal", "insert", "delete"] },
+                        "text": of_type("string"),
+                    }),
+                    &["op", "text"],
+                ),
+            },
+            "changes": of_type("integer"),
+        }),
+        &["ops", "changes"],
+    );
+    let timings = object(
+        json!({
+            "transcribed_ms": nullable(of_type("integer")),
+            "refined_ms": nullable(of_type("integer")),
+            "delivered_ms": nullable(of_type("integer")),
+            "total_ms": of_type("integer"),
+            "preconnect_saved_ms": nullable(of_type("integer")),
+        }),
+        &[
+            "transcribed_ms",
+            "refined_ms",
+            "delivered_ms",
+            "total_ms",
+            "preconnect_saved_ms",
+        ],
+    );
+    let capture = object(
+        json!({
+            "device": of_type("string"),
+            "sample_rate": of_type("integer"),
+            "channels": of_type("integer"),
+            "resampled": of_type("boolean"),
+            "agc": of_type("boolean"),
+            "noise_gate": of_type("boolean"),
+        }),
+        &[
+            "device",
+            "sample_rate",
+            "channels",
+            "resampled",
+            "agc",
+            "noise_gate",
+        ],
+    );
+    let word = object(
+        json!({
+            "text": of_type("string"),
+            "start": of_type("number"),
+            "end": of_type("number"),
+            "type": of_type("string"),
+            "speaker_id": of_type("string"),
+            "logprob": of_type("number"),
+        }),
+        &["text", "start", "end", "type"],
+    );
+    let server_error = object(
+        json!({
+            "kind": of_type("string"),
+            "message": of_type("string"),
+            "details": of_type("object"),
+        }),
+        &["kind", "message"],
+    );
+    let audio_counts = [
+        "captured_chunks",
+        "sent_chunks",
+        "voiced_chunks",
+        "bytes_sent",
+        "dropped_buffer_full",
+        "dropped_socket_behind",
+        "ignored_gate_closed",
+    ];
+
+    vec![
+        EventSpec {
+            key: "STATUS",
+            name: EVENT_STATUS,
+            payload: object(
+                json!({
+                    "phase": { "enum": phases },
+                    "message": of_type("string"),
+                    "code": nullable(of_type("string")),
+                    "provider": nullable(of_type("string")),
+                    "actions": { "type": "array", "items": { "enum": actions } },
+                    "retry_after_secs": nullable(of_type("integer")),
+                }),
+                &[
+                    "phase",
+                    "message",
+                    "code",
+                    "provider",
+                    "actions",
+                    "retry_after_secs",
+                ],
+            ),
+        },
+        EventSpec {
+            key: "PARTIAL",
+            name: EVENT_PARTIAL,
+            payload: object(
+                json!({ "text": of_type("string"), "interim": of_type("boolean") }),
+                &["text"],
+            ),
+        },
+        EventSpec {
+            key: "COMPLETE",
+            name: EVENT_COMPLETE,
+            payload: object(
+                with_text_stats(json!({
+                    "text": of_type("string"),
+                    "diff": nullable(diff),
+                    "timings": nullable(timings),
+                    "elevated_target": of_type("boolean"),
+                    "translated": of_type("boolean"),
+                    "translation_forced": of_type("boolean"),
+                    "target_language": nullable(of_type("string")),
+                    "capture": nullable(capture),
+                })),
+                &[
+                    "text",
+                    "diff",
+                    "timings",
+                    "word_count",
+                    "char_count",
+                    "words_per_minute",
+                    "elevated_target",
+                    "translated",
+                    "translation_forced",
+                    "target_language",
+                    "capture",
+                ],
+            ),
+        },
+        EventSpec {
+            key: "SEGMENT",
+            name: EVENT_SEGMENT,
+            payload: object(
+                with_text_stats(json!({
+                    "text": of_type("string"),
+                    "elevated_target": of_type("boolean"),
+                })),
+                &[
+                    "text",
+                    "word_count",
+                    "char_count",
+                    "words_per_minute",
+                    "elevated_target",
+                ],
+            ),
+        },
+        // The settings window emits it too, with an empty payload
+        EventSpec {
+            key: "SETTINGS_CHANGED",
+            name: EVENT_SETTINGS_CHANGED,
+            payload: object(
+                json!({
+                    "provider": { "enum": providers },
+                    "model": of_type("string"),
+                    "auto_translate": of_type("boolean"),
+                    "target_language": of_type("string"),
+                    "active_preset": of_type("string"),
+                }),
+                &[],
+            ),
+        },
+        EventSpec {
+            key: "RECORDING_TICK",
+            name: EVENT_RECORDING_TICK,
+            payload: object(
+                json!({
+                    "elapsed_secs": of_type("integer"),
+                    "remaining_secs": nullable(of_type("integer")),
+                }),
+                &["elapsed_secs", "remaining_secs"],
+            ),
+        },
+        EventSpec {
+            key: "OVERLAY_INTERACTIVE",
+            name: EVENT_OVERLAY_INTERACTIVE,
+            payload: object(
+                json!({ "interactive": of_type("boolean") }),
+                &["interactive"],
+            ),
+        },
+        EventSpec {
+            key: "SESSION_CHANGED",
+            name: EVENT_SESSION_CHANGED,
+            payload: object(
+                json!({
+                    "active": of_type("boolean"),
+                    "started_at": nullable(of_type("string")),
+                    "utterances": of_type("integer"),
+                    "word_count": of_type("integer"),
+                    "last_utterance_at": nullable(of_type("string")),
+                }),
+                &[
+                    "active",
+                    "started_at",
+                    "utterances",
+                    "word_count",
+                    "last_utterance_at",
+                ],
+            ),
+        },
+        EventSpec {
+            key: "RETRANSCRIBE_PROGRESS",
+            name: EVENT_RETRANSCRIBE_PROGRESS,
+            payload: object(
+                json!({
+                    "id": of_type("integer"),
+                    "done": of_type("integer"),
+                    "total": of_type("integer"),
+                    "error": nullable(of_type("string")),
+                }),
+                &["id", "done", "total", "error"],
+            ),
+        },
+        EventSpec {
+            key: "UPDATE_AVAILABLE",
+            name: EVENT_UPDATE_AVAILABLE,
+            payload: of_type("string"),
+        },
+        EventSpec {
+            key: "UPDATE_PROGRESS",
+            name: EVENT_UPDATE_PROGRESS,
+            payload: object(
+                json!({
+                    "downloaded": of_type("integer"),
+                    "total": nullable(of_type("integer")),
+                }),
+                &["downloaded", "total"],
+            ),
+        },
+        EventSpec {
+            key: "UPDATE_DOWNLOADED",
+            name: EVENT_UPDATE_DOWNLOADED,
+            payload: of_type("string"),
+        },
+        EventSpec {
+            key: "UPDATE_DEFERRED",
+            name: EVENT_UPDATE_DEFERRED,
+            payload: of_type("string"),
+        },
+        EventSpec {
+            key: "UPDATE_INSTALLED",
+            name: EVENT_UPDATE_INSTALLED,
+            payload: of_type("string"),
+        },
+        EventSpec {
+            key: "ELEVENLABS_SESSION_STARTED",
+            name: EVENT_ELEVENLABS_SESSION_STARTED,
+            payload: object(json!({ "session_id": of_type("string") }), &["session_id"]),
+        },
+        EventSpec {
+            key: "ELEVENLABS_TRANSCRIPT",
+            name: EVENT_ELEVENLABS_TRANSCRIPT,
+            payload: object(
+                json!({
+                    "text": of_type("string"),
+                    "is_partial": of_type("boolean"),
+                    "words": { "type": "array", "items": word },
+                    "language_code": of_type("string"),
+                }),
+                &["text", "is_partial"],
+            ),
+        },
+        EventSpec {
+            key: "ELEVENLABS_ERROR",
+            name: EVENT_ELEVENLABS_ERROR,
+            payload: object(
+                json!({
+                    "error": of_type("string"),
+                    "code": of_type("string"),
+                    "server_error": server_error,
+                }),
+                &["error", "code"],
+            ),
+        },
+        EventSpec {
+            key: "ELEVENLABS_CONNECTION_CLOSED",
+            name: EVENT_ELEVENLABS_CONNECTION_CLOSED,
+            payload: object(
+                json!({ "code": of_type("integer"), "reason": of_type("string") }),
+                &["code", "reason"],
+            ),
+        },
+        EventSpec {
+            key: "ELEVENLABS_AUDIO_STATS",
+            name: EVENT_ELEVENLABS_AUDIO_STATS,
+            payload: object(
+                audio_counts
+                    .iter()
+                    .map(|&name| (name.to_string(), of_type("integer")))
+                    .collect(),
+                &audio_counts,
+            ),
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        audio_stream::AudioStats,
+        core::dictation_session::{DictationSession, SessionInfo},
+        elevenlabs_protocol::{ServerErrorKind, WordKind},
+        messages::Language,
+    };
+
+    #[test]
+    fn test_status_payload_serialization() {
+        let payload = StatusPayload {
+            phase: StatusPhase::Error.key(),
+            message: "Groq responded with 429 Too Many Requests: slow down",
+            code: Some(ErrorCode::RateLimited),
+            provider: Some("groq"),
+            actions: ErrorCode::RateLimited.actions(true),
+            retry_after_secs: Some(20),
+        };
+        assert_eq!(
+            serde_json::to_value(&payload).unwrap(),
+            serde_json::json!({
+                "phase": "error",
+                "message": "Groq responded with 429 Too Many Requests: slow down",
+                "code": "rate_limited",
+                "provider": "groq",
+                "actions": ["retry"],
+                "retry_after_secs": 20,
+            })
+        );
+    }
+
+    #[test]
+    fn test_status_payload_without_code_keeps_fields() {
+        let payload = StatusPayload {
+            phase: StatusPhase::Idle.key(),
+            message: StatusPhase::Idle.default_message(),
+            code: None,
+            provider: None,
+            actions: Vec::new(),
+            retry_after_secs: None,
+        };
+        let value = serde_json::to_value(&payload).unwrap();
+        assert_eq!(value["phase"], "idle");
+        assert!(value["code"].is_null());
+        assert!(value["provider"].is_null());
+        assert_eq!(value["actions"], json!([]));
+    }
+
+    #[test]
+    fn test_error_actions() {
+        use ErrorAction::*;
+        assert_eq!(
+            ErrorCode::MissingApiKey.actions(true),
+            [OpenSettings, SwitchProvider]
+        );
+        assert_eq!(ErrorCode::Network.actions(true), [Retry]);
+        // Nothing to retry with once the recording is gone
+        assert!(ErrorCode::Network.actions(false).is_empty());
+        assert_eq!(ErrorCode::ProviderError.actions(false), [SwitchProvider]);
+        assert_eq!(ErrorCode::Unknown.actions(true), [ViewLogs]);
+        assert!(ErrorCode::MicMuted.actions(true).is_empty());
+        for action in ErrorAction::ALL {
+            assert_eq!(json!(action), json!(action.key()));
+        }
+    }
+
+    #[test]
+    fn test_retry_after_travels_with_the_error() {
+        let err = anyhow::Error::new(HttpStatusError {
+            provider: "Groq",
+            status: reqwest::StatusCode::TOO_MANY_REQUESTS,
+            body: Some("slow down".into()),
+            retry_after: Some(Duration::from_secs(30)),
+        })
+        .context("Groq transcription failed");
+        assert_eq!(ErrorCode::classify(&err), ErrorCode::RateLimited);
+        assert_eq!(
+            err.chain()
+                .find_map(|c| c.downcast_ref::<HttpStatusError>())
+                .and_then(|e| e.retry_after)
+                .map(|after| after.as_secs()),
+            Some(30)
+        );
+    }
+
+    #[test]
+    fn test_error_code_serialization() {
+        let codes = [
+            (ErrorCode::MissingApiKey, "missing_api_key"),
+            (ErrorCode::MicNotFound, "mic_not_found"),
+            (ErrorCode::CommitTimeout, "commit_timeout"),
+            (ErrorCode::ConnectTimeout, "connect_timeout"),
+            (ErrorCode::TranscriptionTimeout, "transcription_timeout"),
+            (
+                ErrorCode::HotkeyRegistrationFailed,
+                "hotkey_registration_failed",
+            ),
+        ];
+        for (code, expected) in codes {
+            assert_eq!(serde_json::to_value(code).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_classify_connect_timeout() {
+        let err = anyhow::Error::from(ConnectTimeout(std::time::Duration::from_secs(8)))
+            .context("Reconnection failed");
+        assert_eq!(ErrorCode::classify(&err), ErrorCode::ConnectTimeout);
+    }
+
+    #[test]
+    fn test_classify_settings_locked() {
+        let err = anyhow::Error::from(SettingsLocked).context("Transcription failed");
+        assert_eq!(ErrorCode::classify(&err), ErrorCode::SettingsLocked);
+        assert_eq!(
+            serde_json::to_value(ErrorCode::SettingsLocked).unwrap(),
+            "settings_locked"
+        );
+    }
+
+    #[test]
+    fn test_classify_mic_muted() {
+        let err = anyhow::Error::from(MicMuted).context("Failed to open gate");
+        assert_eq!(ErrorCode::classify(&err), ErrorCode::MicMuted);
+        assert_eq!(
+            serde_json::to_value(ErrorCode::MicMuted).unwrap(),
+            "mic_muted"
+        );
+
+        let err = anyhow::Error::from(MicBlocked::PrivacyDenied).context("Failed to open gate");
+        assert_eq!(ErrorCode::classify(&err), ErrorCode::MicBlocked);
+        assert_eq!(
+            serde_json::to_value(ErrorCode::MicBlocked).unwrap(),
+            "mic_blocked"
+        );
+    }
+
+    #[test]
+    fn test_classify_elevenlabs_server_error() {
+        let error = match crate::elevenlabs_protocol::Inbound::parse(
+            r#"{"message_type":"auth_error","error":"Invalid API key"}"#,
+        )
+        .unwrap()
+        {
+            crate::elevenlabs_protocol::Inbound::Error(error) => error,
+            other => panic!("expected an error, got {:?}", other),
+        };
+        let err = anyhow::Error::from(error).context("ElevenLabs transcription failed");
+        assert_eq!(ErrorCode::classify(&err), ErrorCode::AuthFailed);
+    }
+
+    /// First illegal step of `phases`, if any
+    fn illegal_step(phases: &[StatusPhase]) -> Option<(StatusPhase, StatusPhase)> {
+        phases
+            .windows(2)
+            .map(|pair| (pair[0], pair[1]))
+            .find(|(from, to)| !from.can_precede(*to))
+    }
+
+    #[test]
+    fn test_overlay_routing() {
+        let overlay = EventTarget::WebviewWindow {
+            label: OVERLAY_LABEL.to_string(),
+        };
+        let main = EventTarget::WebviewWindow {
+            label: "main".to_string(),
+        };
+        let settings = AppSettings {
+            overlay_show_partials: false,
+            ..AppSettings::default()
+        };
+
+        assert!(!reaches(&overlay, OverlayContent::Partials, &settings));
+        assert!(!reaches(
+            &EventTarget::labeled(OVERLAY_LABEL),
+            OverlayContent::Partials,
+            &settings
+        ));
+        assert!(reaches(&main, OverlayContent::Partials, &settings));
+        assert!(reaches(
+            &EventTarget::App,
+            OverlayContent::Partials,
+            &settings
+        ));
+        assert!(reaches(&overlay, OverlayContent::Final, &settings));
+        assert!(reaches(&overlay, OverlayContent::Levels, &settings));
+
+        let defaults = AppSettings::default();
+        for content in [
+            OverlayContent::Partials,
+            OverlayContent::Final,
+            OverlayContent::Levels,
+        ] {
+            assert!(reaches(&overlay, content, &defaults));
+        }
+    }
+
+    #[test]
+    fn test_phase_transitions() {
+        use StatusPhase::*;
+        // Upload, streaming with a pause, and a repeated delivery
+        assert_eq!(
+            illegal_step(&[
+                Idle,
+                Recording,
+                Transcribing,
+                Transcribing,
+                Delivering,
+                Success,
+                Idle
+            ]),
+            None
+        );
+        assert_eq!(
+            illegal_step(&[
+                Idle,
+                Recording,
+                Paused,
+                Recording,
+                Transcribing,
+                Error,
+                Idle
+            ]),
+            None
+        );
+        assert_eq!(illegal_step(&[Success, Delivering, Success]), None);
+
+        // Success before the text is delivered
+        assert_eq!(
+            illegal_step(&[Recording, Transcribing, Success]),
+            Some((Transcribing, Success))
+        );
+        assert_eq!(illegal_step(&[Idle, Success]), Some((Idle, Success)));
+        assert_eq!(
+            illegal_step(&[Recording, Delivering]),
+            Some((Recording, Delivering))
+        );
+        assert_eq!(illegal_step(&[Paused, Success]), Some((Paused, Success)));
+    }
+
+    #[test]
+    fn test_classify_typed_errors() {
+        use reqwest::StatusCode;
+
+        let classify =
+            |err: anyhow::Error| ErrorCode::classify(&err.context("Transcription failed"));
+        let http = |status| anyhow::Error::new(HttpStatusError::new("OpenAI", status));
+        assert_eq!(
+            classify(ProviderError::MissingApiKey("OpenAI").into_anyhow()),
+            ErrorCode::MissingApiKey
+        );
+        assert_eq!(
+            classify(SettingsValidationError::UnsetKeyVariable("OPENAI_API_KEY".into()).into()),
+            ErrorCode::MissingApiKey
+        );
+        assert_eq!(
+            classify(SettingsValidationError::MissingRefinementKey("Groq").into()),
+            ErrorCode::MissingApiKey
+        );
+        assert_eq!(classify(NoMicrophone.into()), ErrorCode::MicNotFound);
+        assert_eq!(
+            classify(http(StatusCode::UNAUTHORIZED)),
+            ErrorCode::AuthFailed
+        );
+        assert_eq!(
+            classify(http(StatusCode::TOO_MANY_REQUESTS)),
+            ErrorCode::RateLimited
+        );
+        assert_eq!(
+            classify(http(StatusCode::INTERNAL_SERVER_ERROR)),
+            ErrorCode::ProviderError
+        );
+        assert_eq!(classify(NoAudioRecorded.into()), ErrorCode::NoAudio);
+        assert_eq!(classify(AudioFileError::Empty.into()), ErrorCode::NoAudio);
+        // Text alone no longer decides the code, in any language
+        assert_eq!(
+            classify(anyhow::anyhow!("OpenAI responded with 401 Unauthorized")),
+            ErrorCode::Unknown
+        );
+        assert_eq!(
+            classify(anyhow::anyhow!(messages::text_in(
+                Msg::NoMicrophone,
+                Language::Ru
+            ))),
+            ErrorCode::Unknown
+        );
+    }
+
+    /// Why `value` doesn't match `schema`; handles the subset `manifest` uses
+    fn schema_violation(schema: &Value, value: &Value, path: &str) -> Option<String> {
+        if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+            if !allowed.contains(value) {
+                return Some(format!("{path}: {value} is not one of {allowed:?}"));
+            }
+        }
+        if let Some(kind) = schema.get("type") {
+            let kinds: Vec<&str> = match kind {
+                Value::Array(kinds) => kinds.iter().filter_map(Value::as_str).collect(),
+                kind => kind.as_str().into_iter().collect(),
+            };
+            let matches = |kind: &str| match kind {
+                "null" => value.is_null(),
+                "boolean" => value.is_boolean(),
+                "integer" => value.is_u64() || value.is_i64(),
+                "number" => value.is_number(),
+                "string" => value.is_string(),
+                "array" => value.is_array(),
+                "object" => value.is_object(),
+                other => panic!("{path}: unsupported schema type {other}"),
+            };
+            if !kinds.iter().any(|&kind| matches(kind)) {
+                return Some(format!("{path}: {value} is not {kinds:?}"));
+            }
+        }
+        if let Some(object) = value.as_object() {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for name in schema["required"].as_array().into_iter().flatten() {
+                let name = name.as_str().unwrap();
+                if !object.contains_key(name) {
+                    return Some(format!("{path}: missing {name}"));
+                }
+            }
+            for (name, field) in object {
+                let field_path = format!("{path}.{name}");
+                match properties.and_then(|properties| properties.get(name)) {
+                    Some(field_schema) => {
+                        if let Some(violation) = schema_violation(field_schema, field, &field_path)
+                        {
+                            return Some(violation);
+                        }
+                    }
+                    None if schema["additionalProperties"] == false => {
+                        return Some(format!("{field_path} is not in the schema"));
+                    }
+                    None => {}
+                }
+            }
+        }
+        if let (Some(items), Some(values)) = (schema.get("items"), value.as_array()) {
+            for (i, item) in values.iter().enumerate() {
+                if let Some(violation) = schema_violation(items, item, &format!("{path}[{i}]")) {
+                    return Some(violation);
+                }
+            }
+        }
+        None
+    }
+
+    /// Payloads as the emit sites build them, with optional fields both set and unset
+    fn sample_payloads() -> Vec<(&'static str, Value)> {
+        let stats = TextStats::measure("hello brave world", Some(Duration::from_secs(2)));
+        let diff = crate::diff::word_diff("hello world", "Hello, brave world.");
+        let timings = TimingsReport {
+            transcribed_ms: Some(800),
+            refined_ms: None,
+            delivered_ms: Some(1200),
+            total_ms: 1300,
+            preconnect_saved_ms: None,
+        };
+        let server_error = ServerError {
+            kind: ServerErrorKind::AuthError,
+            message: "Invalid API key".to_string(),
+            details: serde_json::Map::from_iter([("code".to_string(), json!(401))]),
+        };
+        let word = Word {
+            text: "hello".to_string(),
+            start: 0.1,
+            end: 0.4,
+            kind: WordKind::Word,
+            speaker_id: Some("speaker_0".to_string()),
+            logprob: Some(-0.2),
+        };
+        let mut session = DictationSession::new(chrono::Utc::now());
+        session.push("Budget approved", chrono::Utc::now());
+        let mut samples = vec![
+            (EVENT_PARTIAL, value(&TextPayload { text: "hel" })),
+            (
+                EVENT_PARTIAL,
+                value(&InterimPayload {
+                    text: "hello so far",
+                    interim: true,
+                }),
+            ),
+            (
+                EVENT_COMPLETE,
+                value(&CompletePayload {
+                    text: "Hello, brave world.",
+                    diff: Some(&diff),
+                    timings: Some(&timings),
+                    stats: &stats,
+                    elevated_target: false,
+                    translation: &Translation {
+                        translated: true,
+                        translation_forced: true,
+                        target_language: Some("English".to_string()),
+                    },
+                    capture: Some(&CaptureInfo {
+                        device: "Headset".to_string(),
+                        sample_rate: 48_000,
+                        channels: 1,
+                        agc: true,
+                        ..CaptureInfo::default()
+                    }),
+                }),
+            ),
+            (
+                EVENT_COMPLETE,
+                value(&CompletePayload {
+                    text: "",
+                    diff: None,
+                    timings: None,
+                    stats: &TextStats::default(),
+                    elevated_target: true,
+                    translation: &Translation::default(),
+                    capture: None,
+                }),
+            ),
+            (
+                EVENT_SEGMENT,
+                value(&SegmentPayload {
+                    text: "hello",
+                    stats: &stats,
+                    elevated_target: false,
+                }),
+            ),
+            (
+                EVENT_SETTINGS_CHANGED,
+                value(&SettingsChangedPayload {
+                    provider: TranscriptionProvider::ElevenLabs,
+                    model: "gpt-4o-transcribe".to_string(),
+                    auto_translate: true,
+                    target_language: "English".to_string(),
+                    active_preset: "Default".to_string(),
+                }),
+            ),
+            (EVENT_SETTINGS_CHANGED, json!({})),
+            (
+                EVENT_RECORDING_TICK,
+                value(&RecordingTickPayload {
+                    elapsed_secs: 12,
+                    remaining_secs: Some(48),
+                }),
+            ),
+            (
+                EVENT_RECORDING_TICK,
+                value(&RecordingTickPayload {
+                    elapsed_secs: 0,
+                    remaining_secs: None,
+                }),
+            ),
+            (
+                EVENT_OVERLAY_INTERACTIVE,
+                value(&OverlayInteractivePayload { interactive: true }),
+            ),
+            (EVENT_SESSION_CHANGED, value(&session.info())),
+            (EVENT_SESSION_CHANGED, value(&SessionInfo::default())),
+            (
+                EVENT_RETRANSCRIBE_PROGRESS,
+                value(&RetranscribeProgressPayload {
+                    id: 7,
+                    done: 1,
+                    total: 3,
+                    error: None,
+                }),
+            ),
+            (
+                EVENT_RETRANSCRIBE_PROGRESS,
+                value(&RetranscribeProgressPayload {
+                    id: 9,
+                    done: 2,
+                    total: 3,
+                    error: Some("OpenAI API error (429)".to_string()),
+                }),
+            ),
+            (EVENT_UPDATE_AVAILABLE, json!("1.4.0")),
+            (
+                EVENT_UPDATE_PROGRESS,
+                value(&UpdateProgressPayload {
+                    downloaded: 4096,
+                    total: None,
+                }),
+            ),
+            (EVENT_UPDATE_DOWNLOADED, json!("1.4.0")),
+            (EVENT_UPDATE_DEFERRED, json!("1.4.0")),
+            (EVENT_UPDATE_INSTALLED, json!("1.4.0")),
+            (
+                EVENT_ELEVENLABS_SESSION_STARTED,
+                value(&SessionStartedPayload {
+                    session_id: "s-1".to_string(),
+                }),
+            ),
+            (
+                EVENT_ELEVENLABS_TRANSCRIPT,
+                value(&StreamingTranscriptPayload {
+                    text: "hel".to_string(),
+                    is_partial: true,
+                    words: None,
+                    language_code: None,
+                }),
+            ),
+            (
+                EVENT_ELEVENLABS_TRANSCRIPT,
+                value(&StreamingTranscriptPayload {
+                    text: "hello".to_string(),
+                    is_partial: false,
+                    words: Some(vec![word]),
+                    language_code: Some("en".to_string()),
+                }),
+            ),
+            (
+                EVENT_ELEVENLABS_ERROR,
+                value(&StreamingErrorPayload {
+                    error: server_error.to_string(),
+                    code: server_error.error_code(),
+                    server_error: Some(server_error),
+                }),
+            ),
+            (
+                EVENT_ELEVENLABS_ERROR,
+                value(&StreamingErrorPayload {
+                    error: "Commit timeout".to_string(),
+                    code: ErrorCode::CommitTimeout,
+                    server_error: None,
+                }),
+            ),
+            (
+                EVENT_ELEVENLABS_CONNECTION_CLOSED,
+                value(&ConnectionClosedPayload {
+                    code: 1006,
+                    reason: "Stream ended".to_string(),
+                }),
+            ),
+            (
+                EVENT_ELEVENLABS_AUDIO_STATS,
+                value(&AudioStats {
+                    captured_chunks: 30,
+                    sent_chunks: 28,
+                    voiced_chunks: 19,
+                    bytes_sent: 28 * 3200,
+                    dropped_buffer_full: 1,
+                    dropped_socket_behind: 1,
+                    ignored_gate_closed: 0,
+                }),
+            ),
+        ];
+        for phase in StatusPhase::ALL {
+            let code = matches!(phase, StatusPhase::Error).then_some(ErrorCode::RateLimited);
+            samples.push((
+                EVENT_STATUS,
+                value(&StatusPayload {
+                    phase: phase.key(),
+                    message: phase.default_message(),
+                    code,
+                    provider: Some("openai"),
+                    actions: code.map_or_else(Vec::new, |code| code.actions(true)),
+                    retry_after_secs: code.map(|_| 20),
+                }),
+            ));
+        }
+        samples
+    }
+
+    fn value(payload: impl Serialize) -> Value {
+        serde_json::to_value(payload).unwrap()
+    }
+
+    #[test]
+    fn test_payloads_match_the_manifest() {
+        let manifest = manifest();
+        let samples = sample_payloads();
+        for spec in &manifest {
+            assert!(
+                samples.iter().any(|(name, _)| *name == spec.name),
+                "no sample payload for {}",
+                spec.name
+            );
+        }
+        for (name, payload) in &samples {
+            let spec = manifest
+                .iter()
+                .find(|spec| spec.name == *name)
+                .unwrap_or_else(|| panic!("{name} is missing from the manifest"));
+            if let Some(violation) = schema_violation(&spec.payload, payload, name) {
+                panic!("{violation}\npayload: {payload}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_schema_catches_drift() {
+        let status = &manifest()[0].payload;
+        let payload = json!({
+            "phase": "idle",
+            "message": "Ready",
+            "code": null,
+            "provider": null,
+            "actions": [],
+            "retry_after_secs": null,
+        });
+        assert_eq!(schema_violation(status, &payload, "status"), None);
+
+        let mut renamed = payload.clone();
+        renamed["state"] = renamed["phase"].take();
+        renamed.as_object_mut().unwrap().remove("phase");
+        assert!(schema_violation(status, &renamed, "status").is_some());
+        let mut retyped = payload.clone();
+        retyped["message"] = json!(1);
+        assert!(schema_violation(status, &retyped, "status").is_some());
+        let mut unknown_action = payload.clone();
+        unknown_action["actions"] = json!(["reboot"]);
+        assert!(schema_violation(status, &unknown_action, "status").is_some());
+        let mut unknown_phase = payload;
+        unknown_phase["phase"] = json!("sleeping");
+        assert!(schema_violation(status, &unknown_phase, "status").is_some());
+    }
+
+    #[test]
+    fn test_streaming_transcript_round_trips() {
+        for payload in sample_payloads()
+            .into_iter()
+            .filter(|(name, _)| *name == EVENT_ELEVENLABS_TRANSCRIPT)
+            .map(|(_, payload)| payload)
+        {
+            let parsed: StreamingTranscriptPayload =
+                serde_json::from_value(payload.clone()).unwrap();
+            assert_eq!(serde_json::to_value(&parsed).unwrap(), payload);
+        }
+    }
+
+    #[test]
+    fn test_event_names_are_unique() {
+        let manifest = manifest();
+        for (i, spec) in manifest.iter().enumerate() {
+            assert!(
+                manifest[i + 1..]
+                    .iter()
+                    .all(|other| other.name != spec.name && other.key != spec.key),
+                "{} is listed twice",
+                spec.name
+            );
+        }
+    }
+
+    /// `frontend/events.js`, generated from the manifest
+    fn frontend_events_script() -> String {
+        let mut script = String::from(
+            "// Generated from src-tauri/src/core/events.rs; don't edit by hand.\n\
+             // After changing an event there, run `UPDATE_EVENTS_JS=1 cargo test events`.\n\
+             const EVENTS = Object.freeze({\n",
+        );
+        for spec in manifest() {
+            script.push_str(&format!("  {}: \"{}\",\n", spec.key, spec.name));
+        }
+        script.push_str("});\n// `phase` of a STATUS event\nconst PHASES = Object.freeze({\n");
+        for phase in StatusPhase::ALL {
+            script.push_str(&format!(
+                "  {}: \"{}\",\n",
+                phase.key().to_uppercase(),
+                phase.key()
+            ));
+        }
+        script.push_str(
+            "});\n// `actions` of an error STATUS event\nconst ERROR_ACTIONS = Object.freeze({\n",
+        );
+        for action in ErrorAction::ALL {
+            script.push_str(&format!(
+                "  {}: \"{}\",\n",
+                action.key().to_uppercase(),
+                action.key()
+            ));
+        }
+        script.push_str("});\n");
+        script
+    }
+
+    #[test]
+    fn test_frontend_event_names_are_up_to_date() {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../frontend/events.js");
+        let expected = frontend_events_script();
+        if std::env::var_os("UPDATE_EVENTS_JS").is_some() {
+            std::fs::write(&path, &expected).unwrap();
+        }
+        let actual = std::fs::read_to_string(&path).unwrap_or_default();
+        assert!(
+            actual.replace("\r\n", "\n") == expected,
+            "frontend/events.js is out of date; run `UPDATE_EVENTS_JS=1 cargo test events`"
+        );
+    }
 }
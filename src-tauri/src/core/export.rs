@@ -0,0 +1,220 @@
+use std::fmt::Display;
+
+use anyhow::{Context, Result};
+use chrono::{NaiveDate, TimeZone};
+use serde::Deserialize;
+
+use super::state::HistoryEntry;
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Json,
+    Csv,
+    Markdown,
+}
+
+const CSV_HEADER: [&str; 9] = [
+    "id",
+    "timestamp",
+    "original_text",
+    "translated_text",
+    "source_language",
+    "target_language",
+    "transcription_provider",
+    "llm_provider",
+    "custom_instructions_used",
+];
+
+/// Serialize history entries in the requested format.
+/// Entries are written in chronological order regardless of input order.
+pub fn serialize_history<Tz: TimeZone>(
+    entries: &[HistoryEntry],
+    format: ExportFormat,
+    tz: &Tz,
+) -> Result<String>
+where
+    Tz::Offset: Display,
+{
+    let mut sorted = entries.to_vec();
+    sorted.sort_by_key(|e| (e.timestamp, e.id));
+
+    match format {
+        ExportFormat::Json => {
+            serde_json::to_string_pretty(&sorted).context("Failed to serialize history to JSON")
+        }
+        ExportFormat::Csv => Ok(to_csv(&sorted)),
+        ExportFormat::Markdown => Ok(to_markdown(&sorted, tz)),
+    }
+}
+
+/// Quote a CSV field per RFC 4180 when it contains separators, quotes or line breaks
+pub fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+pub fn to_csv(entries: &[HistoryEntry]) -> String {
+    let mut out = CSV_HEADER.join(",");
+    out.push_str("\r\n");
+
+    for entry in entries {
+        let fields = [
+            entry.id.to_string(),
+            entry.timestamp.to_rfc3339(),
+            entry.original_text.clone(),
+            entry.translated_text.clone().unwrap_or_default(),
+            entry.source_language.clone().unwrap_or_default(),
+            entry.target_language.clone().unwrap_or_default(),
+            entry.transcription_provider.clone().unwrap_or_default(),
+            entry.llm_provider.clone().unwrap_or_default(),
+            entry.custom_instructions_used.to_string(),
+        ];
+        let row: Vec<String> = fields.iter().map(|f| csv_escape(f)).collect();
+        out.push_str(&row.join(","));
+        out.push_str("\r\n");
+    }
+
+    out
+}
+
+/// Escape text for use inside a Markdown table cell
+pub fn markdown_cell(text: &str) -> String {
+    text.trim()
+        .replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace('\n', "<br>")
+}
+
+/// Render entries grouped by day; entries with a translation use a two-column table
+pub fn to_markdown<Tz: TimeZone>(entries: &[HistoryEntry], tz: &Tz) -> String
+where
+    Tz::Offset: Display,
+{
+    let mut out = String::from("# Easy Dictate history\n");
+    let mut current_day: Option<NaiveDate> = None;
+
+    for entry in entries {
+        let local = entry.timestamp.with_timezone(tz);
+        let day = local.date_naive();
+        if current_day != Some(day) {
+            out.push_str(&format!("\n## {}\n", day.format("%Y-%m-%d")));
+            current_day = Some(day);
+        }
+
+        let mut meta = vec![format!("**{}**", local.format("%H:%M:%S"))];
+        if let Some(provider) = &entry.transcription_provider {
+            meta.push(provider.clone());
+        }
+        if let Some(llm) = &entry.llm_provider {
+            meta.push(format!("+{llm}"));
+        }
+        out.push_str(&format!("\n{}\n\n", meta.join(" · ")));
+
+        match &entry.translated_text {
+            Some(translated) => {
+                let target = entry.target_language.as_deref().unwrap_or("Translated");
+                out.push_str(&format!("| Original | {} |\n", markdown_cell(target)));
+                out.push_str("| --- | --- |\n");
+                out.push_str(&format!(
+                    "| {} | {} |\n",
+                    markdown_cell(&entry.original_text),
+                    markdown_cell(translated)
+                ));
+            }
+            None => {
+                out.push_str(entry.original_text.trim());
+                out.push('\n');
+            }
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn entry(id: u64, hour: u32, original: &str, translated: Option<&str>) -> HistoryEntry {
+        HistoryEntry {
+            id,
+            timestamp: Utc.with_ymd_and_hms(2025, 6, 11, hour, 0, 0).unwrap(),
+            original_text: original.to_string(),
+            translated_text: translated.map(str::to_string),
+            source_language: None,
+            target_language: translated.map(|_| "English".to_string()),
+            translated: translated.is_some(),
+            translation_forced: false,
+            transcription_provider: Some("openai".to_string()),
+            llm_provider: None,
+            custom_instructions_used: false,
+            refinement_failed: false,
+            combined_translation: false,
+            was_modified: false,
+            change_count: 0,
+            latency_ms: None,
+            word_count: 0,
+            char_count: 0,
+            words_per_minute: None,
+            recording_path: None,
+            segments: Vec::new(),
+            alternate_transcripts: Vec::new(),
+            capture: None,
+        }
+    }
+
+    #[test]
+    fn test_csv_escape_plain() {
+        assert_eq!(csv_escape("hello"), "hello");
+        assert_eq!(csv_escape(""), "");
+    }
+
+    #[test]
+    fn test_csv_escape_commas_quotes_newlines() {
+        assert_eq!(csv_escape("a, b"), "\"a, b\"");
+        assert_eq!(csv_escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_escape("line1\nline2"), "\"line1\nline2\"");
+        assert_eq!(csv_escape("line1\r\nline2"), "\"line1\r\nline2\"");
+    }
+
+    #[test]
+    fn test_to_csv_rows() {
+        let csv = to_csv(&[entry(1, 10, "hello, world", None)]);
+        let mut lines = csv.split("\r\n");
+        assert_eq!(lines.next().unwrap(), CSV_HEADER.join(","));
+        let row = lines.next().unwrap();
+        assert!(row.starts_with("1,2025-06-11T10:00:00+00:00,\"hello, world\",,"));
+        assert!(row.ends_with(",openai,,false"));
+    }
+
+    #[test]
+    fn test_markdown_cell_escapes_pipes_and_newlines() {
+        assert_eq!(markdown_cell("a | b\nc"), "a \\| b<br>c");
+    }
+
+    #[test]
+    fn test_to_markdown_groups_by_day_and_uses_table_for_translations() {
+        let entries = vec![
+            entry(1, 9, "просто текст", None),
+            entry(2, 10, "привет", Some("hello")),
+        ];
+        let md = to_markdown(&entries, &Utc);
+        assert_eq!(md.matches("## 2025-06-11").count(), 1);
+        assert!(md.contains("просто текст\n"));
+        assert!(md.contains("| Original | English |"));
+        assert!(md.contains("| привет | hello |"));
+    }
+
+    #[test]
+    fn test_serialize_history_sorts_chronologically() {
+        let entries = vec![entry(2, 11, "second", None), entry(1, 9, "first", None)];
+        let json = serialize_history(&entries, ExportFormat::Json, &Utc).unwrap();
+        assert!(json.find("first").unwrap() < json.find("second").unwrap());
+    }
+}
@@ -0,0 +1,116 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::Ordering,
+};
+
+use tauri::{AppHandle, Manager, State};
+
+use crate::{
+    audio_file::{self, AudioFileError},
+    messages::{self, Msg},
+};
+
+use super::{
+    error::CommandError,
+    events::{emit_error_code, emit_status, ErrorCode, StatusPhase},
+    session::SessionContext,
+    state::AppState,
+    timings::Timings,
+    transcription,
+};
+
+fn error_code(err: &AudioFileError) -> ErrorCode {
+    match err {
+        AudioFileError::Unsupported(_) => ErrorCode::UnsupportedAudioFormat,
+        AudioFileError::Empty => ErrorCode::NoAudio,
+        AudioFileError::Io(_) | AudioFileError::Decode(_) => ErrorCode::Unknown,
+    }
+}
+
+fn display_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+/// Run an audio file through the dictation pipeline. Nothing is typed into the
+/// focused window: the result goes to the clipboard and history, and is returned.
+pub async fn transcribe_file(app: &AppHandle, path: PathBuf) -> Result<String, CommandError> {
+    let state: State<'_, AppState> = app.state();
+    if state.is_transcribing().swap(true, Ordering::SeqCst) {
+        return Err(CommandError::Unavailable(
+            "A transcription is already in progress".into(),
+        ));
+    }
+
+    let name = display_name(&path);
+    tracing::info!("[FileTranscription] Transcribing {:?}", path);
+    emit_status(
+        app,
+        StatusPhase::Transcribing,
+        Some(&messages::fill(Msg::DecodingFile, &[("name", &name)])),
+    );
+
+    // Decoding a long memo takes a while; keep it off the async workers
+    let decoded = match tokio::task::spawn_blocking(move || audio_file::decode_to_wav(&path)).await
+    {
+        Ok(result) => result,
+        Err(e) => Err(AudioFileError::Decode(e.to_string())),
+    };
+    let audio_wav = match decoded {
+        Ok(audio_wav) => audio_wav,
+        Err(err) => {
+            tracing::warn!("[FileTranscription] {}: {}", name, err);
+            state.is_transcribing().store(false, Ordering::SeqCst);
+            emit_error_code(app, error_code(&err), None, &err.to_string());
+            emit_status(app, StatusPhase::Idle, None);
+            return Err(err.into());
+        }
+    };
+
+    let mut settings = (*state.current_settings().await).clone();
+    settings.simulate_typing = false;
+    settings.copy_to_clipboard = true;
+
+    emit_status(
+        app,
+        StatusPhase::Transcribing,
+        Some(messages::text(Msg::UploadingAudio)),
+    );
+    let text = transcription::transcribe_and_deliver(
+        app,
+        &settings,
+        SessionContext::default(),
+        audio_wav,
+        Timings::start(),
+    )
+    .await?;
+    Ok(text)
+}
+
+/// Transcribe a file dropped on the main window. Only one file per drop: the first
+/// supported one, or else the first file, so the user sees why it was rejected.
+pub fn transcribe_dropped(app: &AppHandle, paths: &[PathBuf]) {
+    let Some(path) = paths
+        .iter()
+        .find(|path| audio_file::is_supported(path))
+        .or_else(|| paths.first())
+        .cloned()
+    else {
+        return;
+    };
+    if paths.len() > 1 {
+        tracing::info!(
+            "[FileTranscription] {} files dropped; transcribing {:?}",
+            paths.len(),
+            path
+        );
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = transcribe_file(&app, path).await {
+            tracing::warn!("[FileTranscription] Dropped file not transcribed: {}", e);
+        }
+    });
+}
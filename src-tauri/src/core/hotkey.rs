@@ -1,32 +1,92 @@
-use std::sync::atomic::Ordering;
+use std::{
+    sync::{atomic::Ordering, Arc},
+    time::{Duration, Instant},
+};
 
 use anyhow::{anyhow, Result};
 use tauri::{AppHandle, Manager, State};
-use tauri_plugin_global_shortcut::{GlobalShortcut, ShortcutState};
+use tauri_plugin_global_shortcut::{GlobalShortcut, GlobalShortcutExt, ShortcutState};
 
 use crate::{
-    audio::RecordingSession,
-    settings::{AppSettings, TranscriptionProvider},
+    audio::{CaptureInfo, RecordingSession},
+    elevenlabs_streaming::ConnectionConfig,
+    language,
+    messages::{self, Msg},
+    settings::{AppOverrides, AppSettings, TranscriptionProvider},
 };
 
 use super::{
-    events::{emit_error, emit_settings_changed, emit_status, StatusPhase},
+    app_rules,
+    commands::{change_translation, AudioStartFailed, TranslationChange},
+    connect_breaker,
+    error::CommandError,
+    events::{emit_error_code, emit_error_from, emit_status, ErrorCode, StatusPhase},
+    overlay::cancel_overlay_hide,
+    pseudo_streaming,
+    recording_timer::{start_recording_timer, stop_recording_timer},
+    session::{DictationOptions, PendingCommit, SessionContext},
+    session_manager::{self, HotkeyEventKind},
     state::AppState,
+    timings::Timings,
     transcription,
 };
 
-pub fn rebind_hotkey(app: &AppHandle, settings: &AppSettings) -> Result<()> {
-    let shortcuts: State<'_, GlobalShortcut<tauri::Wry>> = app.state();
+/// How long a streaming commit may go without a transcript or error before the
+/// transcribing flag is force-cleared. The client itself gives up on a commit after 3s.
+const COMMIT_WATCHDOG_TIMEOUT: Duration = Duration::from_secs(15);
+const HOTKEY_RELEASE_DELAY: Duration = Duration::from_millis(200);
+
+/// Every hotkey `rebind_hotkey` registers for a set of settings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HotkeyBindings {
+    main: String,
+    translate: String,
+    toggle_translate: String,
+    cycle_preset: String,
+    cycle_target_language: String,
+    commit_segment: String,
+}
+
+impl HotkeyBindings {
+    pub fn from_settings(settings: &AppSettings) -> Self {
+        Self {
+            main: settings.normalized_hotkey(),
+            translate: settings.translate_hotkey.trim().to_string(),
+            toggle_translate: settings.toggle_translate_hotkey.trim().to_string(),
+            cycle_preset: settings.cycle_preset_hotkey.trim().to_string(),
+            cycle_target_language: settings.cycle_target_language_hotkey.trim().to_string(),
+            commit_segment: settings.commit_segment_hotkey.trim().to_string(),
+        }
+    }
+}
+
+/// Register the hotkeys of `settings` in place of the current ones. A no-op if the
+/// last rebind registered the same hotkeys without errors, so saving unrelated
+/// settings doesn't drop a hotkey for the release delay. Callers hold
+/// `AppState::lock_settings_apply`.
+pub async fn rebind_hotkey(app: &AppHandle, settings: &AppSettings) -> Result<()> {
+    let state: State<'_, AppState> = app.state();
+    let bindings = HotkeyBindings::from_settings(settings);
+    let unchanged = state
+        .bound_hotkeys()
+        .lock()
+        .map_or(false, |bound| bound.as_ref() == Some(&bindings));
+    if unchanged {
+        tracing::debug!("[Hotkey] Hotkeys unchanged; keeping the registered ones");
+        return Ok(());
+    }
 
     // Unregister all existing shortcuts first
     // Log the result but continue even on failure (some hotkeys might not be registered)
-    match shortcuts.unregister_all() {
+    match app.global_shortcut().unregister_all() {
         Ok(_) => tracing::info!("[Hotkey] Unregistered all existing shortcuts"),
         Err(e) => tracing::warn!("[Hotkey] Failed to unregister shortcuts: {}", e),
     }
 
     // Longer delay to ensure OS releases the hotkey handles (Windows quirk)
-    std::thread::sleep(std::time::Duration::from_millis(200));
+    tokio::time::sleep(HOTKEY_RELEASE_DELAY).await;
+
+    let shortcuts: State<'_, GlobalShortcut<tauri::Wry>> = app.state();
 
     let mut errors: Vec<String> = Vec::new();
 
@@ -37,7 +97,7 @@ pub fn rebind_hotkey(app: &AppHandle, settings: &AppSettings) -> Result<()> {
         hotkey.as_str(),
         move |app_handle, _shortcut, event| match event.state {
             ShortcutState::Pressed => {
-                handle_hotkey_pressed(app_handle, false);
+                handle_hotkey_pressed(app_handle, DictationOptions::default());
             }
             ShortcutState::Released => {
                 handle_hotkey_released(app_handle);
@@ -63,7 +123,7 @@ pub fn rebind_hotkey(app: &AppHandle, settings: &AppSettings) -> Result<()> {
             translate_hotkey.as_str(),
             move |app_handle, _shortcut, event| match event.state {
                 ShortcutState::Pressed => {
-                    handle_hotkey_pressed(app_handle, true);
+                    handle_hotkey_pressed(app_handle, DictationOptions::translated());
                 }
                 ShortcutState::Released => {
                     handle_hotkey_released(app_handle);
@@ -115,6 +175,98 @@ pub fn rebind_hotkey(app: &AppHandle, settings: &AppSettings) -> Result<()> {
         }
     }
 
+    // Register preset cycle hotkey
+    if !settings.cycle_preset_hotkey.is_empty() {
+        let cycle_hotkey = settings.cycle_preset_hotkey.trim().to_string();
+        let cycle_hotkey_clone = cycle_hotkey.clone();
+        match shortcuts.on_shortcut(
+            cycle_hotkey.as_str(),
+            move |app_handle, _shortcut, event| {
+                if event.state == ShortcutState::Pressed {
+                    handle_cycle_preset_hotkey(app_handle);
+                }
+            },
+        ) {
+            Ok(_) => tracing::info!(
+                "[Hotkey] Registered preset cycle hotkey: {}",
+                cycle_hotkey_clone
+            ),
+            Err(e) => {
+                tracing::error!(
+                    "[Hotkey] Failed to register preset cycle hotkey {}: {}",
+                    cycle_hotkey_clone,
+                    e
+                );
+                errors.push(format!("Preset hotkey '{}': {}", cycle_hotkey_clone, e));
+            }
+        }
+    }
+
+    // Register target language cycle hotkey
+    if !settings.cycle_target_language_hotkey.is_empty() {
+        let cycle_hotkey = settings.cycle_target_language_hotkey.trim().to_string();
+        let cycle_hotkey_clone = cycle_hotkey.clone();
+        match shortcuts.on_shortcut(
+            cycle_hotkey.as_str(),
+            move |app_handle, _shortcut, event| {
+                if event.state == ShortcutState::Pressed {
+                    handle_cycle_target_language_hotkey(app_handle);
+                }
+            },
+        ) {
+            Ok(_) => tracing::info!(
+                "[Hotkey] Registered target language cycle hotkey: {}",
+                cycle_hotkey_clone
+            ),
+            Err(e) => {
+                tracing::error!(
+                    "[Hotkey] Failed to register target language cycle hotkey {}: {}",
+                    cycle_hotkey_clone,
+                    e
+                );
+                errors.push(format!(
+                    "Target language hotkey '{}': {}",
+                    cycle_hotkey_clone, e
+                ));
+            }
+        }
+    }
+
+    // Register segment commit hotkey (ElevenLabs streaming only)
+    if !settings.commit_segment_hotkey.is_empty() {
+        let segment_hotkey = settings.commit_segment_hotkey.trim().to_string();
+        let segment_hotkey_clone = segment_hotkey.clone();
+        match shortcuts.on_shortcut(
+            segment_hotkey.as_str(),
+            move |app_handle, _shortcut, event| {
+                if event.state == ShortcutState::Pressed {
+                    handle_commit_segment_hotkey(app_handle);
+                }
+            },
+        ) {
+            Ok(_) => tracing::info!(
+                "[Hotkey] Registered segment commit hotkey: {}",
+                segment_hotkey_clone
+            ),
+            Err(e) => {
+                tracing::error!(
+                    "[Hotkey] Failed to register segment commit hotkey {}: {}",
+                    segment_hotkey_clone,
+                    e
+                );
+                errors.push(format!("Segment hotkey '{}': {}", segment_hotkey_clone, e));
+            }
+        }
+    }
+
+    if let Ok(mut guard) = state.hotkey_errors().lock() {
+        *guard = Some(errors.clone());
+    }
+    if let Ok(mut guard) = state.bound_hotkeys().lock() {
+        // A hotkey that failed to register is retried by the next rebind
+        *guard = errors.is_empty().then_some(bindings);
+    }
+
     // Return error only if ALL hotkeys failed
     if !errors.is_empty() {
         // Log all errors but only fail if main hotkey failed (it's required)
@@ -131,105 +283,244 @@ pub fn rebind_hotkey(app: &AppHandle, settings: &AppSettings) -> Result<()> {
     Ok(())
 }
 
-/// Handle hotkey press event - spawns async task to avoid blocking the event thread
-/// force_translate: if true, translation will be forced ON regardless of settings
-pub fn handle_hotkey_pressed(app: &AppHandle, force_translate: bool) {
+/// Handle hotkey press event - queued for the session manager so it never blocks the
+/// event thread and is processed in order with the matching release
+/// options: forced translation and a provider override for this dictation only
+pub fn handle_hotkey_pressed(app: &AppHandle, options: DictationOptions) {
+    session_manager::send(app, HotkeyEventKind::Pressed(options));
+}
+
+/// Start a dictation; run by the session manager
+pub async fn press(app: &AppHandle, options: DictationOptions) {
+    if let Err(err) = handle_hotkey_pressed_async(app, options).await {
+        emit_error_from(app, &err, None);
+    }
+}
+
+/// Handle toggle translate hotkey - toggles auto_translate setting
+pub fn handle_toggle_translate_hotkey(app: &AppHandle) {
     let app_clone = app.clone();
 
-    // Spawn async task to handle the press without blocking
     tauri::async_runtime::spawn(async move {
-        if let Err(err) = handle_hotkey_pressed_async(&app_clone, force_translate).await {
-            emit_error(&app_clone, &err.to_string());
+        let state: State<'_, AppState> = app_clone.state();
+
+        if let Err(e) = change_translation(&app_clone, &state, TranslationChange::Toggle).await {
+            emit_error_code(
+                &app_clone,
+                ErrorCode::SettingsSaveFailed,
+                None,
+                &messages::fill(Msg::SettingsSaveFailed, &[("error", &e)]),
+            );
         }
     });
 }
 
-/// Handle toggle translate hotkey - toggles auto_translate setting
-pub fn handle_toggle_translate_hotkey(app: &AppHandle) {
+/// Handle preset cycle hotkey - switches to the next prompt preset
+pub fn handle_cycle_preset_hotkey(app: &AppHandle) {
     let app_clone = app.clone();
 
     tauri::async_runtime::spawn(async move {
         let state: State<'_, AppState> = app_clone.state();
+        if let Err(e) = crate::core::commands::change_active_preset(&app_clone, &state, None).await
+        {
+            emit_error_code(
+                &app_clone,
+                ErrorCode::SettingsSaveFailed,
+                None,
+                &e.to_string(),
+            );
+        }
+    });
+}
 
-        // Atomic read-modify-write under exclusive lock to prevent TOCTOU race
-        let settings = state
-            .update_settings(|s| {
-                s.auto_translate = !s.auto_translate;
-            })
-            .await;
+/// Handle target language cycle hotkey - switches to the next target language
+pub fn handle_cycle_target_language_hotkey(app: &AppHandle) {
+    let app_clone = app.clone();
 
-        if let Err(e) = state.persist_settings(&settings).await {
-            emit_error(&app_clone, &format!("Failed to save settings: {}", e));
-            return;
+    tauri::async_runtime::spawn(async move {
+        let state: State<'_, AppState> = app_clone.state();
+        if let Err(e) = change_translation(&app_clone, &state, TranslationChange::Cycle).await {
+            emit_error_code(
+                &app_clone,
+                ErrorCode::SettingsSaveFailed,
+                None,
+                &e.to_string(),
+            );
         }
+    });
+}
 
-        tracing::info!(
-            "[Toggle Hotkey] Auto-translate now: {} (target: {})",
-            settings.auto_translate,
-            settings.target_language
-        );
+/// Handle segment commit hotkey - queued like presses and releases, so it lands
+/// inside the dictation it belongs to
+pub fn handle_commit_segment_hotkey(app: &AppHandle) {
+    session_manager::send(app, HotkeyEventKind::CommitSegment);
+}
 
-        // Emit settings changed event for UI sync
-        emit_settings_changed(
-            &app_clone,
-            settings.auto_translate,
-            &settings.target_language,
+/// Deliver what was said so far and keep streaming into the next segment; run by the
+/// session manager. Only streamed dictations have segments; otherwise a no-op.
+pub async fn commit_segment(app: &AppHandle) {
+    let state: State<'_, AppState> = app.state();
+    let streaming = state.elevenlabs_streaming();
+    if !streaming.has_audio_since_open().await {
+        tracing::info!("[Hotkey] Nothing said since the last segment; not committing");
+        return;
+    }
+    let Some(segment) = state.next_segment() else {
+        tracing::info!("[Hotkey] No streamed dictation in progress; not committing");
+        return;
+    };
+
+    tracing::info!(
+        "[Hotkey] Committing segment {} of session {}",
+        segment.segment + 1,
+        segment.id
+    );
+    if let Ok(mut guard) = state.pending_commits().lock() {
+        guard.push_back(PendingCommit {
+            session: segment.clone(),
+            timings: Timings::start(),
+            recorded: segment.gate_opened_at.map(|at| at.elapsed()),
+            continues: true,
+            audio: streaming.audio_stats(),
+        });
+    }
+    spawn_commit_watchdog(app, segment.clone());
+
+    let silence = state.current_settings().await.commit_silence();
+    let error = match streaming.commit_segment(silence).await {
+        Ok(true) => return,
+        Ok(false) => None,
+        Err(e) => Some(e),
+    };
+    // No transcript is coming for it
+    if let Ok(mut guard) = state.pending_commits().lock() {
+        guard.retain(|pending| !is_commit_of(pending, &segment));
+    }
+    if let Some(e) = error {
+        emit_error_code(
+            app,
+            ErrorCode::StreamingError,
+            Some(TranscriptionProvider::ElevenLabs.id()),
+            &messages::fill(Msg::SegmentCommitFailed, &[("error", &e)]),
         );
+    }
+}
 
-        // Emit status update with target language info
-        let message = if settings.auto_translate {
-            format!("Перевод ВКЛ → {}", settings.target_language)
-        } else {
-            "Перевод ВЫКЛ".to_string()
-        };
-        emit_status(&app_clone, StatusPhase::Idle, Some(&message));
-    });
+/// Overrides of the app rule matching the foreground application, if any
+fn app_overrides(settings: &AppSettings) -> Option<AppOverrides> {
+    if settings.app_rules.is_empty() {
+        return None;
+    }
+    let app = app_rules::foreground_app()?;
+    let rule = app_rules::matching_rule(&settings.app_rules, &app)?;
+    tracing::info!(
+        "[AppRules] {} matches the rule for \"{}\"",
+        app.name,
+        rule.process
+    );
+    Some(rule.overrides.clone())
 }
 
 /// Async implementation of hotkey press handling
-async fn handle_hotkey_pressed_async(app: &AppHandle, force_translate: bool) -> Result<()> {
+async fn handle_hotkey_pressed_async(app: &AppHandle, options: DictationOptions) -> Result<()> {
+    let pressed_at = Instant::now();
     let state: State<'_, AppState> = app.state();
+    // A transcript still being read aloud would end up in the new recording
+    state.speaker().stop();
 
-    // Start a new session — if force_translate, bind it to this session ID
-    let _session_id = state.start_session(force_translate);
+    // Start a new session; its translate and provider decisions travel with this utterance
+    let mut session = state.start_session(options);
 
     // Get settings once at the beginning
-    let settings = state.current_settings().await;
+    let mut settings = state.current_settings().await;
+    if let Some(overrides) = app_overrides(&settings) {
+        // Decided here too, so a rule's target language picks the streaming language
+        let mut applied = (*settings).clone();
+        overrides.apply(&mut applied);
+        settings = Arc::new(applied);
+        state.set_app_overrides(session.id, overrides.clone());
+        session.app_overrides = Some(overrides);
+    }
+    let provider = session.provider(&settings);
+    // A speculative connect from the held modifiers may still be running; it's ours now
+    let preconnect_saved = state.preconnect().claim(pressed_at).await;
     let is_streaming_connected = state.elevenlabs_streaming().is_connected().await;
+    if let Some(saved) = preconnect_saved.filter(|_| is_streaming_connected) {
+        tracing::info!(
+            "[Hotkey] Speculative connect saved {}ms of connect time",
+            saved.as_millis()
+        );
+        state.set_preconnect_saved(session.id, saved);
+        session.preconnect_saved = Some(saved);
+    }
 
     tracing::info!(
         "[Hotkey] Pressed. Provider: {:?}, Streaming connected: {}",
-        settings.provider,
+        provider,
         is_streaming_connected
     );
 
-    if settings.provider == TranscriptionProvider::ElevenLabs {
+    if session.uses_streaming(&settings) {
         let is_committing = state.elevenlabs_streaming().is_committing().await;
+        // A connection to the streaming provider configured before isn't reused
+        let last_config = state
+            .elevenlabs_streaming()
+            .get_last_config()
+            .await
+            .filter(|config| config.provider == settings.streaming_provider);
+        let language = streaming_language(
+            &settings,
+            session.force_translate,
+            last_config
+                .as_ref()
+                .map(|config| config.language_code.as_str()),
+        );
 
-        if !is_streaming_connected || is_committing {
+        if !is_streaming_connected || is_committing || language.reconnect {
             tracing::info!(
-                "[Hotkey] Preparing clean session (connected: {}, committing: {})",
+                "[Hotkey] Preparing clean session (connected: {}, committing: {}, language: {})",
                 is_streaming_connected,
-                is_committing
+                is_committing,
+                language.code
             );
 
+            if language.reconnect && is_streaming_connected && !is_committing {
+                if let Err(e) = crate::core::commands::disconnect_streaming(&state).await {
+                    tracing::warn!(
+                        "[Hotkey] Failed to drop connection for language switch: {}",
+                        e
+                    );
+                }
+            }
+
             let mut connected = false;
+            let mut attempted = false;
+            // A stalled handshake is not retried: the user is already speaking
+            let mut timed_out = false;
+            // Neither is a capture that failed to start; another connect won't fix it
+            let mut audio_failed = false;
+            let breaker_open = state.connect_breaker().open_for(Instant::now());
 
-            // Try to reconnect using last config (including audio stream restart)
-            if let Some((api_key, sample_rate, language_code)) =
-                state.elevenlabs_streaming().get_last_config().await
-            {
+            if let Some(left) = breaker_open {
+                tracing::warn!(
+                    "[Hotkey] Streaming connects keep failing; recording without streaming for {}s",
+                    left.as_secs()
+                );
+            } else if let Some(config) = last_config {
+                // Try to reconnect using last config (including audio stream restart)
+                attempted = true;
                 tracing::info!(
                     "[Hotkey] Reconnecting with last config: rate={}, lang={}",
-                    sample_rate,
-                    language_code
+                    config.sample_rate,
+                    language.code
                 );
-                match crate::core::commands::elevenlabs_streaming_connect(
-                    app.clone(),
-                    state.clone(),
-                    api_key,
-                    sample_rate,
-                    language_code,
+                match crate::core::commands::connect_streaming(
+                    app,
+                    &state,
+                    ConnectionConfig {
+                        language_code: language.code.clone(),
+                        ..config
+                    },
                 )
                 .await
                 {
@@ -239,6 +530,8 @@ async fn handle_hotkey_pressed_async(app: &AppHandle, force_translate: bool) ->
                     }
                     Err(e) => {
                         tracing::error!("[Hotkey] Reconnection with last config failed: {}", e);
+                        timed_out = is_connect_timeout(&e);
+                        audio_failed = is_audio_start_failure(&e);
                         connected = false;
                     }
                 }
@@ -247,20 +540,25 @@ async fn handle_hotkey_pressed_async(app: &AppHandle, force_translate: bool) ->
             }
 
             // Fallback to settings if no last config or reconnection failed
-            if !connected {
-                let api_key = settings.elevenlabs_api_key.trim().to_string();
+            if !connected && !timed_out && !audio_failed && breaker_open.is_none() {
+                let api_key = settings.streaming_key_field().trim().to_string();
                 if api_key.is_empty() {
                     tracing::warn!(
-                        "[Hotkey] ElevenLabs API key is empty; falling back to standard recording."
+                        "[Hotkey] {:?} streaming API key is empty; falling back to standard recording.",
+                        settings.streaming_provider
                     );
                 } else {
                     tracing::info!("[Hotkey] Using settings fallback to connect");
-                    match crate::core::commands::elevenlabs_streaming_connect(
-                        app.clone(),
-                        state.clone(),
-                        api_key,
-                        48_000,
-                        "auto".to_string(),
+                    attempted = true;
+                    match crate::core::commands::connect_streaming(
+                        app,
+                        &state,
+                        ConnectionConfig {
+                            provider: settings.streaming_provider,
+                            api_key,
+                            sample_rate: 48_000,
+                            language_code: language.code.clone(),
+                        },
                     )
                     .await
                     {
@@ -270,30 +568,47 @@ async fn handle_hotkey_pressed_async(app: &AppHandle, force_translate: bool) ->
                         }
                         Err(e) => {
                             tracing::error!("[Hotkey] Settings fallback connection failed: {}", e);
+                            timed_out = is_connect_timeout(&e);
+                            audio_failed = is_audio_start_failure(&e);
                             connected = false;
                         }
                     }
                 }
             }
 
+            if connected {
+                state.connect_breaker().record_success();
+            } else if attempted && state.connect_breaker().record_failure(Instant::now()) {
+                tracing::warn!(
+                    "[Hotkey] Streaming connect failed {} times in a row; skipping it for {}s",
+                    connect_breaker::FAILURE_THRESHOLD,
+                    connect_breaker::COOLDOWN.as_secs()
+                );
+            }
+            if timed_out {
+                tracing::warn!(
+                    "[Hotkey] Streaming handshake timed out; recording without streaming"
+                );
+            }
+            if audio_failed {
+                tracing::warn!(
+                    "[Hotkey] Streaming audio capture failed to start; recording without streaming"
+                );
+            }
+
             if connected {
                 tracing::info!("[Hotkey] Clean session ready. Opening gate...");
-                if let Err(e) = state.elevenlabs_streaming().open_gate().await {
-                    emit_error(app, &format!("Failed to open gate: {}", e));
-                } else {
-                    emit_status(app, StatusPhase::Recording, Some("Streaming..."));
-                }
+                open_streaming_gate(app, &state, session.id, &language).await;
                 return Ok(());
             }
             // else fall through to legacy recording
         } else {
             // Already connected and not committing: open gate
-            tracing::info!("[Hotkey] ElevenLabs gated streaming - opening gate");
-            if let Err(e) = state.elevenlabs_streaming().open_gate().await {
-                emit_error(app, &format!("Failed to open gate: {}", e));
-            } else {
-                emit_status(app, StatusPhase::Recording, Some("Streaming..."));
-            }
+            tracing::info!(
+                "[Hotkey] {:?} gated streaming - opening gate",
+                settings.streaming_provider
+            );
+            open_streaming_gate(app, &state, session.id, &language).await;
             return Ok(());
         }
     }
@@ -302,13 +617,9 @@ async fn handle_hotkey_pressed_async(app: &AppHandle, force_translate: bool) ->
 
     // For Mock provider in test mode, skip real recording
     // Tests use inject_test_audio() to provide audio data directly
-    if settings.provider == TranscriptionProvider::Mock {
+    if provider == TranscriptionProvider::Mock {
         tracing::info!("[Hotkey] Mock provider - skipping real microphone recording");
-        emit_status(
-            app,
-            StatusPhase::Recording,
-            Some("Mock recording (test mode)..."),
-        );
+        begin_recording(app, "Mock recording (test mode)...");
         return Ok(());
     }
 
@@ -316,7 +627,7 @@ async fn handle_hotkey_pressed_async(app: &AppHandle, force_translate: bool) ->
         emit_status(
             app,
             StatusPhase::Transcribing,
-            Some("Already transcribing, please wait."),
+            Some(messages::text(Msg::AlreadyTranscribing)),
         );
         return Ok(());
     }
@@ -330,20 +641,129 @@ async fn handle_hotkey_pressed_async(app: &AppHandle, force_translate: bool) ->
         return Ok(());
     }
 
-    match state.recorder().start() {
-        Ok(active) => {
-            emit_status(app, StatusPhase::Recording, Some("Recording..."));
+    match state.recorder().start(settings.recording_spill_after()) {
+        Ok((active, capture)) => {
+            state.set_capture(session.id, capture);
+            let snapshot = active.snapshot();
             *guard = Some(active);
+            begin_recording(app, messages::text(Msg::Recording));
+            if let Some(interval) = settings.pseudo_streaming_interval() {
+                let mut interim_settings = (*settings).clone();
+                interim_settings.provider = provider;
+                pseudo_streaming::spawn(app, snapshot, interim_settings, interval);
+            }
         }
-        Err(err) => emit_error(app, &err.to_string()),
+        Err(err) => emit_error_from(app, &err, None),
     }
 
     Ok(())
 }
 
-/// Handle hotkey release event - spawns async task for streaming, sync for legacy recording
+fn is_connect_timeout(err: &CommandError) -> bool {
+    matches!(err, CommandError::Hotkey(e) if ErrorCode::classify(e) == ErrorCode::ConnectTimeout)
+}
+
+/// The socket connected but the audio capture behind it didn't start
+fn is_audio_start_failure(err: &CommandError) -> bool {
+    matches!(err, CommandError::Hotkey(e) if e.is::<AudioStartFailed>())
+}
+
+/// Language for a dictation's ElevenLabs streaming connection
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct StreamingLanguage {
+    /// `language_code` to connect with ("auto" = detect)
+    code: String,
+    /// The live connection was opened with another language and must be replaced
+    reconnect: bool,
+    /// The transcript will already be in the target language, so translating it is
+    /// a wasted LLM call
+    in_target_language: bool,
+}
+
+/// A translated dictation is transcribed with the configured `transcription_language`,
+/// reconnecting if the live connection uses another one. Other dictations keep the
+/// language of the live connection to avoid a reconnect, or detect it.
+fn streaming_language(
+    settings: &AppSettings,
+    force_translate: bool,
+    connected_with: Option<&str>,
+) -> StreamingLanguage {
+    if !force_translate {
+        return StreamingLanguage {
+            code: connected_with.unwrap_or("auto").to_string(),
+            reconnect: false,
+            in_target_language: false,
+        };
+    }
+    let code = settings.transcription_language.clone();
+    StreamingLanguage {
+        reconnect: connected_with.is_some_and(|current| current != code),
+        in_target_language: language::language_for_code(&code)
+            .is_some_and(|language| language.matches(&settings.target_language)),
+        code,
+    }
+}
+
+async fn open_streaming_gate(
+    app: &AppHandle,
+    state: &AppState,
+    session_id: u64,
+    language: &StreamingLanguage,
+) {
+    let opened = async {
+        // The streaming capture stays open between dictations, so the system mute is
+        // looked at each time the gate opens
+        state.recorder().check_access()?;
+        state.elevenlabs_streaming().open_gate().await
+    };
+    if let Err(e) = opened.await {
+        emit_gate_error(app, Msg::GateOpenFailed, &e);
+        return;
+    }
+    state.mark_gate_opened(session_id);
+    if let Some(capture) = state.streaming_capture() {
+        // The audio task picks up the AGC settings of the moment with this gate opening
+        let agc = state.current_settings().await.agc().is_some();
+        state.set_capture(session_id, CaptureInfo { agc, ..capture });
+    }
+    if language.in_target_language {
+        state.mark_transcribed_in_target(session_id);
+    }
+    begin_recording(app, messages::text(Msg::Streaming));
+}
+
+fn emit_gate_error(app: &AppHandle, msg: Msg, err: &anyhow::Error) {
+    let code = match ErrorCode::classify(err) {
+        code @ (ErrorCode::MicMuted | ErrorCode::MicBlocked) => code,
+        _ => ErrorCode::StreamingError,
+    };
+    emit_error_code(
+        app,
+        code,
+        Some(TranscriptionProvider::ElevenLabs.id()),
+        &messages::fill(msg, &[("error", err)]),
+    );
+}
+
+/// Announce the recording and start its elapsed-time ticks (shared by both recording paths)
+fn begin_recording(app: &AppHandle, message: &str) {
+    cancel_overlay_hide(app);
+    emit_status(app, StatusPhase::Recording, Some(message));
+    start_recording_timer(app, None);
+}
+
+/// Handle hotkey release event - queued behind the press it ends
 pub fn handle_hotkey_released(app: &AppHandle) {
+    session_manager::send(app, HotkeyEventKind::Released);
+}
+
+/// Stop the dictation in progress; run by the session manager. Returns whether the
+/// recording went to the provider: false when it was dropped or had no audio.
+pub async fn release(app: &AppHandle) -> bool {
     let state: State<'_, AppState> = app.state();
+    stop_recording_timer(app);
+    // Taken now, before a new press can start the next session
+    let session = state.take_recording_session().unwrap_or_default();
 
     // For legacy recording mode, we need to stop the recording synchronously
     // to capture the audio data before it's lost
@@ -359,57 +779,260 @@ pub fn handle_hotkey_released(app: &AppHandle) {
         // Handle legacy recording stop synchronously
         match active.stop() {
             Ok(audio_wav) => {
+                let thresholds = state.current_settings().await.upload_thresholds();
+                if let Some(skip) = thresholds.check_wav(&audio_wav) {
+                    tracing::info!("[Hotkey] Recording not sent to the provider: {:?}", skip);
+                    emit_status(app, StatusPhase::Idle, Some(skip.message()));
+                    return false;
+                }
                 if state.is_transcribing().swap(true, Ordering::SeqCst) {
-                    return;
+                    return false;
                 }
-                emit_status(app, StatusPhase::Transcribing, Some("Uploading audio..."));
-                transcription::spawn_transcription(app, audio_wav);
+                emit_status(
+                    app,
+                    StatusPhase::Transcribing,
+                    Some(messages::text(Msg::UploadingAudio)),
+                );
+                transcription::spawn_transcription(app, audio_wav, session);
+                return true;
             }
-            Err(err) => emit_error(app, &err.to_string()),
+            Err(err) => emit_error_from(app, &err, None),
         }
-        return;
+        return false;
     }
 
-    // For ElevenLabs streaming, spawn async task
-    let app_clone = app.clone();
+    // For ElevenLabs streaming, close the gate before the next press is looked at
+    handle_hotkey_released_async(app, session)
+        .await
+        .unwrap_or_else(|err| {
+            emit_error_from(app, &err, None);
+            false
+        })
+}
+
+/// Deliver the last transcript again, e.g. into the window it should have gone to
+pub async fn repeat_last_delivery(app: &AppHandle) {
+    let state: State<'_, AppState> = app.state();
+    let Some(text) = state.last_delivery() else {
+        tracing::info!("[Hotkey] Double-tap, but nothing was delivered yet");
+        emit_status(
+            app,
+            StatusPhase::Idle,
+            Some(messages::text(Msg::NothingToRepeat)),
+        );
+        return;
+    };
+    tracing::info!("[Hotkey] Double-tap: delivering the last transcript again");
+    let settings = state.current_settings().await;
+    emit_status(app, StatusPhase::Delivering, None);
+    let delivery = transcription::deliver_text(app, &settings, text).await;
+    let message = messages::text(if delivery.elevated_target {
+        Msg::ElevatedTarget
+    } else {
+        Msg::DeliveredAgain
+    });
+    emit_status(app, StatusPhase::Success, Some(message));
+}
+
+/// Whether `pending` is the commit of `session`'s current segment
+fn is_commit_of(pending: &PendingCommit, session: &SessionContext) -> bool {
+    pending.session.id == session.id && pending.session.segment == session.segment
+}
+
+/// Force-clear `is_transcribing` if neither a committed transcript nor an error
+/// arrives for the commit of `session`, so new dictations aren't refused forever
+fn spawn_commit_watchdog(app: &AppHandle, session: SessionContext) {
+    let app = app.clone();
     tauri::async_runtime::spawn(async move {
-        if let Err(err) = handle_hotkey_released_async(&app_clone).await {
-            emit_error(&app_clone, &err.to_string());
+        tokio::time::sleep(COMMIT_WATCHDOG_TIMEOUT).await;
+
+        let state: State<'_, AppState> = app.state();
+        // The handler takes the oldest pending commit as soon as it picks it up
+        let stuck = state.pending_commits().lock().ok().and_then(|mut guard| {
+            if guard
+                .front()
+                .is_some_and(|pending| is_commit_of(pending, &session))
+            {
+                guard.pop_front()
+            } else {
+                None
+            }
+        });
+        let Some(stuck) = stuck else {
+            return;
+        };
+        tracing::warn!(
+            "[Hotkey] No transcript for session {} (segment {}) after {}s; resetting",
+            session.id,
+            session.segment,
+            COMMIT_WATCHDOG_TIMEOUT.as_secs()
+        );
+        emit_error_code(
+            &app,
+            ErrorCode::CommitTimeout,
+            Some(TranscriptionProvider::ElevenLabs.id()),
+            messages::text(Msg::NoStreamingTranscript),
+        );
+        // A lost segment leaves the dictation it belongs to running
+        if !stuck.continues {
+            state.is_transcribing().store(false, Ordering::SeqCst);
+            emit_status(
+                &app,
+                StatusPhase::Idle,
+                Some(messages::text(Msg::ReadyForNext)),
+            );
         }
     });
 }
 
-/// Async implementation of hotkey release handling for streaming mode
-async fn handle_hotkey_released_async(app: &AppHandle) -> Result<()> {
+/// Async implementation of hotkey release handling for streaming mode; true if a
+/// commit went out
+async fn handle_hotkey_released_async(app: &AppHandle, session: SessionContext) -> Result<bool> {
     let state: State<'_, AppState> = app.state();
     let settings = state.current_settings().await;
 
     // Check if using gated streaming for ElevenLabs
-    if settings.provider == TranscriptionProvider::ElevenLabs {
+    if session.uses_streaming(&settings) {
         let is_streaming_connected = state.elevenlabs_streaming().is_connected().await;
 
         if is_streaming_connected {
-            // If no audio was captured, don't send commit
-            let had_audio = state.elevenlabs_streaming().has_audio_since_open().await;
+            // If no, too little or only silent audio was captured, don't send commit
+            let chunks = state.elevenlabs_streaming().chunks_since_open().await;
+            let voiced = state.elevenlabs_streaming().audio_stats().voiced_chunks;
+            let thresholds = settings.upload_thresholds();
 
-            if !had_audio {
-                tracing::info!("[Hotkey] No audio since gate opened; closing gate without commit");
+            if let Some(skip) = thresholds.check_stream(chunks, voiced) {
+                tracing::info!(
+                    "[Hotkey] {} chunk(s) since gate opened, {} voiced, {} needed; closing gate without commit",
+                    chunks,
+                    voiced,
+                    thresholds.min_streaming_chunks()
+                );
                 let _ = state.elevenlabs_streaming().close_gate().await;
-                emit_status(app, StatusPhase::Idle, Some("Ready for next transcription"));
+                // The connection stays open for the next press
+                let message = if chunks == 0 {
+                    messages::text(Msg::ReadyForNext)
+                } else {
+                    skip.message()
+                };
+                emit_status(app, StatusPhase::Paused, Some(message));
+                return Ok(false);
             } else {
                 // Gated streaming mode - close gate and send commit
                 tracing::info!("[Hotkey] ElevenLabs gated streaming - closing gate and committing");
 
                 // Emit processing status BEFORE waiting for commit
-                emit_status(app, StatusPhase::Transcribing, Some("Processing..."));
+                emit_status(
+                    app,
+                    StatusPhase::Transcribing,
+                    Some(messages::text(Msg::Processing)),
+                );
+
+                if let Ok(mut guard) = state.pending_commits().lock() {
+                    guard.push_back(PendingCommit {
+                        session: session.clone(),
+                        timings: Timings::start(),
+                        recorded: session.gate_opened_at.map(|at| at.elapsed()),
+                        continues: false,
+                        audio: state.elevenlabs_streaming().audio_stats(),
+                    });
+                }
+                // Cleared by the committed-transcript and error handlers
+                state.is_transcribing().store(true, Ordering::SeqCst);
+                spawn_commit_watchdog(app, session);
 
-                if let Err(e) = state.elevenlabs_streaming().close_gate_and_commit().await {
-                    emit_error(app, &format!("Failed to close gate: {}", e));
-                    emit_status(app, StatusPhase::Idle, Some("Ready for next transcription"));
+                if let Err(e) = state
+                    .elevenlabs_streaming()
+                    .close_gate_and_commit(settings.commit_silence())
+                    .await
+                {
+                    state.is_transcribing().store(false, Ordering::SeqCst);
+                    emit_gate_error(app, Msg::GateCloseFailed, &e);
+                    emit_status(
+                        app,
+                        StatusPhase::Idle,
+                        Some(messages::text(Msg::ReadyForNext)),
+                    );
+                    return Ok(false);
                 }
+                return Ok(true);
             }
         }
     }
 
-    Ok(())
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(transcription_language: &str, target_language: &str) -> AppSettings {
+        AppSettings {
+            transcription_language: transcription_language.to_string(),
+            target_language: target_language.to_string(),
+            ..AppSettings::default()
+        }
+    }
+
+    #[test]
+    fn test_hotkey_bindings_only_see_hotkeys() {
+        let saved = AppSettings {
+            toggle_translate_hotkey: "Ctrl+Alt+T".to_string(),
+            ..AppSettings::default()
+        };
+        let mut edited = saved.clone();
+        edited.auto_translate = !saved.auto_translate;
+        edited.toggle_translate_hotkey = " Ctrl+Alt+T ".to_string();
+        assert_eq!(
+            HotkeyBindings::from_settings(&saved),
+            HotkeyBindings::from_settings(&edited)
+        );
+
+        edited.commit_segment_hotkey = "Ctrl+Alt+S".to_string();
+        assert_ne!(
+            HotkeyBindings::from_settings(&saved),
+            HotkeyBindings::from_settings(&edited)
+        );
+    }
+
+    #[test]
+    fn test_normal_dictation_keeps_live_language() {
+        let settings = settings("ru", "English");
+        assert_eq!(
+            streaming_language(&settings, false, Some("auto")),
+            StreamingLanguage {
+                code: "auto".to_string(),
+                reconnect: false,
+                in_target_language: false,
+            }
+        );
+        assert_eq!(streaming_language(&settings, false, None).code, "auto");
+    }
+
+    #[test]
+    fn test_translated_dictation_switches_to_configured_language() {
+        let settings = settings("ru", "English");
+        assert_eq!(
+            streaming_language(&settings, true, Some("auto")),
+            StreamingLanguage {
+                code: "ru".to_string(),
+                reconnect: true,
+                in_target_language: false,
+            }
+        );
+        assert!(!streaming_language(&settings, true, Some("ru")).reconnect);
+        assert!(!streaming_language(&settings, true, None).reconnect);
+    }
+
+    #[test]
+    fn test_translated_dictation_in_target_language_needs_no_translation() {
+        let language = streaming_language(&settings("en", "English"), true, Some("en"));
+        assert!(language.in_target_language);
+        assert!(!language.reconnect);
+
+        // Auto-detection says nothing about the language of the transcript
+        let language = streaming_language(&settings("auto", "English"), true, Some("auto"));
+        assert!(!language.in_target_language);
+    }
 }
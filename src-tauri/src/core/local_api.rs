@@ -0,0 +1,488 @@
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use futures::future::{BoxFuture, FutureExt};
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::settings::{AppSettings, TranscriptionProvider};
+
+use super::{
+    commands::{change_translation, TranslationChange},
+    hotkey,
+    session::DictationOptions,
+    state::AppState,
+};
+
+/// Requests with a longer head than this are rejected
+const MAX_HEAD_BYTES: usize = 8 * 1024;
+
+/// What the local API can do; the app drives the real dictation, tests a stand-in
+pub trait Controller: Send + Sync {
+    fn app_state(&self) -> &AppState;
+    fn start_dictation(&self);
+    fn stop_dictation(&self);
+    /// Toggle translation; returns whether it is on now
+    fn toggle_translate(&self) -> BoxFuture<'_, Result<bool>>;
+}
+
+impl Controller for AppHandle {
+    fn app_state(&self) -> &AppState {
+        Manager::state::<AppState>(self).inner()
+    }
+
+    fn start_dictation(&self) {
+        hotkey::handle_hotkey_pressed(self, DictationOptions::default());
+    }
+
+    fn stop_dictation(&self) {
+        hotkey::handle_hotkey_released(self);
+    }
+
+    fn toggle_translate(&self) -> BoxFuture<'_, Result<bool>> {
+        async move {
+            let settings =
+                change_translation(self, self.app_state(), TranslationChange::Toggle).await?;
+            Ok(settings.auto_translate)
+        }
+        .boxed()
+    }
+}
+
+/// Answer of `POST /status`
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiStatus {
+    /// Audio is being captured, by either recording path
+    pub recording: bool,
+    pub transcribing: bool,
+    pub mic_muted: bool,
+    pub auto_translate: bool,
+    pub target_language: String,
+    pub provider: TranscriptionProvider,
+}
+
+impl ApiStatus {
+    async fn collect(state: &AppState) -> Self {
+        let settings = state.current_settings().await;
+        let gate_open = state.elevenlabs_streaming().state().await.transmitting;
+        let recording = state
+            .active_recording()
+            .lock()
+            .is_ok_and(|guard| guard.is_some());
+        Self {
+            recording: recording || gate_open,
+            transcribing: state
+                .is_transcribing()
+                .load(std::sync::atomic::Ordering::SeqCst),
+            mic_muted: state.mic_mute().is_muted(),
+            auto_translate: settings.auto_translate,
+            target_language: settings.target_language.clone(),
+            provider: settings.provider,
+        }
+    }
+}
+
+/// Where the local API listens
+#[derive(Debug, Clone, Serialize)]
+pub struct LocalApiInfo {
+    pub enabled: bool,
+    /// None while the server is off or failed to start
+    pub port: Option<u16>,
+    /// The bearer token, only when it was just generated: it isn't shown again
+    pub token: Option<String>,
+}
+
+/// A new random token for `local_api_token`
+pub fn generate_token() -> Result<String> {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).context("Failed to generate a local API token")?;
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+struct Running {
+    port: u16,
+    /// `local_api_port` and token it was started with, to tell when to restart
+    configured_port: u16,
+    token: String,
+    cancel: CancellationToken,
+}
+
+/// The local HTTP API (`local_api_enabled`), started and stopped with the settings
+#[derive(Default)]
+pub struct LocalApiServer {
+    running: Mutex<Option<Running>>,
+}
+
+impl LocalApiServer {
+    /// Port the API listens on; None while it is off
+    pub fn port(&self) -> Option<u16> {
+        self.running
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+            .map(|running| running.port)
+    }
+
+    /// Start, restart or stop the server to match `settings`
+    pub async fn apply(
+        &self,
+        controller: Arc<dyn Controller>,
+        settings: &AppSettings,
+    ) -> Result<()> {
+        let wanted = settings.local_api_enabled && !settings.local_api_token.is_empty();
+        let unchanged = self
+            .running
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+            .is_some_and(|running| {
+                running.configured_port == settings.local_api_port
+                    && running.token == settings.local_api_token
+            });
+        if wanted && unchanged {
+            return Ok(());
+        }
+        self.stop();
+        if !wanted {
+            return Ok(());
+        }
+
+        let listener = bind(settings.local_api_port).await?;
+        let port = listener.local_addr()?.port();
+        let cancel = CancellationToken::new();
+        tauri::async_runtime::spawn(serve(
+            listener,
+            settings.local_api_token.clone(),
+            controller,
+            cancel.clone(),
+        ));
+        tracing::info!("[LocalApi] Listening on 127.0.0.1:{}", port);
+        *self.running.lock().unwrap_or_else(PoisonError::into_inner) = Some(Running {
+            port,
+            configured_port: settings.local_api_port,
+            token: settings.local_api_token.clone(),
+            cancel,
+        });
+        Ok(())
+    }
+
+    pub fn stop(&self) {
+        if let Some(running) = self
+            .running
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+        {
+            running.cancel.cancel();
+            tracing::info!("[LocalApi] Stopped");
+        }
+    }
+}
+
+/// Apply `settings` to the app's local API server, logging a failure to start it
+pub async fn apply_settings(app: &AppHandle, settings: &AppSettings) {
+    if let Err(e) = app
+        .app_state()
+        .local_api()
+        .apply(Arc::new(app.clone()), settings)
+        .await
+    {
+        tracing::warn!("[LocalApi] Failed to start: {:#}", e);
+    }
+}
+
+/// Listener on the loopback interface only; port 0 picks a free one
+pub async fn bind(port: u16) -> Result<TcpListener> {
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to bind the local API to {addr}"))
+}
+
+/// Accept connections until `cancel` fires, one task per connection
+pub async fn serve(
+    listener: TcpListener,
+    token: String,
+    controller: Arc<dyn Controller>,
+    cancel: CancellationToken,
+) {
+    let token = Arc::new(token);
+    loop {
+        let stream = tokio::select! {
+            _ = cancel.cancelled() => return,
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    tracing::warn!("[LocalApi] Accept failed: {}", e);
+                    continue;
+                }
+            },
+        };
+        let token = Arc::clone(&token);
+        let controller = Arc::clone(&controller);
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, &token, controller.as_ref()).await {
+                tracing::debug!("[LocalApi] Connection failed: {:#}", e);
+            }
+        });
+    }
+}
+
+/// Method, path and bearer token of a request
+struct RequestHead {
+    method: String,
+    path: String,
+    token: Option<String>,
+}
+
+async fn read_head(stream: &mut TcpStream) -> Result<Option<RequestHead>> {
+    // A head cut off by the limit ends like a closed connection
+    let mut reader = BufReader::new(stream.take(MAX_HEAD_BYTES as u64));
+    let mut line = String::new();
+    let mut head: Option<RequestHead> = None;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).await? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            return Ok(head);
+        }
+        match head.as_mut() {
+            None => {
+                let mut parts = line.split_whitespace();
+                let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+                    return Ok(None);
+                };
+                head = Some(RequestHead {
+                    method: method.to_string(),
+                    path: path.split('?').next().unwrap_or_default().to_string(),
+                    token: None,
+                });
+            }
+            Some(head) => {
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.trim().eq_ignore_ascii_case("authorization") {
+                        head.token = value
+                            .trim()
+                            .strip_prefix("Bearer ")
+                            .map(|token| token.trim().to_string());
+                    }
+                }
+            }
+        }
+    }
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    token: &str,
+    controller: &dyn Controller,
+) -> Result<()> {
+    let (status, body) = match read_head(&mut stream).await? {
+        None => (400, json!({ "error": "Malformed request" })),
+        Some(head) => respond(&head, token, controller).await,
+    };
+    let body = body.to_string();
+    let response = format!(
+        "HTTP/1.1 {status} {}\r\ncontent-type: application/json\r\n\
+         content-length: {}\r\nconnection: close\r\n\r\n{body}",
+        reason(status),
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+async fn respond(head: &RequestHead, token: &str, controller: &dyn Controller) -> (u16, Value) {
+    if !head
+        .token
+        .as_deref()
+        .is_some_and(|given| tokens_match(given, token))
+    {
+        return (401, json!({ "error": "Missing or wrong token" }));
+    }
+    if head.method != "POST" {
+        return (405, json!({ "error": "Use POST" }));
+    }
+    match head.path.trim_end_matches('/') {
+        "/dictation/start" => {
+            controller.start_dictation();
+            (200, json!({ "ok": true }))
+        }
+        "/dictation/stop" => {
+            controller.stop_dictation();
+            (200, json!({ "ok": true }))
+        }
+        "/translate/toggle" => match controller.toggle_translate().await {
+            Ok(auto_translate) => (200, json!({ "auto_translate": auto_translate })),
+            Err(e) => (500, json!({ "error": format!("{e:#}") })),
+        },
+        "/status" => {
+            let status = ApiStatus::collect(controller.app_state()).await;
+            (200, serde_json::to_value(status).unwrap_or_default())
+        }
+        "/transcript/last" => (
+            200,
+            json!({ "text": controller.app_state().last_delivery() }),
+        ),
+        _ => (404, json!({ "error": "No such endpoint" })),
+    }
+}
+
+/// Compare without stopping at the first difference, so timing doesn't leak the token
+fn tokens_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::settings::SettingsStore;
+
+    /// Stands in for the app: a real state with the Mock provider, counting the
+    /// dictations it was asked to start and stop
+    struct FakeApp {
+        state: AppState,
+        started: AtomicUsize,
+        stopped: AtomicUsize,
+    }
+
+    impl Controller for FakeApp {
+        fn app_state(&self) -> &AppState {
+            &self.state
+        }
+
+        fn start_dictation(&self) {
+            self.started.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn stop_dictation(&self) {
+            self.stopped.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn toggle_translate(&self) -> BoxFuture<'_, Result<bool>> {
+            async move {
+                let settings = self
+                    .state
+                    .update_settings(|s| s.auto_translate = !s.auto_translate)
+                    .await;
+                Ok(settings.auto_translate)
+            }
+            .boxed()
+        }
+    }
+
+    const TOKEN: &str = "test-token";
+
+    async fn start_server() -> (Arc<FakeApp>, String, CancellationToken) {
+        let root =
+            std::env::temp_dir().join(format!("easy-dictate-local-api-{}", std::process::id()));
+        let settings = AppSettings {
+            provider: TranscriptionProvider::Mock,
+            ..AppSettings::default()
+        };
+        let app = Arc::new(FakeApp {
+            state: AppState::new(SettingsStore::without_keyring(root), settings).unwrap(),
+            started: AtomicUsize::new(0),
+            stopped: AtomicUsize::new(0),
+        });
+        let listener = bind(0).await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let cancel = CancellationToken::new();
+        tokio::spawn(serve(
+            listener,
+            TOKEN.to_string(),
+            app.clone(),
+            cancel.clone(),
+        ));
+        (app, url, cancel)
+    }
+
+    async fn post(url: &str, path: &str, token: Option<&str>) -> (u16, Value) {
+        let mut request = reqwest::Client::new().post(format!("{url}{path}"));
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        let response = request.send().await.unwrap();
+        let status = response.status().as_u16();
+        (status, response.json().await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_requests_need_the_token() {
+        let (app, url, cancel) = start_server().await;
+        assert_eq!(post(&url, "/dictation/start", None).await.0, 401);
+        assert_eq!(post(&url, "/dictation/start", Some("wrong")).await.0, 401);
+        assert_eq!(app.started.load(Ordering::SeqCst), 0);
+        cancel.cancel();
+    }
+
+    #[tokio::test]
+    async fn test_endpoints_drive_the_app() {
+        let (app, url, cancel) = start_server().await;
+        let token = Some(TOKEN);
+
+        assert_eq!(post(&url, "/dictation/start", token).await.0, 200);
+        assert_eq!(post(&url, "/dictation/stop", token).await.0, 200);
+        assert_eq!(app.started.load(Ordering::SeqCst), 1);
+        assert_eq!(app.stopped.load(Ordering::SeqCst), 1);
+
+        let (status, body) = post(&url, "/translate/toggle", token).await;
+        assert_eq!(status, 200);
+        assert_eq!(body["auto_translate"], true);
+
+        let (_, body) = post(&url, "/status", token).await;
+        assert_eq!(body["provider"], "mock");
+        assert_eq!(body["recording"], false);
+        assert_eq!(body["auto_translate"], true);
+
+        let (_, body) = post(&url, "/transcript/last", token).await;
+        assert_eq!(body["text"], Value::Null);
+        app.state.record_delivery("Hello");
+        let (_, body) = post(&url, "/transcript/last", token).await;
+        assert_eq!(body["text"], "Hello");
+
+        assert_eq!(post(&url, "/nothing", token).await.0, 404);
+        cancel.cancel();
+    }
+
+    #[test]
+    fn test_generated_tokens_differ() {
+        let token = generate_token().unwrap();
+        assert_eq!(token.len(), 43);
+        assert_ne!(token, generate_token().unwrap());
+        assert!(tokens_match(&token, &token));
+        assert!(!tokens_match(&token, &token[1..]));
+    }
+}
@@ -1,7 +1,43 @@
+pub mod app_rules;
+pub mod audio_supervisor;
+pub mod client_factory;
 pub mod commands;
+pub mod connect_breaker;
+pub mod delivery;
+pub mod diagnostics;
+pub mod dictation_session;
 pub mod error;
 pub mod events;
+pub mod export;
+pub mod file_transcription;
 pub mod hotkey;
+pub mod local_api;
+pub mod model_catalog;
+pub mod notify;
+pub mod overlay;
+pub mod permissions;
+pub mod post_command;
+pub mod preconnect;
+pub mod pseudo_streaming;
+pub mod recording_timer;
+pub mod retranscribe;
+pub mod session;
+pub mod session_manager;
+pub mod shutdown;
+pub mod speak;
+pub mod startup;
 pub mod state;
+pub mod stats;
+pub mod status_watchdog;
+pub mod streaming_idle;
+pub mod support_bundle;
+// Only the debug-build test commands use it
+#[cfg_attr(not(debug_assertions), allow(dead_code))]
+pub mod test_state;
+pub mod timings;
+pub mod transcript_log;
 pub mod transcription;
 pub mod tray;
+pub mod updater;
+pub mod vocabulary;
+pub mod window_state;
@@ -0,0 +1,322 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::settings::TranscriptionProvider;
+
+use super::state::AppState;
+
+/// A provider's model list is fetched again after this
+pub const MODEL_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Prefix that routes a transcription model to Groq (see `GroqClient::transcribe`)
+const GROQ_PREFIX: &str = "groq/";
+/// Ids containing one of these are neither transcription nor chat models
+const UNRELATED: &[&str] = &[
+    "tts",
+    "embedding",
+    "dall-e",
+    "image",
+    "moderation",
+    "realtime",
+    "audio",
+    "search",
+    "guard",
+    "davinci",
+    "babbage",
+    "sora",
+];
+/// OpenAI chat model families; the rest of its list is legacy or unrelated
+const OPENAI_CHAT_PREFIXES: &[&str] = &["gpt-", "chatgpt-", "o1", "o3", "o4"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelKind {
+    /// Goes in the `model` setting
+    Transcription,
+    /// Goes in the `llm_model` setting
+    Chat,
+}
+
+/// A model the settings can pick, with `id` as it is stored in the settings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ModelOption {
+    pub id: String,
+    pub kind: ModelKind,
+}
+
+/// Why a model list came back empty
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelListReason {
+    MissingApiKey,
+    RequestFailed,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ModelList {
+    pub models: Vec<ModelOption>,
+    pub reason: Option<ModelListReason>,
+}
+
+impl ModelList {
+    fn unavailable(reason: ModelListReason) -> Self {
+        Self {
+            models: Vec::new(),
+            reason: Some(reason),
+        }
+    }
+}
+
+fn classify(provider: &TranscriptionProvider, id: &str) -> Option<ModelOption> {
+    match provider {
+        // Only its speech-to-text models are listed
+        TranscriptionProvider::ElevenLabs => {
+            return Some(ModelOption {
+                id: id.to_string(),
+                kind: ModelKind::Transcription,
+            })
+        }
+        TranscriptionProvider::Mock => return None,
+        TranscriptionProvider::OpenAI | TranscriptionProvider::Groq => {}
+    }
+    let lower = id.to_lowercase();
+    if lower.contains("whisper") || lower.contains("transcribe") {
+        let id = match provider {
+            TranscriptionProvider::Groq => format!("{GROQ_PREFIX}{id}"),
+            _ => id.to_string(),
+        };
+        return Some(ModelOption {
+            id,
+            kind: ModelKind::Transcription,
+        });
+    }
+    if UNRELATED.iter().any(|word| lower.contains(word)) {
+        return None;
+    }
+    let chat = *provider == TranscriptionProvider::Groq
+        || OPENAI_CHAT_PREFIXES
+            .iter()
+            .any(|prefix| lower.starts_with(prefix));
+    chat.then(|| ModelOption {
+        id: id.to_string(),
+        kind: ModelKind::Chat,
+    })
+}
+
+/// The transcription and chat models among `ids`, transcription models first
+fn catalog(provider: &TranscriptionProvider, ids: &[String]) -> Vec<ModelOption> {
+    let mut models: Vec<ModelOption> = ids.iter().filter_map(|id| classify(provider, id)).collect();
+    models.sort_by(|a, b| (a.kind, &a.id).cmp(&(b.kind, &b.id)));
+    models.dedup();
+    models
+}
+
+/// Providers whose list comes from their API (and needs a key) rather than a fixed set
+fn lists_remotely(provider: &TranscriptionProvider) -> bool {
+    matches!(
+        provider,
+        TranscriptionProvider::OpenAI | TranscriptionProvider::Groq
+    )
+}
+
+struct CachedModels {
+    api_key: String,
+    fetched_at: Instant,
+    models: Vec<ModelOption>,
+}
+
+/// Model lists fetched in the last `MODEL_CACHE_TTL`, per provider. An entry is
+/// only used with the key it was fetched with.
+#[derive(Default)]
+pub struct ModelCache {
+    entries: Mutex<HashMap<TranscriptionProvider, CachedModels>>,
+}
+
+impl ModelCache {
+    fn get(
+        &self,
+        provider: &TranscriptionProvider,
+        api_key: &str,
+        now: Instant,
+    ) -> Option<Vec<ModelOption>> {
+        let entries = self.entries.lock().ok()?;
+        let cached = entries.get(provider)?;
+        let fresh = now.saturating_duration_since(cached.fetched_at) < MODEL_CACHE_TTL;
+        (fresh && cached.api_key == api_key).then(|| cached.models.clone())
+    }
+
+    fn put(
+        &self,
+        provider: TranscriptionProvider,
+        api_key: &str,
+        now: Instant,
+        models: Vec<ModelOption>,
+    ) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(
+                provider,
+                CachedModels {
+                    api_key: api_key.to_string(),
+                    fetched_at: now,
+                    models,
+                },
+            );
+        }
+    }
+}
+
+/// Models `provider` offers with the configured key. A missing key or a failed
+/// request comes back as an empty list with a reason, so the settings stay usable.
+pub async fn list_models(state: &AppState, provider: TranscriptionProvider) -> ModelList {
+    let settings = state.current_settings().await;
+    let api_key = settings.provider_key(&provider);
+    let remote = lists_remotely(&provider);
+    if remote && api_key.is_empty() {
+        return ModelList::unavailable(ModelListReason::MissingApiKey);
+    }
+    if let Some(models) = state.model_cache().get(&provider, &api_key, Instant::now()) {
+        return ModelList {
+            models,
+            reason: None,
+        };
+    }
+
+    match state.transcription().list_models(&provider, &api_key).await {
+        Ok(ids) => {
+            let models = catalog(&provider, &ids);
+            if remote {
+                state
+                    .model_cache()
+                    .put(provider, &api_key, Instant::now(), models.clone());
+            }
+            ModelList {
+                models,
+                reason: None,
+            }
+        }
+        Err(e) => {
+            tracing::warn!(
+                "[Models] Failed to list {} models: {:#}",
+                provider.display_name(),
+                e
+            );
+            ModelList::unavailable(ModelListReason::RequestFailed)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn option(id: &str, kind: ModelKind) -> ModelOption {
+        ModelOption {
+            id: id.to_string(),
+            kind,
+        }
+    }
+
+    #[test]
+    fn test_openai_catalog_keeps_audio_and_chat_models() {
+        let models = catalog(
+            &TranscriptionProvider::OpenAI,
+            &ids(&[
+                "gpt-4o-mini",
+                "text-embedding-3-small",
+                "whisper-1",
+                "gpt-4o-transcribe",
+                "tts-1",
+                "dall-e-3",
+                "gpt-4o-realtime-preview",
+                "o3-mini",
+                "davinci-002",
+                "gpt-4o-mini",
+            ]),
+        );
+        assert_eq!(
+            models,
+            vec![
+                option("gpt-4o-transcribe", ModelKind::Transcription),
+                option("whisper-1", ModelKind::Transcription),
+                option("gpt-4o-mini", ModelKind::Chat),
+                option("o3-mini", ModelKind::Chat),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_groq_transcription_models_get_the_prefix() {
+        let models = catalog(
+            &TranscriptionProvider::Groq,
+            &ids(&[
+                "llama-3.1-8b-instant",
+                "whisper-large-v3-turbo",
+                "playai-tts",
+                "meta-llama/llama-guard-4-12b",
+                "openai/gpt-oss-20b",
+            ]),
+        );
+        assert_eq!(
+            models,
+            vec![
+                option("groq/whisper-large-v3-turbo", ModelKind::Transcription),
+                option("llama-3.1-8b-instant", ModelKind::Chat),
+                option("openai/gpt-oss-20b", ModelKind::Chat),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_elevenlabs_models_are_transcription_models() {
+        let models = catalog(
+            &TranscriptionProvider::ElevenLabs,
+            &ids(crate::elevenlabs::MODELS),
+        );
+        assert!(!models.is_empty());
+        assert!(models.iter().all(|m| m.kind == ModelKind::Transcription));
+    }
+
+    #[test]
+    fn test_cache_expires_and_follows_the_key() {
+        let cache = ModelCache::default();
+        let now = Instant::now();
+        let models = vec![option("whisper-1", ModelKind::Transcription)];
+        cache.put(TranscriptionProvider::OpenAI, "sk-1", now, models.clone());
+
+        let later = now + MODEL_CACHE_TTL - Duration::from_secs(1);
+        assert_eq!(
+            cache.get(&TranscriptionProvider::OpenAI, "sk-1", later),
+            Some(models)
+        );
+        assert_eq!(
+            cache.get(&TranscriptionProvider::OpenAI, "sk-2", later),
+            None
+        );
+        assert_eq!(cache.get(&TranscriptionProvider::Groq, "sk-1", later), None);
+        assert_eq!(
+            cache.get(
+                &TranscriptionProvider::OpenAI,
+                "sk-1",
+                now + MODEL_CACHE_TTL
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_missing_key_reason_serializes_as_snake_case() {
+        let json =
+            serde_json::to_value(ModelList::unavailable(ModelListReason::MissingApiKey)).unwrap();
+        assert_eq!(json["reason"], "missing_api_key");
+        assert_eq!(json["models"], serde_json::json!([]));
+    }
+}
@@ -0,0 +1,182 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use tauri::{AppHandle, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+use super::{state::AppState, tray};
+
+/// Transcript preview length in the completion notification
+const PREVIEW_MAX_CHARS: usize = 100;
+
+/// Main window focus this soon after a notification is taken as a click on it
+const CLICK_WINDOW: Duration = Duration::from_secs(30);
+
+/// When the last notification was shown, until the next window focus settles it
+static SHOWN_AT: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Shorten the transcript to a single-line preview for the notification body
+fn preview(text: &str) -> String {
+    let flat = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if flat.chars().count() <= PREVIEW_MAX_CHARS {
+        return flat;
+    }
+    let cut: String = flat.chars().take(PREVIEW_MAX_CHARS).collect();
+    format!("{}…", cut.trim_end())
+}
+
+/// The settings window already shows the result, so don't duplicate it
+fn main_window_focused(app: &AppHandle) -> bool {
+    app.get_webview_window("main")
+        .and_then(|window| window.is_focused().ok())
+        .unwrap_or(false)
+}
+
+fn show(app: &AppHandle, title: &str, body: &str) {
+    match app.notification().builder().title(title).body(body).show() {
+        Ok(()) => mark_shown(Instant::now()),
+        Err(e) => tracing::warn!("[Notify] Failed to show notification: {}", e),
+    }
+}
+
+fn mark_shown(at: Instant) {
+    if let Ok(mut shown_at) = SHOWN_AT.lock() {
+        *shown_at = Some(at);
+    }
+}
+
+/// Whether window `label` gaining focus at `now` answers the notification shown at
+/// `shown_at`. Any focus settles the notification: once the overlay or another
+/// window has taken focus, a later main window focus isn't the click.
+fn claims_click(shown_at: &mut Option<Instant>, label: &str, now: Instant) -> bool {
+    shown_at
+        .take()
+        .is_some_and(|at| label == "main" && now.saturating_duration_since(at) <= CLICK_WINDOW)
+}
+
+/// Window `label` gained focus. The plugin reports no clicks on desktop, but clicking
+/// a notification activates the app, so the main window gaining focus right after one
+/// opens it with the history.
+pub fn window_focused(app: &AppHandle, label: &str) {
+    let clicked = SHOWN_AT
+        .lock()
+        .is_ok_and(|mut shown_at| claims_click(&mut shown_at, label, Instant::now()));
+    if clicked {
+        tracing::info!("[Notify] App activated after a notification, showing history");
+        tray::show_settings_window(app);
+    }
+}
+
+/// The app was activated without a window taking focus (the macOS Dock icon)
+pub fn app_activated(app: &AppHandle) {
+    window_focused(app, "main");
+}
+
+/// Which setting gates a notification
+#[derive(Clone, Copy)]
+enum NotifyKind {
+    Complete,
+    Error,
+}
+
+fn notify(app: &AppHandle, kind: NotifyKind, title: &'static str, body: String) {
+    let app = app.clone();
+    // Settings live behind an async lock and the emitters are called from sync code
+    tauri::async_runtime::spawn(async move {
+        let Some(state) = app.try_state::<AppState>() else {
+            return;
+        };
+        let settings = state.current_settings().await;
+        let enabled = match kind {
+            NotifyKind::Complete => settings.notify_on_complete,
+            NotifyKind::Error => settings.notify_on_error,
+        };
+        if enabled && !main_window_focused(&app) {
+            show(&app, title, &body);
+        }
+    });
+}
+
+pub fn notify_complete(app: &AppHandle, text: &str) {
+    if text.trim().is_empty() {
+        return;
+    }
+    notify(
+        app,
+        NotifyKind::Complete,
+        "Transcription complete",
+        preview(text),
+    );
+}
+
+pub fn notify_error(app: &AppHandle, message: &str) {
+    notify(
+        app,
+        NotifyKind::Error,
+        "Easy Dictate error",
+        message.to_string(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::overlay::OVERLAY_LABEL;
+
+    #[test]
+    fn test_preview_keeps_short_text() {
+        assert_eq!(preview("  Привет,\nмир  "), "Привет, мир");
+    }
+
+    #[test]
+    fn test_activation_after_a_notification_is_a_click() {
+        let now = Instant::now();
+        let mut shown_at = Some(now);
+        assert!(claims_click(
+            &mut shown_at,
+            "main",
+            now + Duration::from_secs(5)
+        ));
+        // Claimed once
+        assert!(!claims_click(
+            &mut shown_at,
+            "main",
+            now + Duration::from_secs(6)
+        ));
+
+        let mut shown_at = Some(now);
+        assert!(!claims_click(
+            &mut shown_at,
+            "main",
+            now + CLICK_WINDOW + Duration::from_secs(1)
+        ));
+    }
+
+    #[test]
+    fn test_other_window_focus_is_not_a_click() {
+        let now = Instant::now();
+        let mut shown_at = Some(now);
+        // Dragging the overlay right after a notification
+        assert!(!claims_click(
+            &mut shown_at,
+            OVERLAY_LABEL,
+            now + Duration::from_secs(1)
+        ));
+        // ...and switching back to the main window later doesn't count either
+        assert!(!claims_click(
+            &mut shown_at,
+            "main",
+            now + Duration::from_secs(2)
+        ));
+    }
+
+    #[test]
+    fn test_preview_truncates_long_text() {
+        let text = "слово ".repeat(40);
+        let result = preview(&text);
+        assert!(result.ends_with('…'));
+        assert!(result.chars().count() <= PREVIEW_MAX_CHARS + 1);
+    }
+}
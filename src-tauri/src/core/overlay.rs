@@ -0,0 +1,353 @@
+use std::time::Duration;
+
+use tauri::{AppHandle, Listener, Manager, State, WebviewWindow};
+
+use crate::settings::OverlayPosition;
+
+use super::{
+    events::emit_overlay_interactive,
+    state::AppState,
+    window_state::{self, clamp_to_monitors, Rect, WindowState},
+};
+
+pub const OVERLAY_LABEL: &str = "overlay";
+
+/// Sent by the overlay webview when the user stops dragging it, with the window's
+/// new top-left corner (`OverlayPosition`)
+pub const EVENT_OVERLAY_MOVED: &str = "overlay://moved";
+
+/// Overlay size and distance from the bottom of the screen, in logical pixels
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+const OVERLAY_WIDTH: f64 = 600.0;
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+const OVERLAY_HEIGHT: f64 = 150.0;
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+const BOTTOM_MARGIN: f64 = 60.0;
+
+/// Errors are shown briefly; the message also lands in the main window and notifications
+const ERROR_HIDE_DELAY: Duration = Duration::from_millis(1500);
+
+/// Hide the overlay once the result has been shown for `overlay_hide_after_ms`
+pub fn hide_overlay_after_complete(app: &AppHandle) {
+    schedule_hide(app, None);
+}
+
+pub fn hide_overlay_after_error(app: &AppHandle) {
+    schedule_hide(app, Some(ERROR_HIDE_DELAY));
+}
+
+/// Invalidate any pending hide, e.g. because a new recording just started
+pub fn cancel_overlay_hide(app: &AppHandle) {
+    if let Some(state) = app.try_state::<AppState>() {
+        state.next_overlay_generation();
+    }
+}
+
+/// Hide the overlay after `delay` (the configured preview duration if None) unless
+/// a new recording starts first. The webview fades itself out too, but the backend
+/// owns the final hide so the overlay never sticks around when the webview misses an event.
+fn schedule_hide(app: &AppHandle, delay: Option<Duration>) {
+    let Some(state) = app.try_state::<AppState>() else {
+        return;
+    };
+    // Taken synchronously so a recording that starts while we sleep cancels this hide
+    let generation = state.next_overlay_generation();
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let state: State<'_, AppState> = app.state();
+        let delay = match delay {
+            Some(delay) => delay,
+            None => Duration::from_millis(state.current_settings().await.overlay_hide_after_ms),
+        };
+        tokio::time::sleep(delay).await;
+
+        if !state.is_overlay_generation(generation) {
+            tracing::debug!("[Overlay] Hide cancelled by a newer session");
+            return;
+        }
+        if state.is_overlay_interactive() {
+            tracing::debug!("[Overlay] Hide skipped while the overlay is being placed");
+            return;
+        }
+        if let Some(window) = app.get_webview_window(OVERLAY_LABEL) {
+            // Hiding never activates a window, so focus stays with the user's app
+            if let Err(e) = window.hide() {
+                tracing::warn!("[Overlay] Failed to hide overlay: {}", e);
+            }
+        }
+    });
+}
+
+/// Top-left corner that centres a `width` x `height` window horizontally in `area`,
+/// `margin` above its bottom edge
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn bottom_center(area: Rect, width: u32, height: u32, margin: u32) -> (i32, i32) {
+    let x = i64::from(area.x) + (i64::from(area.width) - i64::from(width)) / 2;
+    let y = i64::from(area.y) + i64::from(area.height) - i64::from(height) - i64::from(margin);
+    // Never above the top of the work area on very short screens
+    (x as i32, y.max(i64::from(area.y)) as i32)
+}
+
+/// Top-left corner for an overlay of `width` x `height` pinned at `pin`: moved fully
+/// onto the monitor it overlaps most, or onto the first (primary) one when the
+/// monitor it was pinned on is gone
+fn pinned_position(pin: OverlayPosition, width: u32, height: u32, monitors: &[Rect]) -> (i32, i32) {
+    let placed = clamp_to_monitors(
+        WindowState {
+            x: pin.x,
+            y: pin.y,
+            width,
+            height,
+            maximized: false,
+        },
+        monitors,
+    );
+    (placed.x, placed.y)
+}
+
+/// Where to show the overlay if the user pinned it, given the monitors connected now;
+/// None for the automatic placement
+pub fn pinned_placement(
+    window: &WebviewWindow,
+    pin: Option<OverlayPosition>,
+) -> Option<(i32, i32)> {
+    let pin = pin?;
+    let size = window
+        .outer_size()
+        .inspect_err(|e| tracing::warn!("[Overlay] Failed to get overlay size: {}", e))
+        .ok()?;
+    Some(pinned_position(
+        pin,
+        size.width,
+        size.height,
+        &window_state::monitors(window),
+    ))
+}
+
+/// Let the overlay take the mouse so the user can drag it (it then stays up), or
+/// make it click-through again
+pub fn set_interactive(app: &AppHandle, interactive: bool) -> anyhow::Result<()> {
+    let state: State<'_, AppState> = app.state();
+    state.set_overlay_interactive(interactive);
+    if let Some(window) = app.get_webview_window(OVERLAY_LABEL) {
+        window.set_ignore_cursor_events(!interactive)?;
+    }
+    emit_overlay_interactive(app, interactive);
+    Ok(())
+}
+
+/// Save `position` as where the overlay is shown, or go back to the automatic
+/// placement with None
+pub async fn pin(app: &AppHandle, position: Option<OverlayPosition>) -> anyhow::Result<()> {
+    let state: State<'_, AppState> = app.state();
+    state.wait_for_settings_loaded().await;
+    let _apply = state.lock_settings_apply().await;
+    let settings = state
+        .update_settings(|s| s.overlay_position = position)
+        .await;
+    state.persist_settings(&settings).await?;
+    tracing::info!("[Overlay] Overlay position set to {:?}", position);
+    Ok(())
+}
+
+/// Pin the overlay where the user drops it. Moves outside of placing are ignored:
+/// the overlay also moves whenever it is shown at its automatic position.
+pub fn listen_for_moves(app: &AppHandle) {
+    let handle = app.clone();
+    app.listen(EVENT_OVERLAY_MOVED, move |event| {
+        let state: State<'_, AppState> = handle.state();
+        if !state.is_overlay_interactive() {
+            return;
+        }
+        let position: OverlayPosition = match serde_json::from_str(event.payload()) {
+            Ok(position) => position,
+            Err(e) => {
+                tracing::warn!(
+                    "[Overlay] Ignoring malformed move {:?}: {}",
+                    event.payload(),
+                    e
+                );
+                return;
+            }
+        };
+        let app = handle.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = pin(&app, Some(position)).await {
+                tracing::error!("[Overlay] Failed to save overlay position: {:#}", e);
+            }
+        });
+    });
+}
+
+/// Show the overlay at `pinned`, or else on the screen under the cursor, without
+/// activating it, so the app being dictated into keeps keyboard focus.
+/// `window.show()` would make the overlay the key window.
+#[cfg(target_os = "macos")]
+pub fn show_without_focus(
+    window: &tauri::WebviewWindow,
+    pinned: Option<(i32, i32)>,
+    click_through: bool,
+) -> anyhow::Result<()> {
+    use tauri::PhysicalPosition;
+
+    let app = window.app_handle();
+    let cursor = app.cursor_position()?;
+    let monitor = match app.monitor_from_point(cursor.x, cursor.y)? {
+        Some(monitor) => Some(monitor),
+        None => app.primary_monitor()?,
+    };
+    if let Some((x, y)) = pinned {
+        tracing::info!("[Overlay] Positioning overlay at pinned ({}, {})", x, y);
+        window.set_position(PhysicalPosition::new(x, y))?;
+    } else if let Some(monitor) = monitor {
+        let scale = monitor.scale_factor();
+        let area = monitor.work_area();
+        let (x, y) = bottom_center(
+            Rect {
+                x: area.position.x,
+                y: area.position.y,
+                width: area.size.width,
+                height: area.size.height,
+            },
+            (OVERLAY_WIDTH * scale).round() as u32,
+            (OVERLAY_HEIGHT * scale).round() as u32,
+            (BOTTOM_MARGIN * scale).round() as u32,
+        );
+        tracing::info!(
+            "[Overlay] Positioning overlay at ({}, {}) on monitor with cursor",
+            x,
+            y
+        );
+        window.set_position(PhysicalPosition::new(x, y))?;
+    }
+    window.set_ignore_cursor_events(click_through)?;
+
+    // AppKit must be called on the main thread
+    let target = window.clone();
+    window.run_on_main_thread(move || match target.ns_window() {
+        // SAFETY: the pointer is the overlay's live NSWindow and we are on the main thread
+        Ok(ns_window) => unsafe { macos::order_front_without_activation(ns_window) },
+        Err(e) => tracing::warn!("[Overlay] Failed to get NSWindow for overlay: {}", e),
+    })?;
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use std::ffi::c_void;
+
+    use objc2::{msg_send, runtime::AnyObject};
+
+    /// `NSStatusWindowLevel`: above normal and floating windows, including full-screen apps
+    const STATUS_WINDOW_LEVEL: isize = 25;
+
+    /// `NSWindowCollectionBehavior` flags
+    const CAN_JOIN_ALL_SPACES: usize = 1 << 0;
+    const STATIONARY: usize = 1 << 4;
+    const IGNORES_CYCLE: usize = 1 << 6;
+    const FULL_SCREEN_AUXILIARY: usize = 1 << 8;
+
+    /// Float the window on every Space (next to full-screen apps too), keep it out of
+    /// Cmd+` cycling and bring it to the front without making it key or activating the app.
+    ///
+    /// # Safety
+    /// `ns_window` must point to a live `NSWindow`; must be called on the main thread.
+    pub unsafe fn order_front_without_activation(ns_window: *mut c_void) {
+        let ns_window = ns_window.cast::<AnyObject>();
+        let behavior = CAN_JOIN_ALL_SPACES | STATIONARY | IGNORES_CYCLE | FULL_SCREEN_AUXILIARY;
+        let _: () = msg_send![ns_window, setLevel: STATUS_WINDOW_LEVEL];
+        let _: () = msg_send![ns_window, setCollectionBehavior: behavior];
+        let _: () = msg_send![ns_window, orderFrontRegardless];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AREA: Rect = Rect {
+        x: 0,
+        y: 25,
+        width: 1440,
+        height: 875,
+    };
+
+    #[test]
+    fn test_bottom_center() {
+        assert_eq!(bottom_center(AREA, 600, 150, 60), (420, 690));
+
+        // Retina: the same logical layout in physical pixels on a monitor to the right
+        let retina = Rect {
+            x: 1440,
+            y: 50,
+            width: 2880,
+            height: 1750,
+        };
+        assert_eq!(bottom_center(retina, 1200, 300, 120), (2280, 1380));
+    }
+
+    #[test]
+    fn test_bottom_center_on_small_screen() {
+        let small = Rect {
+            x: -800,
+            y: 0,
+            width: 400,
+            height: 180,
+        };
+        assert_eq!(bottom_center(small, 600, 150, 60), (-900, 0));
+    }
+
+    const PRIMARY: Rect = Rect {
+        x: 0,
+        y: 0,
+        width: 1920,
+        height: 1040,
+    };
+    const RIGHT: Rect = Rect {
+        x: 1920,
+        y: 0,
+        width: 2560,
+        height: 1400,
+    };
+
+    fn pin(x: i32, y: i32) -> OverlayPosition {
+        OverlayPosition { x, y }
+    }
+
+    #[test]
+    fn test_pinned_position_is_kept_on_screen() {
+        assert_eq!(
+            pinned_position(pin(100, 200), 600, 150, &[PRIMARY, RIGHT]),
+            (100, 200)
+        );
+        assert_eq!(
+            pinned_position(pin(3000, 1300), 600, 150, &[PRIMARY, RIGHT]),
+            (3000, 1250)
+        );
+        // Straddling two monitors: onto the one it overlaps most
+        assert_eq!(
+            pinned_position(pin(1700, 500), 600, 150, &[PRIMARY, RIGHT]),
+            (1920, 500)
+        );
+    }
+
+    #[test]
+    fn test_pinned_position_when_its_monitor_disappears() {
+        // Pinned on the right monitor, which has been unplugged
+        assert_eq!(
+            pinned_position(pin(3000, 1200), 600, 150, &[PRIMARY]),
+            (1320, 890)
+        );
+        // Partly on the primary: only pulled back far enough to fit
+        assert_eq!(
+            pinned_position(pin(1700, 500), 600, 150, &[PRIMARY]),
+            (1320, 500)
+        );
+        // No monitors reported at all: left where it was pinned
+        assert_eq!(
+            pinned_position(pin(3000, 1200), 600, 150, &[]),
+            (3000, 1200)
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use serde::Serialize;
+
+/// Authorization state of one OS privacy permission
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PermissionState {
+    Granted,
+    Denied,
+    /// The user has not been asked yet; the OS prompts on first use
+    NotDetermined,
+    /// Blocked by a policy (parental controls, MDM) the user cannot change
+    Restricted,
+    /// The platform has no such permission
+    NotRequired,
+}
+
+/// What dictation needs from the OS: the microphone to record and accessibility
+/// to type the result into other apps. Without the latter typing fails silently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Permissions {
+    pub microphone: PermissionState,
+    pub accessibility: PermissionState,
+}
+
+/// Map an `AVAuthorizationStatus` value
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn from_av_status(status: isize) -> PermissionState {
+    match status {
+        0 => PermissionState::NotDetermined,
+        1 => PermissionState::Restricted,
+        3 => PermissionState::Granted,
+        _ => PermissionState::Denied,
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use objc2::{class, msg_send};
+    use objc2_foundation::NSString;
+
+    #[link(name = "AVFoundation", kind = "framework")]
+    extern "C" {
+        static AVMediaTypeAudio: &'static NSString;
+    }
+
+    #[link(name = "ApplicationServices", kind = "framework")]
+    extern "C" {
+        fn AXIsProcessTrusted() -> u8;
+    }
+
+    pub fn microphone_status() -> isize {
+        // SAFETY: class method with a valid media type constant; returns an NSInteger
+        unsafe {
+            msg_send![
+                class!(AVCaptureDevice),
+                authorizationStatusForMediaType: AVMediaTypeAudio
+            ]
+        }
+    }
+
+    pub fn accessibility_trusted() -> bool {
+        // SAFETY: takes no arguments and only reads the TCC database
+        unsafe { AXIsProcessTrusted() != 0 }
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn check() -> Permissions {
+    Permissions {
+        microphone: from_av_status(macos::microphone_status()),
+        // There is no "not asked yet" for accessibility; untrusted apps are added
+        // to the list unchecked
+        accessibility: if macos::accessibility_trusted() {
+            PermissionState::Granted
+        } else {
+            PermissionState::Denied
+        },
+    }
+}
+
+/// Windows and Linux let any app record and synthesize input
+#[cfg(not(target_os = "macos"))]
+pub fn check() -> Permissions {
+    Permissions {
+        microphone: PermissionState::NotRequired,
+        accessibility: PermissionState::NotRequired,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_av_status() {
+        assert_eq!(from_av_status(0), PermissionState::NotDetermined);
+        assert_eq!(from_av_status(1), PermissionState::Restricted);
+        assert_eq!(from_av_status(2), PermissionState::Denied);
+        assert_eq!(from_av_status(3), PermissionState::Granted);
+    }
+
+    #[test]
+    fn test_serializes_snake_case() {
+        let permissions = Permissions {
+            microphone: PermissionState::NotDetermined,
+            accessibility: PermissionState::NotRequired,
+        };
+        assert_eq!(
+            serde_json::to_value(permissions).unwrap(),
+            serde_json::json!({
+                "microphone": "not_determined",
+                "accessibility": "not_required",
+            })
+        );
+    }
+}
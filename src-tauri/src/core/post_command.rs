@@ -0,0 +1,154 @@
+use std::time::Duration;
+
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    settings::AppSettings,
+    subprocess::{self, Exit, ProcessOutput},
+};
+
+use super::state::AppState;
+
+/// A hook still running after this is killed
+pub const HOOK_TIMEOUT: Duration = Duration::from_secs(30);
+/// Hooks running at once; a transcript delivered while all are busy skips its hook
+pub const MAX_CONCURRENT_HOOKS: usize = 2;
+/// Only the end of a failing hook's stderr is logged
+const MAX_LOGGED_STDERR: usize = 2048;
+
+/// What the hook gets besides the text on stdin
+#[derive(Debug, Clone)]
+pub struct HookInput {
+    pub text: String,
+    pub provider: String,
+    /// Empty if the language wasn't detected
+    pub language: String,
+}
+
+/// Run `command` through the shell with the text on stdin and `ED_TEXT`,
+/// `ED_PROVIDER` and `ED_LANG` set; blocks until it exits or `limit` passes.
+/// Its output goes to the log as it comes.
+pub fn run(command: &str, input: &HookInput, limit: Duration) -> std::io::Result<ProcessOutput> {
+    let mut shell = subprocess::shell(command);
+    shell
+        .env("ED_TEXT", &input.text)
+        .env("ED_PROVIDER", &input.provider)
+        .env("ED_LANG", &input.language);
+    subprocess::run("Hook", shell, Some(input.text.clone().into_bytes()), limit)
+}
+
+fn tail(text: &str, max_bytes: usize) -> &str {
+    let text = text.trim_end();
+    let mut start = text.len().saturating_sub(max_bytes);
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    &text[start..]
+}
+
+/// Hand a delivered transcript to the post-transcription command, if one is enabled
+/// and confirmed. Returns right away: the hook never holds up delivery.
+pub fn spawn(app: &AppHandle, settings: &AppSettings, text: &str, language: Option<&str>) {
+    let Some(command) = settings.active_post_command() else {
+        return;
+    };
+    if text.trim().is_empty() {
+        return;
+    }
+    let state = app.state::<AppState>();
+    let Ok(permit) = state.post_command_slots().clone().try_acquire_owned() else {
+        tracing::warn!(
+            "[Hook] {} post-transcription commands still running, skipping this one",
+            MAX_CONCURRENT_HOOKS
+        );
+        return;
+    };
+
+    let command = command.to_string();
+    let input = HookInput {
+        text: text.to_string(),
+        provider: settings.provider.id().to_string(),
+        language: language.unwrap_or_default().to_string(),
+    };
+    tauri::async_runtime::spawn_blocking(move || {
+        let _permit = permit;
+        match run(&command, &input, HOOK_TIMEOUT) {
+            Ok(output) => match output.exit {
+                Exit::Exited(status) if status.success() => {
+                    tracing::debug!(
+                        "[Hook] Post-transcription command finished in {}ms",
+                        output.elapsed.as_millis()
+                    );
+                }
+                Exit::Exited(status) => {
+                    tracing::warn!(
+                        "[Hook] Post-transcription command failed ({}): {}",
+                        status,
+                        tail(&output.stderr, MAX_LOGGED_STDERR)
+                    );
+                }
+                Exit::TimedOut => {
+                    tracing::warn!(
+                        "[Hook] Post-transcription command killed after {}s",
+                        HOOK_TIMEOUT.as_secs()
+                    );
+                }
+            },
+            Err(e) => {
+                tracing::warn!("[Hook] Failed to start post-transcription command: {}", e);
+            }
+        }
+    });
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    fn input(text: &str) -> HookInput {
+        HookInput {
+            text: text.to_string(),
+            provider: "elevenlabs".to_string(),
+            language: "ru".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_text_arrives_on_stdin_and_in_env() {
+        let command =
+            r#"read line; [ "$line" = "$ED_TEXT" ] || exit 3; echo "$ED_PROVIDER $ED_LANG" >&2"#;
+        let outcome = run(command, &input("Привет, мир"), Duration::from_secs(5)).unwrap();
+        let Exit::Exited(status) = outcome.exit else {
+            panic!("hook timed out");
+        };
+        assert!(status.success());
+        assert_eq!(outcome.stderr, "elevenlabs ru\n");
+    }
+
+    #[test]
+    fn test_failure_keeps_stderr() {
+        let outcome = run("echo boom >&2; exit 4", &input("x"), Duration::from_secs(5)).unwrap();
+        let Exit::Exited(status) = outcome.exit else {
+            panic!("hook timed out");
+        };
+        assert_eq!(status.code(), Some(4));
+        assert_eq!(outcome.stderr, "boom\n");
+    }
+
+    #[test]
+    fn test_stalled_hook_is_killed() {
+        let started = Instant::now();
+        let outcome = run("sleep 5", &input("x"), Duration::from_millis(200)).unwrap();
+        assert!(matches!(outcome.exit, Exit::TimedOut));
+        assert!(started.elapsed() < Duration::from_secs(4));
+    }
+
+    #[test]
+    fn test_tail_respects_char_boundaries() {
+        assert_eq!(tail("ошибка\n", 4), "ка");
+        assert_eq!(tail("ошибка", 5), "ка");
+        assert_eq!(tail("short", 100), "short");
+    }
+}
@@ -0,0 +1,528 @@
+use std::{
+    ops::BitOr,
+    sync::{atomic::Ordering, Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use tauri::{AppHandle, Manager, State};
+use tokio::sync::OwnedMutexGuard;
+
+use crate::settings::{AppSettings, TranscriptionProvider};
+
+use super::{
+    commands::{connect_with_last_config, disconnect_streaming},
+    session_manager::{self, HotkeyEventKind},
+    state::AppState,
+};
+
+/// Speculative connects are at least this far apart, so holding Ctrl for shortcuts
+/// in other apps doesn't keep reconnecting
+const COOLDOWN: Duration = Duration::from_secs(10);
+
+/// A speculative connection no press used within this long after it was ready is
+/// closed again
+const SPECULATION_WINDOW: Duration = Duration::from_secs(2);
+
+/// A set of modifier keys, left and right alike
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Modifiers(u8);
+
+impl Modifiers {
+    pub const CTRL: Self = Self(1);
+    pub const SHIFT: Self = Self(1 << 1);
+    pub const ALT: Self = Self(1 << 2);
+    pub const WIN: Self = Self(1 << 3);
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl BitOr for Modifiers {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+/// Modifiers of a hotkey like "Ctrl+Shift+Space"; None for one without any (a bare
+/// F-key), which gives nothing to anticipate
+pub fn hotkey_modifiers(hotkey: &str) -> Option<Modifiers> {
+    let parts: Vec<&str> = hotkey.split('+').map(str::trim).collect();
+    let (_, modifiers) = parts.split_last()?;
+    let modifiers = modifiers
+        .iter()
+        .filter_map(|name| match *name {
+            "Ctrl" => Some(Modifiers::CTRL),
+            "Shift" => Some(Modifiers::SHIFT),
+            "Alt" => Some(Modifiers::ALT),
+            "Win" => Some(Modifiers::WIN),
+            _ => None,
+        })
+        .fold(Modifiers::default(), BitOr::bitor);
+    (!modifiers.is_empty()).then_some(modifiers)
+}
+
+/// Modifier sets of the hotkeys that start a dictation
+fn chords(settings: &AppSettings) -> Vec<Modifiers> {
+    [
+        settings.normalized_hotkey(),
+        settings.translate_hotkey.trim().to_string(),
+    ]
+    .iter()
+    .filter_map(|hotkey| hotkey_modifiers(hotkey))
+    .collect()
+}
+
+/// A speculative connect and when it was ready
+#[derive(Debug, Clone, Copy)]
+struct Speculation {
+    id: u64,
+    started: Instant,
+    connected: Option<Instant>,
+}
+
+/// When held modifiers start a speculative connect, and what it saved the press
+#[derive(Debug, Default)]
+struct Tracker {
+    chords: Vec<Modifiers>,
+    held: Modifiers,
+    last_attempt: Option<Instant>,
+    speculation: Option<Speculation>,
+    next_id: u64,
+}
+
+impl Tracker {
+    /// Note the modifiers held now. True if they just completed the modifiers of a
+    /// hotkey and no speculative connect is running or was started recently.
+    fn modifiers_changed(&mut self, held: Modifiers, now: Instant) -> bool {
+        let before = std::mem::replace(&mut self.held, held);
+        let completed = self
+            .chords
+            .iter()
+            .any(|&chord| held.contains(chord) && !before.contains(chord));
+        let cooling = self
+            .last_attempt
+            .is_some_and(|at| now.saturating_duration_since(at) < COOLDOWN);
+        if !completed || cooling || self.speculation.is_some() {
+            return false;
+        }
+        self.last_attempt = Some(now);
+        true
+    }
+
+    fn begin(&mut self, now: Instant) -> u64 {
+        self.next_id += 1;
+        self.speculation = Some(Speculation {
+            id: self.next_id,
+            started: now,
+            connected: None,
+        });
+        self.next_id
+    }
+
+    fn connected(&mut self, id: u64, now: Instant) {
+        if let Some(speculation) = self.speculation.as_mut().filter(|s| s.id == id) {
+            speculation.connected = Some(now);
+        }
+    }
+
+    /// Forget speculation `id` without a connection to show for it
+    fn abandon(&mut self, id: u64) {
+        if self.speculation.is_some_and(|s| s.id == id) {
+            self.speculation = None;
+        }
+    }
+
+    /// Hand the speculation to a press at `pressed`: the part of the connect that was
+    /// done by then
+    fn claim(&mut self, pressed: Instant) -> Option<Duration> {
+        let speculation = self.speculation.take()?;
+        let ready = speculation.connected.map_or(pressed, |at| at.min(pressed));
+        Some(ready.saturating_duration_since(speculation.started))
+    }
+
+    /// True if speculation `id` was never claimed by a press; it is forgotten
+    fn expire(&mut self, id: u64) -> bool {
+        let unclaimed = self.speculation.is_some_and(|s| s.id == id);
+        if unclaimed {
+            self.speculation = None;
+        }
+        unclaimed
+    }
+}
+
+/// Connects ElevenLabs streaming while the hotkey's modifiers are held, before its
+/// last key lands (`preconnect_on_modifiers`). Watching the modifiers needs a
+/// low-level keyboard hook, so this only works on Windows.
+#[derive(Default)]
+pub struct Preconnector {
+    tracker: Mutex<Tracker>,
+    /// Held while a speculative connect runs, so a press waits for it instead of
+    /// connecting a second time
+    connecting: Arc<tokio::sync::Mutex<()>>,
+    #[cfg(windows)]
+    hook: Mutex<Option<win32::Hook>>,
+}
+
+impl Preconnector {
+    /// Start speculation for the modifiers held now, if they call for it
+    fn trigger(&self, held: Modifiers, now: Instant) -> Option<(u64, OwnedMutexGuard<()>)> {
+        let mut tracker = self.tracker.lock().ok()?;
+        if !tracker.modifiers_changed(held, now) {
+            return None;
+        }
+        let connecting = self.connecting.clone().try_lock_owned().ok()?;
+        Some((tracker.begin(now), connecting))
+    }
+
+    fn with_tracker<T>(&self, f: impl FnOnce(&mut Tracker) -> T) -> Option<T> {
+        self.tracker.lock().ok().map(|mut tracker| f(&mut tracker))
+    }
+
+    /// Wait for a speculative connect still running, then take the connect time it
+    /// saved a press at `pressed`; None if there was none
+    pub async fn claim(&self, pressed: Instant) -> Option<Duration> {
+        let _settled = self.connecting.lock().await;
+        self.with_tracker(|tracker| tracker.claim(pressed))
+            .flatten()
+    }
+}
+
+/// Follow `settings`: watch the modifiers of the dictation hotkeys while
+/// `preconnect_on_modifiers` is on. Whether ElevenLabs is the provider is checked
+/// when they are held, as the tray switches providers without coming through here.
+pub fn apply_settings(app: &AppHandle, settings: &AppSettings) {
+    let state: State<'_, AppState> = app.state();
+    let preconnector = state.preconnect();
+    let chords = if settings.preconnect_on_modifiers {
+        chords(settings)
+    } else {
+        Vec::new()
+    };
+    let watch = !chords.is_empty();
+    preconnector.with_tracker(|tracker| tracker.chords = chords);
+
+    #[cfg(windows)]
+    if let Ok(mut hook) = preconnector.hook.lock() {
+        if !watch {
+            *hook = None;
+        } else if hook.is_none() {
+            let (sink, modifiers) = std::sync::mpsc::channel();
+            match win32::Hook::install(sink) {
+                Ok(installed) => {
+                    tracing::info!("[Preconnect] Watching the hotkey modifiers");
+                    *hook = Some(installed);
+                    let app = app.clone();
+                    // Ends when the hook is removed and its sender dropped
+                    std::thread::spawn(move || {
+                        for held in modifiers {
+                            modifiers_changed(&app, held);
+                        }
+                    });
+                }
+                Err(e) => tracing::warn!("[Preconnect] Keyboard hook failed: {:#}", e),
+            }
+        }
+    }
+    #[cfg(not(windows))]
+    if watch {
+        tracing::info!("[Preconnect] Watching modifiers needs Windows; connecting on the press");
+    }
+}
+
+/// Called from the hook's worker thread whenever the held modifiers change
+#[cfg_attr(not(windows), allow(dead_code))]
+fn modifiers_changed(app: &AppHandle, held: Modifiers) {
+    let state: State<'_, AppState> = app.state();
+    let Some((id, connecting)) = state.preconnect().trigger(held, Instant::now()) else {
+        return;
+    };
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        speculate(&app, id).await;
+        drop(connecting);
+    });
+}
+
+/// Connect with the last streaming config unless streaming is connected already or
+/// a dictation is under way; a press waits for this to finish
+async fn speculate(app: &AppHandle, id: u64) {
+    let state: State<'_, AppState> = app.state();
+    let settings = state.current_settings().await;
+    let idle = settings.provider == TranscriptionProvider::ElevenLabs
+        && state.recording_session().is_none()
+        && !state.is_transcribing().load(Ordering::SeqCst)
+        && state.connect_breaker().open_for(Instant::now()).is_none()
+        && !state.elevenlabs_streaming().is_connected().await;
+    if !idle {
+        state
+            .preconnect()
+            .with_tracker(|tracker| tracker.abandon(id));
+        return;
+    }
+
+    tracing::info!("[Preconnect] Hotkey modifiers held; connecting ahead of the press");
+    if let Err(e) = connect_with_last_config(app).await {
+        tracing::info!("[Preconnect] Speculative connect failed: {}", e);
+        state
+            .preconnect()
+            .with_tracker(|tracker| tracker.abandon(id));
+        return;
+    }
+    state
+        .preconnect()
+        .with_tracker(|tracker| tracker.connected(id, Instant::now()));
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(SPECULATION_WINDOW).await;
+        session_manager::send(&app, HotkeyEventKind::PreconnectExpired(id));
+    });
+}
+
+/// Close the speculative connection `id` if no press used it; run by the session
+/// manager, so it can't interleave with a press
+pub async fn drop_unclaimed(app: &AppHandle, id: u64) {
+    let state: State<'_, AppState> = app.state();
+    let unclaimed = state
+        .preconnect()
+        .with_tracker(|tracker| tracker.expire(id))
+        .unwrap_or(false);
+    if !unclaimed {
+        return;
+    }
+    tracing::info!("[Preconnect] The hotkey never completed; closing the speculative connection");
+    if let Err(e) = disconnect_streaming(&state).await {
+        tracing::warn!(
+            "[Preconnect] Failed to close the speculative connection: {}",
+            e
+        );
+    }
+}
+
+#[cfg(windows)]
+mod win32 {
+    use std::sync::{
+        atomic::{AtomicU8, Ordering},
+        mpsc::Sender,
+        Mutex,
+    };
+
+    use anyhow::{anyhow, Result};
+    use windows::{
+        core::PCWSTR,
+        Win32::{
+            Foundation::{HINSTANCE, LPARAM, LRESULT, WPARAM},
+            System::{LibraryLoader::GetModuleHandleW, Threading::GetCurrentThreadId},
+            UI::{
+                Input::KeyboardAndMouse::{
+                    VIRTUAL_KEY, VK_LCONTROL, VK_LMENU, VK_LSHIFT, VK_LWIN, VK_RCONTROL, VK_RMENU,
+                    VK_RSHIFT, VK_RWIN,
+                },
+                WindowsAndMessaging::{
+                    CallNextHookEx, GetMessageW, PostThreadMessageW, SetWindowsHookExW,
+                    UnhookWindowsHookEx, HC_ACTION, KBDLLHOOKSTRUCT, LLKHF_INJECTED, MSG,
+                    WH_KEYBOARD_LL, WM_KEYDOWN, WM_QUIT, WM_SYSKEYDOWN,
+                },
+            },
+        },
+    };
+
+    use super::Modifiers;
+
+    /// Each modifier key, left and right apart, so releasing one of two held keeps
+    /// the modifier held
+    const KEYS: [(VIRTUAL_KEY, u8); 8] = [
+        (VK_LCONTROL, 1),
+        (VK_RCONTROL, 1 << 1),
+        (VK_LSHIFT, 1 << 2),
+        (VK_RSHIFT, 1 << 3),
+        (VK_LMENU, 1 << 4),
+        (VK_RMENU, 1 << 5),
+        (VK_LWIN, 1 << 6),
+        (VK_RWIN, 1 << 7),
+    ];
+
+    /// Modifier keys down, as bits of `KEYS`
+    static HELD: AtomicU8 = AtomicU8::new(0);
+    static SINK: Mutex<Option<Sender<Modifiers>>> = Mutex::new(None);
+
+    fn modifiers(keys: u8) -> Modifiers {
+        [
+            (0b11, Modifiers::CTRL),
+            (0b1100, Modifiers::SHIFT),
+            (0b11_0000, Modifiers::ALT),
+            (0b1100_0000, Modifiers::WIN),
+        ]
+        .into_iter()
+        .filter(|(bits, _)| keys & bits != 0)
+        .fold(Modifiers::default(), |all, (_, modifier)| all | modifier)
+    }
+
+    /// Runs for every key event on the system, so it only updates `HELD` and passes a
+    /// change on; our own injected typing is ignored
+    unsafe extern "system" fn keyboard_hook(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+        if code == HC_ACTION as i32 {
+            // SAFETY: for HC_ACTION, lparam points to the KBDLLHOOKSTRUCT of the event
+            let event = unsafe { &*(lparam.0 as *const KBDLLHOOKSTRUCT) };
+            let bit = KEYS
+                .iter()
+                .find(|(key, _)| u32::from(key.0) == event.vkCode)
+                .map(|&(_, bit)| bit);
+            if let Some(bit) = bit.filter(|_| !event.flags.contains(LLKHF_INJECTED)) {
+                let down = matches!(wparam.0 as u32, WM_KEYDOWN | WM_SYSKEYDOWN);
+                let before = HELD.load(Ordering::Relaxed);
+                let after = if down { before | bit } else { before & !bit };
+                if modifiers(after) != modifiers(before) {
+                    if let Ok(sink) = SINK.lock() {
+                        if let Some(sink) = sink.as_ref() {
+                            let _ = sink.send(modifiers(after));
+                        }
+                    }
+                }
+                HELD.store(after, Ordering::Relaxed);
+            }
+        }
+        // SAFETY: passing the event on unchanged, as every hook must
+        unsafe { CallNextHookEx(None, code, wparam, lparam) }
+    }
+
+    /// A low-level keyboard hook on its own thread; removed when dropped
+    pub struct Hook {
+        thread_id: u32,
+    }
+
+    impl Hook {
+        /// Install the hook, sending the held modifiers to `sink` whenever they change
+        pub fn install(sink: Sender<Modifiers>) -> Result<Self> {
+            HELD.store(0, Ordering::Relaxed);
+            if let Ok(mut guard) = SINK.lock() {
+                *guard = Some(sink);
+            }
+            let (ready, started) = std::sync::mpsc::channel();
+            std::thread::Builder::new()
+                .name("modifier-hook".to_string())
+                .spawn(move || {
+                    // SAFETY: the hook procedure lives for the whole program and the
+                    // module handle of our own executable needs no closing
+                    let hook = unsafe {
+                        let module = GetModuleHandleW(PCWSTR::null()).ok().map(HINSTANCE::from);
+                        SetWindowsHookExW(WH_KEYBOARD_LL, Some(keyboard_hook), module, 0)
+                    };
+                    let hook = match hook {
+                        Ok(hook) => hook,
+                        Err(e) => {
+                            let _ = ready.send(Err(e));
+                            return;
+                        }
+                    };
+                    // SAFETY: no preconditions
+                    let _ = ready.send(Ok(unsafe { GetCurrentThreadId() }));
+                    // The hook is called on this thread while it waits for messages;
+                    // WM_QUIT from `drop` ends the loop
+                    let mut msg = MSG::default();
+                    // SAFETY: `msg` is a valid out pointer; the hook is removed once
+                    while unsafe { GetMessageW(&mut msg, None, 0, 0) }.0 > 0 {}
+                    unsafe {
+                        let _ = UnhookWindowsHookEx(hook);
+                    }
+                })?;
+            let thread_id = started
+                .recv()
+                .map_err(|_| anyhow!("Keyboard hook thread exited"))??;
+            Ok(Self { thread_id })
+        }
+    }
+
+    impl Drop for Hook {
+        fn drop(&mut self) {
+            // SAFETY: posting to a thread id that may have exited fails harmlessly
+            if let Err(e) =
+                unsafe { PostThreadMessageW(self.thread_id, WM_QUIT, WPARAM(0), LPARAM(0)) }
+            {
+                tracing::warn!("[Preconnect] Failed to stop the keyboard hook: {}", e);
+            }
+            if let Ok(mut guard) = SINK.lock() {
+                *guard = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker(hotkeys: &[&str]) -> Tracker {
+        Tracker {
+            chords: hotkeys
+                .iter()
+                .filter_map(|hotkey| hotkey_modifiers(hotkey))
+                .collect(),
+            ..Tracker::default()
+        }
+    }
+
+    #[test]
+    fn test_hotkey_modifiers() {
+        assert_eq!(
+            hotkey_modifiers("Ctrl+Shift+Space"),
+            Some(Modifiers::CTRL | Modifiers::SHIFT)
+        );
+        assert_eq!(
+            hotkey_modifiers("Alt+Win+F1"),
+            Some(Modifiers::ALT | Modifiers::WIN)
+        );
+        assert_eq!(hotkey_modifiers("F9"), None);
+        assert_eq!(hotkey_modifiers(""), None);
+    }
+
+    #[test]
+    fn test_speculates_once_the_chord_modifiers_are_held() {
+        let mut tracker = tracker(&["Ctrl+Shift+Space", "F9"]);
+        let start = Instant::now();
+        assert!(!tracker.modifiers_changed(Modifiers::CTRL, start));
+        assert!(tracker.modifiers_changed(Modifiers::CTRL | Modifiers::SHIFT, start));
+        // Holding more modifiers or letting one go doesn't start another
+        let id = tracker.begin(start);
+        let all = Modifiers::CTRL | Modifiers::SHIFT | Modifiers::ALT;
+        assert!(!tracker.modifiers_changed(all, start));
+        assert!(!tracker.modifiers_changed(Modifiers::CTRL, start));
+        assert!(tracker.expire(id));
+
+        // Within the cooldown nothing happens, after it the next chord speculates
+        let soon = start + Duration::from_secs(1);
+        assert!(!tracker.modifiers_changed(Modifiers::CTRL | Modifiers::SHIFT, soon));
+        tracker.modifiers_changed(Modifiers::default(), soon);
+        let later = start + COOLDOWN;
+        assert!(tracker.modifiers_changed(Modifiers::CTRL | Modifiers::SHIFT, later));
+    }
+
+    #[test]
+    fn test_claim_reports_the_connect_time_saved() {
+        let mut tracker = tracker(&["Ctrl+Space"]);
+        let start = Instant::now();
+        let ms = |ms| start + Duration::from_millis(ms);
+
+        // Ready before the press: the whole connect was saved
+        let id = tracker.begin(start);
+        tracker.connected(id, ms(350));
+        assert_eq!(tracker.claim(ms(500)), Some(Duration::from_millis(350)));
+        assert!(!tracker.expire(id), "a claimed connection stays");
+
+        // Still connecting at the press: the part done by then
+        let id = tracker.begin(start);
+        tracker.connected(id, ms(700));
+        assert_eq!(tracker.claim(ms(200)), Some(Duration::from_millis(200)));
+
+        let id = tracker.begin(start);
+        tracker.abandon(id);
+        assert_eq!(tracker.claim(ms(200)), None);
+    }
+}
@@ -0,0 +1,68 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use tauri::{AppHandle, Manager, State};
+
+use crate::{audio::AudioSnapshot, settings::AppSettings};
+
+use super::{events::emit_interim, state::AppState};
+
+/// Interim transcripts for a legacy recording: every `interval` the audio captured so
+/// far is transcribed (without refinement) and shown as interim text. Stops with the
+/// recording; the final transcript comes from the usual full upload.
+pub fn spawn(app: &AppHandle, snapshot: AudioSnapshot, settings: AppSettings, interval: Duration) {
+    let app = app.clone();
+    let settings = Arc::new(settings);
+    tauri::async_runtime::spawn(async move {
+        let in_flight = Arc::new(AtomicBool::new(false));
+        let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+
+        loop {
+            ticker.tick().await;
+            if !snapshot.is_recording() {
+                break;
+            }
+            // A slow provider gets the next tick instead of a pile of requests
+            if in_flight.swap(true, Ordering::AcqRel) {
+                tracing::debug!("[Interim] Previous interim request still running, skipping");
+                continue;
+            }
+            let audio_wav = match snapshot.wav() {
+                Some(Ok(audio_wav)) => audio_wav,
+                Some(Err(e)) => {
+                    tracing::warn!("[Interim] Failed to read the audio so far: {}", e);
+                    in_flight.store(false, Ordering::Release);
+                    continue;
+                }
+                None => break,
+            };
+
+            let app = app.clone();
+            let snapshot = snapshot.clone();
+            let settings = settings.clone();
+            let in_flight = in_flight.clone();
+            tauri::async_runtime::spawn(async move {
+                let state: State<'_, AppState> = app.state();
+                let result = state
+                    .transcription()
+                    .transcribe_interim(&settings, audio_wav)
+                    .await;
+                in_flight.store(false, Ordering::Release);
+                match result {
+                    // The final transcript may already be on its way
+                    Ok(text) if snapshot.is_recording() && !text.trim().is_empty() => {
+                        emit_interim(&app, text.trim());
+                    }
+                    Ok(_) => {}
+                    Err(e) => tracing::warn!("[Interim] Interim transcription failed: {}", e),
+                }
+            });
+        }
+        tracing::debug!("[Interim] Recording stopped, no more interim transcripts");
+    });
+}
@@ -0,0 +1,84 @@
+use std::time::{Duration, Instant};
+
+use tauri::{AppHandle, Manager, State};
+use tokio_util::sync::CancellationToken;
+
+use super::{events::emit_recording_tick, state::AppState};
+
+const TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Seconds left before `max_duration` is reached, if a cap is configured
+fn remaining_secs(elapsed: Duration, max_duration: Option<Duration>) -> Option<u64> {
+    max_duration.map(|max| max.saturating_sub(elapsed).as_secs())
+}
+
+/// Start emitting `recording://tick` once a second for the current recording.
+/// Replaces any timer left over from a previous session.
+pub fn start_recording_timer(app: &AppHandle, max_duration: Option<Duration>) {
+    let state: State<'_, AppState> = app.state();
+    let cancel_token = CancellationToken::new();
+
+    if let Ok(mut guard) = state.recording_timer().lock() {
+        if let Some(previous) = guard.replace(cancel_token.clone()) {
+            previous.cancel();
+        }
+    } else {
+        tracing::warn!("[RecordingTimer] Failed to lock timer state");
+        return;
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let started = Instant::now();
+        let mut interval =
+            tokio::time::interval_at(tokio::time::Instant::now() + TICK_INTERVAL, TICK_INTERVAL);
+
+        loop {
+            tokio::select! {
+                _ = cancel_token.cancelled() => break,
+                _ = interval.tick() => {
+                    let elapsed = started.elapsed();
+                    emit_recording_tick(
+                        &app,
+                        elapsed.as_secs(),
+                        remaining_secs(elapsed, max_duration),
+                    );
+                }
+            }
+        }
+    });
+}
+
+/// Stop the tick timer of the current recording, if one is running
+pub fn stop_recording_timer(app: &AppHandle) {
+    let state: State<'_, AppState> = app.state();
+    cancel_recording_timer(&state);
+}
+
+pub fn cancel_recording_timer(state: &AppState) {
+    let token = match state.recording_timer().lock() {
+        Ok(mut guard) => guard.take(),
+        Err(_) => None,
+    };
+    if let Some(token) = token {
+        token.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remaining_secs() {
+        assert_eq!(remaining_secs(Duration::from_secs(5), None), None);
+        assert_eq!(
+            remaining_secs(Duration::from_millis(5500), Some(Duration::from_secs(60))),
+            Some(54)
+        );
+        assert_eq!(
+            remaining_secs(Duration::from_secs(90), Some(Duration::from_secs(60))),
+            Some(0)
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use std::path::PathBuf;
+
+use futures::{stream, StreamExt};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    audio_file::{self, AudioFileError},
+    settings::{AppSettings, TranscriptionProvider},
+};
+
+use super::{
+    error::CommandError,
+    events::{RetranscribeProgressPayload, EVENT_RETRANSCRIBE_PROGRESS},
+    session::SessionContext,
+    state::{AlternateTranscript, AppState},
+    timings::Timings,
+};
+
+/// Recordings transcribed at the same time; kept low for the providers' rate limits
+const MAX_PARALLEL: usize = 2;
+
+/// Outcome of a `retranscribe_recordings` batch
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RetranscribeSummary {
+    pub completed: usize,
+    pub failed: usize,
+    /// Requested entries that are gone or have no saved recording
+    pub skipped: usize,
+    pub cancelled: bool,
+}
+
+/// Run the saved recordings of history entries `ids` through `provider` again. Nothing
+/// is delivered: each transcript is stored with its entry as an alternate transcript.
+/// Progress is reported per recording; `cancel` stops the batch between and during
+/// requests.
+pub async fn retranscribe(
+    app: &AppHandle,
+    ids: Vec<u64>,
+    provider: TranscriptionProvider,
+) -> Result<RetranscribeSummary, CommandError> {
+    let state: State<'_, AppState> = app.state();
+    let token = {
+        let mut guard = state.retranscription().lock()?;
+        if guard.is_some() {
+            return Err(CommandError::Unavailable(
+                "A re-transcription is already running".into(),
+            ));
+        }
+        guard.insert(CancellationToken::new()).clone()
+    };
+
+    let summary = run(app, &state, &ids, provider, &token).await;
+
+    if let Ok(mut guard) = state.retranscription().lock() {
+        *guard = None;
+    }
+    tracing::info!(
+        "[Retranscribe] {} done, {} failed, {} skipped{}",
+        summary.completed,
+        summary.failed,
+        summary.skipped,
+        if summary.cancelled { ", cancelled" } else { "" }
+    );
+    Ok(summary)
+}
+
+/// Stop the running batch; false if there is none
+pub fn cancel(state: &AppState) -> bool {
+    let token = match state.retranscription().lock() {
+        Ok(guard) => guard.clone(),
+        Err(_) => None,
+    };
+    match token {
+        Some(token) => {
+            token.cancel();
+            true
+        }
+        None => false,
+    }
+}
+
+async fn run(
+    app: &AppHandle,
+    state: &AppState,
+    ids: &[u64],
+    provider: TranscriptionProvider,
+    token: &CancellationToken,
+) -> RetranscribeSummary {
+    let mut recordings = Vec::new();
+    for &id in ids {
+        if let Some(path) = state
+            .history_entry(id)
+            .await
+            .and_then(|entry| entry.recording_path)
+        {
+            recordings.push((id, path));
+        }
+    }
+    let mut summary = RetranscribeSummary {
+        skipped: ids.len() - recordings.len(),
+        ..RetranscribeSummary::default()
+    };
+
+    let mut settings = (*state.current_settings().await).clone();
+    SessionContext {
+        provider_override: Some(provider),
+        ..SessionContext::default()
+    }
+    .apply(&mut settings);
+
+    let total = recordings.len();
+    tracing::info!(
+        "[Retranscribe] Transcribing {} recordings with {}",
+        total,
+        provider.display_name()
+    );
+    let mut results = stream::iter(recordings)
+        .map(|(id, path)| {
+            let settings = &settings;
+            async move { (id, transcribe(state, settings, path).await) }
+        })
+        .buffer_unordered(MAX_PARALLEL);
+
+    let mut done = 0;
+    loop {
+        // Dropping `results` aborts the requests in flight
+        let next = tokio::select! {
+            _ = token.cancelled() => {
+                summary.cancelled = true;
+                break;
+            }
+            next = results.next() => next,
+        };
+        let Some((id, outcome)) = next else {
+            break;
+        };
+        done += 1;
+        let error = match outcome {
+            Ok(text) => {
+                let alternate = AlternateTranscript {
+                    provider: provider.id().to_string(),
+                    text,
+                };
+                if state.add_alternate_transcript(id, alternate).await {
+                    summary.completed += 1;
+                    None
+                } else {
+                    summary.failed += 1;
+                    Some(format!("History entry {id} was deleted"))
+                }
+            }
+            Err(err) => {
+                tracing::warn!("[Retranscribe] Entry {}: {}", id, err);
+                summary.failed += 1;
+                Some(err)
+            }
+        };
+        let payload = RetranscribeProgressPayload {
+            id,
+            done,
+            total,
+            error,
+        };
+        if let Err(e) = app.emit(EVENT_RETRANSCRIBE_PROGRESS, payload) {
+            tracing::error!("[Events] Failed to emit retranscribe progress: {}", e);
+        }
+    }
+    summary
+}
+
+async fn transcribe(
+    state: &AppState,
+    settings: &AppSettings,
+    path: PathBuf,
+) -> Result<String, String> {
+    let decoded = match tokio::task::spawn_blocking(move || audio_file::decode_to_wav(&path)).await
+    {
+        Ok(result) => result,
+        Err(e) => Err(AudioFileError::Decode(e.to_string())),
+    };
+    let audio_wav = decoded.map_err(|e| e.to_string())?;
+    let result = state
+        .transcription()
+        .perform(settings, audio_wav, &[], &mut Timings::start(), &|_, _| {})
+        .await
+        .map_err(|e| format!("{e:#}"))?;
+    Ok(result.processed.trim().to_string())
+}
@@ -0,0 +1,450 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    audio::CaptureInfo,
+    audio_stream::AudioStats,
+    settings::{AppOverrides, AppSettings, TranscriptionProvider},
+};
+
+use super::timings::Timings;
+
+/// How a dictation was started: the plain hotkey, the translate hotkey or a command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(default)]
+pub struct DictationOptions {
+    pub force_translate: bool,
+    /// Transcribe with this provider instead of the configured one
+    pub provider_override: Option<TranscriptionProvider>,
+}
+
+impl DictationOptions {
+    pub fn translated() -> Self {
+        Self {
+            force_translate: true,
+            ..Self::default()
+        }
+    }
+}
+
+/// Per-utterance options fixed when the hotkey is pressed and carried with the
+/// audio until its transcript is processed
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SessionContext {
+    pub id: u64,
+    /// Translate this utterance regardless of the `auto_translate` setting
+    pub force_translate: bool,
+    /// Provider for this utterance only; the setting is left alone
+    pub provider_override: Option<TranscriptionProvider>,
+    /// Streamed with the target language as the transcription language, so the
+    /// transcript needs no translation
+    pub transcribed_in_target: bool,
+    /// When the ElevenLabs gate opened; a streamed recording is timed from here.
+    /// Moved to the segment start by `commit_segment`.
+    pub gate_opened_at: Option<Instant>,
+    /// Segments committed before this one while the gate stayed open
+    pub segment: u32,
+    /// Overrides of the `app_rules` entry matching the application that was in the
+    /// foreground at the press
+    pub app_overrides: Option<AppOverrides>,
+    /// Microphone the utterance is recorded with
+    pub capture: Option<CaptureInfo>,
+    /// Connect time a speculative connect (`preconnect_on_modifiers`) saved the press
+    pub preconnect_saved: Option<Duration>,
+}
+
+impl SessionContext {
+    /// Settings to process this utterance with
+    pub fn apply(&self, settings: &mut AppSettings) {
+        // The translate hotkey and a picked provider still win over the app rule
+        if let Some(overrides) = &self.app_overrides {
+            overrides.apply(settings);
+            tracing::info!(
+                "[Session] Application overrides applied for session {}",
+                self.id
+            );
+        }
+        if self.force_translate {
+            settings.auto_translate = true;
+            tracing::info!("[Session] Force translate enabled for session {}", self.id);
+        }
+        if let Some(provider) = self.provider_override {
+            tracing::info!(
+                "[Session] Using {} for session {}",
+                provider.display_name(),
+                self.id
+            );
+            settings.provider = provider;
+        }
+        if self.transcribed_in_target && settings.auto_translate {
+            settings.auto_translate = false;
+            tracing::info!(
+                "[Session] Session {} was transcribed in {}; skipping translation",
+                self.id,
+                settings.target_language
+            );
+        }
+    }
+
+    /// Provider this utterance is transcribed with
+    pub fn provider(&self, settings: &AppSettings) -> TranscriptionProvider {
+        self.provider_override.unwrap_or(settings.provider)
+    }
+
+    /// Whether this utterance goes through the ElevenLabs streaming connection. That
+    /// connection only exists while ElevenLabs is the configured provider; a one-off
+    /// ElevenLabs dictation is recorded and sent in one piece instead.
+    pub fn uses_streaming(&self, settings: &AppSettings) -> bool {
+        settings.provider == TranscriptionProvider::ElevenLabs
+            && self.provider(settings) == TranscriptionProvider::ElevenLabs
+    }
+
+    /// What became of the translation for this utterance. `settings` are the ones
+    /// `apply` produced; `refined` tells whether an LLM pass (or a combined
+    /// transcribe-and-translate request) actually ran.
+    pub fn translation(&self, settings: &AppSettings, refined: bool) -> Translation {
+        let translated = self.transcribed_in_target || (settings.auto_translate && refined);
+        Translation {
+            translated,
+            translation_forced: self.force_translate,
+            target_language: translated.then(|| settings.target_language.clone()),
+        }
+    }
+}
+
+/// Whether a delivered utterance was translated, as reported in its history entry
+/// and complete event
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Translation {
+    pub translated: bool,
+    /// Translated because of the translate hotkey rather than `auto_translate`
+    pub translation_forced: bool,
+    /// Language the text was delivered in; None if it wasn't translated
+    pub target_language: Option<String>,
+}
+
+/// An ElevenLabs commit waiting for its committed transcript
+#[derive(Debug, Clone)]
+pub struct PendingCommit {
+    pub session: SessionContext,
+    /// Started when the gate closed
+    pub timings: Timings,
+    /// How long the gate was open; None if its opening wasn't recorded
+    pub recorded: Option<Duration>,
+    /// A segment committed with the gate still open: the dictation goes on after it
+    pub continues: bool,
+    /// What happened to the streamed audio of the committed utterance
+    pub audio: AudioStats,
+}
+
+/// Hands out session IDs and holds the context of the recording in progress
+/// until the hotkey is released
+#[derive(Debug, Default)]
+pub struct Sessions {
+    counter: AtomicU64,
+    recording: Mutex<Option<SessionContext>>,
+    /// Provider picked for the next session only (`set_next_provider`)
+    next_provider: Mutex<Option<TranscriptionProvider>>,
+}
+
+impl Sessions {
+    /// Use `provider` for the next session, or clear the choice with None
+    pub fn set_next_provider(&self, provider: Option<TranscriptionProvider>) {
+        if let Ok(mut guard) = self.next_provider.lock() {
+            *guard = provider;
+        }
+    }
+
+    /// Start a new session; it replaces any recording that was never released.
+    /// A provider picked for the next session is used up here, even when `options`
+    /// names another one.
+    pub fn start(&self, options: DictationOptions) -> SessionContext {
+        let next_provider = self
+            .next_provider
+            .lock()
+            .ok()
+            .and_then(|mut guard| guard.take());
+        let session = SessionContext {
+            id: self.counter.fetch_add(1, Ordering::SeqCst) + 1,
+            force_translate: options.force_translate,
+            provider_override: options.provider_override.or(next_provider),
+            transcribed_in_target: false,
+            gate_opened_at: None,
+            segment: 0,
+            app_overrides: None,
+            capture: None,
+            preconnect_saved: None,
+        };
+        if let Ok(mut guard) = self.recording.lock() {
+            *guard = Some(session.clone());
+        }
+        session
+    }
+
+    /// The recording in progress, if any
+    pub fn current(&self) -> Option<SessionContext> {
+        self.recording.lock().ok().and_then(|guard| guard.clone())
+    }
+
+    /// Note that recording `id` streams in the target language; ignored once another
+    /// recording has started
+    pub fn mark_transcribed_in_target(&self, id: u64) {
+        self.update_recording(id, |session| session.transcribed_in_target = true);
+    }
+
+    /// Process recording `id` with the overrides of the application it was started in
+    pub fn set_app_overrides(&self, id: u64, overrides: AppOverrides) {
+        self.update_recording(id, |session| session.app_overrides = Some(overrides));
+    }
+
+    /// Note the microphone recording `id` is captured from
+    pub fn set_capture(&self, id: u64, capture: CaptureInfo) {
+        self.update_recording(id, |session| session.capture = Some(capture));
+    }
+
+    /// Note the connect time a speculative connect saved recording `id`
+    pub fn set_preconnect_saved(&self, id: u64, saved: Duration) {
+        self.update_recording(id, |session| session.preconnect_saved = Some(saved));
+    }
+
+    /// Note that the gate of recording `id` opened just now
+    pub fn mark_gate_opened(&self, id: u64) {
+        self.update_recording(id, |session| session.gate_opened_at = Some(Instant::now()));
+    }
+
+    /// End the current segment of a streamed recording: returns its context and starts
+    /// the next segment now. None if no streamed recording is in progress.
+    pub fn next_segment(&self) -> Option<SessionContext> {
+        let mut guard = self.recording.lock().ok()?;
+        let session = guard
+            .as_mut()
+            .filter(|session| session.gate_opened_at.is_some())?;
+        let ended = session.clone();
+        session.segment += 1;
+        session.gate_opened_at = Some(Instant::now());
+        Some(ended)
+    }
+
+    fn update_recording(&self, id: u64, update: impl FnOnce(&mut SessionContext)) {
+        if let Ok(mut guard) = self.recording.lock() {
+            if let Some(session) = guard.as_mut().filter(|session| session.id == id) {
+                update(session);
+            }
+        }
+    }
+
+    /// Context of the recording that just stopped
+    pub fn take_recording(&self) -> Option<SessionContext> {
+        self.recording
+            .lock()
+            .ok()
+            .and_then(|mut guard| guard.take())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::TranscriptionProvider;
+
+    fn mock_settings() -> AppSettings {
+        AppSettings {
+            provider: TranscriptionProvider::Mock,
+            auto_translate: false,
+            ..AppSettings::default()
+        }
+    }
+
+    fn translate_decision(session: &SessionContext) -> bool {
+        let mut settings = mock_settings();
+        session.apply(&mut settings);
+        settings.auto_translate
+    }
+
+    #[test]
+    fn test_interleaved_sessions_keep_their_own_decision() {
+        let sessions = Sessions::default();
+
+        // Translated dictation released, then a normal one started before it is processed
+        let translated = sessions.start(DictationOptions::translated());
+        assert_eq!(sessions.take_recording().as_ref(), Some(&translated));
+        let normal = sessions.start(DictationOptions::default());
+        assert_eq!(sessions.take_recording().as_ref(), Some(&normal));
+
+        // Processed in reverse order
+        assert!(!translate_decision(&normal));
+        assert!(translate_decision(&translated));
+        assert_ne!(translated.id, normal.id);
+    }
+
+    #[test]
+    fn test_new_recording_does_not_steal_translation() {
+        let sessions = Sessions::default();
+
+        let translated = sessions.start(DictationOptions::translated());
+        let released = sessions.take_recording().unwrap();
+        // A normal recording is still running when the translated one is processed
+        let normal = sessions.start(DictationOptions::default());
+
+        assert!(translate_decision(&released));
+        assert_eq!(sessions.take_recording().as_ref(), Some(&normal));
+        assert!(!translate_decision(&normal));
+        assert_eq!(released, translated);
+    }
+
+    #[test]
+    fn test_recording_is_taken_once() {
+        let sessions = Sessions::default();
+        sessions.start(DictationOptions::translated());
+        assert!(sessions.take_recording().is_some());
+        assert_eq!(sessions.take_recording(), None);
+    }
+
+    fn provider_used(session: &SessionContext) -> TranscriptionProvider {
+        let mut settings = mock_settings();
+        session.apply(&mut settings);
+        settings.provider
+    }
+
+    #[test]
+    fn test_next_provider_applies_to_one_session() {
+        let sessions = Sessions::default();
+        sessions.set_next_provider(Some(TranscriptionProvider::Groq));
+
+        let groq = sessions.start(DictationOptions::default());
+        let released = sessions.take_recording().unwrap();
+        // The next utterance starts before the Groq one is processed
+        let normal = sessions.start(DictationOptions::default());
+
+        assert_eq!(released, groq);
+        assert_eq!(provider_used(&groq), TranscriptionProvider::Groq);
+        assert_eq!(provider_used(&normal), TranscriptionProvider::Mock);
+    }
+
+    #[test]
+    fn test_explicit_override_wins_and_uses_up_next_provider() {
+        let sessions = Sessions::default();
+        sessions.set_next_provider(Some(TranscriptionProvider::Groq));
+
+        let openai = sessions.start(DictationOptions {
+            provider_override: Some(TranscriptionProvider::OpenAI),
+            ..DictationOptions::default()
+        });
+        assert_eq!(provider_used(&openai), TranscriptionProvider::OpenAI);
+        let normal = sessions.start(DictationOptions::default());
+        assert_eq!(provider_used(&normal), TranscriptionProvider::Mock);
+
+        // Cleared before it was used
+        sessions.set_next_provider(Some(TranscriptionProvider::Groq));
+        sessions.set_next_provider(None);
+        assert_eq!(
+            sessions
+                .start(DictationOptions::default())
+                .provider_override,
+            None
+        );
+    }
+
+    #[test]
+    fn test_transcribed_in_target_skips_forced_translation() {
+        let sessions = Sessions::default();
+        let translated = sessions.start(DictationOptions::translated());
+        sessions.mark_transcribed_in_target(translated.id + 1);
+        assert!(translate_decision(&sessions.take_recording().unwrap()));
+
+        let translated = sessions.start(DictationOptions::translated());
+        sessions.mark_transcribed_in_target(translated.id);
+        let released = sessions.take_recording().unwrap();
+        assert!(released.transcribed_in_target);
+        assert!(!translate_decision(&released));
+    }
+
+    #[test]
+    fn test_translation_reports_what_was_applied() {
+        let translation = |session: &SessionContext, refined| {
+            let mut settings = mock_settings();
+            session.apply(&mut settings);
+            session.translation(&settings, refined)
+        };
+        let sessions = Sessions::default();
+
+        let forced = sessions.start(DictationOptions::translated());
+        let applied = translation(&forced, true);
+        assert!(applied.translated && applied.translation_forced);
+        assert!(applied.target_language.is_some());
+        // Refinement failed and the raw transcript went out
+        assert_eq!(
+            translation(&forced, false),
+            Translation {
+                translation_forced: true,
+                ..Translation::default()
+            }
+        );
+
+        let streamed = SessionContext {
+            transcribed_in_target: true,
+            ..forced
+        };
+        assert!(translation(&streamed, false).translated);
+        assert_eq!(
+            translation(&SessionContext::default(), false),
+            Translation::default()
+        );
+    }
+
+    #[test]
+    fn test_segments_of_streamed_recording() {
+        let sessions = Sessions::default();
+        let session = sessions.start(DictationOptions::translated());
+        // The gate never opened: recorded for upload
+        assert_eq!(sessions.next_segment(), None);
+
+        sessions.mark_gate_opened(session.id);
+        let headset = CaptureInfo {
+            device: "Headset".to_string(),
+            sample_rate: 48_000,
+            channels: 1,
+            ..CaptureInfo::default()
+        };
+        sessions.set_capture(session.id, headset.clone());
+        let first = sessions.next_segment().unwrap();
+        let second = sessions.next_segment().unwrap();
+        assert_eq!((first.id, first.segment), (session.id, 0));
+        assert_eq!((second.id, second.segment), (session.id, 1));
+        assert!(second.gate_opened_at >= first.gate_opened_at);
+        assert!(second.force_translate);
+        assert_eq!(second.capture, Some(headset));
+
+        let released = sessions.take_recording().unwrap();
+        assert_eq!(released.segment, 2);
+        assert_eq!(sessions.next_segment(), None);
+    }
+
+    #[test]
+    fn test_one_off_elevenlabs_does_not_stream() {
+        let session = SessionContext {
+            provider_override: Some(TranscriptionProvider::ElevenLabs),
+            ..SessionContext::default()
+        };
+        assert!(!session.uses_streaming(&mock_settings()));
+
+        let elevenlabs = AppSettings {
+            provider: TranscriptionProvider::ElevenLabs,
+            ..AppSettings::default()
+        };
+        assert!(session.uses_streaming(&elevenlabs));
+        let groq = SessionContext {
+            provider_override: Some(TranscriptionProvider::Groq),
+            ..SessionContext::default()
+        };
+        assert!(!groq.uses_streaming(&elevenlabs));
+        assert!(SessionContext::default().uses_streaming(&elevenlabs));
+    }
+}
@@ -0,0 +1,482 @@
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use tauri::{AppHandle, Manager, State};
+use tokio::sync::mpsc;
+
+use super::{hotkey, preconnect, session::DictationOptions, state::AppState, streaming_idle};
+
+/// Hotkey events waiting for the session manager; more than this means it is stuck
+pub const EVENT_QUEUE_CAPACITY: usize = 32;
+
+/// An event that sat in the queue longer than this is logged as a warning
+const SLOW_EVENT: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HotkeyEventKind {
+    Pressed(DictationOptions),
+    Released,
+    /// Deliver the text so far and keep recording
+    CommitSegment,
+    /// The streaming connection went unused for too long
+    StreamingIdle,
+    /// No press followed the speculative connect with this id in time
+    PreconnectExpired(u64),
+}
+
+/// A hotkey press, release, segment commit or idle check, stamped when the shortcut callback fired
+#[derive(Debug, Clone, Copy)]
+pub struct HotkeyEvent {
+    pub kind: HotkeyEventKind,
+    pub at: Instant,
+}
+
+impl HotkeyEvent {
+    pub fn new(kind: HotkeyEventKind) -> Self {
+        Self {
+            kind,
+            at: Instant::now(),
+        }
+    }
+}
+
+/// What the manager does in response to an event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionAction {
+    Start(DictationOptions),
+    Stop,
+    CommitSegment,
+    DisconnectIdle,
+    /// Close the speculative connection with this id unless a press used it
+    DropPreconnect(u64),
+    /// Deliver the last transcript again (double-tap of the main hotkey)
+    Repeat,
+}
+
+/// How a performed action went, as far as the state machine cares
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActionOutcome {
+    Done,
+    /// A stop that sent nothing to the provider (no or too little audio). If it
+    /// ended a tap of the main hotkey, a press within `double_tap` of that tap's
+    /// press repeats the last delivery.
+    NothingSent {
+        double_tap: Option<Duration>,
+    },
+}
+
+/// Start/stop state machine: one dictation at a time, each press paired with
+/// exactly one release
+#[derive(Debug, Default)]
+pub struct SessionMachine {
+    recording: bool,
+    /// Press and release of the current or just stopped dictation, if it was
+    /// started with the main hotkey
+    main_press: Option<Instant>,
+    released_at: Option<Instant>,
+    /// A main-hotkey tap that recorded nothing, and the window for its second tap
+    tap: Option<(Instant, Duration)>,
+    /// The second press of a double-tap is down; its release ends nothing
+    repeating: bool,
+}
+
+impl SessionMachine {
+    pub fn handle(&mut self, event: HotkeyEvent) -> Option<SessionAction> {
+        match (event.kind, self.recording || self.repeating) {
+            (HotkeyEventKind::Pressed(options), false) => {
+                let main = options == DictationOptions::default();
+                let tap = self.tap.take();
+                if main
+                    && tap.is_some_and(|(first, window)| {
+                        event.at.saturating_duration_since(first) <= window
+                    })
+                {
+                    self.repeating = true;
+                    return Some(SessionAction::Repeat);
+                }
+                self.recording = true;
+                self.main_press = main.then_some(event.at);
+                Some(SessionAction::Start(options))
+            }
+            (HotkeyEventKind::Released, true) if self.repeating => {
+                self.repeating = false;
+                None
+            }
+            (HotkeyEventKind::Released, true) => {
+                self.recording = false;
+                self.released_at = Some(event.at);
+                Some(SessionAction::Stop)
+            }
+            (HotkeyEventKind::CommitSegment, true) if self.recording => {
+                Some(SessionAction::CommitSegment)
+            }
+            (HotkeyEventKind::StreamingIdle, false) => Some(SessionAction::DisconnectIdle),
+            (HotkeyEventKind::PreconnectExpired(id), false) => {
+                Some(SessionAction::DropPreconnect(id))
+            }
+            // A second press (key repeat, the other hotkey), a release whose press
+            // was never seen, a segment commit outside a dictation or an idle check
+            // during one
+            (kind, _) => {
+                tracing::debug!(
+                    "[Session] Ignoring {:?} (recording: {})",
+                    kind,
+                    self.recording
+                );
+                None
+            }
+        }
+    }
+
+    /// Learn how `action` went. A stop that sent nothing after a press no longer than
+    /// the double-tap window was a tap: the next press may repeat the last delivery.
+    /// A real dictation, however short, never counts.
+    pub fn finished(&mut self, action: SessionAction, outcome: ActionOutcome) {
+        if action != SessionAction::Stop {
+            return;
+        }
+        let pressed_at = self.main_press.take();
+        let released_at = self.released_at.take();
+        self.tap = match (outcome, pressed_at, released_at) {
+            (
+                ActionOutcome::NothingSent {
+                    double_tap: Some(window),
+                },
+                Some(pressed_at),
+                Some(released_at),
+            ) if released_at.saturating_duration_since(pressed_at) <= window => {
+                Some((pressed_at, window))
+            }
+            _ => None,
+        };
+    }
+}
+
+/// Feed `events` through the state machine in order. Each action is awaited before
+/// the next event is looked at, so a release never overtakes the press it ends.
+async fn run<F, Fut>(mut events: mpsc::Receiver<HotkeyEvent>, mut perform: F)
+where
+    F: FnMut(SessionAction) -> Fut,
+    Fut: Future<Output = ActionOutcome>,
+{
+    let mut machine = SessionMachine::default();
+    while let Some(event) = events.recv().await {
+        let waited = event.at.elapsed();
+        if waited >= SLOW_EVENT {
+            tracing::warn!(
+                "[Session] {:?} waited {}ms in the queue",
+                event.kind,
+                waited.as_millis()
+            );
+        }
+        if let Some(action) = machine.handle(event) {
+            let outcome = perform(action).await;
+            machine.finished(action, outcome);
+        }
+    }
+}
+
+/// Queue a hotkey event for the session manager. Called from shortcut callbacks, so
+/// it never blocks; an event that doesn't fit is dropped and logged.
+pub fn send(app: &AppHandle, kind: HotkeyEventKind) {
+    let state: State<'_, AppState> = app.state();
+    if let Err(e) = state.hotkey_events().try_send(HotkeyEvent::new(kind)) {
+        tracing::warn!("[Session] Dropping hotkey event: {}", e);
+    }
+}
+
+async fn stop(app: &AppHandle) -> ActionOutcome {
+    if hotkey::release(app).await {
+        return ActionOutcome::Done;
+    }
+    let state: State<'_, AppState> = app.state();
+    ActionOutcome::NothingSent {
+        double_tap: state.current_settings().await.double_tap_window(),
+    }
+}
+
+/// Start the task that owns the dictation state machine
+pub fn spawn_session_manager(app: &AppHandle) {
+    let state: State<'_, AppState> = app.state();
+    let Some(events) = state.take_hotkey_event_receiver() else {
+        tracing::warn!("[Session] Session manager already running");
+        return;
+    };
+    let app = app.clone();
+    tauri::async_runtime::spawn(run(events, move |action| {
+        let app = app.clone();
+        async move {
+            match action {
+                SessionAction::Start(options) => hotkey::press(&app, options).await,
+                SessionAction::Stop => return stop(&app).await,
+                SessionAction::CommitSegment => hotkey::commit_segment(&app).await,
+                SessionAction::DisconnectIdle => streaming_idle::disconnect_if_idle(&app).await,
+                SessionAction::DropPreconnect(id) => preconnect::drop_unclaimed(&app, id).await,
+                SessionAction::Repeat => hotkey::repeat_last_delivery(&app).await,
+            }
+            ActionOutcome::Done
+        }
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    fn press() -> HotkeyEventKind {
+        HotkeyEventKind::Pressed(DictationOptions::default())
+    }
+
+    const TAP_WINDOW: Duration = Duration::from_millis(400);
+
+    /// Run `events` (kind, milliseconds from the start) through a machine whose
+    /// stops report `stopped`
+    fn timed_actions(
+        events: &[(HotkeyEventKind, u64)],
+        stopped: ActionOutcome,
+    ) -> Vec<SessionAction> {
+        let start = Instant::now();
+        let mut machine = SessionMachine::default();
+        events
+            .iter()
+            .filter_map(|&(kind, ms)| {
+                let at = start + Duration::from_millis(ms);
+                let action = machine.handle(HotkeyEvent { kind, at })?;
+                let outcome = match action {
+                    SessionAction::Stop => stopped,
+                    _ => ActionOutcome::Done,
+                };
+                machine.finished(action, outcome);
+                Some(action)
+            })
+            .collect()
+    }
+
+    /// Events seconds apart, every stop sending its recording
+    fn actions(events: &[HotkeyEventKind]) -> Vec<SessionAction> {
+        let spaced: Vec<_> = (0..)
+            .step_by(10_000)
+            .zip(events)
+            .map(|(ms, &kind)| (kind, ms))
+            .collect();
+        timed_actions(&spaced, ActionOutcome::Done)
+    }
+
+    fn nothing_sent() -> ActionOutcome {
+        ActionOutcome::NothingSent {
+            double_tap: Some(TAP_WINDOW),
+        }
+    }
+
+    #[test]
+    fn test_press_release_cycles() {
+        let start = SessionAction::Start(DictationOptions::default());
+        assert_eq!(
+            actions(&[
+                press(),
+                HotkeyEventKind::Released,
+                press(),
+                HotkeyEventKind::Released
+            ]),
+            vec![start, SessionAction::Stop, start, SessionAction::Stop]
+        );
+    }
+
+    #[test]
+    fn test_double_press_starts_once() {
+        let translated = HotkeyEventKind::Pressed(DictationOptions::translated());
+        assert_eq!(
+            actions(&[press(), translated, HotkeyEventKind::Released]),
+            vec![
+                SessionAction::Start(DictationOptions::default()),
+                SessionAction::Stop
+            ]
+        );
+    }
+
+    #[test]
+    fn test_release_without_press_is_ignored() {
+        assert_eq!(
+            actions(&[HotkeyEventKind::Released, HotkeyEventKind::Released]),
+            vec![]
+        );
+        assert_eq!(
+            actions(&[HotkeyEventKind::Released, press()]),
+            vec![SessionAction::Start(DictationOptions::default())]
+        );
+    }
+
+    #[test]
+    fn test_segment_commit_only_while_recording() {
+        assert_eq!(
+            actions(&[
+                HotkeyEventKind::CommitSegment,
+                press(),
+                HotkeyEventKind::CommitSegment,
+                HotkeyEventKind::CommitSegment,
+                HotkeyEventKind::Released,
+                HotkeyEventKind::CommitSegment,
+            ]),
+            vec![
+                SessionAction::Start(DictationOptions::default()),
+                SessionAction::CommitSegment,
+                SessionAction::CommitSegment,
+                SessionAction::Stop
+            ]
+        );
+    }
+
+    #[test]
+    fn test_idle_disconnect_only_between_dictations() {
+        assert_eq!(
+            actions(&[
+                HotkeyEventKind::StreamingIdle,
+                press(),
+                HotkeyEventKind::StreamingIdle,
+                HotkeyEventKind::Released,
+            ]),
+            vec![
+                SessionAction::DisconnectIdle,
+                SessionAction::Start(DictationOptions::default()),
+                SessionAction::Stop
+            ]
+        );
+    }
+
+    #[test]
+    fn test_speculative_connection_only_dropped_between_dictations() {
+        assert_eq!(
+            actions(&[
+                press(),
+                HotkeyEventKind::PreconnectExpired(1),
+                HotkeyEventKind::Released,
+                HotkeyEventKind::PreconnectExpired(2),
+            ]),
+            vec![
+                SessionAction::Start(DictationOptions::default()),
+                SessionAction::Stop,
+                SessionAction::DropPreconnect(2)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_double_tap_repeats_the_last_delivery() {
+        let start = SessionAction::Start(DictationOptions::default());
+        assert_eq!(
+            timed_actions(
+                &[
+                    (press(), 0),
+                    (HotkeyEventKind::Released, 90),
+                    (press(), 250),
+                    (HotkeyEventKind::Released, 330),
+                    (press(), 5_000),
+                ],
+                nothing_sent(),
+            ),
+            vec![start, SessionAction::Stop, SessionAction::Repeat, start]
+        );
+    }
+
+    #[test]
+    fn test_short_dictation_is_not_a_double_tap() {
+        let start = SessionAction::Start(DictationOptions::default());
+        let events = [
+            (press(), 0),
+            (HotkeyEventKind::Released, 300),
+            (press(), 380),
+        ];
+        // One word was spoken and sent
+        assert_eq!(
+            timed_actions(&events, ActionOutcome::Done),
+            vec![start, SessionAction::Stop, start]
+        );
+        // Too slow for a double-tap
+        assert_eq!(
+            timed_actions(
+                &[
+                    (press(), 0),
+                    (HotkeyEventKind::Released, 100),
+                    (press(), 450)
+                ],
+                nothing_sent()
+            ),
+            vec![start, SessionAction::Stop, start]
+        );
+        // Turned off
+        assert_eq!(
+            timed_actions(&events, ActionOutcome::NothingSent { double_tap: None }),
+            vec![start, SessionAction::Stop, start]
+        );
+    }
+
+    #[test]
+    fn test_only_the_main_hotkey_double_taps() {
+        let translated = HotkeyEventKind::Pressed(DictationOptions::translated());
+        assert_eq!(
+            timed_actions(
+                &[
+                    (translated, 0),
+                    (HotkeyEventKind::Released, 90),
+                    (press(), 250),
+                    (HotkeyEventKind::Released, 330),
+                    (translated, 400),
+                ],
+                nothing_sent(),
+            ),
+            vec![
+                SessionAction::Start(DictationOptions::translated()),
+                SessionAction::Stop,
+                SessionAction::Start(DictationOptions::default()),
+                SessionAction::Stop,
+                SessionAction::Start(DictationOptions::translated()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_release_waits_for_slow_start() {
+        let (events, receiver) = mpsc::channel(EVENT_QUEUE_CAPACITY);
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let manager = {
+            let log = log.clone();
+            tokio::spawn(run(receiver, move |action| {
+                let log = log.clone();
+                async move {
+                    log.lock().unwrap().push(format!("begin {action:?}"));
+                    if matches!(action, SessionAction::Start(_)) {
+                        // Connecting takes a while
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                    }
+                    log.lock().unwrap().push(format!("end {action:?}"));
+                    ActionOutcome::Done
+                }
+            }))
+        };
+
+        // Press, release and press again faster than the first start finishes
+        for kind in [press(), HotkeyEventKind::Released, press()] {
+            events.send(HotkeyEvent::new(kind)).await.unwrap();
+        }
+        drop(events);
+        manager.await.unwrap();
+
+        let start = format!("{:?}", SessionAction::Start(DictationOptions::default()));
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                format!("begin {start}"),
+                format!("end {start}"),
+                "begin Stop".to_string(),
+                "end Stop".to_string(),
+                format!("begin {start}"),
+                format!("end {start}"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,90 @@
+use std::{
+    sync::atomic::{AtomicU8, Ordering},
+    time::Duration,
+};
+
+use tauri::{AppHandle, Manager, State};
+
+use crate::messages::{self, Msg};
+
+use super::{
+    commands::disconnect_streaming,
+    events::{emit_status, StatusPhase},
+    state::AppState,
+    window_state,
+};
+
+/// Quitting never waits longer than this, even if the network hangs
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+const RUNNING: u8 = 0;
+const SHUTTING_DOWN: u8 = 1;
+const DONE: u8 = 2;
+
+static PHASE: AtomicU8 = AtomicU8::new(RUNNING);
+
+/// Whether the app may exit now. Until the shutdown routine has finished,
+/// an exit request should be prevented and `request_shutdown` called instead.
+pub fn is_shutdown_complete() -> bool {
+    PHASE.load(Ordering::SeqCst) == DONE
+}
+
+/// Flush in-flight work and then exit. Repeated requests while the routine
+/// is running are ignored.
+pub fn request_shutdown(app: &AppHandle) {
+    if PHASE
+        .compare_exchange(RUNNING, SHUTTING_DOWN, Ordering::SeqCst, Ordering::SeqCst)
+        .is_err()
+    {
+        return;
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tracing::info!("[Shutdown] Shutting down...");
+        emit_status(
+            &app,
+            StatusPhase::Idle,
+            Some(messages::text(Msg::ShuttingDown)),
+        );
+
+        if tokio::time::timeout(SHUTDOWN_TIMEOUT, flush(&app))
+            .await
+            .is_err()
+        {
+            tracing::warn!(
+                "[Shutdown] Cleanup did not finish within {}s, exiting anyway",
+                SHUTDOWN_TIMEOUT.as_secs()
+            );
+        }
+
+        PHASE.store(DONE, Ordering::SeqCst);
+        app.exit(0);
+    });
+}
+
+async fn flush(app: &AppHandle) {
+    let Some(state): Option<State<'_, AppState>> = app.try_state() else {
+        return;
+    };
+
+    // Close an open gate without committing: the transcript could not be delivered
+    // before the app is gone anyway
+    if state.elevenlabs_streaming().is_connected().await {
+        if let Err(e) = state.elevenlabs_streaming().close_gate().await {
+            tracing::warn!("[Shutdown] Failed to close gate: {}", e);
+        }
+    }
+
+    // Joins the audio streaming thread before the WebSocket goes away
+    if let Err(e) = disconnect_streaming(&state).await {
+        tracing::warn!("[Shutdown] Failed to disconnect streaming: {}", e);
+    }
+
+    // A move or resize within the last debounce interval has not been written yet
+    window_state::save(app, &state).await;
+
+    // History is kept in memory only; settings are the one thing written to disk
+    state.wait_for_settings_save().await;
+    tracing::info!("[Shutdown] Cleanup finished");
+}
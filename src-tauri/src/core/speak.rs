@@ -0,0 +1,129 @@
+use std::sync::{mpsc::Sender, Mutex, PoisonError};
+
+use tauri::{AppHandle, Manager};
+
+use crate::settings::AppSettings;
+
+use super::state::AppState;
+
+enum Request {
+    Speak { text: String, rate: f32 },
+    Stop,
+}
+
+/// Reads delivered transcripts aloud (`speak_result`). The voice lives on a thread of
+/// its own, started with the first transcript, so speaking never holds up delivery.
+/// Without text-to-speech support (the `tts` feature, Windows only) nothing is spoken.
+#[derive(Default)]
+pub struct Speaker {
+    requests: Mutex<Option<Sender<Request>>>,
+}
+
+impl Speaker {
+    /// Read `text` at `rate` times the normal speed, cutting off the previous one
+    pub fn speak(&self, text: &str, rate: f32) {
+        if text.trim().is_empty() {
+            return;
+        }
+        let mut requests = self.requests.lock().unwrap_or_else(PoisonError::into_inner);
+        if requests.is_none() {
+            *requests = voice::spawn();
+        }
+        let request = Request::Speak {
+            text: text.to_string(),
+            rate,
+        };
+        if requests
+            .as_ref()
+            .is_some_and(|tx| tx.send(request).is_err())
+        {
+            *requests = None;
+        }
+    }
+
+    /// Stop reading; nothing happens if no transcript is being read
+    pub fn stop(&self) {
+        let requests = self.requests.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(tx) = requests.as_ref() {
+            let _ = tx.send(Request::Stop);
+        }
+    }
+}
+
+/// Read a delivered transcript aloud if `speak_result` is on. Returns right away.
+pub fn speak_result(app: &AppHandle, settings: &AppSettings, text: &str) {
+    if settings.speak_result {
+        app.state::<AppState>()
+            .speaker()
+            .speak(text, settings.speak_rate());
+    }
+}
+
+#[cfg(all(windows, feature = "tts"))]
+mod voice {
+    use std::{
+        sync::mpsc::{self, Receiver, Sender},
+        thread,
+    };
+
+    use tts::Tts;
+
+    use super::Request;
+
+    pub fn spawn() -> Option<Sender<Request>> {
+        let (tx, rx) = mpsc::channel();
+        match thread::Builder::new()
+            .name("speak".into())
+            .spawn(move || run(rx))
+        {
+            Ok(_) => Some(tx),
+            Err(e) => {
+                tracing::warn!("[Speak] Failed to start the speech thread: {}", e);
+                None
+            }
+        }
+    }
+
+    fn run(requests: Receiver<Request>) {
+        let mut tts = match Tts::default() {
+            Ok(tts) => tts,
+            Err(e) => {
+                tracing::warn!("[Speak] Text-to-speech is unavailable: {}", e);
+                // Keep the channel open so the speaker doesn't retry on every transcript
+                for _ in requests {}
+                return;
+            }
+        };
+        let features = tts.supported_features();
+        for request in requests {
+            let result = match request {
+                Request::Speak { text, rate } => {
+                    if features.rate {
+                        let rate = (tts.normal_rate() * rate).clamp(tts.min_rate(), tts.max_rate());
+                        if let Err(e) = tts.set_rate(rate) {
+                            tracing::debug!("[Speak] Failed to set the rate: {}", e);
+                        }
+                    }
+                    tts.speak(text, true).map(|_| ())
+                }
+                Request::Stop if features.stop => tts.stop().map(|_| ()),
+                Request::Stop => Ok(()),
+            };
+            if let Err(e) = result {
+                tracing::warn!("[Speak] Text-to-speech failed: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(not(all(windows, feature = "tts")))]
+mod voice {
+    use std::sync::mpsc::Sender;
+
+    use super::Request;
+
+    pub fn spawn() -> Option<Sender<Request>> {
+        tracing::debug!("[Speak] Text-to-speech is not supported in this build");
+        None
+    }
+}
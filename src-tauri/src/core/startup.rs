@@ -0,0 +1,118 @@
+use tauri::{AppHandle, Manager, State};
+
+use crate::{
+    messages::{self, Msg},
+    recording_buffer,
+    settings::AppSettings,
+};
+
+use super::{
+    commands::apply_autostart,
+    events::{emit_error_code, emit_settings_changed, emit_status, ErrorCode, StatusPhase},
+    hotkey, local_api, preconnect,
+    state::AppState,
+    transcript_log, tray, updater, window_state,
+};
+
+/// Second half of setup, run off the main thread: load the saved settings over the
+/// provisional defaults and apply them. Failures fall back to defaults instead of
+/// preventing launch.
+pub async fn load_settings_and_bind(app: AppHandle, has_autostart_arg: bool) {
+    let state: State<'_, AppState> = app.state();
+    // Held until the hotkeys are bound, so an early save can't interleave
+    let apply = state.lock_settings_apply().await;
+
+    let (settings, load_error) = match state.load_persisted_settings().await {
+        Ok(settings) => (settings, None),
+        Err(e) => {
+            tracing::error!("[Setup] Failed to load settings, using defaults: {:#}", e);
+            (AppSettings::default(), Some(e))
+        }
+    };
+    state.replace_settings(settings.clone()).await;
+    state.enforce_history_limits().await;
+    state.mark_settings_loaded();
+    transcript_log::cleanup_on_startup(&app, settings.log_transcripts).await;
+    recording_buffer::remove_stale_spills();
+
+    // Check if app should start minimized:
+    // 1. Command line args --autostart or --minimized (for autostart plugin)
+    // 2. Setting: start_minimized is true
+    let should_start_minimized = has_autostart_arg || settings.start_minimized;
+    tracing::info!(
+        "[Setup] Start minimized: {} (args: {}, setting: {})",
+        should_start_minimized,
+        has_autostart_arg,
+        settings.start_minimized
+    );
+    window_state::restore(&app, !should_start_minimized);
+    show_main_window(&app, !should_start_minimized);
+
+    if let Err(e) = apply_autostart(&app, settings.auto_start) {
+        tracing::warn!("[Setup] Failed to apply autostart setting: {}", e);
+    }
+    if let Err(e) = tray::refresh_tray_menu(&app, &settings) {
+        tracing::warn!("[Tray] Failed to refresh tray menu: {}", e);
+    }
+    emit_settings_changed(&app, &settings);
+
+    if let Some(e) = load_error {
+        emit_error_code(
+            &app,
+            ErrorCode::SettingsLoadFailed,
+            None,
+            &messages::fill(Msg::SettingsLoadFailed, &[("error", &e)]),
+        );
+    }
+
+    // Handle hotkey registration failure gracefully (e.g., when another instance is running)
+    // This allows the app to start but won't have hotkey functionality
+    if let Err(e) = hotkey::rebind_hotkey(&app, &settings).await {
+        tracing::warn!(
+            "[Setup] Failed to register hotkey (another instance may be running): {}",
+            e
+        );
+        // Emit an error to let the user know
+        emit_error_code(
+            &app,
+            ErrorCode::HotkeyRegistrationFailed,
+            None,
+            &messages::fill(Msg::HotkeyRegistrationFailed, &[("error", &e)]),
+        );
+    }
+    local_api::apply_settings(&app, &settings).await;
+    preconnect::apply_settings(&app, &settings);
+    drop(apply);
+    emit_status(&app, StatusPhase::Idle, None);
+
+    // Check for updates on app start (background task) - if enabled in settings.
+    // Installation waits until the app is idle so it never restarts mid-dictation.
+    if settings.auto_update {
+        updater::spawn_auto_update(&app);
+    } else {
+        tracing::info!("[Updater] Auto-update disabled in settings");
+    }
+}
+
+fn show_main_window(app: &AppHandle, visible: bool) {
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+    if !visible {
+        // Keep window hidden when starting minimized
+        if let Err(e) = window.hide() {
+            tracing::warn!("[Setup] Failed to hide main window: {}", e);
+        }
+        tracing::info!("[Setup] Window hidden (start minimized enabled)");
+        return;
+    }
+    if let Err(e) = window.show() {
+        tracing::warn!("[Setup] Failed to show main window: {}", e);
+    }
+    if let Err(e) = window.unminimize() {
+        tracing::warn!("[Setup] Failed to unminimize main window: {}", e);
+    }
+    if let Err(e) = window.set_focus() {
+        tracing::warn!("[Setup] Failed to set focus on main window: {}", e);
+    }
+}
@@ -1,27 +1,47 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
 use std::sync::{
     atomic::{AtomicBool, AtomicU64},
     Arc, Mutex,
 };
-use std::thread::JoinHandle;
+use std::time::Instant;
 
 use anyhow::Result;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use tauri::menu::MenuItem;
-use tokio::sync::RwLock;
+use tokio::sync::{oneshot, RwLock, Semaphore};
 
 use crate::{
-    audio::{Recorder, RecordingSession},
-    elevenlabs::ElevenLabsClient,
-    elevenlabs_streaming::ElevenLabsStreamingClient,
-    groq::GroqClient,
-    groq_llm::GroqLLMClient,
+    audio::{CaptureInfo, MicMute, Recorder, RecordingSession},
+    elevenlabs_protocol::{Word, WordKind},
+    elevenlabs_streaming::StreamingClient,
     input::KeyboardController,
-    openai::OpenAiClient,
-    settings::{AppSettings, SettingsStore},
+    messages::{self, Language},
+    settings::{resolve_api_key, AppOverrides, AppSettings, SettingsStore, TranscriptionProvider},
+    text_utils::{self, TextStats},
 };
 
-use super::transcription::TranscriptionService;
+use super::{
+    audio_supervisor::AudioThreadPanic,
+    client_factory::ClientFactory,
+    connect_breaker::ConnectBreaker,
+    delivery::DeliveryQueue,
+    dictation_session::SessionBuffer,
+    events::ErrorCode,
+    hotkey::HotkeyBindings,
+    local_api::LocalApiServer,
+    model_catalog::ModelCache,
+    post_command::MAX_CONCURRENT_HOOKS,
+    preconnect::Preconnector,
+    session::{DictationOptions, PendingCommit, SessionContext, Sessions, Translation},
+    session_manager::{HotkeyEvent, EVENT_QUEUE_CAPACITY},
+    speak::Speaker,
+    transcription::TranscriptionService,
+    tray::{TrayIconState, TrayMenuItems},
+    updater::DownloadedUpdate,
+    window_state::WindowState,
+};
 
 /// Entry in the transcription history
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,6 +56,12 @@ pub struct HistoryEntry {
     /// Target language if translated
     #[serde(default)]
     pub target_language: Option<String>,
+    /// The delivered text is a translation
+    #[serde(default)]
+    pub translated: bool,
+    /// Translation was requested with the translate hotkey
+    #[serde(default)]
+    pub translation_forced: bool,
     /// Transcription provider used (e.g., "openai", "groq", "elevenlabs")
     #[serde(default)]
     pub transcription_provider: Option<String>,
@@ -45,6 +71,104 @@ pub struct HistoryEntry {
     /// Whether custom instructions were applied
     #[serde(default)]
     pub custom_instructions_used: bool,
+    /// LLM refinement failed and the raw transcript was delivered instead
+    #[serde(default)]
+    pub refinement_failed: bool,
+    /// Transcribed and translated to English by OpenAI in one request
+    #[serde(default)]
+    pub combined_translation: bool,
+    /// Whether refinement changed the transcript, and by how many words
+    #[serde(default)]
+    pub was_modified: bool,
+    #[serde(default)]
+    pub change_count: usize,
+    /// Milliseconds from the end of the recording until the text was delivered
+    #[serde(default)]
+    pub latency_ms: Option<u64>,
+    /// Size of the delivered text and how fast it was spoken
+    #[serde(default)]
+    pub word_count: usize,
+    #[serde(default)]
+    pub char_count: usize,
+    #[serde(default)]
+    pub words_per_minute: Option<f64>,
+    /// Saved recording of the dictation, if one was kept
+    #[serde(default)]
+    pub recording_path: Option<PathBuf>,
+    /// Sentences of the transcript with their place in the recording, if the
+    /// provider reported timestamps
+    #[serde(default)]
+    pub segments: Vec<TimedSegment>,
+    /// Transcripts of `recording_path` by other providers, one per provider
+    #[serde(default)]
+    pub alternate_transcripts: Vec<AlternateTranscript>,
+    /// Microphone the dictation was recorded with
+    #[serde(default)]
+    pub capture: Option<CaptureInfo>,
+}
+
+/// The saved recording of an entry as another provider transcribed it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AlternateTranscript {
+    /// `TranscriptionProvider::id`
+    pub provider: String,
+    pub text: String,
+}
+
+/// A sentence of a transcript and its time span, from the start of the recording
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimedSegment {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub text: String,
+}
+
+impl TimedSegment {
+    /// Group the words of a timestamped transcript into sentences
+    pub fn sentences(words: &[Word]) -> Vec<Self> {
+        let mut segments = Vec::new();
+        let mut text = String::new();
+        let mut span: Option<(f64, f64)> = None;
+        for word in words {
+            match word.kind {
+                WordKind::Word => {
+                    text.push_str(&word.text);
+                    let start = span.map_or(word.start, |(start, _)| start);
+                    span = Some((start, word.end));
+                }
+                WordKind::Spacing => text.push_str(&word.text),
+                WordKind::AudioEvent | WordKind::Other => {}
+            }
+            let ends_sentence =
+                word.kind == WordKind::Word && word.text.trim_end().ends_with(['.', '!', '?', '…']);
+            if ends_sentence {
+                segments.extend(Self::finish(&mut text, span.take()));
+            }
+        }
+        segments.extend(Self::finish(&mut text, span));
+        segments
+    }
+
+    fn finish(text: &mut String, span: Option<(f64, f64)>) -> Option<Self> {
+        let sentence = std::mem::take(text);
+        let (start, end) = span?;
+        let to_ms = |secs: f64| (secs.max(0.0) * 1000.0).round() as u64;
+        Some(Self {
+            start_ms: to_ms(start),
+            end_ms: to_ms(end),
+            text: sentence.trim().to_string(),
+        })
+    }
+
+    /// The same sentence `offset` later, for a recording that continues an earlier one
+    fn shifted(self, offset: std::time::Duration) -> Self {
+        let offset = offset.as_millis() as u64;
+        Self {
+            start_ms: self.start_ms + offset,
+            end_ms: self.end_ms + offset,
+            ..self
+        }
+    }
 }
 
 /// Data needed to create a new history entry
@@ -52,13 +176,60 @@ pub struct NewHistoryEntry {
     pub original: String,
     pub translated: Option<String>,
     pub source_language: Option<String>,
-    pub target_language: Option<String>,
+    pub translation: Translation,
     pub transcription_provider: Option<String>,
     pub llm_provider: Option<String>,
     pub custom_instructions_used: bool,
+    pub refinement_failed: bool,
+    pub combined_translation: bool,
+    pub change_count: usize,
+    pub latency_ms: Option<u64>,
+    pub stats: TextStats,
+    pub segments: Vec<TimedSegment>,
+    pub capture: Option<CaptureInfo>,
 }
 
 impl HistoryEntry {
+    /// Extend a combined entry with the next segment of the same dictation.
+    /// `recorded` is the recording time of all its segments so far.
+    fn append_segment(&mut self, data: NewHistoryEntry, recorded: Option<std::time::Duration>) {
+        if self.translated_text.is_some() || data.translated.is_some() {
+            self.translated_text = Some(join_segments(self.final_text(), data.final_text()));
+        }
+        self.original_text = join_segments(&self.original_text, &data.original);
+        self.source_language = self.source_language.take().or(data.source_language);
+        self.target_language = self
+            .target_language
+            .take()
+            .or(data.translation.target_language);
+        self.translated |= data.translation.translated;
+        self.translation_forced |= data.translation.translation_forced;
+        self.llm_provider = self.llm_provider.take().or(data.llm_provider);
+        self.custom_instructions_used |= data.custom_instructions_used;
+        self.refinement_failed |= data.refinement_failed;
+        self.change_count += data.change_count;
+        self.was_modified = self.change_count > 0;
+        self.latency_ms = data.latency_ms;
+        self.word_count += data.stats.word_count;
+        // Plus the space between the segments
+        self.char_count += data.stats.char_count + 1;
+        self.words_per_minute =
+            recorded.and_then(|recorded| text_utils::words_per_minute(self.word_count, recorded));
+        self.segments.extend(data.segments);
+        self.capture = self.capture.take().or(data.capture);
+    }
+
+    fn set_alternate_transcript(&mut self, alternate: AlternateTranscript) {
+        match self
+            .alternate_transcripts
+            .iter_mut()
+            .find(|existing| existing.provider == alternate.provider)
+        {
+            Some(existing) => *existing = alternate,
+            None => self.alternate_transcripts.push(alternate),
+        }
+    }
+
     fn from_new(id: u64, data: NewHistoryEntry) -> Self {
         Self {
             id,
@@ -66,37 +237,274 @@ impl HistoryEntry {
             original_text: data.original,
             translated_text: data.translated,
             source_language: data.source_language,
-            target_language: data.target_language,
+            target_language: data.translation.target_language,
+            translated: data.translation.translated,
+            translation_forced: data.translation.translation_forced,
             transcription_provider: data.transcription_provider,
             llm_provider: data.llm_provider,
             custom_instructions_used: data.custom_instructions_used,
+            refinement_failed: data.refinement_failed,
+            combined_translation: data.combined_translation,
+            was_modified: data.change_count > 0,
+            change_count: data.change_count,
+            latency_ms: data.latency_ms,
+            word_count: data.stats.word_count,
+            char_count: data.stats.char_count,
+            words_per_minute: data.stats.words_per_minute,
+            recording_path: None,
+            segments: data.segments,
+            alternate_transcripts: Vec::new(),
+            capture: data.capture,
+        }
+    }
+}
+
+/// Drop entries that exceed the configured size or retention limits.
+/// `max_entries == 0` and `retention_days == 0` disable the respective limit.
+/// Returns the number of removed entries.
+pub fn prune_history(
+    history: &mut Vec<HistoryEntry>,
+    max_entries: usize,
+    retention_days: u32,
+    now: DateTime<Utc>,
+) -> usize {
+    let initial_len = history.len();
+
+    if retention_days > 0 {
+        let cutoff = now - Duration::days(i64::from(retention_days));
+        history.retain(|e| e.timestamp >= cutoff);
+    }
+
+    // Entries are stored oldest first, so trim from the front
+    if max_entries > 0 && history.len() > max_entries {
+        let drain_count = history.len() - max_entries;
+        history.drain(0..drain_count);
+    }
+
+    initial_len - history.len()
+}
+
+fn join_segments(first: &str, next: &str) -> String {
+    format!("{} {}", first.trim_end(), next.trim_start())
+}
+
+/// History entry that later segments of a streamed dictation are appended to
+#[derive(Debug, Clone, Copy)]
+struct SegmentEntry {
+    session_id: u64,
+    entry_id: u64,
+    /// Recording time of the segments in the entry
+    recorded: Option<std::time::Duration>,
+}
+
+/// Character budget for recent dictations passed to the LLM as context
+pub const REFINEMENT_CONTEXT_MAX_CHARS: usize = 2000;
+
+/// Larger recordings aren't kept for a retry; the largest upload OpenAI accepts
+const MAX_RETRY_AUDIO_BYTES: usize = 25 * 1024 * 1024;
+
+/// Audio of the latest transcription, kept until it succeeds so a failure can be
+/// retried without recording again
+#[derive(Debug, Clone)]
+pub struct RetryAudio {
+    pub wav: Vec<u8>,
+    pub session: SessionContext,
+}
+
+impl RetryAudio {
+    /// None if `wav` is too large to keep around
+    fn new(wav: &[u8], session: &SessionContext) -> Option<Self> {
+        (wav.len() <= MAX_RETRY_AUDIO_BYTES).then(|| Self {
+            wav: wav.to_vec(),
+            session: session.clone(),
+        })
+    }
+}
+
+impl HistoryEntry {
+    /// Text that was actually delivered to the user
+    pub fn final_text(&self) -> &str {
+        self.translated_text
+            .as_deref()
+            .unwrap_or(&self.original_text)
+    }
+}
+
+/// Final texts of the last `count` entries (oldest first), stopping before the
+/// total would exceed `max_chars`. `history` is in insertion order, newest last.
+pub fn context_window(history: &[HistoryEntry], count: usize, max_chars: usize) -> Vec<String> {
+    let mut context = Vec::new();
+    let mut used = 0;
+    for entry in history.iter().rev().take(count) {
+        let text = entry.final_text().trim();
+        let len = text.chars().count();
+        if used + len > max_chars {
+            break;
         }
+        used += len;
+        context.push(text.to_string());
+    }
+    context.reverse();
+    context
+}
+
+impl NewHistoryEntry {
+    /// Text that was actually delivered to the user
+    fn final_text(&self) -> &str {
+        self.translated.as_deref().unwrap_or(&self.original)
+    }
+}
+
+/// Returns true if the candidate repeats the most recent entry within `window_secs`.
+/// Guards against ElevenLabs delivering the same committed transcript twice.
+pub fn is_duplicate_entry(
+    last: Option<&HistoryEntry>,
+    candidate: &NewHistoryEntry,
+    window_secs: u64,
+    now: DateTime<Utc>,
+) -> bool {
+    let Some(last) = last else {
+        return false;
+    };
+    if window_secs == 0 {
+        return false;
     }
+    let window = Duration::seconds(i64::try_from(window_secs).unwrap_or(i64::MAX / 1000));
+    last.original_text.trim() == candidate.original.trim() && now - last.timestamp <= window
+}
+
+/// Keep the log redaction in sync with the API keys of the settings being applied
+/// (for an `env:` reference it's the variable's value that must not reach the log),
+/// and messages with their interface language
+fn register_globals(settings: AppSettings) -> AppSettings {
+    let keys = [
+        &settings.api_key,
+        &settings.groq_api_key,
+        &settings.elevenlabs_api_key,
+        &settings.deepgram_api_key,
+    ]
+    .map(|field| resolve_api_key(field).unwrap_or_default());
+    crate::redact::set_configured_keys(keys.iter().map(String::as_str));
+    messages::set_language(Language::from_code(&settings.ui_language));
+    settings
 }
 
 /// Handle for managing an audio streaming thread
 pub struct AudioStreamingHandle {
     pub cancel_token: tokio_util::sync::CancellationToken,
-    pub join_handle: JoinHandle<()>,
+    /// Resolved by the thread as its last step. Awaited instead of joining the thread,
+    /// so stopping it never blocks a runtime worker.
+    pub finished: oneshot::Receiver<()>,
+    /// Cleared by the thread right before it resolves `finished`
+    pub running: Arc<AtomicBool>,
+}
+
+impl AudioStreamingHandle {
+    /// Cancel the thread and wait until it has wound down
+    pub async fn stop(self) {
+        self.cancel_token.cancel();
+        match self.finished.await {
+            Ok(()) => tracing::info!("[Commands] Audio streaming task stopped cleanly"),
+            Err(_) => tracing::warn!("[Commands] Audio streaming task exited without signalling"),
+        }
+    }
 }
 
-/// Maximum number of history entries to keep
-const MAX_HISTORY_ENTRIES: usize = 100;
+/// Stop the thread whose handle is in `slot`, if any. The slot is only locked long
+/// enough to take the handle, so it stays usable while the thread winds down.
+async fn stop_audio_streaming_in(slot: &tokio::sync::Mutex<Option<AudioStreamingHandle>>) {
+    let handle = slot.lock().await.take();
+    if let Some(handle) = handle {
+        tracing::info!("[Commands] Stopping audio streaming task...");
+        handle.stop().await;
+    }
+}
 
 pub struct AppState {
     settings_store: SettingsStore,
     settings: RwLock<Arc<AppSettings>>,
     recorder: Recorder,
     active_recording: Mutex<Option<RecordingSession>>,
-    transcription: TranscriptionService,
-    elevenlabs_streaming: ElevenLabsStreamingClient,
-    audio_streaming_handle: Mutex<Option<AudioStreamingHandle>>,
+    /// Hard mute: shared with the recorder and the streaming gate; not persisted
+    mic_mute: MicMute,
+    /// The streaming capture has the input device open
+    capture_running: Arc<AtomicBool>,
+    /// Device of the streaming capture; None while a capture waits for unmute
+    streaming_capture: Mutex<Option<CaptureInfo>>,
+    /// Provider clients, rebuilt when the settings they use change
+    clients: ClientFactory,
+    elevenlabs_streaming: StreamingClient,
+    audio_streaming_handle: tokio::sync::Mutex<Option<AudioStreamingHandle>>,
+    /// Sends dictations straight to legacy recording while streaming connects keep failing
+    connect_breaker: ConnectBreaker,
+    /// Caps the post-transcription commands running at once
+    post_command_slots: Arc<Semaphore>,
     is_transcribing: AtomicBool,
-    /// Session ID for which force_translate was requested (0 = none)
-    force_translate_session: AtomicU64,
-    /// Current recording session counter
-    session_counter: AtomicU64,
+    /// Session IDs and the context of the recording in progress
+    sessions: Sessions,
+    /// Error code of the latest failure and text of the latest completed dictation,
+    /// for E2E tests; both cleared when a dictation starts
+    last_error: Mutex<Option<ErrorCode>>,
+    last_transcript: Mutex<Option<String>>,
+    /// Text of the latest delivery, typed again by a double-tap of the main hotkey
+    last_delivery: Mutex<Option<String>>,
+    /// Audio of the latest transcription until it succeeds
+    retry_audio: Mutex<Option<RetryAudio>>,
+    /// Hotkey presses and releases, consumed in order by the session manager
+    hotkey_events: tokio::sync::mpsc::Sender<HotkeyEvent>,
+    /// Taken once by the session manager task when it starts
+    hotkey_event_receiver: Mutex<Option<tokio::sync::mpsc::Receiver<HotkeyEvent>>>,
+    /// Bumped whenever an overlay hide is scheduled or cancelled; a pending hide
+    /// only runs if the generation is still the one it was scheduled with
+    overlay_generation: AtomicU64,
+    /// The user is placing the overlay: it takes the mouse and stays up
+    overlay_interactive: AtomicBool,
+    /// Bumped on every move/resize of the main window; only the last scheduled save runs
+    window_state_generation: AtomicU64,
+    /// Last known bounds of the main window, kept while it is maximized
+    window_state: Mutex<Option<WindowState>>,
     tray_status_item: Mutex<Option<MenuItem<tauri::Wry>>>,
+    tray_menu_items: Mutex<Option<TrayMenuItems>>,
+    tray_icon: Mutex<Option<TrayIconState>>,
+    /// Queued when a commit is sent (on gate close or for a segment), taken in order
+    /// by the committed-transcript handler
+    pending_commits: Mutex<VecDeque<PendingCommit>>,
+    /// Entry the segments of the current streamed dictation are combined into
+    segment_entry: Mutex<Option<SegmentEntry>>,
+    /// Cancels the `recording://tick` timer of the active recording
+    recording_timer: Mutex<Option<tokio_util::sync::CancellationToken>>,
+    /// Cancels the running batch of `retranscribe_recordings`
+    retranscription: Mutex<Option<tokio_util::sync::CancellationToken>>,
+    /// Last panic of the audio streaming thread, for diagnostics
+    audio_thread_panic: Mutex<Option<AudioThreadPanic>>,
+    /// Errors from the last hotkey rebind; None until hotkeys were bound once
+    hotkey_errors: Mutex<Option<Vec<String>>>,
+    /// Hotkeys registered by the last rebind that bound all of them
+    bound_hotkeys: Mutex<Option<HotkeyBindings>>,
+    /// When the status last moved to Transcribing; cleared by any other phase
+    transcribing_since: Mutex<Option<Instant>>,
+    /// Update package downloaded and waiting for `install_update`
+    pending_update: Mutex<Option<DownloadedUpdate>>,
+    /// Provider model lists for the settings' model picker
+    model_cache: ModelCache,
+    /// Output delivery of both transcription paths takes turns here
+    delivery: DeliveryQueue,
+    /// Reads delivered transcripts aloud
+    speaker: Speaker,
+    /// Utterances of the running dictation session
+    dictation_session: SessionBuffer,
+    /// Speculative streaming connects while the hotkey's modifiers are held
+    preconnect: Preconnector,
+    /// HTTP API for external tools (`local_api_enabled`)
+    local_api: LocalApiServer,
+    /// Held while settings are written to disk, so shutdown can wait for the write
+    settings_save: tokio::sync::Mutex<()>,
+    /// Held by a settings change from persisting it through applying it (hotkeys,
+    /// tray, autostart), so disk, memory and the registered hotkeys can't end up
+    /// from different versions
+    settings_apply: tokio::sync::Mutex<()>,
+    /// Flipped once the saved settings replaced the provisional defaults
+    settings_loaded: tokio::sync::watch::Sender<bool>,
     /// Transcription history
     history: RwLock<Vec<HistoryEntry>>,
     /// Counter for generating unique history entry IDs
@@ -105,30 +513,61 @@ pub struct AppState {
 
 impl AppState {
     pub fn new(settings_store: SettingsStore, initial: AppSettings) -> Result<Self> {
-        let recorder = Recorder::new()?;
+        let mic_mute = MicMute::default();
+        let recorder = Recorder::new(mic_mute.clone())?;
         let keyboard = Arc::new(KeyboardController::new()?);
-        let transcription = TranscriptionService::new(
-            OpenAiClient::new()?,
-            GroqClient::new()?,
-            GroqLLMClient::new()?,
-            ElevenLabsClient::new()?,
-            keyboard,
-        );
+        let clients = ClientFactory::new(keyboard, &initial)?;
 
-        let elevenlabs_streaming = ElevenLabsStreamingClient::new();
+        let elevenlabs_streaming = StreamingClient::new().with_mic_mute(mic_mute.clone());
+        let (hotkey_events, hotkey_event_receiver) =
+            tokio::sync::mpsc::channel(EVENT_QUEUE_CAPACITY);
 
         Ok(Self {
             settings_store,
-            settings: RwLock::new(Arc::new(initial)),
+            settings: RwLock::new(Arc::new(register_globals(initial))),
             recorder,
             active_recording: Mutex::new(None),
-            transcription,
+            mic_mute,
+            capture_running: Arc::new(AtomicBool::new(false)),
+            streaming_capture: Mutex::new(None),
+            clients,
             elevenlabs_streaming,
-            audio_streaming_handle: Mutex::new(None),
+            audio_streaming_handle: tokio::sync::Mutex::new(None),
+            connect_breaker: ConnectBreaker::default(),
+            post_command_slots: Arc::new(Semaphore::new(MAX_CONCURRENT_HOOKS)),
             is_transcribing: AtomicBool::new(false),
-            force_translate_session: AtomicU64::new(0),
-            session_counter: AtomicU64::new(0),
+            sessions: Sessions::default(),
+            last_error: Mutex::new(None),
+            last_transcript: Mutex::new(None),
+            last_delivery: Mutex::new(None),
+            retry_audio: Mutex::new(None),
+            hotkey_events,
+            hotkey_event_receiver: Mutex::new(Some(hotkey_event_receiver)),
+            overlay_generation: AtomicU64::new(0),
+            overlay_interactive: AtomicBool::new(false),
+            window_state_generation: AtomicU64::new(0),
+            window_state: Mutex::new(None),
             tray_status_item: Mutex::new(None),
+            tray_menu_items: Mutex::new(None),
+            tray_icon: Mutex::new(None),
+            pending_commits: Mutex::new(VecDeque::new()),
+            segment_entry: Mutex::new(None),
+            recording_timer: Mutex::new(None),
+            retranscription: Mutex::new(None),
+            audio_thread_panic: Mutex::new(None),
+            hotkey_errors: Mutex::new(None),
+            bound_hotkeys: Mutex::new(None),
+            transcribing_since: Mutex::new(None),
+            pending_update: Mutex::new(None),
+            model_cache: ModelCache::default(),
+            delivery: DeliveryQueue::default(),
+            speaker: Speaker::default(),
+            dictation_session: SessionBuffer::default(),
+            preconnect: Preconnector::default(),
+            local_api: LocalApiServer::default(),
+            settings_save: tokio::sync::Mutex::new(()),
+            settings_apply: tokio::sync::Mutex::new(()),
+            settings_loaded: tokio::sync::watch::Sender::new(false),
             history: RwLock::new(Vec::new()),
             history_id_counter: std::sync::atomic::AtomicU64::new(1),
         })
@@ -138,6 +577,11 @@ impl AppState {
         self.settings.read().await.clone()
     }
 
+    /// `current_settings` for synchronous callers; None while they are being replaced
+    pub fn try_current_settings(&self) -> Option<Arc<AppSettings>> {
+        self.settings.try_read().ok().map(|guard| guard.clone())
+    }
+
     /// Atomically read-modify-write settings under an exclusive lock.
     pub async fn update_settings<F>(&self, f: F) -> AppSettings
     where
@@ -146,28 +590,90 @@ impl AppState {
         let mut guard = self.settings.write().await;
         let mut new = (**guard).clone();
         f(&mut new);
-        *guard = Arc::new(new.clone());
+        self.clients.refresh(&new);
+        *guard = Arc::new(register_globals(new.clone()));
         new
     }
 
     pub async fn replace_settings(&self, next: AppSettings) {
-        *self.settings.write().await = Arc::new(next);
+        let mut guard = self.settings.write().await;
+        self.clients.refresh(&next);
+        *guard = Arc::new(register_globals(next));
     }
 
     pub async fn persist_settings(&self, next: &AppSettings) -> Result<()> {
+        let _saving = self.settings_save.lock().await;
         self.settings_store.save(next).await
     }
 
+    /// The settings file, for unlocking or re-encrypting its API keys
+    pub fn settings_store(&self) -> &SettingsStore {
+        &self.settings_store
+    }
+
+    pub fn settings_path(&self) -> std::path::PathBuf {
+        self.settings_store.file_path()
+    }
+
+    /// Read the saved settings from disk (without applying them)
+    pub async fn load_persisted_settings(&self) -> Result<AppSettings> {
+        self.settings_store.load().await
+    }
+
+    /// Startup is done replacing the provisional defaults
+    pub fn mark_settings_loaded(&self) {
+        self.settings_loaded.send_replace(true);
+    }
+
+    pub async fn wait_for_settings_loaded(&self) {
+        let mut loaded = self.settings_loaded.subscribe();
+        let _ = loaded.wait_for(|loaded| *loaded).await;
+    }
+
+    /// Take turns with other settings changes; hold the guard until the change is
+    /// persisted, current and applied
+    pub async fn lock_settings_apply(&self) -> tokio::sync::MutexGuard<'_, ()> {
+        self.settings_apply.lock().await
+    }
+
+    /// Wait for an in-flight `persist_settings` to finish writing
+    pub async fn wait_for_settings_save(&self) {
+        drop(self.settings_save.lock().await);
+    }
+
     pub fn recorder(&self) -> &Recorder {
         &self.recorder
     }
 
+    pub fn mic_mute(&self) -> &MicMute {
+        &self.mic_mute
+    }
+
+    pub fn capture_running(&self) -> Arc<AtomicBool> {
+        self.capture_running.clone()
+    }
+
+    pub fn streaming_capture(&self) -> Option<CaptureInfo> {
+        self.streaming_capture
+            .lock()
+            .ok()
+            .and_then(|guard| guard.clone())
+    }
+
+    pub fn set_streaming_capture(&self, capture: Option<CaptureInfo>) {
+        if let Ok(mut guard) = self.streaming_capture.lock() {
+            *guard = capture;
+        }
+    }
+
     pub fn active_recording(&self) -> &Mutex<Option<RecordingSession>> {
         &self.active_recording
     }
 
+    /// The provider clients as currently configured; keep the returned service for
+    /// the whole of one transcription
     pub fn transcription(&self) -> TranscriptionService {
-        self.transcription.clone()
+        self.clients.service()
     }
 
     pub fn is_transcribing(&self) -> &AtomicBool {
@@ -178,66 +684,394 @@ impl AppState {
         &self.tray_status_item
     }
 
-    pub fn elevenlabs_streaming(&self) -> &ElevenLabsStreamingClient {
+    pub fn tray_menu_items(&self) -> &Mutex<Option<TrayMenuItems>> {
+        &self.tray_menu_items
+    }
+
+    pub fn tray_icon(&self) -> &Mutex<Option<TrayIconState>> {
+        &self.tray_icon
+    }
+
+    pub fn pending_commits(&self) -> &Mutex<VecDeque<PendingCommit>> {
+        &self.pending_commits
+    }
+
+    pub fn recording_timer(&self) -> &Mutex<Option<tokio_util::sync::CancellationToken>> {
+        &self.recording_timer
+    }
+
+    pub fn retranscription(&self) -> &Mutex<Option<tokio_util::sync::CancellationToken>> {
+        &self.retranscription
+    }
+
+    pub fn audio_thread_panic(&self) -> &Mutex<Option<AudioThreadPanic>> {
+        &self.audio_thread_panic
+    }
+
+    pub fn hotkey_errors(&self) -> &Mutex<Option<Vec<String>>> {
+        &self.hotkey_errors
+    }
+
+    pub fn bound_hotkeys(&self) -> &Mutex<Option<HotkeyBindings>> {
+        &self.bound_hotkeys
+    }
+
+    pub fn transcribing_since(&self) -> &Mutex<Option<Instant>> {
+        &self.transcribing_since
+    }
+
+    pub fn model_cache(&self) -> &ModelCache {
+        &self.model_cache
+    }
+
+    pub fn speaker(&self) -> &Speaker {
+        &self.speaker
+    }
+
+    pub fn dictation_session(&self) -> &SessionBuffer {
+        &self.dictation_session
+    }
+
+    pub fn preconnect(&self) -> &Preconnector {
+        &self.preconnect
+    }
+
+    pub fn local_api(&self) -> &LocalApiServer {
+        &self.local_api
+    }
+
+    pub fn pending_update(&self) -> &Mutex<Option<DownloadedUpdate>> {
+        &self.pending_update
+    }
+
+    pub fn delivery(&self) -> &DeliveryQueue {
+        &self.delivery
+    }
+
+    pub fn elevenlabs_streaming(&self) -> &StreamingClient {
         &self.elevenlabs_streaming
     }
 
-    pub fn audio_streaming_handle(&self) -> &Mutex<Option<AudioStreamingHandle>> {
+    pub fn connect_breaker(&self) -> &ConnectBreaker {
+        &self.connect_breaker
+    }
+
+    pub fn post_command_slots(&self) -> &Arc<Semaphore> {
+        &self.post_command_slots
+    }
+
+    pub fn audio_streaming_handle(&self) -> &tokio::sync::Mutex<Option<AudioStreamingHandle>> {
         &self.audio_streaming_handle
     }
 
-    /// Start a new recording session and return its ID.
-    /// If `force_translate` is true, mark this session for forced translation.
-    pub fn start_session(&self, force_translate: bool) -> u64 {
-        let session_id = self
-            .session_counter
-            .fetch_add(1, std::sync::atomic::Ordering::SeqCst)
-            + 1;
-        if force_translate {
-            self.force_translate_session
-                .store(session_id, std::sync::atomic::Ordering::SeqCst);
+    /// Stop the audio streaming thread, if one is running, and wait for it to finish
+    pub async fn stop_audio_streaming(&self) {
+        stop_audio_streaming_in(&self.audio_streaming_handle).await;
+    }
+
+    /// Whether an audio streaming thread is registered and hasn't exited yet
+    pub async fn audio_streaming_running(&self) -> bool {
+        self.audio_streaming_handle
+            .lock()
+            .await
+            .as_ref()
+            .is_some_and(|handle| handle.running.load(std::sync::atomic::Ordering::SeqCst))
+    }
+
+    pub fn hotkey_events(&self) -> &tokio::sync::mpsc::Sender<HotkeyEvent> {
+        &self.hotkey_events
+    }
+
+    /// Hotkey events waiting for the session manager
+    pub fn queued_hotkey_events(&self) -> usize {
+        self.hotkey_events.max_capacity() - self.hotkey_events.capacity()
+    }
+
+    pub fn record_error(&self, code: ErrorCode) {
+        if let Ok(mut guard) = self.last_error.lock() {
+            *guard = Some(code);
         }
-        session_id
     }
 
-    /// Get the current session ID (set by the last start_session call).
-    pub fn current_session_id(&self) -> u64 {
-        self.session_counter
-            .load(std::sync::atomic::Ordering::SeqCst)
+    pub fn last_error(&self) -> Option<ErrorCode> {
+        self.last_error.lock().ok().and_then(|guard| *guard)
+    }
+
+    pub fn record_transcript(&self, text: &str) {
+        if let Ok(mut guard) = self.last_transcript.lock() {
+            *guard = Some(text.to_string());
+        }
+    }
+
+    pub fn last_transcript(&self) -> Option<String> {
+        self.last_transcript
+            .lock()
+            .ok()
+            .and_then(|guard| guard.clone())
     }
 
-    /// Check if the given session has force_translate, and consume it.
-    pub fn take_force_translate(&self, session_id: u64) -> bool {
-        let stored = self
-            .force_translate_session
-            .load(std::sync::atomic::Ordering::SeqCst);
-        if stored == session_id && stored != 0 {
-            self.force_translate_session
-                .store(0, std::sync::atomic::Ordering::SeqCst);
-            true
-        } else {
-            false
+    pub fn record_delivery(&self, text: &str) {
+        if let Ok(mut guard) = self.last_delivery.lock() {
+            *guard = Some(text.to_string());
         }
     }
 
-    /// Add a new entry to the history
-    pub async fn add_history_entry(&self, data: NewHistoryEntry) -> HistoryEntry {
+    pub fn last_delivery(&self) -> Option<String> {
+        self.last_delivery
+            .lock()
+            .ok()
+            .and_then(|guard| guard.clone())
+    }
+
+    /// Keep `wav` for `retry_last_transcription` until its transcription succeeds;
+    /// a recording too large to keep drops the one kept before
+    pub fn keep_for_retry(&self, wav: &[u8], session: &SessionContext) {
+        if let Ok(mut guard) = self.retry_audio.lock() {
+            *guard = RetryAudio::new(wav, session);
+        }
+    }
+
+    pub fn clear_retry_audio(&self) {
+        if let Ok(mut guard) = self.retry_audio.lock() {
+            *guard = None;
+        }
+    }
+
+    pub fn has_retry_audio(&self) -> bool {
+        self.retry_audio.lock().is_ok_and(|guard| guard.is_some())
+    }
+
+    pub fn retry_audio(&self) -> Option<RetryAudio> {
+        self.retry_audio.lock().ok().and_then(|guard| guard.clone())
+    }
+
+    /// The receiving end of `hotkey_events`; None once the session manager took it
+    pub fn take_hotkey_event_receiver(&self) -> Option<tokio::sync::mpsc::Receiver<HotkeyEvent>> {
+        self.hotkey_event_receiver
+            .lock()
+            .ok()
+            .and_then(|mut guard| guard.take())
+    }
+
+    /// Start a new recording session; its context travels with the audio
+    pub fn start_session(&self, options: DictationOptions) -> SessionContext {
+        if let Ok(mut guard) = self.last_error.lock() {
+            *guard = None;
+        }
+        if let Ok(mut guard) = self.last_transcript.lock() {
+            *guard = None;
+        }
+        self.sessions.start(options)
+    }
+
+    pub fn set_app_overrides(&self, id: u64, overrides: AppOverrides) {
+        self.sessions.set_app_overrides(id, overrides);
+    }
+
+    pub fn set_capture(&self, id: u64, capture: CaptureInfo) {
+        self.sessions.set_capture(id, capture);
+    }
+
+    pub fn set_preconnect_saved(&self, id: u64, saved: std::time::Duration) {
+        self.sessions.set_preconnect_saved(id, saved);
+    }
+
+    /// The recording `id` is being transcribed in the target language
+    pub fn mark_transcribed_in_target(&self, id: u64) {
+        self.sessions.mark_transcribed_in_target(id);
+    }
+
+    pub fn mark_gate_opened(&self, id: u64) {
+        self.sessions.mark_gate_opened(id);
+    }
+
+    /// Context of the recording in progress, left in place
+    pub fn recording_session(&self) -> Option<SessionContext> {
+        self.sessions.current()
+    }
+
+    /// End the current segment of the streamed recording; see `Sessions::next_segment`
+    pub fn next_segment(&self) -> Option<SessionContext> {
+        self.sessions.next_segment()
+    }
+
+    /// Transcribe the next session with `provider` (None clears the choice)
+    pub fn set_next_provider(&self, provider: Option<TranscriptionProvider>) {
+        self.sessions.set_next_provider(provider);
+    }
+
+    /// Context of the recording that just stopped (set by `start_session`)
+    pub fn take_recording_session(&self) -> Option<SessionContext> {
+        self.sessions.take_recording()
+    }
+
+    pub fn next_overlay_generation(&self) -> u64 {
+        self.overlay_generation
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst)
+            + 1
+    }
+
+    pub fn is_overlay_generation(&self, generation: u64) -> bool {
+        self.overlay_generation
+            .load(std::sync::atomic::Ordering::SeqCst)
+            == generation
+    }
+
+    pub fn set_overlay_interactive(&self, interactive: bool) {
+        self.overlay_interactive
+            .store(interactive, std::sync::atomic::Ordering::SeqCst);
+    }
+
+    pub fn is_overlay_interactive(&self) -> bool {
+        self.overlay_interactive
+            .load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    pub fn next_window_state_generation(&self) -> u64 {
+        self.window_state_generation
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst)
+            + 1
+    }
+
+    pub fn is_window_state_generation(&self, generation: u64) -> bool {
+        self.window_state_generation
+            .load(std::sync::atomic::Ordering::SeqCst)
+            == generation
+    }
+
+    pub fn window_state(&self) -> &Mutex<Option<WindowState>> {
+        &self.window_state
+    }
+
+    /// Add a new entry to the history.
+    /// Returns `None` if the entry was skipped (empty result or duplicate of the last entry).
+    pub async fn add_history_entry(&self, data: NewHistoryEntry) -> Option<HistoryEntry> {
+        if data.final_text().trim().is_empty() {
+            tracing::debug!("[History] Skipping empty result");
+            return None;
+        }
+
+        let settings = self.current_settings().await;
+
+        let mut history = self.history.write().await;
+        if is_duplicate_entry(
+            history.last(),
+            &data,
+            settings.history_dedupe_window_secs,
+            Utc::now(),
+        ) {
+            tracing::info!("[History] Skipping duplicate of the most recent entry");
+            return None;
+        }
+
         let id = self
             .history_id_counter
             .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
         let entry = HistoryEntry::from_new(id, data);
-
-        let mut history = self.history.write().await;
         history.push(entry.clone());
 
-        // Keep only the last MAX_HISTORY_ENTRIES
-        if history.len() > MAX_HISTORY_ENTRIES {
-            let drain_count = history.len() - MAX_HISTORY_ENTRIES;
-            history.drain(0..drain_count);
-        }
+        prune_history(
+            &mut history,
+            settings.history_max_entries,
+            settings.history_retention_days,
+            Utc::now(),
+        );
 
         tracing::info!("[History] Added entry {} (total: {})", id, history.len());
-        entry
+        Some(entry)
+    }
+
+    /// Add a segment of streamed dictation `session_id`, appending it to the entry of
+    /// the dictation's earlier segments if there is one. `recorded` is the segment's
+    /// own recording time.
+    pub async fn add_combined_history_entry(
+        &self,
+        session_id: u64,
+        data: NewHistoryEntry,
+        recorded: Option<std::time::Duration>,
+    ) -> Option<HistoryEntry> {
+        let open = self
+            .segment_entry
+            .lock()
+            .ok()
+            .and_then(|guard| *guard)
+            .filter(|open| open.session_id == session_id);
+
+        if let Some(open) = open {
+            if data.final_text().trim().is_empty() {
+                return None;
+            }
+            // The segment's timings count from its own start; without the length of
+            // the earlier segments they can't be placed in the dictation
+            let mut data = data;
+            data.segments = match open.recorded {
+                Some(earlier) => data
+                    .segments
+                    .into_iter()
+                    .map(|segment| segment.shifted(earlier))
+                    .collect(),
+                None => Vec::new(),
+            };
+            let recorded = open.recorded.zip(recorded).map(|(a, b)| a + b);
+            let mut history = self.history.write().await;
+            // Gone if it was deleted or pruned meanwhile; the segment starts a new one
+            if let Some(entry) = history.iter_mut().find(|entry| entry.id == open.entry_id) {
+                entry.append_segment(data, recorded);
+                if let Ok(mut guard) = self.segment_entry.lock() {
+                    *guard = Some(SegmentEntry { recorded, ..open });
+                }
+                tracing::info!("[History] Appended segment to entry {}", open.entry_id);
+                return Some(entry.clone());
+            }
+        }
+
+        let entry = self.add_history_entry(data).await?;
+        if let Ok(mut guard) = self.segment_entry.lock() {
+            *guard = Some(SegmentEntry {
+                session_id,
+                entry_id: entry.id,
+                recorded,
+            });
+        }
+        Some(entry)
+    }
+
+    /// Re-apply the configured size and retention limits to the stored history.
+    /// Called on startup and whenever the limits change.
+    pub async fn enforce_history_limits(&self) -> usize {
+        let settings = self.current_settings().await;
+        let mut history = self.history.write().await;
+        let removed = prune_history(
+            &mut history,
+            settings.history_max_entries,
+            settings.history_retention_days,
+            Utc::now(),
+        );
+        if removed > 0 {
+            tracing::info!("[History] Purged {} entries exceeding limits", removed);
+        }
+        removed
+    }
+
+    /// Delete all history entries older than `days` days; 0 purges nothing
+    pub async fn purge_history_older_than(&self, days: u32) -> usize {
+        let mut history = self.history.write().await;
+        let removed = prune_history(&mut history, 0, days, Utc::now());
+        tracing::info!(
+            "[History] Purged {} entries older than {} days",
+            removed,
+            days
+        );
+        removed
+    }
+
+    /// Recent dictations to give the LLM as context; empty when `count` is 0
+    pub async fn refinement_context(&self, count: usize) -> Vec<String> {
+        if count == 0 {
+            return Vec::new();
+        }
+        let history = self.history.read().await;
+        context_window(&history, count, REFINEMENT_CONTEXT_MAX_CHARS)
     }
 
     /// Get all history entries (newest first)
@@ -256,6 +1090,22 @@ impl AppState {
     }
 
     /// Delete a specific history entry by ID
+    pub async fn history_entry(&self, id: u64) -> Option<HistoryEntry> {
+        let history = self.history.read().await;
+        history.iter().find(|entry| entry.id == id).cloned()
+    }
+
+    /// Store `alternate` on entry `id`, replacing an earlier transcript by the same
+    /// provider. False if the entry is gone.
+    pub async fn add_alternate_transcript(&self, id: u64, alternate: AlternateTranscript) -> bool {
+        let mut history = self.history.write().await;
+        let Some(entry) = history.iter_mut().find(|entry| entry.id == id) else {
+            return false;
+        };
+        entry.set_alternate_transcript(alternate);
+        true
+    }
+
     pub async fn delete_history_entry(&self, id: u64) -> bool {
         let mut history = self.history.write().await;
         let initial_len = history.len();
@@ -267,3 +1117,427 @@ impl AppState {
         deleted
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_audio_is_bounded() {
+        let session = SessionContext {
+            id: 7,
+            ..SessionContext::default()
+        };
+        let kept = RetryAudio::new(b"RIFF", &session).unwrap();
+        assert_eq!(kept.wav, b"RIFF");
+        assert_eq!(kept.session, session);
+        assert!(RetryAudio::new(&vec![0; MAX_RETRY_AUDIO_BYTES + 1], &session).is_none());
+    }
+
+    /// A streaming thread that needs `wind_down` after cancellation, like one whose
+    /// capture backend is slow to release the device
+    fn slow_audio_thread(wind_down: std::time::Duration) -> AudioStreamingHandle {
+        let cancel_token = tokio_util::sync::CancellationToken::new();
+        let (done, finished) = oneshot::channel();
+        let running = Arc::new(AtomicBool::new(true));
+        let thread_token = cancel_token.clone();
+        let thread_running = running.clone();
+        std::thread::spawn(move || {
+            while !thread_token.is_cancelled() {
+                std::thread::sleep(std::time::Duration::from_millis(5));
+            }
+            std::thread::sleep(wind_down);
+            thread_running.store(false, std::sync::atomic::Ordering::SeqCst);
+            let _ = done.send(());
+        });
+        AudioStreamingHandle {
+            cancel_token,
+            finished,
+            running,
+        }
+    }
+
+    // Single-threaded runtime: a blocking join would starve everything else
+    #[tokio::test]
+    async fn test_stopping_slow_audio_thread_keeps_runtime_responsive() {
+        let slot = Arc::new(tokio::sync::Mutex::new(Some(slow_audio_thread(
+            std::time::Duration::from_secs(2),
+        ))));
+
+        let started = Instant::now();
+        let stopping = {
+            let slot = slot.clone();
+            tokio::spawn(async move { stop_audio_streaming_in(&slot).await })
+        };
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        // Other work runs, and the slot is free for the next connect
+        let probe = Instant::now();
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        assert!(probe.elapsed() < std::time::Duration::from_millis(500));
+        assert!(slot.lock().await.is_none());
+        assert!(!stopping.is_finished());
+
+        stopping.await.unwrap();
+        assert!(started.elapsed() >= std::time::Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn test_stopping_thread_that_died_without_signalling() {
+        let (done, finished) = oneshot::channel::<()>();
+        drop(done);
+        let slot = tokio::sync::Mutex::new(Some(AudioStreamingHandle {
+            cancel_token: tokio_util::sync::CancellationToken::new(),
+            finished,
+            running: Arc::new(AtomicBool::new(false)),
+        }));
+        stop_audio_streaming_in(&slot).await;
+        assert!(slot.lock().await.is_none());
+    }
+
+    /// Saves from the settings form and translation toggles, racing each other with
+    /// the same lock discipline as `save_settings` and `change_translation`
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_saves_and_toggles_stay_consistent() {
+        let root = std::env::temp_dir().join(format!("easy-dictate-apply-{}", std::process::id()));
+        let state = Arc::new(
+            AppState::new(
+                SettingsStore::without_keyring(root.clone()),
+                AppSettings::default(),
+            )
+            .unwrap(),
+        );
+
+        let tasks: Vec<_> = (0..40)
+            .map(|i| {
+                let state = state.clone();
+                tokio::spawn(async move {
+                    let _apply = state.lock_settings_apply().await;
+                    if i % 2 == 0 {
+                        let mut next = (*state.current_settings().await).clone();
+                        next.hotkey = format!("Ctrl+Shift+F{}", i % 12 + 1);
+                        next.auto_translate = i % 4 == 0;
+                        state.persist_settings(&next).await.unwrap();
+                        tokio::task::yield_now().await;
+                        state.replace_settings(next).await;
+                    } else {
+                        let next = state
+                            .update_settings(|s| s.auto_translate = !s.auto_translate)
+                            .await;
+                        tokio::task::yield_now().await;
+                        state.persist_settings(&next).await.unwrap();
+                    }
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        let current = state.current_settings().await;
+        let on_disk = state.load_persisted_settings().await.unwrap();
+        assert_eq!(on_disk.hotkey, current.hotkey);
+        assert_eq!(on_disk.auto_translate, current.auto_translate);
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    /// Local stand-in for an OpenAI-compatible API that lists one model, `name`
+    async fn models_server(name: &'static str) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = [0u8; 4096];
+                let _ = socket.read(&mut request).await;
+                let body = format!(r#"{{"data":[{{"id":"{name}"}}]}}"#);
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\n\
+                     content-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_changed_base_url_rebuilds_the_clients() {
+        let root =
+            std::env::temp_dir().join(format!("easy-dictate-clients-{}", std::process::id()));
+        let state = AppState::new(
+            SettingsStore::without_keyring(root.clone()),
+            AppSettings::default(),
+        )
+        .unwrap();
+        let with_url = |url: String| AppSettings {
+            openai_base_url: url,
+            ..AppSettings::default()
+        };
+        let models = |service: TranscriptionService| async move {
+            service
+                .list_models(&TranscriptionProvider::OpenAI, "sk-test")
+                .await
+                .unwrap()
+        };
+
+        state
+            .replace_settings(with_url(models_server("first").await))
+            .await;
+        let in_flight = state.transcription();
+        state
+            .replace_settings(with_url(models_server("second").await))
+            .await;
+
+        assert_eq!(models(state.transcription()).await, vec!["second"]);
+        // A transcription that started before the change finishes with the old clients
+        assert_eq!(models(in_flight).await, vec!["first"]);
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    fn new_entry(original: &str, translated: Option<&str>) -> NewHistoryEntry {
+        NewHistoryEntry {
+            original: original.to_string(),
+            translated: translated.map(str::to_string),
+            source_language: None,
+            translation: Translation::default(),
+            transcription_provider: None,
+            llm_provider: None,
+            custom_instructions_used: false,
+            refinement_failed: false,
+            combined_translation: false,
+            change_count: 0,
+            latency_ms: None,
+            stats: TextStats::default(),
+            segments: Vec::new(),
+            capture: None,
+        }
+    }
+
+    fn entry_at(id: u64, timestamp: DateTime<Utc>) -> HistoryEntry {
+        let mut entry = HistoryEntry::from_new(id, new_entry(&format!("entry {id}"), None));
+        entry.timestamp = timestamp;
+        entry
+    }
+
+    #[test]
+    fn test_prune_history_max_entries_keeps_newest() {
+        let now = Utc::now();
+        let mut history: Vec<_> = (1..=5).map(|id| entry_at(id, now)).collect();
+
+        let removed = prune_history(&mut history, 3, 0, now);
+        assert_eq!(removed, 2);
+        assert_eq!(
+            history.iter().map(|e| e.id).collect::<Vec<_>>(),
+            vec![3, 4, 5]
+        );
+    }
+
+    #[test]
+    fn test_prune_history_retention_days() {
+        let now = Utc::now();
+        let mut history = vec![
+            entry_at(1, now - Duration::days(10)),
+            entry_at(2, now - Duration::days(2)),
+            entry_at(3, now),
+        ];
+
+        let removed = prune_history(&mut history, 0, 7, now);
+        assert_eq!(removed, 1);
+        assert_eq!(history.iter().map(|e| e.id).collect::<Vec<_>>(), vec![2, 3]);
+    }
+
+    #[test]
+    fn test_prune_history_zero_limits_are_unlimited() {
+        let now = Utc::now();
+        let mut history: Vec<_> = (1..=200)
+            .map(|id| entry_at(id, now - Duration::days(365)))
+            .collect();
+
+        assert_eq!(prune_history(&mut history, 0, 0, now), 0);
+        assert_eq!(history.len(), 200);
+    }
+
+    #[test]
+    fn test_duplicate_within_window_is_detected() {
+        let now = Utc::now();
+        let last = entry_at(1, now - Duration::seconds(5));
+        assert!(is_duplicate_entry(
+            Some(&last),
+            &new_entry("  entry 1 ", None),
+            30,
+            now
+        ));
+    }
+
+    #[test]
+    fn test_duplicate_outside_window_or_different_text_is_kept() {
+        let now = Utc::now();
+        let last = entry_at(1, now - Duration::seconds(60));
+        assert!(!is_duplicate_entry(
+            Some(&last),
+            &new_entry("entry 1", None),
+            30,
+            now
+        ));
+        assert!(!is_duplicate_entry(
+            Some(&entry_at(1, now)),
+            &new_entry("something else", None),
+            30,
+            now
+        ));
+        assert!(!is_duplicate_entry(
+            None,
+            &new_entry("entry 1", None),
+            30,
+            now
+        ));
+    }
+
+    #[test]
+    fn test_zero_window_disables_dedupe() {
+        let now = Utc::now();
+        assert!(!is_duplicate_entry(
+            Some(&entry_at(1, now)),
+            &new_entry("entry 1", None),
+            0,
+            now
+        ));
+    }
+
+    #[test]
+    fn test_final_text_prefers_translation() {
+        assert_eq!(new_entry("привет", Some("hello")).final_text(), "hello");
+        assert!(new_entry("привет", Some("  "))
+            .final_text()
+            .trim()
+            .is_empty());
+        assert!(new_entry(" \n", None).final_text().trim().is_empty());
+    }
+
+    #[test]
+    fn test_sentences_from_word_timings() {
+        let word = |text: &str, start: f64, end: f64, kind: WordKind| Word {
+            text: text.to_string(),
+            start,
+            end,
+            kind,
+            speaker_id: None,
+            logprob: None,
+        };
+        let words = [
+            word("Hello", 0.12, 0.48, WordKind::Word),
+            word(" ", 0.48, 0.52, WordKind::Spacing),
+            word("world.", 0.52, 0.9, WordKind::Word),
+            word(" ", 0.9, 1.3, WordKind::Spacing),
+            word("(laughs)", 1.3, 1.6, WordKind::AudioEvent),
+            word("How", 1.6, 1.8, WordKind::Word),
+            word(" ", 1.8, 1.85, WordKind::Spacing),
+            word("are", 1.85, 2.0, WordKind::Word),
+        ];
+        let segment = |start_ms, end_ms, text: &str| TimedSegment {
+            start_ms,
+            end_ms,
+            text: text.to_string(),
+        };
+        assert_eq!(
+            TimedSegment::sentences(&words),
+            vec![
+                segment(120, 900, "Hello world."),
+                segment(1600, 2000, "How are"),
+            ]
+        );
+        assert!(TimedSegment::sentences(&[]).is_empty());
+        assert_eq!(
+            segment(120, 900, "Hello world.").shifted(std::time::Duration::from_secs(2)),
+            segment(2120, 2900, "Hello world.")
+        );
+    }
+
+    #[test]
+    fn test_append_segment_combines_texts_and_stats() {
+        let stats = |word_count, char_count| TextStats {
+            word_count,
+            char_count,
+            words_per_minute: None,
+        };
+        let mut entry = HistoryEntry::from_new(
+            1,
+            NewHistoryEntry {
+                latency_ms: Some(900),
+                stats: stats(2, 11),
+                ..new_entry("Hello there", None)
+            },
+        );
+        entry.append_segment(
+            NewHistoryEntry {
+                change_count: 1,
+                latency_ms: Some(400),
+                stats: stats(2, 10),
+                translation: Translation {
+                    translated: true,
+                    translation_forced: true,
+                    target_language: Some("English".to_string()),
+                },
+                ..new_entry("How are", Some("how are you"))
+            },
+            Some(std::time::Duration::from_secs(3)),
+        );
+
+        assert_eq!(entry.original_text, "Hello there How are");
+        // A translated segment makes the whole entry carry a delivered text
+        assert_eq!(
+            entry.translated_text.as_deref(),
+            Some("Hello there how are you")
+        );
+        assert_eq!((entry.word_count, entry.char_count), (4, 22));
+        assert_eq!(entry.words_per_minute, Some(80.0));
+        assert_eq!(entry.latency_ms, Some(400));
+        assert!(entry.was_modified);
+        assert!(entry.translated && entry.translation_forced);
+        assert_eq!(entry.target_language.as_deref(), Some("English"));
+    }
+
+    #[test]
+    fn test_alternate_transcript_replaces_same_provider() {
+        let alternate = |provider: &str, text: &str| AlternateTranscript {
+            provider: provider.to_string(),
+            text: text.to_string(),
+        };
+        let mut entry = HistoryEntry::from_new(1, new_entry("hello", None));
+        entry.set_alternate_transcript(alternate("openai", "Hello."));
+        entry.set_alternate_transcript(alternate("groq", "hello"));
+        entry.set_alternate_transcript(alternate("openai", "Hello!"));
+        assert_eq!(
+            entry.alternate_transcripts,
+            vec![alternate("openai", "Hello!"), alternate("groq", "hello")]
+        );
+    }
+
+    #[test]
+    fn test_context_window_takes_recent_final_texts_oldest_first() {
+        let now = Utc::now();
+        let mut history: Vec<_> = (1..=4).map(|id| entry_at(id, now)).collect();
+        history[3].translated_text = Some("translated 4".to_string());
+
+        assert_eq!(
+            context_window(&history, 2, 1000),
+            vec!["entry 3".to_string(), "translated 4".to_string()]
+        );
+        assert!(context_window(&history, 0, 1000).is_empty());
+    }
+
+    #[test]
+    fn test_context_window_respects_char_budget() {
+        let now = Utc::now();
+        let history: Vec<_> = (1..=4).map(|id| entry_at(id, now)).collect();
+
+        // Each "entry N" is 7 chars; only the two newest fit in 15
+        assert_eq!(
+            context_window(&history, 4, 15),
+            vec!["entry 3".to_string(), "entry 4".to_string()]
+        );
+    }
+}
@@ -0,0 +1,265 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone};
+use serde::Serialize;
+
+use crate::text_utils::count_words;
+
+use super::state::HistoryEntry;
+
+/// Number of days covered by the per-day breakdown
+const DAILY_BUCKETS: i64 = 30;
+
+/// Aggregated dictation statistics, shaped for charting in the UI
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct DictationStats {
+    pub total_recordings: usize,
+    pub total_words: usize,
+    pub words_today: usize,
+    pub words_this_week: usize,
+    pub recordings_today: usize,
+    pub recordings_this_week: usize,
+    pub most_used_provider: Option<String>,
+    /// Mean recording-to-delivery time over entries that measured it
+    pub average_latency_ms: Option<u64>,
+    pub providers: Vec<ProviderStats>,
+    /// One bucket per day for the last 30 days, oldest first (days without entries included)
+    pub daily: Vec<DailyStats>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ProviderStats {
+    pub provider: String,
+    pub recordings: usize,
+    pub words: usize,
+    /// Mean latency of this provider's entries that measured it
+    pub average_latency_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct DailyStats {
+    pub date: NaiveDate,
+    pub recordings: usize,
+    pub words: usize,
+}
+
+/// Running mean of the latencies that were measured
+#[derive(Debug, Default)]
+struct LatencyTotal {
+    sum_ms: u64,
+    count: u64,
+}
+
+impl LatencyTotal {
+    fn add(&mut self, latency_ms: Option<u64>) {
+        if let Some(latency_ms) = latency_ms {
+            self.sum_ms += latency_ms;
+            self.count += 1;
+        }
+    }
+
+    fn average(&self) -> Option<u64> {
+        (self.count > 0).then(|| self.sum_ms / self.count)
+    }
+}
+
+/// Aggregate statistics over history entries.
+/// Day boundaries are computed in the timezone of `now`.
+pub fn compute_stats<Tz: TimeZone>(entries: &[HistoryEntry], now: &DateTime<Tz>) -> DictationStats {
+    let tz = now.timezone();
+    let today = now.date_naive();
+    let week_start = today - Duration::days(i64::from(today.weekday().num_days_from_monday()));
+    let first_bucket = today - Duration::days(DAILY_BUCKETS - 1);
+
+    let mut stats = DictationStats::default();
+    let mut providers: HashMap<String, ProviderStats> = HashMap::new();
+    let mut latency = LatencyTotal::default();
+    let mut provider_latency: HashMap<String, LatencyTotal> = HashMap::new();
+    let mut daily: BTreeMap<NaiveDate, DailyStats> = (0..DAILY_BUCKETS)
+        .map(|offset| {
+            let date = first_bucket + Duration::days(offset);
+            (
+                date,
+                DailyStats {
+                    date,
+                    recordings: 0,
+                    words: 0,
+                },
+            )
+        })
+        .collect();
+
+    for entry in entries {
+        let words = count_words(&entry.original_text);
+        let day = entry.timestamp.with_timezone(&tz).date_naive();
+
+        stats.total_recordings += 1;
+        stats.total_words += words;
+        latency.add(entry.latency_ms);
+
+        if day == today {
+            stats.recordings_today += 1;
+            stats.words_today += words;
+        }
+        if day >= week_start && day <= today {
+            stats.recordings_this_week += 1;
+            stats.words_this_week += words;
+        }
+
+        if let Some(bucket) = daily.get_mut(&day) {
+            bucket.recordings += 1;
+            bucket.words += words;
+        }
+
+        let provider = entry
+            .transcription_provider
+            .clone()
+            .unwrap_or_else(|| "unknown".to_string());
+        provider_latency
+            .entry(provider.clone())
+            .or_default()
+            .add(entry.latency_ms);
+        let provider_stats = providers
+            .entry(provider.clone())
+            .or_insert_with(|| ProviderStats {
+                provider,
+                recordings: 0,
+                words: 0,
+                average_latency_ms: None,
+            });
+        provider_stats.recordings += 1;
+        provider_stats.words += words;
+    }
+
+    let mut providers: Vec<ProviderStats> = providers
+        .into_values()
+        .map(|mut p| {
+            p.average_latency_ms = provider_latency[&p.provider].average();
+            p
+        })
+        .collect();
+    // Most used first; ties broken by name for stable output
+    providers.sort_by(|a, b| {
+        b.recordings
+            .cmp(&a.recordings)
+            .then_with(|| a.provider.cmp(&b.provider))
+    });
+
+    stats.most_used_provider = providers.first().map(|p| p.provider.clone());
+    stats.average_latency_ms = latency.average();
+    stats.providers = providers;
+    stats.daily = daily.into_values().collect();
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn entry(text: &str, provider: &str, timestamp: DateTime<Utc>) -> HistoryEntry {
+        HistoryEntry {
+            id: 0,
+            timestamp,
+            original_text: text.to_string(),
+            translated_text: None,
+            source_language: None,
+            target_language: None,
+            translated: false,
+            translation_forced: false,
+            transcription_provider: Some(provider.to_string()),
+            llm_provider: None,
+            custom_instructions_used: false,
+            refinement_failed: false,
+            combined_translation: false,
+            was_modified: false,
+            change_count: 0,
+            latency_ms: None,
+            word_count: 0,
+            char_count: 0,
+            words_per_minute: None,
+            recording_path: None,
+            segments: Vec::new(),
+            alternate_transcripts: Vec::new(),
+            capture: None,
+        }
+    }
+
+    #[test]
+    fn test_count_words_unicode() {
+        assert_eq!(count_words("Привет, как дела?"), 3);
+        assert_eq!(count_words("hello   world\n"), 2);
+        assert_eq!(count_words("don't stop"), 2);
+        assert_eq!(count_words("  ...  "), 0);
+    }
+
+    #[test]
+    fn test_compute_stats_empty() {
+        let now = Utc.with_ymd_and_hms(2025, 6, 11, 12, 0, 0).unwrap();
+        let stats = compute_stats(&[], &now);
+        assert_eq!(stats.total_recordings, 0);
+        assert_eq!(stats.most_used_provider, None);
+        assert_eq!(stats.average_latency_ms, None);
+        assert_eq!(stats.daily.len(), 30);
+        assert_eq!(stats.daily.last().unwrap().date, now.date_naive());
+    }
+
+    #[test]
+    fn test_compute_stats_buckets_and_providers() {
+        // Wednesday
+        let now = Utc.with_ymd_and_hms(2025, 6, 11, 12, 0, 0).unwrap();
+        let entries = vec![
+            entry("один два три", "elevenlabs", now),
+            entry("one two", "openai", now - Duration::days(1)),
+            entry("four five six seven", "elevenlabs", now - Duration::days(5)),
+            entry("ancient history", "groq", now - Duration::days(60)),
+        ];
+
+        let stats = compute_stats(&entries, &now);
+        assert_eq!(stats.total_recordings, 4);
+        assert_eq!(stats.total_words, 11);
+        assert_eq!(stats.recordings_today, 1);
+        assert_eq!(stats.words_today, 3);
+        // Week starts on Monday 2025-06-09
+        assert_eq!(stats.recordings_this_week, 2);
+        assert_eq!(stats.words_this_week, 5);
+
+        assert_eq!(stats.most_used_provider.as_deref(), Some("elevenlabs"));
+        assert_eq!(stats.providers[0].recordings, 2);
+        assert_eq!(stats.providers[0].words, 7);
+
+        let in_buckets: usize = stats.daily.iter().map(|d| d.recordings).sum();
+        assert_eq!(in_buckets, 3);
+    }
+
+    #[test]
+    fn test_compute_stats_latency() {
+        let now = Utc.with_ymd_and_hms(2025, 6, 11, 12, 0, 0).unwrap();
+        let timed = |provider, latency_ms| HistoryEntry {
+            latency_ms: Some(latency_ms),
+            ..entry("text", provider, now)
+        };
+        let entries = vec![
+            timed("elevenlabs", 1000),
+            timed("elevenlabs", 2000),
+            timed("groq", 600),
+            // Recorded before latency was measured; left out of the averages
+            entry("text", "elevenlabs", now),
+            entry("text", "openai", now),
+        ];
+
+        let stats = compute_stats(&entries, &now);
+        assert_eq!(stats.average_latency_ms, Some(1200));
+        let latency = |name: &str| {
+            stats
+                .providers
+                .iter()
+                .find(|p| p.provider == name)
+                .unwrap()
+                .average_latency_ms
+        };
+        assert_eq!(latency("elevenlabs"), Some(1500));
+        assert_eq!(latency("groq"), Some(600));
+        assert_eq!(latency("openai"), None);
+    }
+}
@@ -0,0 +1,88 @@
+use std::{
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
+
+use tauri::{AppHandle, Manager, State};
+
+use crate::messages::{self, Msg};
+
+use super::{
+    events::{emit_error_code, emit_status, ErrorCode, StatusPhase},
+    state::AppState,
+};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Remember when the status moved to Transcribing; any other phase ends it.
+/// Repeated Transcribing updates (e.g. "Applying LLM...") keep the original start.
+pub fn track_phase(state: &AppState, phase: StatusPhase) {
+    if let Ok(mut since) = state.transcribing_since().lock() {
+        match phase {
+            // Typing a long transcript can hang just like the request before it
+            StatusPhase::Transcribing | StatusPhase::Delivering => {
+                since.get_or_insert_with(Instant::now);
+            }
+            _ => *since = None,
+        }
+    }
+}
+
+fn is_stuck(since: Instant, now: Instant, limit: Option<Duration>) -> bool {
+    limit.is_some_and(|limit| now.saturating_duration_since(since) >= limit)
+}
+
+/// Periodically reset a "Transcribing" status that neither completed nor failed
+/// within `AppSettings::transcribing_timeout`, so a lost event or a panicked task
+/// can't leave the app looking hung
+pub fn spawn_status_watchdog(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+
+            let state: State<'_, AppState> = app.state();
+            let limit = state.current_settings().await.transcribing_timeout();
+            let Some(since) = state.transcribing_since().lock().ok().and_then(|g| *g) else {
+                continue;
+            };
+            if !is_stuck(since, Instant::now(), limit) {
+                continue;
+            }
+
+            tracing::warn!(
+                "[Watchdog] Transcribing for {}s without a result; resetting",
+                since.elapsed().as_secs()
+            );
+            state.is_transcribing().store(false, Ordering::SeqCst);
+            if let Ok(mut guard) = state.pending_commits().lock() {
+                guard.clear();
+            }
+            emit_error_code(
+                &app,
+                ErrorCode::TranscriptionTimeout,
+                None,
+                messages::text(Msg::TranscriptionTimedOut),
+            );
+            emit_status(
+                &app,
+                StatusPhase::Idle,
+                Some(messages::text(Msg::ReadyForNext)),
+            );
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_stuck() {
+        let since = Instant::now();
+        let limit = Some(Duration::from_secs(120));
+        assert!(!is_stuck(since, since + Duration::from_secs(119), limit));
+        assert!(is_stuck(since, since + Duration::from_secs(120), limit));
+        assert!(!is_stuck(since, since + Duration::from_secs(3600), None));
+    }
+}
@@ -0,0 +1,83 @@
+use std::{sync::atomic::Ordering, time::Duration};
+
+use tauri::{AppHandle, Manager, State};
+
+use crate::messages::{self, Msg};
+
+use super::{
+    commands::disconnect_streaming,
+    events::{emit_status, StatusPhase},
+    session_manager::{self, HotkeyEventKind},
+    state::AppState,
+};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+fn is_idle(idle_for: Duration, limit: Option<Duration>) -> bool {
+    limit.is_some_and(|limit| idle_for >= limit)
+}
+
+/// Idle time of the streaming connection under the current settings, if it is
+/// due to be dropped
+async fn idle_past_limit(state: &AppState) -> Option<Duration> {
+    let limit = state.current_settings().await.streaming_idle_disconnect();
+    let idle_for = state.elevenlabs_streaming().idle_for().await?;
+    is_idle(idle_for, limit).then_some(idle_for)
+}
+
+/// Periodically check for a streaming connection whose gate stayed closed for
+/// `AppSettings::streaming_idle_disconnect`. The disconnect itself goes through the
+/// session manager, so it can't interleave with a hotkey press.
+pub fn spawn_idle_disconnect(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+
+            let state: State<'_, AppState> = app.state();
+            if idle_past_limit(&state).await.is_some() {
+                session_manager::send(&app, HotkeyEventKind::StreamingIdle);
+            }
+        }
+    });
+}
+
+/// Close an idle streaming connection. `last_config` is kept, so the next press
+/// reconnects as usual.
+pub async fn disconnect_if_idle(app: &AppHandle) {
+    let state: State<'_, AppState> = app.state();
+    // Re-checked: a dictation may have come and gone while the event was queued
+    if state.is_transcribing().load(Ordering::SeqCst) {
+        return;
+    }
+    let Some(idle_for) = idle_past_limit(&state).await else {
+        return;
+    };
+
+    tracing::info!(
+        "[Streaming] No dictation for {}s; disconnecting until the next press",
+        idle_for.as_secs()
+    );
+    if let Err(e) = disconnect_streaming(&state).await {
+        tracing::warn!("[Streaming] Idle disconnect failed: {}", e);
+        return;
+    }
+    emit_status(
+        app,
+        StatusPhase::Idle,
+        Some(messages::text(Msg::StreamingIdle)),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_idle() {
+        let limit = Some(Duration::from_secs(300));
+        assert!(!is_idle(Duration::from_secs(299), limit));
+        assert!(is_idle(Duration::from_secs(300), limit));
+        assert!(!is_idle(Duration::from_secs(86_400), None));
+    }
+}
@@ -0,0 +1,367 @@
+use std::{
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+
+use crate::{
+    redact,
+    settings::{AppSettings, ENV_KEY_PREFIX},
+    subprocess,
+};
+
+use super::{diagnostics, state::AppState};
+
+/// File names of bundles start with this, so `reveal` only opens bundles
+const BUNDLE_PREFIX: &str = "easy-dictate-support-";
+
+/// Only the end of the log goes in; that's where the reported problem is
+const MAX_LOG_BYTES: u64 = 2 * 1024 * 1024;
+
+/// Newest history entries included when the user opts in
+const HISTORY_ENTRIES: usize = 50;
+
+/// A component the bundle was made without, and why
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct Omission {
+    pub component: String,
+    pub reason: String,
+}
+
+/// `manifest.json` of a bundle
+#[derive(Debug, Clone, Serialize)]
+struct Manifest {
+    app_version: String,
+    os: &'static str,
+    arch: &'static str,
+    created_at: DateTime<Utc>,
+    included: Vec<String>,
+    omitted: Vec<Omission>,
+}
+
+/// Files of a bundle; a component that failed is recorded instead of failing it
+#[derive(Debug, Default)]
+struct Contents {
+    files: Vec<(&'static str, String)>,
+    omitted: Vec<Omission>,
+}
+
+impl Contents {
+    fn add(&mut self, name: &'static str, contents: Result<String>) {
+        match contents {
+            // Whatever else ends up in a component, no key leaves the machine
+            Ok(text) => self.files.push((name, redact::redact(&text).into_owned())),
+            Err(e) => {
+                tracing::warn!("[Support] Leaving {} out of the bundle: {:#}", name, e);
+                self.omit(name, format!("{e:#}"));
+            }
+        }
+    }
+
+    fn omit(&mut self, name: &str, reason: impl Into<String>) {
+        self.omitted.push(Omission {
+            component: name.to_string(),
+            reason: reason.into(),
+        });
+    }
+}
+
+/// `key` as the bundle shows it: an `env:` reference names a variable and is kept,
+/// anything else is a secret
+fn mask_key(key: &str) -> String {
+    if key.trim().is_empty() || key.trim().starts_with(ENV_KEY_PREFIX) {
+        key.to_string()
+    } else {
+        redact::MASK.to_string()
+    }
+}
+
+/// The settings as JSON with their API keys and the local API token masked
+fn redacted_settings(settings: &AppSettings) -> Result<String> {
+    let mut settings = settings.clone();
+    for key in [
+        &mut settings.api_key,
+        &mut settings.groq_api_key,
+        &mut settings.elevenlabs_api_key,
+        &mut settings.deepgram_api_key,
+    ] {
+        *key = mask_key(key);
+    }
+    if !settings.local_api_token.is_empty() {
+        settings.local_api_token = redact::MASK.to_string();
+    }
+    serde_json::to_string_pretty(&settings).context("Failed to serialize settings")
+}
+
+/// The last `max_bytes` of the file at `path`, starting at a line when cut
+fn log_tail(path: &Path, max_bytes: u64) -> Result<String> {
+    let mut file =
+        std::fs::File::open(path).with_context(|| format!("Cannot open {}", path.display()))?;
+    let len = file.metadata()?.len();
+    let start = len.saturating_sub(max_bytes);
+    file.seek(SeekFrom::Start(start))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    if start > 0 {
+        let line_start = bytes.iter().position(|&b| b == b'\n').map_or(0, |i| i + 1);
+        bytes.drain(..line_start);
+    }
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Write `contents` and its manifest as a zip at `path`
+fn write_zip(path: &Path, contents: &Contents, manifest: &Manifest) -> Result<()> {
+    let file =
+        std::fs::File::create(path).with_context(|| format!("Cannot create {}", path.display()))?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    let manifest = serde_json::to_string_pretty(manifest)?;
+    let files = contents
+        .files
+        .iter()
+        .map(|(name, text)| (*name, text.as_str()))
+        .chain([("manifest.json", manifest.as_str())]);
+    for (name, text) in files {
+        zip.start_file(name, options)?;
+        zip.write_all(text.as_bytes())?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+fn bundle_path(dir: &Path, now: DateTime<Utc>) -> PathBuf {
+    dir.join(format!(
+        "{BUNDLE_PREFIX}{}.zip",
+        now.format("%Y%m%d-%H%M%S")
+    ))
+}
+
+/// Gather settings (keys masked), the end of the app log, a diagnostics report and,
+/// with `include_history`, the newest history entries into a zip in the temp
+/// directory for a bug report. A part that can't be collected is left out and noted
+/// in `manifest.json`; only failing to write the zip itself is an error.
+pub async fn create(app: &AppHandle, include_history: bool) -> Result<PathBuf> {
+    let state: State<'_, AppState> = app.state();
+    state.wait_for_settings_loaded().await;
+    let mut contents = Contents::default();
+
+    let settings = state.current_settings().await;
+    contents.add("settings.json", redacted_settings(&settings));
+
+    let log = match app.path().app_log_dir() {
+        Ok(dir) => tauri::async_runtime::spawn_blocking(move || {
+            log_tail(&dir.join("logs.log"), MAX_LOG_BYTES)
+        })
+        .await
+        .map_err(|e| anyhow!("Reading the log panicked: {e}"))
+        .and_then(|tail| tail),
+        Err(e) => Err(anyhow!("No log directory: {e}")),
+    };
+    contents.add("logs.log", log);
+
+    let checks = diagnostics::run(app).await;
+    contents.add(
+        "diagnostics.json",
+        serde_json::to_string_pretty(&checks).context("Failed to serialize diagnostics"),
+    );
+
+    if include_history {
+        let mut history = state.get_history().await;
+        history.truncate(HISTORY_ENTRIES);
+        contents.add(
+            "history.json",
+            serde_json::to_string_pretty(&history).context("Failed to serialize history"),
+        );
+    } else {
+        contents.omit("history.json", "Not included by the user");
+    }
+
+    let now = Utc::now();
+    let manifest = Manifest {
+        app_version: app.package_info().version.to_string(),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        created_at: now,
+        included: contents
+            .files
+            .iter()
+            .map(|(name, _)| name.to_string())
+            .collect(),
+        omitted: contents.omitted.clone(),
+    };
+    let path = bundle_path(&std::env::temp_dir(), now);
+    let target = path.clone();
+    tauri::async_runtime::spawn_blocking(move || write_zip(&target, &contents, &manifest))
+        .await
+        .map_err(|e| anyhow!("Writing the bundle panicked: {e}"))??;
+    tracing::info!("[Support] Bundle written to {}", path.display());
+    Ok(path)
+}
+
+/// Whether `path` names a bundle `create` could have written
+fn is_bundle(path: &Path, temp_dir: &Path) -> bool {
+    let name = path.file_name().and_then(|name| name.to_str());
+    path.parent() == Some(temp_dir)
+        && name.is_some_and(|name| name.starts_with(BUNDLE_PREFIX) && name.ends_with(".zip"))
+}
+
+/// Command selecting `path` in Explorer
+#[cfg(windows)]
+fn file_manager(path: &Path) -> Command {
+    let mut command = subprocess::command("explorer");
+    command.arg(format!("/select,{}", path.display()));
+    command
+}
+
+/// Command selecting `path` in Finder
+#[cfg(target_os = "macos")]
+fn file_manager(path: &Path) -> Command {
+    let mut command = subprocess::command("open");
+    command.arg("-R").arg(path);
+    command
+}
+
+/// Command opening the folder holding `path`; there's no common way to select a file
+#[cfg(not(any(windows, target_os = "macos")))]
+fn file_manager(path: &Path) -> Command {
+    let mut command = subprocess::command("xdg-open");
+    command.arg(path.parent().unwrap_or(path));
+    command
+}
+
+/// Show a bundle from `create` in the system file manager
+pub fn reveal(path: &Path) -> Result<()> {
+    if !is_bundle(path, &std::env::temp_dir()) || !path.is_file() {
+        bail!("Not a support bundle: {}", path.display());
+    }
+    show_in_file_manager(path)
+}
+
+/// Show the app log in the system file manager
+pub fn reveal_log(app: &AppHandle) -> Result<()> {
+    let path = app
+        .path()
+        .app_log_dir()
+        .map_err(|e| anyhow!("No log directory: {e}"))?
+        .join("logs.log");
+    if !path.is_file() {
+        bail!("No log file at {}", path.display());
+    }
+    show_in_file_manager(&path)
+}
+
+fn show_in_file_manager(path: &Path) -> Result<()> {
+    let mut child = file_manager(path)
+        .spawn()
+        .context("Failed to start the file manager")?;
+    // Explorer exits with 1 even when it worked; only reap it
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "easy-dictate-bundle-test-{name}-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_settings_keys_are_masked() {
+        let settings = AppSettings {
+            api_key: "sk-proj-0123456789abcdefghij".to_string(),
+            groq_api_key: "env:GROQ_API_KEY".to_string(),
+            local_api_token: "local-token-value".to_string(),
+            ..AppSettings::default()
+        };
+        let json = redacted_settings(&settings).unwrap();
+        assert!(!json.contains("0123456789abcdefghij"));
+        assert!(!json.contains("local-token-value"));
+        assert!(json.contains("env:GROQ_API_KEY"));
+        assert!(json.contains(redact::MASK));
+    }
+
+    #[test]
+    fn test_log_tail_starts_at_a_line() {
+        let dir = scratch_dir("log");
+        let path = dir.join("logs.log");
+        std::fs::write(&path, "first line\nsecond line\nthird line\n").unwrap();
+
+        assert_eq!(
+            log_tail(&path, 1024).unwrap(),
+            "first line\nsecond line\nthird line\n"
+        );
+        assert_eq!(log_tail(&path, 15).unwrap(), "third line\n");
+        assert!(log_tail(&dir.join("missing.log"), 1024).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_bundle_lists_what_was_left_out() {
+        let dir = scratch_dir("zip");
+        let mut contents = Contents::default();
+        contents.add(
+            "settings.json",
+            Ok("{\"key\": \"gsk_0123456789abcdefABCD\"}".into()),
+        );
+        contents.add("logs.log", Err(anyhow!("No log directory")));
+        let manifest = Manifest {
+            app_version: "1.0.0".to_string(),
+            os: "test",
+            arch: "test",
+            created_at: Utc::now(),
+            included: vec!["settings.json".to_string()],
+            omitted: contents.omitted.clone(),
+        };
+        let path = bundle_path(&dir, manifest.created_at);
+        write_zip(&path, &contents, &manifest).unwrap();
+        assert!(is_bundle(&path, &dir));
+
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+        let mut names: Vec<_> = archive.file_names().map(str::to_string).collect();
+        names.sort();
+        assert_eq!(names, ["manifest.json", "settings.json"]);
+
+        let mut settings = String::new();
+        archive
+            .by_name("settings.json")
+            .unwrap()
+            .read_to_string(&mut settings)
+            .unwrap();
+        assert_eq!(settings, "{\"key\": \"[REDACTED]\"}");
+
+        let mut manifest = String::new();
+        archive
+            .by_name("manifest.json")
+            .unwrap()
+            .read_to_string(&mut manifest)
+            .unwrap();
+        assert!(manifest.contains("\"component\": \"logs.log\""));
+        assert!(manifest.contains("No log directory"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_only_bundles_are_revealed() {
+        let dir = std::env::temp_dir();
+        assert!(is_bundle(&bundle_path(&dir, Utc::now()), &dir));
+        assert!(!is_bundle(&dir.join("easy-dictate-support-x.exe"), &dir));
+        assert!(!is_bundle(&dir.join("other.zip"), &dir));
+        assert!(!is_bundle(
+            Path::new("/elsewhere/easy-dictate-support-x.zip"),
+            &dir
+        ));
+    }
+}
@@ -0,0 +1,193 @@
+use std::{sync::atomic::Ordering, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use super::{dictation_session::SessionInfo, events::ErrorCode, state::AppState};
+
+/// Bumped whenever a field is removed or changes meaning; new fields don't bump it
+pub const SCHEMA: u32 = 2;
+
+/// `wait_for_test_state` gives up after this unless the caller passes a timeout
+pub const DEFAULT_WAIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often `wait_for_test_state` re-checks its condition
+pub const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// What the E2E tests can observe of the backend
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TestState {
+    pub schema: u32,
+    pub audio_thread_panic: Option<String>,
+    /// A legacy (upload) recording is running
+    pub is_recording: bool,
+    pub is_transcribing: bool,
+    pub provider: String,
+    pub has_api_key: bool,
+    pub hotkey: String,
+    // Schema 2
+    pub streaming_connected: bool,
+    pub gate_open: bool,
+    pub committing: bool,
+    /// Audio bytes queued for the ElevenLabs socket
+    pub streaming_queued_bytes: usize,
+    pub audio_thread_running: bool,
+    /// Deliveries waiting for their turn or typing right now
+    pub pending_deliveries: usize,
+    pub queued_hotkey_events: usize,
+    /// Both cleared when a dictation starts
+    pub last_error: Option<ErrorCode>,
+    pub last_transcript: Option<String>,
+    /// The microphone is hard-muted
+    pub mic_muted: bool,
+    /// The streaming capture has the input device open; false while muted and idle
+    pub capture_running: bool,
+    /// The running dictation session; kept across provider switches
+    pub session: SessionInfo,
+}
+
+impl TestState {
+    pub async fn collect(state: &AppState) -> Self {
+        let settings = state.current_settings().await;
+        let streaming = state.elevenlabs_streaming().state().await;
+        let audio_thread_running = state.audio_streaming_running().await;
+        Self {
+            schema: SCHEMA,
+            audio_thread_panic: state
+                .audio_thread_panic()
+                .lock()
+                .ok()
+                .and_then(|guard| guard.as_ref().map(|panic| panic.message.clone())),
+            is_recording: state
+                .active_recording()
+                .lock()
+                .map(|guard| guard.is_some())
+                .unwrap_or(false),
+            is_transcribing: state.is_transcribing().load(Ordering::SeqCst),
+            provider: format!("{:?}", settings.provider),
+            has_api_key: !settings.api_key.is_empty(),
+            hotkey: settings.hotkey.clone(),
+            streaming_connected: streaming.connected,
+            gate_open: streaming.transmitting,
+            committing: streaming.committing,
+            streaming_queued_bytes: streaming.in_flight_bytes,
+            audio_thread_running,
+            pending_deliveries: state.delivery().pending(),
+            queued_hotkey_events: state.queued_hotkey_events(),
+            last_error: state.last_error(),
+            last_transcript: state.last_transcript(),
+            mic_muted: state.mic_mute().is_muted(),
+            capture_running: state.capture_running().load(Ordering::Acquire),
+            session: state.dictation_session().info(),
+        }
+    }
+}
+
+/// Named conditions `wait_for_test_state` can wait for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TestCondition {
+    /// Nothing recorded, queued, transcribed or typed
+    Idle,
+    /// Audio is being captured, by either recording path
+    Recording,
+    Transcribing,
+    StreamingConnected,
+    /// A dictation delivered its text since the last one started
+    Completed,
+    /// A dictation failed since the last one started
+    Failed,
+}
+
+impl TestCondition {
+    pub fn holds(self, state: &TestState) -> bool {
+        match self {
+            Self::Idle => {
+                !state.is_recording
+                    && !state.is_transcribing
+                    && !state.gate_open
+                    && !state.committing
+                    && state.pending_deliveries == 0
+                    && state.queued_hotkey_events == 0
+            }
+            Self::Recording => state.is_recording || state.gate_open,
+            Self::Transcribing => state.is_transcribing,
+            Self::StreamingConnected => state.streaming_connected,
+            Self::Completed => state.last_transcript.is_some(),
+            Self::Failed => state.last_error.is_some(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_idle_requires_empty_queues() {
+        let idle = TestState::default();
+        assert!(TestCondition::Idle.holds(&idle));
+
+        let delivering = TestState {
+            pending_deliveries: 1,
+            ..TestState::default()
+        };
+        assert!(!TestCondition::Idle.holds(&delivering));
+
+        let queued = TestState {
+            queued_hotkey_events: 1,
+            ..TestState::default()
+        };
+        assert!(!TestCondition::Idle.holds(&queued));
+    }
+
+    #[test]
+    fn test_streaming_gate_counts_as_recording() {
+        let streaming = TestState {
+            streaming_connected: true,
+            gate_open: true,
+            ..TestState::default()
+        };
+        assert!(TestCondition::Recording.holds(&streaming));
+        assert!(TestCondition::StreamingConnected.holds(&streaming));
+        assert!(!TestCondition::Idle.holds(&streaming));
+
+        let committing = TestState {
+            streaming_connected: true,
+            committing: true,
+            ..TestState::default()
+        };
+        assert!(!TestCondition::Recording.holds(&committing));
+        assert!(!TestCondition::Idle.holds(&committing));
+    }
+
+    #[test]
+    fn test_completed_and_failed() {
+        let completed = TestState {
+            last_transcript: Some("hello".to_string()),
+            ..TestState::default()
+        };
+        assert!(TestCondition::Completed.holds(&completed));
+        assert!(!TestCondition::Failed.holds(&completed));
+
+        let failed = TestState {
+            last_error: Some(ErrorCode::NoAudio),
+            ..TestState::default()
+        };
+        assert!(TestCondition::Failed.holds(&failed));
+    }
+
+    #[test]
+    fn test_serializes_schema_and_condition_names() {
+        let value = serde_json::to_value(TestState {
+            schema: SCHEMA,
+            ..TestState::default()
+        })
+        .unwrap();
+        assert_eq!(value["schema"], 2);
+        assert_eq!(value["is_recording"], false);
+        assert_eq!(value["last_error"], serde_json::Value::Null);
+
+        let condition: TestCondition = serde_json::from_str(r#""streaming_connected""#).unwrap();
+        assert_eq!(condition, TestCondition::StreamingConnected);
+    }
+}
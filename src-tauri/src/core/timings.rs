@@ -0,0 +1,134 @@
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+/// Wall-clock checkpoints of one dictation, measured from the end of the recording
+/// (recording stop for uploads, gate close for ElevenLabs streaming)
+#[derive(Debug, Clone)]
+pub struct Timings {
+    started: Instant,
+    transcribed: Option<Duration>,
+    refined: Option<Duration>,
+    delivered: Option<Duration>,
+    preconnect_saved: Option<Duration>,
+}
+
+/// Milliseconds after the end of the recording at which each stage finished
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, Default)]
+pub struct TimingsReport {
+    /// Provider returned the transcript
+    pub transcribed_ms: Option<u64>,
+    /// LLM refinement finished (or was skipped)
+    pub refined_ms: Option<u64>,
+    /// Text was typed / copied
+    pub delivered_ms: Option<u64>,
+    pub total_ms: u64,
+    /// Connect time saved by connecting while the hotkey's modifiers were held
+    pub preconnect_saved_ms: Option<u64>,
+}
+
+fn millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+impl Timings {
+    pub fn start() -> Self {
+        Self::starting_at(Instant::now())
+    }
+
+    pub fn starting_at(started: Instant) -> Self {
+        Self {
+            started,
+            transcribed: None,
+            refined: None,
+            delivered: None,
+            preconnect_saved: None,
+        }
+    }
+
+    pub fn mark_transcribed(&mut self) {
+        self.transcribed = Some(self.started.elapsed());
+    }
+
+    pub fn mark_refined(&mut self) {
+        self.refined = Some(self.started.elapsed());
+    }
+
+    pub fn mark_delivered(&mut self) {
+        self.delivered = Some(self.started.elapsed());
+    }
+
+    /// Note the connect time a speculative connect saved the dictation
+    pub fn set_preconnect_saved(&mut self, saved: Option<Duration>) {
+        self.preconnect_saved = saved;
+    }
+
+    /// Total is the delivery time once delivered, otherwise the time elapsed so far
+    pub fn report(&self) -> TimingsReport {
+        TimingsReport {
+            transcribed_ms: self.transcribed.map(millis),
+            refined_ms: self.refined.map(millis),
+            delivered_ms: self.delivered.map(millis),
+            total_ms: millis(self.delivered.unwrap_or_else(|| self.started.elapsed())),
+            preconnect_saved_ms: self.preconnect_saved.map(millis),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_marks_are_monotonic() {
+        let mut timings = Timings::start();
+        timings.mark_transcribed();
+        std::thread::sleep(Duration::from_millis(5));
+        timings.mark_refined();
+        std::thread::sleep(Duration::from_millis(5));
+        timings.mark_delivered();
+
+        let report = timings.report();
+        let transcribed = report.transcribed_ms.unwrap();
+        let refined = report.refined_ms.unwrap();
+        let delivered = report.delivered_ms.unwrap();
+        assert!(transcribed <= refined);
+        assert!(refined <= delivered);
+        assert_eq!(report.total_ms, delivered);
+        assert!(delivered >= 10);
+    }
+
+    #[test]
+    fn test_report_before_delivery() {
+        let started = Instant::now() - Duration::from_millis(1500);
+        let mut timings = Timings::starting_at(started);
+        timings.mark_transcribed();
+
+        let report = timings.report();
+        assert!(report.transcribed_ms.unwrap() >= 1500);
+        assert_eq!(report.refined_ms, None);
+        assert_eq!(report.delivered_ms, None);
+        assert!(report.total_ms >= report.transcribed_ms.unwrap());
+    }
+
+    #[test]
+    fn test_report_serialization() {
+        let report = TimingsReport {
+            transcribed_ms: Some(900),
+            refined_ms: Some(1500),
+            delivered_ms: Some(1800),
+            total_ms: 1800,
+            preconnect_saved_ms: Some(240),
+        };
+        assert_eq!(
+            serde_json::to_value(report).unwrap(),
+            serde_json::json!({
+                "transcribed_ms": 900,
+                "refined_ms": 1500,
+                "delivered_ms": 1800,
+                "total_ms": 1800,
+                "preconnect_saved_ms": 240,
+            })
+        );
+    }
+}
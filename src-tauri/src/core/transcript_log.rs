@@ -0,0 +1,163 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_log::log;
+
+use super::state::AppState;
+
+const LOG_FILE: &str = "transcripts.log";
+
+/// The log is rotated once it grows past this; one rotated file is kept
+const MAX_LOG_BYTES: u64 = 5 * 1024 * 1024;
+
+fn log_dir(app: &AppHandle) -> Option<PathBuf> {
+    match app.path().app_log_dir() {
+        Ok(dir) => Some(dir),
+        Err(e) => {
+            tracing::warn!("[Transcript] Failed to get log directory: {}", e);
+            None
+        }
+    }
+}
+
+fn rotated_path(path: &Path) -> PathBuf {
+    path.with_extension("log.1")
+}
+
+/// Append one `[tag] text` line, first moving a full log aside to `transcripts.log.1`
+fn append_to(path: &Path, max_bytes: u64, tag: &str, text: &str) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    if std::fs::metadata(path).is_ok_and(|meta| meta.len() >= max_bytes) {
+        std::fs::rename(path, rotated_path(path))?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "[{}] {}", tag, text)
+}
+
+/// Delete the log and its rotated copy; missing files are not an error
+fn remove_in(dir: &Path) -> std::io::Result<()> {
+    let path = dir.join(LOG_FILE);
+    for file in [rotated_path(&path), path] {
+        match std::fs::remove_file(&file) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn append(app: &AppHandle, tag: &'static str, text: &str) {
+    let app = app.clone();
+    let text = text.to_string();
+    tauri::async_runtime::spawn(async move {
+        let state: State<'_, AppState> = app.state();
+        if !state.current_settings().await.log_transcripts {
+            return;
+        }
+        let Some(dir) = log_dir(&app) else {
+            return;
+        };
+        let path = dir.join(LOG_FILE);
+        let result = tauri::async_runtime::spawn_blocking(move || {
+            append_to(&path, MAX_LOG_BYTES, tag, &text)
+        })
+        .await;
+        if let Ok(Err(e)) = result {
+            tracing::warn!("[Transcript] Failed to write transcript log: {}", e);
+        }
+    });
+}
+
+/// Log a committed transcript if `log_transcripts` is on
+pub fn log_committed(app: &AppHandle, text: &str) {
+    append(app, "committed", text);
+}
+
+/// Partials arrive several times a second; they are only logged while debugging
+pub fn log_partial(app: &AppHandle, text: &str) {
+    if log::log_enabled!(log::Level::Debug) {
+        append(app, "partial", text);
+    }
+}
+
+/// Delete the transcript log, e.g. when `log_transcripts` is switched off
+pub async fn clear(app: &AppHandle) -> std::io::Result<()> {
+    let Some(dir) = log_dir(app) else {
+        return Ok(());
+    };
+    tauri::async_runtime::spawn_blocking(move || remove_in(&dir))
+        .await
+        .map_err(std::io::Error::other)?
+}
+
+/// Startup cleanup: a log left over from a version that always logged is removed
+/// unless logging is enabled
+pub async fn cleanup_on_startup(app: &AppHandle, log_transcripts: bool) {
+    if log_transcripts {
+        return;
+    }
+    if let Err(e) = clear(app).await {
+        tracing::warn!("[Transcript] Failed to remove transcript log: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "easy-dictate-transcripts-{name}-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_rotates_at_size_limit() {
+        let dir = temp_dir("rotate");
+        let path = dir.join(LOG_FILE);
+
+        append_to(&path, 32, "committed", "first line of text").unwrap();
+        append_to(&path, 32, "committed", "second line of text").unwrap();
+        // Both lines exceed the limit now; the next write starts a fresh file
+        append_to(&path, 32, "committed", "third").unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "[committed] third\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(rotated_path(&path)).unwrap(),
+            "[committed] first line of text\n[committed] second line of text\n"
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_remove_deletes_log_and_rotated_copy() {
+        let dir = temp_dir("remove");
+        let path = dir.join(LOG_FILE);
+        append_to(&path, 1, "committed", "one").unwrap();
+        append_to(&path, 1, "committed", "two").unwrap();
+        assert!(rotated_path(&path).exists());
+
+        remove_in(&dir).unwrap();
+        assert!(!path.exists());
+        assert!(!rotated_path(&path).exists());
+        // Nothing left to delete is fine
+        remove_in(&dir).unwrap();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,21 +1,47 @@
-use std::sync::{atomic::Ordering, Arc};
+use std::{
+    collections::HashMap,
+    sync::{atomic::Ordering, Arc},
+};
 
 use anyhow::{anyhow, Result};
+use serde::Serialize;
 use tauri::{AppHandle, Manager, State};
 use tauri_plugin_clipboard_manager::ClipboardExt as _;
 
 use crate::{
-    elevenlabs::{ElevenLabsClient, ElevenLabsTranscriptionRequest},
+    audio_file, chunking, diff,
+    elevenlabs::ElevenLabsClient,
     groq::GroqClient,
     groq_llm::GroqLLMClient,
     input::KeyboardController,
-    openai::{OpenAiClient, RefinementRequest, TranscriptionRequest},
-    settings::{AppSettings, LLMProvider, TranscriptionProvider},
+    language,
+    messages::{self, Msg},
+    ollama::OllamaClient,
+    openai::{ChunkPosition, OpenAiClient, RefinedText, RefinementRequest, TranscriptionRequest},
+    profanity,
+    settings::{
+        resolve_api_key, AppSettings, LLMProvider, LlmFailurePolicy, SettingsValidationError,
+        TranscriptionProvider,
+    },
+    speech_to_text::{
+        AudioPayload, MockSpeechToText, ProviderError, SpeechToText, TranscribeOptions,
+    },
+    text_utils::TextStats,
 };
 
 use super::{
-    events::{emit_complete, emit_error, emit_partial, emit_status, StatusPhase},
+    client_factory::ClientConfig,
+    delivery::Delivery,
+    dictation_session,
+    events::{
+        emit_complete, emit_error_code, emit_error_from, emit_partial, emit_status,
+        CompletePayload, ErrorCode, StatusPhase,
+    },
+    post_command,
+    session::SessionContext,
+    speak,
     state::{AppState, NewHistoryEntry},
+    timings::Timings,
 };
 
 /// Result of transcription containing both original and processed text
@@ -26,14 +52,179 @@ pub struct TranscriptionResult {
     pub processed: String,
     /// Whether LLM processing was applied
     pub llm_applied: bool,
+    /// Detected language of the original transcript
+    pub source_language: Option<String>,
+    /// Whether the LLM output was cut off at the model's output limit
+    pub truncated: bool,
+    /// LLM refinement failed and the raw transcript was delivered instead
+    pub refinement_failed: bool,
+    /// OpenAI's translations endpoint produced the English text directly;
+    /// there is no separate source-language transcript
+    pub combined_translation: bool,
+}
+
+/// Outcome of the optional LLM post-processing step
+pub struct Refinement {
+    pub text: String,
+    pub llm_applied: bool,
+    pub source_language: Option<String>,
+    pub truncated: bool,
+    pub refinement_failed: bool,
+}
+
+/// Why a refinement preview found nothing for the model to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RefinementSkip {
+    /// No translation, instructions or vocabulary are on (or the sample is empty)
+    NotConfigured,
+    /// The sample is already in the target language and translating is all there is
+    AlreadyInTarget,
+}
+
+/// The refinement a dictation of a sample would get, from `preview_refinement`
+#[derive(Debug, Clone, Serialize)]
+pub struct RefinementPreview {
+    pub llm_provider: LLMProvider,
+    pub model: String,
+    /// Detected language of the sample
+    pub source_language: Option<String>,
+    /// Set when a dictation of the sample would be delivered without refinement
+    pub skipped: Option<RefinementSkip>,
+    pub system_prompt: Option<String>,
+    /// Recent dictations sent along for consistent terminology
+    pub context_prompt: Option<String>,
+    /// The model's answer; only when the call was asked for
+    pub refined: Option<String>,
+    pub truncated: bool,
+}
+
+/// Pause before the single retry of a failed refinement request
+const REFINE_RETRY_DELAY: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Success status message for a delivered dictation, if it needs a warning
+pub fn completion_warning(
+    settings: &AppSettings,
+    truncated: bool,
+    refinement_failed: bool,
+) -> Option<String> {
+    if refinement_failed {
+        let msg = if settings.auto_translate {
+            Msg::DeliveredUntranslated
+        } else {
+            Msg::DeliveredRaw
+        };
+        let provider = settings.llm_provider.display_name();
+        return Some(messages::fill(msg, &[("provider", &provider)]));
+    }
+    truncated.then(|| messages::text(Msg::Truncated).to_string())
+}
+
+/// OpenAI's `/v1/audio/translations` transcribes straight into English, which saves
+/// the chat request when translating to English is the only refinement
+fn uses_combined_translation(settings: &AppSettings) -> bool {
+    settings.provider == TranscriptionProvider::OpenAI
+        && settings.auto_translate
+        && settings
+            .target_language
+            .trim()
+            .eq_ignore_ascii_case("english")
+        && settings.active_instructions().is_none()
+        && settings.effective_vocabulary().is_empty()
+}
+
+/// Typing errors are only logged, except a keyboard emulator that could not be
+/// initialized: that is shown once, since every later dictation would fail the same way
+pub fn report_keyboard_failure(app: &AppHandle, keyboard: &KeyboardController) {
+    if let Some(error) = keyboard.take_unreported_failure() {
+        emit_error_code(app, ErrorCode::TypingFailed, None, &error);
+    }
+}
+
+/// Deterministic, offline clean-up applied right before text is typed, copied or
+/// emitted. History keeps the unfiltered text.
+pub fn prepare_for_delivery(settings: &AppSettings, text: &str) -> String {
+    profanity::filter_profanity(text, settings.profanity_filter, &settings.profanity_words)
+}
+
+/// Copy and/or type `text` as `settings` ask, taking turns with every other delivery,
+/// and remember it for a repeat. Clipboard and typing failures are reported here.
+pub async fn deliver_text(app: &AppHandle, settings: &AppSettings, text: String) -> Delivery {
+    let state: State<'_, AppState> = app.state();
+    let keyboard = state.transcription().keyboard();
+    let delivery = state
+        .delivery()
+        .deliver(
+            keyboard.clone(),
+            text.clone(),
+            settings.simulate_typing,
+            settings.copy_to_clipboard,
+            |text| {
+                if let Err(err) = app.clipboard().write_text(text) {
+                    emit_error_code(
+                        app,
+                        ErrorCode::ClipboardFailed,
+                        None,
+                        &messages::fill(Msg::ClipboardFailed, &[("error", &err)]),
+                    );
+                }
+            },
+        )
+        .await;
+    if !text.is_empty() {
+        state.record_delivery(&text);
+    }
+    delivery.unwrap_or_else(|err| {
+        tracing::error!("[Typing] Failed to simulate typing: {}", err);
+        report_keyboard_failure(app, &keyboard);
+        Delivery::default()
+    })
+}
+
+/// `deliver_text`, unless a dictation session is running: the text is collected by
+/// the session then, and delivered as well only if `session_suppress_delivery` is off.
+/// Also returns how many utterances the session has collected.
+pub async fn deliver_or_collect(
+    app: &AppHandle,
+    settings: &AppSettings,
+    text: String,
+) -> (Delivery, Option<usize>) {
+    let collected = dictation_session::capture(app, &text);
+    if collected.is_some() && settings.session_suppress_delivery {
+        return (Delivery::default(), collected);
+    }
+    (deliver_text(app, settings, text).await, collected)
+}
+
+/// Message for the Success status of a dictation a session collected without
+/// delivering it
+pub fn collected_message(settings: &AppSettings, collected: Option<usize>) -> Option<String> {
+    collected
+        .filter(|_| settings.session_suppress_delivery)
+        .map(|count| messages::fill(Msg::SessionUtteranceAdded, &[("count", &count)]))
+}
+
+/// Progress callback for chunked refinement that reports through status events
+pub fn refinement_progress(app: &AppHandle) -> impl Fn(usize, usize) + Send + Sync + '_ {
+    move |index, total| {
+        emit_status(
+            app,
+            StatusPhase::Transcribing,
+            Some(&messages::fill(
+                Msg::RefiningPart,
+                &[("index", &index), ("total", &total)],
+            )),
+        );
+    }
 }
 
 #[derive(Clone)]
 pub struct TranscriptionService {
     openai: OpenAiClient,
-    groq: GroqClient,
     groq_llm: GroqLLMClient,
-    elevenlabs: ElevenLabsClient,
+    ollama: OllamaClient,
+    /// Batch transcription backend for every `TranscriptionProvider`
+    providers: HashMap<TranscriptionProvider, Arc<dyn SpeechToText>>,
     keyboard: Arc<KeyboardController>,
 }
 
@@ -42,242 +233,913 @@ impl TranscriptionService {
         openai: OpenAiClient,
         groq: GroqClient,
         groq_llm: GroqLLMClient,
+        ollama: OllamaClient,
         elevenlabs: ElevenLabsClient,
         keyboard: Arc<KeyboardController>,
     ) -> Self {
+        let providers: [(TranscriptionProvider, Arc<dyn SpeechToText>); 4] = [
+            (TranscriptionProvider::OpenAI, Arc::new(openai.clone())),
+            (TranscriptionProvider::Groq, Arc::new(groq)),
+            (TranscriptionProvider::ElevenLabs, Arc::new(elevenlabs)),
+            (TranscriptionProvider::Mock, Arc::new(MockSpeechToText)),
+        ];
         Self {
             openai,
-            groq,
             groq_llm,
-            elevenlabs,
+            ollama,
+            providers: HashMap::from(providers),
             keyboard,
         }
     }
 
+    /// Service with freshly built provider clients for the default settings
+    pub fn with_default_clients(keyboard: Arc<KeyboardController>) -> Result<Self> {
+        ClientConfig::from_settings(&AppSettings::default()).build(keyboard)
+    }
+
     pub fn keyboard(&self) -> Arc<KeyboardController> {
         Arc::clone(&self.keyboard)
     }
 
-    /// Apply LLM refinement (translation, custom instructions, vocabulary) to text.
-    /// Reuses existing HTTP clients to avoid creating new ones per call.
-    pub async fn refine(&self, settings: &AppSettings, text: String) -> Result<String> {
-        let refinements_key = match settings.llm_provider {
-            LLMProvider::OpenAI => settings.api_key.trim().to_string(),
-            LLMProvider::Groq => settings.groq_api_key.trim().to_string(),
+    fn provider(&self, provider: &TranscriptionProvider) -> Result<&dyn SpeechToText> {
+        self.providers
+            .get(provider)
+            .map(Arc::as_ref)
+            .ok_or_else(|| anyhow!("{} is not available", provider.display_name()))
+    }
+
+    /// The configured provider and its options; fails if its API key is missing
+    fn resolve(&self, settings: &AppSettings) -> Result<(&dyn SpeechToText, TranscribeOptions)> {
+        let provider = self.provider(&settings.provider)?;
+        let opts = TranscribeOptions::resolve(settings, &settings.provider);
+        if settings.provider.needs_api_key() && opts.api_key.is_empty() {
+            settings.ensure_unlocked()?;
+            // An `env:` reference to an unset variable names the variable instead
+            settings.resolve_provider_key(&settings.provider)?;
+            return Err(
+                ProviderError::MissingApiKey(settings.provider.display_name()).into_anyhow(),
+            );
+        }
+        Ok((provider, opts))
+    }
+
+    /// Raw transcript of `audio_wav` without refinement, for interim text
+    pub async fn transcribe_interim(
+        &self,
+        settings: &AppSettings,
+        audio_wav: Vec<u8>,
+    ) -> Result<String> {
+        let (provider, opts) = self.resolve(settings)?;
+        let transcript = provider
+            .transcribe(AudioPayload { wav: audio_wav }, &opts)
+            .await
+            .map_err(ProviderError::into_anyhow)?;
+        Ok(transcript.text)
+    }
+
+    /// Ask `provider` whether it accepts `api_key` (the Groq key also covers Groq refinement)
+    pub async fn check_api_key(
+        &self,
+        provider: &TranscriptionProvider,
+        api_key: &str,
+    ) -> Result<()> {
+        if api_key.trim().is_empty() {
+            return Err(SettingsValidationError::MissingApiKey(provider.display_name()).into());
+        }
+        self.provider(provider)?.check_key(api_key).await
+    }
+
+    /// Every model id `provider` offers to `api_key`
+    pub async fn list_models(
+        &self,
+        provider: &TranscriptionProvider,
+        api_key: &str,
+    ) -> Result<Vec<String>> {
+        self.provider(provider)?.list_models(api_key).await
+    }
+
+    pub async fn check_ollama(&self, base_url: &str) -> Result<()> {
+        self.ollama.check_server(base_url).await
+    }
+
+    /// Detect the transcript language and run LLM refinement if anything needs it.
+    /// Translation into the language the text is already in is skipped, and long
+    /// transcripts are refined chunk by chunk, reporting `(part, total)` via `on_progress`.
+    /// Failures are retried once, then handled per `settings.llm_failure_policy`.
+    /// `context` holds recent dictations for consistent terminology across utterances.
+    pub async fn apply_refinement(
+        &self,
+        settings: &AppSettings,
+        text: String,
+        context: &[String],
+        on_progress: &(dyn Fn(usize, usize) + Send + Sync),
+    ) -> Result<Refinement> {
+        let detected = language::detect_language(&text);
+        let source_language = language::source_language_hint(detected.as_ref());
+
+        if text.trim().is_empty() || !settings.requires_llm() {
+            return Ok(Refinement {
+                text,
+                llm_applied: false,
+                source_language,
+                truncated: false,
+                refinement_failed: false,
+            });
+        }
+
+        if language::translation_redundant(settings, detected.as_ref()) {
+            tracing::info!(
+                "[Transcription] Transcript already in {}, skipping translation",
+                settings.target_language
+            );
+            return Ok(Refinement {
+                text,
+                llm_applied: false,
+                source_language,
+                truncated: false,
+                refinement_failed: false,
+            });
+        }
+
+        let refined = match Self::refinement_request(settings, source_language.as_deref(), context)
+        {
+            Ok(job) => self.refine_chunks(settings, &text, &job, on_progress).await,
+            Err(err) => Err(err),
         };
 
-        if refinements_key.is_empty() {
-            let provider_name = settings.llm_provider.display_name();
-            return Err(anyhow!(
-                "{} API key is required for translation, custom instructions, or vocabulary correction",
-                provider_name
-            ));
+        match refined {
+            Ok(refined) => Ok(Refinement {
+                text: refined.text,
+                llm_applied: true,
+                source_language,
+                truncated: refined.truncated,
+                refinement_failed: false,
+            }),
+            Err(err) if settings.llm_failure_policy == LlmFailurePolicy::FallbackToRaw => {
+                tracing::warn!(
+                    "[Transcription] LLM refinement failed, delivering raw transcript: {}",
+                    err
+                );
+                Ok(Refinement {
+                    text,
+                    llm_applied: false,
+                    source_language,
+                    truncated: false,
+                    refinement_failed: true,
+                })
+            }
+            Err(err) => Err(err),
         }
+    }
 
-        let custom_instructions = if settings.use_custom_instructions {
-            let trimmed = settings.custom_instructions.trim();
-            if trimmed.is_empty() {
-                None
+    /// Refine `text`, split into chunks when it exceeds the configured budget
+    async fn refine_chunks(
+        &self,
+        settings: &AppSettings,
+        text: &str,
+        job: &RefinementRequest,
+        on_progress: &(dyn Fn(usize, usize) + Send + Sync),
+    ) -> Result<RefinedText> {
+        let chunks = chunking::split_into_chunks(text, settings.llm_chunk_max_chars);
+        let total = chunks.len();
+        tracing::info!(
+            "[Transcription] Refining ~{} tokens in {} chunk(s) with {} context entries",
+            chunking::estimate_tokens(text),
+            total,
+            job.context.len()
+        );
+
+        let mut parts = Vec::with_capacity(total);
+        let mut truncated = false;
+        for (i, chunk) in chunks.into_iter().enumerate() {
+            let chunk_job = if total > 1 {
+                on_progress(i + 1, total);
+                RefinementRequest {
+                    chunk: Some(ChunkPosition {
+                        index: i + 1,
+                        total,
+                    }),
+                    ..job.clone()
+                }
             } else {
-                Some(trimmed.to_string())
+                job.clone()
+            };
+
+            let refined = self
+                .refine_with_retry(settings, chunk.text, &chunk_job)
+                .await?;
+            if refined.truncated {
+                tracing::warn!(
+                    "[Transcription] LLM output truncated (chunk {}/{})",
+                    i + 1,
+                    total
+                );
             }
-        } else {
-            None
-        };
+            truncated |= refined.truncated;
+            parts.push((chunk.separator, refined.text));
+        }
 
-        let vocabulary = if settings.use_vocabulary {
-            settings.custom_vocabulary.clone()
-        } else {
-            Vec::new()
-        };
+        Ok(RefinedText {
+            text: chunking::join_chunks(parts.iter().map(|(sep, text)| (*sep, text.as_str()))),
+            truncated,
+        })
+    }
 
-        let refinement = RefinementRequest {
-            api_key: refinements_key,
-            model: settings.llm_model.clone(),
+    /// `refine` with one retry after a short pause
+    async fn refine_with_retry(
+        &self,
+        settings: &AppSettings,
+        text: String,
+        job: &RefinementRequest,
+    ) -> Result<RefinedText> {
+        match self.refine(settings, text.clone(), job).await {
+            Ok(refined) => Ok(refined),
+            Err(err) => {
+                tracing::warn!("[Transcription] LLM refinement failed, retrying: {}", err);
+                tokio::time::sleep(REFINE_RETRY_DELAY).await;
+                self.refine(settings, text, job).await
+            }
+        }
+    }
+
+    /// The refinement job for `settings` (translation, the active preset's instructions
+    /// and vocabulary, model, temperature) without its API key. Every refinement is
+    /// built here, `preview_refinement` included. `context` holds recent dictations that
+    /// are shown to the model but not rewritten.
+    pub fn refinement_job(
+        settings: &AppSettings,
+        source_language: Option<&str>,
+        context: &[String],
+    ) -> RefinementRequest {
+        RefinementRequest {
+            api_key: String::new(),
+            model: settings.effective_llm_model(),
             auto_translate: settings.auto_translate,
             target_language: settings.target_language.clone(),
-            custom_instructions,
-            vocabulary,
+            source_language: source_language.map(str::to_string),
+            custom_instructions: settings.active_instructions().map(str::to_string),
+            vocabulary: settings.effective_vocabulary().to_vec(),
+            temperature: settings.llm_temperature,
+            chunk: None,
+            context: context.to_vec(),
+        }
+    }
+
+    /// `refinement_job` with the key of the LLM provider; fails if it needs one and has none
+    pub fn refinement_request(
+        settings: &AppSettings,
+        source_language: Option<&str>,
+        context: &[String],
+    ) -> Result<RefinementRequest> {
+        let refinements_key = match settings.llm_provider {
+            LLMProvider::OpenAI => resolve_api_key(&settings.api_key)?,
+            LLMProvider::Groq => resolve_api_key(&settings.groq_api_key)?,
+            // Local server, reachable through its base URL without a key
+            LLMProvider::Ollama => String::new(),
         };
 
-        match settings.llm_provider {
-            LLMProvider::OpenAI => self.openai.refine_transcript(text, &refinement).await,
-            LLMProvider::Groq => self.groq_llm.refine_transcript(text, &refinement).await,
+        if refinements_key.is_empty() && settings.llm_provider != LLMProvider::Ollama {
+            settings.ensure_unlocked()?;
+            let provider_name = settings.llm_provider.display_name();
+            return Err(SettingsValidationError::MissingRefinementKey(provider_name).into());
         }
+
+        Ok(RefinementRequest {
+            api_key: refinements_key,
+            ..Self::refinement_job(settings, source_language, context)
+        })
     }
 
-    pub async fn perform(
+    /// What `apply_refinement` would do with `sample`, without recording anything: the
+    /// prompts it would send and, with `execute`, the model's answer
+    pub async fn preview_refinement(
         &self,
         settings: &AppSettings,
-        audio_wav: Vec<u8>,
-    ) -> Result<TranscriptionResult> {
-        // Handle Mock provider for E2E testing
-        if settings.provider.is_mock() {
-            tracing::info!("[Transcription] Using Mock provider for testing");
-            // Simulate processing delay
-            tokio::time::sleep(std::time::Duration::from_millis(500)).await;
-            let mock_text = "Mock transcription result for E2E testing".to_string();
-            return Ok(TranscriptionResult {
-                original: mock_text.clone(),
-                processed: mock_text,
-                llm_applied: false,
-            });
-        }
+        sample: &str,
+        context: &[String],
+        execute: bool,
+    ) -> Result<RefinementPreview> {
+        let detected = language::detect_language(sample);
+        let source_language = language::source_language_hint(detected.as_ref());
+        let skipped = if sample.trim().is_empty() || !settings.requires_llm() {
+            Some(RefinementSkip::NotConfigured)
+        } else if language::translation_redundant(settings, detected.as_ref()) {
+            Some(RefinementSkip::AlreadyInTarget)
+        } else {
+            None
+        };
 
-        let transcription_api_key = match settings.provider {
-            TranscriptionProvider::OpenAI => settings.api_key.trim().to_string(),
-            TranscriptionProvider::Groq => settings.groq_api_key.trim().to_string(),
-            TranscriptionProvider::ElevenLabs => settings.elevenlabs_api_key.trim().to_string(),
-            TranscriptionProvider::Mock => String::new(), // Already handled above
+        let job = Self::refinement_job(settings, source_language.as_deref(), context);
+        let mut preview = RefinementPreview {
+            llm_provider: settings.llm_provider.clone(),
+            model: job.model.clone(),
+            source_language,
+            skipped,
+            system_prompt: None,
+            context_prompt: None,
+            refined: None,
+            truncated: false,
         };
+        if skipped.is_some() {
+            return Ok(preview);
+        }
+        preview.system_prompt = job.system_prompt();
+        preview.context_prompt = job.context_prompt();
 
-        if transcription_api_key.is_empty() {
-            let provider_name = settings.provider.display_name();
-            return Err(anyhow!(
-                "{} API key is required before starting a transcription",
-                provider_name
-            ));
+        if execute {
+            let job =
+                Self::refinement_request(settings, preview.source_language.as_deref(), context)?;
+            let refined = self.refine(settings, sample.to_string(), &job).await?;
+            preview.refined = Some(refined.text);
+            preview.truncated = refined.truncated;
         }
+        Ok(preview)
+    }
 
-        let original_text = match settings.provider {
-            TranscriptionProvider::OpenAI | TranscriptionProvider::Groq => {
-                let request = TranscriptionRequest {
-                    api_key: transcription_api_key,
-                    model: settings.model.clone(),
-                    audio_wav,
-                };
+    /// Send a refinement job to the configured LLM provider.
+    /// Reuses existing HTTP clients to avoid creating new ones per call.
+    pub async fn refine(
+        &self,
+        settings: &AppSettings,
+        text: String,
+        refinement: &RefinementRequest,
+    ) -> Result<RefinedText> {
+        match settings.llm_provider {
+            LLMProvider::OpenAI => self.openai.refine_transcript(text, refinement).await,
+            LLMProvider::Groq => self.groq_llm.refine_transcript(text, refinement).await,
+            LLMProvider::Ollama => {
+                self.ollama
+                    .refine_transcript(text, &settings.ollama_base_url, refinement)
+                    .await
+            }
+        }
+    }
 
-                match settings.provider {
-                    TranscriptionProvider::OpenAI => self.openai.transcribe(request).await?,
-                    TranscriptionProvider::Groq => self.groq.transcribe(request).await?,
-                    _ => unreachable!(),
+    pub async fn perform(
+        &self,
+        settings: &AppSettings,
+        audio_wav: Vec<u8>,
+        context: &[String],
+        timings: &mut Timings,
+        on_progress: &(dyn Fn(usize, usize) + Send + Sync),
+    ) -> Result<TranscriptionResult> {
+        let (provider, opts) = self.resolve(settings)?;
+
+        if uses_combined_translation(settings) {
+            let request = TranscriptionRequest {
+                api_key: opts.api_key.clone(),
+                model: opts.model.clone(),
+                audio_wav: audio_wav.clone(),
+                ..TranscriptionRequest::default()
+            };
+            match self.openai.translate_to_english(request).await {
+                Ok(text) => {
+                    tracing::info!("[Transcription] Translated to English in one request");
+                    timings.mark_transcribed();
+                    timings.mark_refined();
+                    return Ok(TranscriptionResult {
+                        original: text.clone(),
+                        processed: text,
+                        llm_applied: false,
+                        source_language: None,
+                        truncated: false,
+                        refinement_failed: false,
+                        combined_translation: true,
+                    });
                 }
+                Err(e) => tracing::warn!(
+                    "[Transcription] Translations endpoint failed, falling back to transcription + refinement: {}",
+                    e
+                ),
             }
-            TranscriptionProvider::ElevenLabs => {
-                let el_request = ElevenLabsTranscriptionRequest {
-                    api_key: transcription_api_key,
-                    audio_wav,
-                    language: String::new(),
-                };
-                self.elevenlabs.transcribe(el_request).await?
-            }
-            TranscriptionProvider::Mock => {
-                // Should never reach here - Mock is handled above
-                unreachable!("Mock provider should be handled earlier")
-            }
-        };
+        }
 
-        let mut processed_text = original_text.clone();
-        let mut llm_applied = false;
+        let original_text = provider
+            .transcribe(AudioPayload { wav: audio_wav }, &opts)
+            .await
+            .map_err(ProviderError::into_anyhow)?
+            .text;
+        timings.mark_transcribed();
 
-        if !original_text.trim().is_empty() && settings.requires_llm() {
-            processed_text = self.refine(settings, original_text.clone()).await?;
-            llm_applied = true;
+        // E2E tests expect the fixed mock text back untouched
+        if settings.provider.is_mock() {
+            timings.mark_refined();
+            return Ok(TranscriptionResult {
+                original: original_text.clone(),
+                processed: original_text,
+                llm_applied: false,
+                source_language: None,
+                truncated: false,
+                refinement_failed: false,
+                combined_translation: false,
+            });
         }
 
+        let refinement = self
+            .apply_refinement(settings, original_text.clone(), context, on_progress)
+            .await?;
+        timings.mark_refined();
+
         Ok(TranscriptionResult {
             original: original_text,
-            processed: processed_text,
-            llm_applied,
+            processed: refinement.text,
+            llm_applied: refinement.llm_applied,
+            source_language: refinement.source_language,
+            truncated: refinement.truncated,
+            refinement_failed: refinement.refinement_failed,
+            combined_translation: false,
         })
     }
 }
 
-pub fn spawn_transcription(app: &AppHandle, audio_wav: Vec<u8>) {
+pub fn spawn_transcription(app: &AppHandle, audio_wav: Vec<u8>, session: SessionContext) {
+    // Called right after the recording stopped
+    let timings = Timings::start();
     let app_handle = app.clone();
     tauri::async_runtime::spawn(async move {
         let state: State<'_, AppState> = app_handle.state();
         let mut settings = (*state.current_settings().await).clone();
+        session.apply(&mut settings);
+        // Offered for a retry if this fails
+        state.keep_for_retry(&audio_wav, &session);
 
-        // Check if force_translate was requested for the current session
-        let session_id = state.current_session_id();
-        if state.take_force_translate(session_id) {
-            settings.auto_translate = true;
-            tracing::info!(
-                "[Transcription] Force translate enabled for session {}",
-                session_id
-            );
-        }
+        let _ = transcribe_and_deliver(&app_handle, &settings, session, audio_wav, timings).await;
+    });
+}
 
-        let service = state.transcription();
-        let keyboard = service.keyboard();
+/// Transcribe, refine, deliver and record `audio_wav`, reporting progress and errors
+/// through status events. The caller has set `is_transcribing`; this clears it and
+/// ends in Idle. `settings` have `session` applied. Returns the delivered text.
+pub async fn transcribe_and_deliver(
+    app_handle: &AppHandle,
+    settings: &AppSettings,
+    session: SessionContext,
+    audio_wav: Vec<u8>,
+    mut timings: Timings,
+) -> Result<String> {
+    let state: State<'_, AppState> = app_handle.state();
+    let service = state.transcription();
+    let recorded = audio_file::wav_duration(&audio_wav);
 
-        let outcome = service.perform(&settings, audio_wav).await;
+    let context = state
+        .refinement_context(settings.refinement_context_entries)
+        .await;
+    let outcome = service
+        .perform(
+            settings,
+            audio_wav,
+            &context,
+            &mut timings,
+            &refinement_progress(app_handle),
+        )
+        .await;
 
-        match outcome {
-            Ok(result) => {
-                let trimmed = result.processed.trim().to_string();
-                let original_trimmed = result.original.trim().to_string();
+    let delivered = match outcome {
+        Ok(result) => {
+            state.clear_retry_audio();
+            let trimmed = result.processed.trim().to_string();
+            let original_trimmed = result.original.trim().to_string();
+            let delivered = prepare_for_delivery(settings, &trimmed);
+            let text_stats = TextStats::measure(&delivered, recorded);
+            let translation =
+                session.translation(settings, result.llm_applied || result.combined_translation);
 
-                if settings.use_streaming && !trimmed.is_empty() {
-                    emit_partial(&app_handle, &trimmed);
-                }
+            if settings.use_streaming && !delivered.is_empty() {
+                emit_partial(app_handle, &delivered);
+            }
 
-                if settings.copy_to_clipboard {
-                    if let Err(err) = app_handle.clipboard().write_text(trimmed.clone()) {
-                        emit_error(&app_handle, &format!("Failed to copy to clipboard: {err}"));
-                    }
-                }
+            emit_status(app_handle, StatusPhase::Delivering, None);
+            let delivery = deliver_or_collect(app_handle, settings, delivered.clone());
+            // Computed while the text is typed, so the diff doesn't delay delivery
+            let ((delivery, collected), text_diff) = tokio::join!(delivery, async {
+                result
+                    .llm_applied
+                    .then(|| diff::word_diff(&original_trimmed, &trimmed))
+            });
+            timings.mark_delivered();
+            let timings = timings.report();
+            post_command::spawn(
+                app_handle,
+                settings,
+                &delivered,
+                result.source_language.as_deref(),
+            );
+            speak::speak_result(app_handle, settings, &delivered);
 
-                if settings.simulate_typing && !trimmed.is_empty() {
-                    let keyboard_clone = keyboard.clone();
-                    let text_clone = trimmed.clone();
-                    tauri::async_runtime::spawn_blocking(move || {
-                        if let Err(err) = keyboard_clone.type_text(&text_clone) {
-                            tracing::error!("[Typing] Failed to simulate typing: {}", err);
-                        }
-                    });
-                }
+            // Save to history (only non-empty results, unless history is disabled). A
+            // session's utterances become one entry when it ends.
+            if settings.history_enabled && !trimmed.is_empty() && collected.is_none() {
+                // Determine providers used
+                let transcription_provider =
+                    Some(format!("{:?}", settings.provider).to_lowercase());
+                let llm_provider_used = if result.llm_applied {
+                    Some(format!("{:?}", settings.llm_provider).to_lowercase())
+                } else {
+                    None
+                };
 
-                // Save to history (only non-empty results)
-                if !trimmed.is_empty() {
-                    // Determine providers used
-                    let transcription_provider =
-                        Some(format!("{:?}", settings.provider).to_lowercase());
-                    let llm_provider_used = if result.llm_applied {
-                        Some(format!("{:?}", settings.llm_provider).to_lowercase())
+                // Check if custom instructions were used
+                let custom_instructions_used = settings.active_instructions().is_some();
+
+                // If LLM was applied, save original and processed separately
+                let (original_text, translated_text) =
+                    if result.llm_applied && original_trimmed != trimmed {
+                        (original_trimmed, Some(trimmed.clone()))
                     } else {
-                        None
+                        (trimmed.clone(), None)
                     };
 
-                    // Check if custom instructions were used
-                    let custom_instructions_used = settings.use_custom_instructions
-                        && !settings.custom_instructions.trim().is_empty();
-
-                    // If LLM was applied, save original and processed separately
-                    let (original_text, translated_text) =
-                        if result.llm_applied && original_trimmed != trimmed {
-                            (original_trimmed, Some(trimmed.clone()))
-                        } else {
-                            (trimmed.clone(), None)
-                        };
-
-                    state
-                        .add_history_entry(NewHistoryEntry {
-                            original: original_text,
-                            translated: translated_text,
-                            source_language: None, // TODO: detect from transcription
-                            target_language: if settings.auto_translate {
-                                Some(settings.target_language.clone())
-                            } else {
-                                None
-                            },
-                            transcription_provider,
-                            llm_provider: llm_provider_used,
-                            custom_instructions_used,
-                        })
-                        .await;
-                }
-
-                emit_status(&app_handle, StatusPhase::Success, None);
-                emit_complete(&app_handle, &trimmed);
-            }
-            Err(err) => {
-                emit_error(&app_handle, &err.to_string());
+                state
+                    .add_history_entry(NewHistoryEntry {
+                        original: original_text,
+                        translated: translated_text,
+                        source_language: result.source_language.clone(),
+                        translation: translation.clone(),
+                        transcription_provider,
+                        llm_provider: llm_provider_used,
+                        custom_instructions_used,
+                        refinement_failed: result.refinement_failed,
+                        combined_translation: result.combined_translation,
+                        change_count: text_diff.as_ref().map_or(0, |d| d.changes),
+                        latency_ms: Some(timings.total_ms),
+                        stats: text_stats,
+                        // The upload providers answer with plain text, without timings
+                        segments: Vec::new(),
+                        capture: session.capture.clone(),
+                    })
+                    .await;
             }
+
+            let message = if delivery.elevated_target {
+                Some(messages::text(Msg::ElevatedTarget).to_string())
+            } else {
+                completion_warning(settings, result.truncated, result.refinement_failed)
+                    .or_else(|| collected_message(settings, collected))
+            };
+            emit_status(app_handle, StatusPhase::Success, message.as_deref());
+            emit_complete(
+                app_handle,
+                CompletePayload {
+                    text: &delivered,
+                    diff: text_diff.as_ref(),
+                    timings: Some(&timings),
+                    stats: &text_stats,
+                    elevated_target: delivery.elevated_target,
+                    translation: &translation,
+                    capture: session.capture.as_ref(),
+                },
+            );
+            Ok(delivered)
+        }
+        Err(err) => {
+            // Transcription and refinement errors both end up here; the message names the provider
+            emit_error_from(app_handle, &err, None);
+            Err(err)
         }
+    };
 
-        state.is_transcribing().store(false, Ordering::SeqCst);
-        emit_status(&app_handle, StatusPhase::Idle, None);
-    });
+    state.is_transcribing().store(false, Ordering::SeqCst);
+    emit_status(app_handle, StatusPhase::Idle, None);
+    delivered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service() -> TranscriptionService {
+        TranscriptionService::new(
+            OpenAiClient::new().unwrap(),
+            GroqClient::new().unwrap(),
+            GroqLLMClient::new().unwrap(),
+            OllamaClient::new().unwrap(),
+            ElevenLabsClient::new().unwrap(),
+            Arc::new(KeyboardController::new().unwrap()),
+        )
+    }
+
+    /// Ollama pointed at a port nobody listens on, so every refinement fails
+    fn unreachable_llm(policy: LlmFailurePolicy) -> AppSettings {
+        let mut settings = AppSettings::default();
+        settings.llm_provider = LLMProvider::Ollama;
+        settings.ollama_base_url = "http://127.0.0.1:9".to_string();
+        settings.auto_translate = true;
+        settings.target_language = "German".to_string();
+        settings.llm_failure_policy = policy;
+        settings
+    }
+
+    #[tokio::test]
+    async fn test_refinement_failure_falls_back_to_raw() {
+        let settings = unreachable_llm(LlmFailurePolicy::FallbackToRaw);
+        let refinement = service()
+            .apply_refinement(&settings, "hello there".to_string(), &[], &|_, _| {})
+            .await
+            .unwrap();
+
+        assert_eq!(refinement.text, "hello there");
+        assert!(refinement.refinement_failed);
+        assert!(!refinement.llm_applied);
+        assert_eq!(
+            completion_warning(&settings, false, true).as_deref(),
+            Some("Delivered without translation — Ollama refinement failed")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_refinement_failure_propagates_with_fail_policy() {
+        let settings = unreachable_llm(LlmFailurePolicy::Fail);
+        let result = service()
+            .apply_refinement(&settings, "hello there".to_string(), &[], &|_, _| {})
+            .await;
+
+        let err = result.err().expect("refinement should fail");
+        assert!(err.to_string().contains("Could not connect to Ollama"));
+    }
+
+    #[test]
+    fn test_combined_translation_only_for_plain_openai_to_english() {
+        let mut settings = AppSettings::default();
+        settings.provider = TranscriptionProvider::OpenAI;
+        settings.auto_translate = true;
+        settings.target_language = " english ".to_string();
+        assert!(uses_combined_translation(&settings));
+
+        let mut groq = settings.clone();
+        groq.provider = TranscriptionProvider::Groq;
+        assert!(!uses_combined_translation(&groq));
+
+        let mut german = settings.clone();
+        german.target_language = "German".to_string();
+        assert!(!uses_combined_translation(&german));
+
+        let mut vocabulary = settings.clone();
+        vocabulary.use_vocabulary = true;
+        vocabulary.custom_vocabulary = vec!["Tauri".to_string()];
+        assert!(!uses_combined_translation(&vocabulary));
+
+        let mut instructions = settings.clone();
+        instructions.use_custom_instructions = true;
+        instructions.custom_instructions = "Be formal".to_string();
+        assert!(!uses_combined_translation(&instructions.normalized()));
+
+        settings.auto_translate = false;
+        assert!(!uses_combined_translation(&settings));
+    }
+
+    #[test]
+    fn test_refinement_prompt_uses_the_active_preset_vocabulary() {
+        let mut settings = AppSettings {
+            llm_provider: LLMProvider::Ollama,
+            use_vocabulary: true,
+            custom_vocabulary: vec!["Tauri".to_string()],
+            prompt_presets: vec![crate::settings::PromptPreset {
+                name: "Support".to_string(),
+                instructions: String::new(),
+                vocabulary: vec!["Zendesk".to_string()],
+            }],
+            active_preset: "Support".to_string(),
+            ..AppSettings::default()
+        };
+        let job = TranscriptionService::refinement_request(&settings, None, &[]).unwrap();
+        assert_eq!(job.vocabulary, ["Zendesk"]);
+        let prompt = job.system_prompt().unwrap();
+        assert!(prompt.contains("Zendesk") && !prompt.contains("Tauri"));
+
+        settings.prompt_presets[0].vocabulary.clear();
+        let job = TranscriptionService::refinement_request(&settings, None, &[]).unwrap();
+        assert_eq!(job.vocabulary, ["Tauri"]);
+    }
+
+    #[test]
+    fn test_every_provider_is_registered_and_validated() {
+        let service = service();
+        for provider in TranscriptionProvider::ALL {
+            assert!(
+                service.provider(&provider).is_ok(),
+                "{provider:?} not registered"
+            );
+
+            let mut settings = AppSettings::default();
+            settings.provider = provider;
+            assert_eq!(
+                settings.validate_for_transcription().is_err(),
+                provider.needs_api_key(),
+                "{provider:?} without a key"
+            );
+
+            settings.api_key = "sk-test".to_string();
+            settings.groq_api_key = "gsk-test".to_string();
+            settings.elevenlabs_api_key = "el-test".to_string();
+            assert!(
+                settings.validate_for_transcription().is_ok(),
+                "{provider:?} with a key"
+            );
+            assert_eq!(
+                TranscribeOptions::resolve(&settings, &provider)
+                    .api_key
+                    .is_empty(),
+                !provider.needs_api_key()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mock_skips_key_check_and_refinement() {
+        let mut settings = AppSettings::default();
+        settings.provider = TranscriptionProvider::Mock;
+        settings.auto_translate = true;
+        let result = service()
+            .perform(
+                &settings,
+                Vec::new(),
+                &[],
+                &mut Timings::start(),
+                &|_, _| {},
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(result.processed, crate::speech_to_text::MOCK_TRANSCRIPT);
+        assert_eq!(result.original, result.processed);
+        assert!(!result.llm_applied);
+    }
+
+    #[tokio::test]
+    async fn test_missing_key_is_reported_before_any_request() {
+        let mut settings = AppSettings::default();
+        settings.provider = TranscriptionProvider::Groq;
+        let err = service()
+            .perform(
+                &settings,
+                Vec::new(),
+                &[],
+                &mut Timings::start(),
+                &|_, _| {},
+            )
+            .await
+            .err()
+            .expect("transcription should fail");
+        assert_eq!(
+            err.to_string(),
+            "Groq API key is required before starting a transcription"
+        );
+        assert_eq!(ErrorCode::classify(&err), ErrorCode::MissingApiKey);
+    }
+
+    #[tokio::test]
+    async fn test_locked_settings_are_reported_instead_of_a_missing_key() {
+        let mut settings = AppSettings::default();
+        settings.provider = TranscriptionProvider::Groq;
+        settings.keys_locked = true;
+        let err = service()
+            .perform(
+                &settings,
+                Vec::new(),
+                &[],
+                &mut Timings::start(),
+                &|_, _| {},
+            )
+            .await
+            .err()
+            .expect("transcription should fail");
+        assert_eq!(ErrorCode::classify(&err), ErrorCode::SettingsLocked);
+    }
+
+    #[test]
+    fn test_completion_warning() {
+        let settings = AppSettings::default();
+        assert_eq!(completion_warning(&settings, false, false), None);
+        assert_eq!(
+            completion_warning(&settings, true, false).as_deref(),
+            Some(messages::text(Msg::Truncated))
+        );
+        assert_eq!(
+            completion_warning(&settings, true, true).as_deref(),
+            Some("Delivered raw transcript — OpenAI refinement failed")
+        );
+    }
+
+    #[test]
+    fn test_refinement_job_follows_every_setting() {
+        let job = |settings: &AppSettings| {
+            TranscriptionService::refinement_job(settings, Some("Russian"), &[])
+        };
+        assert!(job(&AppSettings::default()).system_prompt().is_none());
+
+        let mut translate = AppSettings::default();
+        translate.auto_translate = true;
+        translate.target_language = "German".to_string();
+        let prompt = job(&translate).system_prompt().unwrap();
+        assert!(prompt.contains("Translate the transcript from Russian into German"));
+        assert!(!prompt.contains("Polish"));
+
+        let mut instructions = AppSettings::default();
+        instructions.use_custom_instructions = true;
+        instructions.custom_instructions = "Use bullet points".to_string();
+        let prompt = job(&instructions.clone().normalized())
+            .system_prompt()
+            .unwrap();
+        assert!(prompt.contains("Polish") && prompt.contains("Use bullet points"));
+        instructions.use_custom_instructions = false;
+        assert!(job(&instructions.normalized()).system_prompt().is_none());
+
+        let mut vocabulary = AppSettings::default();
+        vocabulary.use_vocabulary = true;
+        vocabulary.custom_vocabulary = vec!["Tauri".to_string()];
+        let prompt = job(&vocabulary).system_prompt().unwrap();
+        assert!(prompt.contains("Tauri") && !prompt.contains("Polish"));
+        vocabulary.use_vocabulary = false;
+        assert!(job(&vocabulary).system_prompt().is_none());
+
+        let mut model = translate.clone();
+        model.llm_provider = LLMProvider::Ollama;
+        model.ollama_model = "llama3".to_string();
+        model.llm_temperature = 0.7;
+        let context = ["earlier dictation".to_string()];
+        let ollama = TranscriptionService::refinement_job(&model, None, &context);
+        assert_eq!(ollama.model, "llama3");
+        assert_eq!(ollama.temperature, 0.7);
+        assert!(ollama
+            .context_prompt()
+            .unwrap()
+            .contains("earlier dictation"));
+        assert!(ollama
+            .system_prompt()
+            .unwrap()
+            .contains("Translate the transcript into German"));
+        assert!(job(&translate).context_prompt().is_none());
+    }
+
+    #[test]
+    fn test_refinement_request_needs_a_key_only_for_cloud_providers() {
+        let mut settings = AppSettings::default();
+        settings.auto_translate = true;
+        settings.llm_provider = LLMProvider::OpenAI;
+        assert!(TranscriptionService::refinement_request(&settings, None, &[]).is_err());
+
+        settings.api_key = "sk-test".to_string();
+        let job = TranscriptionService::refinement_request(&settings, None, &[]).unwrap();
+        assert_eq!(job.api_key, "sk-test");
+        assert!(job.auto_translate);
+
+        settings.llm_provider = LLMProvider::Ollama;
+        settings.api_key.clear();
+        assert!(TranscriptionService::refinement_request(&settings, None, &[]).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_preview_shows_the_prompt_without_calling_the_model() {
+        let service = service();
+        let sample = "Please send the quarterly report to the whole team by Friday morning.";
+
+        let preview = service
+            .preview_refinement(&AppSettings::default(), sample, &[], false)
+            .await
+            .unwrap();
+        assert_eq!(preview.skipped, Some(RefinementSkip::NotConfigured));
+        assert!(preview.system_prompt.is_none());
+
+        let mut settings = AppSettings::default();
+        settings.auto_translate = true;
+        settings.target_language = "English".to_string();
+        let preview = service
+            .preview_refinement(&settings, sample, &[], false)
+            .await
+            .unwrap();
+        assert_eq!(preview.source_language.as_deref(), Some("English"));
+        assert_eq!(preview.skipped, Some(RefinementSkip::AlreadyInTarget));
+
+        // No key is needed to look at the prompt
+        settings.target_language = "German".to_string();
+        let context = ["earlier dictation".to_string()];
+        let preview = service
+            .preview_refinement(&settings, sample, &context, false)
+            .await
+            .unwrap();
+        assert_eq!(preview.skipped, None);
+        assert!(preview
+            .system_prompt
+            .unwrap()
+            .contains("Translate the transcript from English into German"));
+        assert!(preview.context_prompt.is_some());
+        assert!(preview.refined.is_none());
+
+        let err = service
+            .preview_refinement(&settings, sample, &[], true)
+            .await
+            .err()
+            .expect("the call needs a key");
+        assert!(err.to_string().contains("API key"));
+    }
+
+    #[tokio::test]
+    async fn test_preview_reports_a_failed_call() {
+        let settings = unreachable_llm(LlmFailurePolicy::FallbackToRaw);
+        let result = service()
+            .preview_refinement(&settings, "hello there", &[], true)
+            .await;
+        let err = result.err().expect("the model is unreachable");
+        assert!(err.to_string().contains("Could not connect to Ollama"));
+    }
 }
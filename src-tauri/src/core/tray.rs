@@ -1,41 +1,281 @@
+use std::time::Duration;
+
 use anyhow::Result;
 use tauri::{
     image::Image,
-    menu::{MenuBuilder, MenuItem, MenuItemBuilder},
-    tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
-    AppHandle, Manager,
+    menu::{
+        CheckMenuItem, CheckMenuItemBuilder, Menu, MenuBuilder, MenuItem, MenuItemBuilder,
+        SubmenuBuilder,
+    },
+    tray::{MouseButton, MouseButtonState, TrayIcon, TrayIconBuilder, TrayIconEvent},
+    AppHandle, Manager, State,
+};
+
+use crate::settings::{AppSettings, TranscriptionProvider};
+
+use super::{
+    commands::{change_mic_mute, change_provider, change_translation, TranslationChange},
+    dictation_session,
+    events::{emit_error, StatusPhase},
+    state::AppState,
 };
 
-pub fn install_tray(app: &AppHandle) -> Result<MenuItem<tauri::Wry>> {
+const TRAY_ID: &str = "main";
+const PRESET_ID_PREFIX: &str = "preset:";
+const LANGUAGE_ID_PREFIX: &str = "language:";
+const AUTO_TRANSLATE_ID: &str = "auto_translate";
+const MIC_MUTE_ID: &str = "mic_mute";
+const SESSION_ID: &str = "dictation_session";
+const PROVIDER_ID_PREFIX: &str = "provider:";
+const SWITCHABLE_PROVIDERS: [TranscriptionProvider; 3] = [
+    TranscriptionProvider::OpenAI,
+    TranscriptionProvider::Groq,
+    TranscriptionProvider::ElevenLabs,
+];
+
+/// How long the icon waits before returning to normal, so quick
+/// Success→Idle→Recording flips don't flicker
+const ICON_SETTLE_DELAY: Duration = Duration::from_millis(400);
+/// Keep the error icon up long enough to be noticed even if Idle follows immediately
+const ICON_ERROR_HOLD: Duration = Duration::from_secs(3);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TrayIconKind {
+    Normal,
+    Recording,
+    Transcribing,
+    Error,
+    /// Idle with the microphone hard-muted
+    Muted,
+}
+
+impl TrayIconKind {
+    fn for_phase(phase: StatusPhase) -> Self {
+        match phase {
+            StatusPhase::Idle | StatusPhase::Paused | StatusPhase::Success => TrayIconKind::Normal,
+            StatusPhase::Recording => TrayIconKind::Recording,
+            StatusPhase::Transcribing | StatusPhase::Delivering => TrayIconKind::Transcribing,
+            StatusPhase::Error => TrayIconKind::Error,
+        }
+    }
+
+    fn bytes(self) -> &'static [u8] {
+        match self {
+            TrayIconKind::Normal => include_bytes!("../../icons/32x32.png"),
+            TrayIconKind::Recording => include_bytes!("../../icons/tray-recording.png"),
+            TrayIconKind::Transcribing => include_bytes!("../../icons/tray-transcribing.png"),
+            TrayIconKind::Error => include_bytes!("../../icons/tray-error.png"),
+            TrayIconKind::Muted => include_bytes!("../../icons/tray-muted.png"),
+        }
+    }
+
+    fn tooltip(self) -> &'static str {
+        match self {
+            TrayIconKind::Normal => "Easy Dictate",
+            TrayIconKind::Recording => "Easy Dictate — Recording…",
+            TrayIconKind::Transcribing => "Easy Dictate — Transcribing…",
+            TrayIconKind::Error => "Easy Dictate — Error",
+            TrayIconKind::Muted => "Easy Dictate — Microphone muted",
+        }
+    }
+}
+
+/// Tray icon handle plus the variant currently shown
+pub struct TrayIconState {
+    icon: TrayIcon<tauri::Wry>,
+    current: TrayIconKind,
+    /// Bumped on every phase change so delayed resets can tell they're stale
+    generation: u64,
+    /// Show `Muted` in place of `Normal`
+    muted: bool,
+}
+
+impl TrayIconState {
+    fn show(&mut self, kind: TrayIconKind) {
+        let kind = match kind {
+            TrayIconKind::Normal if self.muted => TrayIconKind::Muted,
+            kind => kind,
+        };
+        if self.current == kind {
+            return;
+        }
+        if let Ok(image) = Image::from_bytes(kind.bytes()) {
+            if let Err(e) = self.icon.set_icon(Some(image)) {
+                tracing::warn!("[Tray] Failed to update tray icon: {}", e);
+            }
+        }
+        let _ = self.icon.set_tooltip(Some(kind.tooltip()));
+        self.current = kind;
+    }
+}
+
+/// Checkable tray items whose state mirrors settings; kept in `AppState` so any
+/// settings change (hotkey, settings window, tray) can update them in place
+pub struct TrayMenuItems {
+    providers: Vec<(TranscriptionProvider, CheckMenuItem<tauri::Wry>)>,
+    auto_translate: CheckMenuItem<tauri::Wry>,
+    languages: Vec<(String, CheckMenuItem<tauri::Wry>)>,
+    presets: Vec<(String, CheckMenuItem<tauri::Wry>)>,
+    /// Mirrors the hard mute rather than a setting
+    mic_mute: CheckMenuItem<tauri::Wry>,
+    /// Checked while a dictation session runs
+    session: CheckMenuItem<tauri::Wry>,
+}
+
+impl TrayMenuItems {
+    fn sync(&self, settings: &AppSettings) -> tauri::Result<()> {
+        for (provider, item) in &self.providers {
+            item.set_checked(*provider == settings.provider)?;
+        }
+        self.auto_translate.set_checked(settings.auto_translate)?;
+        for (language, item) in &self.languages {
+            item.set_checked(*language == settings.target_language)?;
+        }
+        for (name, item) in &self.presets {
+            item.set_checked(*name == settings.active_preset)?;
+        }
+        Ok(())
+    }
+}
+
+/// Languages offered in the tray submenu: the configured target languages plus the
+/// current target if it isn't in the list
+fn tray_languages(settings: &AppSettings) -> Vec<String> {
+    let mut languages = settings.target_languages.clone();
+    if !languages.contains(&settings.target_language) {
+        languages.push(settings.target_language.clone());
+    }
+    languages
+}
+
+fn build_menu(
+    app: &AppHandle,
+    settings: &AppSettings,
+) -> Result<(Menu<tauri::Wry>, MenuItem<tauri::Wry>, TrayMenuItems)> {
     let open_item = MenuItemBuilder::with_id("open", "Show Settings").build(app)?;
     let status_item = MenuItemBuilder::with_id("status", "Status: Idle")
         .enabled(false)
         .build(app)?;
     let quit_item = MenuItemBuilder::with_id("quit", "Quit").build(app)?;
 
-    let menu = MenuBuilder::new(app)
+    // Only providers with an API key (plus the current one) can be picked from the tray
+    let mut providers = Vec::new();
+    let mut provider_menu = SubmenuBuilder::new(app, "Provider");
+    for provider in SWITCHABLE_PROVIDERS {
+        if provider != settings.provider && !settings.has_provider_key(&provider) {
+            continue;
+        }
+        let item = CheckMenuItemBuilder::with_id(
+            format!("{PROVIDER_ID_PREFIX}{}", provider.id()),
+            provider.display_name(),
+        )
+        .checked(provider == settings.provider)
+        .build(app)?;
+        provider_menu = provider_menu.item(&item);
+        providers.push((provider, item));
+    }
+
+    let auto_translate = CheckMenuItemBuilder::with_id(AUTO_TRANSLATE_ID, "Auto-translate")
+        .checked(settings.auto_translate)
+        .build(app)?;
+
+    let mut languages = Vec::new();
+    let mut language_menu = SubmenuBuilder::new(app, "Target Language");
+    for language in tray_languages(settings) {
+        let item =
+            CheckMenuItemBuilder::with_id(format!("{LANGUAGE_ID_PREFIX}{language}"), &language)
+                .checked(language == settings.target_language)
+                .build(app)?;
+        language_menu = language_menu.item(&item);
+        languages.push((language, item));
+    }
+
+    let muted = app
+        .try_state::<AppState>()
+        .is_some_and(|state| state.mic_mute().is_muted());
+    let mic_mute = CheckMenuItemBuilder::with_id(MIC_MUTE_ID, "Mute Microphone")
+        .checked(muted)
+        .build(app)?;
+
+    let session_active = app
+        .try_state::<AppState>()
+        .is_some_and(|state| state.dictation_session().info().active);
+    let session = CheckMenuItemBuilder::with_id(SESSION_ID, "Dictation Session")
+        .checked(session_active)
+        .build(app)?;
+
+    let mut menu = MenuBuilder::new(app)
         .item(&open_item)
         .separator()
+        .item(&mic_mute)
+        .item(&session)
+        .separator()
+        .item(&provider_menu.build()?)
+        .item(&auto_translate)
+        .item(&language_menu.build()?)
+        .separator();
+
+    let mut presets = Vec::new();
+    if !settings.prompt_presets.is_empty() {
+        let mut preset_menu = SubmenuBuilder::new(app, "Prompt Preset");
+        for preset in &settings.prompt_presets {
+            let item = CheckMenuItemBuilder::with_id(
+                format!("{PRESET_ID_PREFIX}{}", preset.name),
+                &preset.name,
+            )
+            .checked(preset.name == settings.active_preset)
+            .build(app)?;
+            preset_menu = preset_menu.item(&item);
+            presets.push((preset.name.clone(), item));
+        }
+        menu = menu.item(&preset_menu.build()?).separator();
+    }
+
+    let menu = menu
         .item(&status_item)
         .separator()
         .item(&quit_item)
         .build()?;
 
-    let handle = app.clone();
+    let items = TrayMenuItems {
+        providers,
+        auto_translate,
+        languages,
+        presets,
+        mic_mute,
+        session,
+    };
+
+    Ok((menu, status_item, items))
+}
 
-    // Load tray icon from embedded resources
-    let icon_bytes = include_bytes!("../../icons/32x32.png");
+/// Keep the handles of a freshly built menu so later updates reach the visible items
+fn store_menu_handles(app: &AppHandle, status_item: MenuItem<tauri::Wry>, items: TrayMenuItems) {
+    if let Some(state) = app.try_state::<AppState>() {
+        if let Ok(mut guard) = state.tray_status_item().lock() {
+            *guard = Some(status_item);
+        }
+        if let Ok(mut guard) = state.tray_menu_items().lock() {
+            *guard = Some(items);
+        }
+    }
+}
 
-    let mut tray_builder = TrayIconBuilder::with_id("main")
+pub fn install_tray(app: &AppHandle, settings: &AppSettings) -> Result<()> {
+    let (menu, status_item, items) = build_menu(app, settings)?;
+
+    let handle = app.clone();
+
+    let mut tray_builder = TrayIconBuilder::with_id(TRAY_ID)
         .menu(&menu)
-        .tooltip("Easy Dictate");
+        .tooltip(TrayIconKind::Normal.tooltip());
 
-    // Create icon from PNG bytes
-    if let Ok(icon_image) = Image::from_bytes(icon_bytes) {
+    // Create icon from PNG bytes embedded at compile time
+    if let Ok(icon_image) = Image::from_bytes(TrayIconKind::Normal.bytes()) {
         tray_builder = tray_builder.icon(icon_image);
     }
 
-    tray_builder
+    let tray = tray_builder
         .on_tray_icon_event(move |_tray, event| match event {
             TrayIconEvent::Click {
                 button,
@@ -53,7 +293,187 @@ pub fn install_tray(app: &AppHandle) -> Result<MenuItem<tauri::Wry>> {
         })
         .build(app)?;
 
-    Ok(status_item)
+    store_menu_handles(app, status_item, items);
+    if let Some(state) = app.try_state::<AppState>() {
+        if let Ok(mut guard) = state.tray_icon().lock() {
+            let mut icon = TrayIconState {
+                icon: tray,
+                current: TrayIconKind::Normal,
+                generation: 0,
+                muted: state.mic_mute().is_muted(),
+            };
+            icon.show(TrayIconKind::Normal);
+            *guard = Some(icon);
+        }
+    }
+
+    Ok(())
+}
+
+/// Rebuild the tray menu after settings that it reflects have changed
+pub fn refresh_tray_menu(app: &AppHandle, settings: &AppSettings) -> Result<()> {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return Ok(());
+    };
+
+    let (menu, status_item, items) = build_menu(app, settings)?;
+    tray.set_menu(Some(menu))?;
+    store_menu_handles(app, status_item, items);
+
+    Ok(())
+}
+
+/// Update check marks in the existing tray menu to match `settings`
+pub fn sync_tray_menu(app: &AppHandle, settings: &AppSettings) {
+    let Some(state) = app.try_state::<AppState>() else {
+        return;
+    };
+    let Ok(guard) = state.tray_menu_items().lock() else {
+        return;
+    };
+    if let Some(items) = guard.as_ref() {
+        if let Err(e) = items.sync(settings) {
+            tracing::warn!("[Tray] Failed to sync tray menu: {}", e);
+        }
+    }
+}
+
+/// Show the hard mute in the tray: the muted icon while idle and the menu check mark
+pub fn set_tray_muted(app: &AppHandle, muted: bool) {
+    let Some(state) = app.try_state::<AppState>() else {
+        return;
+    };
+    if let Ok(guard) = state.tray_menu_items().lock() {
+        if let Some(items) = guard.as_ref() {
+            if let Err(e) = items.mic_mute.set_checked(muted) {
+                tracing::warn!("[Tray] Failed to sync the mute item: {}", e);
+            }
+        }
+    }
+    if let Ok(mut guard) = state.tray_icon().lock() {
+        if let Some(tray) = guard.as_mut() {
+            tray.muted = muted;
+            if matches!(tray.current, TrayIconKind::Normal | TrayIconKind::Muted) {
+                tray.show(TrayIconKind::Normal);
+            }
+        }
+    }
+}
+
+/// Check the session item while a dictation session runs
+pub fn set_tray_session(app: &AppHandle, active: bool) {
+    let Some(state) = app.try_state::<AppState>() else {
+        return;
+    };
+    if let Ok(guard) = state.tray_menu_items().lock() {
+        if let Some(items) = guard.as_ref() {
+            if let Err(e) = items.session.set_checked(active) {
+                tracing::warn!("[Tray] Failed to sync the session item: {}", e);
+            }
+        }
+    }
+}
+
+/// Switch the tray icon to match the status phase. Alerting states (recording,
+/// transcribing, error) show immediately; returning to normal is debounced.
+pub fn update_tray_icon(app: &AppHandle, phase: StatusPhase) {
+    let kind = TrayIconKind::for_phase(phase);
+    let Some(state) = app.try_state::<AppState>() else {
+        return;
+    };
+
+    let (generation, delay) = {
+        let Ok(mut guard) = state.tray_icon().lock() else {
+            return;
+        };
+        let Some(tray) = guard.as_mut() else {
+            return;
+        };
+        tray.generation += 1;
+        if kind != TrayIconKind::Normal
+            || matches!(tray.current, TrayIconKind::Normal | TrayIconKind::Muted)
+        {
+            tray.show(kind);
+            return;
+        }
+        let delay = if tray.current == TrayIconKind::Error {
+            ICON_ERROR_HOLD
+        } else {
+            ICON_SETTLE_DELAY
+        };
+        (tray.generation, delay)
+    };
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(delay).await;
+        let state = app.state::<AppState>();
+        let Ok(mut guard) = state.tray_icon().lock() else {
+            return;
+        };
+        if let Some(tray) = guard.as_mut() {
+            if tray.generation == generation {
+                tray.show(TrayIconKind::Normal);
+            }
+        }
+    });
+}
+
+/// Handle tray menu clicks that aren't plain window actions
+pub fn handle_menu_event(app: &AppHandle, id: &str) {
+    if id == MIC_MUTE_ID {
+        if let Some(state) = app.try_state::<AppState>() {
+            change_mic_mute(app, &state, !state.mic_mute().is_muted());
+        }
+        return;
+    }
+    if id == SESSION_ID {
+        let app_clone = app.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = dictation_session::toggle(&app_clone).await {
+                emit_error(&app_clone, &e.to_string());
+            }
+        });
+        return;
+    }
+    let translation_change = if id == AUTO_TRANSLATE_ID {
+        Some(TranslationChange::Toggle)
+    } else {
+        id.strip_prefix(LANGUAGE_ID_PREFIX)
+            .map(|language| TranslationChange::Target(language.to_string()))
+    };
+
+    if let Some(change) = translation_change {
+        let app_clone = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let state: State<'_, AppState> = app_clone.state();
+            if let Err(e) = change_translation(&app_clone, &state, change).await {
+                emit_error(&app_clone, &e.to_string());
+            }
+        });
+    } else if let Some(provider) = id
+        .strip_prefix(PROVIDER_ID_PREFIX)
+        .and_then(|id| SWITCHABLE_PROVIDERS.into_iter().find(|p| p.id() == id))
+    {
+        let app_clone = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let state: State<'_, AppState> = app_clone.state();
+            if let Err(e) = change_provider(&app_clone, &state, provider).await {
+                emit_error(&app_clone, &e.to_string());
+            }
+        });
+    } else if let Some(name) = id.strip_prefix(PRESET_ID_PREFIX) {
+        let app_clone = app.clone();
+        let name = name.to_string();
+        tauri::async_runtime::spawn(async move {
+            let state: State<'_, AppState> = app_clone.state();
+            if let Err(e) =
+                super::commands::change_active_preset(&app_clone, &state, Some(&name)).await
+            {
+                emit_error(&app_clone, &e.to_string());
+            }
+        });
+    }
 }
 
 pub fn show_settings_window(app: &AppHandle) {
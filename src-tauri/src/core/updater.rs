@@ -0,0 +1,431 @@
+use std::{
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_updater::{Update, UpdaterExt};
+
+use crate::settings::UpdateChannel;
+
+use super::{
+    events::{
+        UpdateProgressPayload, EVENT_UPDATE_AVAILABLE, EVENT_UPDATE_DEFERRED,
+        EVENT_UPDATE_DOWNLOADED, EVENT_UPDATE_INSTALLED, EVENT_UPDATE_PROGRESS,
+    },
+    state::AppState,
+};
+
+const STABLE_ENDPOINT: &str =
+    "https://github.com/RuKapSan/easy-dictate/releases/latest/download/latest.json";
+/// GitHub's "latest" release skips pre-releases, so beta builds publish their
+/// manifest on a rolling `beta` tag
+const BETA_ENDPOINT: &str =
+    "https://github.com/RuKapSan/easy-dictate/releases/download/beta/latest.json";
+
+const IDLE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateInfo {
+    pub available: bool,
+    pub current: String,
+    pub latest: Option<String>,
+    pub notes: Option<String>,
+}
+
+/// An update whose package has been downloaded but not installed yet
+pub struct DownloadedUpdate {
+    update: Update,
+    bytes: Vec<u8>,
+}
+
+/// Manifests read for `channel`. Beta reads stable too: the rolling beta manifest
+/// still names the last beta after a final release ships.
+fn endpoints(channel: UpdateChannel) -> Vec<&'static str> {
+    match channel {
+        UpdateChannel::Stable => vec![STABLE_ENDPOINT],
+        UpdateChannel::Beta => vec![BETA_ENDPOINT, STABLE_ENDPOINT],
+    }
+}
+
+/// Whether `latest` should be offered to someone running `current` on `channel`.
+/// Never offers an older build, e.g. after switching from beta back to stable.
+fn is_offered(current: &semver::Version, latest: &semver::Version, channel: UpdateChannel) -> bool {
+    if latest <= current {
+        return false;
+    }
+    match channel {
+        UpdateChannel::Stable => latest.pre.is_empty(),
+        UpdateChannel::Beta => true,
+    }
+}
+
+/// The newest of `releases` that is offered to `current` on `channel`
+fn newest_offered<T>(
+    current: &semver::Version,
+    channel: UpdateChannel,
+    releases: Vec<(semver::Version, T)>,
+) -> Option<T> {
+    releases
+        .into_iter()
+        .filter(|(version, _)| is_offered(current, version, channel))
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, release)| release)
+}
+
+/// Ask the update endpoints of the configured channel for a newer version and take
+/// the newest one offered. The channel is read on every check, so switching it needs
+/// no restart. Fails only if no endpoint answers.
+pub async fn check(app: &AppHandle) -> Result<(UpdateInfo, Option<Update>)> {
+    let state: State<'_, AppState> = app.state();
+    let channel = state.current_settings().await.update_channel;
+
+    tracing::info!("[Updater] Checking {:?} channel", channel);
+    let endpoints = endpoints(channel);
+    let mut releases = Vec::new();
+    let mut failures = Vec::new();
+    for &endpoint in &endpoints {
+        let updater = app
+            .updater_builder()
+            .endpoints(vec![tauri::Url::parse(endpoint)?])?
+            .version_comparator(move |current, release| {
+                is_offered(&current, &release.version, channel)
+            })
+            .build()?;
+        match updater.check().await {
+            Ok(Some(update)) => match semver::Version::parse(&update.version) {
+                Ok(version) => releases.push((version, update)),
+                Err(e) => tracing::warn!("[Updater] {} names a bad version: {}", endpoint, e),
+            },
+            Ok(None) => {}
+            Err(e) => {
+                tracing::warn!("[Updater] {} did not answer: {}", endpoint, e);
+                failures.push(e);
+            }
+        }
+    }
+    if failures.len() == endpoints.len() {
+        if let Some(e) = failures.pop() {
+            return Err(anyhow!("Update check failed: {e}"));
+        }
+    }
+    let update = newest_offered(&app.package_info().version, channel, releases);
+
+    let info = match &update {
+        Some(update) => {
+            tracing::info!(
+                "[Updater] Update available: {} -> {}",
+                update.current_version,
+                update.version
+            );
+            UpdateInfo {
+                available: true,
+                current: update.current_version.clone(),
+                latest: Some(update.version.clone()),
+                notes: update.body.clone(),
+            }
+        }
+        None => {
+            tracing::info!("[Updater] App is up to date");
+            UpdateInfo {
+                available: false,
+                current: app.package_info().version.to_string(),
+                latest: None,
+                notes: None,
+            }
+        }
+    };
+    Ok((info, update))
+}
+
+/// Download the update package, reporting progress, and keep it for `install`
+pub async fn download(app: &AppHandle, update: Update) -> Result<String> {
+    let version = update.version.clone();
+    tracing::info!("[Updater] Downloading update v{}...", version);
+
+    let mut downloaded: u64 = 0;
+    let bytes = update
+        .download(
+            |chunk, total| {
+                downloaded += chunk as u64;
+                let _ = app.emit(
+                    EVENT_UPDATE_PROGRESS,
+                    UpdateProgressPayload { downloaded, total },
+                );
+            },
+            || tracing::info!("[Updater] Download finished"),
+        )
+        .await
+        .map_err(|e| anyhow!("Update download failed: {e}"))?;
+
+    let state: State<'_, AppState> = app.state();
+    *state
+        .pending_update()
+        .lock()
+        .map_err(|_| anyhow!("Failed to lock pending update"))? =
+        Some(DownloadedUpdate { update, bytes });
+
+    let _ = app.emit(EVENT_UPDATE_DOWNLOADED, &version);
+    Ok(version)
+}
+
+/// Install the previously downloaded update. The app must be restarted afterwards
+/// (on Windows the installer restarts it itself).
+pub fn install(app: &AppHandle) -> Result<String> {
+    let state: State<'_, AppState> = app.state();
+    let pending = state
+        .pending_update()
+        .lock()
+        .map_err(|_| anyhow!("Failed to lock pending update"))?
+        .take()
+        .ok_or_else(|| anyhow!("No downloaded update to install"))?;
+
+    let version = pending.update.version.clone();
+    tracing::info!("[Updater] Installing update v{}...", version);
+    pending
+        .update
+        .install(&pending.bytes)
+        .map_err(|e| anyhow!("Update install failed: {e}"))?;
+
+    tracing::info!("[Updater] Update v{} installed. Restart required.", version);
+    let _ = app.emit(EVENT_UPDATE_INSTALLED, &version);
+    Ok(version)
+}
+
+/// What the app was doing when the updater looked. A lock that can't be taken
+/// counts as busy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Activity {
+    /// The hotkey is held, for either transcription path
+    recording: bool,
+    /// An ElevenLabs gate is open or a recording tick timer runs
+    gate_open: bool,
+    transcribing: bool,
+    /// Results being typed or waiting for their turn
+    deliveries: usize,
+    /// Streamed commits still waiting for their transcript
+    pending_commits: usize,
+}
+
+impl Activity {
+    fn of(state: &AppState) -> Self {
+        let session = state.recording_session();
+        Self {
+            recording: session.is_some()
+                || state
+                    .active_recording()
+                    .lock()
+                    .map_or(true, |g| g.is_some()),
+            gate_open: session.is_some_and(|s| s.gate_opened_at.is_some())
+                || state.recording_timer().lock().map_or(true, |g| g.is_some()),
+            transcribing: state.is_transcribing().load(Ordering::SeqCst),
+            deliveries: state.delivery().pending(),
+            pending_commits: state.pending_commits().lock().map_or(1, |g| g.len()),
+        }
+    }
+
+    fn is_idle(&self) -> bool {
+        !self.recording
+            && !self.gate_open
+            && !self.transcribing
+            && self.deliveries == 0
+            && self.pending_commits == 0
+    }
+}
+
+/// When the app last became idle, fed one `Activity` snapshot per poll
+#[derive(Debug, Default)]
+struct IdleClock {
+    idle_since: Option<Instant>,
+}
+
+impl IdleClock {
+    /// How long the app has been idle as of `now`; zero while it is busy
+    fn observe(&mut self, activity: Activity, now: Instant) -> Duration {
+        if !activity.is_idle() {
+            self.idle_since = None;
+            return Duration::ZERO;
+        }
+        now.saturating_duration_since(*self.idle_since.get_or_insert(now))
+    }
+}
+
+/// Wait until the app has been idle for `update_idle_grace_secs`, read on every poll
+async fn wait_until_idle(app: &AppHandle) {
+    let state: State<'_, AppState> = app.state();
+    let mut clock = IdleClock::default();
+    loop {
+        let grace = Duration::from_secs(state.current_settings().await.update_idle_grace_secs);
+        if clock.observe(Activity::of(&state), Instant::now()) >= grace {
+            return;
+        }
+        tokio::time::sleep(IDLE_POLL_INTERVAL).await;
+    }
+}
+
+/// Startup auto-update: check and download right away, but only install once the
+/// app has been idle, so a restart never interrupts a dictation
+pub fn spawn_auto_update(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tracing::info!("[Updater] Checking for updates...");
+        let update = match check(&app).await {
+            Ok((_, Some(update))) => update,
+            Ok((_, None)) => return,
+            Err(e) => {
+                tracing::warn!("[Updater] {}", e);
+                return;
+            }
+        };
+
+        let _ = app.emit(EVENT_UPDATE_AVAILABLE, &update.version);
+        let version = match download(&app, update).await {
+            Ok(version) => version,
+            Err(e) => {
+                tracing::error!("[Updater] {}", e);
+                return;
+            }
+        };
+
+        let grace = app
+            .state::<AppState>()
+            .current_settings()
+            .await
+            .update_idle_grace_secs;
+        tracing::info!(
+            "[Updater] Waiting for {}s of inactivity before installing",
+            grace
+        );
+        let _ = app.emit(EVENT_UPDATE_DEFERRED, &version);
+        wait_until_idle(&app).await;
+        // Installed from the settings window meanwhile
+        let state: State<'_, AppState> = app.state();
+        if state.pending_update().lock().is_ok_and(|g| g.is_none()) {
+            return;
+        }
+        if let Err(e) = install(&app) {
+            tracing::error!("[Updater] {}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(version: &str) -> semver::Version {
+        semver::Version::parse(version).unwrap()
+    }
+
+    #[test]
+    fn test_stable_skips_prereleases() {
+        assert!(is_offered(&v("0.2.6"), &v("0.2.7"), UpdateChannel::Stable));
+        assert!(!is_offered(
+            &v("0.2.6"),
+            &v("0.3.0-beta.1"),
+            UpdateChannel::Stable
+        ));
+        assert!(is_offered(
+            &v("0.2.6"),
+            &v("0.3.0-beta.1"),
+            UpdateChannel::Beta
+        ));
+    }
+
+    #[test]
+    fn test_beta_user_gets_final_release() {
+        assert!(is_offered(
+            &v("0.3.0-beta.2"),
+            &v("0.3.0"),
+            UpdateChannel::Stable
+        ));
+        assert!(is_offered(
+            &v("0.3.0-beta.1"),
+            &v("0.3.0-beta.2"),
+            UpdateChannel::Beta
+        ));
+    }
+
+    #[test]
+    fn test_beta_user_gets_newer_stable_release() {
+        // The rolling beta manifest still names the beta this user already runs
+        let manifests = vec![(v("0.3.0-beta.2"), "beta"), (v("0.3.0"), "stable")];
+        assert_eq!(
+            newest_offered(&v("0.3.0-beta.2"), UpdateChannel::Beta, manifests),
+            Some("stable")
+        );
+        // A newer beta still wins over the last stable release
+        let manifests = vec![(v("0.4.0-beta.1"), "beta"), (v("0.3.0"), "stable")];
+        assert_eq!(
+            newest_offered(&v("0.3.0"), UpdateChannel::Beta, manifests),
+            Some("beta")
+        );
+        let manifests = vec![(v("0.3.0-beta.2"), "beta"), (v("0.2.6"), "stable")];
+        assert_eq!(
+            newest_offered(&v("0.3.0-beta.2"), UpdateChannel::Beta, manifests),
+            None
+        );
+    }
+
+    #[test]
+    fn test_any_activity_is_busy() {
+        assert!(Activity::default().is_idle());
+        let busy = [
+            Activity {
+                recording: true,
+                ..Activity::default()
+            },
+            // Streaming connection with the gate open
+            Activity {
+                gate_open: true,
+                ..Activity::default()
+            },
+            Activity {
+                transcribing: true,
+                ..Activity::default()
+            },
+            Activity {
+                deliveries: 1,
+                ..Activity::default()
+            },
+            Activity {
+                pending_commits: 2,
+                ..Activity::default()
+            },
+        ];
+        for activity in busy {
+            assert!(!activity.is_idle(), "{activity:?}");
+        }
+    }
+
+    #[test]
+    fn test_idle_clock_restarts_on_activity() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let idle = Activity::default();
+        let typing = Activity {
+            deliveries: 1,
+            ..Activity::default()
+        };
+        let mut clock = IdleClock::default();
+
+        assert_eq!(clock.observe(idle, at(0)), Duration::ZERO);
+        assert_eq!(clock.observe(idle, at(40)), Duration::from_secs(40));
+        assert_eq!(clock.observe(typing, at(45)), Duration::ZERO);
+        assert_eq!(clock.observe(idle, at(50)), Duration::ZERO);
+        assert_eq!(clock.observe(idle, at(110)), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_never_downgrades() {
+        // Back on stable after running a beta: the older stable build is not offered
+        assert!(!is_offered(
+            &v("0.3.0-beta.1"),
+            &v("0.2.6"),
+            UpdateChannel::Stable
+        ));
+        assert!(!is_offered(&v("0.2.6"), &v("0.2.6"), UpdateChannel::Beta));
+    }
+}
@@ -0,0 +1,81 @@
+use serde::Deserialize;
+
+use crate::settings::normalize_vocabulary;
+
+/// What `import_vocabulary` does with the terms already configured
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    /// Keep the current terms and add the new ones after them
+    #[default]
+    Merge,
+    /// Use only the imported terms
+    Replace,
+}
+
+/// Terms of a vocabulary file: one per line. A line starting with `#` is a comment,
+/// as is the rest of a line after ` #`, so terms like "C#" survive.
+pub fn parse(contents: &str) -> Vec<String> {
+    let terms = contents.lines().filter_map(|line| {
+        let line = line.trim_start_matches('\u{feff}').trim();
+        if line.starts_with('#') {
+            return None;
+        }
+        let term = match line.find(" #").or_else(|| line.find("\t#")) {
+            Some(comment) => &line[..comment],
+            None => line,
+        };
+        Some(term.to_string())
+    });
+    normalize_vocabulary(terms)
+}
+
+/// File contents for `terms`, readable by `parse`
+pub fn render(terms: &[String]) -> String {
+    let mut contents = String::from("# Easy Dictate vocabulary: one term per line\n");
+    for term in terms {
+        contents.push_str(term);
+        contents.push('\n');
+    }
+    contents
+}
+
+/// The vocabulary after importing `imported` into `current`
+pub fn combine(current: &[String], imported: Vec<String>, mode: ImportMode) -> Vec<String> {
+    match mode {
+        ImportMode::Merge => normalize_vocabulary(current.iter().cloned().chain(imported)),
+        ImportMode::Replace => normalize_vocabulary(imported),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_skips_comments_and_blank_lines() {
+        let contents =
+            "\u{feff}# Product names\nGroq\n\n  Tauri   # the framework\nC#\nweb  socket\ngroq\n";
+        assert_eq!(parse(contents), ["Groq", "Tauri", "C#", "web socket"]);
+    }
+
+    #[test]
+    fn test_merge_keeps_current_terms_first() {
+        let current = vec!["Tauri".to_string(), "Groq".to_string()];
+        let imported = vec!["GROQ".to_string(), "ElevenLabs".to_string()];
+        assert_eq!(
+            combine(&current, imported.clone(), ImportMode::Merge),
+            ["Tauri", "Groq", "ElevenLabs"]
+        );
+        assert_eq!(
+            combine(&current, imported, ImportMode::Replace),
+            ["GROQ", "ElevenLabs"]
+        );
+    }
+
+    #[test]
+    fn test_rendered_file_parses_back() {
+        let terms = vec!["Tauri".to_string(), "C#".to_string()];
+        assert_eq!(parse(&render(&terms)), terms);
+    }
+}
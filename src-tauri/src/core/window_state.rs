@@ -0,0 +1,330 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, PhysicalPosition, PhysicalSize, State, WebviewWindow};
+
+use super::state::AppState;
+
+const MAIN_LABEL: &str = "main";
+const WINDOW_STATE_FILE: &str = "window_state.json";
+
+/// Dragging or resizing fires a burst of events; only the final geometry is written
+const SAVE_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Outer bounds of the main window in physical pixels. While maximized the bounds are
+/// the ones the window returns to when it is restored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowState {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    #[serde(default)]
+    pub maximized: bool,
+}
+
+/// A monitor's work area in physical pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Length of the intersection of two 1-D spans
+fn span_overlap(a_start: i32, a_len: u32, b_start: i32, b_len: u32) -> u64 {
+    let from = i64::from(a_start.max(b_start));
+    let to = (i64::from(a_start) + i64::from(a_len)).min(i64::from(b_start) + i64::from(b_len));
+    u64::try_from(to - from).unwrap_or(0)
+}
+
+impl Rect {
+    fn overlap(&self, state: &WindowState) -> u64 {
+        span_overlap(state.x, state.width, self.x, self.width)
+            * span_overlap(state.y, state.height, self.y, self.height)
+    }
+}
+
+/// Fit saved bounds into the current monitor layout: the window lands on the monitor it
+/// overlaps most (the first one if it overlaps none, e.g. because its monitor was
+/// disconnected), shrunk to that monitor if necessary and moved fully inside it.
+/// `monitors` should list the primary monitor first.
+pub fn clamp_to_monitors(state: WindowState, monitors: &[Rect]) -> WindowState {
+    let Some(target) = monitors
+        .iter()
+        .filter(|monitor| monitor.overlap(&state) > 0)
+        .max_by_key(|monitor| monitor.overlap(&state))
+        .or_else(|| monitors.first())
+    else {
+        return state;
+    };
+
+    let width = state.width.min(target.width);
+    let height = state.height.min(target.height);
+    let clamp = |pos: i32, len: u32, start: i32, extent: u32| {
+        let max = i64::from(start) + i64::from(extent) - i64::from(len);
+        // `len` never exceeds `extent`, so the result stays within the monitor's i32 range
+        i64::from(pos).clamp(i64::from(start), max) as i32
+    };
+
+    WindowState {
+        x: clamp(state.x, width, target.x, target.width),
+        y: clamp(state.y, height, target.y, target.height),
+        width,
+        height,
+        maximized: state.maximized,
+    }
+}
+
+fn state_path(state: &AppState) -> PathBuf {
+    state.settings_path().with_file_name(WINDOW_STATE_FILE)
+}
+
+fn load(path: &Path) -> Option<WindowState> {
+    let raw = match std::fs::read_to_string(path) {
+        Ok(raw) => raw,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+        Err(e) => {
+            tracing::warn!("[Window] Failed to read {:?}: {}", path, e);
+            return None;
+        }
+    };
+    match serde_json::from_str(&raw) {
+        Ok(saved) => Some(saved),
+        Err(e) => {
+            tracing::warn!("[Window] Ignoring malformed {:?}: {}", path, e);
+            None
+        }
+    }
+}
+
+/// Work areas of the connected monitors
+pub fn monitors(window: &WebviewWindow) -> Vec<Rect> {
+    let primary = window.primary_monitor().ok().flatten();
+    let mut monitors = window.available_monitors().unwrap_or_default();
+    // Primary first, so a window whose monitor is gone ends up there
+    monitors.sort_by_key(|monitor| {
+        primary
+            .as_ref()
+            .map_or(true, |primary| monitor.name() != primary.name())
+    });
+    monitors
+        .iter()
+        .map(|monitor| {
+            let area = monitor.work_area();
+            Rect {
+                x: area.position.x,
+                y: area.position.y,
+                width: area.size.width,
+                height: area.size.height,
+            }
+        })
+        .collect()
+}
+
+/// Move the (still hidden) main window to where it was last closed. A window that
+/// stays hidden is not maximized yet, since maximizing would show it on Windows;
+/// it keeps the restored bounds instead.
+pub fn restore(app: &AppHandle, visible: bool) {
+    let Some(window) = app.get_webview_window(MAIN_LABEL) else {
+        return;
+    };
+    let state: State<'_, AppState> = app.state();
+    let Some(saved) = load(&state_path(&state)) else {
+        return;
+    };
+
+    let restored = clamp_to_monitors(saved, &monitors(&window));
+    if restored != saved {
+        tracing::info!(
+            "[Window] Saved bounds {:?} moved on screen to {:?}",
+            saved,
+            restored
+        );
+    }
+    if let Err(e) = window.set_size(PhysicalSize::new(restored.width, restored.height)) {
+        tracing::warn!("[Window] Failed to restore window size: {}", e);
+    }
+    if let Err(e) = window.set_position(PhysicalPosition::new(restored.x, restored.y)) {
+        tracing::warn!("[Window] Failed to restore window position: {}", e);
+    }
+    if restored.maximized && visible {
+        if let Err(e) = window.maximize() {
+            tracing::warn!("[Window] Failed to maximize window: {}", e);
+        }
+    }
+    // Seeds the bounds kept while the window is maximized
+    if let Ok(mut guard) = state.window_state().lock() {
+        *guard = Some(restored);
+    }
+}
+
+/// Current geometry of the main window, or None while it is minimized
+/// (minimized windows report a placeholder position far off-screen)
+fn current(window: &WebviewWindow, state: &AppState) -> Option<WindowState> {
+    if window.is_minimized().unwrap_or(false) {
+        return None;
+    }
+    let maximized = window.is_maximized().unwrap_or(false);
+    let mut guard = state.window_state().lock().ok()?;
+    let current = match (*guard, maximized) {
+        // A maximized window covers the monitor; keep the bounds it restores to
+        (Some(previous), true) => WindowState {
+            maximized: true,
+            ..previous
+        },
+        _ => {
+            let position = window.outer_position().ok()?;
+            let size = window.outer_size().ok()?;
+            WindowState {
+                x: position.x,
+                y: position.y,
+                width: size.width,
+                height: size.height,
+                maximized,
+            }
+        }
+    };
+    *guard = Some(current);
+    Some(current)
+}
+
+async fn write(path: PathBuf, saved: WindowState) {
+    let json = match serde_json::to_vec_pretty(&saved) {
+        Ok(json) => json,
+        Err(e) => {
+            tracing::warn!("[Window] Failed to serialize window state: {}", e);
+            return;
+        }
+    };
+    if let Some(dir) = path.parent() {
+        let _ = tokio::fs::create_dir_all(dir).await;
+    }
+    if let Err(e) = tokio::fs::write(&path, json).await {
+        tracing::warn!("[Window] Failed to write {:?}: {}", path, e);
+    }
+}
+
+/// Save the main window's geometry once it stopped moving or resizing for `SAVE_DEBOUNCE`
+pub fn schedule_save(app: &AppHandle) {
+    let Some(state) = app.try_state::<AppState>() else {
+        return;
+    };
+    let generation = state.next_window_state_generation();
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(SAVE_DEBOUNCE).await;
+        let state: State<'_, AppState> = app.state();
+        if !state.is_window_state_generation(generation) {
+            return;
+        }
+        save(&app, &state).await;
+    });
+}
+
+/// Write the current geometry right away, e.g. on shutdown
+pub async fn save(app: &AppHandle, state: &AppState) {
+    let Some(window) = app.get_webview_window(MAIN_LABEL) else {
+        return;
+    };
+    if let Some(current) = current(&window, state) {
+        write(state_path(state), current).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRIMARY: Rect = Rect {
+        x: 0,
+        y: 0,
+        width: 1920,
+        height: 1040,
+    };
+    /// Secondary monitor to the left of the primary one
+    const LEFT: Rect = Rect {
+        x: -2560,
+        y: -200,
+        width: 2560,
+        height: 1400,
+    };
+
+    fn window(x: i32, y: i32, width: u32, height: u32) -> WindowState {
+        WindowState {
+            x,
+            y,
+            width,
+            height,
+            maximized: false,
+        }
+    }
+
+    #[test]
+    fn test_visible_window_is_unchanged() {
+        let saved = window(100, 80, 820, 680);
+        assert_eq!(clamp_to_monitors(saved, &[PRIMARY, LEFT]), saved);
+
+        let on_left = window(-2000, -100, 820, 680);
+        assert_eq!(clamp_to_monitors(on_left, &[PRIMARY, LEFT]), on_left);
+    }
+
+    #[test]
+    fn test_disconnected_monitor_moves_window_to_primary() {
+        // Saved on the left monitor, which is gone now
+        let saved = window(-2000, -100, 820, 680);
+        assert_eq!(clamp_to_monitors(saved, &[PRIMARY]), window(0, 0, 820, 680));
+    }
+
+    #[test]
+    fn test_partially_off_screen_window_is_pulled_in() {
+        let saved = window(1500, 700, 820, 680);
+        assert_eq!(
+            clamp_to_monitors(saved, &[PRIMARY]),
+            window(1100, 360, 820, 680)
+        );
+    }
+
+    #[test]
+    fn test_window_lands_on_monitor_it_overlaps_most() {
+        // Mostly on the left monitor, a little on the primary one
+        let saved = window(-700, 100, 820, 680);
+        assert_eq!(
+            clamp_to_monitors(saved, &[PRIMARY, LEFT]),
+            window(-820, 100, 820, 680)
+        );
+    }
+
+    #[test]
+    fn test_oversized_window_is_shrunk_and_keeps_maximized() {
+        let saved = WindowState {
+            maximized: true,
+            ..window(-10, -10, 2560, 1440)
+        };
+        assert_eq!(
+            clamp_to_monitors(saved, &[PRIMARY]),
+            WindowState {
+                maximized: true,
+                ..window(0, 0, 1920, 1040)
+            }
+        );
+    }
+
+    #[test]
+    fn test_no_monitors_keeps_saved_bounds() {
+        let saved = window(5000, 5000, 820, 680);
+        assert_eq!(clamp_to_monitors(saved, &[]), saved);
+    }
+
+    #[test]
+    fn test_missing_maximized_defaults_to_false() {
+        let saved: WindowState =
+            serde_json::from_str(r#"{"x": 10, "y": 20, "width": 820, "height": 680}"#).unwrap();
+        assert_eq!(saved, window(10, 20, 820, 680));
+    }
+}
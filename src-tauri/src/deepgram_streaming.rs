@@ -0,0 +1,386 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::{
+    client::IntoClientRequest, handshake::client::Request, http::HeaderValue, Message,
+};
+
+use crate::core::events::{
+    ErrorCode, StreamingErrorPayload, StreamingTranscriptPayload, EVENT_ELEVENLABS_ERROR,
+    EVENT_ELEVENLABS_TRANSCRIPT,
+};
+use crate::elevenlabs_streaming::{emit, ConnectionConfig, StreamingEventSink};
+use crate::streaming_session::{silence_chunks, StreamingSession};
+
+const REALTIME_ENDPOINT: &str = "wss://api.deepgram.com/v1/listen";
+const MODEL: &str = "nova-3";
+
+/// Deepgram closes a connection that got neither audio nor a `KeepAlive` for 10 s
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Control messages the client sends as text; audio goes out as binary frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "type")]
+enum Control {
+    /// Transcribe everything sent so far now; answered with `from_finalize` results
+    Finalize,
+    /// No more audio; the server flushes, sends `Metadata` and closes
+    CloseStream,
+    KeepAlive,
+}
+
+impl Control {
+    fn message(self) -> Message {
+        let json = serde_json::to_string(&self).unwrap_or_default();
+        Message::Text(json)
+    }
+}
+
+/// Messages from the server, by their `type`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type")]
+enum Inbound {
+    Results(Results),
+    Metadata(Metadata),
+    Error(ServerError),
+    /// `SpeechStarted`, `UtteranceEnd` and anything newer
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct Results {
+    channel: Channel,
+    /// The text of this stretch of audio won't change any more
+    #[serde(default)]
+    is_final: bool,
+    /// The last final result answering a `Finalize`
+    #[serde(default)]
+    from_finalize: bool,
+}
+
+impl Results {
+    fn transcript(&self) -> &str {
+        self.channel
+            .alternatives
+            .first()
+            .map_or("", |alternative| alternative.transcript.trim())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct Channel {
+    #[serde(default)]
+    alternatives: Vec<Alternative>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct Alternative {
+    #[serde(default)]
+    transcript: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct Metadata {
+    #[serde(default)]
+    request_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct ServerError {
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    message: String,
+}
+
+impl ServerError {
+    fn text(&self) -> &str {
+        if self.description.is_empty() {
+            &self.message
+        } else {
+            &self.description
+        }
+    }
+}
+
+/// Deepgram's live transcription protocol: linear16 audio as binary frames, `Finalize`
+/// for a commit and `CloseStream` at the end. Deepgram finalizes stretches of speech
+/// on its own too, so the final results of a segment are collected until the one
+/// answering `Finalize` and emitted as one committed transcript.
+pub struct DeepgramSession {
+    api_key: String,
+    sample_rate: u32,
+    language_code: String,
+    /// Final results of the segment so far
+    finals: std::sync::Mutex<Vec<String>>,
+}
+
+impl DeepgramSession {
+    pub fn new(config: &ConnectionConfig) -> Self {
+        Self {
+            api_key: config.api_key.clone(),
+            sample_rate: config.sample_rate,
+            language_code: config.language_code.clone(),
+            finals: std::sync::Mutex::new(Vec::new()),
+        }
+    }
+
+    fn finals(&self) -> std::sync::MutexGuard<'_, Vec<String>> {
+        self.finals.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn url(&self, endpoint: &str) -> String {
+        // Without a language nova-3 transcribes whatever mix of languages it hears
+        let language = if self.language_code.is_empty() || self.language_code == "auto" {
+            "multi"
+        } else {
+            &self.language_code
+        };
+        format!(
+            "{endpoint}?model={MODEL}&language={language}&encoding=linear16&sample_rate={}&channels=1&interim_results=true&punctuate=true&smart_format=true",
+            self.sample_rate
+        )
+    }
+
+    fn handle_results(&self, results: Results, events: &dyn StreamingEventSink) -> bool {
+        let transcript = results.transcript();
+        let mut finals = self.finals();
+        let text = if results.is_final {
+            if !transcript.is_empty() {
+                finals.push(transcript.to_string());
+            }
+            finals.join(" ")
+        } else {
+            finals
+                .iter()
+                .map(String::as_str)
+                .chain([transcript].into_iter().filter(|t| !t.is_empty()))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        if results.from_finalize {
+            finals.clear();
+            tracing::info!("[Deepgram] Committed: {}", text);
+        } else if text.is_empty() {
+            return false;
+        } else {
+            tracing::info!("[Deepgram] Partial: {}", text);
+        }
+        emit(
+            events,
+            EVENT_ELEVENLABS_TRANSCRIPT,
+            StreamingTranscriptPayload {
+                text,
+                is_partial: !results.from_finalize,
+                words: None,
+                language_code: None,
+            },
+        );
+        results.from_finalize
+    }
+}
+
+impl StreamingSession for DeepgramSession {
+    fn name(&self) -> &'static str {
+        "Deepgram"
+    }
+
+    fn default_endpoint(&self) -> &'static str {
+        REALTIME_ENDPOINT
+    }
+
+    fn request(&self, endpoint: &str) -> Result<Request> {
+        tracing::info!(
+            "[Deepgram] Connecting to WebSocket (sample_rate: {}, language: {})",
+            self.sample_rate,
+            self.language_code
+        );
+        let mut request = self
+            .url(endpoint)
+            .into_client_request()
+            .context("Failed to build WebSocket request")?;
+        request.headers_mut().insert(
+            "Authorization",
+            HeaderValue::from_str(&format!("Token {}", self.api_key)).context("Invalid API key")?,
+        );
+        Ok(request)
+    }
+
+    fn gate_opened(&self) {
+        // Finals of an abandoned segment don't belong to the new dictation
+        self.finals().clear();
+    }
+
+    fn audio(&self, pcm: &[u8]) -> Result<Vec<Message>> {
+        Ok(vec![Message::Binary(pcm.to_vec())])
+    }
+
+    fn finalize(&self, silence: &[u8]) -> Result<Vec<Message>> {
+        let mut messages: Vec<Message> = silence_chunks(self.sample_rate, silence)
+            .into_iter()
+            .filter(|chunk| !chunk.is_empty())
+            .map(|chunk| Message::Binary(chunk.to_vec()))
+            .collect();
+        messages.push(Control::Finalize.message());
+        Ok(messages)
+    }
+
+    fn keepalive_interval(&self) -> Duration {
+        KEEPALIVE_INTERVAL
+    }
+
+    fn keepalive(&self) -> Message {
+        Control::KeepAlive.message()
+    }
+
+    fn close(&self) -> Message {
+        Control::CloseStream.message()
+    }
+
+    fn handle_text(&self, text: &str, events: &dyn StreamingEventSink) -> bool {
+        tracing::debug!("[Deepgram] Raw message: {}", text);
+
+        match serde_json::from_str::<Inbound>(text) {
+            Ok(Inbound::Results(results)) => self.handle_results(results, events),
+            Ok(Inbound::Metadata(metadata)) => {
+                tracing::info!(
+                    "[Deepgram] Stream metadata, request {}",
+                    metadata.request_id
+                );
+                false
+            }
+            Ok(Inbound::Error(error)) => {
+                tracing::error!("[Deepgram] Error received: {}", error.text());
+                emit(
+                    events,
+                    EVENT_ELEVENLABS_ERROR,
+                    StreamingErrorPayload {
+                        error: format!("Deepgram: {}", error.text()),
+                        code: ErrorCode::StreamingError,
+                        server_error: None,
+                    },
+                );
+                false
+            }
+            Ok(Inbound::Unknown) => false,
+            Err(e) => {
+                tracing::warn!("[Deepgram] Malformed message: {}", e);
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::StreamingProvider;
+    use serde_json::{json, Value};
+
+    #[derive(Default)]
+    struct RecordedEvents(std::sync::Mutex<Vec<(String, Value)>>);
+
+    impl StreamingEventSink for RecordedEvents {
+        fn emit_event(&self, event: &str, payload: Value) {
+            self.0.lock().unwrap().push((event.to_string(), payload));
+        }
+    }
+
+    fn session(language_code: &str) -> DeepgramSession {
+        DeepgramSession::new(&ConnectionConfig {
+            provider: StreamingProvider::Deepgram,
+            api_key: "dg-key".to_string(),
+            sample_rate: 48_000,
+            language_code: language_code.to_string(),
+        })
+    }
+
+    fn results(transcript: &str, is_final: bool, from_finalize: bool) -> String {
+        json!({
+            "type": "Results",
+            "channel": {"alternatives": [{"transcript": transcript, "confidence": 0.98}]},
+            "is_final": is_final,
+            "speech_final": is_final,
+            "from_finalize": from_finalize,
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_request_carries_format_and_key() {
+        let request = session("auto").request(REALTIME_ENDPOINT).unwrap();
+        let uri = request.uri().to_string();
+        assert!(uri.starts_with(REALTIME_ENDPOINT));
+        assert!(uri.contains("language=multi"));
+        assert!(uri.contains("encoding=linear16&sample_rate=48000&channels=1"));
+        assert_eq!(request.headers()["Authorization"], "Token dg-key");
+
+        let request = session("ru").request(REALTIME_ENDPOINT).unwrap();
+        assert!(request.uri().to_string().contains("language=ru"));
+    }
+
+    #[test]
+    fn test_control_messages() {
+        let session = session("en");
+        let messages = session.finalize(&[0; 4]).unwrap();
+        assert_eq!(
+            messages,
+            vec![
+                Message::Binary(vec![0; 4]),
+                Message::Text(r#"{"type":"Finalize"}"#.to_string()),
+            ]
+        );
+        assert_eq!(session.finalize(&[]).unwrap().len(), 1);
+        assert_eq!(
+            session.close(),
+            Message::Text(r#"{"type":"CloseStream"}"#.to_string())
+        );
+        assert_eq!(
+            session.keepalive(),
+            Message::Text(r#"{"type":"KeepAlive"}"#.to_string())
+        );
+    }
+
+    #[test]
+    fn test_finals_are_committed_together() {
+        let session = session("en");
+        let events = RecordedEvents::default();
+
+        assert!(!session.handle_text(&results("hello", false, false), &events));
+        assert!(!session.handle_text(&results("hello there", true, false), &events));
+        assert!(!session.handle_text(&results("how", false, false), &events));
+        assert!(session.handle_text(&results("how are you", true, true), &events));
+        // Nothing left over for the next segment
+        assert!(session.handle_text(&results("", true, true), &events));
+        assert!(!session.handle_text(r#"{"type":"SpeechStarted"}"#, &events));
+        assert!(!session.handle_text("{truncated", &events));
+
+        let transcripts: Vec<(String, Value)> = events.0.lock().unwrap().clone();
+        let payloads: Vec<&Value> = transcripts.iter().map(|(_, payload)| payload).collect();
+        assert_eq!(
+            payloads,
+            [
+                &json!({"text": "hello", "is_partial": true}),
+                &json!({"text": "hello there", "is_partial": true}),
+                &json!({"text": "hello there how", "is_partial": true}),
+                &json!({"text": "hello there how are you", "is_partial": false}),
+                &json!({"text": "", "is_partial": false}),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_server_error_becomes_an_event() {
+        let session = session("en");
+        let events = RecordedEvents::default();
+        let error = json!({"type": "Error", "description": "Bad audio", "message": "x"});
+        assert!(!session.handle_text(&error.to_string(), &events));
+
+        let recorded = events.0.lock().unwrap();
+        assert_eq!(recorded[0].0, EVENT_ELEVENLABS_ERROR);
+        assert_eq!(recorded[0].1["error"], "Deepgram: Bad audio");
+        assert_eq!(recorded[0].1["code"], json!(ErrorCode::StreamingError));
+    }
+}
@@ -0,0 +1,177 @@
+use serde::Serialize;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Above this many DP cells the diff degrades to "everything replaced"
+const MAX_DIFF_CELLS: usize = 1_000_000;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffOpKind {
+    Equal,
+    Insert,
+    Delete,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct DiffOp {
+    pub op: DiffOpKind,
+    pub text: String,
+}
+
+/// Word-level diff between the raw transcript and the refined text
+#[derive(Debug, Clone, Serialize, PartialEq, Eq, Default)]
+pub struct TextDiff {
+    pub ops: Vec<DiffOp>,
+    /// Number of inserted plus deleted words/punctuation marks (whitespace ignored)
+    pub changes: usize,
+}
+
+impl TextDiff {
+    pub fn was_modified(&self) -> bool {
+        self.changes > 0
+    }
+
+    fn push(&mut self, op: DiffOpKind, token: &str) {
+        if op != DiffOpKind::Equal && !token.trim().is_empty() {
+            self.changes += 1;
+        }
+        match self.ops.last_mut() {
+            Some(last) if last.op == op => last.text.push_str(token),
+            _ => self.ops.push(DiffOp {
+                op,
+                text: token.to_string(),
+            }),
+        }
+    }
+}
+
+/// Diff two texts on word boundaries (words, punctuation and whitespace are separate tokens)
+pub fn word_diff(original: &str, refined: &str) -> TextDiff {
+    let a: Vec<&str> = original.split_word_bounds().collect();
+    let b: Vec<&str> = refined.split_word_bounds().collect();
+
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut diff = TextDiff::default();
+    for token in &a[..prefix] {
+        diff.push(DiffOpKind::Equal, token);
+    }
+
+    let (n, m) = (a_mid.len(), b_mid.len());
+    if n.saturating_mul(m) > MAX_DIFF_CELLS {
+        a_mid.iter().for_each(|t| diff.push(DiffOpKind::Delete, t));
+        b_mid.iter().for_each(|t| diff.push(DiffOpKind::Insert, t));
+    } else {
+        // lcs[i][j] = length of the longest common subsequence of a_mid[i..] and b_mid[j..]
+        let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lcs[i][j] = if a_mid[i] == b_mid[j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < m {
+            if i < n && j < m && a_mid[i] == b_mid[j] {
+                diff.push(DiffOpKind::Equal, a_mid[i]);
+                i += 1;
+                j += 1;
+            } else if j == m || (i < n && lcs[i + 1][j] >= lcs[i][j + 1]) {
+                diff.push(DiffOpKind::Delete, a_mid[i]);
+                i += 1;
+            } else {
+                diff.push(DiffOpKind::Insert, b_mid[j]);
+                j += 1;
+            }
+        }
+    }
+
+    for token in &a[a.len() - suffix..] {
+        diff.push(DiffOpKind::Equal, token);
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn op(op: DiffOpKind, text: &str) -> DiffOp {
+        DiffOp {
+            op,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_identical_texts_have_no_changes() {
+        let diff = word_diff("Привет, мир!", "Привет, мир!");
+        assert!(!diff.was_modified());
+        assert_eq!(diff.ops, vec![op(DiffOpKind::Equal, "Привет, мир!")]);
+    }
+
+    #[test]
+    fn test_cyrillic_word_replacement() {
+        let diff = word_diff("я пошёл в магазин", "я пошёл в аптеку");
+        assert_eq!(
+            diff.ops,
+            vec![
+                op(DiffOpKind::Equal, "я пошёл в "),
+                op(DiffOpKind::Delete, "магазин"),
+                op(DiffOpKind::Insert, "аптеку"),
+            ]
+        );
+        assert_eq!(diff.changes, 2);
+    }
+
+    #[test]
+    fn test_punctuation_only_changes() {
+        let diff = word_diff("привет как дела", "привет, как дела?");
+        assert_eq!(
+            diff.ops,
+            vec![
+                op(DiffOpKind::Equal, "привет"),
+                op(DiffOpKind::Insert, ","),
+                op(DiffOpKind::Equal, " как дела"),
+                op(DiffOpKind::Insert, "?"),
+            ]
+        );
+        assert_eq!(diff.changes, 2);
+    }
+
+    #[test]
+    fn test_ops_reconstruct_both_texts() {
+        let original = "ну короче мы завтра встречаемся в 10";
+        let refined = "Мы встречаемся завтра в 10:00.";
+        let diff = word_diff(original, refined);
+
+        let rebuild = |skip: DiffOpKind| -> String {
+            diff.ops
+                .iter()
+                .filter(|o| o.op != skip)
+                .map(|o| o.text.as_str())
+                .collect()
+        };
+        assert_eq!(rebuild(DiffOpKind::Insert), original);
+        assert_eq!(rebuild(DiffOpKind::Delete), refined);
+    }
+
+    #[test]
+    fn test_empty_inputs() {
+        assert_eq!(word_diff("", "").ops, Vec::new());
+        let diff = word_diff("", "hello");
+        assert_eq!(diff.ops, vec![op(DiffOpKind::Insert, "hello")]);
+        assert_eq!(diff.changes, 1);
+    }
+}
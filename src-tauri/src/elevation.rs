@@ -0,0 +1,99 @@
+/// Whether synthetic input would reach the focused window
+pub trait ElevationCheck: Send + Sync + 'static {
+    /// True if the focused window runs elevated and we don't: Windows (UIPI) then
+    /// discards our input without any error
+    fn input_blocked(&self) -> bool;
+}
+
+/// Checks the real foreground window; never blocks outside Windows
+pub struct ForegroundWindow;
+
+impl ElevationCheck for ForegroundWindow {
+    #[cfg(windows)]
+    fn input_blocked(&self) -> bool {
+        // If either check fails, type anyway; the worst case is the old behaviour
+        win32::foreground_elevated() == Some(true) && win32::current_elevated() == Some(false)
+    }
+
+    #[cfg(not(windows))]
+    fn input_blocked(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(windows)]
+mod win32 {
+    use std::ffi::c_void;
+
+    use windows::Win32::{
+        Foundation::{CloseHandle, ERROR_ACCESS_DENIED, HANDLE},
+        Security::{GetTokenInformation, TokenElevation, TOKEN_ELEVATION, TOKEN_QUERY},
+        System::Threading::{
+            GetCurrentProcess, OpenProcess, OpenProcessToken, PROCESS_QUERY_LIMITED_INFORMATION,
+        },
+        UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId},
+    };
+
+    /// Elevation of `process`. A non-elevated process may not open the token of an
+    /// elevated one, so access denied counts as elevated.
+    fn process_elevated(process: HANDLE) -> Option<bool> {
+        let mut token = HANDLE::default();
+        // SAFETY: `process` is a valid process handle and `token` a valid out pointer
+        if let Err(e) = unsafe { OpenProcessToken(process, TOKEN_QUERY, &mut token) } {
+            return (e.code() == ERROR_ACCESS_DENIED.to_hresult()).then_some(true);
+        }
+
+        let mut elevation = TOKEN_ELEVATION::default();
+        let mut returned = 0u32;
+        // SAFETY: the buffer is a TOKEN_ELEVATION of the size we pass; the token is
+        // closed exactly once below
+        let result = unsafe {
+            GetTokenInformation(
+                token,
+                TokenElevation,
+                Some(&mut elevation as *mut TOKEN_ELEVATION as *mut c_void),
+                std::mem::size_of::<TOKEN_ELEVATION>() as u32,
+                &mut returned,
+            )
+        };
+        unsafe {
+            let _ = CloseHandle(token);
+        }
+        result.ok()?;
+        Some(elevation.TokenIsElevated != 0)
+    }
+
+    pub fn current_elevated() -> Option<bool> {
+        // SAFETY: the pseudo handle of the current process needs no closing
+        process_elevated(unsafe { GetCurrentProcess() })
+    }
+
+    /// Elevation of the process owning the foreground window; None if there is none
+    pub fn foreground_elevated() -> Option<bool> {
+        // SAFETY: plain queries on a window handle that may be null or stale; both
+        // calls fail gracefully in that case
+        let pid = unsafe {
+            let hwnd = GetForegroundWindow();
+            if hwnd.is_invalid() {
+                return None;
+            }
+            let mut pid = 0u32;
+            GetWindowThreadProcessId(hwnd, Some(&mut pid));
+            pid
+        };
+        if pid == 0 {
+            return None;
+        }
+
+        // SAFETY: the handle is only used after a successful open and closed exactly once
+        let process = match unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) } {
+            Ok(process) => process,
+            Err(e) => return (e.code() == ERROR_ACCESS_DENIED.to_hresult()).then_some(true),
+        };
+        let elevated = process_elevated(process);
+        unsafe {
+            let _ = CloseHandle(process);
+        }
+        elevated
+    }
+}
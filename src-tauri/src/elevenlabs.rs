@@ -1,12 +1,12 @@
-use anyhow::{anyhow, Context, Result};
-use base64::Engine;
+use anyhow::{Context, Result};
 use futures::{SinkExt, StreamExt};
-use serde::{Deserialize, Serialize};
 use std::io::Cursor;
-use tokio_tungstenite::{
-    connect_async,
-    tungstenite::{http::Request, Message},
-};
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::{http::Request, Message};
+
+use crate::elevenlabs_protocol::{Inbound, Outbound};
+use crate::elevenlabs_streaming::connect_with_timeout;
+use crate::speech_to_text::{HttpStatusError, ProviderError};
 
 #[derive(Clone, Debug)]
 pub struct ElevenLabsTranscriptionRequest {
@@ -14,36 +14,47 @@ pub struct ElevenLabsTranscriptionRequest {
     pub audio_wav: Vec<u8>,
     #[allow(dead_code)]
     pub language: String,
+    /// Limit for the WebSocket handshake
+    pub connect_timeout: Duration,
 }
 
-#[derive(Clone)]
-pub struct ElevenLabsClient;
-
-#[derive(Serialize)]
-struct AudioChunkMessage {
-    message_type: String,
-    audio_base_64: String,
-    sample_rate: u32,
-    #[serde(default)]
-    commit: bool,
-}
+const USER_ENDPOINT: &str = "https://api.elevenlabs.io/v1/user";
+/// Speech-to-text models the app talks to; ElevenLabs has no endpoint listing them
+pub const MODELS: &[&str] = &["scribe_v2_realtime"];
 
-#[derive(Deserialize, Debug)]
-struct TranscriptMessage {
-    message_type: String,
-    #[serde(default)]
-    text: String,
+#[derive(Clone)]
+pub struct ElevenLabsClient {
+    http: reqwest::Client,
 }
 
 impl ElevenLabsClient {
     pub fn new() -> Result<Self> {
-        Ok(Self)
+        let http = reqwest::Client::builder()
+            .build()
+            .context("Failed to build HTTP client for ElevenLabs")?;
+        Ok(Self { http })
+    }
+
+    /// Cheap authenticated request that succeeds only if the key is accepted
+    pub async fn check_key(&self, api_key: &str) -> Result<()> {
+        let response = self
+            .http
+            .get(USER_ENDPOINT)
+            .header("xi-api-key", api_key)
+            .send()
+            .await
+            .context("ElevenLabs key check request failed")?;
+
+        if !response.status().is_success() {
+            return Err(HttpStatusError::new("ElevenLabs", response.status()).into());
+        }
+        Ok(())
     }
 
     /// Отправляет аудио на транскрипцию в ElevenLabs через WebSocket
     pub async fn transcribe(&self, job: ElevenLabsTranscriptionRequest) -> Result<String> {
         if job.api_key.trim().is_empty() {
-            return Err(anyhow!("ElevenLabs API key is missing"));
+            return Err(ProviderError::MissingApiKey("ElevenLabs").into_anyhow());
         }
 
         // Извлекаем аудио из WAV файла и получаем sample rate СРАЗУ
@@ -94,19 +105,20 @@ impl ElevenLabsClient {
             .body(())
             .context("Failed to build WebSocket request")?;
 
-        let (ws_stream, response) = match connect_async(request).await {
-            Ok(result) => {
-                tracing::info!(
-                    "[ElevenLabs] WebSocket connected successfully, status: {:?}",
-                    result.1.status()
-                );
-                result
-            }
-            Err(e) => {
-                tracing::error!("[ElevenLabs] Failed to connect to WebSocket: {:?}", e);
-                return Err(anyhow!("Failed to connect to ElevenLabs WebSocket: {}", e));
-            }
-        };
+        let (ws_stream, response) =
+            match connect_with_timeout("ElevenLabs", request, job.connect_timeout).await {
+                Ok(result) => {
+                    tracing::info!(
+                        "[ElevenLabs] WebSocket connected successfully, status: {:?}",
+                        result.1.status()
+                    );
+                    result
+                }
+                Err(e) => {
+                    tracing::error!("[ElevenLabs] Failed to connect to WebSocket: {:?}", e);
+                    return Err(e);
+                }
+            };
 
         tracing::info!(
             "[ElevenLabs] WebSocket response headers: {:?}",
@@ -116,33 +128,27 @@ impl ElevenLabsClient {
         let (mut write, mut read) = ws_stream.split();
 
         // Аудио уже извлечено выше (для определения audio_format)
-        // Кодируем аудио в base64
-        let audio_base64 = base64::engine::general_purpose::STANDARD.encode(&audio_data);
-        let audio_size = audio_base64.len();
-
-        // Отправляем аудиоблок с commit=true чтобы получить финальную транскрипцию
-        let message = AudioChunkMessage {
-            message_type: "input_audio_chunk".to_string(),
-            audio_base_64: audio_base64,
-            sample_rate,
-            commit: true,
-        };
-
-        let json =
-            serde_json::to_string(&message).context("Failed to serialize audio chunk message")?;
-
+        // Отправляем аудио кусками, commit=true на последнем - чтобы получить финальную транскрипцию
+        let chunks = Outbound::chunks(&audio_data, sample_rate, true);
+        let count = chunks.len();
         tracing::info!(
-            "[ElevenLabs] Sending audio chunk ({} bytes of base64, sample_rate: {})",
-            audio_size,
+            "[ElevenLabs] Sending {} bytes of audio in {} chunk(s), sample_rate: {}",
+            audio_data.len(),
+            count,
             sample_rate
         );
 
-        write
-            .send(Message::Text(json))
-            .await
-            .context("Failed to send audio chunk")?;
+        for (i, chunk) in chunks.iter().enumerate() {
+            let json = chunk
+                .to_json()
+                .context("Failed to serialize audio chunk message")?;
+            write
+                .send(Message::Text(json))
+                .await
+                .with_context(|| format!("Failed to send audio chunk {} of {}", i + 1, count))?;
+        }
 
-        tracing::info!("[ElevenLabs] Audio chunk sent, waiting for responses...");
+        tracing::info!("[ElevenLabs] Audio sent, waiting for responses...");
 
         // Читаем результаты
         let mut transcript = String::new();
@@ -155,26 +161,26 @@ impl ElevenLabsClient {
                 Message::Text(text) => {
                     tracing::debug!("[ElevenLabs] Raw message: {}", text);
 
-                    let response: TranscriptMessage = serde_json::from_str(&text)
-                        .context("Failed to parse transcript message")?;
-
-                    tracing::info!("[ElevenLabs] Message type: {}", response.message_type);
+                    let response =
+                        Inbound::parse(&text).context("Failed to parse transcript message")?;
 
-                    match response.message_type.as_str() {
-                        "session_started" => {
-                            tracing::info!("[ElevenLabs] Session started");
+                    match response {
+                        Inbound::SessionStarted(started) => {
+                            tracing::info!("[ElevenLabs] Session started: {}", started.session_id);
                         }
-                        "committed_transcript" | "committed_transcript_with_timestamps" => {
+                        Inbound::CommittedTranscript(_)
+                        | Inbound::CommittedTranscriptWithTimestamps(_) => {
+                            let text = response.committed_text().unwrap_or_default();
                             tracing::info!(
                                 "[ElevenLabs] Committed transcript received (length: {}): '{}'",
-                                response.text.len(),
-                                response.text
+                                text.len(),
+                                text
                             );
-                            if !response.text.is_empty() {
+                            if !text.is_empty() {
                                 if !transcript.is_empty() {
                                     transcript.push(' ');
                                 }
-                                transcript.push_str(&response.text);
+                                transcript.push_str(text);
                             } else {
                                 tracing::warn!("[ElevenLabs] Committed transcript is empty!");
                             }
@@ -184,19 +190,16 @@ impl ElevenLabsClient {
                             );
                             break;
                         }
-                        "partial_transcript" => {
+                        Inbound::PartialTranscript(partial) => {
                             // Игнорируем partial для финальной транскрипции
-                            tracing::debug!("[ElevenLabs] Partial transcript: {}", response.text);
+                            tracing::debug!("[ElevenLabs] Partial transcript: {}", partial.text);
                         }
-                        "error" | "auth_error" | "quota_exceeded_error" => {
-                            tracing::error!("[ElevenLabs] Error: {:?}", response);
-                            return Err(anyhow!("ElevenLabs API error: {:?}", response));
+                        Inbound::Error(error) => {
+                            tracing::error!("[ElevenLabs] Error: {:?}", error);
+                            return Err(error.into());
                         }
-                        _ => {
-                            tracing::debug!(
-                                "[ElevenLabs] Unknown message type: {}",
-                                response.message_type
-                            );
+                        Inbound::Unknown(message_type) => {
+                            tracing::debug!("[ElevenLabs] Unknown message type: {}", message_type);
                         }
                     }
                 }
@@ -226,58 +229,246 @@ impl ElevenLabsClient {
     }
 }
 
-/// Извлекает PCM аудиоданные из WAV файла и возвращает их вместе с sample rate
-fn extract_pcm_from_wav(wav_data: &[u8]) -> Result<(Vec<u8>, u32)> {
-    let mut cursor = Cursor::new(wav_data);
-    let reader = hound::WavReader::new(&mut cursor).context("Failed to read WAV file")?;
+/// WAV data chunks larger than this are refused before anything is decoded: over
+/// 20 minutes of 48 kHz stereo PCM16, far past what a dictation produces
+const MAX_WAV_DATA_BYTES: u64 = 256 * 1024 * 1024;
+/// Highest sample rate the batch path accepts
+const MAX_WAV_SAMPLE_RATE: u32 = 192_000;
 
-    let spec = reader.spec();
-    let sample_rate = spec.sample_rate;
+/// Why WAV bytes can't be transcribed
+#[derive(Debug, thiserror::Error)]
+pub enum WavError {
+    #[error("Invalid WAV: {0}")]
+    Malformed(String),
+
+    #[error("WAV data is too large: {bytes} bytes (at most {MAX_WAV_DATA_BYTES})")]
+    TooLarge { bytes: u64 },
+
+    #[error("WAV data chunk declares {declared} bytes but only {available} are present")]
+    Truncated { declared: u64, available: u64 },
+
+    #[error("Unsupported WAV sample rate: {0} Hz")]
+    SampleRate(u32),
+
+    #[error("Unsupported WAV channel count: {0} (mono or stereo only)")]
+    Channels(u16),
 
+    #[error("Unsupported WAV format: {format:?} with {bits} bits per sample")]
+    Format {
+        format: hound::SampleFormat,
+        bits: u16,
+    },
+}
+
+impl From<hound::Error> for WavError {
+    fn from(err: hound::Error) -> Self {
+        Self::Malformed(err.to_string())
+    }
+}
+
+/// Offset and declared length of the `data` chunk, found by walking the RIFF chunks
+fn find_data_chunk(wav_data: &[u8]) -> Result<(usize, u64), WavError> {
+    if wav_data.len() < 12 || &wav_data[0..4] != b"RIFF" || &wav_data[8..12] != b"WAVE" {
+        return Err(WavError::Malformed("missing RIFF/WAVE header".to_string()));
+    }
+    let mut offset = 12usize;
+    while let Some(header) = wav_data.get(offset..offset + 8) {
+        let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        let body = offset + 8;
+        if &header[0..4] == b"data" {
+            return Ok((body, u64::from(size)));
+        }
+        // Chunks are padded to an even length
+        offset = body
+            .checked_add(size as usize + (size as usize & 1))
+            .ok_or_else(|| WavError::Malformed("chunk size overflows".to_string()))?;
+    }
+    Err(WavError::Malformed("no data chunk".to_string()))
+}
+
+/// Reader over WAV bytes whose headers were checked against the limits above, so
+/// decoding neither allocates without bound nor fails halfway through
+fn open_wav(wav_data: &[u8]) -> Result<hound::WavReader<Cursor<&[u8]>>, WavError> {
+    let (data_offset, declared) = find_data_chunk(wav_data)?;
+    if declared > MAX_WAV_DATA_BYTES {
+        return Err(WavError::TooLarge { bytes: declared });
+    }
+    let available = (wav_data.len() - data_offset) as u64;
+    if declared > available {
+        return Err(WavError::Truncated {
+            declared,
+            available,
+        });
+    }
+
+    let reader = hound::WavReader::new(Cursor::new(wav_data))?;
+    let spec = reader.spec();
     tracing::info!(
         "[ElevenLabs] WAV spec - sample_rate: {}, channels: {}, bits_per_sample: {}",
         spec.sample_rate,
         spec.channels,
         spec.bits_per_sample
     );
+    if spec.sample_rate == 0 || spec.sample_rate > MAX_WAV_SAMPLE_RATE {
+        return Err(WavError::SampleRate(spec.sample_rate));
+    }
+    if spec.channels > 2 {
+        return Err(WavError::Channels(spec.channels));
+    }
+    Ok(reader)
+}
+
+/// Check that WAV bytes are something the transcription pipeline can decode
+pub fn check_wav(wav_data: &[u8]) -> Result<(), WavError> {
+    open_wav(wav_data).map(|_| ())
+}
+
+/// Извлекает PCM аудиоданные из WAV файла и возвращает их вместе с sample rate.
+/// The result is mono PCM16: stereo is downmixed, other sample formats converted.
+fn extract_pcm_from_wav(wav_data: &[u8]) -> Result<(Vec<u8>, u32), WavError> {
+    let reader = open_wav(wav_data)?;
+    let spec = reader.spec();
 
     // Для ElevenLabs нужны данные в формате PCM16 (16-bit signed integers, little-endian)
-    let mut samples = Vec::new();
-    let reader =
-        hound::WavReader::new(Cursor::new(wav_data)).context("Failed to re-create WAV reader")?;
-
-    match (spec.sample_format, spec.bits_per_sample) {
-        (hound::SampleFormat::Int, 16) => {
-            // PCM16 - просто копируем сэмплы как i16
-            for sample in reader.into_samples::<i16>() {
-                let s = sample.context("Failed to read sample")?;
-                samples.extend_from_slice(&s.to_le_bytes());
-            }
-        }
-        (hound::SampleFormat::Int, 32) => {
-            // Конвертируем i32 в i16
-            for sample in reader.into_samples::<i32>() {
-                let s = sample.context("Failed to read sample")?;
-                let s16 = (s >> 16) as i16; // Берем старшие 16 бит
-                samples.extend_from_slice(&s16.to_le_bytes());
-            }
-        }
-        (hound::SampleFormat::Float, _) => {
-            // Конвертируем float в i16
-            for sample in reader.into_samples::<f32>() {
-                let s = sample.context("Failed to read sample")?;
-                let s16 = (s * 32767.0).clamp(-32768.0, 32767.0) as i16;
-                samples.extend_from_slice(&s16.to_le_bytes());
-            }
+    let samples: Vec<i16> = match (spec.sample_format, spec.bits_per_sample) {
+        // PCM16 - просто копируем сэмплы как i16
+        (hound::SampleFormat::Int, 16) => reader.into_samples::<i16>().collect::<Result<_, _>>()?,
+        // Конвертируем i32 в i16, берем старшие 16 бит
+        (hound::SampleFormat::Int, 32) => reader
+            .into_samples::<i32>()
+            .map(|s| s.map(|s| (s >> 16) as i16))
+            .collect::<Result<_, _>>()?,
+        // Конвертируем float в i16
+        (hound::SampleFormat::Float, _) => reader
+            .into_samples::<f32>()
+            .map(|s| s.map(|s| (s * 32767.0).clamp(-32768.0, 32767.0) as i16))
+            .collect::<Result<_, _>>()?,
+        (format, bits) => return Err(WavError::Format { format, bits }),
+    };
+
+    let pcm = if spec.channels == 2 {
+        samples
+            .chunks_exact(2)
+            .flat_map(|frame| {
+                let mono = (i32::from(frame[0]) + i32::from(frame[1])) / 2;
+                (mono as i16).to_le_bytes()
+            })
+            .collect()
+    } else {
+        samples.iter().flat_map(|s| s.to_le_bytes()).collect()
+    };
+    Ok((pcm, spec.sample_rate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Hand-made PCM16 WAV: a 16-byte fmt chunk, then a data chunk declaring
+    /// `declared` bytes followed by `data`
+    fn wav_bytes(sample_rate: u32, channels: u16, declared: u32, data: &[u8]) -> Vec<u8> {
+        let block_align = channels * 2;
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&declared.saturating_add(36).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&channels.to_le_bytes());
+        wav.extend_from_slice(&sample_rate.to_le_bytes());
+        wav.extend_from_slice(&(sample_rate * u32::from(block_align)).to_le_bytes());
+        wav.extend_from_slice(&block_align.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&declared.to_le_bytes());
+        wav.extend_from_slice(data);
+        wav
+    }
+
+    fn pcm(samples: &[i16]) -> Vec<u8> {
+        samples.iter().flat_map(|s| s.to_le_bytes()).collect()
+    }
+
+    fn valid(sample_rate: u32, channels: u16, samples: &[i16]) -> Vec<u8> {
+        let data = pcm(samples);
+        wav_bytes(sample_rate, channels, data.len() as u32, &data)
+    }
+
+    #[test]
+    fn test_mono_pcm16_passes_through() {
+        let samples = [0, 1000, -1000, i16::MAX, i16::MIN];
+        let (audio, rate) = extract_pcm_from_wav(&valid(16_000, 1, &samples)).unwrap();
+        assert_eq!(rate, 16_000);
+        assert_eq!(audio, pcm(&samples));
+    }
+
+    #[test]
+    fn test_stereo_is_downmixed() {
+        let (audio, rate) =
+            extract_pcm_from_wav(&valid(48_000, 2, &[1000, 3000, -2000, -4000])).unwrap();
+        assert_eq!(rate, 48_000);
+        assert_eq!(audio, pcm(&[2000, -3000]));
+    }
+
+    #[test]
+    fn test_truncated_header_is_rejected() {
+        let wav = valid(16_000, 1, &[1, 2, 3]);
+        for len in [0, 4, 11, 20, 40] {
+            assert!(
+                matches!(
+                    extract_pcm_from_wav(&wav[..len]),
+                    Err(WavError::Malformed(_))
+                ),
+                "{len} bytes"
+            );
         }
-        _ => {
-            return Err(anyhow!(
-                "Unsupported WAV format: {:?} with {} bits per sample",
-                spec.sample_format,
-                spec.bits_per_sample
-            ));
+        assert!(matches!(
+            check_wav(b"RIFF\0\0\0\0AVI LIST"),
+            Err(WavError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn test_declared_length_beyond_the_data_is_rejected() {
+        let wav = wav_bytes(16_000, 1, 1_000, &pcm(&[1, 2, 3]));
+        assert!(matches!(
+            extract_pcm_from_wav(&wav),
+            Err(WavError::Truncated {
+                declared: 1_000,
+                available: 6
+            })
+        ));
+    }
+
+    #[test]
+    fn test_huge_data_chunk_is_rejected_before_reading() {
+        let wav = wav_bytes(16_000, 1, u32::MAX - 1, &pcm(&[1, 2]));
+        assert!(matches!(
+            extract_pcm_from_wav(&wav),
+            Err(WavError::TooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn test_absurd_sample_rates_are_rejected() {
+        for rate in [0, 1_000_000] {
+            assert!(
+                matches!(
+                    extract_pcm_from_wav(&valid(rate, 1, &[1, 2])),
+                    Err(WavError::SampleRate(r)) if r == rate
+                ),
+                "{rate} Hz"
+            );
         }
     }
 
-    Ok((samples, sample_rate))
+    #[test]
+    fn test_more_than_two_channels_are_rejected() {
+        let wav = valid(16_000, 3, &[1, 2, 3]);
+        assert!(matches!(
+            extract_pcm_from_wav(&wav),
+            Err(WavError::Channels(3))
+        ));
+        assert!(check_wav(&valid(16_000, 2, &[1, 2])).is_ok());
+    }
 }
@@ -1,28 +1,37 @@
-use crate::core::state::{AppState, NewHistoryEntry};
-use tauri::{AppHandle, Emitter, Listener, Manager};
+use crate::audio_stream::AudioStats;
+use crate::core::{
+    events::{
+        emit_partial, StreamingTranscriptPayload, EVENT_ELEVENLABS_ERROR,
+        EVENT_ELEVENLABS_TRANSCRIPT,
+    },
+    post_command,
+    session::{PendingCommit, SessionContext},
+    speak,
+    state::{AppState, NewHistoryEntry, TimedSegment},
+    timings::Timings,
+    transcript_log,
+};
+use crate::messages::{self, Msg};
+use crate::text_utils::TextStats;
+use tauri::{AppHandle, Listener, Manager};
 
 /// Настраивает обработчики событий для ElevenLabs streaming
 pub fn setup_elevenlabs_event_handlers(app: &AppHandle) {
     let app_clone = app.clone();
 
     // Обработчик транскрипций
-    app.listen("elevenlabs://transcript", move |event| {
+    app.listen(EVENT_ELEVENLABS_TRANSCRIPT, move |event| {
         let app = app_clone.clone();
 
         // Парсим payload
-        if let Ok(payload) = serde_json::from_str::<TranscriptEventPayload>(event.payload()) {
+        if let Ok(payload) = serde_json::from_str::<StreamingTranscriptPayload>(event.payload()) {
             // Обрабатываем partial транскрипции - показываем в UI
             if payload.is_partial {
                 tracing::debug!("[ElevenLabs Handler] Partial transcript: {}", payload.text);
 
                 // Отправляем partial событие в UI для отображения в реальном времени
-                let _ = app.emit(
-                    "transcription://partial",
-                    serde_json::json!({
-                        "text": payload.text
-                    }),
-                );
-                append_transcript_log(&app, "partial", &payload.text);
+                emit_partial(&app, &payload.text);
+                transcript_log::log_partial(&app, &payload.text);
                 return;
             }
 
@@ -33,7 +42,8 @@ pub fn setup_elevenlabs_event_handlers(app: &AppHandle) {
 
             // Запускаем обработку в отдельной задаче
             tauri::async_runtime::spawn(async move {
-                if let Err(e) = process_transcript(&app, payload.text).await {
+                let words = payload.words.unwrap_or_default();
+                if let Err(e) = process_transcript(&app, payload.text, &words).await {
                     tracing::error!("[ElevenLabs Handler] Failed to process transcript: {}", e);
                 }
             });
@@ -43,68 +53,37 @@ pub fn setup_elevenlabs_event_handlers(app: &AppHandle) {
     tracing::info!("[ElevenLabs Handler] Event handlers registered");
 }
 
-fn append_transcript_log(app: &AppHandle, tag: &str, text: &str) {
-    let handle = app.clone();
-    let tag = tag.to_string();
-    let text = text.to_string();
-    tauri::async_runtime::spawn_blocking(move || {
-        let resolver = handle.path();
-        let dir = match resolver.app_log_dir() {
-            Ok(d) => d,
-            Err(e) => {
-                tracing::warn!("[Transcript] Failed to get log directory: {}", e);
-                return;
-            }
-        };
-
-        if let Err(e) = std::fs::create_dir_all(&dir) {
-            tracing::warn!(
-                "[Transcript] Failed to create log directory {:?}: {}",
-                dir,
-                e
-            );
-            return;
-        }
-
-        let path = dir.join("transcripts.log");
-        let mut file = match std::fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&path)
-        {
-            Ok(f) => f,
-            Err(e) => {
-                tracing::warn!("[Transcript] Failed to open {:?}: {}", path, e);
-                return;
-            }
-        };
-
-        use std::io::Write;
-        if let Err(e) = writeln!(file, "[{}] {}", tag, text) {
-            tracing::warn!("[Transcript] Failed to write to {:?}: {}", path, e);
-        }
-    });
-}
-
 // Also handle ElevenLabs errors to update UI status
 pub fn setup_elevenlabs_error_handlers(app: &AppHandle) {
     let app_err = app.clone();
-    app.listen("elevenlabs://error", move |event| {
+    app.listen(EVENT_ELEVENLABS_ERROR, move |event| {
         let app = app_err.clone();
-        use crate::core::events::{emit_status, StatusPhase};
+        use crate::core::events::{emit_error_code, emit_status, ErrorCode, StatusPhase};
         use std::sync::atomic::Ordering;
 
         tracing::error!("[ElevenLabs Handler] Error event: {}", event.payload());
 
         let state = app.state::<AppState>();
         state.is_transcribing().store(false, Ordering::SeqCst);
+        // The commits are over; keeps the watchdog from reporting them a second time
+        if let Ok(mut guard) = state.pending_commits().lock() {
+            guard.clear();
+        }
 
-        emit_status(&app, StatusPhase::Error, Some("Streaming error"));
+        let code = serde_json::from_str::<ErrorEventPayload>(event.payload())
+            .map(|payload| payload.code)
+            .unwrap_or(ErrorCode::StreamingError);
+        emit_error_code(
+            &app,
+            code,
+            Some("elevenlabs"),
+            messages::text(Msg::StreamingError),
+        );
         // Transition back to Idle after error for UI to recover
         emit_status(
             &app,
             StatusPhase::Idle,
-            Some("Ready for next transcription"),
+            Some(messages::text(Msg::ReadyForNext)),
         );
         tracing::info!("[ElevenLabs Handler] Error handled, state reset to Idle");
     });
@@ -112,82 +91,144 @@ pub fn setup_elevenlabs_error_handlers(app: &AppHandle) {
     tracing::info!("[ElevenLabs Handler] Error handlers registered");
 }
 
+/// The part of `StreamingErrorPayload` the handler reads
 #[derive(serde::Deserialize)]
-struct TranscriptEventPayload {
-    text: String,
-    is_partial: bool,
+struct ErrorEventPayload {
+    code: crate::core::events::ErrorCode,
 }
 
 /// Обрабатывает полученную транскрипцию и выводит текст
-async fn process_transcript(app: &AppHandle, text: String) -> anyhow::Result<()> {
-    use crate::core::events::{emit_complete, emit_status, StatusPhase};
+async fn process_transcript(
+    app: &AppHandle,
+    text: String,
+    words: &[crate::elevenlabs_protocol::Word],
+) -> anyhow::Result<()> {
+    use crate::core::events::{
+        emit_complete, emit_error_code, emit_segment, emit_status, CompletePayload, ErrorCode,
+        StatusPhase,
+    };
+    use crate::core::transcription::{
+        collected_message, completion_warning, deliver_or_collect, prepare_for_delivery,
+        refinement_progress,
+    };
+    use crate::settings::SegmentHistory;
     use std::sync::atomic::Ordering;
     use tauri::Manager;
-    use tauri_plugin_clipboard_manager::ClipboardExt;
 
     let state = app.state::<AppState>();
-    let mut settings = (*state.current_settings().await).clone();
 
-    // Check if force_translate was requested for the current session
-    let session_id = state.current_session_id();
-    if state.take_force_translate(session_id) {
-        settings.auto_translate = true;
-        tracing::info!(
-            "[ElevenLabs Handler] Force translate enabled for session {}",
-            session_id
-        );
-    }
+    // Transcripts arrive in commit order. Timings are measured from the commit; a
+    // commit that wasn't recorded starts now and uses the default session options.
+    let PendingCommit {
+        session,
+        mut timings,
+        recorded,
+        continues,
+        audio,
+    } = state
+        .pending_commits()
+        .lock()
+        .ok()
+        .and_then(|mut guard| guard.pop_front())
+        .unwrap_or_else(|| PendingCommit {
+            session: SessionContext::default(),
+            timings: Timings::start(),
+            recorded: None,
+            continues: false,
+            audio: AudioStats::default(),
+        });
+    timings.mark_transcribed();
+    // Only the first segment waited for the connection
+    timings.set_preconnect_saved(session.preconnect_saved.filter(|_| session.segment == 0));
+
+    let mut settings = (*state.current_settings().await).clone();
+    session.apply(&mut settings);
 
     // Store original text before LLM processing for history
     let original_text = text.clone();
 
-    // Применяем LLM обработку если нужно (reusing clients from AppState)
-    let final_text = if settings.requires_llm() {
+    // Применяем LLM обработку если нужно (reusing clients from AppState).
+    // A segment is processed while the dictation goes on, so the status stays Recording.
+    if settings.requires_llm() && !continues {
         tracing::info!("[ElevenLabs Handler] Applying LLM processing...");
-        emit_status(app, StatusPhase::Transcribing, Some("Applying LLM..."));
-
-        let service = state.transcription();
-        match service.refine(&settings, original_text.clone()).await {
-            Ok(refined) => refined,
-            Err(e) => {
-                tracing::error!("[ElevenLabs Handler] LLM processing failed: {}", e);
-                original_text.clone() // Используем оригинальный текст
+        emit_status(
+            app,
+            StatusPhase::Transcribing,
+            Some(messages::text(Msg::ApplyingLlm)),
+        );
+    }
+
+    let service = state.transcription();
+    let context = state
+        .refinement_context(settings.refinement_context_entries)
+        .await;
+    // Fallback to the raw text is handled by the service according to llm_failure_policy
+    let refinement = match service
+        .apply_refinement(
+            &settings,
+            original_text.clone(),
+            &context,
+            &refinement_progress(app),
+        )
+        .await
+    {
+        Ok(refinement) => refinement,
+        Err(e) => {
+            tracing::error!("[ElevenLabs Handler] LLM processing failed: {}", e);
+            emit_error_code(
+                app,
+                ErrorCode::RefinementFailed,
+                Some(settings.llm_provider.id()),
+                &e.to_string(),
+            );
+            if !continues {
+                state.is_transcribing().store(false, Ordering::SeqCst);
+                emit_status(
+                    app,
+                    StatusPhase::Idle,
+                    Some(messages::text(Msg::ReadyForNext)),
+                );
             }
+            return Err(e);
         }
-    } else {
-        original_text.clone()
     };
+    timings.mark_refined();
+    let final_text = refinement.text;
+    let llm_applied = refinement.llm_applied;
 
     let trimmed = final_text.trim().to_string();
-
-    // Copy to clipboard if enabled (ALWAYS, not just when simulate_typing is off)
-    if settings.copy_to_clipboard && !trimmed.is_empty() {
-        if let Err(e) = app.clipboard().write_text(&trimmed) {
-            tracing::error!("[ElevenLabs Handler] Failed to copy to clipboard: {}", e);
-        } else {
-            tracing::info!("[ElevenLabs Handler] Text copied to clipboard");
-        }
+    let delivered = prepare_for_delivery(&settings, &trimmed);
+    let text_stats = TextStats::measure(&delivered, recorded);
+
+    // Clipboard and typing take turns with the upload path, so simultaneous results
+    // never interleave
+    // A segment is delivered while the dictation goes on, so the status stays Recording
+    if !continues {
+        emit_status(app, StatusPhase::Delivering, None);
     }
-
-    // Выводим текст через эмуляцию ввода если включено
-    if settings.simulate_typing && !trimmed.is_empty() {
-        tracing::info!("[ElevenLabs Handler] Typing text character by character");
-        let keyboard = state.transcription().keyboard();
-        let text_clone = trimmed.clone();
-
-        if let Err(e) =
-            tauri::async_runtime::spawn_blocking(move || keyboard.type_text(&text_clone))
-                .await
-                .map_err(|e| anyhow::anyhow!("Task join error: {}", e))?
-        {
-            tracing::error!("[ElevenLabs Handler] Failed to type text: {}", e);
-        }
+    let (delivery, collected) = deliver_or_collect(app, &settings, delivered.clone()).await;
+
+    timings.mark_delivered();
+    let timings = timings.report();
+
+    transcript_log::log_committed(app, &trimmed);
+    post_command::spawn(
+        app,
+        &settings,
+        &delivered,
+        refinement.source_language.as_deref(),
+    );
+    // Not while the dictation goes on: the microphone would pick it up
+    if !continues {
+        speak::speak_result(app, &settings, &delivered);
     }
 
-    append_transcript_log(app, "committed", &trimmed);
+    let text_diff = llm_applied.then(|| crate::diff::word_diff(original_text.trim(), &trimmed));
+    let translation = session.translation(&settings, llm_applied);
 
-    // Save to history (only non-empty results)
-    if !trimmed.is_empty() {
+    // Save to history (only non-empty results, unless history is disabled). A
+    // session's utterances become one entry when it ends.
+    if settings.history_enabled && !trimmed.is_empty() && collected.is_none() {
         let translated_text = if settings.auto_translate && trimmed != original_text {
             Some(trimmed.clone())
         } else {
@@ -195,45 +236,91 @@ async fn process_transcript(app: &AppHandle, text: String) -> anyhow::Result<()>
         };
 
         // Determine LLM provider if LLM was used
-        let llm_provider_used = if settings.requires_llm() {
+        let llm_provider_used = if llm_applied {
             Some(format!("{:?}", settings.llm_provider).to_lowercase())
         } else {
             None
         };
 
         // Check if custom instructions were used
-        let custom_instructions_used =
-            settings.use_custom_instructions && !settings.custom_instructions.trim().is_empty();
-
-        state
-            .add_history_entry(NewHistoryEntry {
-                original: if translated_text.is_some() {
-                    original_text
-                } else {
-                    trimmed.clone()
-                },
-                translated: translated_text,
-                source_language: None, // TODO: detect from transcription
-                target_language: if settings.auto_translate {
-                    Some(settings.target_language.clone())
-                } else {
-                    None
-                },
-                transcription_provider: Some("elevenlabs".to_string()),
-                llm_provider: llm_provider_used,
-                custom_instructions_used,
-            })
-            .await;
-        tracing::info!("[ElevenLabs Handler] Added to history");
+        let custom_instructions_used = settings.active_instructions().is_some();
+
+        let entry = NewHistoryEntry {
+            original: if translated_text.is_some() {
+                original_text
+            } else {
+                trimmed.clone()
+            },
+            translated: translated_text,
+            source_language: refinement.source_language,
+            translation: translation.clone(),
+            transcription_provider: Some("elevenlabs".to_string()),
+            llm_provider: llm_provider_used,
+            custom_instructions_used,
+            refinement_failed: refinement.refinement_failed,
+            combined_translation: false,
+            change_count: text_diff.as_ref().map_or(0, |d| d.changes),
+            latency_ms: Some(timings.total_ms),
+            stats: text_stats,
+            segments: TimedSegment::sentences(words),
+            capture: session.capture.clone(),
+        };
+        // A dictation committed in segments is one entry or one per segment
+        let segmented = continues || session.segment > 0;
+        let added = if segmented && settings.segment_history == SegmentHistory::Combined {
+            state
+                .add_combined_history_entry(session.id, entry, recorded)
+                .await
+        } else {
+            state.add_history_entry(entry).await
+        };
+        if added.is_some() {
+            tracing::info!("[ElevenLabs Handler] Added to history");
+        }
+    }
+
+    if continues {
+        emit_segment(app, &delivered, &text_stats, delivery.elevated_target);
+        return Ok(());
     }
 
     // Сбрасываем флаг транскрипции
     state.is_transcribing().store(false, Ordering::SeqCst);
 
     // Emit success status BEFORE complete (for overlay to show final text)
-    emit_status(app, StatusPhase::Success, None);
-    emit_complete(app, &trimmed);
-    emit_status(app, StatusPhase::Idle, Some("Ready for next transcription"));
+    let message = if delivery.elevated_target {
+        Some(messages::text(Msg::ElevatedTarget).to_string())
+    } else {
+        completion_warning(
+            &settings,
+            refinement.truncated,
+            refinement.refinement_failed,
+        )
+        .or_else(|| {
+            audio
+                .incomplete()
+                .then(|| messages::text(Msg::LostAudio).to_string())
+        })
+        .or_else(|| collected_message(&settings, collected))
+    };
+    emit_status(app, StatusPhase::Success, message.as_deref());
+    emit_complete(
+        app,
+        CompletePayload {
+            text: &delivered,
+            diff: text_diff.as_ref(),
+            timings: Some(&timings),
+            stats: &text_stats,
+            elevated_target: delivery.elevated_target,
+            translation: &translation,
+            capture: session.capture.as_ref(),
+        },
+    );
+    emit_status(
+        app,
+        StatusPhase::Idle,
+        Some(messages::text(Msg::ReadyForNext)),
+    );
 
     Ok(())
 }
@@ -0,0 +1,463 @@
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::core::events::ErrorCode;
+
+/// Kinds of error messages the server sends, by their `message_type`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServerErrorKind {
+    Error,
+    AuthError,
+    /// Older servers send `quota_exceeded_error`
+    #[serde(alias = "quota_exceeded_error")]
+    QuotaExceeded,
+    RateLimited,
+    CommitThrottled,
+    InputError,
+    TranscriberError,
+    QueueOverflow,
+    ResourceExhausted,
+    SessionTimeLimitExceeded,
+    ChunkSizeExceeded,
+    InsufficientAudioActivity,
+    UnacceptedTerms,
+}
+
+impl ServerErrorKind {
+    fn from_message_type(message_type: &str) -> Option<Self> {
+        Self::deserialize(Value::String(message_type.to_string())).ok()
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::AuthError => "auth_error",
+            Self::QuotaExceeded => "quota_exceeded",
+            Self::RateLimited => "rate_limited",
+            Self::CommitThrottled => "commit_throttled",
+            Self::InputError => "input_error",
+            Self::TranscriberError => "transcriber_error",
+            Self::QueueOverflow => "queue_overflow",
+            Self::ResourceExhausted => "resource_exhausted",
+            Self::SessionTimeLimitExceeded => "session_time_limit_exceeded",
+            Self::ChunkSizeExceeded => "chunk_size_exceeded",
+            Self::InsufficientAudioActivity => "insufficient_audio_activity",
+            Self::UnacceptedTerms => "unaccepted_terms",
+        }
+    }
+
+    pub fn error_code(self) -> ErrorCode {
+        match self {
+            Self::AuthError | Self::UnacceptedTerms => ErrorCode::AuthFailed,
+            Self::QuotaExceeded => ErrorCode::QuotaExceeded,
+            Self::RateLimited | Self::CommitThrottled => ErrorCode::RateLimited,
+            _ => ErrorCode::StreamingError,
+        }
+    }
+}
+
+/// An error message from the server
+#[derive(Debug, Clone, PartialEq, Serialize, thiserror::Error)]
+#[error("ElevenLabs {}: {message}", .kind.as_str())]
+pub struct ServerError {
+    pub kind: ServerErrorKind,
+    /// Human-readable description (`error` field, `message` on some kinds)
+    pub message: String,
+    /// Any other fields the server attached
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    pub details: Map<String, Value>,
+}
+
+impl ServerError {
+    pub fn error_code(&self) -> ErrorCode {
+        self.kind.error_code()
+    }
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    #[serde(default, alias = "message")]
+    error: String,
+    #[serde(flatten)]
+    details: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SessionStarted {
+    pub session_id: String,
+    /// The session options as the server applied them
+    #[serde(default)]
+    pub config: Option<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Transcript {
+    #[serde(default)]
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TimestampedTranscript {
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub language_code: Option<String>,
+    #[serde(default)]
+    pub words: Vec<Word>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WordKind {
+    #[default]
+    Word,
+    Spacing,
+    AudioEvent,
+    #[serde(other)]
+    Other,
+}
+
+/// A word (or the space between words) of a timestamped transcript; times in seconds
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Word {
+    pub text: String,
+    #[serde(default)]
+    pub start: f64,
+    #[serde(default)]
+    pub end: f64,
+    #[serde(default, rename = "type")]
+    pub kind: WordKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speaker_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logprob: Option<f64>,
+}
+
+/// A message from the server
+#[derive(Debug, Clone, PartialEq)]
+pub enum Inbound {
+    SessionStarted(SessionStarted),
+    PartialTranscript(Transcript),
+    CommittedTranscript(Transcript),
+    CommittedTranscriptWithTimestamps(TimestampedTranscript),
+    Error(ServerError),
+    /// A `message_type` this client doesn't know; ignored
+    Unknown(String),
+}
+
+impl Inbound {
+    /// Fails on invalid JSON, a missing `message_type` or a known message with
+    /// malformed fields; unknown message types parse as `Unknown`
+    pub fn parse(text: &str) -> serde_json::Result<Self> {
+        let mut value: Value = serde_json::from_str(text)?;
+        let message_type = match value
+            .as_object_mut()
+            .and_then(|object| object.remove("message_type"))
+        {
+            Some(Value::String(message_type)) => message_type,
+            _ => return Err(serde::de::Error::missing_field("message_type")),
+        };
+
+        let message = match message_type.as_str() {
+            "session_started" => Self::SessionStarted(serde_json::from_value(value)?),
+            "partial_transcript" => Self::PartialTranscript(serde_json::from_value(value)?),
+            "committed_transcript" => Self::CommittedTranscript(serde_json::from_value(value)?),
+            "committed_transcript_with_timestamps" => {
+                Self::CommittedTranscriptWithTimestamps(serde_json::from_value(value)?)
+            }
+            other => match ServerErrorKind::from_message_type(other) {
+                Some(kind) => {
+                    let body: ErrorBody = serde_json::from_value(value)?;
+                    Self::Error(ServerError {
+                        kind,
+                        message: body.error,
+                        details: body.details,
+                    })
+                }
+                None => Self::Unknown(message_type),
+            },
+        };
+        Ok(message)
+    }
+
+    /// Text of a committed transcript, with or without timestamps
+    pub fn committed_text(&self) -> Option<&str> {
+        match self {
+            Self::CommittedTranscript(transcript) => Some(&transcript.text),
+            Self::CommittedTranscriptWithTimestamps(transcript) => Some(&transcript.text),
+            _ => None,
+        }
+    }
+}
+
+/// Most PCM put into one `input_audio_chunk`. Base64 grows it by a third, and some
+/// proxies reject text frames of a few MB, so longer audio goes out in several.
+pub const MAX_AUDIO_CHUNK_BYTES: usize = 512 * 1024;
+
+/// A message to the server
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "message_type", rename_all = "snake_case")]
+pub enum Outbound {
+    /// PCM audio; `commit` asks for a committed transcript of everything sent so far
+    InputAudioChunk {
+        audio_base_64: String,
+        sample_rate: u32,
+        commit: bool,
+    },
+}
+
+impl Outbound {
+    pub fn audio(pcm: &[u8], sample_rate: u32) -> Self {
+        Self::audio_chunk(pcm, sample_rate, false)
+    }
+
+    /// Audio (may be empty) followed by a commit
+    pub fn commit(pcm: &[u8], sample_rate: u32) -> Self {
+        Self::audio_chunk(pcm, sample_rate, true)
+    }
+
+    /// `pcm` as messages of at most `MAX_AUDIO_CHUNK_BYTES` each; with `commit` the
+    /// last one carries the flag
+    pub fn chunks(pcm: &[u8], sample_rate: u32, commit: bool) -> Vec<Self> {
+        let pieces = split_pcm(pcm, MAX_AUDIO_CHUNK_BYTES);
+        let last = pieces.len() - 1;
+        pieces
+            .into_iter()
+            .enumerate()
+            .map(|(i, piece)| Self::audio_chunk(piece, sample_rate, commit && i == last))
+            .collect()
+    }
+
+    fn audio_chunk(pcm: &[u8], sample_rate: u32, commit: bool) -> Self {
+        Self::InputAudioChunk {
+            audio_base_64: base64::engine::general_purpose::STANDARD.encode(pcm),
+            sample_rate,
+            commit,
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+}
+
+/// `pcm` cut into pieces of at most `max_bytes`, never inside a 16-bit sample; at
+/// least one, which is empty when there is no audio
+fn split_pcm(pcm: &[u8], max_bytes: usize) -> Vec<&[u8]> {
+    let piece_len = (max_bytes & !1).max(2);
+    let mut pieces: Vec<&[u8]> = pcm.chunks(piece_len).collect();
+    if pieces.is_empty() {
+        pieces.push(&[]);
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Captured from the realtime endpoint
+    const SESSION_STARTED: &str = r#"{"message_type":"session_started","session_id":"0b8c5a6e2f1d4c7a","config":{"sample_rate":16000,"audio_format":"pcm_16000","language_code":"ru","model_id":"scribe_v2_realtime","vad_commit_strategy":false}}"#;
+    const PARTIAL: &str = r#"{"message_type":"partial_transcript","text":"Привет как"}"#;
+    const COMMITTED: &str = r#"{"message_type":"committed_transcript","text":"Привет, как дела?"}"#;
+    const COMMITTED_WITH_TIMESTAMPS: &str = r#"{"message_type":"committed_transcript_with_timestamps","text":"Hello world","language_code":"en","words":[{"text":"Hello","start":0.12,"end":0.48,"type":"word","logprob":-0.05},{"text":" ","start":0.48,"end":0.52,"type":"spacing"},{"text":"world","start":0.52,"end":0.9,"type":"word","speaker_id":"speaker_0"}]}"#;
+    const AUTH_ERROR: &str = r#"{"message_type":"auth_error","error":"Invalid API key"}"#;
+    const QUOTA_EXCEEDED: &str = r#"{"message_type":"quota_exceeded","error":"This request exceeds your quota of 10000. You have 12 credits remaining."}"#;
+    const QUOTA_EXCEEDED_LEGACY: &str =
+        r#"{"message_type":"quota_exceeded_error","error":"Quota exceeded"}"#;
+    const INPUT_ERROR: &str = r#"{"message_type":"input_error","error":"Invalid sample rate: 44000","field":"sample_rate"}"#;
+    const COMMIT_THROTTLED: &str =
+        r#"{"message_type":"commit_throttled","error":"Commits must be at least 0.3s apart"}"#;
+    const GENERIC_ERROR: &str = r#"{"message_type":"error","message":"Internal server error"}"#;
+
+    #[test]
+    fn test_session_started() {
+        let Inbound::SessionStarted(started) = Inbound::parse(SESSION_STARTED).unwrap() else {
+            panic!("expected session_started");
+        };
+        assert_eq!(started.session_id, "0b8c5a6e2f1d4c7a");
+        assert_eq!(started.config.unwrap()["language_code"], "ru");
+    }
+
+    #[test]
+    fn test_transcripts() {
+        assert_eq!(
+            Inbound::parse(PARTIAL).unwrap(),
+            Inbound::PartialTranscript(Transcript {
+                text: "Привет как".to_string()
+            })
+        );
+        let committed = Inbound::parse(COMMITTED).unwrap();
+        assert_eq!(committed.committed_text(), Some("Привет, как дела?"));
+        assert_eq!(Inbound::parse(PARTIAL).unwrap().committed_text(), None);
+    }
+
+    #[test]
+    fn test_committed_transcript_with_timestamps() {
+        let message = Inbound::parse(COMMITTED_WITH_TIMESTAMPS).unwrap();
+        assert_eq!(message.committed_text(), Some("Hello world"));
+        let Inbound::CommittedTranscriptWithTimestamps(transcript) = message else {
+            panic!("expected committed_transcript_with_timestamps");
+        };
+        assert_eq!(transcript.language_code.as_deref(), Some("en"));
+        assert_eq!(transcript.words.len(), 3);
+        assert_eq!(
+            transcript.words[0],
+            Word {
+                text: "Hello".to_string(),
+                start: 0.12,
+                end: 0.48,
+                kind: WordKind::Word,
+                speaker_id: None,
+                logprob: Some(-0.05),
+            }
+        );
+        assert_eq!(transcript.words[1].kind, WordKind::Spacing);
+        assert_eq!(transcript.words[2].speaker_id.as_deref(), Some("speaker_0"));
+    }
+
+    #[test]
+    fn test_errors() {
+        let cases = [
+            (
+                AUTH_ERROR,
+                ServerErrorKind::AuthError,
+                ErrorCode::AuthFailed,
+            ),
+            (
+                QUOTA_EXCEEDED,
+                ServerErrorKind::QuotaExceeded,
+                ErrorCode::QuotaExceeded,
+            ),
+            (
+                QUOTA_EXCEEDED_LEGACY,
+                ServerErrorKind::QuotaExceeded,
+                ErrorCode::QuotaExceeded,
+            ),
+            (
+                INPUT_ERROR,
+                ServerErrorKind::InputError,
+                ErrorCode::StreamingError,
+            ),
+            (
+                COMMIT_THROTTLED,
+                ServerErrorKind::CommitThrottled,
+                ErrorCode::RateLimited,
+            ),
+            (
+                GENERIC_ERROR,
+                ServerErrorKind::Error,
+                ErrorCode::StreamingError,
+            ),
+        ];
+        for (text, kind, code) in cases {
+            let Inbound::Error(error) = Inbound::parse(text).unwrap() else {
+                panic!("expected an error: {}", text);
+            };
+            assert_eq!(error.kind, kind);
+            assert_eq!(error.error_code(), code);
+            assert!(!error.message.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_error_keeps_extra_fields() {
+        let Inbound::Error(error) = Inbound::parse(INPUT_ERROR).unwrap() else {
+            panic!("expected an error");
+        };
+        assert_eq!(error.message, "Invalid sample rate: 44000");
+        assert_eq!(error.details["field"], "sample_rate");
+        assert_eq!(
+            error.to_string(),
+            "ElevenLabs input_error: Invalid sample rate: 44000"
+        );
+    }
+
+    #[test]
+    fn test_unknown_message_type() {
+        assert_eq!(
+            Inbound::parse(r#"{"message_type":"speaker_changed","speaker_id":"speaker_1"}"#)
+                .unwrap(),
+            Inbound::Unknown("speaker_changed".to_string())
+        );
+    }
+
+    #[test]
+    fn test_malformed_messages() {
+        assert!(Inbound::parse("not json").is_err());
+        assert!(Inbound::parse(r#"{"text":"no type"}"#).is_err());
+        assert!(Inbound::parse(r#"["session_started"]"#).is_err());
+        // A known type with fields of the wrong shape
+        assert!(Inbound::parse(r#"{"message_type":"session_started"}"#).is_err());
+        assert!(Inbound::parse(
+            r#"{"message_type":"committed_transcript_with_timestamps","text":"hi","words":"hi"}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_audio_chunks() {
+        let audio: Value =
+            serde_json::from_str(&Outbound::audio(&[1, 2, 3], 16000).to_json().unwrap()).unwrap();
+        assert_eq!(audio["message_type"], "input_audio_chunk");
+        assert_eq!(audio["audio_base_64"], "AQID");
+        assert_eq!(audio["sample_rate"], 16000);
+        assert_eq!(audio["commit"], false);
+
+        let commit: Value =
+            serde_json::from_str(&Outbound::commit(&[], 16000).to_json().unwrap()).unwrap();
+        assert_eq!(commit["audio_base_64"], "");
+        assert_eq!(commit["commit"], true);
+    }
+
+    #[test]
+    fn test_split_pcm() {
+        let pcm: Vec<u8> = (0..10).collect();
+        let lens = |max| -> Vec<usize> { split_pcm(&pcm, max).iter().map(|p| p.len()).collect() };
+        assert_eq!(lens(4), [4, 4, 2]);
+        assert_eq!(lens(10), [10]);
+        assert_eq!(lens(64), [10]);
+        // Odd limits round down to whole samples
+        assert_eq!(lens(5), [4, 4, 2]);
+        assert_eq!(lens(1), [2; 5]);
+        assert_eq!(split_pcm(&pcm, 4).concat(), pcm);
+        assert_eq!(split_pcm(&[], 4), [&[] as &[u8]]);
+    }
+
+    #[test]
+    fn test_long_audio_is_split_with_one_commit() {
+        let pcm = vec![7u8; MAX_AUDIO_CHUNK_BYTES * 2 + 6];
+        let chunks = Outbound::chunks(&pcm, 16000, true);
+        assert_eq!(chunks.len(), 3);
+
+        let mut decoded = Vec::new();
+        let mut commits = Vec::new();
+        for chunk in &chunks {
+            let Outbound::InputAudioChunk {
+                audio_base_64,
+                commit,
+                ..
+            } = chunk;
+            let piece = base64::engine::general_purpose::STANDARD
+                .decode(audio_base_64)
+                .unwrap();
+            assert!(piece.len() <= MAX_AUDIO_CHUNK_BYTES);
+            assert_eq!(piece.len() % 2, 0);
+            decoded.extend(piece);
+            commits.push(*commit);
+        }
+        assert_eq!(decoded, pcm);
+        assert_eq!(commits, [false, false, true]);
+
+        let audio = Outbound::chunks(&pcm, 16000, false);
+        assert!(audio
+            .iter()
+            .all(|chunk| !matches!(chunk, Outbound::InputAudioChunk { commit: true, .. })));
+        assert_eq!(
+            Outbound::chunks(&[], 16000, true),
+            [Outbound::commit(&[], 16000)]
+        );
+    }
+}
@@ -1,119 +1,368 @@
 use anyhow::{anyhow, Context, Result};
-use base64::Engine;
 use futures_util::{SinkExt, StreamExt};
-use serde::{Deserialize, Serialize};
-use std::sync::atomic::{AtomicBool, Ordering};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Instant;
 use tauri::{AppHandle, Emitter};
 use tokio::net::TcpStream;
-use tokio::sync::{Mutex, Notify};
+use tokio::sync::{oneshot, Mutex, Notify};
 use tokio::time::{interval, timeout, Duration};
 use tokio_tungstenite::{
     connect_async,
     tungstenite::{
-        http::Request, protocol::frame::coding::CloseCode, protocol::CloseFrame, Message,
+        client::IntoClientRequest,
+        handshake::client::{Request, Response},
+        http::HeaderValue,
+        protocol::frame::coding::CloseCode,
+        protocol::CloseFrame,
+        Message,
     },
     MaybeTlsStream, WebSocketStream,
 };
 
-type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+use crate::audio::MicMute;
+use crate::audio_stream::{AudioCounters, AudioStats};
+use crate::core::events::{
+    ConnectionClosedPayload, ErrorCode, SessionStartedPayload, StreamingErrorPayload,
+    StreamingTranscriptPayload, EVENT_ELEVENLABS_AUDIO_STATS, EVENT_ELEVENLABS_CONNECTION_CLOSED,
+    EVENT_ELEVENLABS_ERROR, EVENT_ELEVENLABS_SESSION_STARTED, EVENT_ELEVENLABS_TRANSCRIPT,
+};
+use crate::deepgram_streaming::DeepgramSession;
+use crate::elevenlabs_protocol::{Inbound, Outbound};
+use crate::settings::StreamingProvider;
+use crate::streaming_session::{silence_chunks, silence_pcm, StreamingSession};
+
+pub type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+const REALTIME_ENDPOINT: &str = "wss://api.elevenlabs.io/v1/speech-to-text/realtime";
+const COMMIT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Audio queued or being written is capped at this; further chunks are dropped
+/// until the socket catches up (about 10 s at 48 kHz)
+const MAX_IN_FLIGHT_BYTES: usize = 1024 * 1024;
+/// A slow socket lets chunks pile up; they are sent as one message of at most this size
+const MAX_MESSAGE_BYTES: usize = 128 * 1024;
+
+/// The WebSocket handshake did not finish in time, e.g. because TLS stalled
+#[derive(Debug, thiserror::Error)]
+#[error("WebSocket handshake timed out after {}s", .0.as_secs())]
+pub struct ConnectTimeout(pub Duration);
+
+/// `connect_async` to `service` that gives up after `limit` instead of waiting on a
+/// stalled handshake forever
+pub async fn connect_with_timeout(
+    service: &str,
+    request: impl IntoClientRequest + Unpin,
+    limit: Duration,
+) -> Result<(WsStream, Response)> {
+    match timeout(limit, connect_async(request)).await {
+        Ok(result) => result.with_context(|| format!("Failed to connect to {service} WebSocket")),
+        Err(_) => Err(ConnectTimeout(limit).into()),
+    }
+}
+
+/// Receiver of the client's events (session start, transcripts, errors, close).
+/// The app forwards them to the webview; tests record them without a Tauri app.
+pub trait StreamingEventSink: Send + Sync + 'static {
+    fn emit_event(&self, event: &str, payload: serde_json::Value);
+}
+
+impl StreamingEventSink for AppHandle {
+    fn emit_event(&self, event: &str, payload: serde_json::Value) {
+        let _ = self.emit(event, payload);
+    }
+}
+
+type EventSink = Arc<dyn StreamingEventSink>;
+
+pub(crate) fn emit<T: Serialize>(events: &dyn StreamingEventSink, event: &str, payload: T) {
+    match serde_json::to_value(payload) {
+        Ok(payload) => events.emit_event(event, payload),
+        Err(e) => tracing::error!("[Streaming] Failed to serialize {} payload: {}", event, e),
+    }
+}
+
+type WsSink = Arc<Mutex<futures_util::stream::SplitSink<WsStream, Message>>>;
+
+/// What the writer task sends next
+enum Outgoing {
+    /// One or more queued chunks joined into a single audio message
+    Audio { pcm: Vec<u8>, chunks: usize },
+    /// The commit, after `silence` of digital silence; only handed out once the audio
+    /// queued before it is sent
+    Commit {
+        silence: Duration,
+        reply: oneshot::Sender<Result<()>>,
+    },
+}
+
+#[derive(Default)]
+struct QueueState {
+    chunks: VecDeque<Vec<u8>>,
+    /// Bytes queued plus bytes the writer is sending right now
+    in_flight_bytes: usize,
+    commit: Option<oneshot::Sender<Result<()>>>,
+    /// Silence sent ahead of the pending commit
+    commit_silence: Duration,
+    /// Chunks queued before the commit; the ones behind it belong to the next segment
+    before_commit: usize,
+}
+
+/// Per-connection queue between the audio pump and the writer task, so a stalled
+/// socket never blocks the pump
+struct SendQueue {
+    state: std::sync::Mutex<QueueState>,
+    ready: Notify,
+    max_in_flight_bytes: usize,
+    coalesced: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl SendQueue {
+    fn new(max_in_flight_bytes: usize) -> Self {
+        Self {
+            state: std::sync::Mutex::new(QueueState::default()),
+            ready: Notify::new(),
+            max_in_flight_bytes,
+            coalesced: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, QueueState> {
+        // The state stays consistent even if a holder panicked
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Queue a chunk for the writer; false if it was dropped because the in-flight cap
+    /// is reached
+    fn push_audio(&self, pcm: Vec<u8>) -> bool {
+        {
+            let mut state = self.lock();
+            if state.in_flight_bytes + pcm.len() > self.max_in_flight_bytes {
+                drop(state);
+                let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+                if dropped % 10 == 1 {
+                    tracing::warn!(
+                        "[Streaming] Socket is falling behind, dropped {} audio chunk(s)",
+                        dropped
+                    );
+                }
+                return false;
+            }
+            state.in_flight_bytes += pcm.len();
+            state.chunks.push_back(pcm);
+        }
+        self.ready.notify_one();
+        true
+    }
+
+    /// Ask the writer to send `silence` and the commit after everything queued so far
+    fn push_commit(&self, silence: Duration, reply: oneshot::Sender<Result<()>>) {
+        {
+            let mut state = self.lock();
+            state.before_commit = state.chunks.len();
+            state.commit = Some(reply);
+            state.commit_silence = silence;
+        }
+        self.ready.notify_one();
+    }
+
+    /// Everything queued, joined into messages of at most `MAX_MESSAGE_BYTES`; the
+    /// commit is never merged into audio and goes out right after the chunks queued
+    /// before it, even if more audio arrived in the meantime
+    fn next(&self) -> Option<Outgoing> {
+        let mut state = self.lock();
+        let committing = state.commit.is_some();
+        if committing && state.before_commit == 0 {
+            let silence = state.commit_silence;
+            return state
+                .commit
+                .take()
+                .map(|reply| Outgoing::Commit { silence, reply });
+        }
+        let limit = if committing {
+            state.before_commit
+        } else {
+            usize::MAX
+        };
+        let mut pcm = state.chunks.pop_front()?;
+        let mut chunks = 1;
+        while let Some(next) = state.chunks.front() {
+            if chunks == limit || pcm.len() + next.len() > MAX_MESSAGE_BYTES {
+                break;
+            }
+            if let Some(next) = state.chunks.pop_front() {
+                pcm.extend_from_slice(&next);
+                chunks += 1;
+            }
+        }
+        if committing {
+            state.before_commit -= chunks;
+        }
+        drop(state);
+        if chunks > 1 {
+            self.coalesced
+                .fetch_add(chunks as u64 - 1, Ordering::Relaxed);
+            tracing::debug!("[Streaming] Coalesced {} queued audio chunks", chunks);
+        }
+        Some(Outgoing::Audio { pcm, chunks })
+    }
+
+    /// The writer finished sending `bytes` of audio
+    fn sent(&self, bytes: usize) {
+        let mut state = self.lock();
+        state.in_flight_bytes = state.in_flight_bytes.saturating_sub(bytes);
+    }
+
+    fn in_flight_bytes(&self) -> usize {
+        self.lock().in_flight_bytes
+    }
+}
+
+/// Connection state and send-path counters, for diagnostics
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct StreamingState {
+    pub connected: bool,
+    pub transmitting: bool,
+    pub committing: bool,
+    /// Audio bytes queued or being written
+    pub in_flight_bytes: usize,
+    /// Chunks merged into an earlier chunk's message because the socket was slow
+    pub coalesced_chunks: u64,
+    /// Chunks dropped because the in-flight cap was reached
+    pub dropped_chunks: u64,
+    /// The audio of the current or last dictation
+    pub audio: AudioStats,
+}
 
 /// Структура для активного WebSocket соединения
 struct StreamingConnection {
-    write: Arc<Mutex<futures_util::stream::SplitSink<WsStream, Message>>>,
+    session: Arc<dyn StreamingSession>,
+    write: WsSink,
+    queue: Arc<SendQueue>,
     is_transmitting: Arc<AtomicBool>,
-    sent_since_open: Arc<AtomicBool>,
+    /// Audio chunks queued since the gate opened or the last segment commit
+    chunks_since_open: Arc<AtomicU64>,
     is_committing: Arc<AtomicBool>,
+    /// When the connection opened or the gate last opened or closed
+    gate_activity: std::sync::Mutex<Instant>,
     commit_notify: Arc<Notify>,
     is_alive: Arc<AtomicBool>,
     cancel_token: tokio_util::sync::CancellationToken,
     reader_task: tokio::task::JoinHandle<()>,
     keepalive_task: tokio::task::JoinHandle<()>,
-    sample_rate: u32,
-    app_handle: AppHandle,
+    writer_task: tokio::task::JoinHandle<()>,
+    events: EventSink,
 }
 
-/// Публичный клиент для gated streaming
-#[derive(Clone)]
-pub struct ElevenLabsStreamingClient {
-    connection: Arc<Mutex<Option<StreamingConnection>>>,
-    last_config: Arc<Mutex<Option<ConnectionConfig>>>,
+impl StreamingConnection {
+    /// Restart the idle clock
+    fn touch_gate(&self) {
+        if let Ok(mut at) = self.gate_activity.lock() {
+            *at = Instant::now();
+        }
+    }
 }
 
+/// Публичный клиент для gated streaming. The gate, commits and the audio pump work
+/// the same for every service; the provider of each connection picks the
+/// `StreamingSession` that speaks its protocol.
 #[derive(Clone)]
-struct ConnectionConfig {
-    api_key: String,
-    sample_rate: u32,
-    language_code: String,
-}
-
-#[derive(Serialize)]
-struct AudioChunkMessage {
-    message_type: String,
-    audio_base_64: String,
-    sample_rate: u32,
-    #[serde(default)]
-    commit: bool,
-}
-
-#[derive(Deserialize, Debug)]
-struct TranscriptMessage {
-    message_type: String,
-    #[serde(default)]
-    text: String,
-    #[serde(default)]
-    session_id: Option<String>,
-}
-
-// Tauri event payloads
-#[derive(Serialize, Clone)]
-struct SessionStartedEvent {
-    session_id: String,
-}
-
-#[derive(Serialize, Clone)]
-struct TranscriptEvent {
-    text: String,
-    is_partial: bool,
+pub struct StreamingClient {
+    connection: Arc<Mutex<Option<StreamingConnection>>>,
+    last_config: Arc<Mutex<Option<ConnectionConfig>>>,
+    /// Replaces the session's realtime endpoint (a local mock server in tests)
+    endpoint: Option<String>,
+    /// Bumped each time the gate opens, so the audio task can tell dictations apart
+    gate_openings: Arc<AtomicU64>,
+    /// Shared with the audio capture; reset when the gate opens or a segment commits
+    audio_counters: Arc<AudioCounters>,
+    /// The gate stays closed while this is on
+    mic_mute: MicMute,
 }
 
-#[derive(Serialize, Clone)]
-struct ErrorEvent {
-    error: String,
+/// What a streaming connection was opened with, kept for reconnecting
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionConfig {
+    pub provider: StreamingProvider,
+    pub api_key: String,
+    pub sample_rate: u32,
+    /// "auto" or empty = detect
+    pub language_code: String,
 }
 
-#[derive(Serialize, Clone)]
-struct ConnectionClosedEvent {
-    code: u16,
-    reason: String,
+impl ConnectionConfig {
+    /// The protocol of `provider`, for one connection
+    fn session(&self) -> Arc<dyn StreamingSession> {
+        match self.provider {
+            StreamingProvider::ElevenLabs => Arc::new(ElevenLabsSession::new(self)),
+            StreamingProvider::Deepgram => Arc::new(DeepgramSession::new(self)),
+        }
+    }
 }
 
-impl ElevenLabsStreamingClient {
+impl StreamingClient {
     pub fn new() -> Self {
         Self {
             connection: Arc::new(Mutex::new(None)),
             last_config: Arc::new(Mutex::new(None)),
+            endpoint: None,
+            gate_openings: Arc::new(AtomicU64::new(0)),
+            audio_counters: Arc::new(AudioCounters::default()),
+            mic_mute: MicMute::default(),
         }
     }
 
+    /// Client talking to another realtime endpoint, whatever the provider (a local
+    /// mock server in tests)
+    #[cfg(test)]
+    pub fn with_endpoint(endpoint: impl Into<String>) -> Self {
+        Self {
+            endpoint: Some(endpoint.into()),
+            ..Self::new()
+        }
+    }
+
+    /// Follow the app's hard mute switch instead of a switch of its own
+    pub fn with_mic_mute(mut self, mic_mute: MicMute) -> Self {
+        self.mic_mute = mic_mute;
+        self
+    }
+
+    /// How many times the gate has been opened
+    pub fn gate_openings(&self) -> u64 {
+        self.gate_openings.load(Ordering::Acquire)
+    }
+
+    /// Counters for the audio capture to report captured and dropped chunks to
+    pub fn audio_counters(&self) -> Arc<AudioCounters> {
+        self.audio_counters.clone()
+    }
+
+    /// What happened to the audio since the gate opened or the last segment commit
+    pub fn audio_stats(&self) -> AudioStats {
+        self.audio_counters.stats()
+    }
+
     /// Retrieve the last used connection configuration
-    pub async fn get_last_config(&self) -> Option<(String, u32, String)> {
-        let guard = self.last_config.lock().await;
-        guard.as_ref().map(|cfg| {
-            (
-                cfg.api_key.clone(),
-                cfg.sample_rate,
-                cfg.language_code.clone(),
-            )
-        })
+    pub async fn get_last_config(&self) -> Option<ConnectionConfig> {
+        self.last_config.lock().await.clone()
     }
 
     /// Returns whether any audio has been sent since the last gate open
     pub async fn has_audio_since_open(&self) -> bool {
+        self.chunks_since_open().await > 0
+    }
+
+    /// Audio chunks sent since the last gate open or segment commit
+    pub async fn chunks_since_open(&self) -> u64 {
         if let Some(conn) = self.connection.lock().await.as_ref() {
-            conn.sent_since_open.load(Ordering::Acquire)
+            conn.chunks_since_open.load(Ordering::Acquire)
         } else {
-            false
+            0
         }
     }
 
@@ -126,14 +375,47 @@ impl ElevenLabsStreamingClient {
         }
     }
 
-    /// Подключиться к ElevenLabs WebSocket и начать gated streaming
+    /// How long a live connection has sat with its gate closed, or None while the
+    /// gate is open, a commit is running or nothing is connected
+    pub async fn idle_for(&self) -> Option<Duration> {
+        let guard = self.connection.lock().await;
+        let conn = guard.as_ref()?;
+        if !conn.is_alive.load(Ordering::Acquire)
+            || conn.is_transmitting.load(Ordering::Acquire)
+            || conn.is_committing.load(Ordering::Acquire)
+        {
+            return None;
+        }
+        let since = *conn.gate_activity.lock().ok()?;
+        Some(since.elapsed())
+    }
+
+    /// Connection state and the send queue's counters for the current connection
+    pub async fn state(&self) -> StreamingState {
+        match self.connection.lock().await.as_ref() {
+            Some(conn) => StreamingState {
+                connected: conn.is_alive.load(Ordering::Acquire),
+                transmitting: conn.is_transmitting.load(Ordering::Acquire),
+                committing: conn.is_committing.load(Ordering::Acquire),
+                in_flight_bytes: conn.queue.in_flight_bytes(),
+                coalesced_chunks: conn.queue.coalesced.load(Ordering::Relaxed),
+                dropped_chunks: conn.queue.dropped.load(Ordering::Relaxed),
+                audio: self.audio_counters.stats(),
+            },
+            None => StreamingState::default(),
+        }
+    }
+
+    /// Подключиться к WebSocket of `config.provider` и начать gated streaming. A
+    /// handshake taking longer than `connect_timeout` fails with `ConnectTimeout`.
     pub async fn connect(
         &self,
-        api_key: String,
-        sample_rate: u32,
-        language_code: String,
-        app_handle: AppHandle,
+        config: ConnectionConfig,
+        connect_timeout: Duration,
+        events: impl StreamingEventSink,
     ) -> Result<()> {
+        let events: EventSink = Arc::new(events);
+
         // Проверяем что нет активного соединения
         let mut conn_guard = self.connection.lock().await;
 
@@ -144,77 +426,26 @@ impl ElevenLabsStreamingClient {
                 return Err(anyhow!("Connection already exists. Disconnect first."));
             } else {
                 // Cleanup dead connection
-                tracing::info!("[ElevenLabs] Cleaning up dead connection before reconnecting");
+                tracing::info!("[Streaming] Cleaning up dead connection before reconnecting");
                 *conn_guard = None;
             }
         }
 
         // Сохраняем конфиг для быстрого переподключения
-        {
-            let mut guard = self.last_config.lock().await;
-            *guard = Some(ConnectionConfig {
-                api_key: api_key.clone(),
-                sample_rate,
-                language_code: language_code.clone(),
-            });
-        }
-
-        // Определяем audio format на основе sample rate
-        let audio_format = match sample_rate {
-            8000 => "pcm_8000",
-            16000 => "pcm_16000",
-            22050 => "pcm_22050",
-            24000 => "pcm_24000",
-            44100 => "pcm_44100",
-            48000 => "pcm_48000",
-            _ => {
-                tracing::warn!(
-                    "[ElevenLabs] Unsupported sample rate {}, using pcm_16000",
-                    sample_rate
-                );
-                "pcm_16000"
-            }
-        };
-
-        let ws_url = if language_code.is_empty() || language_code == "auto" {
-            format!(
-                "wss://api.elevenlabs.io/v1/speech-to-text/realtime?model_id=scribe_v2_realtime&audio_format={}&commit_strategy=manual&enable_partials=true",
-                audio_format
-            )
-        } else {
-            format!(
-                "wss://api.elevenlabs.io/v1/speech-to-text/realtime?model_id=scribe_v2_realtime&language_code={}&audio_format={}&commit_strategy=manual&enable_partials=true",
-                language_code, audio_format
-            )
-        };
-
-        tracing::info!(
-            "[ElevenLabs] Connecting to WebSocket (sample_rate: {}, audio_format: {})",
-            sample_rate,
-            audio_format
-        );
-
-        // Создаем HTTP запрос с заголовком xi-api-key
-        let request = Request::builder()
-            .uri(ws_url)
-            .header("Host", "api.elevenlabs.io")
-            .header("Connection", "Upgrade")
-            .header("Upgrade", "websocket")
-            .header("Sec-WebSocket-Version", "13")
-            .header(
-                "Sec-WebSocket-Key",
-                tokio_tungstenite::tungstenite::handshake::client::generate_key(),
-            )
-            .header("xi-api-key", &api_key)
-            .body(())
-            .context("Failed to build WebSocket request")?;
+        *self.last_config.lock().await = Some(config.clone());
 
-        let (ws_stream, response) = connect_async(request)
-            .await
-            .context("Failed to connect to ElevenLabs WebSocket")?;
+        let session = config.session();
+        let endpoint = self
+            .endpoint
+            .as_deref()
+            .unwrap_or(session.default_endpoint());
+        let request = session.request(endpoint)?;
+        let (ws_stream, response) =
+            connect_with_timeout(session.name(), request, connect_timeout).await?;
 
         tracing::info!(
-            "[ElevenLabs] WebSocket connected successfully, status: {:?}",
+            "[{}] WebSocket connected successfully, status: {:?}",
+            session.name(),
             response.status()
         );
 
@@ -223,7 +454,7 @@ impl ElevenLabsStreamingClient {
 
         // Флаги для gate/commit control
         let is_transmitting = Arc::new(AtomicBool::new(false));
-        let sent_since_open = Arc::new(AtomicBool::new(false));
+        let chunks_since_open = Arc::new(AtomicU64::new(0));
         let is_committing = Arc::new(AtomicBool::new(false));
         let commit_notify = Arc::new(Notify::new());
 
@@ -235,16 +466,18 @@ impl ElevenLabsStreamingClient {
 
         // Запускаем background task для чтения сообщений
         let reader_task = {
-            let app_handle = app_handle.clone();
+            let events = events.clone();
             let cancel_token = cancel_token.clone();
             let is_alive = is_alive.clone();
             let write = write.clone();
             let commit_notify = commit_notify.clone();
+            let session = session.clone();
             tokio::spawn(async move {
                 message_reader_task(
                     read,
                     write,
-                    app_handle,
+                    session,
+                    events,
                     cancel_token,
                     is_alive,
                     commit_notify,
@@ -253,81 +486,99 @@ impl ElevenLabsStreamingClient {
             })
         };
 
+        // Writer task: the only sender of audio and commits
+        let queue = Arc::new(SendQueue::new(MAX_IN_FLIGHT_BYTES));
+        let writer_task = {
+            let write = write.clone();
+            let queue = queue.clone();
+            let events = events.clone();
+            let cancel_token = cancel_token.clone();
+            let is_alive = is_alive.clone();
+            let session = session.clone();
+            let sample_rate = config.sample_rate;
+            tokio::spawn(async move {
+                writer_task(
+                    write,
+                    queue,
+                    session,
+                    sample_rate,
+                    events,
+                    cancel_token,
+                    is_alive,
+                )
+                .await;
+            })
+        };
+
         // Запускаем background task для keep-alive
         let keepalive_task = {
             let write = write.clone();
             let cancel_token = cancel_token.clone();
+            let session = session.clone();
             tokio::spawn(async move {
-                keepalive_task(write, cancel_token).await;
+                keepalive_task(write, session, cancel_token).await;
             })
         };
 
         // Сохраняем соединение
+        let name = session.name();
         *conn_guard = Some(StreamingConnection {
+            session,
             write,
+            queue,
             is_transmitting,
-            sent_since_open,
+            chunks_since_open,
             is_committing,
+            gate_activity: std::sync::Mutex::new(Instant::now()),
             commit_notify,
             is_alive,
             cancel_token,
             reader_task,
             keepalive_task,
-            sample_rate,
-            app_handle,
+            writer_task,
+            events,
         });
 
-        tracing::info!("[ElevenLabs] Gated streaming session started");
+        tracing::info!("[{}] Gated streaming session started", name);
         Ok(())
     }
 
-    /// Отправить чанк аудио (только если gate открыт)
-    pub async fn send_audio_chunk(&self, pcm_data: Vec<u8>) -> Result<()> {
-        // Lock connection briefly to check state and get write handle
-        let (write_handle, sample_rate) = {
-            let conn_guard = self.connection.lock().await;
-            let conn = conn_guard
-                .as_ref()
-                .ok_or_else(|| anyhow!("Not connected. Call connect() first."))?;
-
-            // Use Acquire/Release ordering for proper synchronization across threads
-            if !conn.is_alive.load(Ordering::Acquire) {
-                return Err(anyhow!("Connection is dead"));
-            }
-
-            // Check gate - Acquire ensures we see the latest state
-            if !conn.is_transmitting.load(Ordering::Acquire) {
-                // Gate closed - ignore audio
-                return Ok(());
-            }
-
-            // Gate open - mark that audio was sent
-            conn.sent_since_open.store(true, Ordering::Release);
-            (conn.write.clone(), conn.sample_rate)
-        }; // connection lock released here — write I/O happens outside
-
-        let audio_base64 = base64::engine::general_purpose::STANDARD.encode(&pcm_data);
-
-        let message = AudioChunkMessage {
-            message_type: "input_audio_chunk".to_string(),
-            audio_base_64: audio_base64,
-            sample_rate,
-            commit: false,
-        };
+    /// Отправить чанк аудио (только если gate открыт). The chunk is queued for the
+    /// writer task, so this never waits on the socket. `voiced` marks a chunk loud
+    /// enough to hold speech, counted in `AudioStats::voiced_chunks`.
+    pub async fn send_audio_chunk(&self, pcm_data: Vec<u8>, voiced: bool) -> Result<()> {
+        let conn_guard = self.connection.lock().await;
+        let conn = conn_guard
+            .as_ref()
+            .ok_or_else(|| anyhow!("Not connected. Call connect() first."))?;
 
-        let json = serde_json::to_string(&message)?;
+        // Use Acquire/Release ordering for proper synchronization across threads
+        if !conn.is_alive.load(Ordering::Acquire) {
+            return Err(anyhow!("Connection is dead"));
+        }
 
-        let mut write = write_handle.lock().await;
-        write
-            .send(Message::Text(json))
-            .await
-            .context("Failed to send audio chunk")?;
+        // Check gate - Acquire ensures we see the latest state
+        if !conn.is_transmitting.load(Ordering::Acquire) {
+            // Gate closed - ignore audio
+            self.audio_counters.ignored_gate_closed();
+            return Ok(());
+        }
 
+        // Queued under the connection lock, so nothing lands behind a commit
+        let bytes = pcm_data.len();
+        if conn.queue.push_audio(pcm_data) {
+            // Gate open - count the chunk as sent
+            conn.chunks_since_open.fetch_add(1, Ordering::AcqRel);
+            self.audio_counters.sent(bytes, voiced);
+        } else {
+            self.audio_counters.dropped_socket_behind();
+        }
         Ok(())
     }
 
     /// Open gate - start transmitting (KeyDown)
     pub async fn open_gate(&self) -> Result<()> {
+        self.mic_mute.check()?;
         let conn_guard = self.connection.lock().await;
         let conn = conn_guard
             .as_ref()
@@ -338,16 +589,70 @@ impl ElevenLabsStreamingClient {
         }
 
         // Use Release ordering to ensure other threads see these writes
-        conn.sent_since_open.store(false, Ordering::Release);
+        conn.chunks_since_open.store(0, Ordering::Release);
+        self.audio_counters.reset();
+        conn.session.gate_opened();
         conn.is_transmitting.store(true, Ordering::Release);
-        tracing::info!("[ElevenLabs] Gate OPENED - transmitting audio");
+        conn.touch_gate();
+        self.gate_openings.fetch_add(1, Ordering::AcqRel);
+        tracing::info!("[{}] Gate OPENED - transmitting audio", conn.session.name());
         Ok(())
     }
 
-    /// Close gate and send commit (KeyUp)
-    pub async fn close_gate_and_commit(&self) -> Result<()> {
-        // 1) Validate & mark committing; send final silence + commit
+    /// Commit the audio sent so far while the gate stays open, so dictation can go on
+    /// into the next segment. Audio sent after this call belongs to that segment.
+    /// Returns false if nothing was sent since the gate opened or the last segment.
+    /// `silence` is appended first, so the last word isn't cut off.
+    pub async fn commit_segment(&self, silence: Duration) -> Result<bool> {
+        let (commit_sent, commit_notify) = {
+            let conn_guard = self.connection.lock().await;
+            let conn = conn_guard
+                .as_ref()
+                .ok_or_else(|| anyhow!("Not connected"))?;
+
+            if !conn.is_alive.load(Ordering::Acquire) {
+                return Err(anyhow!("Connection is dead"));
+            }
+            if !conn.is_transmitting.load(Ordering::Acquire) {
+                return Err(anyhow!("Gate is closed"));
+            }
+
+            // Reset under the connection lock, so every chunk lands on one side of the commit
+            if conn.chunks_since_open.swap(0, Ordering::AcqRel) == 0 {
+                return Ok(false);
+            }
+
+            self.report_audio_stats(conn);
+            self.audio_counters.reset();
+
+            let (reply, commit_sent) = oneshot::channel();
+            conn.queue.push_commit(silence, reply);
+            tracing::info!(
+                "[{}] Segment commit queued, gate stays open",
+                conn.session.name()
+            );
+            (commit_sent, conn.commit_notify.clone())
+        };
+        commit_sent
+            .await
+            .map_err(|_| anyhow!("Connection closed before the commit was sent"))?
+            .context("Failed to send commit")?;
+
+        // Wait for the transcript, so the next commit isn't woken by this one's
+        if timeout(COMMIT_TIMEOUT, commit_notify.notified())
+            .await
+            .is_err()
         {
+            tracing::warn!("[Streaming] No committed transcript for the segment yet");
+        }
+        Ok(true)
+    }
+
+    /// Close gate and send commit (KeyUp), after `silence` that lets the endpointer
+    /// finish the last word
+    pub async fn close_gate_and_commit(&self, silence: Duration) -> Result<()> {
+        // 1) Validate & mark committing; queue the commit behind the pending audio
+        let commit_sent = {
             let conn_guard = self.connection.lock().await;
             let conn = conn_guard
                 .as_ref()
@@ -360,40 +665,30 @@ impl ElevenLabsStreamingClient {
             // Use Release to ensure audio thread sees gate closed
             conn.is_transmitting.store(false, Ordering::Release);
             conn.is_committing.store(true, Ordering::Release);
-            tracing::info!("[ElevenLabs] Gate CLOSED - sending commit");
+            conn.touch_gate();
+            tracing::info!("[{}] Gate CLOSED - sending commit", conn.session.name());
 
             // If no audio was sent since gate open, skip commit (no-op)
             // Use Acquire to see all writes from audio thread
-            if !conn.sent_since_open.load(Ordering::Acquire) {
-                tracing::warn!("[ElevenLabs] No audio since gate opened; skipping commit");
+            if conn.chunks_since_open.load(Ordering::Acquire) == 0 {
+                tracing::warn!("[Streaming] No audio since gate opened; skipping commit");
                 conn.is_committing.store(false, Ordering::Release);
                 return Ok(());
             }
 
-            // Send small silence then commit=true
-            let duration_ms: usize = 1;
-            let samples = (conn.sample_rate as usize * duration_ms) / 1000;
-            let silence_bytes = vec![0u8; samples * 2];
-            let audio_base64 = base64::engine::general_purpose::STANDARD.encode(&silence_bytes);
-
-            let message = AudioChunkMessage {
-                message_type: "input_audio_chunk".to_string(),
-                audio_base_64: audio_base64,
-                sample_rate: conn.sample_rate,
-                commit: true,
-            };
-
-            let json = serde_json::to_string(&message)?;
+            self.report_audio_stats(conn);
 
-            let mut write = conn.write.lock().await;
-            write
-                .send(Message::Text(json))
-                .await
-                .context("Failed to send commit")?;
-        }
+            let (reply, commit_sent) = oneshot::channel();
+            conn.queue.push_commit(silence, reply);
+            commit_sent
+        };
+        commit_sent
+            .await
+            .map_err(|_| anyhow!("Connection closed before the commit was sent"))?
+            .context("Failed to send commit")?;
 
         // 2) Wait for committed notification (timeout)
-        let (app_handle, _cancel_token, commit_notify) = {
+        let (events, _cancel_token, commit_notify) = {
             let guard = self.connection.lock().await;
             // If connection is gone, we can't do anything
             let conn = guard
@@ -401,66 +696,82 @@ impl ElevenLabsStreamingClient {
                 .ok_or_else(|| anyhow!("Connection missing after commit"))?;
 
             (
-                conn.app_handle.clone(),
+                conn.events.clone(),
                 conn.cancel_token.clone(),
                 conn.commit_notify.clone(),
             )
         };
 
-        let commit_ok = timeout(Duration::from_secs(3), commit_notify.notified())
+        let commit_ok = timeout(COMMIT_TIMEOUT, commit_notify.notified())
             .await
             .is_ok();
 
         if !commit_ok {
-            let _ = app_handle.emit(
-                "elevenlabs://error",
-                ErrorEvent {
+            emit(
+                events.as_ref(),
+                EVENT_ELEVENLABS_ERROR,
+                StreamingErrorPayload {
                     error: "Commit timeout".to_string(),
+                    code: ErrorCode::CommitTimeout,
+                    server_error: None,
                 },
             );
         }
 
-        // 3) Graceful Shutdown: Send Close frame -> Wait for Reader to see Close -> Cancel if stuck
+        // 3) Graceful Shutdown: End the stream -> Wait for Reader to see Close -> Cancel if stuck
         {
             let mut guard = self.connection.lock().await;
             if let Some(conn) = guard.take() {
                 // Use Release to ensure reader thread sees this
                 conn.is_alive.store(false, Ordering::Release);
 
-                // Stop keepalive immediately
+                // Stop keepalive immediately; the writer is idle once the commit is out
                 conn.keepalive_task.abort();
+                conn.writer_task.abort();
 
-                // Send Close frame
+                // End the stream the way the service expects
+                let name = conn.session.name();
                 {
                     let mut write = conn.write.lock().await;
-                    let _ = write
-                        .send(Message::Close(Some(CloseFrame {
-                            code: CloseCode::Library(4001),
-                            reason: "ContextReset".into(),
-                        })))
-                        .await;
+                    let _ = write.send(conn.session.close()).await;
                 }
-                tracing::info!("[ElevenLabs] Sent Close(4001), waiting for server close...");
+                tracing::info!("[{}] Stream ended, waiting for server close...", name);
 
                 // Wait for reader task to finish (it should exit when it receives Close from server)
                 // We give it a short timeout
                 let reader_result = timeout(Duration::from_secs(2), conn.reader_task).await;
 
                 match reader_result {
-                    Ok(_) => tracing::info!("[ElevenLabs] Reader task finished gracefully"),
+                    Ok(_) => tracing::info!("[{}] Reader task finished gracefully", name),
                     Err(_) => {
-                        tracing::warn!("[ElevenLabs] Reader task timed out, forcing cancel");
+                        tracing::warn!("[{}] Reader task timed out, forcing cancel", name);
                         conn.cancel_token.cancel();
                     }
                 }
 
-                tracing::info!("[ElevenLabs] Connection closed and cleaned up");
+                tracing::info!("[{}] Connection closed and cleaned up", name);
             }
         }
 
         Ok(())
     }
 
+    /// Log the audio of the utterance being committed and emit it as
+    /// `elevenlabs://audio-stats`
+    fn report_audio_stats(&self, conn: &StreamingConnection) {
+        let stats = self.audio_counters.stats();
+        if stats.lost_chunks() > 0 {
+            tracing::warn!(
+                "[Streaming] {} of {} chunk(s) lost before the commit ({} buffer full, {} socket behind)",
+                stats.lost_chunks(),
+                stats.captured_chunks,
+                stats.dropped_buffer_full,
+                stats.dropped_socket_behind
+            );
+        }
+        emit(conn.events.as_ref(), EVENT_ELEVENLABS_AUDIO_STATS, stats);
+    }
+
     /// Close gate without commit (if no audio was sent)
     pub async fn close_gate(&self) -> Result<()> {
         let conn_guard = self.connection.lock().await;
@@ -472,6 +783,7 @@ impl ElevenLabsStreamingClient {
         }
         // Use Release to ensure audio thread sees gate closed
         conn.is_transmitting.store(false, Ordering::Release);
+        conn.touch_gate();
         Ok(())
     }
 
@@ -480,7 +792,7 @@ impl ElevenLabsStreamingClient {
         let mut conn_guard = self.connection.lock().await;
 
         if let Some(conn) = conn_guard.take() {
-            tracing::info!("[ElevenLabs] Disconnecting...");
+            tracing::info!("[{}] Disconnecting...", conn.session.name());
 
             // Use Release to ensure all threads see connection is dead
             conn.is_alive.store(false, Ordering::Release);
@@ -491,12 +803,13 @@ impl ElevenLabsStreamingClient {
             // Ждем завершения tasks
             let _ = conn.reader_task.await;
             let _ = conn.keepalive_task.await;
+            let _ = conn.writer_task.await;
 
             // Закрываем WebSocket
             let mut write = conn.write.lock().await;
             let _ = write.send(Message::Close(None)).await;
 
-            tracing::info!("[ElevenLabs] Disconnected");
+            tracing::info!("[{}] Disconnected", conn.session.name());
         }
 
         Ok(())
@@ -513,63 +826,175 @@ impl ElevenLabsStreamingClient {
     }
 }
 
+/// The ElevenLabs realtime protocol: audio as base64 in JSON, a commit flag on the
+/// last chunk of a segment and Close(4001) to reset the context after a dictation
+struct ElevenLabsSession {
+    api_key: String,
+    sample_rate: u32,
+    language_code: String,
+}
+
+impl ElevenLabsSession {
+    fn new(config: &ConnectionConfig) -> Self {
+        Self {
+            api_key: config.api_key.clone(),
+            sample_rate: config.sample_rate,
+            language_code: config.language_code.clone(),
+        }
+    }
+
+    fn message(&self, pcm: &[u8], commit: bool) -> Result<Message> {
+        let message = if commit {
+            Outbound::commit(pcm, self.sample_rate)
+        } else {
+            Outbound::audio(pcm, self.sample_rate)
+        };
+        Ok(Message::Text(message.to_json()?))
+    }
+}
+
+impl StreamingSession for ElevenLabsSession {
+    fn name(&self) -> &'static str {
+        "ElevenLabs"
+    }
+
+    fn default_endpoint(&self) -> &'static str {
+        REALTIME_ENDPOINT
+    }
+
+    fn request(&self, endpoint: &str) -> Result<Request> {
+        // Определяем audio format на основе sample rate
+        let audio_format = match self.sample_rate {
+            8000 => "pcm_8000",
+            16000 => "pcm_16000",
+            22050 => "pcm_22050",
+            24000 => "pcm_24000",
+            44100 => "pcm_44100",
+            48000 => "pcm_48000",
+            _ => {
+                tracing::warn!(
+                    "[ElevenLabs] Unsupported sample rate {}, using pcm_16000",
+                    self.sample_rate
+                );
+                "pcm_16000"
+            }
+        };
+
+        let ws_url = if self.language_code.is_empty() || self.language_code == "auto" {
+            format!(
+                "{}?model_id=scribe_v2_realtime&audio_format={}&commit_strategy=manual&enable_partials=true",
+                endpoint, audio_format
+            )
+        } else {
+            format!(
+                "{}?model_id=scribe_v2_realtime&language_code={}&audio_format={}&commit_strategy=manual&enable_partials=true",
+                endpoint, self.language_code, audio_format
+            )
+        };
+
+        tracing::info!(
+            "[ElevenLabs] Connecting to WebSocket (sample_rate: {}, audio_format: {})",
+            self.sample_rate,
+            audio_format
+        );
+
+        // Создаем HTTP запрос с заголовком xi-api-key (Host и upgrade-заголовки берутся из URL)
+        let mut request = ws_url
+            .into_client_request()
+            .context("Failed to build WebSocket request")?;
+        request.headers_mut().insert(
+            "xi-api-key",
+            HeaderValue::from_str(&self.api_key).context("Invalid API key")?,
+        );
+        Ok(request)
+    }
+
+    /// Split so a backlog flushed at once doesn't go out as one huge frame
+    fn audio(&self, pcm: &[u8]) -> Result<Vec<Message>> {
+        Outbound::chunks(pcm, self.sample_rate, false)
+            .iter()
+            .map(|chunk| Ok(Message::Text(chunk.to_json()?)))
+            .collect()
+    }
+
+    /// `silence` in chunks the length of a captured one, the commit flag on the last
+    fn finalize(&self, silence: &[u8]) -> Result<Vec<Message>> {
+        let mut chunks = silence_chunks(self.sample_rate, silence);
+        let last = chunks.pop().unwrap_or_default();
+        let mut messages = chunks
+            .into_iter()
+            .map(|chunk| self.message(chunk, false))
+            .collect::<Result<Vec<_>>>()?;
+        messages.push(self.message(last, true)?);
+        Ok(messages)
+    }
+
+    fn close(&self) -> Message {
+        Message::Close(Some(CloseFrame {
+            code: CloseCode::Library(4001),
+            reason: "ContextReset".into(),
+        }))
+    }
+
+    fn handle_text(&self, text: &str, events: &dyn StreamingEventSink) -> bool {
+        handle_text_message(text, events)
+    }
+}
+
 /// Background task для чтения сообщений из WebSocket
 async fn message_reader_task(
     mut read: futures_util::stream::SplitStream<WsStream>,
-    _write: Arc<Mutex<futures_util::stream::SplitSink<WsStream, Message>>>,
-    app_handle: AppHandle,
+    _write: WsSink,
+    session: Arc<dyn StreamingSession>,
+    events: EventSink,
     cancel_token: tokio_util::sync::CancellationToken,
     is_alive: Arc<AtomicBool>,
     commit_notify: Arc<Notify>,
 ) {
+    let name = session.name();
     loop {
         tokio::select! {
             _ = cancel_token.cancelled() => {
-                tracing::info!("[ElevenLabs] Reader task cancelled");
+                tracing::info!("[{}] Reader task cancelled", name);
                 break;
             }
             msg_result = read.next() => {
                 match msg_result {
                     Some(Ok(Message::Text(text))) => {
-                        // Side-effects for UI
-                        handle_text_message(&text, &app_handle);
-                        // Notify waiting commit
-                        if let Ok(msg) = serde_json::from_str::<TranscriptMessage>(&text) {
-                            match msg.message_type.as_str() {
-                                "committed_transcript" | "committed_transcript_with_timestamps" => {
-                                    commit_notify.notify_one();
-                                }
-                                _ => {}
-                            }
+                        // Side-effects for UI; a committed transcript wakes the waiting commit
+                        if session.handle_text(&text, events.as_ref()) {
+                            commit_notify.notify_one();
                         }
                     }
                     Some(Ok(Message::Close(frame))) => {
-                        tracing::info!("[ElevenLabs] WebSocket closed: {:?}", frame);
+                        tracing::info!("[{}] WebSocket closed: {:?}", name, frame);
                         let (code, reason) = if let Some(f) = frame {
                             (u16::from(f.code), f.reason.to_string())
                         } else {
                             (1005, "".to_string()) // 1005 = No Status Received
                         };
 
-                        let _ = app_handle.emit("elevenlabs://connection-closed", ConnectionClosedEvent {
+                        emit(events.as_ref(), EVENT_ELEVENLABS_CONNECTION_CLOSED, ConnectionClosedPayload {
                             code,
                             reason,
                         });
                         break;
                     }
                     Some(Ok(Message::Pong(_))) => {
-                        tracing::debug!("[ElevenLabs] Received pong");
+                        tracing::debug!("[{}] Received pong", name);
                     }
                     Some(Err(e)) => {
-                        tracing::error!("[ElevenLabs] WebSocket error: {:?}", e);
-                        let _ = app_handle.emit("elevenlabs://error", ErrorEvent {
+                        tracing::error!("[{}] WebSocket error: {:?}", name, e);
+                        emit(events.as_ref(), EVENT_ELEVENLABS_ERROR, StreamingErrorPayload {
                             error: e.to_string(),
+                            code: ErrorCode::StreamingError,
+                            server_error: None,
                         });
                         break;
                     }
                     None => {
-                        tracing::info!("[ElevenLabs] WebSocket stream ended");
-                        let _ = app_handle.emit("elevenlabs://connection-closed", ConnectionClosedEvent {
+                        tracing::info!("[{}] WebSocket stream ended", name);
+                        emit(events.as_ref(), EVENT_ELEVENLABS_CONNECTION_CLOSED, ConnectionClosedPayload {
                             code: 1006, // Abnormal Closure
                             reason: "Stream ended".to_string(),
                         });
@@ -583,89 +1008,1056 @@ async fn message_reader_task(
     // Use Release to ensure other threads see connection is dead
     is_alive.store(false, Ordering::Release);
     cancel_token.cancel(); // Stop keepalive task
-    tracing::info!("[ElevenLabs] Reader task finished, connection marked dead");
+    tracing::info!("[{}] Reader task finished, connection marked dead", name);
 }
 
 /// Обработка текстовых сообщений от ElevenLabs
-/// Returns true if connection should be closed (committed transcript received)
-fn handle_text_message(text: &str, app_handle: &AppHandle) -> bool {
+/// Returns true if a committed transcript was received
+fn handle_text_message(text: &str, events: &dyn StreamingEventSink) -> bool {
     tracing::debug!("[ElevenLabs] Raw message: {}", text);
 
-    if let Ok(msg) = serde_json::from_str::<TranscriptMessage>(text) {
-        tracing::info!("[ElevenLabs] Message type: {}", msg.message_type);
+    let message = match Inbound::parse(text) {
+        Ok(message) => message,
+        Err(e) => {
+            tracing::warn!("[ElevenLabs] Malformed message: {}", e);
+            return false;
+        }
+    };
+
+    match message {
+        Inbound::SessionStarted(started) => {
+            tracing::info!("[ElevenLabs] Session started: {}", started.session_id);
+            emit(
+                events,
+                EVENT_ELEVENLABS_SESSION_STARTED,
+                SessionStartedPayload {
+                    session_id: started.session_id,
+                },
+            );
+            false
+        }
+        Inbound::PartialTranscript(transcript) => {
+            tracing::info!("[ElevenLabs] Partial: {}", transcript.text);
+            emit(
+                events,
+                EVENT_ELEVENLABS_TRANSCRIPT,
+                StreamingTranscriptPayload {
+                    text: transcript.text,
+                    is_partial: true,
+                    words: None,
+                    language_code: None,
+                },
+            );
+            false
+        }
+        Inbound::CommittedTranscript(transcript) => {
+            tracing::info!("[ElevenLabs] Committed: {}", transcript.text);
+            emit(
+                events,
+                EVENT_ELEVENLABS_TRANSCRIPT,
+                StreamingTranscriptPayload {
+                    text: transcript.text,
+                    is_partial: false,
+                    words: None,
+                    language_code: None,
+                },
+            );
+            true
+        }
+        Inbound::CommittedTranscriptWithTimestamps(transcript) => {
+            tracing::info!(
+                "[ElevenLabs] Committed: {} ({} timed words)",
+                transcript.text,
+                transcript.words.len()
+            );
+            emit(
+                events,
+                EVENT_ELEVENLABS_TRANSCRIPT,
+                StreamingTranscriptPayload {
+                    text: transcript.text,
+                    is_partial: false,
+                    words: Some(transcript.words),
+                    language_code: transcript.language_code,
+                },
+            );
+            true
+        }
+        Inbound::Error(error) => {
+            tracing::error!("[ElevenLabs] Error received: {:?}", error);
+            emit(
+                events,
+                EVENT_ELEVENLABS_ERROR,
+                StreamingErrorPayload {
+                    error: error.to_string(),
+                    code: error.error_code(),
+                    server_error: Some(error),
+                },
+            );
+            false
+        }
+        Inbound::Unknown(message_type) => {
+            tracing::debug!("[ElevenLabs] Unknown message type: {}", message_type);
+            false
+        }
+    }
+}
+
+/// Send `messages` in order, stopping at the first failure
+async fn send_messages(write: &WsSink, messages: Vec<Message>) -> Result<()> {
+    let mut write = write.lock().await;
+    for message in messages {
+        write.send(message).await?;
+    }
+    Ok(())
+}
 
-        match msg.message_type.as_str() {
-            "session_started" => {
-                if let Some(session_id) = msg.session_id {
-                    tracing::info!("[ElevenLabs] Session started: {}", session_id);
-                    let _ = app_handle.emit(
-                        "elevenlabs://session-started",
-                        SessionStartedEvent { session_id },
-                    );
+/// Background task that drains the send queue. Chunks that piled up while a send was
+/// in progress go out as one message; a failed send kills the connection.
+async fn writer_task(
+    write: WsSink,
+    queue: Arc<SendQueue>,
+    session: Arc<dyn StreamingSession>,
+    sample_rate: u32,
+    events: EventSink,
+    cancel_token: tokio_util::sync::CancellationToken,
+    is_alive: Arc<AtomicBool>,
+) {
+    loop {
+        let outgoing = match queue.next() {
+            Some(outgoing) => outgoing,
+            None => {
+                tokio::select! {
+                    _ = cancel_token.cancelled() => break,
+                    _ = queue.ready.notified() => continue,
                 }
-                false
-            }
-            "partial_transcript" => {
-                tracing::info!("[ElevenLabs] Partial: {}", msg.text);
-                let _ = app_handle.emit(
-                    "elevenlabs://transcript",
-                    TranscriptEvent {
-                        text: msg.text,
-                        is_partial: true,
-                    },
-                );
-                false
-            }
-            "committed_transcript" | "committed_transcript_with_timestamps" => {
-                tracing::info!("[ElevenLabs] Committed: {}", msg.text);
-                let _ = app_handle.emit(
-                    "elevenlabs://transcript",
-                    TranscriptEvent {
-                        text: msg.text,
-                        is_partial: false,
-                    },
-                );
-                false
             }
-            "error" | "auth_error" | "quota_exceeded_error" | "input_error" => {
-                tracing::error!("[ElevenLabs] Error received: {:?}", msg);
-                let _ = app_handle.emit(
-                    "elevenlabs://error",
-                    ErrorEvent {
-                        error: format!("{:?}", msg),
-                    },
-                );
-                false
+        };
+
+        let result = match outgoing {
+            Outgoing::Audio { pcm, chunks } => {
+                let send = async { send_messages(&write, session.audio(&pcm)?).await };
+                let result = tokio::select! {
+                    _ = cancel_token.cancelled() => break,
+                    result = send => result,
+                };
+                queue.sent(pcm.len());
+                result.with_context(|| format!("Failed to send {} audio chunk(s)", chunks))
             }
-            _ => {
-                tracing::debug!("[ElevenLabs] Unknown message type: {}", msg.message_type);
-                false
+            Outgoing::Commit { silence, reply } => {
+                let silence = silence_pcm(sample_rate, silence);
+                let send = async { send_messages(&write, session.finalize(&silence)?).await };
+                let result = tokio::select! {
+                    _ = cancel_token.cancelled() => break,
+                    result = send => result,
+                };
+                let failed = result
+                    .as_ref()
+                    .err()
+                    .map(|e| anyhow!("Failed to send commit: {}", e));
+                let _ = reply.send(result);
+                failed.map_or(Ok(()), Err)
             }
+        };
+
+        if let Err(e) = result {
+            tracing::error!("[{}] {:#}", session.name(), e);
+            emit(
+                events.as_ref(),
+                EVENT_ELEVENLABS_ERROR,
+                StreamingErrorPayload {
+                    error: format!("{:#}", e),
+                    code: ErrorCode::StreamingError,
+                    server_error: None,
+                },
+            );
+            is_alive.store(false, Ordering::Release);
+            cancel_token.cancel();
+            break;
         }
-    } else {
-        false
     }
+    tracing::info!("[{}] Writer task finished", session.name());
 }
 
-/// Background task для keep-alive ping
+/// Background task для keep-alive
 async fn keepalive_task(
-    write: Arc<Mutex<futures_util::stream::SplitSink<WsStream, Message>>>,
+    write: WsSink,
+    session: Arc<dyn StreamingSession>,
     cancel_token: tokio_util::sync::CancellationToken,
 ) {
-    let mut interval = interval(Duration::from_secs(10));
+    let mut interval = interval(session.keepalive_interval());
     loop {
         tokio::select! {
             _ = cancel_token.cancelled() => {
-                tracing::info!("[ElevenLabs] Keep-alive task cancelled");
+                tracing::info!("[{}] Keep-alive task cancelled", session.name());
                 break;
             }
             _ = interval.tick() => {
                 let mut guard = write.lock().await;
-                if let Err(e) = guard.send(Message::Ping(vec![])).await {
-                    tracing::error!("[ElevenLabs] Failed to send ping: {}", e);
+                if let Err(e) = guard.send(session.keepalive()).await {
+                    tracing::error!("[{}] Failed to send keep-alive: {}", session.name(), e);
                     break;
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elevenlabs_protocol::MAX_AUDIO_CHUNK_BYTES;
+    use serde_json::{json, Value};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+    use tokio_tungstenite::{
+        accept_hdr_async,
+        tungstenite::handshake::server::{Request, Response},
+    };
+
+    const WAIT: Duration = Duration::from_secs(5);
+    /// Short enough to go out with the commit in one message
+    const SILENCE: Duration = Duration::from_millis(1);
+
+    /// Events the client emitted, in order
+    #[derive(Clone, Default)]
+    struct RecordedEvents(Arc<std::sync::Mutex<Vec<(String, Value)>>>);
+
+    impl StreamingEventSink for RecordedEvents {
+        fn emit_event(&self, event: &str, payload: Value) {
+            self.0.lock().unwrap().push((event.to_string(), payload));
+        }
+    }
+
+    impl RecordedEvents {
+        fn all(&self, event: &str) -> Vec<Value> {
+            self.0
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(name, _)| name == event)
+                .map(|(_, payload)| payload.clone())
+                .collect()
+        }
+
+        async fn wait_for(&self, event: &str) -> Value {
+            timeout(WAIT, async {
+                loop {
+                    if let Some(payload) = self.all(event).into_iter().next() {
+                        return payload;
+                    }
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            })
+            .await
+            .unwrap_or_else(|_| panic!("no {event} event"))
+        }
+    }
+
+    /// Local WebSocket server standing in for the realtime service; each test scripts
+    /// the server side of its connections in the protocol it tests
+    struct MockServer {
+        url: String,
+        connections: mpsc::UnboundedReceiver<MockConnection>,
+    }
+
+    struct MockConnection {
+        uri: String,
+        /// `xi-api-key` header (ElevenLabs)
+        api_key: Option<String>,
+        /// `Authorization` header (Deepgram)
+        authorization: Option<String>,
+        ws: WebSocketStream<TcpStream>,
+    }
+
+    impl MockServer {
+        async fn start() -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!(
+                "ws://{}/v1/speech-to-text/realtime",
+                listener.local_addr().unwrap()
+            );
+            let (tx, connections) = mpsc::unbounded_channel();
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let mut uri = String::new();
+                    let mut api_key = None;
+                    let mut authorization = None;
+                    let ws = accept_hdr_async(stream, |request: &Request, response: Response| {
+                        let header = |name: &str| {
+                            request
+                                .headers()
+                                .get(name)
+                                .and_then(|value| value.to_str().ok())
+                                .map(str::to_string)
+                        };
+                        uri = request.uri().to_string();
+                        api_key = header("xi-api-key");
+                        authorization = header("authorization");
+                        Ok(response)
+                    })
+                    .await
+                    .unwrap();
+                    let connection = MockConnection {
+                        uri,
+                        api_key,
+                        authorization,
+                        ws,
+                    };
+                    if tx.send(connection).is_err() {
+                        break;
+                    }
+                }
+            });
+            Self { url, connections }
+        }
+
+        async fn accept(&mut self) -> MockConnection {
+            timeout(WAIT, self.connections.recv())
+                .await
+                .expect("client did not connect")
+                .unwrap()
+        }
+    }
+
+    impl MockConnection {
+        async fn send_json(&mut self, message: Value) {
+            self.ws
+                .send(Message::Text(message.to_string()))
+                .await
+                .unwrap();
+        }
+
+        /// Next JSON message from the client; None once it closed the socket
+        async fn next_json(&mut self) -> Option<Value> {
+            while let Some(message) = self.ws.next().await {
+                match message {
+                    Ok(Message::Text(text)) => return Some(serde_json::from_str(&text).unwrap()),
+                    Ok(Message::Close(_)) | Err(_) => return None,
+                    Ok(_) => {}
+                }
+            }
+            None
+        }
+
+        /// Next text or binary message from the client, skipping pings; None once it
+        /// closed the socket
+        async fn next_frame(&mut self) -> Option<Message> {
+            while let Some(message) = self.ws.next().await {
+                match message {
+                    Ok(message @ (Message::Text(_) | Message::Binary(_))) => return Some(message),
+                    Ok(Message::Close(_)) | Err(_) => return None,
+                    Ok(_) => {}
+                }
+            }
+            None
+        }
+
+        /// Read until the client closes; returns its close code. Reading also flushes
+        /// the close reply the client is waiting for.
+        async fn finish(mut self) -> Option<u16> {
+            let mut code = None;
+            while let Some(message) = self.ws.next().await {
+                match message {
+                    Ok(Message::Close(frame)) => code = frame.map(|f| u16::from(f.code)),
+                    Err(_) => break,
+                    Ok(_) => {}
+                }
+            }
+            code
+        }
+    }
+
+    fn config(provider: StreamingProvider, language_code: &str) -> ConnectionConfig {
+        ConnectionConfig {
+            provider,
+            api_key: "test-key".to_string(),
+            sample_rate: 16000,
+            language_code: language_code.to_string(),
+        }
+    }
+
+    async fn connected_to(
+        server: &mut MockServer,
+        events: &RecordedEvents,
+        config: ConnectionConfig,
+    ) -> (StreamingClient, MockConnection) {
+        let client = StreamingClient::with_endpoint(&server.url);
+        client.connect(config, WAIT, events.clone()).await.unwrap();
+        let connection = server.accept().await;
+        (client, connection)
+    }
+
+    async fn connected(
+        server: &mut MockServer,
+        events: &RecordedEvents,
+        language_code: &str,
+    ) -> (StreamingClient, MockConnection) {
+        let config = config(StreamingProvider::ElevenLabs, language_code);
+        connected_to(server, events, config).await
+    }
+
+    async fn wait_until_dead(client: &StreamingClient) {
+        timeout(WAIT, async {
+            while client.is_connected().await {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("connection still alive");
+    }
+
+    #[test]
+    fn test_text_messages_become_events() {
+        let events = RecordedEvents::default();
+        let committed = json!({
+            "message_type": "committed_transcript_with_timestamps",
+            "text": "hi there",
+            "language_code": "en",
+            "words": [
+                {"text": "hi", "start": 0.1, "end": 0.3, "type": "word"},
+                {"text": " ", "start": 0.3, "end": 0.35, "type": "spacing"},
+                {"text": "there", "start": 0.35, "end": 0.7, "type": "word"},
+            ],
+        });
+        assert!(handle_text_message(&committed.to_string(), &events));
+        assert!(!handle_text_message(
+            r#"{"message_type":"quota_exceeded","error":"Out of credits"}"#,
+            &events
+        ));
+        assert!(!handle_text_message("{truncated", &events));
+
+        let transcript = &events.all(EVENT_ELEVENLABS_TRANSCRIPT)[0];
+        assert_eq!(transcript["is_partial"], false);
+        assert_eq!(transcript["language_code"], "en");
+        assert_eq!(transcript["words"][2]["text"], "there");
+        assert_eq!(transcript["words"][1]["type"], "spacing");
+
+        let error = &events.all(EVENT_ELEVENLABS_ERROR)[0];
+        assert_eq!(error["code"], "quota_exceeded");
+        assert_eq!(error["server_error"]["kind"], "quota_exceeded");
+        assert_eq!(error["server_error"]["message"], "Out of credits");
+    }
+
+    #[test]
+    fn test_queue_coalesces_pending_chunks() {
+        let queue = SendQueue::new(MAX_IN_FLIGHT_BYTES);
+        for chunk in [vec![1, 2], vec![3], vec![4, 5, 6]] {
+            assert!(queue.push_audio(chunk));
+        }
+        assert_eq!(queue.in_flight_bytes(), 6);
+
+        let Some(Outgoing::Audio { pcm, chunks }) = queue.next() else {
+            panic!("expected audio");
+        };
+        assert_eq!((pcm, chunks), (vec![1, 2, 3, 4, 5, 6], 3));
+        assert_eq!(queue.coalesced.load(Ordering::Relaxed), 2);
+        assert!(queue.next().is_none());
+
+        // Still in flight until the writer reports it sent
+        assert_eq!(queue.in_flight_bytes(), 6);
+        queue.sent(6);
+        assert_eq!(queue.in_flight_bytes(), 0);
+    }
+
+    #[test]
+    fn test_queue_splits_at_message_size() {
+        let queue = SendQueue::new(MAX_IN_FLIGHT_BYTES);
+        let half = MAX_MESSAGE_BYTES / 2;
+        for _ in 0..3 {
+            assert!(queue.push_audio(vec![0; half]));
+        }
+
+        let Some(Outgoing::Audio { chunks, .. }) = queue.next() else {
+            panic!("expected audio");
+        };
+        assert_eq!(chunks, 2);
+        let Some(Outgoing::Audio { chunks, .. }) = queue.next() else {
+            panic!("expected audio");
+        };
+        assert_eq!(chunks, 1);
+        assert_eq!(queue.coalesced.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_oversized_audio_is_sent_in_several_frames() {
+        let session = ElevenLabsSession::new(&ConnectionConfig {
+            provider: StreamingProvider::ElevenLabs,
+            api_key: "xi-key".to_string(),
+            sample_rate: 16000,
+            language_code: "en".to_string(),
+        });
+        let messages = session.audio(&vec![0; MAX_AUDIO_CHUNK_BYTES + 2]).unwrap();
+        assert_eq!(messages.len(), 2);
+        for message in &messages {
+            let Message::Text(text) = message else {
+                panic!("expected a text frame");
+            };
+            let json: Value = serde_json::from_str(text).unwrap();
+            assert_eq!(json["commit"], false);
+        }
+        assert_eq!(session.audio(&[0; 4]).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_queue_drops_chunks_over_in_flight_cap() {
+        let queue = SendQueue::new(8);
+        assert!(queue.push_audio(vec![0; 6]));
+        assert!(!queue.push_audio(vec![0; 4]));
+        assert!(queue.push_audio(vec![0; 2]));
+        assert_eq!(queue.dropped.load(Ordering::Relaxed), 1);
+
+        // Sending frees room again
+        let Some(Outgoing::Audio { pcm, .. }) = queue.next() else {
+            panic!("expected audio");
+        };
+        queue.sent(pcm.len());
+        assert!(queue.push_audio(vec![0; 4]));
+    }
+
+    #[test]
+    fn test_queue_sends_commit_last_and_alone() {
+        let queue = SendQueue::new(MAX_IN_FLIGHT_BYTES);
+        assert!(queue.push_audio(vec![1]));
+        let (reply, _sent) = oneshot::channel();
+        queue.push_commit(Duration::ZERO, reply);
+
+        assert!(matches!(
+            queue.next(),
+            Some(Outgoing::Audio { chunks: 1, .. })
+        ));
+        assert!(matches!(queue.next(), Some(Outgoing::Commit { .. })));
+        assert!(queue.next().is_none());
+    }
+
+    #[test]
+    fn test_queue_keeps_later_audio_behind_commit() {
+        let queue = SendQueue::new(MAX_IN_FLIGHT_BYTES);
+        assert!(queue.push_audio(vec![1]));
+        assert!(queue.push_audio(vec![2]));
+        let (reply, _sent) = oneshot::channel();
+        queue.push_commit(Duration::ZERO, reply);
+        // The next segment starts before the writer got to the commit
+        assert!(queue.push_audio(vec![3]));
+
+        let Some(Outgoing::Audio { pcm, chunks }) = queue.next() else {
+            panic!("expected audio");
+        };
+        assert_eq!((pcm, chunks), (vec![1, 2], 2));
+        assert!(matches!(queue.next(), Some(Outgoing::Commit { .. })));
+        let Some(Outgoing::Audio { pcm, .. }) = queue.next() else {
+            panic!("expected audio");
+        };
+        assert_eq!(pcm, vec![3]);
+        assert!(queue.next().is_none());
+    }
+
+    #[test]
+    fn test_silence_length() {
+        assert_eq!(silence_pcm(16_000, Duration::from_millis(1)).len(), 32);
+        assert_eq!(silence_pcm(16_000, Duration::from_millis(200)).len(), 6_400);
+        assert_eq!(
+            silence_pcm(44_100, Duration::from_millis(250)).len(),
+            22_050
+        );
+        assert_eq!(
+            silence_pcm(48_000, Duration::from_millis(1_000)).len(),
+            96_000
+        );
+        assert!(silence_pcm(16_000, Duration::ZERO).is_empty());
+        assert!(silence_pcm(16_000, Duration::from_millis(200))
+            .iter()
+            .all(|&b| b == 0));
+    }
+
+    #[tokio::test]
+    async fn test_state_reports_send_counters() {
+        let mut server = MockServer::start().await;
+        let events = RecordedEvents::default();
+        let (client, mut conn) = connected(&mut server, &events, "auto").await;
+        assert_eq!(
+            client.state().await,
+            StreamingState {
+                connected: true,
+                ..StreamingState::default()
+            }
+        );
+
+        // Audio before the gate opens is ignored and doesn't count for the dictation
+        client.send_audio_chunk(vec![9, 9], true).await.unwrap();
+        assert_eq!(client.audio_stats().ignored_gate_closed, 1);
+
+        assert_eq!(client.gate_openings(), 0);
+        client.open_gate().await.unwrap();
+        assert_eq!(client.gate_openings(), 1);
+        client.send_audio_chunk(vec![1, 2], true).await.unwrap();
+        let chunk = conn.next_json().await.unwrap();
+        assert_eq!(chunk["message_type"], "input_audio_chunk");
+
+        let state = client.state().await;
+        assert!(state.transmitting);
+        assert_eq!(state.dropped_chunks, 0);
+        assert_eq!(
+            state.audio,
+            AudioStats {
+                sent_chunks: 1,
+                voiced_chunks: 1,
+                bytes_sent: 2,
+                ..AudioStats::default()
+            }
+        );
+
+        client.disconnect().await.unwrap();
+        assert_eq!(client.state().await, StreamingState::default());
+    }
+
+    #[tokio::test]
+    async fn test_commit_happy_path() {
+        let mut server = MockServer::start().await;
+        let events = RecordedEvents::default();
+        let (client, mut conn) = connected(&mut server, &events, "auto").await;
+        assert_eq!(conn.api_key.as_deref(), Some("test-key"));
+        assert!(!conn.uri.contains("language_code"));
+
+        conn.send_json(json!({"message_type": "session_started", "session_id": "s-1"}))
+            .await;
+        let started = events.wait_for(EVENT_ELEVENLABS_SESSION_STARTED).await;
+        assert_eq!(started["session_id"], "s-1");
+
+        client.open_gate().await.unwrap();
+        client
+            .send_audio_chunk(vec![1, 2, 3, 4], true)
+            .await
+            .unwrap();
+        assert!(client.has_audio_since_open().await);
+        assert_eq!(client.chunks_since_open().await, 1);
+
+        let server_side = tokio::spawn(async move {
+            let chunk = conn.next_json().await.unwrap();
+            assert_eq!(chunk["message_type"], "input_audio_chunk");
+            assert_eq!(chunk["commit"], false);
+            conn.send_json(json!({"message_type": "partial_transcript", "text": "hel"}))
+                .await;
+
+            let commit = conn.next_json().await.unwrap();
+            assert_eq!(commit["commit"], true);
+            conn.send_json(json!({"message_type": "committed_transcript", "text": "hello"}))
+                .await;
+            conn.finish().await
+        });
+
+        client.close_gate_and_commit(SILENCE).await.unwrap();
+        assert_eq!(server_side.await.unwrap(), Some(4001));
+
+        let stats = events.wait_for(EVENT_ELEVENLABS_AUDIO_STATS).await;
+        assert_eq!(stats["sent_chunks"], 1);
+        assert_eq!(stats["bytes_sent"], 4);
+        assert_eq!(stats["dropped_buffer_full"], 0);
+
+        assert_eq!(
+            events.all(EVENT_ELEVENLABS_TRANSCRIPT),
+            vec![
+                json!({"text": "hel", "is_partial": true}),
+                json!({"text": "hello", "is_partial": false}),
+            ]
+        );
+        assert!(events.all(EVENT_ELEVENLABS_ERROR).is_empty());
+        assert!(!client.is_connected().await);
+    }
+
+    #[tokio::test]
+    async fn test_segment_commit_keeps_streaming() {
+        let mut server = MockServer::start().await;
+        let events = RecordedEvents::default();
+        let (client, mut conn) = connected(&mut server, &events, "auto").await;
+
+        client.open_gate().await.unwrap();
+        client.send_audio_chunk(vec![1, 2], true).await.unwrap();
+
+        let server_side = tokio::spawn(async move {
+            assert_eq!(conn.next_json().await.unwrap()["commit"], false);
+            assert_eq!(conn.next_json().await.unwrap()["commit"], true);
+            conn.send_json(json!({"message_type": "committed_transcript", "text": "first"}))
+                .await;
+            conn
+        });
+        assert!(client.commit_segment(SILENCE).await.unwrap());
+        let mut conn = server_side.await.unwrap();
+
+        // Still connected with the gate open; nothing new to commit yet
+        let state = client.state().await;
+        assert!(state.connected && state.transmitting && !state.committing);
+        assert!(!client.has_audio_since_open().await);
+        assert!(!client.commit_segment(SILENCE).await.unwrap());
+
+        client.send_audio_chunk(vec![3, 4], true).await.unwrap();
+        let server_side = tokio::spawn(async move {
+            assert_eq!(conn.next_json().await.unwrap()["commit"], false);
+            assert_eq!(conn.next_json().await.unwrap()["commit"], true);
+            conn.send_json(json!({"message_type": "committed_transcript", "text": "second"}))
+                .await;
+            conn.finish().await
+        });
+        client.close_gate_and_commit(SILENCE).await.unwrap();
+        assert_eq!(server_side.await.unwrap(), Some(4001));
+
+        assert_eq!(
+            events.all(EVENT_ELEVENLABS_TRANSCRIPT),
+            vec![
+                json!({"text": "first", "is_partial": false}),
+                json!({"text": "second", "is_partial": false}),
+            ]
+        );
+        assert!(events.all(EVENT_ELEVENLABS_ERROR).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_commit_silence_is_sent_in_chunks() {
+        use base64::Engine;
+
+        let mut server = MockServer::start().await;
+        let events = RecordedEvents::default();
+        let (client, mut conn) = connected(&mut server, &events, "auto").await;
+
+        client.open_gate().await.unwrap();
+        client.send_audio_chunk(vec![1, 2], true).await.unwrap();
+        let server_side = tokio::spawn(async move {
+            let mut messages = Vec::new();
+            while let Some(message) = conn.next_json().await {
+                let decoded = base64::engine::general_purpose::STANDARD
+                    .decode(message["audio_base_64"].as_str().unwrap())
+                    .unwrap();
+                let commit = message["commit"] == true;
+                messages.push((decoded, commit));
+                if commit {
+                    break;
+                }
+            }
+            conn.send_json(json!({"message_type": "committed_transcript", "text": "done"}))
+                .await;
+            conn.finish().await;
+            messages
+        });
+        client
+            .close_gate_and_commit(Duration::from_millis(250))
+            .await
+            .unwrap();
+
+        // 250 ms at 16 kHz after the audio: two 100 ms chunks, then 50 ms with the commit
+        let messages = server_side.await.unwrap();
+        let lengths: Vec<(usize, bool)> = messages
+            .iter()
+            .map(|(pcm, commit)| (pcm.len(), *commit))
+            .collect();
+        assert_eq!(
+            lengths,
+            vec![(2, false), (3_200, false), (3_200, false), (1_600, true)]
+        );
+        assert!(messages[1..]
+            .iter()
+            .all(|(pcm, _)| pcm.iter().all(|&b| b == 0)));
+
+        // No silence: the commit goes out empty
+        let (client, mut conn) = connected(&mut server, &events, "auto").await;
+        client.open_gate().await.unwrap();
+        client.send_audio_chunk(vec![1, 2], true).await.unwrap();
+        let server_side = tokio::spawn(async move {
+            assert_eq!(conn.next_json().await.unwrap()["commit"], false);
+            let commit = conn.next_json().await.unwrap();
+            conn.send_json(json!({"message_type": "committed_transcript", "text": "done"}))
+                .await;
+            conn.finish().await;
+            commit
+        });
+        client.close_gate_and_commit(Duration::ZERO).await.unwrap();
+        let commit = server_side.await.unwrap();
+        assert_eq!(
+            (commit["audio_base_64"].as_str(), commit["commit"].as_bool()),
+            (Some(""), Some(true))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_segment_commit_needs_open_gate() {
+        let mut server = MockServer::start().await;
+        let events = RecordedEvents::default();
+        let (client, _conn) = connected(&mut server, &events, "auto").await;
+        assert!(client.commit_segment(SILENCE).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_commit_timeout() {
+        let mut server = MockServer::start().await;
+        let events = RecordedEvents::default();
+        let (client, mut conn) = connected(&mut server, &events, "en").await;
+
+        client.open_gate().await.unwrap();
+        client.send_audio_chunk(vec![0; 32], true).await.unwrap();
+
+        // The server takes the commit but never answers it
+        let server_side = tokio::spawn(async move {
+            while let Some(message) = conn.next_json().await {
+                if message["commit"] == true {
+                    break;
+                }
+            }
+            conn.finish().await
+        });
+
+        client.close_gate_and_commit(SILENCE).await.unwrap();
+        assert_eq!(server_side.await.unwrap(), Some(4001));
+
+        let error = events.wait_for(EVENT_ELEVENLABS_ERROR).await;
+        assert_eq!(error["code"], json!(ErrorCode::CommitTimeout));
+        assert!(events.all(EVENT_ELEVENLABS_TRANSCRIPT).is_empty());
+        assert!(!client.is_connected().await);
+    }
+
+    #[tokio::test]
+    async fn test_commit_without_audio_is_skipped() {
+        let mut server = MockServer::start().await;
+        let events = RecordedEvents::default();
+        let (client, mut conn) = connected(&mut server, &events, "auto").await;
+
+        client.open_gate().await.unwrap();
+        client.close_gate_and_commit(SILENCE).await.unwrap();
+
+        // Nothing was sent and the connection stays up for the next gate
+        assert!(timeout(Duration::from_millis(200), conn.next_json())
+            .await
+            .is_err());
+        assert!(!client.is_committing().await);
+        assert!(client.is_connected().await);
+        assert!(events.all(EVENT_ELEVENLABS_ERROR).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_idle_clock_follows_the_gate() {
+        let mut server = MockServer::start().await;
+        let events = RecordedEvents::default();
+        let (client, _conn) = connected(&mut server, &events, "en").await;
+
+        assert!(client.idle_for().await.is_some());
+        client.open_gate().await.unwrap();
+        assert_eq!(client.idle_for().await, None);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        client.close_gate().await.unwrap();
+        assert!(client.idle_for().await.unwrap() < Duration::from_millis(50));
+
+        // An idle disconnect keeps what the next press reconnects with
+        client.disconnect().await.unwrap();
+        assert_eq!(client.idle_for().await, None);
+        assert!(client.get_last_config().await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_server_initiated_close() {
+        let mut server = MockServer::start().await;
+        let events = RecordedEvents::default();
+        let (client, mut conn) = connected(&mut server, &events, "auto").await;
+
+        conn.ws
+            .close(Some(CloseFrame {
+                code: CloseCode::Policy,
+                reason: "session expired".into(),
+            }))
+            .await
+            .unwrap();
+
+        let closed = events.wait_for(EVENT_ELEVENLABS_CONNECTION_CLOSED).await;
+        assert_eq!(closed, json!({"code": 1008, "reason": "session expired"}));
+        wait_until_dead(&client).await;
+        assert!(client.open_gate().await.is_err());
+        assert!(client.send_audio_chunk(vec![0; 4], true).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_muted_microphone_keeps_the_gate_closed() {
+        let mut server = MockServer::start().await;
+        let events = RecordedEvents::default();
+        let (client, _conn) = connected(&mut server, &events, "auto").await;
+        let mute = MicMute::default();
+        let client = client.with_mic_mute(mute.clone());
+
+        mute.set(true);
+        let err = client.open_gate().await.unwrap_err();
+        assert!(err.is::<crate::audio::MicMuted>());
+        assert!(!client.state().await.transmitting);
+        assert_eq!(client.gate_openings(), 0);
+
+        mute.set(false);
+        client.open_gate().await.unwrap();
+        assert!(client.state().await.transmitting);
+    }
+
+    #[tokio::test]
+    async fn test_stalled_handshake_times_out() {
+        // Accepts TCP connections but never answers the WebSocket upgrade
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!(
+            "ws://{}/v1/speech-to-text/realtime",
+            listener.local_addr().unwrap()
+        );
+        let _server = tokio::spawn(async move {
+            let mut sockets = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                sockets.push(socket);
+            }
+        });
+
+        let client = StreamingClient::with_endpoint(url);
+        let err = client
+            .connect(
+                config(StreamingProvider::ElevenLabs, "auto"),
+                Duration::from_millis(200),
+                RecordedEvents::default(),
+            )
+            .await
+            .unwrap_err();
+        assert!(err.is::<ConnectTimeout>());
+        assert!(!client.is_connected().await);
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_with_last_config() {
+        let mut server = MockServer::start().await;
+        let events = RecordedEvents::default();
+        let (client, mut conn) = connected(&mut server, &events, "en").await;
+
+        conn.ws.close(None).await.unwrap();
+        wait_until_dead(&client).await;
+
+        let last_config = client.get_last_config().await.unwrap();
+        assert_eq!(last_config, config(StreamingProvider::ElevenLabs, "en"));
+        client
+            .connect(last_config, WAIT, events.clone())
+            .await
+            .unwrap();
+
+        let conn = server.accept().await;
+        assert_eq!(conn.api_key.as_deref(), Some("test-key"));
+        assert!(conn.uri.contains("language_code=en"));
+        assert!(conn.uri.contains("audio_format=pcm_16000"));
+        assert!(client.is_connected().await);
+
+        // A live connection is not replaced
+        let other = ConnectionConfig {
+            api_key: "other-key".to_string(),
+            ..config(StreamingProvider::ElevenLabs, "en")
+        };
+        assert!(client.connect(other, WAIT, events.clone()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_deepgram_commit_happy_path() {
+        let mut server = MockServer::start().await;
+        let events = RecordedEvents::default();
+        let config = config(StreamingProvider::Deepgram, "auto");
+        let (client, mut conn) = connected_to(&mut server, &events, config).await;
+        assert_eq!(conn.authorization.as_deref(), Some("Token test-key"));
+        assert!(conn.uri.contains("encoding=linear16&sample_rate=16000"));
+        assert!(conn.uri.contains("language=multi"));
+
+        client.open_gate().await.unwrap();
+        client
+            .send_audio_chunk(vec![1, 2, 3, 4], true)
+            .await
+            .unwrap();
+
+        let server_side = tokio::spawn(async move {
+            assert_eq!(
+                conn.next_frame().await.unwrap(),
+                Message::Binary(vec![1, 2, 3, 4])
+            );
+            conn.send_json(json!({
+                "type": "Results",
+                "channel": {"alternatives": [{"transcript": "hel"}]},
+                "is_final": false,
+            }))
+            .await;
+
+            // The silence goes out as audio, then the segment is finalized
+            assert_eq!(
+                conn.next_frame().await.unwrap(),
+                Message::Binary(vec![0; 32])
+            );
+            let finalize = conn.next_json().await.unwrap();
+            assert_eq!(finalize, json!({"type": "Finalize"}));
+            conn.send_json(json!({
+                "type": "Results",
+                "channel": {"alternatives": [{"transcript": "hello"}]},
+                "is_final": true,
+                "from_finalize": true,
+            }))
+            .await;
+
+            let close = conn.next_json().await.unwrap();
+            assert_eq!(close, json!({"type": "CloseStream"}));
+            conn.send_json(json!({"type": "Metadata", "request_id": "r-1"}))
+                .await;
+            conn.ws.close(None).await.unwrap();
+            conn.finish().await
+        });
+
+        client.close_gate_and_commit(SILENCE).await.unwrap();
+        server_side.await.unwrap();
+
+        let stats = events.wait_for(EVENT_ELEVENLABS_AUDIO_STATS).await;
+        assert_eq!(stats["bytes_sent"], 4);
+        assert_eq!(
+            events.all(EVENT_ELEVENLABS_TRANSCRIPT),
+            vec![
+                json!({"text": "hel", "is_partial": true}),
+                json!({"text": "hello", "is_partial": false}),
+            ]
+        );
+        assert!(events.all(EVENT_ELEVENLABS_ERROR).is_empty());
+        assert!(!client.is_connected().await);
+    }
+
+    #[tokio::test]
+    async fn test_deepgram_segment_commit_keeps_streaming() {
+        let mut server = MockServer::start().await;
+        let events = RecordedEvents::default();
+        let config = config(StreamingProvider::Deepgram, "en");
+        let (client, mut conn) = connected_to(&mut server, &events, config).await;
+        assert!(conn.uri.contains("language=en"));
+
+        client.open_gate().await.unwrap();
+        client.send_audio_chunk(vec![1, 2], true).await.unwrap();
+        let server_side = tokio::spawn(async move {
+            assert!(matches!(conn.next_frame().await, Some(Message::Binary(_))));
+            // Deepgram finalized part of the speech on its own before the commit
+            conn.send_json(json!({
+                "type": "Results",
+                "channel": {"alternatives": [{"transcript": "first"}]},
+                "is_final": true,
+            }))
+            .await;
+            while conn.next_json().await.unwrap() != json!({"type": "Finalize"}) {}
+            conn.send_json(json!({
+                "type": "Results",
+                "channel": {"alternatives": [{"transcript": "segment"}]},
+                "is_final": true,
+                "from_finalize": true,
+            }))
+            .await;
+            conn
+        });
+        assert!(client.commit_segment(Duration::ZERO).await.unwrap());
+        let _conn = server_side.await.unwrap();
+
+        let state = client.state().await;
+        assert!(state.connected && state.transmitting && !state.committing);
+        assert_eq!(
+            events.all(EVENT_ELEVENLABS_TRANSCRIPT),
+            vec![
+                json!({"text": "first", "is_partial": true}),
+                json!({"text": "first segment", "is_partial": false}),
+            ]
+        );
+        assert_eq!(
+            client.get_last_config().await.unwrap().provider,
+            StreamingProvider::Deepgram
+        );
+    }
+}
@@ -1,8 +1,14 @@
-﻿use anyhow::{anyhow, Context, Result};
-use reqwest::{multipart::Form, Client};
-use serde::Deserialize;
+use anyhow::{Context, Result};
+use reqwest::Client;
 
-use crate::openai::TranscriptionRequest;
+use crate::{
+    openai::{ModelsResponse, TranscriptionRequest, TranscriptionResponse},
+    speech_to_text::{HttpStatusError, ProviderError},
+};
+
+pub const DEFAULT_BASE_URL: &str = "https://api.groq.com/openai";
+/// Model sent when none is configured
+const DEFAULT_MODEL: &str = "whisper-large-v3-turbo";
 
 #[derive(Clone)]
 pub struct GroqClient {
@@ -10,71 +16,271 @@ pub struct GroqClient {
     base_url: String,
 }
 
-#[derive(Deserialize)]
-struct TranscriptionResponse {
-    text: String,
-}
-
 impl GroqClient {
     pub fn new() -> Result<Self> {
+        Self::with_base_url(DEFAULT_BASE_URL.to_string())
+    }
+
+    pub fn with_base_url(base_url: String) -> Result<Self> {
         let client = Client::builder()
             .build()
             .context("Failed to build HTTP client for Groq")?;
-        let base_url = "https://api.groq.com/openai".to_string();
         Ok(Self { client, base_url })
     }
 
+    /// Cheap authenticated request that succeeds only if the key is accepted
+    pub async fn check_key(&self, api_key: &str) -> Result<()> {
+        let url = format!("{}/v1/models", self.base_url.trim_end_matches('/'));
+        let response = self
+            .client
+            .get(url)
+            .bearer_auth(api_key)
+            .send()
+            .await
+            .context("Groq key check request failed")?;
+
+        if !response.status().is_success() {
+            return Err(HttpStatusError::new("Groq", response.status()).into());
+        }
+        Ok(())
+    }
+
+    /// Ids of every model the key can use, without the `groq/` prefix
+    pub async fn list_models(&self, api_key: &str) -> Result<Vec<String>> {
+        let url = format!("{}/v1/models", self.base_url.trim_end_matches('/'));
+        let response = self
+            .client
+            .get(url)
+            .bearer_auth(api_key)
+            .send()
+            .await
+            .context("Groq model list request failed")?;
+
+        if !response.status().is_success() {
+            return Err(HttpStatusError::new("Groq", response.status()).into());
+        }
+        let payload: ModelsResponse = response
+            .json()
+            .await
+            .context("Failed to parse Groq model list")?;
+        Ok(payload.into_ids())
+    }
+
     pub async fn transcribe(&self, job: TranscriptionRequest) -> Result<String> {
+        let payload = self.post_transcription(job).await?;
+        Ok(payload.text.trim().to_string())
+    }
+
+    /// Transcribe with `verbose_json`: the text with its segments and the detected
+    /// language
+    #[allow(dead_code)] // Nothing asks for segments yet
+    pub async fn transcribe_verbose(
+        &self,
+        job: TranscriptionRequest,
+    ) -> Result<TranscriptionResponse> {
+        let mut payload = self
+            .post_transcription(TranscriptionRequest {
+                verbose: true,
+                ..job
+            })
+            .await?;
+        payload.text = payload.text.trim().to_string();
+        Ok(payload)
+    }
+
+    async fn post_transcription(&self, job: TranscriptionRequest) -> Result<TranscriptionResponse> {
         if job.api_key.trim().is_empty() {
-            return Err(anyhow!("Groq API key is missing"));
+            return Err(ProviderError::MissingApiKey("Groq").into_anyhow());
         }
 
         let url = format!(
             "{}/v1/audio/transcriptions",
             self.base_url.trim_end_matches('/')
         );
-
-        let part = reqwest::multipart::Part::bytes(job.audio_wav)
-            .file_name("clip.wav")
-            .mime_str("audio/wav")
-            .context("Failed to build multipart payload for transcription")?;
-
-        let model = if job.model.starts_with("groq/") {
-            job.model
-                .strip_prefix("groq/")
-                .unwrap_or(&job.model)
-                .to_string()
-        } else {
-            "whisper-large-v3-turbo".to_string()
-        };
-
-        let form = Form::new()
-            .text("model", model)
-            .text("response_format", "json")
-            .part("file", part);
+        let api_key = job.api_key.clone();
+        let model = request_model(&job.model);
+        let form = job.into_form(model)?;
 
         let response = self
             .client
             .post(url)
-            .bearer_auth(job.api_key)
+            .bearer_auth(api_key)
             .multipart(form)
             .send()
             .await
             .context("Groq transcription request failed")?;
 
         if !response.status().is_success() {
-            let status = response.status();
-            let body = response
-                .text()
+            return Err(HttpStatusError::from_response("Groq", response)
                 .await
-                .unwrap_or_else(|_| "<failed to read error body>".into());
-            return Err(anyhow!("Groq responded with {}: {}", status, body));
+                .into());
         }
 
-        let payload: TranscriptionResponse = response
+        response
             .json()
             .await
-            .context("Failed to parse Groq transcription response")?;
-        Ok(payload.text.trim().to_string())
+            .context("Failed to parse Groq transcription response")
+    }
+}
+
+/// Model id as the API knows it: settings keep Groq models as `groq/<id>`, but a
+/// model entered without the prefix is sent as it is
+fn request_model(model: &str) -> String {
+    let model = model.trim();
+    let model = model.strip_prefix("groq/").unwrap_or(model);
+    if model.is_empty() {
+        DEFAULT_MODEL.to_string()
+    } else {
+        model.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        sync::oneshot,
+    };
+
+    /// Local stand-in for the Groq API: answers one transcription with `body` and
+    /// hands over the raw request it received
+    async fn transcription_server(body: &'static str) -> (String, oneshot::Receiver<String>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, rx) = oneshot::channel();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut chunk = [0u8; 4096];
+            // Read the headers, then as much body as they announce
+            loop {
+                let n = socket.read(&mut chunk).await.unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&chunk[..n]);
+                let text = String::from_utf8_lossy(&request).to_string();
+                if let Some(end) = text.find("\r\n\r\n") {
+                    let length = text
+                        .lines()
+                        .find_map(|line| {
+                            line.to_lowercase()
+                                .strip_prefix("content-length:")
+                                .map(|v| v.trim().parse::<usize>().unwrap())
+                        })
+                        .unwrap_or(0);
+                    if request.len() >= end + 4 + length {
+                        break;
+                    }
+                }
+            }
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\n\
+                 content-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            let _ = socket.write_all(response.as_bytes()).await;
+            let _ = tx.send(String::from_utf8_lossy(&request).to_string());
+        });
+        (url, rx)
+    }
+
+    /// Value of the multipart field `name` in a raw request
+    fn form_field(request: &str, name: &str) -> Option<String> {
+        let marker = format!("name=\"{name}\"\r\n\r\n");
+        let start = request.find(&marker)? + marker.len();
+        let end = request[start..].find("\r\n")?;
+        Some(request[start..start + end].to_string())
+    }
+
+    fn job(model: &str) -> TranscriptionRequest {
+        TranscriptionRequest {
+            api_key: "gsk-test".to_string(),
+            model: model.to_string(),
+            audio_wav: b"RIFF....WAVE".to_vec(),
+            ..TranscriptionRequest::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_request_sends_the_optional_fields() {
+        let (url, request) = transcription_server(r#"{"text":" Привет "}"#).await;
+        let client = GroqClient::with_base_url(url).unwrap();
+        let text = client
+            .transcribe(TranscriptionRequest {
+                language: Some("ru".to_string()),
+                prompt: Some("Tauri, Groq".to_string()),
+                temperature: Some(0.2),
+                ..job("groq/whisper-large-v3")
+            })
+            .await
+            .unwrap();
+        assert_eq!(text, "Привет");
+
+        let request = request.await.unwrap();
+        assert!(request.starts_with("POST /v1/audio/transcriptions"));
+        assert_eq!(
+            form_field(&request, "model").as_deref(),
+            Some("whisper-large-v3")
+        );
+        assert_eq!(
+            form_field(&request, "response_format").as_deref(),
+            Some("json")
+        );
+        assert_eq!(form_field(&request, "language").as_deref(), Some("ru"));
+        assert_eq!(
+            form_field(&request, "prompt").as_deref(),
+            Some("Tauri, Groq")
+        );
+        assert_eq!(form_field(&request, "temperature").as_deref(), Some("0.2"));
+    }
+
+    #[tokio::test]
+    async fn test_unset_fields_are_left_out() {
+        let (url, request) = transcription_server(r#"{"text":"hi"}"#).await;
+        let client = GroqClient::with_base_url(url).unwrap();
+        client
+            .transcribe(job("groq/whisper-large-v3-turbo"))
+            .await
+            .unwrap();
+
+        let request = request.await.unwrap();
+        for field in ["language", "prompt", "temperature"] {
+            assert_eq!(form_field(&request, field), None, "{field} was sent");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verbose_response_has_segments_and_language() {
+        let (url, request) = transcription_server(
+            r#"{"text":" Hello there. ","language":"english","segments":[{"id":0,"start":0.0,"end":1.5,"text":" Hello there."}]}"#,
+        )
+        .await;
+        let client = GroqClient::with_base_url(url).unwrap();
+        let transcript = client
+            .transcribe_verbose(job("groq/whisper-large-v3"))
+            .await
+            .unwrap();
+        assert_eq!(transcript.text, "Hello there.");
+        assert_eq!(transcript.language.as_deref(), Some("english"));
+        assert_eq!(transcript.segments.len(), 1);
+        assert_eq!(transcript.segments[0].end, 1.5);
+
+        let request = request.await.unwrap();
+        assert_eq!(
+            form_field(&request, "response_format").as_deref(),
+            Some("verbose_json")
+        );
+    }
+
+    #[test]
+    fn test_model_without_prefix_is_kept() {
+        assert_eq!(request_model("groq/whisper-large-v3"), "whisper-large-v3");
+        assert_eq!(
+            request_model("distil-whisper-large-v3-en"),
+            "distil-whisper-large-v3-en"
+        );
+        assert_eq!(request_model(" "), DEFAULT_MODEL);
     }
 }
@@ -2,7 +2,11 @@ use anyhow::{anyhow, Context, Result};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 
-use crate::openai::RefinementRequest;
+use crate::{
+    openai::{RefinedText, RefinementRequest},
+    settings::SettingsValidationError,
+    speech_to_text::HttpStatusError,
+};
 
 #[derive(Clone)]
 pub struct GroqLLMClient {
@@ -31,6 +35,8 @@ struct ChatResponse {
 #[derive(Deserialize)]
 struct ChatChoice {
     message: ChatContent,
+    #[serde(default)]
+    finish_reason: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -40,24 +46,31 @@ struct ChatContent {
 
 impl GroqLLMClient {
     pub fn new() -> Result<Self> {
+        Self::with_base_url(crate::groq::DEFAULT_BASE_URL.to_string())
+    }
+
+    pub fn with_base_url(base_url: String) -> Result<Self> {
         let client = Client::builder()
             .build()
             .context("Failed to build HTTP client for Groq LLM")?;
-        let base_url = "https://api.groq.com/openai".to_string();
         Ok(Self { client, base_url })
     }
 
-    pub async fn refine_transcript(&self, text: String, job: &RefinementRequest) -> Result<String> {
+    pub async fn refine_transcript(
+        &self,
+        text: String,
+        job: &RefinementRequest,
+    ) -> Result<RefinedText> {
         if text.trim().is_empty() {
-            return Ok(String::new());
+            return Ok(RefinedText::complete(String::new()));
         }
 
         if job.api_key.trim().is_empty() {
-            return Err(anyhow!("Groq API key is required for post-processing"));
+            return Err(SettingsValidationError::MissingApiKey("Groq").into());
         }
 
         let Some(system_prompt) = job.system_prompt() else {
-            return Ok(text);
+            return Ok(RefinedText::complete(text));
         };
 
         let url = format!(
@@ -65,20 +78,7 @@ impl GroqLLMClient {
             self.base_url.trim_end_matches('/')
         );
 
-        let request = ChatRequest {
-            model: "openai/gpt-oss-20b".to_string(),
-            messages: vec![
-                ChatMessage {
-                    role: "system".to_string(),
-                    content: system_prompt,
-                },
-                ChatMessage {
-                    role: "user".to_string(),
-                    content: text.trim().to_string(),
-                },
-            ],
-            temperature: 0.3,
-        };
+        let request = build_chat_request(&text, system_prompt, job);
 
         let response = self
             .client
@@ -90,16 +90,9 @@ impl GroqLLMClient {
             .context("Groq LLM refinement request failed")?;
 
         if !response.status().is_success() {
-            let status = response.status();
-            let body = response
-                .text()
+            return Err(HttpStatusError::from_response("Groq LLM", response)
                 .await
-                .unwrap_or_else(|_| "<failed to read error body>".into());
-            return Err(anyhow!(
-                "Groq LLM responded with {} to refinement request: {}",
-                status,
-                body
-            ));
+                .into());
         }
 
         let payload: ChatResponse = response
@@ -109,8 +102,56 @@ impl GroqLLMClient {
 
         payload
             .choices
-            .first()
-            .map(|choice| choice.message.content.trim().to_string())
+            .into_iter()
+            .next()
+            .map(|choice| RefinedText {
+                text: choice.message.content.trim().to_string(),
+                truncated: choice.finish_reason.as_deref() == Some("length"),
+            })
             .ok_or_else(|| anyhow!("Groq LLM refinement response contained no choices"))
     }
 }
+
+fn build_chat_request(text: &str, system_prompt: String, job: &RefinementRequest) -> ChatRequest {
+    let messages = job
+        .messages(text, system_prompt)
+        .into_iter()
+        .map(|(role, content)| ChatMessage {
+            role: role.to_string(),
+            content,
+        })
+        .collect();
+
+    ChatRequest {
+        model: job.model.clone(),
+        messages,
+        temperature: job.temperature,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chat_request_uses_configured_model_and_temperature() {
+        let job = RefinementRequest {
+            api_key: "key".to_string(),
+            model: "llama-3.3-70b-versatile".to_string(),
+            auto_translate: true,
+            target_language: "English".to_string(),
+            source_language: None,
+            custom_instructions: None,
+            vocabulary: Vec::new(),
+            temperature: 0.5,
+            chunk: None,
+            context: Vec::new(),
+        };
+
+        let request = build_chat_request(" привет ", "system".to_string(), &job);
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["model"], "llama-3.3-70b-versatile");
+        assert_eq!(json["temperature"], 0.5);
+        assert_eq!(json["messages"][1]["content"], "привет");
+    }
+}
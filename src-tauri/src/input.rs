@@ -1,72 +1,196 @@
-use std::sync::Mutex;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
 
 use anyhow::{anyhow, Result};
 use enigo::{Direction, Enigo, Key, Keyboard, Settings};
 
-#[derive(Default)]
+use crate::messages::{self, Msg};
+
+/// A failed Enigo initialization is retried after this, doubling up to `INIT_RETRY_MAX`
+const INIT_RETRY_MIN: Duration = Duration::from_secs(1);
+const INIT_RETRY_MAX: Duration = Duration::from_secs(30);
+
+/// State of the keyboard emulator on the input thread
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputStatus {
+    Starting,
+    Ready,
+    /// Initialization failed; retried in the background and on the next request
+    Failed(String),
+}
+
+enum Request {
+    Type(String),
+    Paste,
+}
+
+type Reply = mpsc::SyncSender<Result<()>>;
+
+/// Synthetic keyboard input. Enigo is created eagerly on a dedicated thread and every
+/// request runs there, so all input originates from one thread and the first
+/// transcript is not delayed by initialization.
 pub struct KeyboardController {
-    inner: Mutex<Option<Enigo>>,
-    settings: Settings,
+    requests: mpsc::Sender<(Request, Reply)>,
+    status: Arc<Mutex<InputStatus>>,
+    /// Set once a failed initialization has been reported to the user
+    failure_reported: AtomicBool,
 }
 
 impl KeyboardController {
     pub fn new() -> Result<Self> {
+        let (requests, receiver) = mpsc::channel();
+        let status = Arc::new(Mutex::new(InputStatus::Starting));
+        let thread_status = status.clone();
+        std::thread::Builder::new()
+            .name("keyboard-input".to_string())
+            .spawn(move || run(Settings::default(), receiver, thread_status))
+            .map_err(|e| {
+                anyhow!(
+                    "{}",
+                    messages::fill(Msg::InputThreadFailed, &[("error", &e)])
+                )
+            })?;
+
         Ok(Self {
-            inner: Mutex::new(None),
-            settings: Settings::default(),
+            requests,
+            status,
+            failure_reported: AtomicBool::new(false),
         })
     }
 
+    pub fn status(&self) -> InputStatus {
+        self.status
+            .lock()
+            .map(|status| status.clone())
+            .unwrap_or_else(|_| InputStatus::Failed(messages::text(Msg::InputThreadCrashed).into()))
+    }
+
+    /// The initialization error, the first time it is asked for after a failure.
+    /// Lets callers show it once instead of on every dictation.
+    pub fn take_unreported_failure(&self) -> Option<String> {
+        match self.status() {
+            InputStatus::Failed(error) if !self.failure_reported.swap(true, Ordering::SeqCst) => {
+                Some(error)
+            }
+            _ => None,
+        }
+    }
+
     pub fn type_text(&self, text: &str) -> Result<()> {
         if text.is_empty() {
             return Ok(());
         }
-
-        let mut guard = self
-            .inner
-            .lock()
-            .map_err(|_| anyhow!("Не удалось захватить эмулятор клавиатуры"))?;
-        if guard.is_none() {
-            *guard = Some(
-                Enigo::new(&self.settings)
-                    .map_err(|e| anyhow!("Ошибка инициализации эмулятора: {e}"))?,
-            );
-        }
-        if let Some(enigo) = guard.as_mut() {
-            enigo
-                .text(text)
-                .map_err(|e| anyhow!("Не удалось ввести текст: {e}"))?
-        } else {
-            return Err(anyhow!("Эмулятор клавиатуры не инициализирован"));
-        }
-        Ok(())
+        self.request(Request::Type(text.to_string()))
     }
 
     #[allow(dead_code)]
     pub fn paste(&self) -> Result<()> {
-        let mut guard = self
-            .inner
-            .lock()
-            .map_err(|_| anyhow!("Не удалось захватить эмулятор клавиатуры"))?;
-        if guard.is_none() {
-            *guard = Some(
-                Enigo::new(&self.settings)
-                    .map_err(|e| anyhow!("Ошибка инициализации эмулятора: {e}"))?,
+        self.request(Request::Paste)
+    }
+
+    /// Queue `request` on the input thread and wait for it to finish
+    fn request(&self, request: Request) -> Result<()> {
+        let (reply, response) = mpsc::sync_channel(1);
+        self.requests
+            .send((request, reply))
+            .map_err(|_| anyhow!("{}", messages::text(Msg::InputThreadStopped)))?;
+        response
+            .recv()
+            .map_err(|_| anyhow!("{}", messages::text(Msg::InputThreadStopped)))?
+    }
+}
+
+fn set_status(status: &Mutex<InputStatus>, value: InputStatus) {
+    if let Ok(mut guard) = status.lock() {
+        *guard = value;
+    }
+}
+
+fn init(settings: &Settings, status: &Mutex<InputStatus>) -> Option<Enigo> {
+    match Enigo::new(settings) {
+        Ok(enigo) => {
+            tracing::info!("[Input] Keyboard emulator ready");
+            set_status(status, InputStatus::Ready);
+            Some(enigo)
+        }
+        Err(e) => {
+            tracing::warn!("[Input] Keyboard emulator initialization failed: {}", e);
+            set_status(
+                status,
+                InputStatus::Failed(messages::fill(Msg::InputInitFailed, &[("error", &e)])),
             );
+            None
+        }
+    }
+}
+
+fn perform(enigo: &mut Enigo, request: Request) -> Result<()> {
+    match request {
+        Request::Type(text) => enigo
+            .text(&text)
+            .map_err(|e| anyhow!("{}", messages::fill(Msg::TypingFailed, &[("error", &e)]))),
+        Request::Paste => {
+            enigo.key(Key::Control, Direction::Press).map_err(|e| {
+                anyhow!(
+                    "{}",
+                    messages::fill(Msg::KeyPressFailed, &[("key", &"Ctrl"), ("error", &e)])
+                )
+            })?;
+            let clicked = enigo.key(Key::Unicode('v'), Direction::Click).map_err(|e| {
+                anyhow!(
+                    "{}",
+                    messages::fill(Msg::KeyPressFailed, &[("key", &"V"), ("error", &e)])
+                )
+            });
+            // Release Ctrl even if V failed, so it doesn't stay stuck
+            enigo.key(Key::Control, Direction::Release).map_err(|e| {
+                anyhow!(
+                    "{}",
+                    messages::fill(Msg::KeyReleaseFailed, &[("key", &"Ctrl"), ("error", &e)])
+                )
+            })?;
+            clicked
         }
-        if let Some(enigo) = guard.as_mut() {
-            enigo
-                .key(Key::Control, Direction::Press)
-                .map_err(|e| anyhow!("Не удалось нажать Ctrl: {e}"))?;
-            enigo
-                .key(Key::Unicode('v'), Direction::Click)
-                .map_err(|e| anyhow!("Не удалось нажать V: {e}"))?;
-            enigo
-                .key(Key::Control, Direction::Release)
-                .map_err(|e| anyhow!("Не удалось отпустить Ctrl: {e}"))?;
-        } else {
-            return Err(anyhow!("Эмулятор клавиатуры не инициализирован"));
+    }
+}
+
+/// Input thread: owns Enigo for the lifetime of the controller and retries a failed
+/// initialization with backoff until it succeeds
+fn run(
+    settings: Settings,
+    requests: mpsc::Receiver<(Request, Reply)>,
+    status: Arc<Mutex<InputStatus>>,
+) {
+    let mut enigo = init(&settings, &status);
+    let mut retry = INIT_RETRY_MIN;
+    loop {
+        let received = match enigo {
+            Some(_) => requests.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            None => requests.recv_timeout(retry),
+        };
+        match received {
+            Ok((request, reply)) => {
+                if enigo.is_none() {
+                    enigo = init(&settings, &status);
+                }
+                let result = match enigo.as_mut() {
+                    Some(enigo) => perform(enigo, request),
+                    None => Err(anyhow!("{}", messages::text(Msg::InputNotReady))),
+                };
+                let _ = reply.send(result);
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                enigo = init(&settings, &status);
+                retry = (retry * 2).min(INIT_RETRY_MAX);
+            }
+            // The controller was dropped
+            Err(RecvTimeoutError::Disconnected) => return,
         }
-        Ok(())
     }
 }
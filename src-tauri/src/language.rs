@@ -0,0 +1,207 @@
+use crate::settings::AppSettings;
+
+/// Minimum detector confidence before we trust it enough to skip translation
+const MIN_SKIP_CONFIDENCE: f64 = 0.8;
+
+/// Minimum detector confidence before the language is named to the LLM and kept in
+/// history; a wrong "source language" hint makes translation worse than none
+const MIN_HINT_CONFIDENCE: f64 = 0.6;
+
+/// Texts shorter than this (in chars) are too ambiguous to detect reliably
+const MIN_DETECTION_CHARS: usize = 12;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectedLanguage {
+    /// English name, e.g. "Russian" — matches the target language values in settings
+    pub name: String,
+    /// Native name, e.g. "Русский"
+    pub native_name: String,
+    pub confidence: f64,
+}
+
+impl DetectedLanguage {
+    /// Whether this language matches a target language as configured in settings
+    /// (English or native name, case-insensitive)
+    pub fn matches(&self, target: &str) -> bool {
+        let target = target.trim().to_lowercase();
+        !target.is_empty()
+            && (self.name.to_lowercase() == target || self.native_name.to_lowercase() == target)
+    }
+}
+
+/// ISO 639-1 codes of common transcription languages and their ISO 639-3 equivalents
+const TWO_LETTER_CODES: &[(&str, &str)] = &[
+    ("en", "eng"),
+    ("ru", "rus"),
+    ("uk", "ukr"),
+    ("de", "deu"),
+    ("fr", "fra"),
+    ("es", "spa"),
+    ("it", "ita"),
+    ("pt", "por"),
+    ("pl", "pol"),
+    ("nl", "nld"),
+    ("tr", "tur"),
+    ("ja", "jpn"),
+    ("ko", "kor"),
+    ("zh", "cmn"),
+];
+
+/// The language named by a transcription language code like "en" or "eng";
+/// None for "auto" and codes we don't know
+pub fn language_for_code(code: &str) -> Option<DetectedLanguage> {
+    let code = code.trim().to_lowercase();
+    let code = TWO_LETTER_CODES
+        .iter()
+        .find(|(two, _)| *two == code)
+        .map_or(code.as_str(), |(_, three)| three);
+    let lang = whatlang::Lang::from_code(code)?;
+    Some(DetectedLanguage {
+        name: lang.eng_name().to_string(),
+        native_name: lang.name().to_string(),
+        confidence: 1.0,
+    })
+}
+
+/// Cheap offline language detection of a transcript
+pub fn detect_language(text: &str) -> Option<DetectedLanguage> {
+    let text = text.trim();
+    if text.chars().count() < MIN_DETECTION_CHARS {
+        return None;
+    }
+    let info = whatlang::detect(text)?;
+    Some(DetectedLanguage {
+        name: info.lang().eng_name().to_string(),
+        native_name: info.lang().name().to_string(),
+        confidence: info.confidence(),
+    })
+}
+
+/// Name of the detected language if the detector is sure enough to pass it on
+pub fn source_language_hint(detected: Option<&DetectedLanguage>) -> Option<String> {
+    detected
+        .filter(|lang| lang.confidence >= MIN_HINT_CONFIDENCE)
+        .map(|lang| lang.name.clone())
+}
+
+/// True when auto-translate is the only refinement requested and the transcript
+/// is already in the target language, so the LLM call can be skipped entirely.
+pub fn translation_redundant(settings: &AppSettings, detected: Option<&DetectedLanguage>) -> bool {
+    if !settings.auto_translate {
+        return false;
+    }
+    if settings.active_instructions().is_some() || !settings.effective_vocabulary().is_empty() {
+        return false;
+    }
+    detected
+        .map(|lang| {
+            lang.confidence >= MIN_SKIP_CONFIDENCE && lang.matches(&settings.target_language)
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn english(confidence: f64) -> DetectedLanguage {
+        DetectedLanguage {
+            name: "English".to_string(),
+            native_name: "English".to_string(),
+            confidence,
+        }
+    }
+
+    fn translate_to(target: &str) -> AppSettings {
+        let mut settings = AppSettings::default();
+        settings.auto_translate = true;
+        settings.target_language = target.to_string();
+        settings
+    }
+
+    #[test]
+    fn test_skip_when_already_in_target_language() {
+        let settings = translate_to("English");
+        assert!(translation_redundant(&settings, Some(&english(0.95))));
+    }
+
+    #[test]
+    fn test_no_skip_when_low_confidence_or_unknown() {
+        let settings = translate_to("English");
+        assert!(!translation_redundant(&settings, Some(&english(0.3))));
+        assert!(!translation_redundant(&settings, None));
+    }
+
+    #[test]
+    fn test_no_skip_when_different_language() {
+        let settings = translate_to("Russian");
+        assert!(!translation_redundant(&settings, Some(&english(0.99))));
+    }
+
+    #[test]
+    fn test_no_skip_when_other_refinements_requested() {
+        let mut settings = translate_to("English");
+        settings.use_vocabulary = true;
+        settings.custom_vocabulary = vec!["Tauri".to_string()];
+        assert!(!translation_redundant(&settings, Some(&english(0.99))));
+
+        let mut settings = translate_to("English");
+        settings.use_custom_instructions = true;
+        settings.custom_instructions = "Make it formal".to_string();
+        let settings = settings.normalized();
+        assert!(!translation_redundant(&settings, Some(&english(0.99))));
+    }
+
+    #[test]
+    fn test_no_skip_without_auto_translate() {
+        let mut settings = translate_to("English");
+        settings.auto_translate = false;
+        assert!(!translation_redundant(&settings, Some(&english(0.99))));
+    }
+
+    #[test]
+    fn test_source_language_hint_needs_confidence() {
+        assert_eq!(
+            source_language_hint(Some(&english(0.95))).as_deref(),
+            Some("English")
+        );
+        assert_eq!(source_language_hint(Some(&english(0.3))), None);
+        assert_eq!(source_language_hint(None), None);
+        // Named to the LLM, yet not trusted enough to skip translation
+        let unsure = english(0.7);
+        assert!(source_language_hint(Some(&unsure)).is_some());
+        assert!(!translation_redundant(
+            &translate_to("English"),
+            Some(&unsure)
+        ));
+    }
+
+    #[test]
+    fn test_matches_native_name() {
+        let russian = DetectedLanguage {
+            name: "Russian".to_string(),
+            native_name: "Русский".to_string(),
+            confidence: 1.0,
+        };
+        assert!(russian.matches("русский"));
+        assert!(russian.matches("Russian"));
+        assert!(!russian.matches("English"));
+    }
+
+    #[test]
+    fn test_language_for_code() {
+        assert!(language_for_code("en").unwrap().matches("English"));
+        assert!(language_for_code("RUS").unwrap().matches("Русский"));
+        assert!(language_for_code("auto").is_none());
+        assert!(language_for_code("").is_none());
+    }
+
+    #[test]
+    fn test_detect_language_russian_and_short_text() {
+        let detected =
+            detect_language("Сегодня мы обсудим планы на следующую неделю и распределим задачи")
+                .unwrap();
+        assert_eq!(detected.name, "Russian");
+        assert!(detect_language("ok").is_none());
+    }
+}
@@ -1,31 +1,53 @@
 use anyhow::anyhow;
-use tauri::{Emitter, Manager, RunEvent};
+use tauri::{Manager, RunEvent};
 use tauri_plugin_log::{Target, TargetKind};
-use tauri_plugin_updater::UpdaterExt;
 
+mod agc;
 mod audio;
+mod audio_file;
 mod audio_stream;
+mod chunking;
+mod cli;
 mod core;
+mod deepgram_streaming;
+mod diff;
+mod elevation;
 mod elevenlabs;
 mod elevenlabs_handler;
+mod elevenlabs_protocol;
 mod elevenlabs_streaming;
 mod groq;
 mod groq_llm;
 mod input;
+mod language;
+mod messages;
+mod mic_access;
+// Groundwork for typing streamed text as it arrives; nothing types live yet
+#[allow(dead_code)]
+mod live_typing;
+mod ollama;
 mod openai;
+mod preflight;
+mod profanity;
+mod recording_buffer;
+mod redact;
 mod settings;
+mod settings_vault;
+mod speech_to_text;
+mod streaming_session;
+mod subprocess;
+mod text_utils;
 
 use core::{
-    commands,
-    events::{emit_error, emit_status, StatusPhase},
-    hotkey,
-    state::AppState,
-    tray,
+    session_manager, shutdown, startup, state::AppState, status_watchdog, streaming_idle, tray,
 };
-use settings::SettingsStore;
+use settings::{AppSettings, SettingsStore};
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    let context = tauri::generate_context!();
+    let start_minimized = cli::start_minimized_or_exit(&context.config().identifier);
+
     tauri::Builder::default()
         .plugin(
             tauri_plugin_log::Builder::new()
@@ -40,7 +62,8 @@ pub fn run() {
         .plugin(tauri_plugin_autostart::Builder::new().build())
         .plugin(tauri_plugin_clipboard_manager::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
-        .setup(|app| {
+        .plugin(tauri_plugin_notification::init())
+        .setup(move |app| {
             let handle = app.handle();
 
             let resolver = handle.path();
@@ -48,64 +71,24 @@ pub fn run() {
                 .app_config_dir()
                 .map_err(|err| anyhow!("Failed to locate application config directory: {err}"))?;
             let store = SettingsStore::new(config_dir);
-            let initial = tauri::async_runtime::block_on(store.load())?;
-            let state = AppState::new(store, initial.clone())?;
+            // Start with defaults so nothing waits on disk here; the saved settings
+            // are loaded and applied by `startup::load_settings_and_bind` below
+            let provisional = AppSettings::default();
+            let state = AppState::new(store, provisional.clone())?;
             app.manage(state);
 
-            // Check if app should start minimized:
-            // 1. Command line args --autostart or --minimized (for autostart plugin)
-            // 2. Setting: start_minimized is true
-            let has_autostart_arg = std::env::args().any(|arg| arg == "--autostart" || arg == "--minimized");
-            let should_start_minimized = has_autostart_arg || initial.start_minimized;
-
-            tracing::info!("[Setup] Start minimized: {} (args: {}, setting: {})",
-                       should_start_minimized, has_autostart_arg, initial.start_minimized);
-
-            if let Some(window) = handle.get_webview_window("main") {
-                if !should_start_minimized {
-                    if let Err(e) = window.show() {
-                        tracing::warn!("[Setup] Failed to show main window: {}", e);
-                    }
-                    if let Err(e) = window.unminimize() {
-                        tracing::warn!("[Setup] Failed to unminimize main window: {}", e);
-                    }
-                    if let Err(e) = window.set_focus() {
-                        tracing::warn!("[Setup] Failed to set focus on main window: {}", e);
-                    }
-                } else {
-                    // Keep window hidden when starting minimized
-                    if let Err(e) = window.hide() {
-                        tracing::warn!("[Setup] Failed to hide main window: {}", e);
-                    }
-                    tracing::info!("[Setup] Window hidden (start minimized enabled)");
-                }
-            }
-
             // Initialize overlay window: keep hidden, set click-through
             // Overlay will be shown and positioned on the correct monitor when recording starts
             if let Some(overlay) = handle.get_webview_window("overlay") {
                 let _ = overlay.set_ignore_cursor_events(true);
                 tracing::info!("[Setup] Overlay window initialized (hidden until recording)");
             }
+            core::overlay::listen_for_moves(handle);
 
-            if let Err(e) = commands::apply_autostart(handle, initial.auto_start) {
-                tracing::warn!("[Setup] Failed to apply autostart setting: {}", e);
-            }
-            // Handle hotkey registration failure gracefully (e.g., when another instance is running)
-            // This allows the app to start but won't have hotkey functionality
-            if let Err(e) = hotkey::rebind_hotkey(handle, &initial) {
-                tracing::warn!("[Setup] Failed to register hotkey (another instance may be running): {}", e);
-                // Emit an error to let the user know
-                emit_error(handle, &format!("Hotkey registration failed: {}. Close other instances and restart.", e));
-            }
-            emit_status(handle, StatusPhase::Idle, None);
-
-            let status_item = tray::install_tray(handle)?;
-            if let Some(state) = handle.try_state::<AppState>() {
-                if let Ok(mut guard) = state.tray_status_item().lock() {
-                    *guard = Some(status_item);
-                }
-            }
+            tray::install_tray(handle, &provisional)?;
+            status_watchdog::spawn_status_watchdog(handle);
+            streaming_idle::spawn_idle_disconnect(handle);
+            session_manager::spawn_session_manager(handle);
 
             // Setup ElevenLabs streaming event handlers
             elevenlabs_handler::setup_elevenlabs_event_handlers(handle);
@@ -116,91 +99,130 @@ pub fn run() {
                 tracing::info!("[Log] File logging enabled: {}", log_dir.join("logs.log").display());
             }
 
-            // Check for updates on app start (background task) - if enabled in settings
-            if initial.auto_update {
-                let update_handle = handle.clone();
-                tauri::async_runtime::spawn(async move {
-                    tracing::info!("[Updater] Checking for updates...");
-                    match update_handle.updater_builder().build() {
-                    Ok(updater) => match updater.check().await {
-                        Ok(Some(update)) => {
-                            let new_version = update.version.to_string();
-                            tracing::info!("[Updater] Update available: {} -> {}", update.current_version, new_version);
-
-                            // Notify frontend that update is available
-                            let _ = update_handle.emit("update://available", &new_version);
-
-                            // Auto-download and install the update
-                            match update.download_and_install(|chunk, total| {
-                                tracing::debug!("[Updater] Downloaded {} of {} bytes", chunk, total.unwrap_or(0));
-                            }, || {
-                                tracing::info!("[Updater] Download finished, installing...");
-                            }).await {
-                                Ok(_) => {
-                                    tracing::info!("[Updater] Update installed successfully. Restart required.");
-                                    let _ = update_handle.emit("update://installed", &new_version);
-                                }
-                                Err(e) => tracing::error!("[Updater] Failed to download/install update: {}", e),
-                            }
-                        }
-                        Ok(None) => tracing::info!("[Updater] App is up to date"),
-                        Err(e) => tracing::warn!("[Updater] Failed to check for updates: {}", e),
-                    },
-                    Err(e) => tracing::error!("[Updater] Failed to build updater: {}", e),
-                }
-                });
-            } else {
-                tracing::info!("[Updater] Auto-update disabled in settings");
-            }
+            tauri::async_runtime::spawn(startup::load_settings_and_bind(
+                handle.clone(),
+                start_minimized,
+            ));
 
             handle.on_menu_event(|app_handle, event| match event.id().as_ref() {
                 "open" => tray::show_settings_window(app_handle),
-                "quit" => app_handle.exit(0),
-                _ => {}
+                "quit" => shutdown::request_shutdown(app_handle),
+                other => tray::handle_menu_event(app_handle, other),
             });
 
             Ok(())
         })
         .on_window_event(|window, event| {
-            if let tauri::WindowEvent::CloseRequested { api, .. } = event {
-                api.prevent_close();
-                if let Err(e) = window.hide() {
-                    tracing::warn!("[Window] Failed to hide window on close request: {}", e);
+            match event {
+                tauri::WindowEvent::CloseRequested { api, .. } => {
+                    api.prevent_close();
+                    if let Err(e) = window.hide() {
+                        tracing::warn!("[Window] Failed to hide window on close request: {}", e);
+                    }
+                }
+                tauri::WindowEvent::DragDrop(tauri::DragDropEvent::Drop { paths, .. })
+                    if window.label() == "main" =>
+                {
+                    core::file_transcription::transcribe_dropped(window.app_handle(), paths);
+                }
+                tauri::WindowEvent::Moved(_) | tauri::WindowEvent::Resized(_)
+                    if window.label() == "main" =>
+                {
+                    core::window_state::schedule_save(window.app_handle());
                 }
+                tauri::WindowEvent::Focused(true) => {
+                    core::notify::window_focused(window.app_handle(), window.label());
+                }
+                _ => {}
             }
         })
         .invoke_handler(tauri::generate_handler![
             core::commands::get_settings,
             core::commands::save_settings,
+            core::commands::confirm_post_command,
             core::commands::ping,
             core::commands::get_app_version,
             core::commands::toggle_auto_translate,
+            core::commands::cycle_target_language,
+            core::commands::start_dictation,
+            core::commands::stop_dictation,
+            core::commands::commit_segment,
+            core::commands::set_next_provider,
+            core::commands::set_mic_muted,
+            core::commands::get_mic_muted,
+            core::commands::get_foreground_app,
+            core::commands::stop_speaking,
+            core::commands::reveal_log,
+            core::commands::show_settings,
+            core::commands::retry_last_transcription,
+            core::commands::create_support_bundle,
+            core::commands::reveal_support_bundle,
+            core::commands::preview_refinement,
+            core::commands::start_session,
+            core::commands::end_session,
+            core::commands::get_session,
+            core::commands::set_overlay_interactive,
+            core::commands::reset_overlay_position,
+            core::commands::set_local_api_enabled,
+            core::commands::reset_local_api_token,
+            core::commands::get_local_api,
+            core::commands::get_settings_lock,
+            core::commands::unlock_settings,
+            core::commands::set_settings_passphrase,
+            core::commands::set_provider,
+            core::commands::set_active_preset,
             core::commands::frontend_log,
+            core::commands::events_manifest,
+            core::commands::list_models,
             core::commands::elevenlabs_streaming_connect,
             core::commands::elevenlabs_streaming_disconnect,
             core::commands::elevenlabs_streaming_open_gate,
             core::commands::elevenlabs_streaming_close_gate,
             core::commands::elevenlabs_streaming_send_chunk,
             core::commands::elevenlabs_streaming_is_connected,
+            core::commands::get_streaming_state,
             core::commands::show_overlay_no_focus,
             core::commands::check_for_updates,
+            core::commands::download_update,
             core::commands::install_update,
+            core::commands::run_diagnostics,
+            core::commands::transcribe_file,
+            core::commands::check_permissions,
+            core::commands::clear_transcript_log,
             // History commands
             core::commands::get_history,
             core::commands::clear_history,
             core::commands::delete_history_entry,
+            core::commands::get_history_entry_audio,
+            core::commands::retranscribe_recordings,
+            core::commands::cancel_retranscription,
+            core::commands::purge_history_older_than,
+            core::commands::get_dictation_stats,
+            core::commands::export_history,
+            core::commands::import_vocabulary,
+            core::commands::export_vocabulary,
             // Test mode commands
             core::commands::inject_test_audio,
             core::commands::get_test_state,
+            core::commands::wait_for_test_state,
             core::commands::simulate_hotkey_press,
             core::commands::simulate_hotkey_release,
+            core::commands::fail_next_audio_capture,
             core::commands::show_main_window,
         ])
-        .build(tauri::generate_context!())
+        .build(context)
         .expect("error while running tauri application")
-        .run(|_app_handle, event| {
-            if matches!(event, RunEvent::ExitRequested { .. }) {
-                // keep running until the user explicitly quits from the tray menu
+        .run(|app_handle, event| match event {
+            RunEvent::ExitRequested { api, .. } => {
+                // Exit only once in-flight work has been flushed
+                if !shutdown::is_shutdown_complete() {
+                    api.prevent_exit();
+                    shutdown::request_shutdown(app_handle);
+                }
             }
+            // Clicking a notification re-activates the app even with no window open
+            #[cfg(target_os = "macos")]
+            RunEvent::Reopen { .. } => core::notify::app_activated(app_handle),
+            _ => {}
         });
 }
@@ -0,0 +1,313 @@
+use std::collections::VecDeque;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Punctuation that ends a sentence or clause; text up to one is typed right away
+pub const DEFAULT_BOUNDARIES: &str = ". ! ? ;";
+
+/// Partials in a row a trailing word must appear in unchanged before it is typed:
+/// the one it shows up in and two newer ones confirming it
+const CONFIRMATIONS: usize = 3;
+
+/// A keyboard step of live typing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeOp {
+    Type(String),
+    /// Delete this many characters (grapheme clusters) before the cursor
+    Backspace(usize),
+}
+
+/// Decides what live typing puts on screen while partial transcripts stream in, so
+/// revisions don't turn into a storm of backspaces. Text up to the last sentence or
+/// clause boundary is typed and then never touched again ("flushed"); the words
+/// after it are held back until partials stop revising them, or until the committed
+/// transcript arrives. Partials are the whole text of the segment so far.
+#[derive(Debug, Clone)]
+pub struct StableTyper {
+    boundaries: Vec<char>,
+    /// What is on screen for the current segment
+    typed: String,
+    /// Bytes of `typed` up to the last flushed boundary
+    flushed: usize,
+    /// The latest partials, newest last
+    recent: VecDeque<String>,
+}
+
+impl Default for StableTyper {
+    fn default() -> Self {
+        Self::new(DEFAULT_BOUNDARIES)
+    }
+}
+
+impl StableTyper {
+    /// Tracker flushing at any of the characters in `boundaries` (whitespace ignored)
+    pub fn new(boundaries: &str) -> Self {
+        Self {
+            boundaries: boundaries.chars().filter(|c| !c.is_whitespace()).collect(),
+            typed: String::new(),
+            flushed: 0,
+            recent: VecDeque::with_capacity(CONFIRMATIONS),
+        }
+    }
+
+    /// Text on screen for the current segment
+    pub fn typed(&self) -> &str {
+        &self.typed
+    }
+
+    /// Part of `typed` that is final
+    pub fn flushed(&self) -> &str {
+        &self.typed[..self.flushed]
+    }
+
+    /// Keyboard steps for a new partial transcript
+    pub fn partial(&mut self, text: &str) -> Vec<TypeOp> {
+        if self.recent.len() == CONFIRMATIONS {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(text.to_string());
+
+        let boundary = self.boundary_end(text);
+        let end = boundary.max(self.confirmed_end());
+        let ops = self.retype(&text[..end]);
+        // A partial contradicting the flushed text can't move the boundary
+        if boundary > self.flushed && self.typed.starts_with(&text[..boundary]) {
+            self.flushed = boundary;
+        }
+        ops
+    }
+
+    /// Keyboard steps for the committed transcript of the segment; the tracker then
+    /// starts over for the next one
+    pub fn commit(&mut self, text: &str) -> Vec<TypeOp> {
+        let ops = self.retype(text);
+        self.typed.clear();
+        self.flushed = 0;
+        self.recent.clear();
+        ops
+    }
+
+    /// End of the last boundary in `text` that more text follows, or 0. A boundary at
+    /// the very end may still grow ("..." or "?!").
+    fn boundary_end(&self, text: &str) -> usize {
+        let mut chars = text.char_indices().peekable();
+        let mut end = 0;
+        while let Some((i, c)) = chars.next() {
+            let followed_by_space = chars.peek().is_some_and(|(_, next)| next.is_whitespace());
+            if followed_by_space && self.boundaries.contains(&c) {
+                end = i + c.len_utf8();
+            }
+        }
+        end
+    }
+
+    /// End of the words the latest partials all agree on, or 0 until there are enough
+    fn confirmed_end(&self) -> usize {
+        if self.recent.len() < CONFIRMATIONS {
+            return 0;
+        }
+        let newest = &self.recent[CONFIRMATIONS - 1];
+        let older: Vec<Vec<&str>> = self
+            .recent
+            .iter()
+            .take(CONFIRMATIONS - 1)
+            .map(|partial| partial.split_word_bounds().collect())
+            .collect();
+        let mut end = 0;
+        for (i, token) in newest.split_word_bounds().enumerate() {
+            if !older.iter().all(|tokens| tokens.get(i) == Some(&token)) {
+                break;
+            }
+            end += token.len();
+        }
+        newest[..end].trim_end().len()
+    }
+
+    /// Steps turning what is on screen after the flushed boundary into the matching
+    /// part of `target`. If `target` contradicts the flushed text, which can't be
+    /// corrected anymore, its rest past the same number of characters is used.
+    fn retype(&mut self, target: &str) -> Vec<TypeOp> {
+        let flushed = &self.typed[..self.flushed];
+        let target_tail = match target.strip_prefix(flushed) {
+            Some(tail) => tail,
+            None => {
+                let skip = flushed.graphemes(true).count();
+                let start = target
+                    .grapheme_indices(true)
+                    .nth(skip)
+                    .map_or(target.len(), |(i, _)| i);
+                &target[start..]
+            }
+        };
+        let current_tail = &self.typed[self.flushed..];
+
+        let mut common = 0;
+        let mut kept = 0;
+        for (a, b) in current_tail
+            .graphemes(true)
+            .zip(target_tail.graphemes(true))
+        {
+            if a != b {
+                break;
+            }
+            common += a.len();
+            kept += 1;
+        }
+
+        let mut ops = Vec::new();
+        let erase = current_tail.graphemes(true).count() - kept;
+        if erase > 0 {
+            ops.push(TypeOp::Backspace(erase));
+        }
+        if common < target_tail.len() {
+            ops.push(TypeOp::Type(target_tail[common..].to_string()));
+        }
+        self.typed = format!("{flushed}{target_tail}");
+        ops
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Play `ops` on `screen`, checking they leave the first `frozen` bytes alone
+    fn apply(screen: &mut String, ops: &[TypeOp], frozen: usize) {
+        for op in ops {
+            match op {
+                TypeOp::Type(text) => screen.push_str(text),
+                TypeOp::Backspace(count) => {
+                    for _ in 0..*count {
+                        let last = screen.graphemes(true).next_back().unwrap();
+                        screen.truncate(screen.len() - last.len());
+                        assert!(
+                            screen.len() >= frozen,
+                            "backspace reached flushed text: {screen:?}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Feed `partials` and then `committed`, returning the screen after each step
+    fn play(typer: &mut StableTyper, partials: &[&str], committed: &str) -> Vec<String> {
+        let mut screen = String::new();
+        let mut screens = Vec::new();
+        for partial in partials {
+            let frozen = typer.flushed().len();
+            let flushed = typer.flushed().to_string();
+            apply(&mut screen, &typer.partial(partial), frozen);
+            assert!(screen.starts_with(&flushed), "{screen:?} lost {flushed:?}");
+            assert_eq!(screen, typer.typed());
+            screens.push(screen.clone());
+        }
+        let frozen = typer.flushed().len();
+        apply(&mut screen, &typer.commit(committed), frozen);
+        screens.push(screen);
+        screens
+    }
+
+    #[test]
+    fn test_trailing_words_wait_for_two_confirmations() {
+        let mut typer = StableTyper::default();
+        let screens = play(
+            &mut typer,
+            &[
+                "I think",
+                "I think we",
+                "I think we sh",
+                "I think we should",
+                "I think we should",
+            ],
+            "I think we should go",
+        );
+        assert_eq!(
+            screens,
+            [
+                "",
+                "",
+                "I think",
+                "I think we",
+                "I think we",
+                "I think we should go"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_boundary_is_typed_at_once_and_never_revised() {
+        let mut typer = StableTyper::default();
+        let screens = play(
+            &mut typer,
+            &[
+                "Let's meet at ten. The",
+                // The recognizer changes its mind about text before the boundary
+                "Let's meet at two. The room",
+                "Let's meet at two. The room is",
+                "Let's meet at two. The room is booked",
+            ],
+            "Let's meet at two. The room is booked; bring slides.",
+        );
+        assert_eq!(screens[0], "Let's meet at ten.");
+        assert_eq!(screens[1], "Let's meet at ten.");
+        // Past the boundary, same-length revisions still line up
+        assert_eq!(screens[3], "Let's meet at ten. The room");
+        assert_eq!(
+            screens[4],
+            "Let's meet at ten. The room is booked; bring slides."
+        );
+    }
+
+    #[test]
+    fn test_revised_words_after_the_boundary_are_corrected() {
+        let mut typer = StableTyper::default();
+        let partials = [
+            "Send it. Their",
+            "Send it. Their report",
+            "Send it. Their report is",
+            "Send it. There report is",
+            "Send it. There report is late.",
+        ];
+        let mut screen = String::new();
+        let mut ops = Vec::new();
+        for partial in partials {
+            let frozen = typer.flushed().len();
+            let step = typer.partial(partial);
+            apply(&mut screen, &step, frozen);
+            ops.push(step);
+        }
+        assert_eq!(ops[2], [TypeOp::Type(" Their".into())]);
+        // Only the word after the boundary is taken back, and held until confirmed
+        assert_eq!(ops[3], [TypeOp::Backspace(6)]);
+        assert!(ops[4].is_empty());
+        assert_eq!(typer.flushed(), "Send it.");
+        assert_eq!(screen, "Send it.");
+
+        let frozen = typer.flushed().len();
+        apply(
+            &mut screen,
+            &typer.commit("Send it. Their report is late."),
+            frozen,
+        );
+        assert_eq!(screen, "Send it. Their report is late.");
+        assert_eq!(typer.typed(), "");
+    }
+
+    #[test]
+    fn test_custom_boundaries_and_trailing_punctuation() {
+        let mut typer = StableTyper::new(",");
+        assert_eq!(
+            typer.partial("First, second."),
+            [TypeOp::Type("First,".into())]
+        );
+        assert_eq!(typer.flushed(), "First,");
+        // A boundary at the very end isn't flushed yet
+        let mut typer = StableTyper::default();
+        assert!(typer.partial("Wait.").is_empty());
+        assert_eq!(
+            typer.partial("Wait... what"),
+            [TypeOp::Type("Wait...".into())]
+        );
+    }
+}
@@ -0,0 +1,459 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Languages the backend's user-facing messages are available in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    En,
+    Ru,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::En, Language::Ru];
+
+    /// `ui_language` value
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::En => "en",
+            Language::Ru => "ru",
+        }
+    }
+
+    /// Language for a `ui_language` value or a locale like "ru-RU" or "en_US.UTF-8";
+    /// English for anything else
+    pub fn from_code(code: &str) -> Self {
+        let code = code.trim().to_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|language| {
+                code.strip_prefix(language.code())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with(['-', '_', '.']))
+            })
+            .unwrap_or(Language::En)
+    }
+
+    /// Language of the system locale, for settings that don't name one yet
+    pub fn system() -> Self {
+        // Tests compare messages in English whatever the machine's locale
+        if cfg!(test) {
+            return Language::En;
+        }
+        sys_locale::get_locale().map_or(Language::En, |locale| Self::from_code(&locale))
+    }
+}
+
+/// Language messages are looked up in; follows `ui_language` of the applied settings
+static CURRENT: AtomicU8 = AtomicU8::new(0);
+
+pub fn set_language(language: Language) {
+    let index = Language::ALL
+        .iter()
+        .position(|l| *l == language)
+        .unwrap_or(0);
+    CURRENT.store(index as u8, Ordering::Relaxed);
+}
+
+pub fn language() -> Language {
+    Language::ALL
+        .get(usize::from(CURRENT.load(Ordering::Relaxed)))
+        .copied()
+        .unwrap_or(Language::En)
+}
+
+/// A user-facing message of the backend: status lines, errors, warnings. `{name}`
+/// marks a value `fill` puts in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Msg {
+    StatusIdle,
+    StatusPaused,
+    StatusRecording,
+    StatusTranscribing,
+    StatusDelivering,
+    StatusSuccess,
+    StatusError,
+    ReadyForNext,
+    SettingsSaved,
+    Recording,
+    Streaming,
+    UploadingAudio,
+    Processing,
+    ApplyingLlm,
+    RefiningPart,
+    DecodingFile,
+    AlreadyTranscribing,
+    NothingToRepeat,
+    DeliveredAgain,
+    NoStreamingTranscript,
+    StreamingIdle,
+    TranscriptionTimedOut,
+    ShuttingDown,
+    ProviderSwitched,
+    PresetSwitched,
+    TranslationOn,
+    TranslationOff,
+    TooShort,
+    TooQuiet,
+    ElevatedTarget,
+    LostAudio,
+    Truncated,
+    DeliveredUntranslated,
+    DeliveredRaw,
+    StreamingError,
+    SettingsSaveFailed,
+    SettingsLoadFailed,
+    SegmentCommitFailed,
+    GateOpenFailed,
+    GateCloseFailed,
+    HotkeyRegistrationFailed,
+    AutostartFailed,
+    ClipboardFailed,
+    CaptureRestarting,
+    CaptureCrashedAgain,
+    CaptureRestartFailed,
+    InputThreadFailed,
+    InputThreadCrashed,
+    InputThreadStopped,
+    InputInitFailed,
+    InputNotReady,
+    TypingFailed,
+    KeyPressFailed,
+    KeyReleaseFailed,
+    NoMicrophone,
+    MicMuted,
+    MicPrivacyBlocked,
+    MicMutedBySystem,
+    RecordingTooQuick,
+    NoAudioRecorded,
+    SessionStarted,
+    SessionAlreadyRunning,
+    NoSessionRunning,
+    SessionUtteranceAdded,
+    SessionCombining,
+    SessionSaved,
+    SessionEmpty,
+    SessionFileFailed,
+}
+
+impl Msg {
+    pub const ALL: [Msg; 68] = [
+        Msg::StatusIdle,
+        Msg::StatusPaused,
+        Msg::StatusRecording,
+        Msg::StatusTranscribing,
+        Msg::StatusDelivering,
+        Msg::StatusSuccess,
+        Msg::StatusError,
+        Msg::ReadyForNext,
+        Msg::SettingsSaved,
+        Msg::Recording,
+        Msg::Streaming,
+        Msg::UploadingAudio,
+        Msg::Processing,
+        Msg::ApplyingLlm,
+        Msg::RefiningPart,
+        Msg::DecodingFile,
+        Msg::AlreadyTranscribing,
+        Msg::NothingToRepeat,
+        Msg::DeliveredAgain,
+        Msg::NoStreamingTranscript,
+        Msg::StreamingIdle,
+        Msg::TranscriptionTimedOut,
+        Msg::ShuttingDown,
+        Msg::ProviderSwitched,
+        Msg::PresetSwitched,
+        Msg::TranslationOn,
+        Msg::TranslationOff,
+        Msg::TooShort,
+        Msg::TooQuiet,
+        Msg::ElevatedTarget,
+        Msg::LostAudio,
+        Msg::Truncated,
+        Msg::DeliveredUntranslated,
+        Msg::DeliveredRaw,
+        Msg::StreamingError,
+        Msg::SettingsSaveFailed,
+        Msg::SettingsLoadFailed,
+        Msg::SegmentCommitFailed,
+        Msg::GateOpenFailed,
+        Msg::GateCloseFailed,
+        Msg::HotkeyRegistrationFailed,
+        Msg::AutostartFailed,
+        Msg::ClipboardFailed,
+        Msg::CaptureRestarting,
+        Msg::CaptureCrashedAgain,
+        Msg::CaptureRestartFailed,
+        Msg::InputThreadFailed,
+        Msg::InputThreadCrashed,
+        Msg::InputThreadStopped,
+        Msg::InputInitFailed,
+        Msg::InputNotReady,
+        Msg::TypingFailed,
+        Msg::KeyPressFailed,
+        Msg::KeyReleaseFailed,
+        Msg::NoMicrophone,
+        Msg::MicMuted,
+        Msg::MicPrivacyBlocked,
+        Msg::MicMutedBySystem,
+        Msg::RecordingTooQuick,
+        Msg::NoAudioRecorded,
+        Msg::SessionStarted,
+        Msg::SessionAlreadyRunning,
+        Msg::NoSessionRunning,
+        Msg::SessionUtteranceAdded,
+        Msg::SessionCombining,
+        Msg::SessionSaved,
+        Msg::SessionEmpty,
+        Msg::SessionFileFailed,
+    ];
+}
+
+/// The catalog. A missing translation falls back to English, which must be complete.
+fn lookup(msg: Msg, language: Language) -> Option<&'static str> {
+    use Language::{En, Ru};
+    Some(match (msg, language) {
+        (Msg::StatusIdle, En) => "Ready. Use the global hotkey to start a recording.",
+        (Msg::StatusIdle, Ru) => "Готово. Нажмите горячую клавишу, чтобы начать запись.",
+        (Msg::StatusPaused, En) => "Connected. Hold the hotkey to continue.",
+        (Msg::StatusPaused, Ru) => "Подключено. Удерживайте горячую клавишу, чтобы продолжить.",
+        (Msg::StatusRecording, En) => "Listening... release the hotkey to stop.",
+        (Msg::StatusRecording, Ru) => "Слушаю... отпустите горячую клавишу, чтобы остановить.",
+        (Msg::StatusTranscribing, En) => "Transcribing audio...",
+        (Msg::StatusTranscribing, Ru) => "Распознаю аудио...",
+        (Msg::StatusDelivering, En) => "Typing the transcript...",
+        (Msg::StatusDelivering, Ru) => "Ввожу текст...",
+        (Msg::StatusSuccess, En) => "Transcription complete.",
+        (Msg::StatusSuccess, Ru) => "Распознавание завершено.",
+        (Msg::StatusError, En) => "Something went wrong.",
+        (Msg::StatusError, Ru) => "Что-то пошло не так.",
+        (Msg::ReadyForNext, En) => "Ready for next transcription",
+        (Msg::ReadyForNext, Ru) => "Готово к следующей записи",
+        (Msg::SettingsSaved, En) => "Settings saved. Ready for the next transcription.",
+        (Msg::SettingsSaved, Ru) => "Настройки сохранены. Готово к следующей записи.",
+        (Msg::Recording, En) => "Recording...",
+        (Msg::Recording, Ru) => "Запись...",
+        (Msg::Streaming, En) => "Streaming...",
+        (Msg::Streaming, Ru) => "Потоковое распознавание...",
+        (Msg::UploadingAudio, En) => "Uploading audio...",
+        (Msg::UploadingAudio, Ru) => "Отправляю аудио...",
+        (Msg::Processing, En) => "Processing...",
+        (Msg::Processing, Ru) => "Обработка...",
+        (Msg::ApplyingLlm, En) => "Applying LLM...",
+        (Msg::ApplyingLlm, Ru) => "Обработка LLM...",
+        (Msg::RefiningPart, En) => "Refining part {index}/{total}...",
+        (Msg::RefiningPart, Ru) => "Обработка части {index}/{total}...",
+        (Msg::DecodingFile, En) => "Decoding {name}...",
+        (Msg::DecodingFile, Ru) => "Декодирую {name}...",
+        (Msg::AlreadyTranscribing, En) => "Already transcribing, please wait.",
+        (Msg::AlreadyTranscribing, Ru) => "Распознавание уже идёт, подождите.",
+        (Msg::NothingToRepeat, En) => "Nothing to repeat yet",
+        (Msg::NothingToRepeat, Ru) => "Пока нечего повторить",
+        (Msg::DeliveredAgain, En) => "Last transcript delivered again",
+        (Msg::DeliveredAgain, Ru) => "Последний текст введён повторно",
+        (Msg::NoStreamingTranscript, En) => "No transcript received from ElevenLabs",
+        (Msg::NoStreamingTranscript, Ru) => "ElevenLabs не прислал текст",
+        (Msg::StreamingIdle, En) => "Streaming idle — will reconnect on next press",
+        (Msg::StreamingIdle, Ru) => "Поток простаивает — подключусь снова при следующем нажатии",
+        (Msg::TranscriptionTimedOut, En) => "Transcription timed out",
+        (Msg::TranscriptionTimedOut, Ru) => "Распознавание не завершилось вовремя",
+        (Msg::ShuttingDown, En) => "Shutting down...",
+        (Msg::ShuttingDown, Ru) => "Завершение работы...",
+        (Msg::ProviderSwitched, En) => "Provider: {provider}",
+        (Msg::ProviderSwitched, Ru) => "Провайдер: {provider}",
+        (Msg::PresetSwitched, En) => "Preset: {preset}",
+        (Msg::PresetSwitched, Ru) => "Пресет: {preset}",
+        (Msg::TranslationOn, En) => "Translation ON → {language}",
+        (Msg::TranslationOn, Ru) => "Перевод ВКЛ → {language}",
+        (Msg::TranslationOff, En) => "Translation OFF",
+        (Msg::TranslationOff, Ru) => "Перевод ВЫКЛ",
+        (Msg::TooShort, En) => "Too short — nothing sent",
+        (Msg::TooShort, Ru) => "Слишком коротко — ничего не отправлено",
+        (Msg::TooQuiet, En) => "Too quiet — nothing sent",
+        (Msg::TooQuiet, Ru) => "Слишком тихо — ничего не отправлено",
+        (Msg::ElevatedTarget, En) => {
+            "Target window is elevated — transcript copied to clipboard instead"
+        }
+        (Msg::ElevatedTarget, Ru) => {
+            "Окно запущено от администратора — текст скопирован в буфер обмена"
+        }
+        (Msg::LostAudio, En) => "Some audio was lost on the way — the transcript may be incomplete",
+        (Msg::LostAudio, Ru) => "Часть аудио потерялась по пути — текст может быть неполным",
+        (Msg::Truncated, En) => {
+            "Transcription complete, but the refined text may be cut off (model output limit \
+             reached)."
+        }
+        (Msg::Truncated, Ru) => {
+            "Распознавание завершено, но обработанный текст может быть обрезан (достигнут \
+             лимит ответа модели)."
+        }
+        (Msg::DeliveredUntranslated, En) => {
+            "Delivered without translation — {provider} refinement failed"
+        }
+        (Msg::DeliveredUntranslated, Ru) => {
+            "Текст введён без перевода — обработка {provider} не удалась"
+        }
+        (Msg::DeliveredRaw, En) => "Delivered raw transcript — {provider} refinement failed",
+        (Msg::DeliveredRaw, Ru) => "Введён исходный текст — обработка {provider} не удалась",
+        (Msg::StreamingError, En) => "Streaming error",
+        (Msg::StreamingError, Ru) => "Ошибка потокового распознавания",
+        (Msg::SettingsSaveFailed, En) => "Failed to save settings: {error}",
+        (Msg::SettingsSaveFailed, Ru) => "Не удалось сохранить настройки: {error}",
+        (Msg::SettingsLoadFailed, En) => "Failed to load settings, using defaults: {error}",
+        (Msg::SettingsLoadFailed, Ru) => {
+            "Не удалось загрузить настройки, используются значения по умолчанию: {error}"
+        }
+        (Msg::SegmentCommitFailed, En) => "Failed to commit segment: {error}",
+        (Msg::SegmentCommitFailed, Ru) => "Не удалось завершить фрагмент: {error}",
+        (Msg::GateOpenFailed, En) => "Failed to open gate: {error}",
+        (Msg::GateOpenFailed, Ru) => "Не удалось начать передачу аудио: {error}",
+        (Msg::GateCloseFailed, En) => "Failed to close gate: {error}",
+        (Msg::GateCloseFailed, Ru) => "Не удалось остановить передачу аудио: {error}",
+        (Msg::HotkeyRegistrationFailed, En) => {
+            "Hotkey registration failed: {error}. Close other instances and restart."
+        }
+        (Msg::HotkeyRegistrationFailed, Ru) => {
+            "Не удалось зарегистрировать горячую клавишу: {error}. Закройте другие копии \
+             приложения и перезапустите его."
+        }
+        (Msg::AutostartFailed, En) => "Autostart update failed: {error}",
+        (Msg::AutostartFailed, Ru) => "Не удалось изменить автозапуск: {error}",
+        (Msg::ClipboardFailed, En) => "Failed to copy to clipboard: {error}",
+        (Msg::ClipboardFailed, Ru) => "Не удалось скопировать в буфер обмена: {error}",
+        (Msg::CaptureRestarting, En) => "Audio capture crashed, restarting…",
+        (Msg::CaptureRestarting, Ru) => "Запись звука аварийно остановилась, перезапускаю…",
+        (Msg::CaptureCrashedAgain, En) => "Audio capture crashed again: {error}",
+        (Msg::CaptureCrashedAgain, Ru) => "Запись звука снова остановилась: {error}",
+        (Msg::CaptureRestartFailed, En) => "Failed to restart audio capture: {error}",
+        (Msg::CaptureRestartFailed, Ru) => "Не удалось перезапустить запись звука: {error}",
+        (Msg::InputThreadFailed, En) => "Failed to start the input thread: {error}",
+        (Msg::InputThreadFailed, Ru) => "Не удалось запустить поток ввода: {error}",
+        (Msg::InputThreadCrashed, En) => "The input thread crashed",
+        (Msg::InputThreadCrashed, Ru) => "Поток ввода аварийно завершился",
+        (Msg::InputThreadStopped, En) => "The input thread has stopped",
+        (Msg::InputThreadStopped, Ru) => "Поток ввода остановлен",
+        (Msg::InputInitFailed, En) => "Failed to initialize the keyboard emulator: {error}",
+        (Msg::InputInitFailed, Ru) => "Ошибка инициализации эмулятора: {error}",
+        (Msg::InputNotReady, En) => "The keyboard emulator is not initialized",
+        (Msg::InputNotReady, Ru) => "Эмулятор клавиатуры не инициализирован",
+        (Msg::TypingFailed, En) => "Failed to type the text: {error}",
+        (Msg::TypingFailed, Ru) => "Не удалось ввести текст: {error}",
+        (Msg::KeyPressFailed, En) => "Failed to press {key}: {error}",
+        (Msg::KeyPressFailed, Ru) => "Не удалось нажать {key}: {error}",
+        (Msg::KeyReleaseFailed, En) => "Failed to release {key}: {error}",
+        (Msg::KeyReleaseFailed, Ru) => "Не удалось отпустить {key}: {error}",
+        (Msg::NoMicrophone, En) => "No input microphone detected",
+        (Msg::NoMicrophone, Ru) => "Не найден микрофон по умолчанию",
+        (Msg::MicMuted, En) => "Microphone is muted; unmute it from the tray to dictate",
+        (Msg::MicMuted, Ru) => "Микрофон выключен; включите его в трее, чтобы диктовать",
+        (Msg::MicPrivacyBlocked, En) => {
+            "Microphone access is turned off in Windows privacy settings"
+        }
+        (Msg::MicPrivacyBlocked, Ru) => {
+            "Доступ к микрофону запрещён в настройках конфиденциальности Windows"
+        }
+        (Msg::MicMutedBySystem, En) => "Microphone is muted in Windows settings",
+        (Msg::MicMutedBySystem, Ru) => "Микрофон отключён в настройках Windows",
+        (Msg::RecordingTooQuick, En) => {
+            "Recording stopped too quickly. Hold the hotkey for a little longer before releasing."
+        }
+        (Msg::RecordingTooQuick, Ru) => {
+            "Запись остановлена слишком быстро. Удерживайте горячую клавишу чуть дольше."
+        }
+        (Msg::NoAudioRecorded, En) => "Recording produced no audio",
+        (Msg::NoAudioRecorded, Ru) => "Запись не содержит звука",
+        (Msg::SessionStarted, En) => "Dictation session started",
+        (Msg::SessionStarted, Ru) => "Сессия диктовки начата",
+        (Msg::SessionAlreadyRunning, En) => "A dictation session is already running",
+        (Msg::SessionAlreadyRunning, Ru) => "Сессия диктовки уже идёт",
+        (Msg::NoSessionRunning, En) => "No dictation session is running",
+        (Msg::NoSessionRunning, Ru) => "Сессия диктовки не запущена",
+        (Msg::SessionUtteranceAdded, En) => "Added to the session ({count} so far)",
+        (Msg::SessionUtteranceAdded, Ru) => "Добавлено в сессию (всего {count})",
+        (Msg::SessionCombining, En) => "Combining the session...",
+        (Msg::SessionCombining, Ru) => "Собираю сессию...",
+        (Msg::SessionSaved, En) => "Session of {count} utterances copied to the clipboard",
+        (Msg::SessionSaved, Ru) => "Сессия из {count} фрагментов скопирована в буфер обмена",
+        (Msg::SessionEmpty, En) => "The session ended without any dictation",
+        (Msg::SessionEmpty, Ru) => "Сессия завершена без диктовки",
+        (Msg::SessionFileFailed, En) => "Failed to write the session to {path}: {error}",
+        (Msg::SessionFileFailed, Ru) => "Не удалось записать сессию в {path}: {error}",
+    })
+}
+
+/// `msg` in `language`
+pub fn text_in(msg: Msg, language: Language) -> &'static str {
+    lookup(msg, language)
+        .or_else(|| lookup(msg, Language::En))
+        .unwrap_or_default()
+}
+
+/// `msg` in the interface language
+pub fn text(msg: Msg) -> &'static str {
+    text_in(msg, language())
+}
+
+/// `msg` in the interface language with its `{name}` placeholders replaced
+pub fn fill(msg: Msg, values: &[(&str, &dyn std::fmt::Display)]) -> String {
+    values
+        .iter()
+        .fold(text(msg).to_string(), |message, (name, value)| {
+            message.replace(&format!("{{{name}}}"), &value.to_string())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `{name}` placeholders of a message
+    fn placeholders(message: &str) -> Vec<&str> {
+        let mut names: Vec<&str> = message
+            .split('{')
+            .skip(1)
+            .filter_map(|rest| rest.split_once('}').map(|(name, _)| name))
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    #[test]
+    fn test_every_message_is_translated() {
+        for msg in Msg::ALL {
+            let english = lookup(msg, Language::En).unwrap();
+            for language in Language::ALL {
+                let translated = lookup(msg, language)
+                    .unwrap_or_else(|| panic!("{msg:?} has no {language:?} text"));
+                assert!(
+                    !translated.trim().is_empty(),
+                    "{msg:?} is empty in {language:?}"
+                );
+                assert_eq!(
+                    placeholders(translated),
+                    placeholders(english),
+                    "{msg:?} in {language:?} has other placeholders than in English"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_language_from_locale() {
+        assert_eq!(Language::from_code("ru"), Language::Ru);
+        assert_eq!(Language::from_code("ru-RU"), Language::Ru);
+        assert_eq!(Language::from_code("ru_UA.UTF-8"), Language::Ru);
+        assert_eq!(Language::from_code(" EN "), Language::En);
+        assert_eq!(Language::from_code("de-DE"), Language::En);
+        // Not Russian just because the code starts with "ru"
+        assert_eq!(Language::from_code("rue"), Language::En);
+        assert_eq!(Language::from_code(""), Language::En);
+    }
+
+    #[test]
+    fn test_fill_replaces_placeholders() {
+        assert_eq!(
+            text_in(Msg::RefiningPart, Language::Ru),
+            "Обработка части {index}/{total}..."
+        );
+        let filled = fill(Msg::RefiningPart, &[("index", &2), ("total", &5)]);
+        assert!(filled.contains("2/5"), "{filled}");
+        assert!(!filled.contains('{'), "{filled}");
+    }
+}
@@ -0,0 +1,156 @@
+use thiserror::Error;
+
+use crate::messages::{self, Msg};
+
+/// Why the OS hands the app silence instead of the microphone
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum MicBlocked {
+    /// Microphone access is turned off in the privacy settings
+    #[error("{}", messages::text(Msg::MicPrivacyBlocked))]
+    PrivacyDenied,
+    /// The input device itself is muted in the sound settings
+    #[error("{}", messages::text(Msg::MicMutedBySystem))]
+    EndpointMuted,
+}
+
+/// Whether the OS lets the app hear the default microphone. When it doesn't, the
+/// capture still opens and records silence, so this is asked before recording.
+pub trait MicAccessCheck: Send + Sync + 'static {
+    fn blocked(&self) -> Option<MicBlocked>;
+}
+
+/// Asks the OS; never blocks outside Windows
+pub struct SystemMicAccess;
+
+impl MicAccessCheck for SystemMicAccess {
+    #[cfg(windows)]
+    fn blocked(&self) -> Option<MicBlocked> {
+        // A failed query lets the recording go ahead; the worst case is the old behaviour
+        if consent_denied(win32::consent_values()) {
+            return Some(MicBlocked::PrivacyDenied);
+        }
+        (win32::default_capture_muted() == Some(true)).then_some(MicBlocked::EndpointMuted)
+    }
+
+    #[cfg(not(windows))]
+    fn blocked(&self) -> Option<MicBlocked> {
+        None
+    }
+}
+
+/// Any of the consent switches is "Deny"; a missing one allows
+#[cfg_attr(not(windows), allow(dead_code))]
+fn consent_denied(values: impl IntoIterator<Item = Option<String>>) -> bool {
+    values
+        .into_iter()
+        .flatten()
+        .any(|value| value.trim().eq_ignore_ascii_case("deny"))
+}
+
+#[cfg(windows)]
+mod win32 {
+    use std::ffi::c_void;
+
+    use windows::{
+        core::{w, PCWSTR},
+        Win32::{
+            Media::Audio::{
+                eCapture, eConsole, Endpoints::IAudioEndpointVolume, IMMDeviceEnumerator,
+                MMDeviceEnumerator,
+            },
+            System::{
+                Com::{
+                    CoCreateInstance, CoInitializeEx, CoUninitialize, CLSCTX_ALL,
+                    COINIT_MULTITHREADED,
+                },
+                Registry::{
+                    RegGetValueW, HKEY, HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE, RRF_RT_REG_SZ,
+                },
+            },
+        },
+    };
+
+    const CONSENT_STORE: PCWSTR = w!(
+        r"Software\Microsoft\Windows\CurrentVersion\CapabilityAccessManager\ConsentStore\microphone"
+    );
+    const CONSENT_STORE_DESKTOP: PCWSTR = w!(
+        r"Software\Microsoft\Windows\CurrentVersion\CapabilityAccessManager\ConsentStore\microphone\NonPackaged"
+    );
+
+    fn read_string(root: HKEY, key: PCWSTR) -> Option<String> {
+        let mut buffer = [0u16; 32];
+        let mut size = std::mem::size_of_val(&buffer) as u32;
+        // SAFETY: the buffer and its size in bytes match; the value is read as REG_SZ
+        // only, which RegGetValueW null-terminates
+        let result = unsafe {
+            RegGetValueW(
+                root,
+                key,
+                w!("Value"),
+                RRF_RT_REG_SZ,
+                None,
+                Some(buffer.as_mut_ptr() as *mut c_void),
+                Some(&mut size as *mut u32),
+            )
+        };
+        result.ok().ok()?;
+        let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+        Some(String::from_utf16_lossy(&buffer[..len]))
+    }
+
+    /// The device-wide switch, the user's switch and the one for desktop apps
+    pub fn consent_values() -> [Option<String>; 3] {
+        [
+            read_string(HKEY_LOCAL_MACHINE, CONSENT_STORE),
+            read_string(HKEY_CURRENT_USER, CONSENT_STORE),
+            read_string(HKEY_CURRENT_USER, CONSENT_STORE_DESKTOP),
+        ]
+    }
+
+    /// Mute state of the default recording device; None if it can't be queried
+    pub fn default_capture_muted() -> Option<bool> {
+        // SAFETY: COM is initialized for this call and uninitialized only if we did
+        // it; the interfaces are released before that
+        unsafe {
+            // Fails on a thread already in another apartment, where COM works anyway
+            let initialized = CoInitializeEx(None, COINIT_MULTITHREADED).is_ok();
+            let muted = (|| {
+                let enumerator: IMMDeviceEnumerator =
+                    CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL).ok()?;
+                let device = enumerator
+                    .GetDefaultAudioEndpoint(eCapture, eConsole)
+                    .ok()?;
+                let volume: IAudioEndpointVolume = device.Activate(CLSCTX_ALL, None).ok()?;
+                volume.GetMute().ok().map(|muted| muted.as_bool())
+            })();
+            if initialized {
+                CoUninitialize();
+            }
+            muted
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_consent_denied() {
+        let value = |v: &str| Some(v.to_string());
+        assert!(!consent_denied([None, None, None]));
+        assert!(!consent_denied([value("Allow"), value("Allow"), None]));
+        assert!(consent_denied([value("Allow"), value("Deny"), None]));
+        assert!(consent_denied([None, None, value("deny")]));
+        assert!(!consent_denied([value("Prompt")]));
+    }
+
+    #[test]
+    fn test_blocked_message() {
+        // The interface language is global, so accept any of them
+        let message = MicBlocked::EndpointMuted.to_string();
+        assert!(messages::Language::ALL
+            .into_iter()
+            .any(|language| message == messages::text_in(Msg::MicMutedBySystem, language)));
+    }
+}
@@ -0,0 +1,153 @@
+use anyhow::{anyhow, Context, Result};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::{
+    openai::{RefinedText, RefinementRequest},
+    speech_to_text::HttpStatusError,
+};
+
+/// A local server answers at once or isn't running
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Generous, as the first request after a start waits for the model to load; a
+/// server that hangs must still not block refinement forever
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Client for a local Ollama server; no API key involved
+#[derive(Clone)]
+pub struct OllamaClient {
+    client: Client,
+}
+
+#[derive(Serialize)]
+struct ChatMessage {
+    role: String,
+    content: String,
+}
+
+#[derive(Serialize)]
+struct ChatOptions {
+    temperature: f32,
+}
+
+#[derive(Serialize)]
+struct ChatRequest {
+    model: String,
+    messages: Vec<ChatMessage>,
+    stream: bool,
+    options: ChatOptions,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    message: ChatContent,
+    /// "length" when generation stopped at the num_predict limit
+    #[serde(default)]
+    done_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChatContent {
+    content: String,
+}
+
+impl OllamaClient {
+    pub fn new() -> Result<Self> {
+        let client = Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .context("Failed to build HTTP client for Ollama")?;
+        Ok(Self { client })
+    }
+
+    /// Check that an Ollama server answers at `base_url`
+    pub async fn check_server(&self, base_url: &str) -> Result<()> {
+        let url = format!("{}/api/tags", base_url.trim().trim_end_matches('/'));
+        let response = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .context("Ollama server is not reachable")?;
+
+        if !response.status().is_success() {
+            return Err(HttpStatusError::new("Ollama", response.status()).into());
+        }
+        Ok(())
+    }
+
+    pub async fn refine_transcript(
+        &self,
+        text: String,
+        base_url: &str,
+        job: &RefinementRequest,
+    ) -> Result<RefinedText> {
+        if text.trim().is_empty() {
+            return Ok(RefinedText::complete(String::new()));
+        }
+
+        if base_url.trim().is_empty() {
+            return Err(anyhow!("Ollama base URL is required for post-processing"));
+        }
+
+        let Some(system_prompt) = job.system_prompt() else {
+            return Ok(RefinedText::complete(text));
+        };
+
+        let url = format!("{}/api/chat", base_url.trim().trim_end_matches('/'));
+
+        let messages = job
+            .messages(&text, system_prompt)
+            .into_iter()
+            .map(|(role, content)| ChatMessage {
+                role: role.to_string(),
+                content,
+            })
+            .collect();
+
+        let request = ChatRequest {
+            model: job.model.clone(),
+            messages,
+            stream: false,
+            options: ChatOptions {
+                temperature: job.temperature,
+            },
+        };
+
+        let response = match self.client.post(&url).json(&request).send().await {
+            Ok(response) => response,
+            Err(e) if e.is_connect() => {
+                return Err(anyhow!(
+                    "Could not connect to Ollama at {}. Is Ollama running?",
+                    base_url.trim()
+                ));
+            }
+            Err(e) if e.is_timeout() => {
+                return Err(anyhow!(
+                    "Ollama at {} did not answer within {}s",
+                    base_url.trim(),
+                    REQUEST_TIMEOUT.as_secs()
+                ));
+            }
+            Err(e) => return Err(e).context("Ollama refinement request failed"),
+        };
+
+        if !response.status().is_success() {
+            return Err(HttpStatusError::from_response("Ollama", response)
+                .await
+                .into());
+        }
+
+        let payload: ChatResponse = response
+            .json()
+            .await
+            .context("Failed to parse Ollama refinement response")?;
+
+        Ok(RefinedText {
+            text: payload.message.content.trim().to_string(),
+            truncated: payload.done_reason.as_deref() == Some("length"),
+        })
+    }
+}
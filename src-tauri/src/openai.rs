@@ -2,11 +2,53 @@ use anyhow::{anyhow, Context, Result};
 use reqwest::{multipart::Form, Client};
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug)]
+use crate::{
+    settings::SettingsValidationError,
+    speech_to_text::{HttpStatusError, ProviderError},
+};
+
+/// The only model `/v1/audio/translations` accepts
+const TRANSLATION_MODEL: &str = "whisper-1";
+
+#[derive(Clone, Debug, Default)]
 pub struct TranscriptionRequest {
     pub api_key: String,
     pub model: String,
     pub audio_wav: Vec<u8>,
+    /// ISO-639-1 code of the spoken language; None = detect
+    pub language: Option<String>,
+    /// Text that guides spelling and style, e.g. vocabulary terms
+    pub prompt: Option<String>,
+    /// Sampling temperature; None = the provider default
+    pub temperature: Option<f32>,
+    /// Ask for `verbose_json`: segments and the detected language
+    pub verbose: bool,
+}
+
+impl TranscriptionRequest {
+    /// Multipart form for `/v1/audio/*` sending `model`, with the optional fields that
+    /// are set. OpenAI and Groq take the same form.
+    pub(crate) fn into_form(self, model: String) -> Result<Form> {
+        let part = reqwest::multipart::Part::bytes(self.audio_wav)
+            .file_name("clip.wav")
+            .mime_str("audio/wav")
+            .context("Failed to build multipart payload for transcription")?;
+        let response_format = if self.verbose { "verbose_json" } else { "json" };
+
+        let mut form = Form::new()
+            .text("model", model)
+            .text("response_format", response_format);
+        if let Some(language) = self.language.filter(|l| !l.trim().is_empty()) {
+            form = form.text("language", language.trim().to_string());
+        }
+        if let Some(prompt) = self.prompt.filter(|p| !p.trim().is_empty()) {
+            form = form.text("prompt", prompt);
+        }
+        if let Some(temperature) = self.temperature {
+            form = form.text("temperature", temperature.to_string());
+        }
+        Ok(form.part("file", part))
+    }
 }
 
 impl RefinementRequest {
@@ -42,9 +84,14 @@ impl RefinementRequest {
         }
 
         if self.auto_translate {
+            let source = self
+                .source_language
+                .as_deref()
+                .map(|lang| format!(" from {lang}"))
+                .unwrap_or_default();
             directives.push(format!(
-                "Translate the transcript into {}, keeping the original intent and tone.",
-                self.target_language
+                "Translate the transcript{} into {}, keeping the original intent and tone.",
+                source, self.target_language
             ));
         } else if !self.has_vocabulary() {
             // Only add generic polish if not just doing vocabulary correction
@@ -65,6 +112,13 @@ impl RefinementRequest {
             ));
         }
 
+        if let Some(chunk) = self.chunk {
+            directives.push(format!(
+                "This is part {} of {} of a longer transcript. Process only this part, keep terminology and style consistent with the surrounding parts, and do not add introductions or closing remarks.",
+                chunk.index, chunk.total
+            ));
+        }
+
         directives.push("Return only the updated transcript with no commentary.".to_string());
 
         Some(format!(
@@ -72,6 +126,29 @@ impl RefinementRequest {
             directives.join(" ")
         ))
     }
+
+    /// The chat turns of a refinement as (role, content): the system prompt, the
+    /// context block if there is one, then the transcript. Each client maps them onto
+    /// its own message type.
+    pub fn messages(&self, text: &str, system_prompt: String) -> Vec<(&'static str, String)> {
+        let mut messages = vec![("system", system_prompt)];
+        if let Some(context) = self.context_prompt() {
+            messages.push(("system", context));
+        }
+        messages.push(("user", text.trim().to_string()));
+        messages
+    }
+
+    /// Condensed block of recent dictations, sent as an extra system message
+    pub fn context_prompt(&self) -> Option<String> {
+        if self.context.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "For context only, the user recently dictated the following. Use it to keep pronouns and terminology consistent, but do not repeat, translate or include it in your reply:\n{}",
+            self.context.join("\n---\n")
+        ))
+    }
 }
 #[derive(Clone, Debug)]
 pub struct RefinementRequest {
@@ -79,8 +156,38 @@ pub struct RefinementRequest {
     pub model: String,
     pub auto_translate: bool,
     pub target_language: String,
+    /// Detected language of the transcript, used to make translation more precise
+    pub source_language: Option<String>,
     pub custom_instructions: Option<String>,
     pub vocabulary: Vec<String>,
+    pub temperature: f32,
+    /// Set when this request covers one part of a transcript split for length
+    pub chunk: Option<ChunkPosition>,
+    /// Recent dictations (oldest first) for consistent pronouns and terminology
+    pub context: Vec<String>,
+}
+
+/// 1-based position of a chunk within a transcript that was split for refinement
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChunkPosition {
+    pub index: usize,
+    pub total: usize,
+}
+
+/// Refined text plus whether the model stopped early because it hit its output limit
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RefinedText {
+    pub text: String,
+    pub truncated: bool,
+}
+
+impl RefinedText {
+    pub fn complete(text: String) -> Self {
+        Self {
+            text,
+            truncated: false,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -89,9 +196,40 @@ pub struct OpenAiClient {
     base_url: String,
 }
 
+/// Body of a `json` or `verbose_json` transcription; only the latter has the
+/// language and segments
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct TranscriptionResponse {
+    pub text: String,
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub segments: Vec<TranscriptSegment>,
+}
+
+/// A stretch of a `verbose_json` transcript, with times in seconds
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct TranscriptSegment {
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+}
+
+/// Body of `/v1/models`; Groq's OpenAI-compatible API answers the same way
+#[derive(Deserialize)]
+pub struct ModelsResponse {
+    data: Vec<ModelEntry>,
+}
+
 #[derive(Deserialize)]
-struct TranscriptionResponse {
-    text: String,
+struct ModelEntry {
+    id: String,
+}
+
+impl ModelsResponse {
+    pub fn into_ids(self) -> Vec<String> {
+        self.data.into_iter().map(|model| model.id).collect()
+    }
 }
 
 #[derive(Serialize)]
@@ -115,6 +253,17 @@ struct ChatResponse {
 #[derive(Deserialize)]
 struct ChatChoice {
     message: ChatContent,
+    #[serde(default)]
+    finish_reason: Option<String>,
+}
+
+impl ChatResponse {
+    fn into_refined(self) -> Option<RefinedText> {
+        self.choices.into_iter().next().map(|choice| RefinedText {
+            text: choice.message.content.trim().to_string(),
+            truncated: choice.finish_reason.as_deref() == Some("length"),
+        })
+    }
 }
 
 #[derive(Deserialize)]
@@ -122,52 +271,109 @@ struct ChatContent {
     content: String,
 }
 
+/// The public API, unless `OPENAI_BASE_URL` points elsewhere
+pub fn default_base_url() -> String {
+    std::env::var("OPENAI_BASE_URL")
+        .ok()
+        .unwrap_or_else(|| "https://api.openai.com".to_string())
+}
+
 impl OpenAiClient {
     pub fn new() -> Result<Self> {
+        Self::with_base_url(default_base_url())
+    }
+
+    pub fn with_base_url(base_url: String) -> Result<Self> {
         let client = Client::builder()
             .build()
             .context("Failed to build HTTP client for OpenAI")?;
-        let base_url = std::env::var("OPENAI_BASE_URL")
-            .ok()
-            .unwrap_or_else(|| "https://api.openai.com".to_string());
         Ok(Self { client, base_url })
     }
 
+    /// Cheap authenticated request that succeeds only if the key is accepted
+    pub async fn check_key(&self, api_key: &str) -> Result<()> {
+        let url = format!("{}/v1/models", self.base_url.trim_end_matches('/'));
+        let response = self
+            .client
+            .get(url)
+            .bearer_auth(api_key)
+            .send()
+            .await
+            .context("OpenAI key check request failed")?;
+
+        if !response.status().is_success() {
+            return Err(HttpStatusError::new("OpenAI", response.status()).into());
+        }
+        Ok(())
+    }
+
+    /// Ids of every model the key can use
+    pub async fn list_models(&self, api_key: &str) -> Result<Vec<String>> {
+        let url = format!("{}/v1/models", self.base_url.trim_end_matches('/'));
+        let response = self
+            .client
+            .get(url)
+            .bearer_auth(api_key)
+            .send()
+            .await
+            .context("OpenAI model list request failed")?;
+
+        if !response.status().is_success() {
+            return Err(HttpStatusError::new("OpenAI", response.status()).into());
+        }
+        let payload: ModelsResponse = response
+            .json()
+            .await
+            .context("Failed to parse OpenAI model list")?;
+        Ok(payload.into_ids())
+    }
+
     pub async fn transcribe(&self, job: TranscriptionRequest) -> Result<String> {
+        self.post_audio("transcriptions", job).await
+    }
+
+    /// Transcribe and translate into English in a single request. The endpoint only
+    /// serves `TRANSLATION_MODEL`, so `job.model` is ignored.
+    pub async fn translate_to_english(&self, job: TranscriptionRequest) -> Result<String> {
+        self.post_audio(
+            "translations",
+            TranscriptionRequest {
+                model: TRANSLATION_MODEL.to_string(),
+                // Translations are always into English; the endpoint takes no language
+                language: None,
+                ..job
+            },
+        )
+        .await
+    }
+
+    /// Upload `job.audio_wav` to `/v1/audio/{endpoint}` and return the text
+    async fn post_audio(&self, endpoint: &str, job: TranscriptionRequest) -> Result<String> {
         if job.api_key.trim().is_empty() {
-            return Err(anyhow!("OpenAI API key is missing"));
+            return Err(ProviderError::MissingApiKey("OpenAI").into_anyhow());
         }
 
         let url = format!(
-            "{}/v1/audio/transcriptions",
+            "{}/v1/audio/{endpoint}",
             self.base_url.trim_end_matches('/')
         );
-        let part = reqwest::multipart::Part::bytes(job.audio_wav)
-            .file_name("clip.wav")
-            .mime_str("audio/wav")
-            .context("Failed to build multipart payload for transcription")?;
-
-        let form = Form::new()
-            .text("model", job.model)
-            .text("response_format", "json")
-            .part("file", part);
+        let api_key = job.api_key.clone();
+        let model = job.model.clone();
+        let form = job.into_form(model)?;
 
         let response = self
             .client
             .post(url)
-            .bearer_auth(job.api_key)
+            .bearer_auth(api_key)
             .multipart(form)
             .send()
             .await
-            .context("OpenAI transcription request failed")?;
+            .with_context(|| format!("OpenAI {endpoint} request failed"))?;
 
         if !response.status().is_success() {
-            let status = response.status();
-            let body = response
-                .text()
+            return Err(HttpStatusError::from_response("OpenAI", response)
                 .await
-                .unwrap_or_else(|_| "<failed to read error body>".into());
-            return Err(anyhow!("OpenAI responded with {}: {}", status, body));
+                .into());
         }
 
         let payload: TranscriptionResponse = response
@@ -177,17 +383,21 @@ impl OpenAiClient {
         Ok(payload.text.trim().to_string())
     }
 
-    pub async fn refine_transcript(&self, text: String, job: &RefinementRequest) -> Result<String> {
+    pub async fn refine_transcript(
+        &self,
+        text: String,
+        job: &RefinementRequest,
+    ) -> Result<RefinedText> {
         if text.trim().is_empty() {
-            return Ok(String::new());
+            return Ok(RefinedText::complete(String::new()));
         }
 
         if job.api_key.trim().is_empty() {
-            return Err(anyhow!("OpenAI API key is required for post-processing"));
+            return Err(SettingsValidationError::MissingApiKey("OpenAI").into());
         }
 
         let Some(system_prompt) = job.system_prompt() else {
-            return Ok(text);
+            return Ok(RefinedText::complete(text));
         };
 
         let url = format!(
@@ -195,20 +405,7 @@ impl OpenAiClient {
             self.base_url.trim_end_matches('/')
         );
 
-        let request = ChatRequest {
-            model: job.model.clone(),
-            messages: vec![
-                ChatMessage {
-                    role: "system".to_string(),
-                    content: system_prompt,
-                },
-                ChatMessage {
-                    role: "user".to_string(),
-                    content: text.trim().to_string(),
-                },
-            ],
-            temperature: 0.3,
-        };
+        let request = build_chat_request(&text, system_prompt, job);
 
         let response = self
             .client
@@ -220,16 +417,9 @@ impl OpenAiClient {
             .context("OpenAI refinement request failed")?;
 
         if !response.status().is_success() {
-            let status = response.status();
-            let body = response
-                .text()
+            return Err(HttpStatusError::from_response("OpenAI", response)
                 .await
-                .unwrap_or_else(|_| "<failed to read error body>".into());
-            return Err(anyhow!(
-                "OpenAI responded with {} to refinement request: {}",
-                status,
-                body
-            ));
+                .into());
         }
 
         let payload: ChatResponse = response
@@ -238,9 +428,128 @@ impl OpenAiClient {
             .context("Failed to parse OpenAI refinement response")?;
 
         payload
-            .choices
-            .first()
-            .map(|choice| choice.message.content.trim().to_string())
+            .into_refined()
             .ok_or_else(|| anyhow!("OpenAI refinement response contained no choices"))
     }
 }
+
+fn build_chat_request(text: &str, system_prompt: String, job: &RefinementRequest) -> ChatRequest {
+    let messages = job
+        .messages(text, system_prompt)
+        .into_iter()
+        .map(|(role, content)| ChatMessage {
+            role: role.to_string(),
+            content,
+        })
+        .collect();
+
+    ChatRequest {
+        model: job.model.clone(),
+        messages,
+        temperature: job.temperature,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chat_request_uses_configured_model_and_temperature() {
+        let job = RefinementRequest {
+            api_key: "key".to_string(),
+            model: "gpt-4.1-mini".to_string(),
+            auto_translate: true,
+            target_language: "English".to_string(),
+            source_language: None,
+            custom_instructions: None,
+            vocabulary: Vec::new(),
+            temperature: 0.5,
+            chunk: None,
+            context: Vec::new(),
+        };
+
+        let request = build_chat_request(" привет ", "system".to_string(), &job);
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["model"], "gpt-4.1-mini");
+        assert_eq!(json["temperature"], 0.5);
+        assert_eq!(json["messages"][1]["content"], "привет");
+    }
+
+    #[test]
+    fn test_models_response_ids() {
+        let payload: ModelsResponse = serde_json::from_str(
+            r#"{"object":"list","data":[{"id":"whisper-1","object":"model","owned_by":"openai"},{"id":"gpt-4o-mini"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(payload.into_ids(), vec!["whisper-1", "gpt-4o-mini"]);
+    }
+
+    #[test]
+    fn test_finish_reason_length_marks_truncated() {
+        let payload: ChatResponse = serde_json::from_str(
+            r#"{"choices":[{"message":{"content":" cut off "},"finish_reason":"length"}]}"#,
+        )
+        .unwrap();
+        let refined = payload.into_refined().unwrap();
+        assert_eq!(refined.text, "cut off");
+        assert!(refined.truncated);
+
+        let payload: ChatResponse =
+            serde_json::from_str(r#"{"choices":[{"message":{"content":"done"}}]}"#).unwrap();
+        assert!(!payload.into_refined().unwrap().truncated);
+    }
+
+    #[test]
+    fn test_system_prompt_mentions_chunk_position() {
+        let mut job = RefinementRequest {
+            api_key: String::new(),
+            model: String::new(),
+            auto_translate: true,
+            target_language: "English".to_string(),
+            source_language: None,
+            custom_instructions: None,
+            vocabulary: Vec::new(),
+            temperature: 0.3,
+            chunk: None,
+            context: Vec::new(),
+        };
+        assert!(!job.system_prompt().unwrap().contains("part"));
+
+        job.chunk = Some(ChunkPosition { index: 2, total: 3 });
+        assert!(job.system_prompt().unwrap().contains("part 2 of 3"));
+    }
+
+    #[test]
+    fn test_context_is_sent_between_system_prompt_and_transcript() {
+        let mut job = RefinementRequest {
+            api_key: "key".to_string(),
+            model: "gpt-4o-mini".to_string(),
+            auto_translate: true,
+            target_language: "English".to_string(),
+            source_language: None,
+            custom_instructions: None,
+            vocabulary: Vec::new(),
+            temperature: 0.3,
+            chunk: None,
+            context: Vec::new(),
+        };
+        let json =
+            serde_json::to_value(build_chat_request("она пришла", "system".to_string(), &job))
+                .unwrap();
+        assert_eq!(json["messages"].as_array().unwrap().len(), 2);
+
+        job.context = vec!["Моя сестра Анна приехала.".to_string()];
+        let json =
+            serde_json::to_value(build_chat_request("она пришла", "system".to_string(), &job))
+                .unwrap();
+        let messages = json["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[1]["role"], "system");
+        assert!(messages[1]["content"]
+            .as_str()
+            .unwrap()
+            .contains("Моя сестра Анна приехала."));
+        assert_eq!(messages[2]["content"], "она пришла");
+    }
+}
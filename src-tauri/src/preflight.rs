@@ -0,0 +1,266 @@
+use std::{io::Cursor, time::Duration};
+
+use hound::WavReader;
+
+use crate::{
+    audio_stream::CHUNK_MS,
+    messages::{self, Msg},
+};
+
+/// Recordings shorter than this aren't uploaded
+pub const DEFAULT_MIN_UPLOAD_DURATION_MS: u64 = 300;
+/// Recordings with a lower overall RMS (fraction of full scale, ~-56 dBFS) aren't uploaded
+pub const DEFAULT_MIN_UPLOAD_RMS: f32 = 0.0015;
+
+/// Limits below which a recording is dropped instead of sent to the provider.
+/// Zero disables a check.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UploadThresholds {
+    pub min_duration: Duration,
+    pub min_rms: f32,
+}
+
+/// Why a recording wasn't sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Skip {
+    TooShort,
+    TooQuiet,
+}
+
+impl Skip {
+    /// Status message shown instead of a transcript
+    pub fn message(self) -> &'static str {
+        messages::text(match self {
+            Skip::TooShort => Msg::TooShort,
+            Skip::TooQuiet => Msg::TooQuiet,
+        })
+    }
+}
+
+impl UploadThresholds {
+    /// Whether interleaved PCM16 `samples` are worth a provider call
+    pub fn check_samples(&self, samples: &[i16], sample_rate: u32, channels: u16) -> Option<Skip> {
+        let frames = samples.len() / usize::from(channels.max(1));
+        let duration = Duration::from_secs_f64(frames as f64 / f64::from(sample_rate.max(1)));
+        if duration < self.min_duration {
+            return Some(Skip::TooShort);
+        }
+        if self.min_rms > 0.0 && rms(samples) < self.min_rms {
+            return Some(Skip::TooQuiet);
+        }
+        None
+    }
+
+    /// `check_samples` for the WAV bytes of a finished recording. WAV that doesn't
+    /// parse is sent anyway: the provider's error says more than a guess here.
+    pub fn check_wav(&self, wav: &[u8]) -> Option<Skip> {
+        if self.min_duration.is_zero() && self.min_rms <= 0.0 {
+            return None;
+        }
+        let mut reader = WavReader::new(Cursor::new(wav)).ok()?;
+        let spec = reader.spec();
+        let samples: Vec<i16> = reader.samples::<i16>().collect::<Result<_, _>>().ok()?;
+        self.check_samples(&samples, spec.sample_rate, spec.channels)
+    }
+
+    /// Fewest streamed chunks worth a commit; at least one
+    pub fn min_streaming_chunks(&self) -> u64 {
+        (self.min_duration.as_millis() as u64)
+            .div_ceil(CHUNK_MS)
+            .max(1)
+    }
+
+    /// Whether a streamed chunk (mono PCM16 little-endian) is loud enough to hold
+    /// speech. The same level the noise gate silences chunks below; with the check
+    /// disabled every chunk counts.
+    pub fn is_voiced(&self, pcm: &[u8]) -> bool {
+        self.min_rms <= 0.0 || pcm_rms(pcm) >= self.min_rms
+    }
+
+    /// Whether a streamed utterance of `chunks` chunks, `voiced` of them loud enough
+    /// for speech (`is_voiced`), is worth a commit
+    pub fn check_stream(&self, chunks: u64, voiced: u64) -> Option<Skip> {
+        if chunks < self.min_streaming_chunks() {
+            return Some(Skip::TooShort);
+        }
+        if voiced == 0 {
+            return Some(Skip::TooQuiet);
+        }
+        None
+    }
+}
+
+/// Overall RMS as a fraction of full scale
+fn rms(samples: &[i16]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let sum_squares: f64 = samples.iter().map(|&s| f64::from(s) * f64::from(s)).sum();
+    ((sum_squares / samples.len() as f64).sqrt() / 32768.0) as f32
+}
+
+/// `rms` of PCM16 little-endian bytes
+pub fn pcm_rms(pcm: &[u8]) -> f32 {
+    let samples: Vec<i16> = pcm
+        .chunks_exact(2)
+        .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    rms(&samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 16_000;
+
+    fn thresholds(min_duration_ms: u64, min_rms: f32) -> UploadThresholds {
+        UploadThresholds {
+            min_duration: Duration::from_millis(min_duration_ms),
+            min_rms,
+        }
+    }
+
+    /// `ms` of a 440 Hz tone with the given peak amplitude
+    fn tone(ms: u64, amplitude: f32) -> Vec<i16> {
+        let count = RATE as u64 * ms / 1000;
+        (0..count)
+            .map(|n| {
+                let phase = 2.0 * std::f32::consts::PI * 440.0 * n as f32 / RATE as f32;
+                (amplitude * phase.sin()).round() as i16
+            })
+            .collect()
+    }
+
+    /// A stream of 100 ms chunks: `silent` of digital silence, then `spoken` of tone
+    /// at `amplitude`
+    fn stream(silent: usize, spoken: usize, amplitude: f32) -> Vec<Vec<u8>> {
+        let chunk = |amplitude: f32| -> Vec<u8> {
+            tone(CHUNK_MS, amplitude)
+                .iter()
+                .flat_map(|s| s.to_le_bytes())
+                .collect()
+        };
+        std::iter::repeat_with(|| chunk(0.0))
+            .take(silent)
+            .chain(std::iter::repeat_with(|| chunk(amplitude)).take(spoken))
+            .collect()
+    }
+
+    /// `check_stream` the way the release path sees a stream: counted chunk by chunk
+    fn check_chunks(limits: &UploadThresholds, chunks: &[Vec<u8>]) -> Option<Skip> {
+        let voiced = chunks
+            .iter()
+            .filter(|chunk| limits.is_voiced(chunk))
+            .count();
+        limits.check_stream(chunks.len() as u64, voiced as u64)
+    }
+
+    fn wav(samples: &[i16], channels: u16) -> Vec<u8> {
+        let mut cursor = Cursor::new(Vec::new());
+        let mut writer = hound::WavWriter::new(
+            &mut cursor,
+            hound::WavSpec {
+                channels,
+                sample_rate: RATE,
+                bits_per_sample: 16,
+                sample_format: hound::SampleFormat::Int,
+            },
+        )
+        .unwrap();
+        for &sample in samples {
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+        cursor.into_inner()
+    }
+
+    #[test]
+    fn test_short_clip_is_skipped() {
+        let limits = thresholds(300, 0.0);
+        assert_eq!(
+            limits.check_samples(&tone(200, 8000.0), RATE, 1),
+            Some(Skip::TooShort)
+        );
+        assert_eq!(limits.check_samples(&tone(400, 8000.0), RATE, 1), None);
+        // Interleaved stereo: twice the samples, same playing time
+        let stereo: Vec<i16> = tone(200, 8000.0).iter().flat_map(|&s| [s, s]).collect();
+        assert_eq!(limits.check_samples(&stereo, RATE, 2), Some(Skip::TooShort));
+    }
+
+    #[test]
+    fn test_quiet_clip_is_skipped() {
+        let limits = thresholds(0, DEFAULT_MIN_UPLOAD_RMS);
+        assert_eq!(
+            limits.check_samples(&tone(1000, 40.0), RATE, 1),
+            Some(Skip::TooQuiet)
+        );
+        assert_eq!(
+            limits.check_samples(&[0; RATE as usize], RATE, 1),
+            Some(Skip::TooQuiet)
+        );
+        assert_eq!(limits.check_samples(&tone(1000, 3000.0), RATE, 1), None);
+    }
+
+    #[test]
+    fn test_zero_thresholds_disable_the_checks() {
+        let limits = thresholds(0, 0.0);
+        assert_eq!(limits.check_samples(&[], RATE, 1), None);
+        assert_eq!(limits.check_wav(b"not a wav"), None);
+    }
+
+    #[test]
+    fn test_check_wav_reads_the_recording() {
+        let limits = thresholds(DEFAULT_MIN_UPLOAD_DURATION_MS, DEFAULT_MIN_UPLOAD_RMS);
+        assert_eq!(
+            limits.check_wav(&wav(&tone(150, 8000.0), 1)),
+            Some(Skip::TooShort)
+        );
+        assert_eq!(
+            limits.check_wav(&wav(&tone(800, 10.0), 1)),
+            Some(Skip::TooQuiet)
+        );
+        assert_eq!(limits.check_wav(&wav(&tone(800, 8000.0), 1)), None);
+        assert_eq!(limits.check_wav(b"not a wav"), None);
+    }
+
+    #[test]
+    fn test_silent_stream_is_skipped() {
+        let limits = thresholds(DEFAULT_MIN_UPLOAD_DURATION_MS, DEFAULT_MIN_UPLOAD_RMS);
+        assert_eq!(
+            check_chunks(&limits, &stream(20, 0, 0.0)),
+            Some(Skip::TooQuiet)
+        );
+        // Room noise well under the speech level
+        assert_eq!(
+            check_chunks(&limits, &stream(0, 20, 30.0)),
+            Some(Skip::TooQuiet)
+        );
+        // One spoken chunk after a long pause is enough
+        assert_eq!(check_chunks(&limits, &stream(19, 1, 3000.0)), None);
+        assert_eq!(check_chunks(&limits, &stream(0, 10, 3000.0)), None);
+        // Too short goes first, as with nothing said at all
+        assert_eq!(
+            check_chunks(&limits, &stream(0, 2, 3000.0)),
+            Some(Skip::TooShort)
+        );
+
+        let disabled = thresholds(DEFAULT_MIN_UPLOAD_DURATION_MS, 0.0);
+        assert_eq!(check_chunks(&disabled, &stream(20, 0, 0.0)), None);
+    }
+
+    #[test]
+    fn test_pcm_rms_matches_samples() {
+        let samples = tone(100, 3000.0);
+        let pcm: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+        assert_eq!(pcm_rms(&pcm), rms(&samples));
+        assert_eq!(pcm_rms(&[]), 0.0);
+    }
+
+    #[test]
+    fn test_min_streaming_chunks() {
+        assert_eq!(thresholds(0, 0.0).min_streaming_chunks(), 1);
+        assert_eq!(thresholds(300, 0.0).min_streaming_chunks(), 3);
+        assert_eq!(thresholds(350, 0.0).min_streaming_chunks(), 4);
+    }
+}
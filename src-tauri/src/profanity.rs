@@ -0,0 +1,282 @@
+use std::{collections::HashSet, sync::OnceLock};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::settings::ProfanityFilter;
+
+/// Built-in English and Russian lists. Whole words only, so word forms are listed
+/// explicitly instead of matching roots (which would hit "assistant" or "употреблять").
+const BUILTIN_WORDS: &[&str] = &[
+    // English
+    "fuck",
+    "fucks",
+    "fucked",
+    "fucker",
+    "fuckers",
+    "fucking",
+    "motherfucker",
+    "motherfuckers",
+    "shit",
+    "shits",
+    "shitty",
+    "bullshit",
+    "ass",
+    "asshole",
+    "assholes",
+    "bitch",
+    "bitches",
+    "bastard",
+    "bastards",
+    "dick",
+    "dickhead",
+    "cunt",
+    "cunts",
+    "piss",
+    "pissed",
+    "prick",
+    "slut",
+    "whore",
+    "wanker",
+    "bollocks",
+    // Russian
+    "бля",
+    "блядь",
+    "блять",
+    "бляди",
+    "блядский",
+    "сука",
+    "суки",
+    "суку",
+    "сучка",
+    "хуй",
+    "хуя",
+    "хую",
+    "хуем",
+    "хуи",
+    "хуйня",
+    "хуйню",
+    "хуево",
+    "нахуй",
+    "нахуя",
+    "похуй",
+    "охуеть",
+    "охуенно",
+    "пизда",
+    "пизды",
+    "пизде",
+    "пизду",
+    "пиздец",
+    "пиздеть",
+    "ебать",
+    "ебал",
+    "ебала",
+    "ебаный",
+    "заебал",
+    "заебала",
+    "заебись",
+    "ебло",
+    "мудак",
+    "мудаки",
+    "мудило",
+    "гондон",
+    "залупа",
+    "долбоеб",
+    "шлюха",
+];
+
+fn builtin_words() -> &'static HashSet<String> {
+    static WORDS: OnceLock<HashSet<String>> = OnceLock::new();
+    WORDS.get_or_init(|| BUILTIN_WORDS.iter().map(|w| normalize_word(w)).collect())
+}
+
+/// Case-fold and treat "ё" as "е" so spelling variants match the same entry
+fn normalize_word(word: &str) -> String {
+    word.trim().to_lowercase().replace('ё', "е")
+}
+
+/// Keep the first and last letter and replace the rest with asterisks, preserving length
+fn mask_word(word: &str) -> String {
+    let chars: Vec<char> = word.chars().collect();
+    match chars.len() {
+        0 => String::new(),
+        1 | 2 => std::iter::once(chars[0])
+            .chain(std::iter::repeat('*').take(chars.len() - 1))
+            .collect(),
+        len => std::iter::once(chars[0])
+            .chain(std::iter::repeat('*').take(len - 2))
+            .chain(std::iter::once(chars[len - 1]))
+            .collect(),
+    }
+}
+
+/// Spaces or tabs, but not a line break
+fn is_space(segment: &str) -> bool {
+    segment
+        .chars()
+        .all(|c| c.is_whitespace() && c != '\n' && c != '\r')
+}
+
+/// Punctuation that attaches to the word before it
+fn is_closing_punctuation(segment: &str) -> bool {
+    segment
+        .chars()
+        .all(|c| matches!(c, ',' | '.' | '!' | '?' | ';' | ':' | '…' | ')'))
+}
+
+/// Drop the profane segments and close the gap each one leaves. Mid-sentence the
+/// space before the word goes, and the space after it if punctuation follows; at the
+/// start of a sentence the punctuation and space right after it go instead. Text
+/// away from removed words is left as it was.
+fn remove_words(segments: &[&str], is_profane: impl Fn(&str) -> bool) -> String {
+    let mut out: Vec<&str> = Vec::with_capacity(segments.len());
+    let mut i = 0;
+    while i < segments.len() {
+        let segment = segments[i];
+        i += 1;
+        if !segment.chars().any(char::is_alphanumeric) || !is_profane(segment) {
+            out.push(segment);
+            continue;
+        }
+
+        let sentence_start = out.iter().rev().find(|s| !is_space(s)).map_or(true, |s| {
+            s.contains('\n') || s.ends_with(['.', '!', '?', '…'])
+        });
+        if sentence_start {
+            while i < segments.len() && is_closing_punctuation(segments[i]) {
+                i += 1;
+            }
+            while i < segments.len() && is_space(segments[i]) {
+                i += 1;
+            }
+            if i == segments.len() || !segments[i].chars().any(|c| !c.is_whitespace()) {
+                // Nothing left on the line: no trailing space either
+                while out.last().is_some_and(|s| is_space(s)) {
+                    out.pop();
+                }
+            }
+        } else {
+            while out.last().is_some_and(|s| is_space(s)) {
+                out.pop();
+            }
+            let mut next = i;
+            while next < segments.len() && is_space(segments[next]) {
+                next += 1;
+            }
+            if next < segments.len() && is_closing_punctuation(segments[next]) {
+                i = next;
+            }
+        }
+    }
+    out.concat()
+}
+
+/// Mask or remove profane words in `text`. `extra_words` extends the built-in lists.
+pub fn filter_profanity(text: &str, mode: ProfanityFilter, extra_words: &[String]) -> String {
+    if mode == ProfanityFilter::Off {
+        return text.to_string();
+    }
+
+    let extra: HashSet<String> = extra_words
+        .iter()
+        .map(|w| normalize_word(w))
+        .filter(|w| !w.is_empty())
+        .collect();
+    let is_profane = |word: &str| {
+        let normalized = normalize_word(word);
+        builtin_words().contains(&normalized) || extra.contains(&normalized)
+    };
+
+    if mode == ProfanityFilter::Remove {
+        let segments: Vec<&str> = text.split_word_bounds().collect();
+        return remove_words(&segments, is_profane);
+    }
+
+    text.split_word_bounds()
+        .map(|segment| {
+            if !segment.chars().any(char::is_alphanumeric) || !is_profane(segment) {
+                return segment.to_string();
+            }
+            mask_word(segment)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mask(text: &str) -> String {
+        filter_profanity(text, ProfanityFilter::Mask, &[])
+    }
+
+    fn remove(text: &str) -> String {
+        filter_profanity(text, ProfanityFilter::Remove, &[])
+    }
+
+    #[test]
+    fn test_off_leaves_text_untouched() {
+        assert_eq!(
+            filter_profanity("what the fuck", ProfanityFilter::Off, &[]),
+            "what the fuck"
+        );
+    }
+
+    #[test]
+    fn test_mask_preserves_length_and_case() {
+        assert_eq!(mask("What the Fuck?"), "What the F**k?");
+        assert_eq!(mask("ну ты и мудак"), "ну ты и м***к");
+        assert_eq!(mask("ass"), "a*s");
+    }
+
+    #[test]
+    fn test_substrings_are_not_filtered() {
+        let text =
+            "The assistant in Scunthorpe passed the class; употреблять можно, оскорблять нельзя.";
+        assert_eq!(mask(text), text);
+        assert_eq!(remove(text), text);
+    }
+
+    #[test]
+    fn test_yo_spelling_matches() {
+        assert_eq!(mask("Хуёво"), "Х***о");
+    }
+
+    #[test]
+    fn test_remove_cleans_up_spaces_and_punctuation() {
+        assert_eq!(remove("this is fucking great"), "this is great");
+        assert_eq!(remove("ну блять , пошли"), "ну, пошли");
+        assert_eq!(remove("shit! it broke"), "it broke");
+        assert_eq!(
+            remove("It failed. Shit, shit, it broke"),
+            "It failed. it broke"
+        );
+        assert_eq!(remove("line one shit\nline two"), "line one\nline two");
+        assert_eq!(remove("that was shit."), "that was.");
+        assert_eq!(remove("Done. Shit"), "Done.");
+    }
+
+    #[test]
+    fn test_remove_leaves_the_rest_of_the_text_alone() {
+        assert_eq!(
+            remove("Note : two  spaces\n    indented shit here"),
+            "Note : two  spaces\n    indented here"
+        );
+        assert_eq!(remove("  shit happens"), "  happens");
+    }
+
+    #[test]
+    fn test_user_words_extend_builtin_list() {
+        let extra = vec![" Darn ".to_string()];
+        assert_eq!(
+            filter_profanity("darn it", ProfanityFilter::Mask, &extra),
+            "d**n it"
+        );
+    }
+
+    #[test]
+    fn test_mask_short_words() {
+        assert_eq!(mask_word("a"), "a");
+        assert_eq!(mask_word("ab"), "a*");
+        assert_eq!(mask_word("хуй"), "х*й");
+    }
+}
@@ -0,0 +1,410 @@
+use std::{
+    io::{BufWriter, Cursor},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc,
+    },
+    thread,
+};
+
+use anyhow::{anyhow, Context, Result};
+use hound::{SampleFormat as WavSampleFormat, WavSpec, WavWriter};
+
+/// Directory of the spill files, under the system temp directory
+const SPILL_DIR: &str = "easy-dictate-recordings";
+
+/// Spill files of one process are told apart by this
+static SPILL_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Samples of a legacy recording. They are kept in memory until there are
+/// `spill_after` of them; from then on each batch of that size goes to a writer
+/// thread that streams it into a temporary WAV file, so a long recording doesn't
+/// grow in RAM. The file and the thread are set up by `start_spill` before the
+/// capture starts, so the audio callback only hands batches over. Without
+/// `spill_after` nothing goes to disk and samples past `max_in_memory` are dropped.
+#[derive(Default)]
+pub struct RecordingBuffer {
+    samples: Vec<f32>,
+    sample_rate: u32,
+    channels: u16,
+    spill_after: Option<usize>,
+    max_in_memory: usize,
+    /// Directory of the spill file; None = one under the system temp directory
+    spill_dir: Option<PathBuf>,
+    spill: Option<Spill>,
+    /// A batch went to the spill file
+    spilled: bool,
+    /// Samples pushed, in memory or on disk
+    len: usize,
+}
+
+impl RecordingBuffer {
+    pub fn new(
+        sample_rate: u32,
+        channels: u16,
+        max_in_memory: usize,
+        spill_after: Option<usize>,
+    ) -> Self {
+        Self {
+            samples: Vec::with_capacity(spill_after.unwrap_or(max_in_memory).min(max_in_memory)),
+            sample_rate,
+            channels,
+            spill_after: spill_after.map(|samples| samples.max(1)),
+            max_in_memory,
+            ..Self::default()
+        }
+    }
+
+    /// Spill into `dir` instead of the system temp directory
+    #[cfg(test)]
+    fn with_spill_dir(mut self, dir: &Path) -> Self {
+        self.spill_dir = Some(dir.to_path_buf());
+        self
+    }
+
+    /// Open the spill file and start its writer; does nothing without `spill_after`.
+    /// If the file can't be created the recording stays in memory, up to the usual cap.
+    pub fn start_spill(mut self) -> Self {
+        if self.spill_after.is_none() || self.spill.is_some() {
+            return self;
+        }
+        let dir = self.spill_dir.clone().unwrap_or_else(spill_dir);
+        match Spill::start(&dir, self.spec()) {
+            Ok(spill) => {
+                tracing::debug!(
+                    "[Audio] A long recording will spill to {}",
+                    spill.path.display()
+                );
+                self.spill = Some(spill);
+            }
+            Err(e) => {
+                tracing::warn!("[Audio] Cannot spill the recording to disk: {:#}", e);
+                self.spill_after = None;
+            }
+        }
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Part of the recording went to disk
+    pub fn spilled(&self) -> bool {
+        self.spilled
+    }
+
+    fn spec(&self) -> WavSpec {
+        WavSpec {
+            channels: self.channels,
+            sample_rate: self.sample_rate,
+            bits_per_sample: 16,
+            sample_format: WavSampleFormat::Int,
+        }
+    }
+
+    pub fn push(&mut self, sample: f32) {
+        self.extend([sample]);
+    }
+
+    /// Append interleaved samples in [-1, 1]
+    pub fn extend(&mut self, samples: impl IntoIterator<Item = f32>) {
+        for sample in samples {
+            if self.spill.is_none() && self.samples.len() >= self.max_in_memory {
+                return;
+            }
+            self.samples.push(sample);
+            self.len += 1;
+            if self.spill.is_some()
+                && self
+                    .spill_after
+                    .is_some_and(|spill_after| self.samples.len() >= spill_after)
+            {
+                self.spill_batch();
+            }
+        }
+    }
+
+    /// Hand the samples in memory to the writer thread
+    fn spill_batch(&mut self) {
+        let Some(spill) = self.spill.as_ref() else {
+            return;
+        };
+        let capacity = self.samples.len();
+        spill.send(std::mem::replace(
+            &mut self.samples,
+            Vec::with_capacity(capacity),
+        ));
+        self.spilled = true;
+    }
+
+    /// WAV of the samples in memory, for interim transcripts; None once the recording
+    /// spilled to disk, where the file can't be read before it is finished
+    pub fn wav(&self) -> Option<Result<Vec<u8>>> {
+        if self.spilled() {
+            return None;
+        }
+        Some(encode_wav(&self.samples, self.sample_rate, self.channels))
+    }
+
+    /// WAV of the whole recording. A spilled recording is finished on disk and read
+    /// back; its temporary file is removed either way.
+    pub fn into_wav(mut self) -> Result<Vec<u8>> {
+        let Some(mut spill) = self.spill.take().filter(|_| self.spilled) else {
+            return encode_wav(&self.samples, self.sample_rate, self.channels);
+        };
+        spill.send(std::mem::take(&mut self.samples));
+        spill.finish()?;
+        std::fs::read(&spill.path)
+            .with_context(|| format!("Failed to read {}", spill.path.display()))
+    }
+}
+
+/// The temporary WAV file of a recording and the thread writing it. Dropping it
+/// stops the writer and removes the file.
+struct Spill {
+    path: PathBuf,
+    tx: Option<mpsc::Sender<Vec<f32>>>,
+    writer: Option<thread::JoinHandle<Result<()>>>,
+}
+
+impl Spill {
+    fn start(dir: &Path, spec: WavSpec) -> Result<Self> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        let path = dir.join(format!(
+            "recording-{}-{}.wav",
+            std::process::id(),
+            SPILL_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = std::fs::File::create(&path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        let (tx, rx) = mpsc::channel::<Vec<f32>>();
+        let writer = thread::spawn(move || -> Result<()> {
+            let mut writer = WavWriter::new(BufWriter::new(file), spec)
+                .context("Failed to create WAV writer")?;
+            for batch in rx {
+                for sample in batch {
+                    writer
+                        .write_sample(to_pcm16(sample))
+                        .context("Failed to write WAV sample")?;
+                }
+            }
+            writer.finalize().context("Failed to finalize WAV output")
+        });
+        Ok(Self {
+            path,
+            tx: Some(tx),
+            writer: Some(writer),
+        })
+    }
+
+    /// Queue `batch` for the writer; a writer that failed is reported by `finish`
+    fn send(&self, batch: Vec<f32>) {
+        if let Some(tx) = self.tx.as_ref() {
+            let _ = tx.send(batch);
+        }
+    }
+
+    /// Let the writer drain its queue and finalize the file
+    fn finish(&mut self) -> Result<()> {
+        self.tx.take();
+        let writer = self
+            .writer
+            .take()
+            .ok_or_else(|| anyhow!("Recording file already finished"))?;
+        writer
+            .join()
+            .map_err(|_| anyhow!("Recording file writer panicked"))?
+            .context("Failed to write the recording to disk")
+    }
+}
+
+impl Drop for Spill {
+    fn drop(&mut self) {
+        let _ = self.finish();
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            tracing::warn!("[Audio] Failed to remove {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+fn spill_dir() -> PathBuf {
+    std::env::temp_dir().join(SPILL_DIR)
+}
+
+/// Startup cleanup: spill files are removed when their recording ends, so any left
+/// over are from a crash
+pub fn remove_stale_spills() {
+    remove_spills_in(&spill_dir());
+}
+
+fn remove_spills_in(dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if path.extension().is_some_and(|ext| ext == "wav") {
+            match std::fs::remove_file(&path) {
+                Ok(()) => tracing::info!("[Audio] Removed stale recording {}", path.display()),
+                Err(e) => tracing::warn!("[Audio] Failed to remove {}: {}", path.display(), e),
+            }
+        }
+    }
+}
+
+fn to_pcm16(sample: f32) -> i16 {
+    (sample * i16::MAX as f32).clamp(i16::MIN as f32, i16::MAX as f32) as i16
+}
+
+/// 16-bit PCM WAV of interleaved samples in [-1, 1]
+fn encode_wav(samples: &[f32], sample_rate: u32, channels: u16) -> Result<Vec<u8>> {
+    let mut cursor = Cursor::new(Vec::with_capacity(samples.len() * 2));
+    let mut writer = WavWriter::new(
+        &mut cursor,
+        WavSpec {
+            channels,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: WavSampleFormat::Int,
+        },
+    )
+    .context("Failed to create WAV writer")?;
+
+    for &sample in samples {
+        writer
+            .write_sample(to_pcm16(sample))
+            .context("Failed to write WAV sample")?;
+    }
+
+    writer.finalize().context("Failed to finalize WAV output")?;
+    Ok(cursor.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 16_000;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "easy-dictate-spill-test-{name}-{}",
+            std::process::id()
+        ))
+    }
+
+    /// `count` samples of a ramp, so every position is told apart
+    fn ramp(count: usize) -> Vec<f32> {
+        (0..count).map(|n| (n % 200) as f32 / 200.0 - 0.5).collect()
+    }
+
+    fn samples_of(wav: &[u8]) -> (WavSpec, Vec<i16>) {
+        let mut reader = hound::WavReader::new(Cursor::new(wav)).unwrap();
+        let spec = reader.spec();
+        (spec, reader.samples::<i16>().map(Result::unwrap).collect())
+    }
+
+    fn files_in(dir: &Path) -> usize {
+        std::fs::read_dir(dir).map_or(0, |entries| entries.count())
+    }
+
+    #[test]
+    fn test_spilled_recording_matches_in_memory() {
+        let dir = scratch_dir("match");
+        let audio = ramp(10_000);
+
+        let mut memory = RecordingBuffer::new(RATE, 2, 1_000_000, None);
+        memory.extend(audio.iter().copied());
+        assert!(!memory.spilled());
+
+        let mut spilled = RecordingBuffer::new(RATE, 2, 1_000_000, Some(256))
+            .with_spill_dir(&dir)
+            .start_spill();
+        for chunk in audio.chunks(300) {
+            spilled.extend(chunk.iter().copied());
+        }
+        assert!(spilled.spilled());
+        assert!(spilled.samples.len() < 256);
+        assert_eq!(files_in(&dir), 1);
+        // Interim transcripts stop once the audio is on disk
+        assert!(spilled.wav().is_none());
+
+        let expected = samples_of(&memory.into_wav().unwrap());
+        let (spec, samples) = samples_of(&spilled.into_wav().unwrap());
+        assert_eq!(spec, expected.0);
+        assert_eq!(samples, expected.1);
+        assert_eq!(files_in(&dir), 0);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_abandoned_recording_removes_its_file() {
+        let dir = scratch_dir("drop");
+        let mut buffer = RecordingBuffer::new(RATE, 1, 1_000_000, Some(100))
+            .with_spill_dir(&dir)
+            .start_spill();
+        buffer.extend(ramp(1_000));
+        assert_eq!(files_in(&dir), 1);
+        drop(buffer);
+        assert_eq!(files_in(&dir), 0);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_unwritable_spill_stays_in_memory() {
+        let dir = scratch_dir("unwritable");
+        std::fs::create_dir_all(dir.parent().unwrap()).unwrap();
+        // A file where the directory should be
+        std::fs::write(&dir, b"").unwrap();
+        let mut buffer = RecordingBuffer::new(RATE, 1, 500, Some(100))
+            .with_spill_dir(&dir)
+            .start_spill();
+        buffer.extend(ramp(1_000));
+        assert!(!buffer.spilled());
+        let (_, samples) = samples_of(&buffer.into_wav().unwrap());
+        assert_eq!(samples.len(), 500);
+        std::fs::remove_file(dir).unwrap();
+    }
+
+    #[test]
+    fn test_spill_file_is_ready_before_capture() {
+        let dir = scratch_dir("ready");
+        let mut buffer = RecordingBuffer::new(RATE, 1, 1_000_000, Some(500))
+            .with_spill_dir(&dir)
+            .start_spill();
+        assert_eq!(files_in(&dir), 1);
+        // A short recording never touches the file and stays readable for interims
+        buffer.extend(ramp(300));
+        assert!(!buffer.spilled());
+        assert!(buffer.wav().is_some());
+        let (_, samples) = samples_of(&buffer.into_wav().unwrap());
+        assert_eq!(samples.len(), 300);
+        assert_eq!(files_in(&dir), 0);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_in_memory_recording_is_capped() {
+        let mut buffer = RecordingBuffer::new(RATE, 1, 300, None);
+        assert!(buffer.is_empty());
+        buffer.extend(ramp(1_000));
+        buffer.push(0.0);
+        let (_, samples) = samples_of(&buffer.wav().unwrap().unwrap());
+        assert_eq!(samples.len(), 300);
+    }
+
+    #[test]
+    fn test_stale_spills_are_removed() {
+        let dir = scratch_dir("stale");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("recording-1-0.wav"), b"RIFF").unwrap();
+        std::fs::write(dir.join("notes.txt"), b"keep").unwrap();
+        remove_spills_in(&dir);
+        assert!(!dir.join("recording-1-0.wav").exists());
+        assert!(dir.join("notes.txt").exists());
+        remove_spills_in(&dir.join("missing"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,152 @@
+use std::{
+    borrow::Cow,
+    sync::{OnceLock, RwLock},
+};
+
+/// What a secret is replaced with
+pub const MASK: &str = "[REDACTED]";
+
+/// Prefixes of the key formats we know: OpenAI (`sk-`, `sk-proj-`), ElevenLabs (`sk_`)
+/// and Groq (`gsk_`)
+const KEY_PREFIXES: &[&str] = &["sk-", "sk_", "gsk_"];
+
+/// Characters after a prefix before it counts as a key; shorter runs are ordinary words
+/// like "sk-learn"
+const MIN_PREFIXED_KEY_LEN: usize = 16;
+
+/// Configured keys shorter than this are not matched, so a placeholder like "test"
+/// does not mask every occurrence of the word
+const MIN_CONFIGURED_KEY_LEN: usize = 8;
+
+fn configured_keys() -> &'static RwLock<Vec<String>> {
+    static KEYS: OnceLock<RwLock<Vec<String>>> = OnceLock::new();
+    KEYS.get_or_init(|| RwLock::new(Vec::new()))
+}
+
+/// Replace the keys `redact` masks by exact match, e.g. after settings changed
+pub fn set_configured_keys<'a>(keys: impl IntoIterator<Item = &'a str>) {
+    let keys = keys
+        .into_iter()
+        .map(str::trim)
+        .filter(|key| key.len() >= MIN_CONFIGURED_KEY_LEN)
+        .map(str::to_string)
+        .collect();
+    if let Ok(mut guard) = configured_keys().write() {
+        *guard = keys;
+    }
+}
+
+/// Mask the configured API keys and anything that looks like a key in text bound for
+/// the log
+pub fn redact(text: &str) -> Cow<'_, str> {
+    match configured_keys().read() {
+        Ok(keys) => redact_with(text, &keys),
+        Err(_) => redact_with(text, &[]),
+    }
+}
+
+fn redact_with<'a>(text: &'a str, keys: &[String]) -> Cow<'a, str> {
+    let mut redacted = Cow::Borrowed(text);
+    for key in keys {
+        if redacted.contains(key.as_str()) {
+            redacted = Cow::Owned(redacted.replace(key.as_str(), MASK));
+        }
+    }
+    match mask_prefixed(&redacted) {
+        Some(masked) => Cow::Owned(masked),
+        None => redacted,
+    }
+}
+
+fn is_key_char(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_'
+}
+
+/// Text with every prefixed key-like token masked; None if there was none
+fn mask_prefixed(text: &str) -> Option<String> {
+    let bytes = text.as_bytes();
+    let mut masked = String::new();
+    let mut copied = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let at_token_start = i == 0 || !is_key_char(bytes[i - 1]);
+        let prefix = KEY_PREFIXES
+            .iter()
+            .find(|prefix| at_token_start && bytes[i..].starts_with(prefix.as_bytes()));
+        if let Some(prefix) = prefix {
+            let body = bytes[i + prefix.len()..]
+                .iter()
+                .take_while(|&&byte| is_key_char(byte))
+                .count();
+            if body >= MIN_PREFIXED_KEY_LEN {
+                // Prefixes and key characters are ASCII, so both ends are char boundaries
+                masked.push_str(&text[copied..i]);
+                masked.push_str(MASK);
+                i += prefix.len() + body;
+                copied = i;
+                continue;
+            }
+        }
+        i += 1;
+    }
+    if copied == 0 {
+        return None;
+    }
+    masked.push_str(&text[copied..]);
+    Some(masked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(keys: &[&str]) -> Vec<String> {
+        keys.iter().map(|key| key.to_string()).collect()
+    }
+
+    #[test]
+    fn test_masks_each_configured_key() {
+        let configured = keys(&[
+            "openai-key-0123456789",
+            "groq-key-abcdefgh",
+            "el-key-zyxwvuts",
+        ]);
+        for key in &configured {
+            let line = format!("Authorization: Bearer {key} was rejected ({key})");
+            let logged = redact_with(&line, &configured);
+            assert!(!logged.contains(key.as_str()), "{logged}");
+            assert_eq!(
+                logged,
+                "Authorization: Bearer [REDACTED] was rejected ([REDACTED])"
+            );
+        }
+    }
+
+    #[test]
+    fn test_masks_keys_by_prefix() {
+        let logged = redact_with(
+            r#"{"error": "Incorrect API key provided: sk-proj-AbCdEf0123456789xyz", "groq": "gsk_0123456789abcdefABCD", "xi": "sk_0123456789abcdef0123"}"#,
+            &[],
+        );
+        assert_eq!(
+            logged,
+            r#"{"error": "Incorrect API key provided: [REDACTED]", "groq": "[REDACTED]", "xi": "[REDACTED]"}"#
+        );
+    }
+
+    #[test]
+    fn test_leaves_ordinary_text_alone() {
+        let text = "Installed sk-learn, task-sk-0123456789abcdefgh and ключ sk-ok";
+        assert!(matches!(redact_with(text, &[]), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_ignores_short_configured_keys() {
+        set_configured_keys(["", "test", "  long-enough-secret  "]);
+        assert_eq!(
+            redact("test with long-enough-secret"),
+            "test with [REDACTED]"
+        );
+        set_configured_keys([]);
+    }
+}
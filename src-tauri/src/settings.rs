@@ -1,10 +1,22 @@
-use std::{collections::HashSet, path::PathBuf};
-
-use anyhow::{Context, Result};
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tokio::fs as async_fs;
 
+use crate::{
+    agc::{AgcConfig, DEFAULT_MAX_GAIN_DB, DEFAULT_TARGET_DB},
+    messages::Language,
+    preflight::{UploadThresholds, DEFAULT_MIN_UPLOAD_DURATION_MS, DEFAULT_MIN_UPLOAD_RMS},
+    settings_vault::{ApiKeys, LockState, SealedKeys, SettingsLocked, Vault, VaultError, VaultKey},
+};
+
 // ---------------------------------------------------------------------------
 // OS Keychain helpers (keyring crate)
 // ---------------------------------------------------------------------------
@@ -32,11 +44,49 @@ fn keyring_set(field: &str, value: &str) -> bool {
 
 const DEFAULT_HOTKEY: &str = "Ctrl+Shift+Space";
 const CONFIG_FILE: &str = "settings.json";
+/// Top-level field of the settings file holding the encrypted API keys
+const SEALED_KEYS_FIELD: &str = "sealed_keys";
 const DEFAULT_MODEL: &str = "gpt-4o-transcribe";
-const DEFAULT_LLM_MODEL: &str = "gpt-4o-mini";
+const DEFAULT_OPENAI_LLM_MODEL: &str = "gpt-4o-mini";
+const DEFAULT_GROQ_LLM_MODEL: &str = "openai/gpt-oss-20b";
+const DEFAULT_LLM_TEMPERATURE: f32 = 0.3;
+const MAX_LLM_TEMPERATURE: f32 = 2.0;
 const DEFAULT_TARGET_LANGUAGE: &str = "English";
-
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+/// ElevenLabs detects the spoken language itself
+const DEFAULT_TRANSCRIPTION_LANGUAGE: &str = "auto";
+const DEFAULT_OLLAMA_BASE_URL: &str = "http://localhost:11434";
+const DEFAULT_OLLAMA_MODEL: &str = "llama3";
+const DEFAULT_PRESET_NAME: &str = "Default";
+const DEFAULT_HISTORY_MAX_ENTRIES: usize = 100;
+const DEFAULT_HISTORY_DEDUPE_WINDOW_SECS: u64 = 30;
+const DEFAULT_OVERLAY_HIDE_AFTER_MS: u64 = 2500;
+const DEFAULT_TRANSCRIBING_TIMEOUT_SECS: u64 = 120;
+const DEFAULT_ELEVENLABS_CONNECT_TIMEOUT_SECS: u64 = 8;
+/// Enough for the ElevenLabs endpointer to finish the last word
+const DEFAULT_COMMIT_SILENCE_MS: u64 = 200;
+const MAX_COMMIT_SILENCE_MS: u64 = 2000;
+const DEFAULT_STREAMING_IDLE_DISCONNECT_MINUTES: u64 = 5;
+const DEFAULT_DOUBLE_TAP_MS: u64 = 400;
+const MAX_DOUBLE_TAP_MS: u64 = 1000;
+const MIN_SPEAK_RATE: f32 = 0.5;
+const MAX_SPEAK_RATE: f32 = 2.0;
+const DEFAULT_PSEUDO_STREAMING_INTERVAL_SECS: u64 = 3;
+/// As long as a recording could get before it spilled, about 46 MB of 48 kHz stereo
+const DEFAULT_RECORDING_SPILL_AFTER_SECS: u64 = 120;
+const DEFAULT_UPDATE_IDLE_GRACE_SECS: u64 = 60;
+/// Local Ollama refinement of a long dictation can take several times longer
+const OLLAMA_TIMEOUT_FACTOR: u32 = 3;
+const DEFAULT_LLM_CHUNK_MAX_CHARS: usize = 6000;
+const DEFAULT_SESSION_INSTRUCTIONS: &str = "Combine these dictated notes into one clean, \
+well-structured document. Fix recognition errors, drop filler words and repetitions, and keep \
+every fact, decision and action item. Reply with the document only.";
+/// An API key field holding `env:NAME` takes the key from that environment variable
+pub const ENV_KEY_PREFIX: &str = "env:";
+const DEFAULT_TARGET_LANGUAGES: &[&str] = &[
+    "English", "Russian", "German", "French", "Spanish", "Chinese", "Japanese",
+];
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum TranscriptionProvider {
     #[default]
@@ -49,15 +99,95 @@ pub enum TranscriptionProvider {
     Mock,
 }
 
+/// Realtime service behind gated streaming, used while ElevenLabs is the transcription
+/// provider; dictations recorded without streaming still go to ElevenLabs
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamingProvider {
+    #[default]
+    ElevenLabs,
+    Deepgram,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum LLMProvider {
     #[default]
     OpenAI,
     Groq,
+    /// Local Ollama server, no API key required
+    Ollama,
+}
+
+/// Which releases the updater offers
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+    #[default]
+    Stable,
+    /// Pre-release builds (semver tags with a pre-release part, e.g. 0.3.0-beta.1)
+    Beta,
+}
+
+/// What to deliver when LLM refinement fails after its retry
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LlmFailurePolicy {
+    /// Abort the dictation with an error
+    Fail,
+    /// Deliver the raw transcript and show a warning
+    #[default]
+    FallbackToRaw,
+}
+
+/// History entries for a dictation delivered in segments (`commit_segment`)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SegmentHistory {
+    /// Each segment is an entry of its own
+    #[default]
+    PerSegment,
+    /// The segments are appended to one entry
+    Combined,
+}
+
+/// Where a transcript goes; stored as `simulate_typing` and `copy_to_clipboard`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputMode {
+    Type,
+    Clipboard,
+    TypeAndCopy,
+}
+
+impl OutputMode {
+    fn apply(self, settings: &mut AppSettings) {
+        settings.simulate_typing = matches!(self, OutputMode::Type | OutputMode::TypeAndCopy);
+        settings.copy_to_clipboard =
+            matches!(self, OutputMode::Clipboard | OutputMode::TypeAndCopy);
+    }
+}
+
+/// How profanity in the delivered text is handled (history keeps the original)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ProfanityFilter {
+    #[default]
+    Off,
+    /// Replace interior letters with asterisks
+    Mask,
+    /// Drop the word entirely
+    Remove,
 }
 
 impl TranscriptionProvider {
+    pub const ALL: [TranscriptionProvider; 4] = [
+        TranscriptionProvider::OpenAI,
+        TranscriptionProvider::Groq,
+        TranscriptionProvider::ElevenLabs,
+        TranscriptionProvider::Mock,
+    ];
+
     pub fn display_name(&self) -> &'static str {
         match self {
             TranscriptionProvider::OpenAI => "OpenAI",
@@ -70,6 +200,47 @@ impl TranscriptionProvider {
     pub fn is_mock(&self) -> bool {
         matches!(self, TranscriptionProvider::Mock)
     }
+
+    /// Whether a transcription can't start without this provider's API key
+    pub fn needs_api_key(&self) -> bool {
+        match self {
+            TranscriptionProvider::OpenAI
+            | TranscriptionProvider::Groq
+            | TranscriptionProvider::ElevenLabs => true,
+            TranscriptionProvider::Mock => false,
+        }
+    }
+
+    /// Stable identifier, same as the serialized form
+    pub fn id(&self) -> &'static str {
+        match self {
+            TranscriptionProvider::OpenAI => "openai",
+            TranscriptionProvider::Groq => "groq",
+            TranscriptionProvider::ElevenLabs => "elevenlabs",
+            TranscriptionProvider::Mock => "mock",
+        }
+    }
+
+    /// Transcription model used after switching to this provider
+    pub fn default_model(&self) -> &'static str {
+        match self {
+            TranscriptionProvider::Groq => "groq/whisper-large-v3-turbo",
+            TranscriptionProvider::ElevenLabs => "scribe_v2_realtime",
+            TranscriptionProvider::OpenAI | TranscriptionProvider::Mock => DEFAULT_MODEL,
+        }
+    }
+
+    /// Whether `model` is one of this provider's transcription models
+    fn accepts_model(&self, model: &str) -> bool {
+        match self {
+            TranscriptionProvider::Groq => model.starts_with("groq/"),
+            TranscriptionProvider::ElevenLabs => model.starts_with("scribe"),
+            TranscriptionProvider::OpenAI => {
+                !model.starts_with("groq/") && !model.starts_with("scribe")
+            }
+            TranscriptionProvider::Mock => true,
+        }
+    }
 }
 
 impl LLMProvider {
@@ -77,9 +248,110 @@ impl LLMProvider {
         match self {
             LLMProvider::OpenAI => "OpenAI",
             LLMProvider::Groq => "Groq",
+            LLMProvider::Ollama => "Ollama",
         }
     }
+
+    /// Stable identifier, same as the serialized form
+    pub fn id(&self) -> &'static str {
+        match self {
+            LLMProvider::OpenAI => "openai",
+            LLMProvider::Groq => "groq",
+            LLMProvider::Ollama => "ollama",
+        }
+    }
+
+    /// Chat model used when the user hasn't configured one
+    pub fn default_model(&self) -> &'static str {
+        match self {
+            LLMProvider::OpenAI => DEFAULT_OPENAI_LLM_MODEL,
+            LLMProvider::Groq => DEFAULT_GROQ_LLM_MODEL,
+            LLMProvider::Ollama => DEFAULT_OLLAMA_MODEL,
+        }
+    }
+}
+/// Named set of custom instructions (e.g. "Email", "Slack", "Code comments")
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct PromptPreset {
+    pub name: String,
+    pub instructions: String,
+    /// Replaces `custom_vocabulary` while this preset is active; empty = the global list
+    pub vocabulary: Vec<String>,
+}
+
+/// Settings an application rule changes for the dictations started in it; None keeps
+/// the configured value
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct AppOverrides {
+    pub preset: Option<String>,
+    pub auto_translate: Option<bool>,
+    pub target_language: Option<String>,
+    pub output_mode: Option<OutputMode>,
 }
+
+impl AppOverrides {
+    /// Apply to the settings of one session; nothing is persisted
+    pub fn apply(&self, settings: &mut AppSettings) {
+        if let Some(preset) = &self.preset {
+            if !settings.select_preset(preset) {
+                tracing::warn!("[AppRules] Preset \"{}\" no longer exists", preset);
+            }
+        }
+        if let Some(auto_translate) = self.auto_translate {
+            settings.auto_translate = auto_translate;
+        }
+        if let Some(language) = &self.target_language {
+            settings.target_language = language.clone();
+        }
+        if let Some(mode) = self.output_mode {
+            mode.apply(settings);
+        }
+    }
+
+    fn normalized(self) -> Self {
+        let non_empty = |value: Option<String>| {
+            value
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+        Self {
+            preset: non_empty(self.preset),
+            target_language: non_empty(self.target_language),
+            ..self
+        }
+    }
+}
+
+/// Overrides for dictations started while `process` is in the foreground (Windows).
+/// `process` is an executable name ("code.exe", "code") or a full path.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct AppRule {
+    pub process: String,
+    #[serde(flatten)]
+    pub overrides: AppOverrides,
+}
+
+/// Top-left corner of the overlay in physical pixels
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct OverlayPosition {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// Trimmed terms with inner whitespace collapsed, without empties and without
+/// case-insensitive duplicates (the first spelling is kept)
+pub fn normalize_vocabulary(terms: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    terms
+        .into_iter()
+        .map(|term| term.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|term| !term.is_empty() && seen.insert(term.to_lowercase()))
+        .collect()
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct AppSettings {
@@ -88,24 +360,164 @@ pub struct AppSettings {
     pub api_key: String,
     pub groq_api_key: String,
     pub elevenlabs_api_key: String,
+    pub deepgram_api_key: String,
+    /// Service the streaming connection goes to
+    pub streaming_provider: StreamingProvider,
+    /// Keep the API keys in the settings file encrypted with a passphrase instead of
+    /// the OS keychain; set only by `set_settings_passphrase`
+    pub encrypt_settings: bool,
+    /// Runtime only: the keys are encrypted and `unlock_settings` hasn't run yet
+    #[serde(skip)]
+    pub keys_locked: bool,
     pub model: String,
+    /// Chat model for refinement; empty = provider default
     pub llm_model: String,
+    pub llm_temperature: f32,
+    /// Transcripts longer than this (in chars) are refined in chunks (0 = never split)
+    pub llm_chunk_max_chars: usize,
+    pub llm_failure_policy: LlmFailurePolicy,
+    /// Number of recent dictations passed to the LLM as context (0 = off)
+    pub refinement_context_entries: usize,
+    pub ollama_base_url: String,
+    pub ollama_model: String,
+    /// OpenAI-compatible endpoint for OpenAI requests; empty = `OPENAI_BASE_URL` or
+    /// the public API
+    pub openai_base_url: String,
+    /// Endpoint for Groq requests; empty = the public API
+    pub groq_base_url: String,
     pub hotkey: String,
     pub translate_hotkey: String,
     pub toggle_translate_hotkey: String,
+    /// Optional hotkey that switches to the next prompt preset
+    pub cycle_preset_hotkey: String,
+    /// Optional hotkey that switches to the next of `target_languages`
+    pub cycle_target_language_hotkey: String,
+    /// Optional hotkey that delivers the text so far and keeps dictating (ElevenLabs streaming)
+    pub commit_segment_hotkey: String,
     pub simulate_typing: bool,
     pub copy_to_clipboard: bool,
     pub auto_start: bool,
     pub start_minimized: bool,
     pub auto_update: bool,
+    pub update_channel: UpdateChannel,
+    /// An automatic update is installed once nothing was recorded, transcribed or
+    /// typed for this many seconds
+    pub update_idle_grace_secs: u64,
     pub use_streaming: bool,
+    /// Show interim transcripts of a recording in progress with providers that don't
+    /// stream (each one is an extra request)
+    pub pseudo_streaming: bool,
+    /// Seconds between interim transcripts
+    pub pseudo_streaming_interval_secs: u64,
+    /// A recording (without streaming) longer than this goes on in a temporary file
+    /// instead of memory (0 = keep it in memory, up to two minutes)
+    pub recording_spill_after_secs: u64,
+    /// How long the overlay keeps showing the result after a transcription completes
+    pub overlay_hide_after_ms: u64,
+    /// What the overlay shows: live partial text, the delivered text and the
+    /// recording timer. The main window gets all of them regardless.
+    pub overlay_show_partials: bool,
+    pub overlay_show_final: bool,
+    pub overlay_show_levels: bool,
+    /// Where the user dragged the overlay; None places it at the bottom centre of
+    /// the screen with the cursor
+    pub overlay_position: Option<OverlayPosition>,
+    /// A "Transcribing"" status older than this is considered stuck and reset (0 = never)
+    pub transcribing_timeout_secs: u64,
+    /// An ElevenLabs WebSocket handshake taking longer than this is abandoned
+    pub elevenlabs_connect_timeout_secs: u64,
+    /// Silence appended to streamed audio before each commit (0 = none)
+    pub commit_silence_ms: u64,
+    /// A streaming connection whose gate stayed closed this long is dropped until
+    /// the next press (0 = never)
+    pub streaming_idle_disconnect_minutes: u64,
+    /// Start connecting ElevenLabs as soon as the modifiers of a dictation hotkey are
+    /// held, before its last key is pressed (Windows only)
+    pub preconnect_on_modifiers: bool,
+    /// Two taps of the main hotkey within this many milliseconds deliver the last
+    /// transcript again (0 = off)
+    pub double_tap_ms: u64,
+    /// Even out the microphone level of streamed audio before it is sent
+    pub agc_enabled: bool,
+    /// RMS level the gain control aims for, in dBFS
+    pub agc_target_db: f32,
+    /// Most the gain control amplifies a quiet speaker, in dB
+    pub agc_max_gain_db: f32,
+    /// Shorter recordings aren't sent to the provider (0 = send everything)
+    pub min_upload_duration_ms: u64,
+    /// Recordings with a lower overall RMS, as a fraction of full scale, aren't sent
+    /// to the provider (0 = send everything)
+    pub min_upload_rms: f32,
+    /// Desktop notification with a transcript preview when a dictation completes
+    pub notify_on_complete: bool,
+    /// Desktop notification with the error message when something fails
+    pub notify_on_error: bool,
+    /// Read each delivered transcript aloud with the system text-to-speech
+    pub speak_result: bool,
+    /// Speech rate for `speak_result` relative to the voice's normal rate
+    pub speak_rate: f32,
+    /// Write committed transcripts to `transcripts.log` in the log directory.
+    /// Off for new installs; settings files from before the option existed keep logging.
+    #[serde(default = "legacy_log_transcripts")]
+    pub log_transcripts: bool,
+    /// Shell command run after each delivered transcript, with the text on stdin.
+    /// It runs with the user's privileges, so it only runs once confirmed.
+    pub post_command: String,
+    pub post_command_enabled: bool,
+    /// The `post_command` the user confirmed last; set only by `confirm_post_command`
+    pub post_command_confirmed: String,
     pub auto_translate: bool,
+    /// The active one of `target_languages`
     pub target_language: String,
+    /// ElevenLabs streaming language code for translated dictations ("auto" = detect)
+    pub transcription_language: String,
+    /// Target languages to cycle through, also offered in the tray submenu
+    #[serde(alias = "tray_languages")]
+    pub target_languages: Vec<String>,
     pub use_custom_instructions: bool,
+    /// Mirror of the active preset's instructions. Kept for the single-prompt editor
+    /// and for migrating settings files written before presets existed.
     pub custom_instructions: String,
+    pub prompt_presets: Vec<PromptPreset>,
+    pub active_preset: String,
     pub use_vocabulary: bool,
     pub custom_vocabulary: Vec<String>,
+    pub profanity_filter: ProfanityFilter,
+    /// Extra words filtered in addition to the built-in English and Russian lists
+    pub profanity_words: Vec<String>,
+    /// Language of the interface and of the backend's messages ("en" or "ru");
+    /// the system's by default
     pub ui_language: String,
+    /// Master switch for recording transcription history
+    pub history_enabled: bool,
+    /// Maximum number of history entries to keep (0 = unlimited)
+    pub history_max_entries: usize,
+    /// Drop history entries older than this many days (0 = keep forever)
+    pub history_retention_days: u32,
+    /// Identical consecutive transcripts within this window are not recorded twice (0 = off)
+    pub history_dedupe_window_secs: u64,
+    pub segment_history: SegmentHistory,
+    /// Per-application overrides, matched against the foreground process on hotkey press
+    pub app_rules: Vec<AppRule>,
+    /// Utterances of a dictation session are only collected, not typed or copied
+    pub session_suppress_delivery: bool,
+    /// Instructions for the LLM pass over a finished session (empty = no pass)
+    pub session_instructions: String,
+    /// A finished session is also appended to this file (empty = none)
+    pub session_output_file: String,
+    /// Serve the local HTTP API on 127.0.0.1 for window managers, Stream Decks etc.
+    pub local_api_enabled: bool,
+    /// Port of the local API (0 = any free port)
+    pub local_api_port: u16,
+    /// Bearer token every local API request must carry; generated on first enable
+    /// and only revealed by `set_local_api_enabled` and `reset_local_api_token`.
+    /// Encrypted with the API keys, so the local API is off while they are locked.
+    pub local_api_token: String,
+}
+
+/// Versions without the `log_transcripts` option always logged transcripts
+fn legacy_log_transcripts() -> bool {
+    true
 }
 
 impl Default for AppSettings {
@@ -116,24 +528,89 @@ impl Default for AppSettings {
             api_key: String::new(),
             groq_api_key: String::new(),
             elevenlabs_api_key: String::new(),
+            deepgram_api_key: String::new(),
+            streaming_provider: StreamingProvider::ElevenLabs,
+            encrypt_settings: false,
+            keys_locked: false,
             model: DEFAULT_MODEL.to_string(),
-            llm_model: DEFAULT_LLM_MODEL.to_string(),
+            llm_model: String::new(),
+            llm_temperature: DEFAULT_LLM_TEMPERATURE,
+            llm_chunk_max_chars: DEFAULT_LLM_CHUNK_MAX_CHARS,
+            llm_failure_policy: LlmFailurePolicy::FallbackToRaw,
+            refinement_context_entries: 0,
+            ollama_base_url: DEFAULT_OLLAMA_BASE_URL.to_string(),
+            ollama_model: DEFAULT_OLLAMA_MODEL.to_string(),
+            openai_base_url: String::new(),
+            groq_base_url: String::new(),
             hotkey: DEFAULT_HOTKEY.to_string(),
             translate_hotkey: String::new(),
             toggle_translate_hotkey: String::new(),
+            cycle_preset_hotkey: String::new(),
+            cycle_target_language_hotkey: String::new(),
+            commit_segment_hotkey: String::new(),
             simulate_typing: true,
             copy_to_clipboard: true,
             auto_start: false,
             start_minimized: false,
             auto_update: true,
+            update_channel: UpdateChannel::Stable,
+            update_idle_grace_secs: DEFAULT_UPDATE_IDLE_GRACE_SECS,
             use_streaming: true,
+            pseudo_streaming: false,
+            pseudo_streaming_interval_secs: DEFAULT_PSEUDO_STREAMING_INTERVAL_SECS,
+            recording_spill_after_secs: DEFAULT_RECORDING_SPILL_AFTER_SECS,
+            overlay_hide_after_ms: DEFAULT_OVERLAY_HIDE_AFTER_MS,
+            overlay_show_partials: true,
+            overlay_show_final: true,
+            overlay_show_levels: true,
+            overlay_position: None,
+            transcribing_timeout_secs: DEFAULT_TRANSCRIBING_TIMEOUT_SECS,
+            elevenlabs_connect_timeout_secs: DEFAULT_ELEVENLABS_CONNECT_TIMEOUT_SECS,
+            commit_silence_ms: DEFAULT_COMMIT_SILENCE_MS,
+            streaming_idle_disconnect_minutes: DEFAULT_STREAMING_IDLE_DISCONNECT_MINUTES,
+            preconnect_on_modifiers: false,
+            double_tap_ms: DEFAULT_DOUBLE_TAP_MS,
+            agc_enabled: false,
+            agc_target_db: DEFAULT_TARGET_DB,
+            agc_max_gain_db: DEFAULT_MAX_GAIN_DB,
+            min_upload_duration_ms: DEFAULT_MIN_UPLOAD_DURATION_MS,
+            min_upload_rms: DEFAULT_MIN_UPLOAD_RMS,
+            notify_on_complete: false,
+            notify_on_error: true,
+            speak_result: false,
+            speak_rate: 1.0,
+            log_transcripts: false,
+            post_command: String::new(),
+            post_command_enabled: false,
+            post_command_confirmed: String::new(),
             auto_translate: false,
             target_language: DEFAULT_TARGET_LANGUAGE.to_string(),
+            transcription_language: DEFAULT_TRANSCRIPTION_LANGUAGE.to_string(),
+            target_languages: DEFAULT_TARGET_LANGUAGES
+                .iter()
+                .map(|l| l.to_string())
+                .collect(),
             use_custom_instructions: false,
             custom_instructions: String::new(),
+            prompt_presets: Vec::new(),
+            active_preset: String::new(),
             use_vocabulary: false,
             custom_vocabulary: Vec::new(),
-            ui_language: "ru".to_string(),
+            profanity_filter: ProfanityFilter::Off,
+            profanity_words: Vec::new(),
+            ui_language: Language::system().code().to_string(),
+            history_enabled: true,
+            history_max_entries: DEFAULT_HISTORY_MAX_ENTRIES,
+            history_retention_days: 0,
+            history_dedupe_window_secs: DEFAULT_HISTORY_DEDUPE_WINDOW_SECS,
+            segment_history: SegmentHistory::PerSegment,
+            app_rules: Vec::new(),
+            session_suppress_delivery: true,
+            session_instructions: DEFAULT_SESSION_INSTRUCTIONS.to_string(),
+            session_output_file: String::new(),
+            local_api_enabled: false,
+            local_api_port: 0,
+            local_api_token: String::new(),
         }
     }
 }
@@ -145,8 +622,36 @@ pub enum SettingsValidationError {
     #[error("Global hotkey '{0}' is not valid.")]
     InvalidHotkey(String),
     #[error("{0} API key is required.")]
-    #[allow(dead_code)]
     MissingApiKey(&'static str),
+    #[error(
+        "{0} API key is required for translation, custom instructions, or vocabulary correction"
+    )]
+    MissingRefinementKey(&'static str),
+    #[error("LLM temperature must be between 0.0 and 2.0 (got {0}).")]
+    InvalidTemperature(f32),
+    #[error("{0} base URL is required.")]
+    #[allow(dead_code)]
+    MissingBaseUrl(&'static str),
+    #[error("Target language '{0}' is not in the list of target languages.")]
+    UnlistedTargetLanguage(String),
+    #[error("environment variable {0} is not set")]
+    UnsetKeyVariable(String),
+}
+
+/// The key an API key field stands for, trimmed: the field itself, or the value of the
+/// variable an `env:NAME` reference names. Resolved on every use rather than on load,
+/// so the reference (not the key) is what the settings file and the UI see.
+pub fn resolve_api_key(field: &str) -> Result<String, SettingsValidationError> {
+    let field = field.trim();
+    let Some(name) = field.strip_prefix(ENV_KEY_PREFIX) else {
+        return Ok(field.to_string());
+    };
+    let name = name.trim();
+    std::env::var(name)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .ok_or_else(|| SettingsValidationError::UnsetKeyVariable(name.to_string()))
 }
 
 impl AppSettings {
@@ -167,32 +672,260 @@ impl AppSettings {
         } else {
             self.model.trim().to_string()
         };
-        self.llm_model = if self.llm_model.trim().is_empty() {
-            DEFAULT_LLM_MODEL.to_string()
+        self.llm_model = self.llm_model.trim().to_string();
+        // Older versions stored the chat model in the shared `model` field
+        if self.llm_model.is_empty() && looks_like_chat_model(&self.model) {
+            self.llm_model = std::mem::replace(&mut self.model, DEFAULT_MODEL.to_string());
+        }
+        // Older versions always wrote the OpenAI default here, even though Groq ignored it
+        if self.llm_provider == LLMProvider::Groq && self.llm_model == DEFAULT_OPENAI_LLM_MODEL {
+            self.llm_model.clear();
+        }
+        self.ollama_base_url = if self.ollama_base_url.trim().is_empty() {
+            DEFAULT_OLLAMA_BASE_URL.to_string()
+        } else {
+            self.ollama_base_url
+                .trim()
+                .trim_end_matches('/')
+                .to_string()
+        };
+        self.openai_base_url = self
+            .openai_base_url
+            .trim()
+            .trim_end_matches('/')
+            .to_string();
+        self.groq_base_url = self.groq_base_url.trim().trim_end_matches('/').to_string();
+        self.ollama_model = if self.ollama_model.trim().is_empty() {
+            DEFAULT_OLLAMA_MODEL.to_string()
         } else {
-            self.llm_model.trim().to_string()
+            self.ollama_model.trim().to_string()
         };
         self.hotkey = self.normalized_hotkey();
         self.translate_hotkey = self.translate_hotkey.trim().to_string();
         self.toggle_translate_hotkey = self.toggle_translate_hotkey.trim().to_string();
+        self.cycle_preset_hotkey = self.cycle_preset_hotkey.trim().to_string();
+        self.cycle_target_language_hotkey = self.cycle_target_language_hotkey.trim().to_string();
+        self.commit_segment_hotkey = self.commit_segment_hotkey.trim().to_string();
+        self.post_command = self.post_command.trim().to_string();
+        self.session_instructions = self.session_instructions.trim().to_string();
+        self.session_output_file = self.session_output_file.trim().to_string();
         self.target_language = if self.target_language.trim().is_empty() {
             DEFAULT_TARGET_LANGUAGE.to_string()
         } else {
             self.target_language.trim().to_string()
         };
-        self.custom_instructions = self.custom_instructions.trim().to_string();
+        self.transcription_language = match self.transcription_language.trim() {
+            "" => DEFAULT_TRANSCRIPTION_LANGUAGE.to_string(),
+            code => code.to_lowercase(),
+        };
+        let mut seen_languages = HashSet::new();
+        // An empty list is kept: there is nothing to cycle through then
+        self.target_languages = std::mem::take(&mut self.target_languages)
+            .into_iter()
+            .map(|l| l.trim().to_string())
+            .filter(|l| !l.is_empty() && seen_languages.insert(l.clone()))
+            .collect();
+        self.normalize_presets();
+        self.custom_vocabulary = normalize_vocabulary(std::mem::take(&mut self.custom_vocabulary));
+        let mut seen_words = HashSet::new();
+        self.profanity_words = std::mem::take(&mut self.profanity_words)
+            .into_iter()
+            .map(|w| w.trim().to_string())
+            .filter(|w| !w.is_empty() && seen_words.insert(w.to_lowercase()))
+            .collect();
+        self.app_rules = std::mem::take(&mut self.app_rules)
+            .into_iter()
+            .map(|rule| AppRule {
+                process: rule.process.trim().to_string(),
+                overrides: rule.overrides.normalized(),
+            })
+            .filter(|rule| !rule.process.is_empty())
+            .collect();
         if !self.use_custom_instructions || self.custom_instructions.is_empty() {
             self.use_custom_instructions = false;
         }
-        let lang = self.ui_language.trim().to_lowercase();
-        self.ui_language = if lang == "en" {
-            "en".to_string()
-        } else {
-            "ru".to_string()
-        };
+        self.ui_language = Language::from_code(&self.ui_language).code().to_string();
         self
     }
 
+    fn normalize_presets(&mut self) {
+        self.custom_instructions = self.custom_instructions.trim().to_string();
+        self.active_preset = self.active_preset.trim().to_string();
+
+        let mut seen = HashSet::new();
+        self.prompt_presets = std::mem::take(&mut self.prompt_presets)
+            .into_iter()
+            .map(|p| PromptPreset {
+                name: p.name.trim().to_string(),
+                instructions: p.instructions.trim().to_string(),
+                vocabulary: normalize_vocabulary(p.vocabulary),
+            })
+            .filter(|p| !p.name.is_empty() && seen.insert(p.name.clone()))
+            .collect();
+
+        // Migrate the pre-preset single prompt into a "Default" preset
+        if self.prompt_presets.is_empty() {
+            if self.custom_instructions.is_empty() {
+                self.active_preset.clear();
+                return;
+            }
+            self.prompt_presets.push(PromptPreset {
+                name: DEFAULT_PRESET_NAME.to_string(),
+                instructions: self.custom_instructions.clone(),
+                vocabulary: Vec::new(),
+            });
+        }
+
+        if !self
+            .prompt_presets
+            .iter()
+            .any(|p| p.name == self.active_preset)
+        {
+            self.active_preset = self.prompt_presets[0].name.clone();
+        }
+
+        // The single-prompt editor writes through to the active preset
+        let legacy = self.custom_instructions.clone();
+        if let Some(active) = self
+            .prompt_presets
+            .iter_mut()
+            .find(|p| p.name == self.active_preset)
+        {
+            active.instructions = legacy;
+        }
+    }
+
+    /// Make `name` the active preset. Returns false if no such preset exists.
+    pub fn select_preset(&mut self, name: &str) -> bool {
+        let Some(preset) = self.prompt_presets.iter().find(|p| p.name == name.trim()) else {
+            return false;
+        };
+        self.active_preset = preset.name.clone();
+        self.custom_instructions = preset.instructions.clone();
+        true
+    }
+
+    /// Switch to the preset after the active one (wrapping around).
+    /// Returns the new active preset name, or None if there are no presets.
+    pub fn cycle_preset(&mut self) -> Option<String> {
+        if self.prompt_presets.is_empty() {
+            return None;
+        }
+        let next = self
+            .prompt_presets
+            .iter()
+            .position(|p| p.name == self.active_preset)
+            .map(|i| (i + 1) % self.prompt_presets.len())
+            .unwrap_or(0);
+        let name = self.prompt_presets[next].name.clone();
+        self.select_preset(&name);
+        Some(name)
+    }
+
+    /// Switch to the target language after the active one (wrapping around).
+    /// Returns the new target language, or None if the list is empty.
+    pub fn cycle_target_language(&mut self) -> Option<String> {
+        if self.target_languages.is_empty() {
+            return None;
+        }
+        let next = self
+            .target_languages
+            .iter()
+            .position(|l| *l == self.target_language)
+            .map(|i| (i + 1) % self.target_languages.len())
+            .unwrap_or(0);
+        self.target_language = self.target_languages[next].clone();
+        Some(self.target_language.clone())
+    }
+
+    /// Instructions of the active preset, if custom instructions are enabled and non-empty
+    pub fn active_instructions(&self) -> Option<&str> {
+        if !self.use_custom_instructions {
+            return None;
+        }
+        self.prompt_presets
+            .iter()
+            .find(|p| p.name == self.active_preset)
+            .map(|p| p.instructions.trim())
+            .filter(|s| !s.is_empty())
+    }
+
+    /// Terms the refinement should spell as listed: the active preset's own list if it
+    /// has one, else `custom_vocabulary`; empty while the vocabulary is turned off
+    pub fn effective_vocabulary(&self) -> &[String] {
+        if !self.use_vocabulary {
+            return &[];
+        }
+        self.prompt_presets
+            .iter()
+            .find(|p| p.name == self.active_preset && !p.vocabulary.is_empty())
+            .map_or(&self.custom_vocabulary, |p| &p.vocabulary)
+    }
+
+    /// API key field for `provider` as stored, possibly an `env:` reference
+    fn provider_key_field(&self, provider: &TranscriptionProvider) -> &str {
+        match provider {
+            TranscriptionProvider::OpenAI => &self.api_key,
+            TranscriptionProvider::Groq => &self.groq_api_key,
+            TranscriptionProvider::ElevenLabs => &self.elevenlabs_api_key,
+            TranscriptionProvider::Mock => "",
+        }
+    }
+
+    /// Resolved API key for `provider`; empty if it isn't set, the provider takes none,
+    /// or it names an unset environment variable
+    pub fn provider_key(&self, provider: &TranscriptionProvider) -> String {
+        self.resolve_provider_key(provider).unwrap_or_default()
+    }
+
+    /// Like `provider_key`, but an unset environment variable is an error
+    pub fn resolve_provider_key(
+        &self,
+        provider: &TranscriptionProvider,
+    ) -> Result<String, SettingsValidationError> {
+        resolve_api_key(self.provider_key_field(provider))
+    }
+
+    /// API key field of the streaming provider as stored, possibly an `env:` reference
+    pub fn streaming_key_field(&self) -> &str {
+        match self.streaming_provider {
+            StreamingProvider::ElevenLabs => &self.elevenlabs_api_key,
+            StreamingProvider::Deepgram => &self.deepgram_api_key,
+        }
+    }
+
+    /// Fails while the API keys are encrypted and not unlocked, so callers that find
+    /// a key empty can report that rather than a missing key
+    pub fn ensure_unlocked(&self) -> Result<(), SettingsLocked> {
+        if self.keys_locked {
+            Err(SettingsLocked)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Whether the API key needed by `provider` is set
+    pub fn has_provider_key(&self, provider: &TranscriptionProvider) -> bool {
+        provider.needs_api_key() && !self.provider_key(provider).is_empty()
+    }
+
+    /// Switch the transcription provider, resetting the model if it belongs to another provider
+    pub fn switch_provider(&mut self, provider: TranscriptionProvider) {
+        if !provider.accepts_model(&self.model) {
+            self.model = provider.default_model().to_string();
+        }
+        self.provider = provider;
+    }
+
+    /// Chat model to use for refinement with the current LLM provider
+    pub fn effective_llm_model(&self) -> String {
+        match self.llm_provider {
+            LLMProvider::Ollama => self.ollama_model.clone(),
+            _ if self.llm_model.trim().is_empty() => self.llm_provider.default_model().to_string(),
+            _ => self.llm_model.trim().to_string(),
+        }
+    }
+
     pub fn is_valid_hotkey(&self) -> bool {
         let hotkey = self.normalized_hotkey();
         if hotkey.is_empty() {
@@ -268,8 +1001,88 @@ impl AppSettings {
 
     pub fn requires_llm(&self) -> bool {
         self.auto_translate
-            || (self.use_custom_instructions && !self.custom_instructions.trim().is_empty())
-            || (self.use_vocabulary && !self.custom_vocabulary.is_empty())
+            || self.active_instructions().is_some()
+            || !self.effective_vocabulary().is_empty()
+    }
+
+    /// How long a dictation may stay in "Transcribing" before it is considered stuck,
+    /// scaled up when refinement runs on a local model
+    pub fn transcribing_timeout(&self) -> Option<Duration> {
+        if self.transcribing_timeout_secs == 0 {
+            return None;
+        }
+        let base = Duration::from_secs(self.transcribing_timeout_secs);
+        if self.llm_provider == LLMProvider::Ollama && self.requires_llm() {
+            Some(base * OLLAMA_TIMEOUT_FACTOR)
+        } else {
+            Some(base)
+        }
+    }
+
+    /// Limit for an ElevenLabs WebSocket handshake; never below one second
+    pub fn elevenlabs_connect_timeout(&self) -> Duration {
+        Duration::from_secs(self.elevenlabs_connect_timeout_secs.max(1))
+    }
+
+    /// Silence sent ahead of a streaming commit; at most two seconds
+    pub fn commit_silence(&self) -> Duration {
+        Duration::from_millis(self.commit_silence_ms.min(MAX_COMMIT_SILENCE_MS))
+    }
+
+    /// `speak_rate` within half to twice the normal rate
+    pub fn speak_rate(&self) -> f32 {
+        if self.speak_rate.is_finite() {
+            self.speak_rate.clamp(MIN_SPEAK_RATE, MAX_SPEAK_RATE)
+        } else {
+            1.0
+        }
+    }
+
+    /// Window for a double-tap of the main hotkey, if it is enabled; at most a second
+    pub fn double_tap_window(&self) -> Option<Duration> {
+        (self.double_tap_ms > 0)
+            .then(|| Duration::from_millis(self.double_tap_ms.min(MAX_DOUBLE_TAP_MS)))
+    }
+
+    /// How long an unused streaming connection is kept open, if it is dropped at all
+    pub fn streaming_idle_disconnect(&self) -> Option<Duration> {
+        (self.streaming_idle_disconnect_minutes > 0)
+            .then(|| Duration::from_secs(self.streaming_idle_disconnect_minutes * 60))
+    }
+
+    /// Time between interim transcripts of a legacy recording, if they are enabled;
+    /// never below one second
+    pub fn pseudo_streaming_interval(&self) -> Option<Duration> {
+        self.pseudo_streaming
+            .then(|| Duration::from_secs(self.pseudo_streaming_interval_secs.max(1)))
+    }
+
+    /// Recorded length after which a legacy recording spills to disk, if it does
+    pub fn recording_spill_after(&self) -> Option<Duration> {
+        (self.recording_spill_after_secs > 0)
+            .then(|| Duration::from_secs(self.recording_spill_after_secs))
+    }
+
+    /// Gain control for streamed audio, if enabled; levels out of range are clamped
+    pub fn agc(&self) -> Option<AgcConfig> {
+        self.agc_enabled
+            .then(|| AgcConfig::new(self.agc_target_db, self.agc_max_gain_db))
+    }
+
+    /// Limits below which a recording isn't worth an API call
+    pub fn upload_thresholds(&self) -> UploadThresholds {
+        UploadThresholds {
+            min_duration: Duration::from_millis(self.min_upload_duration_ms),
+            min_rms: self.min_upload_rms.clamp(0.0, 1.0),
+        }
+    }
+
+    /// The post-transcription command, if it is enabled and the user confirmed it
+    /// since it last changed
+    pub fn active_post_command(&self) -> Option<&str> {
+        let command = self.post_command.as_str();
+        (self.post_command_enabled && !command.is_empty() && command == self.post_command_confirmed)
+            .then_some(command)
     }
 
     pub fn validate(&self) -> Result<(), SettingsValidationError> {
@@ -280,6 +1093,18 @@ impl AppSettings {
         if !self.is_valid_hotkey() {
             return Err(SettingsValidationError::InvalidHotkey(hotkey));
         }
+        if !(0.0..=MAX_LLM_TEMPERATURE).contains(&self.llm_temperature) {
+            return Err(SettingsValidationError::InvalidTemperature(
+                self.llm_temperature,
+            ));
+        }
+        if !self.target_languages.is_empty()
+            && !self.target_languages.contains(&self.target_language)
+        {
+            return Err(SettingsValidationError::UnlistedTargetLanguage(
+                self.target_language.clone(),
+            ));
+        }
 
         // Note: We don't validate API keys here during save_settings.
         // API keys are validated when actually needed (before transcription).
@@ -293,29 +1118,24 @@ impl AppSettings {
     /// This should be called before performing transcription, not during settings save
     #[allow(dead_code)]
     pub fn validate_for_transcription(&self) -> Result<(), SettingsValidationError> {
-        match self.provider {
-            TranscriptionProvider::Mock => {} // Mock doesn't need API key
-            TranscriptionProvider::OpenAI if self.api_key.trim().is_empty() => {
-                return Err(SettingsValidationError::MissingApiKey("OpenAI"));
-            }
-            TranscriptionProvider::Groq if self.groq_api_key.trim().is_empty() => {
-                return Err(SettingsValidationError::MissingApiKey("Groq"));
-            }
-            TranscriptionProvider::ElevenLabs if self.elevenlabs_api_key.trim().is_empty() => {
-                return Err(SettingsValidationError::MissingApiKey("ElevenLabs"));
-            }
-            _ => {} // API key is present
+        if self.provider.needs_api_key() && self.resolve_provider_key(&self.provider)?.is_empty() {
+            return Err(SettingsValidationError::MissingApiKey(
+                self.provider.display_name(),
+            ));
         }
 
         if self.requires_llm() {
             match self.llm_provider {
-                LLMProvider::OpenAI if self.api_key.trim().is_empty() => {
+                LLMProvider::OpenAI if resolve_api_key(&self.api_key)?.is_empty() => {
                     return Err(SettingsValidationError::MissingApiKey("OpenAI"));
                 }
-                LLMProvider::Groq if self.groq_api_key.trim().is_empty() => {
+                LLMProvider::Groq if resolve_api_key(&self.groq_api_key)?.is_empty() => {
                     return Err(SettingsValidationError::MissingApiKey("Groq"));
                 }
-                _ => {} // API key is present
+                LLMProvider::Ollama if self.ollama_base_url.trim().is_empty() => {
+                    return Err(SettingsValidationError::MissingBaseUrl("Ollama"));
+                }
+                _ => {} // API key or base URL is present
             }
         }
 
@@ -323,38 +1143,151 @@ impl AppSettings {
     }
 }
 
+/// Heuristic for migrating chat models out of the transcription `model` field
+fn looks_like_chat_model(model: &str) -> bool {
+    let model = model.trim().to_lowercase();
+    if model.is_empty() || model.contains("transcribe") || model.contains("whisper") {
+        return false;
+    }
+    [
+        "gpt-", "o1", "o3", "o4", "llama", "mixtral", "gemma", "qwen", "openai/",
+    ]
+    .iter()
+    .any(|prefix| model.starts_with(prefix))
+}
+
 #[derive(Clone)]
 pub struct SettingsStore {
     root: PathBuf,
+    /// Keep API keys in the OS keychain; off in tests, which must not touch it
+    use_keyring: bool,
+    /// Encrypted API keys (`encrypt_settings`), shared by clones of the store
+    vault: Arc<Mutex<Vault>>,
 }
 
 impl SettingsStore {
     pub fn new(root: PathBuf) -> Self {
-        Self { root }
+        Self {
+            root,
+            use_keyring: true,
+            vault: Arc::default(),
+        }
     }
 
-    fn file_path(&self) -> PathBuf {
+    /// Store that keeps API keys in the settings file
+    #[cfg(test)]
+    pub fn without_keyring(root: PathBuf) -> Self {
+        Self {
+            root,
+            use_keyring: false,
+            vault: Arc::default(),
+        }
+    }
+
+    pub fn file_path(&self) -> PathBuf {
         self.root.join(CONFIG_FILE)
     }
 
+    fn vault(&self) -> MutexGuard<'_, Vault> {
+        self.vault.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Whether the API keys are encrypted, and if so whether they are unlocked
+    pub fn lock_state(&self) -> LockState {
+        self.vault().state()
+    }
+
+    /// Decrypt the API keys read by `load`. The derived key stays in memory, so later
+    /// saves encrypt the keys again without asking.
+    pub fn unlock(&self, passphrase: &str) -> Result<ApiKeys, VaultError> {
+        let mut vault = self.vault();
+        let Vault::Locked(sealed) = &*vault else {
+            return Err(VaultError::NotLocked);
+        };
+        let (key, keys) = sealed.open(passphrase)?;
+        *vault = Vault::Unlocked(key);
+        Ok(keys)
+    }
+
+    /// Encrypt the API keys with `passphrase` from the next save on, or stop encrypting
+    /// them if it is empty. Once they are encrypted, `current` must be the passphrase in
+    /// use. Returns whether the keys are encrypted now.
+    pub fn set_passphrase(&self, current: &str, passphrase: &str) -> Result<bool, VaultError> {
+        let mut vault = self.vault();
+        match &*vault {
+            Vault::Off => {}
+            Vault::Locked(_) => return Err(SettingsLocked.into()),
+            Vault::Unlocked(key) if !key.matches(current) => {
+                return Err(VaultError::WrongPassphrase)
+            }
+            Vault::Unlocked(_) => {}
+        }
+        *vault = if passphrase.is_empty() {
+            Vault::Off
+        } else {
+            Vault::Unlocked(VaultKey::new(passphrase)?)
+        };
+        Ok(!passphrase.is_empty())
+    }
+
     pub async fn load(&self) -> Result<AppSettings> {
         let path = self.file_path();
-        let mut settings = if !path.exists() {
-            AppSettings::default()
+        let (mut settings, sealed) = if !path.exists() {
+            (AppSettings::default(), None)
         } else {
             let raw = async_fs::read(&path)
                 .await
                 .with_context(|| format!("Failed to read {path:?}"))?;
-            serde_json::from_slice::<AppSettings>(&raw)
-                .with_context(|| format!("Failed to parse {path:?}"))?
+            let mut value = serde_json::from_slice::<serde_json::Value>(&raw)
+                .with_context(|| format!("Failed to parse {path:?}"))?;
+            let sealed = value
+                .as_object_mut()
+                .and_then(|fields| fields.remove(SEALED_KEYS_FIELD))
+                .map(serde_json::from_value::<SealedKeys>)
+                .transpose()
+                .with_context(|| format!("Failed to parse the encrypted keys in {path:?}"))?;
+            let settings = serde_json::from_value::<AppSettings>(value)
+                .with_context(|| format!("Failed to parse {path:?}"))?;
+            (settings, sealed)
         };
 
+        if settings.encrypt_settings {
+            let mut vault = self.vault();
+            let unlocked = match &*vault {
+                Vault::Unlocked(key) => Some(key.clone()),
+                _ => None,
+            };
+            match (sealed, unlocked) {
+                // Reloaded after unlocking: the key in memory still opens them
+                (Some(sealed), Some(key)) => key.open(&sealed)?.apply(&mut settings),
+                (Some(sealed), None) => {
+                    *vault = Vault::Locked(sealed);
+                    ApiKeys::take(&mut settings);
+                    settings.keys_locked = true;
+                    tracing::info!("[Settings] API keys are encrypted; waiting for the passphrase");
+                }
+                (None, _) => {
+                    tracing::warn!(
+                        "[Settings] Settings file has no encrypted keys; encryption turned off"
+                    );
+                    *vault = Vault::Off;
+                    settings.encrypt_settings = false;
+                }
+            }
+            return Ok(settings.normalized());
+        }
+
+        if !self.use_keyring {
+            return Ok(settings.normalized());
+        }
+
         // Override API keys from OS keychain (takes priority over JSON)
         let kr_api = keyring_get("api_key");
         let kr_groq = keyring_get("groq_api_key");
         let kr_el = keyring_get("elevenlabs_api_key");
+        let kr_dg = keyring_get("deepgram_api_key");
 
-        if kr_api.is_some() || kr_groq.is_some() || kr_el.is_some() {
+        if kr_api.is_some() || kr_groq.is_some() || kr_el.is_some() || kr_dg.is_some() {
             if let Some(k) = kr_api {
                 settings.api_key = k;
             }
@@ -364,6 +1297,9 @@ impl SettingsStore {
             if let Some(k) = kr_el {
                 settings.elevenlabs_api_key = k;
             }
+            if let Some(k) = kr_dg {
+                settings.deepgram_api_key = k;
+            }
             tracing::debug!("[Settings] API keys loaded from OS keychain");
         }
 
@@ -384,16 +1320,47 @@ impl SettingsStore {
 
         let mut disk_settings = settings.clone().normalized();
 
+        if disk_settings.encrypt_settings {
+            let keys = ApiKeys::take(&mut disk_settings);
+            let sealed = match &*self.vault() {
+                Vault::Unlocked(key) => key.seal(&keys)?,
+                // Nothing to re-encrypt with; the keys stay as they were
+                Vault::Locked(sealed) => sealed.clone(),
+                Vault::Off => bail!("API keys can't be encrypted without a passphrase"),
+            };
+            if self.use_keyring {
+                // Keys from before encryption was turned on
+                for field in [
+                    "api_key",
+                    "groq_api_key",
+                    "elevenlabs_api_key",
+                    "deepgram_api_key",
+                ] {
+                    keyring_set(field, "");
+                }
+            }
+            let mut value = serde_json::to_value(&disk_settings)
+                .context("Failed to serialize settings to JSON")?;
+            value[SEALED_KEYS_FIELD] =
+                serde_json::to_value(sealed).context("Failed to serialize the encrypted keys")?;
+            let serialized = serde_json::to_vec_pretty(&value)
+                .context("Failed to serialize settings to JSON")?;
+            return self.write(serialized).await;
+        }
+
         // Persist API keys in OS keychain; strip them from the JSON file.
         // If keyring is unavailable (headless Linux, etc.) keys stay in JSON as fallback.
-        let keyring_ok = keyring_set("api_key", &disk_settings.api_key)
+        let keyring_ok = self.use_keyring
+            && keyring_set("api_key", &disk_settings.api_key)
             && keyring_set("groq_api_key", &disk_settings.groq_api_key)
-            && keyring_set("elevenlabs_api_key", &disk_settings.elevenlabs_api_key);
+            && keyring_set("elevenlabs_api_key", &disk_settings.elevenlabs_api_key)
+            && keyring_set("deepgram_api_key", &disk_settings.deepgram_api_key);
 
         if keyring_ok {
             disk_settings.api_key.clear();
             disk_settings.groq_api_key.clear();
             disk_settings.elevenlabs_api_key.clear();
+            disk_settings.deepgram_api_key.clear();
             tracing::debug!("[Settings] API keys saved to OS keychain");
         } else {
             tracing::warn!(
@@ -403,7 +1370,10 @@ impl SettingsStore {
 
         let serialized = serde_json::to_vec_pretty(&disk_settings)
             .context("Failed to serialize settings to JSON")?;
+        self.write(serialized).await
+    }
 
+    async fn write(&self, serialized: Vec<u8>) -> Result<()> {
         // Atomic write: write to temp file first, then rename to avoid corruption on crash
         let target = self.file_path();
         let tmp = target.with_extension("json.tmp");
@@ -431,6 +1401,51 @@ mod tests {
         assert!(!settings.auto_start);
         assert!(!settings.start_minimized);
         assert!(settings.auto_update);
+        assert_eq!(settings.update_channel, UpdateChannel::Stable);
+        assert_eq!(settings.overlay_hide_after_ms, 2500);
+        assert_eq!(settings.transcribing_timeout_secs, 120);
+        assert_eq!(
+            settings.elevenlabs_connect_timeout(),
+            Duration::from_secs(8)
+        );
+        assert!(!settings.notify_on_complete);
+        assert!(settings.notify_on_error);
+        assert_eq!(settings.agc(), None);
+        assert!(settings.history_enabled);
+        assert_eq!(settings.history_max_entries, 100);
+        assert_eq!(settings.history_retention_days, 0);
+    }
+
+    #[test]
+    fn test_transcribing_timeout_scales_for_local_refinement() {
+        let mut settings = AppSettings::default();
+        assert_eq!(
+            settings.transcribing_timeout(),
+            Some(Duration::from_secs(120))
+        );
+
+        settings.llm_provider = LLMProvider::Ollama;
+        assert_eq!(
+            settings.transcribing_timeout(),
+            Some(Duration::from_secs(120)),
+            "no refinement, no local model involved"
+        );
+        settings.auto_translate = true;
+        assert_eq!(
+            settings.transcribing_timeout(),
+            Some(Duration::from_secs(360))
+        );
+
+        settings.transcribing_timeout_secs = 0;
+        assert_eq!(settings.transcribing_timeout(), None);
+    }
+
+    #[test]
+    fn test_missing_history_fields_use_defaults() {
+        let settings: AppSettings = serde_json::from_str(r#"{"hotkey":"Ctrl+Shift+A"}"#).unwrap();
+        assert!(settings.history_enabled);
+        assert_eq!(settings.history_max_entries, 100);
+        assert_eq!(settings.history_retention_days, 0);
     }
 
     #[test]
@@ -514,6 +1529,50 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_env_key_reference_is_resolved_when_used() {
+        std::env::set_var("EASY_DICTATE_TEST_GROQ_KEY", " gsk-from-env ");
+        let mut settings = AppSettings::default();
+        settings.provider = TranscriptionProvider::Groq;
+        settings.groq_api_key = "env:EASY_DICTATE_TEST_GROQ_KEY".to_string();
+
+        assert_eq!(
+            settings.provider_key(&TranscriptionProvider::Groq),
+            "gsk-from-env"
+        );
+        assert!(settings.validate_for_transcription().is_ok());
+        // Saved and shown as the reference, not the key
+        assert_eq!(
+            settings.normalized().groq_api_key,
+            "env:EASY_DICTATE_TEST_GROQ_KEY"
+        );
+        assert_eq!(resolve_api_key(" sk-literal ").unwrap(), "sk-literal");
+    }
+
+    #[test]
+    fn test_unset_env_key_reference_is_its_own_error() {
+        let mut settings = AppSettings::default();
+        settings.provider = TranscriptionProvider::OpenAI;
+        settings.api_key = "env:EASY_DICTATE_TEST_UNSET_KEY".to_string();
+
+        assert!(settings
+            .provider_key(&TranscriptionProvider::OpenAI)
+            .is_empty());
+        let err = settings.validate_for_transcription().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "environment variable EASY_DICTATE_TEST_UNSET_KEY is not set"
+        );
+
+        // The refinement key is checked the same way
+        settings.provider = TranscriptionProvider::Mock;
+        settings.auto_translate = true;
+        assert!(matches!(
+            settings.validate_for_transcription().unwrap_err(),
+            SettingsValidationError::UnsetKeyVariable(name) if name == "EASY_DICTATE_TEST_UNSET_KEY"
+        ));
+    }
+
     #[test]
     fn test_normalized_trims_whitespace() {
         let mut settings = AppSettings::default();
@@ -533,11 +1592,160 @@ mod tests {
         settings.model = "".to_string();
         settings.hotkey = "".to_string();
         settings.target_language = "".to_string();
+        settings.transcription_language = " ".to_string();
 
         let normalized = settings.normalized();
         assert_eq!(normalized.model, "gpt-4o-transcribe");
         assert_eq!(normalized.hotkey, "Ctrl+Shift+Space");
         assert_eq!(normalized.target_language, "English");
+        assert_eq!(normalized.transcription_language, "auto");
+    }
+
+    #[test]
+    fn test_validate_for_transcription_ollama_needs_base_url_not_key() {
+        let mut settings = AppSettings::default();
+        settings.api_key = "sk-test123".to_string();
+        settings.llm_provider = LLMProvider::Ollama;
+        settings.auto_translate = true;
+        assert!(settings.validate_for_transcription().is_ok());
+
+        settings.ollama_base_url = "  ".to_string();
+        assert!(matches!(
+            settings.validate_for_transcription().unwrap_err(),
+            SettingsValidationError::MissingBaseUrl("Ollama")
+        ));
+    }
+
+    #[test]
+    fn test_effective_llm_model_uses_provider_default_when_empty() {
+        let mut settings = AppSettings::default();
+        assert_eq!(settings.effective_llm_model(), "gpt-4o-mini");
+
+        settings.llm_provider = LLMProvider::Groq;
+        assert_eq!(settings.effective_llm_model(), "openai/gpt-oss-20b");
+
+        settings.llm_model = "llama-3.3-70b-versatile".to_string();
+        assert_eq!(settings.effective_llm_model(), "llama-3.3-70b-versatile");
+    }
+
+    #[test]
+    fn test_normalized_migrates_chat_model_from_shared_model() {
+        let mut settings = AppSettings::default();
+        settings.model = "gpt-4o-mini".to_string();
+        settings.llm_model = String::new();
+
+        let normalized = settings.normalized();
+        assert_eq!(normalized.llm_model, "gpt-4o-mini");
+        assert_eq!(normalized.model, "gpt-4o-transcribe");
+    }
+
+    #[test]
+    fn test_normalized_keeps_transcription_model() {
+        let mut settings = AppSettings::default();
+        settings.model = "whisper-1".to_string();
+
+        let normalized = settings.normalized();
+        assert_eq!(normalized.model, "whisper-1");
+        assert!(normalized.llm_model.is_empty());
+    }
+
+    #[test]
+    fn test_normalized_clears_legacy_openai_model_for_groq() {
+        let mut settings = AppSettings::default();
+        settings.llm_provider = LLMProvider::Groq;
+        settings.llm_model = "gpt-4o-mini".to_string();
+
+        let normalized = settings.normalized();
+        assert_eq!(normalized.effective_llm_model(), "openai/gpt-oss-20b");
+    }
+
+    #[test]
+    fn test_validate_llm_temperature_range() {
+        let mut settings = AppSettings::default();
+        settings.llm_temperature = 2.0;
+        assert!(settings.validate().is_ok());
+
+        settings.llm_temperature = 2.5;
+        assert!(matches!(
+            settings.validate().unwrap_err(),
+            SettingsValidationError::InvalidTemperature(_)
+        ));
+
+        settings.llm_temperature = -0.1;
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn test_target_languages_normalized() {
+        let settings = AppSettings {
+            target_languages: vec![
+                " German ".to_string(),
+                String::new(),
+                "English".to_string(),
+                "German".to_string(),
+            ],
+            ..AppSettings::default()
+        }
+        .normalized();
+        assert_eq!(settings.target_languages, vec!["German", "English"]);
+
+        let settings = AppSettings {
+            target_languages: vec!["  ".to_string()],
+            ..AppSettings::default()
+        }
+        .normalized();
+        assert!(settings.target_languages.is_empty());
+    }
+
+    #[test]
+    fn test_legacy_tray_languages_become_target_languages() {
+        let settings: AppSettings =
+            serde_json::from_str(r#"{"tray_languages": ["Russian", "German"]}"#).unwrap();
+        assert_eq!(settings.target_languages, vec!["Russian", "German"]);
+    }
+
+    #[test]
+    fn test_cycle_target_language() {
+        let mut settings = AppSettings {
+            target_language: "English".to_string(),
+            target_languages: vec![
+                "English".to_string(),
+                "Deutsch".to_string(),
+                "Russian".to_string(),
+            ],
+            ..AppSettings::default()
+        };
+        assert_eq!(settings.cycle_target_language().as_deref(), Some("Deutsch"));
+        assert_eq!(settings.cycle_target_language().as_deref(), Some("Russian"));
+        assert_eq!(settings.cycle_target_language().as_deref(), Some("English"));
+
+        // An unlisted target starts over at the first one
+        settings.target_language = "Korean".to_string();
+        assert_eq!(settings.cycle_target_language().as_deref(), Some("English"));
+
+        settings.target_languages.clear();
+        assert_eq!(settings.cycle_target_language(), None);
+        assert_eq!(settings.target_language, "English");
+    }
+
+    #[test]
+    fn test_validate_target_language_in_list() {
+        let mut settings = AppSettings {
+            target_language: "Korean".to_string(),
+            ..AppSettings::default()
+        };
+        assert!(matches!(
+            settings.validate().unwrap_err(),
+            SettingsValidationError::UnlistedTargetLanguage(language) if language == "Korean"
+        ));
+
+        settings.target_languages.push("Korean".to_string());
+        assert!(settings.validate().is_ok());
+
+        // Without a list any target goes
+        settings.target_language = "Italian".to_string();
+        settings.target_languages.clear();
+        assert!(settings.validate().is_ok());
     }
 
     #[test]
@@ -552,9 +1760,237 @@ mod tests {
         let mut settings = AppSettings::default();
         settings.use_custom_instructions = true;
         settings.custom_instructions = "Custom prompt".to_string();
+        let settings = settings.normalized();
         assert!(settings.requires_llm());
     }
 
+    #[test]
+    fn test_log_transcripts_defaults() {
+        // New installs start without a settings file
+        assert!(!AppSettings::default().log_transcripts);
+        // Existing files predate the option and keep the old always-on behaviour
+        let legacy: AppSettings = serde_json::from_str(r#"{"hotkey":"Ctrl+Shift+Space"}"#).unwrap();
+        assert!(legacy.log_transcripts);
+        let opted_out: AppSettings = serde_json::from_str(r#"{"log_transcripts":false}"#).unwrap();
+        assert!(!opted_out.log_transcripts);
+    }
+
+    #[test]
+    fn test_recording_spill_after() {
+        let mut settings = AppSettings::default();
+        assert_eq!(
+            settings.recording_spill_after(),
+            Some(Duration::from_secs(120))
+        );
+        settings.recording_spill_after_secs = 0;
+        assert_eq!(settings.recording_spill_after(), None);
+    }
+
+    #[test]
+    fn test_pseudo_streaming_interval() {
+        let mut settings = AppSettings::default();
+        assert_eq!(settings.pseudo_streaming_interval(), None);
+
+        settings.pseudo_streaming = true;
+        assert_eq!(
+            settings.pseudo_streaming_interval(),
+            Some(Duration::from_secs(3))
+        );
+        settings.pseudo_streaming_interval_secs = 0;
+        assert_eq!(
+            settings.pseudo_streaming_interval(),
+            Some(Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    fn test_speak_rate_is_clamped() {
+        let mut settings = AppSettings::default();
+        assert_eq!(settings.speak_rate(), 1.0);
+        settings.speak_rate = 5.0;
+        assert_eq!(settings.speak_rate(), 2.0);
+        settings.speak_rate = f32::NAN;
+        assert_eq!(settings.speak_rate(), 1.0);
+    }
+
+    #[test]
+    fn test_commit_silence() {
+        let mut settings = AppSettings::default();
+        assert_eq!(settings.commit_silence(), Duration::from_millis(200));
+        settings.commit_silence_ms = 0;
+        assert_eq!(settings.commit_silence(), Duration::ZERO);
+        settings.commit_silence_ms = 60_000;
+        assert_eq!(settings.commit_silence(), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_double_tap_window() {
+        let mut settings = AppSettings::default();
+        assert_eq!(
+            settings.double_tap_window(),
+            Some(Duration::from_millis(400))
+        );
+        settings.double_tap_ms = 5_000;
+        assert_eq!(settings.double_tap_window(), Some(Duration::from_secs(1)));
+        settings.double_tap_ms = 0;
+        assert_eq!(settings.double_tap_window(), None);
+    }
+
+    #[test]
+    fn test_streaming_idle_disconnect() {
+        let mut settings = AppSettings::default();
+        assert_eq!(
+            settings.streaming_idle_disconnect(),
+            Some(Duration::from_secs(300))
+        );
+        settings.streaming_idle_disconnect_minutes = 0;
+        assert_eq!(settings.streaming_idle_disconnect(), None);
+    }
+
+    #[test]
+    fn test_agc_config_is_clamped() {
+        let settings = AppSettings {
+            agc_enabled: true,
+            agc_target_db: 3.0,
+            ..AppSettings::default()
+        };
+        let config = settings.agc().unwrap();
+        assert_eq!(config.target_db, -6.0);
+        assert_eq!(config.max_gain_db, DEFAULT_MAX_GAIN_DB);
+    }
+
+    #[test]
+    fn test_post_command_needs_confirmation() {
+        let mut settings = AppSettings {
+            post_command: "  ~/bin/file-note  ".to_string(),
+            post_command_enabled: true,
+            ..AppSettings::default()
+        }
+        .normalized();
+        assert_eq!(settings.post_command, "~/bin/file-note");
+        assert_eq!(settings.active_post_command(), None);
+
+        settings.post_command_confirmed = settings.post_command.clone();
+        assert_eq!(settings.active_post_command(), Some("~/bin/file-note"));
+
+        settings.post_command_enabled = false;
+        assert_eq!(settings.active_post_command(), None);
+
+        // Any change to the command needs a new confirmation
+        settings.post_command_enabled = true;
+        settings.post_command = "~/bin/file-note --org".to_string();
+        assert_eq!(settings.active_post_command(), None);
+    }
+
+    #[test]
+    fn test_legacy_custom_instructions_migrate_to_default_preset() {
+        let settings: AppSettings = serde_json::from_str(
+            r#"{"use_custom_instructions":true,"custom_instructions":"Be formal"}"#,
+        )
+        .unwrap();
+        let settings = settings.normalized();
+
+        assert_eq!(
+            settings.prompt_presets,
+            vec![PromptPreset {
+                name: "Default".to_string(),
+                instructions: "Be formal".to_string(),
+                vocabulary: Vec::new(),
+            }]
+        );
+        assert_eq!(settings.active_preset, "Default");
+        assert_eq!(settings.active_instructions(), Some("Be formal"));
+    }
+
+    fn settings_with_presets() -> AppSettings {
+        let mut settings = AppSettings::default();
+        settings.use_custom_instructions = true;
+        settings.prompt_presets = vec![
+            PromptPreset {
+                name: "Email".to_string(),
+                instructions: "formal, full sentences".to_string(),
+                vocabulary: Vec::new(),
+            },
+            PromptPreset {
+                name: "Slack".to_string(),
+                instructions: "casual, keep it short".to_string(),
+                vocabulary: vec!["Slack".to_string(), "Jira".to_string()],
+            },
+        ];
+        settings.active_preset = "Email".to_string();
+        settings.custom_instructions = "formal, full sentences".to_string();
+        settings.normalized()
+    }
+
+    #[test]
+    fn test_select_and_cycle_presets() {
+        let mut settings = settings_with_presets();
+        assert!(!settings.select_preset("Missing"));
+
+        assert!(settings.select_preset("Slack"));
+        assert_eq!(
+            settings.active_instructions(),
+            Some("casual, keep it short")
+        );
+        assert_eq!(settings.custom_instructions, "casual, keep it short");
+
+        assert_eq!(settings.cycle_preset().as_deref(), Some("Email"));
+        let settings = settings.normalized();
+        assert_eq!(settings.active_preset, "Email");
+        assert_eq!(
+            settings.active_instructions(),
+            Some("formal, full sentences")
+        );
+    }
+
+    #[test]
+    fn test_preset_vocabulary_overrides_the_global_list() {
+        let mut settings = settings_with_presets();
+        settings.custom_vocabulary = vec!["Tauri".to_string()];
+        assert!(settings.effective_vocabulary().is_empty());
+
+        settings.use_vocabulary = true;
+        assert_eq!(settings.effective_vocabulary(), ["Tauri"]);
+        assert!(settings.select_preset("Slack"));
+        assert_eq!(settings.effective_vocabulary(), ["Slack", "Jira"]);
+
+        settings.custom_vocabulary.clear();
+        assert!(settings.select_preset("Email"));
+        assert!(settings.effective_vocabulary().is_empty());
+    }
+
+    #[test]
+    fn test_vocabulary_is_normalized_and_deduped() {
+        let settings = AppSettings {
+            custom_vocabulary: vec![
+                "  Web  Socket ".to_string(),
+                "".to_string(),
+                "webSocket".to_string(),
+                "web socket".to_string(),
+                "Tauri".to_string(),
+            ],
+            ..AppSettings::default()
+        }
+        .normalized();
+        assert_eq!(
+            settings.custom_vocabulary,
+            ["Web Socket", "webSocket", "Tauri"]
+        );
+    }
+
+    #[test]
+    fn test_legacy_editor_writes_through_to_active_preset() {
+        let mut settings = settings_with_presets();
+        settings.custom_instructions = "very formal".to_string();
+        let settings = settings.normalized();
+
+        assert_eq!(settings.prompt_presets[0].instructions, "very formal");
+        assert_eq!(
+            settings.prompt_presets[1].instructions,
+            "casual, keep it short"
+        );
+    }
+
     #[test]
     fn test_does_not_require_llm_by_default() {
         let settings = AppSettings::default();
@@ -602,4 +2038,95 @@ mod tests {
         assert_eq!(original.hotkey, deserialized.hotkey);
         assert_eq!(original.model, deserialized.model);
     }
+
+    #[test]
+    fn test_switch_provider_resets_foreign_model() {
+        let mut settings = AppSettings::default();
+        settings.model = "gpt-4o-mini-transcribe".to_string();
+
+        settings.switch_provider(TranscriptionProvider::Groq);
+        assert_eq!(settings.provider, TranscriptionProvider::Groq);
+        assert_eq!(settings.model, "groq/whisper-large-v3-turbo");
+
+        settings.model = "groq/whisper-large-v3".to_string();
+        settings.switch_provider(TranscriptionProvider::Groq);
+        assert_eq!(settings.model, "groq/whisper-large-v3");
+
+        settings.switch_provider(TranscriptionProvider::OpenAI);
+        assert_eq!(settings.model, DEFAULT_MODEL);
+    }
+
+    #[test]
+    fn test_app_rules_are_normalized_and_applied() {
+        let json = r#"{"app_rules": [
+            {"process": " slack.exe ", "auto_translate": false, "target_language": " ",
+             "output_mode": "clipboard"},
+            {"process": "  ", "auto_translate": true}
+        ]}"#;
+        let settings = serde_json::from_str::<AppSettings>(json)
+            .unwrap()
+            .normalized();
+        assert_eq!(settings.app_rules.len(), 1);
+        let rule = &settings.app_rules[0];
+        assert_eq!(rule.process, "slack.exe");
+        assert_eq!(rule.overrides.target_language, None);
+
+        let mut applied = AppSettings {
+            auto_translate: true,
+            ..settings.clone()
+        };
+        rule.overrides.apply(&mut applied);
+        assert!(!applied.auto_translate);
+        assert!(!applied.simulate_typing);
+        assert!(applied.copy_to_clipboard);
+        assert_eq!(applied.target_language, settings.target_language);
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_keys_stay_locked_until_unlocked() {
+        let root = std::env::temp_dir().join(format!("easy-dictate-vault-{}", std::process::id()));
+        let store = SettingsStore::without_keyring(root.clone());
+        let mut settings = AppSettings::default();
+        settings.api_key = "sk-secret".to_string();
+        settings.local_api_token = "local-secret".to_string();
+        settings.hotkey = "Ctrl+Alt+D".to_string();
+
+        settings.encrypt_settings = store.set_passphrase("", "correct horse").unwrap();
+        store.save(&settings).await.unwrap();
+        let raw = std::fs::read_to_string(store.file_path()).unwrap();
+        assert!(!raw.contains("sk-secret"));
+        assert!(!raw.contains("local-secret"));
+
+        // A fresh start: everything but the keys is readable
+        let store = SettingsStore::without_keyring(root.clone());
+        let locked = store.load().await.unwrap();
+        assert_eq!(locked.hotkey, "Ctrl+Alt+D");
+        assert!(locked.keys_locked && locked.api_key.is_empty());
+        assert!(locked.local_api_token.is_empty());
+        assert!(locked.ensure_unlocked().is_err());
+        // Saving while locked keeps the encrypted keys as they were
+        store.save(&locked).await.unwrap();
+
+        let store = SettingsStore::without_keyring(root.clone());
+        store.load().await.unwrap();
+        assert!(matches!(
+            store.unlock("battery staple"),
+            Err(VaultError::WrongPassphrase)
+        ));
+        assert_eq!(store.lock_state(), LockState::Locked);
+        let keys = store.unlock("correct horse").unwrap();
+        assert_eq!(keys.api_key, "sk-secret");
+        assert_eq!(keys.local_api_token, "local-secret");
+        assert_eq!(store.lock_state(), LockState::Unlocked);
+
+        // Changing the passphrase takes the current one
+        assert!(store.set_passphrase("battery staple", "new").is_err());
+        assert!(store.set_passphrase("correct horse", "new").unwrap());
+        store.save(&settings).await.unwrap();
+        let store = SettingsStore::without_keyring(root.clone());
+        store.load().await.unwrap();
+        assert_eq!(store.unlock("new").unwrap().api_key, "sk-secret");
+
+        let _ = std::fs::remove_dir_all(root);
+    }
 }
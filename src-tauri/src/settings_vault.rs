@@ -0,0 +1,238 @@
+use aes_gcm::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Key, Nonce,
+};
+use anyhow::anyhow;
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::settings::AppSettings;
+
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+
+/// The settings file keeps its API keys encrypted and `unlock_settings` hasn't been
+/// given the passphrase yet
+#[derive(Debug, Error)]
+#[error("Settings are locked: enter the passphrase to use the API keys")]
+pub struct SettingsLocked;
+
+#[derive(Debug, Error)]
+pub enum VaultError {
+    #[error("Wrong passphrase")]
+    WrongPassphrase,
+    #[error("The passphrase must not be empty")]
+    EmptyPassphrase,
+    #[error("Settings are not locked")]
+    NotLocked,
+    #[error(transparent)]
+    Locked(#[from] SettingsLocked),
+    #[error("Encrypted API keys are unreadable: {0}")]
+    Corrupt(String),
+}
+
+/// The secret fields of the settings, as encrypted together
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiKeys {
+    pub api_key: String,
+    pub groq_api_key: String,
+    pub elevenlabs_api_key: String,
+    pub deepgram_api_key: String,
+    /// Not a provider key, but a credential all the same
+    pub local_api_token: String,
+}
+
+impl ApiKeys {
+    /// Move the keys out of `settings`, leaving the fields empty
+    pub fn take(settings: &mut AppSettings) -> Self {
+        Self {
+            api_key: std::mem::take(&mut settings.api_key),
+            groq_api_key: std::mem::take(&mut settings.groq_api_key),
+            elevenlabs_api_key: std::mem::take(&mut settings.elevenlabs_api_key),
+            deepgram_api_key: std::mem::take(&mut settings.deepgram_api_key),
+            local_api_token: std::mem::take(&mut settings.local_api_token),
+        }
+    }
+
+    pub fn apply(self, settings: &mut AppSettings) {
+        settings.api_key = self.api_key;
+        settings.groq_api_key = self.groq_api_key;
+        settings.elevenlabs_api_key = self.elevenlabs_api_key;
+        settings.deepgram_api_key = self.deepgram_api_key;
+        settings.local_api_token = self.local_api_token;
+    }
+}
+
+/// `ApiKeys` encrypted with a passphrase, as stored in the settings file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SealedKeys {
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+impl SealedKeys {
+    /// Decrypt with `passphrase`; also returns the key to seal them again with
+    pub fn open(&self, passphrase: &str) -> Result<(VaultKey, ApiKeys), VaultError> {
+        let salt: [u8; SALT_LEN] = decode(&self.salt)?
+            .try_into()
+            .map_err(|_| VaultError::Corrupt("bad salt length".into()))?;
+        let key = VaultKey::derive(passphrase, salt)?;
+        let keys = key.open(self)?;
+        Ok((key, keys))
+    }
+}
+
+fn decode(field: &str) -> Result<Vec<u8>, VaultError> {
+    STANDARD
+        .decode(field)
+        .map_err(|e| VaultError::Corrupt(e.to_string()))
+}
+
+/// Key derived from the passphrase with Argon2. Kept in memory while unlocked so
+/// saving can encrypt again without asking; neither it nor the passphrase is ever
+/// written anywhere.
+#[derive(Clone)]
+pub struct VaultKey {
+    key: [u8; KEY_LEN],
+    salt: [u8; SALT_LEN],
+}
+
+impl VaultKey {
+    /// Key for a new passphrase, with a fresh salt
+    pub fn new(passphrase: &str) -> Result<Self, VaultError> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        Self::derive(passphrase, salt)
+    }
+
+    fn derive(passphrase: &str, salt: [u8; SALT_LEN]) -> Result<Self, VaultError> {
+        if passphrase.is_empty() {
+            return Err(VaultError::EmptyPassphrase);
+        }
+        let mut key = [0u8; KEY_LEN];
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|e| VaultError::Corrupt(e.to_string()))?;
+        Ok(Self { key, salt })
+    }
+
+    /// Whether `passphrase` is the one this key was derived from
+    pub fn matches(&self, passphrase: &str) -> bool {
+        Self::derive(passphrase, self.salt).is_ok_and(|other| other.key == self.key)
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&self.key))
+    }
+
+    /// Encrypt `keys` under a fresh nonce
+    pub fn seal(&self, keys: &ApiKeys) -> anyhow::Result<SealedKeys> {
+        let plaintext = serde_json::to_vec(keys)?;
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher()
+            .encrypt(&nonce, plaintext.as_slice())
+            .map_err(|_| anyhow!("Failed to encrypt the API keys"))?;
+        Ok(SealedKeys {
+            salt: STANDARD.encode(self.salt),
+            nonce: STANDARD.encode(nonce),
+            ciphertext: STANDARD.encode(ciphertext),
+        })
+    }
+
+    /// Decrypt keys sealed with this key
+    pub fn open(&self, sealed: &SealedKeys) -> Result<ApiKeys, VaultError> {
+        let nonce = decode(&sealed.nonce)?;
+        if nonce.len() != NONCE_LEN {
+            return Err(VaultError::Corrupt("bad nonce length".into()));
+        }
+        // A key derived from another passphrase (or salt) fails the authentication tag
+        let plaintext = self
+            .cipher()
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                decode(&sealed.ciphertext)?.as_slice(),
+            )
+            .map_err(|_| VaultError::WrongPassphrase)?;
+        serde_json::from_slice(&plaintext).map_err(|e| VaultError::Corrupt(e.to_string()))
+    }
+}
+
+/// Where the API keys are while `encrypt_settings` is on
+#[derive(Default)]
+pub enum Vault {
+    /// Encryption is off: keys go to the keychain or the settings file
+    #[default]
+    Off,
+    /// Read from disk but not unlocked; saving keeps the blob as it is
+    Locked(SealedKeys),
+    /// Unlocked (or a new passphrase set); saving seals the current keys
+    Unlocked(VaultKey),
+}
+
+/// What `get_settings_lock` reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LockState {
+    Off,
+    Locked,
+    Unlocked,
+}
+
+impl Vault {
+    pub fn state(&self) -> LockState {
+        match self {
+            Vault::Off => LockState::Off,
+            Vault::Locked(_) => LockState::Locked,
+            Vault::Unlocked(_) => LockState::Unlocked,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys() -> ApiKeys {
+        ApiKeys {
+            api_key: "sk-openai".into(),
+            groq_api_key: "gsk-groq".into(),
+            elevenlabs_api_key: String::new(),
+            deepgram_api_key: String::new(),
+            local_api_token: "local-token".into(),
+        }
+    }
+
+    #[test]
+    fn test_sealed_keys_open_with_the_passphrase() {
+        let key = VaultKey::new("correct horse").unwrap();
+        let sealed = key.seal(&keys()).unwrap();
+        assert!(!sealed.ciphertext.contains("sk-openai"));
+        assert!(!sealed.ciphertext.contains("local-token"));
+
+        let json = serde_json::to_string(&sealed).unwrap();
+        let sealed: SealedKeys = serde_json::from_str(&json).unwrap();
+        let (reopened, opened) = sealed.open("correct horse").unwrap();
+        assert_eq!(opened, keys());
+        assert!(reopened.matches("correct horse"));
+        assert!(!reopened.matches("battery staple"));
+    }
+
+    #[test]
+    fn test_wrong_passphrase_is_reported() {
+        let sealed = VaultKey::new("correct horse")
+            .unwrap()
+            .seal(&keys())
+            .unwrap();
+        assert!(matches!(
+            sealed.open("battery staple"),
+            Err(VaultError::WrongPassphrase)
+        ));
+        assert!(matches!(sealed.open(""), Err(VaultError::EmptyPassphrase)));
+    }
+}
@@ -0,0 +1,294 @@
+use std::time::Duration;
+
+use futures::future::{BoxFuture, FutureExt};
+use thiserror::Error;
+
+use crate::{
+    elevenlabs::{self, ElevenLabsClient, ElevenLabsTranscriptionRequest},
+    groq::GroqClient,
+    openai::{OpenAiClient, TranscriptionRequest},
+    settings::{AppSettings, TranscriptionProvider},
+};
+
+/// Text the Mock provider returns for every recording
+pub const MOCK_TRANSCRIPT: &str = "Mock transcription result for E2E testing";
+/// Simulated processing time of the Mock provider
+const MOCK_DELAY: Duration = Duration::from_millis(500);
+
+/// A finished recording, as WAV bytes
+#[derive(Debug, Clone)]
+pub struct AudioPayload {
+    pub wav: Vec<u8>,
+}
+
+/// Everything a provider needs from the settings for one request
+#[derive(Debug, Clone)]
+pub struct TranscribeOptions {
+    /// Trimmed, with an `env:` reference resolved; empty for providers that take no key
+    pub api_key: String,
+    pub model: String,
+    /// Limit for a WebSocket handshake (ElevenLabs)
+    pub connect_timeout: Duration,
+}
+
+impl TranscribeOptions {
+    /// Resolve the key, model and timeouts `provider` uses under `settings`. Base URLs
+    /// belong to the clients, which are rebuilt when they change (`ClientFactory`).
+    pub fn resolve(settings: &AppSettings, provider: &TranscriptionProvider) -> Self {
+        Self {
+            api_key: settings.provider_key(provider),
+            model: settings.model.clone(),
+            connect_timeout: settings.elevenlabs_connect_timeout(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transcript {
+    pub text: String,
+}
+
+#[derive(Debug, Error)]
+pub enum ProviderError {
+    #[error("{0} API key is required before starting a transcription")]
+    MissingApiKey(&'static str),
+    #[error(transparent)]
+    Failed(#[from] anyhow::Error),
+}
+
+impl ProviderError {
+    /// Unwrap to the client's own error, so `ErrorCode::classify` still finds
+    /// the HTTP or server error at the top of the chain
+    pub fn into_anyhow(self) -> anyhow::Error {
+        match self {
+            ProviderError::Failed(err) => err,
+            other => anyhow::Error::new(other),
+        }
+    }
+}
+
+/// A provider's HTTP API answered with an error status
+#[derive(Debug, Error)]
+#[error("{provider} responded with {status}{}", .body.as_deref().map(|body| format!(": {body}")).unwrap_or_default())]
+pub struct HttpStatusError {
+    pub provider: &'static str,
+    pub status: reqwest::StatusCode,
+    /// The response body, redacted
+    pub body: Option<String>,
+    /// How long the provider asked to be left alone (`Retry-After` of an HTTP 429)
+    pub retry_after: Option<Duration>,
+}
+
+impl HttpStatusError {
+    /// Just the status, for requests whose error body isn't worth reading
+    pub fn new(provider: &'static str, status: reqwest::StatusCode) -> Self {
+        Self {
+            provider,
+            status,
+            body: None,
+            retry_after: None,
+        }
+    }
+
+    /// The status, the body and the `Retry-After` in seconds, if the provider sent one
+    pub async fn from_response(provider: &'static str, response: reqwest::Response) -> Self {
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok())
+            .map(Duration::from_secs);
+        let body = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "<failed to read error body>".into());
+        Self {
+            provider,
+            status,
+            // Error bodies sometimes quote the key that was sent
+            body: Some(crate::redact::redact(&body).into_owned()),
+            retry_after,
+        }
+    }
+}
+
+/// A batch transcription backend. `TranscriptionService` keeps one per
+/// `TranscriptionProvider` and picks it from the settings.
+pub trait SpeechToText: Send + Sync {
+    fn transcribe<'a>(
+        &'a self,
+        audio: AudioPayload,
+        opts: &'a TranscribeOptions,
+    ) -> BoxFuture<'a, Result<Transcript, ProviderError>>;
+
+    /// Cheap authenticated request that succeeds only if `api_key` is accepted
+    fn check_key<'a>(&'a self, api_key: &'a str) -> BoxFuture<'a, anyhow::Result<()>>;
+
+    /// Ids of the models `api_key` can use, unfiltered. Providers with a fixed set
+    /// ignore the key.
+    fn list_models<'a>(&'a self, api_key: &'a str) -> BoxFuture<'a, anyhow::Result<Vec<String>>>;
+}
+
+impl SpeechToText for OpenAiClient {
+    fn transcribe<'a>(
+        &'a self,
+        audio: AudioPayload,
+        opts: &'a TranscribeOptions,
+    ) -> BoxFuture<'a, Result<Transcript, ProviderError>> {
+        async move {
+            let request = TranscriptionRequest {
+                api_key: opts.api_key.clone(),
+                model: opts.model.clone(),
+                audio_wav: audio.wav,
+                ..TranscriptionRequest::default()
+            };
+            let text = OpenAiClient::transcribe(self, request).await?;
+            Ok(Transcript { text })
+        }
+        .boxed()
+    }
+
+    fn check_key<'a>(&'a self, api_key: &'a str) -> BoxFuture<'a, anyhow::Result<()>> {
+        OpenAiClient::check_key(self, api_key).boxed()
+    }
+
+    fn list_models<'a>(&'a self, api_key: &'a str) -> BoxFuture<'a, anyhow::Result<Vec<String>>> {
+        OpenAiClient::list_models(self, api_key).boxed()
+    }
+}
+
+impl SpeechToText for GroqClient {
+    fn transcribe<'a>(
+        &'a self,
+        audio: AudioPayload,
+        opts: &'a TranscribeOptions,
+    ) -> BoxFuture<'a, Result<Transcript, ProviderError>> {
+        async move {
+            let request = TranscriptionRequest {
+                api_key: opts.api_key.clone(),
+                model: opts.model.clone(),
+                audio_wav: audio.wav,
+                ..TranscriptionRequest::default()
+            };
+            let text = GroqClient::transcribe(self, request).await?;
+            Ok(Transcript { text })
+        }
+        .boxed()
+    }
+
+    fn check_key<'a>(&'a self, api_key: &'a str) -> BoxFuture<'a, anyhow::Result<()>> {
+        GroqClient::check_key(self, api_key).boxed()
+    }
+
+    fn list_models<'a>(&'a self, api_key: &'a str) -> BoxFuture<'a, anyhow::Result<Vec<String>>> {
+        GroqClient::list_models(self, api_key).boxed()
+    }
+}
+
+impl SpeechToText for ElevenLabsClient {
+    fn transcribe<'a>(
+        &'a self,
+        audio: AudioPayload,
+        opts: &'a TranscribeOptions,
+    ) -> BoxFuture<'a, Result<Transcript, ProviderError>> {
+        async move {
+            let request = ElevenLabsTranscriptionRequest {
+                api_key: opts.api_key.clone(),
+                audio_wav: audio.wav,
+                language: String::new(),
+                connect_timeout: opts.connect_timeout,
+            };
+            let text = ElevenLabsClient::transcribe(self, request).await?;
+            Ok(Transcript { text })
+        }
+        .boxed()
+    }
+
+    fn check_key<'a>(&'a self, api_key: &'a str) -> BoxFuture<'a, anyhow::Result<()>> {
+        ElevenLabsClient::check_key(self, api_key).boxed()
+    }
+
+    fn list_models<'a>(&'a self, _api_key: &'a str) -> BoxFuture<'a, anyhow::Result<Vec<String>>> {
+        async { Ok(elevenlabs::MODELS.iter().map(|id| id.to_string()).collect()) }.boxed()
+    }
+}
+
+/// Provider for E2E testing without API keys: a fixed text after a short delay
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MockSpeechToText;
+
+impl SpeechToText for MockSpeechToText {
+    fn transcribe<'a>(
+        &'a self,
+        _audio: AudioPayload,
+        _opts: &'a TranscribeOptions,
+    ) -> BoxFuture<'a, Result<Transcript, ProviderError>> {
+        async move {
+            tracing::info!("[Transcription] Using Mock provider for testing");
+            tokio::time::sleep(MOCK_DELAY).await;
+            Ok(Transcript {
+                text: MOCK_TRANSCRIPT.to_string(),
+            })
+        }
+        .boxed()
+    }
+
+    fn check_key<'a>(&'a self, _api_key: &'a str) -> BoxFuture<'a, anyhow::Result<()>> {
+        async { Ok(()) }.boxed()
+    }
+
+    fn list_models<'a>(&'a self, _api_key: &'a str) -> BoxFuture<'a, anyhow::Result<Vec<String>>> {
+        async { Ok(Vec::new()) }.boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_mock_returns_fixed_text() {
+        let opts =
+            TranscribeOptions::resolve(&AppSettings::default(), &TranscriptionProvider::Mock);
+        assert!(opts.api_key.is_empty());
+        let transcript = MockSpeechToText
+            .transcribe(AudioPayload { wav: Vec::new() }, &opts)
+            .await
+            .unwrap();
+        assert_eq!(transcript.text, MOCK_TRANSCRIPT);
+    }
+
+    #[test]
+    fn test_options_pick_the_provider_key() {
+        let mut settings = AppSettings::default();
+        settings.api_key = "sk-openai".to_string();
+        settings.groq_api_key = " gsk-groq ".to_string();
+
+        let groq = TranscribeOptions::resolve(&settings, &TranscriptionProvider::Groq);
+        assert_eq!(groq.api_key, "gsk-groq");
+        assert_eq!(groq.model, settings.model);
+        let elevenlabs = TranscribeOptions::resolve(&settings, &TranscriptionProvider::ElevenLabs);
+        assert!(elevenlabs.api_key.is_empty());
+    }
+
+    #[test]
+    fn test_missing_key_message() {
+        let err = ProviderError::MissingApiKey("Groq").into_anyhow();
+        assert_eq!(
+            err.to_string(),
+            "Groq API key is required before starting a transcription"
+        );
+    }
+
+    #[test]
+    fn test_http_status_error_message() {
+        let mut err = HttpStatusError::new("Groq", reqwest::StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(err.to_string(), "Groq responded with 429 Too Many Requests");
+        err.body = Some("slow down".into());
+        assert_eq!(
+            err.to_string(),
+            "Groq responded with 429 Too Many Requests: slow down"
+        );
+    }
+}
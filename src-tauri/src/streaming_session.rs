@@ -0,0 +1,69 @@
+use anyhow::Result;
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::{handshake::client::Request, Message};
+
+use crate::audio_stream::CHUNK_MS;
+use crate::elevenlabs_streaming::StreamingEventSink;
+
+/// Wire protocol of a realtime transcription service. `StreamingClient` runs the
+/// gate, the send queue and the socket tasks the same way for every service and
+/// asks the session what to put on the wire and what the server's replies mean.
+/// A session lives as long as its connection, so it may keep state between messages.
+pub trait StreamingSession: Send + Sync + 'static {
+    /// Service name for logs
+    fn name(&self) -> &'static str;
+
+    /// Realtime endpoint used unless the client was given another one
+    fn default_endpoint(&self) -> &'static str;
+
+    /// Handshake request against `endpoint`, carrying the key and the audio format
+    fn request(&self, endpoint: &str) -> Result<Request>;
+
+    /// The gate opened for a new dictation
+    fn gate_opened(&self) {}
+
+    /// Messages carrying a piece of captured audio; more than one if it is too long
+    /// for a single frame
+    fn audio(&self, pcm: &[u8]) -> Result<Vec<Message>>;
+
+    /// Messages ending the segment: `silence` (mono PCM16) so the last word isn't cut
+    /// off, then the request to finalize what was sent
+    fn finalize(&self, silence: &[u8]) -> Result<Vec<Message>>;
+
+    /// How often `keepalive` is sent
+    fn keepalive_interval(&self) -> Duration {
+        Duration::from_secs(10)
+    }
+
+    /// Message keeping the connection open while no audio is sent
+    fn keepalive(&self) -> Message {
+        Message::Ping(vec![])
+    }
+
+    /// Message ending the stream after the final commit; the server closes the socket
+    /// in reply
+    fn close(&self) -> Message;
+
+    /// Turn a text message from the server into events. Returns true once the segment
+    /// asked for by `finalize` is transcribed.
+    fn handle_text(&self, text: &str, events: &dyn StreamingEventSink) -> bool;
+}
+
+/// Digital silence lasting `duration`, as mono PCM16 at `sample_rate`
+pub fn silence_pcm(sample_rate: u32, duration: Duration) -> Vec<u8> {
+    let samples = u128::from(sample_rate) * duration.as_millis() / 1000;
+    vec![0u8; samples as usize * 2]
+}
+
+/// `silence` cut into pieces the length of a captured chunk; at least one, which is
+/// empty when there is no silence
+pub fn silence_chunks(sample_rate: u32, silence: &[u8]) -> Vec<&[u8]> {
+    let chunk_len = silence_pcm(sample_rate, Duration::from_millis(CHUNK_MS))
+        .len()
+        .max(2);
+    let mut chunks: Vec<&[u8]> = silence.chunks(chunk_len).collect();
+    if chunks.is_empty() {
+        chunks.push(&[]);
+    }
+    chunks
+}
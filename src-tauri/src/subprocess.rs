@@ -0,0 +1,261 @@
+use std::{
+    ffi::OsStr,
+    io::{BufRead, BufReader, Read, Write},
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// Output kept per stream for the result; everything is logged either way
+const MAX_CAPTURED: usize = 64 * 1024;
+
+/// How a helper process ended
+#[derive(Debug)]
+pub enum Exit {
+    Exited(ExitStatus),
+    /// Killed after running longer than allowed
+    TimedOut,
+}
+
+/// A finished helper process with what it printed
+#[derive(Debug)]
+pub struct ProcessOutput {
+    pub exit: Exit,
+    // Logged as it comes; no helper's stdout is read back yet
+    #[allow(dead_code)]
+    pub stdout: String,
+    pub stderr: String,
+    pub elapsed: Duration,
+}
+
+/// `Command` for `program` that never opens a console window on Windows. Every
+/// helper the app starts goes through here or `shell`.
+pub fn command(program: impl AsRef<OsStr>) -> Command {
+    #[cfg_attr(not(windows), allow(unused_mut))]
+    let mut command = Command::new(program);
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+    command
+}
+
+/// Run `line` with `cmd /C` on Windows and `sh -c` elsewhere
+pub fn shell(line: &str) -> Command {
+    #[cfg(windows)]
+    {
+        let mut shell = command("cmd");
+        shell.arg("/C").arg(line);
+        shell
+    }
+    #[cfg(not(windows))]
+    {
+        let mut shell = command("sh");
+        shell.arg("-c").arg(line);
+        shell
+    }
+}
+
+/// Log every line of `stream` under `prefix` and keep the first `MAX_CAPTURED` bytes
+fn forward(
+    stream: impl Read + Send + 'static,
+    prefix: String,
+    captured: Arc<Mutex<String>>,
+    is_stderr: bool,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(stream);
+        let mut line = Vec::new();
+        // Until EOF, once the process and any children it started close the pipe
+        while reader
+            .read_until(b'\n', &mut line)
+            .is_ok_and(|read| read > 0)
+        {
+            let text = String::from_utf8_lossy(&line);
+            let trimmed = text.trim_end();
+            if is_stderr {
+                tracing::info!("[{}] stderr: {}", prefix, trimmed);
+            } else {
+                tracing::debug!("[{}] {}", prefix, trimmed);
+            }
+            if let Ok(mut captured) = captured.lock() {
+                if captured.len() < MAX_CAPTURED {
+                    captured.push_str(&text);
+                }
+            }
+            line.clear();
+        }
+    })
+}
+
+fn wait_until(child: &mut Child, deadline: Instant) -> std::io::Result<Option<ExitStatus>> {
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            return Ok(None);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Join the readers that reach EOF by `deadline`; the rest are left running, since a
+/// child the process started in the background can hold the pipes open for good
+fn join_until(readers: Vec<JoinHandle<()>>, deadline: Instant) {
+    while readers.iter().any(|reader| !reader.is_finished()) && Instant::now() < deadline {
+        thread::sleep(POLL_INTERVAL);
+    }
+    for reader in readers.into_iter().filter(JoinHandle::is_finished) {
+        let _ = reader.join();
+    }
+}
+
+/// Start `command` with `stdin` as its input and wait for it, killing it after
+/// `limit`. Its stdout and stderr are logged line by line under `name` as they come
+/// and returned too. Blocks, so async code runs it with `spawn_blocking`.
+///
+/// Input and output are handled on their own threads, so a process that doesn't read
+/// its input or prints a lot still times out instead of hanging. The output is waited
+/// for only until the same deadline: a child the process started in the background
+/// inherits the pipes and may keep them open long after the process exits.
+pub fn run(
+    name: &str,
+    mut command: Command,
+    stdin: Option<Vec<u8>>,
+    limit: Duration,
+) -> std::io::Result<ProcessOutput> {
+    let started = Instant::now();
+    let deadline = started + limit;
+    let mut child = command
+        .stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let prefix = format!("{name}:{}", child.id());
+
+    if let (Some(mut pipe), Some(input)) = (child.stdin.take(), stdin) {
+        // A process that exits without reading its input breaks the pipe; not an error
+        thread::spawn(move || pipe.write_all(&input));
+    }
+    let stdout = Arc::new(Mutex::new(String::new()));
+    let stderr = Arc::new(Mutex::new(String::new()));
+    let mut readers = Vec::new();
+    if let Some(pipe) = child.stdout.take() {
+        readers.push(forward(pipe, prefix.clone(), stdout.clone(), false));
+    }
+    if let Some(pipe) = child.stderr.take() {
+        readers.push(forward(pipe, prefix.clone(), stderr.clone(), true));
+    }
+
+    let exit = match wait_until(&mut child, deadline)? {
+        Some(status) => {
+            join_until(readers, deadline);
+            Exit::Exited(status)
+        }
+        None => {
+            let _ = child.kill();
+            let _ = child.wait();
+            tracing::warn!("[{}] Killed after {}s", prefix, limit.as_secs_f32());
+            Exit::TimedOut
+        }
+    };
+    let take = |captured: Arc<Mutex<String>>| {
+        captured
+            .lock()
+            .map(|mut text| std::mem::take(&mut *text))
+            .unwrap_or_default()
+    };
+    Ok(ProcessOutput {
+        exit,
+        stdout: take(stdout),
+        stderr: take(stderr),
+        elapsed: started.elapsed(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A shell line sleeping about `secs` seconds
+    fn sleep(secs: u32) -> String {
+        if cfg!(windows) {
+            format!("ping -n {} 127.0.0.1 >NUL", secs + 1)
+        } else {
+            format!("sleep {secs}")
+        }
+    }
+
+    #[test]
+    fn test_output_is_captured() {
+        let output = run(
+            "test",
+            shell("echo hello&& echo boom>&2"),
+            None,
+            Duration::from_secs(5),
+        )
+        .unwrap();
+        assert!(matches!(output.exit, Exit::Exited(status) if status.success()));
+        assert_eq!(output.stdout.trim_end(), "hello");
+        assert_eq!(output.stderr.trim_end(), "boom");
+    }
+
+    #[test]
+    fn test_stdin_and_exit_code() {
+        let line = if cfg!(windows) {
+            "findstr x & exit 3"
+        } else {
+            "cat; exit 3"
+        };
+        let output = run(
+            "test",
+            shell(line),
+            Some(b"x marks the spot\n".to_vec()),
+            Duration::from_secs(5),
+        )
+        .unwrap();
+        assert!(matches!(output.exit, Exit::Exited(status) if status.code() == Some(3)));
+        assert_eq!(output.stdout.trim_end(), "x marks the spot");
+    }
+
+    #[test]
+    fn test_stalled_process_is_killed() {
+        let output = run(
+            "test",
+            shell(&format!("echo started&& {}", sleep(5))),
+            None,
+            Duration::from_millis(500),
+        )
+        .unwrap();
+        assert!(matches!(output.exit, Exit::TimedOut));
+        assert!(output.elapsed < Duration::from_secs(4));
+    }
+
+    #[test]
+    fn test_background_child_does_not_hold_up_the_result() {
+        let line = if cfg!(windows) {
+            format!("start /b {} & echo hi", sleep(5))
+        } else {
+            format!("{} & echo hi", sleep(5))
+        };
+        let output = run("test", shell(&line), None, Duration::from_secs(1)).unwrap();
+        assert!(matches!(output.exit, Exit::Exited(status) if status.success()));
+        assert_eq!(output.stdout.trim_end(), "hi");
+        assert!(output.elapsed < Duration::from_secs(4));
+    }
+
+    #[test]
+    fn test_missing_program_fails_to_start() {
+        let missing = command("easy-dictate-no-such-helper");
+        assert!(run("test", missing, None, Duration::from_secs(1)).is_err());
+    }
+}
@@ -0,0 +1,142 @@
+use std::time::Duration;
+
+use serde::Serialize;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// No speaking rate for recordings shorter than this: a word or two would extrapolate
+/// to an absurd rate
+const MIN_RATE_DURATION: Duration = Duration::from_secs(1);
+
+/// Hyphens that join the words on either side into one ("из-за", "well-known")
+const HYPHENS: &[&str] = &["-", "\u{2010}", "\u{2011}"];
+
+/// Size of a transcript and how fast it was spoken
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize)]
+pub struct TextStats {
+    pub word_count: usize,
+    pub char_count: usize,
+    /// None if the recording duration is unknown or too short to tell
+    pub words_per_minute: Option<f64>,
+}
+
+impl TextStats {
+    pub fn measure(text: &str, recorded: Option<Duration>) -> Self {
+        let word_count = count_words(text);
+        Self {
+            word_count,
+            char_count: count_chars(text),
+            words_per_minute: recorded.and_then(|duration| words_per_minute(word_count, duration)),
+        }
+    }
+}
+
+fn is_word(segment: &str) -> bool {
+    segment.chars().any(char::is_alphanumeric)
+}
+
+/// Count words using Unicode word boundaries (works for Cyrillic, CJK, etc.).
+/// Each CJK ideograph is a word of its own; hyphenated compounds count once.
+pub fn count_words(text: &str) -> usize {
+    let mut count = 0;
+    let mut after_word = false;
+    // The previous segment is a hyphen directly after a word
+    let mut joining = false;
+    for segment in text.split_word_bounds() {
+        if is_word(segment) {
+            if !joining {
+                count += 1;
+            }
+            after_word = true;
+            joining = false;
+        } else {
+            joining = after_word && HYPHENS.contains(&segment);
+            after_word = false;
+        }
+    }
+    count
+}
+
+/// Count user-perceived characters (grapheme clusters), whitespace included, so an
+/// emoji sequence or a letter with combining marks counts once
+pub fn count_chars(text: &str) -> usize {
+    text.graphemes(true).count()
+}
+
+/// Words per minute, rounded to one decimal
+pub fn words_per_minute(words: usize, duration: Duration) -> Option<f64> {
+    if duration < MIN_RATE_DURATION {
+        return None;
+    }
+    let rate = words as f64 * 60.0 / duration.as_secs_f64();
+    Some((rate * 10.0).round() / 10.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_words_cyrillic() {
+        assert_eq!(count_words("Привет, как дела?"), 3);
+        assert_eq!(count_words("Встреча в 10 часов, потом обед"), 6);
+    }
+
+    #[test]
+    fn test_count_words_cjk() {
+        assert_eq!(count_words("我爱北京"), 4);
+        assert_eq!(count_words("Release 版本 ready"), 4);
+    }
+
+    #[test]
+    fn test_count_words_ignores_emoji_and_punctuation() {
+        assert_eq!(count_words("Отлично 👍 🎉"), 1);
+        assert_eq!(count_words("  ...  — "), 0);
+        assert_eq!(count_words(""), 0);
+    }
+
+    #[test]
+    fn test_hyphenated_words_count_once() {
+        assert_eq!(count_words("well-known fact"), 2);
+        assert_eq!(count_words("Из-за дождя кто-нибудь опоздает"), 4);
+        assert_eq!(count_words("pre\u{2010}release"), 1);
+        // A dash between spaces or a trailing hyphen joins nothing
+        assert_eq!(count_words("yes - no"), 2);
+        assert_eq!(count_words("re- and pre-"), 3);
+    }
+
+    #[test]
+    fn test_count_chars_uses_graphemes() {
+        assert_eq!(count_chars("дела"), 4);
+        assert_eq!(count_chars("a b"), 3);
+        // Family emoji: four code points joined by ZWJ
+        assert_eq!(count_chars("👨‍👩‍👧‍👦"), 1);
+        // "e" with a combining acute accent
+        assert_eq!(count_chars("e\u{301}"), 1);
+        assert_eq!(count_chars("你好"), 2);
+    }
+
+    #[test]
+    fn test_words_per_minute() {
+        assert_eq!(words_per_minute(30, Duration::from_secs(15)), Some(120.0));
+        assert_eq!(words_per_minute(7, Duration::from_secs(3)), Some(140.0));
+        assert_eq!(
+            words_per_minute(10, Duration::from_millis(7_000)),
+            Some(85.7)
+        );
+        assert_eq!(words_per_minute(2, Duration::from_millis(400)), None);
+    }
+
+    #[test]
+    fn test_measure() {
+        let stats = TextStats::measure("кто-нибудь здесь", Some(Duration::from_secs(2)));
+        assert_eq!(
+            stats,
+            TextStats {
+                word_count: 2,
+                char_count: 16,
+                words_per_minute: Some(60.0),
+            }
+        );
+        assert_eq!(TextStats::measure("hi", None).words_per_minute, None);
+    }
+}
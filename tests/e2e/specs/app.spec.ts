@@ -249,6 +249,7 @@ describe('Easy Dictate Application', () => {
       expect(state).toBeDefined();
       expect(typeof state.is_recording).toBe('boolean');
       expect(typeof state.is_transcribing).toBe('boolean');
+      expect(state.schema).toBe(2);
       logger.info('Test state', state);
     });
   });
@@ -553,7 +554,7 @@ describe('Easy Dictate Application', () => {
 
         // Simulate release
         await browser.simulateHotkeyRelease();
-        await browser.pause(500);
+        await browser.waitForTestState('idle');
 
         await screenshots.capture(browser, 'after_hotkey_release');
       } finally {
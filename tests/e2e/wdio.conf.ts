@@ -426,6 +426,17 @@ function addCustomCommands(browser: WebdriverIO.Browser) {
     return response.result;
   });
 
+  // Long-poll the backend until a named condition holds ('idle', 'recording', 'transcribing',
+  // 'streaming_connected', 'completed', 'failed'); returns the state it held in
+  browser.addCommand('waitForTestState', async function (condition: string, timeout = 10000) {
+    const response = await (browser as any).tauriInvoke('wait_for_test_state', {
+      condition,
+      timeoutMs: timeout,
+    });
+    if (!response.success) throw new Error(response.error);
+    return response.result;
+  });
+
   // Inject test audio (bypasses microphone)
   browser.addCommand('injectTestAudio', async function (audioPath: string) {
     const audioBuffer = fs.readFileSync(audioPath);
@@ -550,6 +561,7 @@ declare global {
       getSettings(): Promise<any>;
       saveSettings(settings: any): Promise<void>;
       getTestState(): Promise<any>;
+      waitForTestState(condition: string, timeout?: number): Promise<any>;
       injectTestAudio(audioPath: string): Promise<string>;
       simulateHotkeyPress(): Promise<void>;
       simulateHotkeyRelease(): Promise<void>;